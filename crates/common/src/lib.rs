@@ -1,6 +1,14 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use bytes::Bytes;
+
+pub mod wire;
 
 // ---------------------------------------------------------------------------
 // Core types
@@ -12,15 +20,37 @@ pub type Lsn = u64;
 /// Logical page identifier.
 pub type PageId = u64;
 
-/// 8 KiB page — matches typical database page size.
+/// Wall-clock time in microseconds since the Unix epoch.
+pub type Timestamp = u64;
+
+/// Default page size, 8 KiB — matches typical database page size. A volume
+/// fixes its own page size, one of `PAGE_SIZES`, when it is created.
 pub const PAGE_SIZE: usize = 8192;
 
-/// A fixed-size page image.
-pub type Page = [u8; PAGE_SIZE];
+/// Page sizes a volume may use. Offsets within a page are `u16`, which caps
+/// them at 32 KiB.
+pub const PAGE_SIZES: [usize; 4] = [4096, 8192, 16384, 32768];
+
+/// A page image, as long as its volume's page size.
+pub type Page = Box<[u8]>;
+
+/// Shared, immutable page image. Cloning bumps a refcount instead of copying
+/// the page, so caches and callers can hand the same image around freely.
+pub type PageRef = Arc<[u8]>;
 
 /// Return a zeroed page (blank slate for materialization).
-pub fn empty_page() -> Page {
-    [0u8; PAGE_SIZE]
+pub fn empty_page(page_size: usize) -> Page {
+    vec![0u8; page_size].into_boxed_slice()
+}
+
+/// Reject a page size a volume can't use.
+pub fn check_page_size(page_size: usize) -> Result<(), StorageError> {
+    if !PAGE_SIZES.contains(&page_size) {
+        return Err(StorageError::Other(format!(
+            "unsupported page size {page_size} (expected 4096, 8192, 16384 or 32768)"
+        )));
+    }
+    Ok(())
 }
 
 // ---------------------------------------------------------------------------
@@ -37,17 +67,21 @@ pub struct RedoRecord {
     /// Byte offset within the page where `data` should be written.
     pub offset: u16,
     /// Payload — the bytes to write at `offset`.
-    pub data: Vec<u8>,
+    pub data: Bytes,
     /// Previous LSN that touched this same page (forms a per-page chain).
     pub prev_lsn: Lsn,
     /// Mini-transaction group identifier.
     pub mtr_id: u64,
     /// When `true`, this record is the Consistency Point LSN (CPL) of its MTR.
     pub is_mtr_end: bool,
+    /// When storage appended the record, assigned with the LSN. Never
+    /// decreases as LSNs grow.
+    #[serde(default)]
+    pub timestamp: Timestamp,
 }
 
 // ---------------------------------------------------------------------------
-// On-disk log entry header (fixed 47 bytes)
+// On-disk log entry header (fixed 49 bytes)
 // ---------------------------------------------------------------------------
 
 /// Fixed-size header written before each redo payload in the WAL file.
@@ -59,11 +93,17 @@ pub struct RedoRecord {
 ///   data_len  : u16  (2)
 ///   prev_lsn  : u64  (8)
 ///   mtr_id    : u64  (8)
-///   flags     : u8   (1)   — bit 0 = is_mtr_end
-///   crc32     : u32  (4)   — CRC of header bytes (excl. crc field) + data
+///   timestamp : u64  (8)
+///   flags     : u8   (1)   — bit 0 = is_mtr_end, bits 1-2 = checksum algorithm
+///   crc32     : u32  (4)   — checksum of header bytes (excl. this field) + data,
+///                            CRC32 unless the flags name another algorithm
 ///   ─────────────────────
-///   total     : 41 bytes
-pub const LOG_ENTRY_HEADER_SIZE: usize = 41;
+///   total     : 49 bytes
+pub const LOG_ENTRY_HEADER_SIZE: usize = 49;
+
+/// Version of the WAL entry layout, recorded in a volume's settings. 1 had
+/// a 41-byte header without the timestamp; 2 is the layout above.
+pub const WAL_FORMAT: u32 = 2;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LogEntryHeader {
@@ -73,6 +113,7 @@ pub struct LogEntryHeader {
     pub data_len: u16,
     pub prev_lsn: Lsn,
     pub mtr_id: u64,
+    pub timestamp: Timestamp,
     pub flags: u8,
     pub crc32: u32,
 }
@@ -88,7 +129,7 @@ impl LogEntryHeader {
 // ---------------------------------------------------------------------------
 
 /// Aurora-style LSN watermarks that track durability progress.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct DurabilityState {
     /// Volume Complete LSN — highest LSN where all prior LSNs are present.
     pub vcl: Lsn,
@@ -96,22 +137,687 @@ pub struct DurabilityState {
     pub vdl: Lsn,
 }
 
+/// A durable append: the LSNs storage gave its records, in order, and the
+/// VDL once it landed. Other appends may take the LSNs on either side.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppendedRedo {
+    /// Empty for an empty batch.
+    pub lsns: RangeInclusive<Lsn>,
+    pub vdl: Lsn,
+}
+
 // ---------------------------------------------------------------------------
 // StorageApi trait — the compute ↔ storage boundary
 // ---------------------------------------------------------------------------
 
+/// Version of the `StorageApi` contract. Compute and storage must agree on
+/// it; bump it on any change an older peer would misread.
+pub const STORAGE_API_VERSION: u32 = 1;
+
+/// What one side of the compute ↔ storage boundary speaks and supports,
+/// exchanged by `StorageApi::handshake` so mixed-version deployments fail
+/// up front or fall back, instead of misbehaving mid-request.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub api_version: u32,
+    /// Envelope version of `wire` messages.
+    pub wire_version: u8,
+    /// `get_pages` serves a batch in one round trip.
+    pub batch_reads: bool,
+    /// `get_bytes` ships only the requested range, not the whole page.
+    pub partial_reads: bool,
+    /// `append_redo` may return before the batch is durable.
+    pub async_durability: bool,
+    /// Payloads may be compressed on the wire.
+    pub compression: bool,
+}
+
+impl Capabilities {
+    /// This build's versions with no optional features.
+    pub fn baseline() -> Self {
+        Self {
+            api_version: STORAGE_API_VERSION,
+            wire_version: wire::WIRE_VERSION,
+            batch_reads: false,
+            partial_reads: false,
+            async_durability: false,
+            compression: false,
+        }
+    }
+
+    /// What both sides can use: the same versions, and the features both
+    /// support. Fails if the versions differ.
+    pub fn negotiate(&self, peer: &Capabilities) -> Result<Capabilities, StorageError> {
+        if self.api_version != peer.api_version || self.wire_version != peer.wire_version {
+            return Err(StorageError::Incompatible(format!(
+                "storage speaks API v{} / wire v{}, peer speaks API v{} / wire v{}",
+                self.api_version, self.wire_version, peer.api_version, peer.wire_version
+            )));
+        }
+        Ok(Self {
+            api_version: self.api_version,
+            wire_version: self.wire_version,
+            batch_reads: self.batch_reads && peer.batch_reads,
+            partial_reads: self.partial_reads && peer.partial_reads,
+            async_durability: self.async_durability && peer.async_durability,
+            compression: self.compression && peer.compression,
+        })
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let features: Vec<&str> = [
+            (self.batch_reads, "batch-reads"),
+            (self.partial_reads, "partial-reads"),
+            (self.async_durability, "async-durability"),
+            (self.compression, "compression"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect();
+        let features = if features.is_empty() { "none".to_string() } else { features.join(", ") };
+        write!(f, "API v{}, wire v{}, features: {features}", self.api_version, self.wire_version)
+    }
+}
+
 #[async_trait]
 pub trait StorageApi: Send + Sync {
     /// Append a batch of redo records. Returns the new durable LSN.
-    async fn append_redo(&self, records: Vec<RedoRecord>) -> Result<Lsn, StorageError>;
+    async fn append_redo(&self, records: Vec<RedoRecord>) -> Result<Lsn, StorageError> {
+        Ok(self.append_redo_lsns(records).await?.vdl)
+    }
+
+    /// `append_redo`, also returning the LSNs the records were given.
+    async fn append_redo_lsns(&self, records: Vec<RedoRecord>) -> Result<AppendedRedo, StorageError>;
 
     /// Read a page materialized up to the given read-point LSN.
-    async fn get_page(&self, page_id: PageId, read_point: Lsn) -> Result<Page, StorageError>;
+    async fn get_page(&self, page_id: PageId, read_point: Lsn) -> Result<PageRef, StorageError>;
+
+    /// Read `len` bytes starting at `offset` from a page materialized up to
+    /// `read_point`. Avoids shipping the full page when only a few bytes are
+    /// needed; the default materializes the page and slices it.
+    async fn get_bytes(
+        &self,
+        page_id: PageId,
+        offset: u16,
+        len: usize,
+        read_point: Lsn,
+    ) -> Result<Vec<u8>, StorageError> {
+        check_page_range(offset, len, self.page_size())?;
+        let page = self.get_page(page_id, read_point).await?;
+        let start = offset as usize;
+        Ok(page[start..start + len].to_vec())
+    }
+
+    /// Read several pages at one read point in a single call. Results are
+    /// per page and in request order, so one missing page doesn't fail the
+    /// batch; the default issues one `get_page` per page.
+    async fn get_pages(
+        &self,
+        page_ids: &[PageId],
+        read_point: Lsn,
+    ) -> Vec<Result<PageRef, StorageError>> {
+        let mut pages = Vec::with_capacity(page_ids.len());
+        for &page_id in page_ids {
+            pages.push(self.get_page(page_id, read_point).await);
+        }
+        pages
+    }
 
     /// Get current durability state (VCL, VDL).
     async fn get_durability_state(&self) -> Result<DurabilityState, StorageError>;
+
+    /// Answer a compute node's heartbeat, advertising the durability state.
+    /// The default is `get_durability_state`.
+    async fn heartbeat(&self) -> Result<DurabilityState, StorageError> {
+        self.get_durability_state().await
+    }
+
+    /// Bytes per page on this volume. The default is `PAGE_SIZE`.
+    fn page_size(&self) -> usize {
+        PAGE_SIZE
+    }
+
+    /// What this storage supports. The default is `Capabilities::baseline`;
+    /// override it along with the methods behind each feature.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::baseline()
+    }
+
+    /// Agree with a compute node on what to use, given what it supports.
+    /// The default negotiates against `capabilities`.
+    async fn handshake(&self, compute: &Capabilities) -> Result<Capabilities, StorageError> {
+        self.capabilities().negotiate(compute)
+    }
+
+    /// Tell storage that `reader` may still read at `read_point` until
+    /// `lease` runs out, so the versions it sees are kept. Registering again
+    /// moves the read point and renews the lease. The default keeps nothing,
+    /// for storage that never discards history.
+    async fn register_reader(
+        &self,
+        _reader: &str,
+        _read_point: Lsn,
+        _lease: Duration,
+    ) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Drop `reader`'s registration before its lease runs out.
+    async fn deregister_reader(&self, _reader: &str) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Count a compute node that writes through this storage, until it
+    /// calls `detach_writer`. The default counts nothing.
+    fn attach_writer(&self) {}
+
+    /// Stop counting a node `attach_writer` counted.
+    fn detach_writer(&self) {}
+
+    /// Compute nodes attached to write. The default is 1, for storage that
+    /// doesn't count them and so can't tell a shared volume from a node's own.
+    fn writers(&self) -> usize {
+        1
+    }
 }
 
+// ---------------------------------------------------------------------------
+// AdminApi trait — maintenance operations on a storage engine
+// ---------------------------------------------------------------------------
+
+/// Maintenance operations, kept off the `StorageApi` data path. Operations
+/// that only apply to segmented storage do nothing on a single WAL file.
+#[async_trait]
+pub trait AdminApi: Send + Sync {
+    /// Fsync the WAL and seal the active segment, so every record up to the
+    /// VDL sits in an immutable file. Returns the checkpoint LSN (the VDL).
+    async fn checkpoint(&self) -> Result<Lsn, StorageError>;
+
+    /// Delete WAL files that nothing refers to, such as those left behind
+    /// by a crash during `compact`.
+    async fn gc(&self) -> Result<GcReport, StorageError>;
+
+    /// Merge each run of adjacent sealed cold segments into one file, so a
+    /// chain walk through the run pays the cold-tier latency once.
+    async fn compact(&self) -> Result<CompactReport, StorageError>;
+
+    /// Move every sealed hot segment to the cold tier now. Returns the
+    /// segments moved.
+    async fn cool(&self) -> Result<Vec<u32>, StorageError>;
+
+    /// Re-read every durable record and verify its CRC.
+    async fn scrub(&self) -> Result<ScrubReport, StorageError>;
+
+    /// Forget the history before `lsn`: reads below it fail with
+    /// `Truncated` from then on. Each page keeps the version `lsn` sees and
+    /// the records it needs; older records whose bytes later ones overwrite
+    /// are dropped from the indexes, and sealed segments left holding none
+    /// are deleted. Refused with `TruncateRefused` if a registered reader
+    /// still reads below `lsn`, if `lsn` is past the last checkpoint, or if
+    /// a segment to delete has a hot copy that hasn't cooled.
+    async fn truncate_before(&self, lsn: Lsn) -> Result<TruncateReport, StorageError>;
+
+    async fn stats(&self) -> Result<StorageStats, StorageError>;
+
+    /// Drop every image from the storage page cache. Returns the number of
+    /// entries dropped.
+    async fn flush_cache(&self) -> Result<u64, StorageError>;
+
+    /// Materialize the latest durable version of the `limit` pages accessed
+    /// most since the engine opened into the storage page cache. Returns the
+    /// pages that weren't cached already.
+    async fn prefetch(&self, limit: usize) -> Result<usize, StorageError>;
+
+    /// Like `prefetch`, for up to `limit` of the pages the last clean
+    /// shutdown recorded as hottest, or the hottest since the engine opened
+    /// if it recorded none. Reports each page to the observer's
+    /// `warmup_progress`. Returns the pages that weren't cached already.
+    async fn warmup(&self, limit: usize) -> Result<usize, StorageError>;
+
+    /// Rate limits in force and what admission control did for each caller.
+    async fn qos(&self) -> Result<QosReport, StorageError>;
+
+    /// Set the rate limit of one caller, or the default for a whole class;
+    /// `None` removes it.
+    async fn set_rate_limit(&self, target: QosTarget, limit: Option<RateLimit>) -> Result<(), StorageError>;
+}
+
+/// Engine-wide totals, for the REPL's `stats` command and metrics export.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StorageStats {
+    /// Durable redo records across the whole WAL.
+    pub total_records: usize,
+    /// The single-file backend counts its WAL file as one hot segment.
+    pub hot: TierStats,
+    pub cold: TierStats,
+    /// Pages with at least one durable record.
+    pub pages: usize,
+    /// Bytes per page on the volume.
+    pub page_size: usize,
+    pub avg_chain_len: f64,
+    pub max_chain_len: usize,
+    pub cache_entries: u64,
+    /// How the page cache picks what to drop: `tinylfu` or `lru`.
+    pub cache_policy: String,
+    /// Cached images dropped to make room since the engine opened.
+    pub cache_evictions: u64,
+    /// Cached images dropped when their TTL ran out.
+    pub cache_expirations: u64,
+    /// Reads that missed the cache while another read was walking the same
+    /// version's chain, and waited for its image instead of walking it too.
+    pub coalesced_reads: u64,
+    /// Cached images of hot pages rolled forward by an append, rather than
+    /// replayed by the next read.
+    pub rolled_forward: u64,
+    /// Page images the checkpointer stored on disk.
+    pub page_images: usize,
+    /// Reads that started from a stored image instead of the first record.
+    pub image_reads: u64,
+    /// The most accessed pages since the engine opened, busiest first.
+    pub hot_pages: Vec<HotPage>,
+    /// Readers holding a live lease; see `StorageApi::register_reader`.
+    pub readers: usize,
+    /// Oldest LSN a reader may still request: the lowest leased read point,
+    /// or the VDL when no reader holds a lease.
+    pub min_read_point: Lsn,
+    pub recovery: RecoveryInfo,
+    /// The engine's memory budget, including buffer pools that share it.
+    pub memory: MemoryUsage,
+}
+
+/// Access counts of one page, for `StorageStats::hot_pages`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct HotPage {
+    pub page_id: PageId,
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// Segment count and on-disk size of one storage tier.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TierStats {
+    pub segments: usize,
+    pub bytes: u64,
+    /// Time the tier's simulated disk has charged for I/O so far; zero on
+    /// the host disk.
+    pub disk_busy_us: u64,
+}
+
+/// What recovery found when the engine opened.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RecoveryInfo {
+    /// Records that survived recovery.
+    pub records: usize,
+    pub vcl: Lsn,
+    pub vdl: Lsn,
+    /// Torn, incomplete or garbage bytes cut from the end of the WAL.
+    pub truncated_bytes: u64,
+    /// The indexes were loaded from a clean shutdown; the WAL wasn't scanned.
+    pub clean_shutdown: bool,
+    pub duration_ms: f64,
+}
+
+/// Files removed by `AdminApi::gc`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GcReport {
+    pub removed: Vec<String>,
+    pub bytes_freed: u64,
+}
+
+/// Result of `AdminApi::truncate_before`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TruncateReport {
+    /// Reads below this LSN now fail.
+    pub lsn: Lsn,
+    /// Page versions no read can ask for any more.
+    pub versions_dropped: usize,
+    /// Records dropped from the indexes, their bytes rewritten by later ones.
+    pub records_dropped: usize,
+    pub segments_removed: Vec<u32>,
+    pub bytes_freed: u64,
+}
+
+/// Result of `AdminApi::compact`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CompactReport {
+    pub segments_before: usize,
+    pub segments_after: usize,
+    pub bytes_rewritten: u64,
+}
+
+/// Priority class of a storage caller. Background callers wait while any
+/// foreground request is in flight, so bulk load can't starve interactive
+/// operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum QosClass {
+    Foreground,
+    Background,
+}
+
+/// Token bucket: `rate` requests per second on average, with bursts of up
+/// to `burst` after a quiet spell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RateLimit {
+    pub rate: u32,
+    pub burst: u32,
+}
+
+/// What `AdminApi::set_rate_limit` applies to. A caller's own limit takes
+/// precedence over its class's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QosTarget {
+    Caller(String),
+    Class(QosClass),
+}
+
+/// Result of `AdminApi::qos`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QosReport {
+    pub foreground_limit: Option<RateLimit>,
+    pub background_limit: Option<RateLimit>,
+    /// Every caller admitted so far, by name.
+    pub callers: Vec<CallerQos>,
+}
+
+/// Admission counters for one caller.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CallerQos {
+    pub caller: String,
+    pub class: QosClass,
+    /// The caller's own limit, if it has one.
+    pub limit: Option<RateLimit>,
+    pub admitted: u64,
+    /// Requests that waited for a token.
+    pub throttled: u64,
+    /// Background requests that waited for foreground ones to finish.
+    pub deferred: u64,
+    pub waited_ms: f64,
+}
+
+/// Result of `AdminApi::scrub`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ScrubReport {
+    pub records_checked: usize,
+    pub bytes_checked: u64,
+    /// Records whose CRC no longer matches.
+    pub corrupt: Vec<Lsn>,
+    /// Sealed segments checked whole against their footers, with no
+    /// per-record reads.
+    pub segments_verified: usize,
+    /// Sealed segments that no longer match their footers. Their records
+    /// are checked one by one to find the damage.
+    pub damaged_segments: Vec<u32>,
+}
+
+// ---------------------------------------------------------------------------
+// MemoryBudget — resident bytes across caches and indexes
+// ---------------------------------------------------------------------------
+
+/// What a [`MemoryCharge`] holds memory for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum MemoryKind {
+    /// Storage's materialized page cache.
+    PageCache,
+    /// Compute buffer pools, all nodes together.
+    BufferPools,
+    /// Storage's page index and LSN → WAL location map.
+    LsnIndex,
+    /// Storage's per-page counters.
+    PageStats,
+}
+
+impl MemoryKind {
+    const ALL: [MemoryKind; 4] = [
+        MemoryKind::PageCache,
+        MemoryKind::BufferPools,
+        MemoryKind::LsnIndex,
+        MemoryKind::PageStats,
+    ];
+}
+
+/// Resident bytes shared by every cache and index in one process, against
+/// an optional limit.
+///
+/// Holders report their size through a [`MemoryCharge`]. The budget only
+/// counts: holders that can give memory back check [`MemoryBudget::over_by`]
+/// and evict until it is zero. The LSN index and page stats can't be
+/// dropped without losing data, so the caches make room for them.
+#[derive(Debug, Default)]
+pub struct MemoryBudget {
+    /// 0 = unlimited.
+    limit: AtomicU64,
+    used: [AtomicU64; 4],
+}
+
+impl MemoryBudget {
+    pub fn new(limit: Option<u64>) -> Self {
+        let budget = Self::default();
+        budget.set_limit(limit);
+        budget
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        Some(self.limit.load(Ordering::Relaxed)).filter(|&l| l > 0)
+    }
+
+    pub fn set_limit(&self, limit: Option<u64>) {
+        self.limit.store(limit.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Start charging `kind` against this budget, at 0 bytes.
+    pub fn charge(self: &Arc<Self>, kind: MemoryKind) -> MemoryCharge {
+        MemoryCharge { budget: Arc::clone(self), kind, bytes: 0 }
+    }
+
+    /// Bytes charged across every holder.
+    pub fn used(&self) -> u64 {
+        self.used.iter().map(|u| u.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Whether `bytes` more would stay within the limit.
+    pub fn fits(&self, bytes: u64) -> bool {
+        self.limit().is_none_or(|limit| self.used() + bytes <= limit)
+    }
+
+    /// Bytes above the limit; 0 when within it or unlimited.
+    pub fn over_by(&self) -> u64 {
+        self.limit().map_or(0, |limit| self.used().saturating_sub(limit))
+    }
+
+    pub fn usage(&self) -> MemoryUsage {
+        let [page_cache, buffer_pools, lsn_index, page_stats] =
+            MemoryKind::ALL.map(|kind| self.used[kind as usize].load(Ordering::Relaxed));
+        MemoryUsage { limit: self.limit(), page_cache, buffer_pools, lsn_index, page_stats }
+    }
+}
+
+/// One holder's share of a [`MemoryBudget`]. Dropping it gives the bytes back.
+#[derive(Debug)]
+pub struct MemoryCharge {
+    budget: Arc<MemoryBudget>,
+    kind: MemoryKind,
+    bytes: u64,
+}
+
+impl MemoryCharge {
+    /// Report the holder's current size.
+    pub fn set(&mut self, bytes: u64) {
+        let used = &self.budget.used[self.kind as usize];
+        if bytes >= self.bytes {
+            used.fetch_add(bytes - self.bytes, Ordering::Relaxed);
+        } else {
+            used.fetch_sub(self.bytes - bytes, Ordering::Relaxed);
+        }
+        self.bytes = bytes;
+    }
+
+    pub fn budget(&self) -> &MemoryBudget {
+        &self.budget
+    }
+}
+
+impl Drop for MemoryCharge {
+    fn drop(&mut self) {
+        self.set(0);
+    }
+}
+
+/// Snapshot of a [`MemoryBudget`], by holder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryUsage {
+    pub limit: Option<u64>,
+    pub page_cache: u64,
+    pub buffer_pools: u64,
+    pub lsn_index: u64,
+    pub page_stats: u64,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> u64 {
+        self.page_cache + self.buffer_pools + self.lsn_index + self.page_stats
+    }
+}
+
+// ---------------------------------------------------------------------------
+// EngineObserver — instrumentation hooks on the read and write paths
+// ---------------------------------------------------------------------------
+
+/// Callbacks fired at each internal step of the storage and compute engines.
+///
+/// Every hook defaults to a no-op, so an observer only implements the steps
+/// it cares about. Hooks run synchronously on the calling task, sometimes
+/// with engine locks held: they must not call back into the engine.
+pub trait EngineObserver: Send + Sync {
+    // ── Storage write path ───────────────────────────────────────────
+
+    /// LSNs `first..=last` were assigned to an incoming batch.
+    fn lsns_assigned(&self, _first: Lsn, _last: Lsn) {}
+
+    /// A record was linked to the previous record for its page.
+    fn prev_lsn_linked(&self, _lsn: Lsn, _page_id: PageId, _prev_lsn: Lsn) {}
+
+    /// A batch of `bytes` was appended to the WAL at `offset` (0 when the
+    /// backend doesn't track a single file offset).
+    fn wal_appended(&self, _first: Lsn, _last: Lsn, _offset: u64, _bytes: u64) {}
+
+    /// The WAL was fsynced, taking `elapsed`. A pipelined append counts
+    /// from when it was submitted, since it waits for its group's write.
+    fn wal_synced(&self, _elapsed: Duration) {}
+
+    /// The file offset of `lsn` was recorded.
+    fn lsn_offset_recorded(&self, _lsn: Lsn, _file_offset: u64) {}
+
+    /// The page index now points `page_id` at `latest_lsn`.
+    fn page_index_updated(&self, _page_id: PageId, _latest_lsn: Lsn) {}
+
+    fn vcl_advanced(&self, _old: Lsn, _new: Lsn) {}
+
+    fn vdl_advanced(&self, _old: Lsn, _new: Lsn) {}
+
+    // ── Storage read path ────────────────────────────────────────────
+
+    /// The page index was consulted; `None` if the page was never written.
+    fn page_index_lookup(&self, _page_id: PageId, _latest_lsn: Option<Lsn>) {}
+
+    fn page_cache_lookup(&self, _page_id: PageId, _read_point: Lsn, _hit: bool) {}
+
+    /// One record of the prev_lsn chain was read. `skipped` records are
+    /// newer than the read point and are not replayed.
+    fn chain_step(&self, _page_id: PageId, _lsn: Lsn, _prev_lsn: Lsn, _skipped: bool) {}
+
+    /// The redo chain for a page was collected, oldest record first, in
+    /// `elapsed`.
+    fn chain_collected(&self, _page_id: PageId, _chain: &[RedoRecord], _elapsed: Duration) {}
+
+    /// A redo record was replayed onto the page being materialized.
+    fn redo_applied(&self, _record: &RedoRecord) {}
+
+    /// The chain was replayed into the page, taking `elapsed`.
+    fn page_materialized(&self, _page_id: PageId, _read_point: Lsn, _elapsed: Duration) {}
+
+    fn page_cache_inserted(&self, _page_id: PageId, _read_point: Lsn) {}
+
+    /// Warmup went through `page_id`, the `done`th of the `total` pages it
+    /// preloads.
+    fn warmup_progress(&self, _page_id: PageId, _done: usize, _total: usize) {}
+
+    /// A read fetched a cold-tier segment, paying `latency`.
+    fn cold_tier_read(&self, _segment_id: u32, _latency: Duration) {}
+
+    /// The active WAL segment filled up: `sealed_id` (holding
+    /// `sealed_lsn_range` in `sealed_bytes`) was sealed and `new_id` opened.
+    fn segment_rotated(
+        &self,
+        _sealed_id: u32,
+        _new_id: u32,
+        _sealed_lsn_range: (Lsn, Lsn),
+        _sealed_bytes: u64,
+    ) {
+    }
+
+    /// A sealed segment moved from the hot tier to the cold tier.
+    fn segment_cooled(&self, _segment_id: u32) {}
+
+    /// A cold segment was copied back to the hot tier after a read.
+    fn segment_promoted(&self, _segment_id: u32) {}
+
+    // ── Recovery ─────────────────────────────────────────────────────
+
+    /// Recovery read a valid entry at `file_offset` (within its segment
+    /// file for tiered storage).
+    fn recovery_entry_scanned(&self, _lsn: Lsn, _page_id: PageId, _file_offset: u64, _is_mtr_end: bool) {}
+
+    /// Recovery found the highest LSN with no gaps below it.
+    fn recovery_vcl_computed(&self, _vcl: Lsn) {}
+
+    /// Recovery found the highest MTR completion point at or below the VCL.
+    fn recovery_vdl_computed(&self, _vdl: Lsn) {}
+
+    /// Recovery cut the WAL (the active segment when tiered) at `offset`,
+    /// discarding `bytes` past the VDL record.
+    fn recovery_truncated(&self, _offset: u64, _bytes: u64) {}
+
+    /// The page index and LSN offsets were rebuilt from the `records` that
+    /// survived, covering `pages` pages.
+    fn recovery_index_rebuilt(&self, _pages: usize, _records: usize) {}
+
+    // ── Compute ──────────────────────────────────────────────────────
+
+    fn mtr_created(&self, _mtr_id: u64, _num_records: usize) {}
+
+    /// `mtrs` batched puts, `records` records in all, sent in one append.
+    fn write_batch_flushed(&self, _mtrs: usize, _records: usize) {}
+
+    fn read_point_updated(&self, _old: Lsn, _new: Lsn) {}
+
+    fn buffer_pool_lookup(&self, _page_id: PageId, _read_point: Lsn, _hit: bool) {}
+
+    fn buffer_pool_inserted(&self, _page_id: PageId, _read_point: Lsn) {}
+
+    /// A resident page was dropped after a write to it.
+    fn buffer_pool_invalidated(&self, _page_id: PageId) {}
+
+    /// A write was applied to the resident image, now current at `read_point`.
+    fn buffer_pool_written_through(&self, _page_id: PageId, _read_point: Lsn) {}
+
+    /// Storage answered a heartbeat after `latency`, advertising `vdl`.
+    fn heartbeat_acked(&self, _latency: Duration, _vdl: Lsn) {}
+
+    /// A heartbeat failed or timed out, the `missed`th in a row.
+    fn heartbeat_missed(&self, _missed: u32) {}
+
+    /// Missed heartbeats marked storage unhealthy, or a beat got through
+    /// and marked it healthy again.
+    fn storage_health_changed(&self, _healthy: bool) {}
+}
+
+/// Observer that ignores every hook; the engines' default.
+pub struct NoopObserver;
+
+impl EngineObserver for NoopObserver {}
+
 // ---------------------------------------------------------------------------
 // Error types
 // ---------------------------------------------------------------------------
@@ -121,9 +827,26 @@ pub enum StorageError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
-    #[error("WAL corruption: {0}")]
+    /// An I/O call on a known file failed.
+    #[error("cannot {op} {}: {source}", path.display())]
+    File {
+        op: &'static str,
+        path: std::path::PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("corruption: {0}")]
     Corruption(String),
 
+    /// A WAL record that the indexes point to can't be read back.
+    #[error("WAL record for LSN {lsn} at {location} {problem}")]
+    CorruptRecord {
+        lsn: Lsn,
+        location: WalLocation,
+        problem: &'static str,
+    },
+
     #[error("page {page_id} not found at or below LSN {lsn}")]
     PageNotFound { page_id: PageId, lsn: Lsn },
 
@@ -133,10 +856,266 @@ pub enum StorageError {
     #[error("redo record data overflows page: offset={offset} len={len}")]
     PageOverflow { offset: u16, len: usize },
 
+    #[error("storage unavailable: {0}")]
+    Unavailable(String),
+
+    /// The simulated network cuts `node` off from storage.
+    #[error("node {node} is partitioned from storage")]
+    Partitioned { node: String },
+
+    /// Another engine has the volume open; the path is its lock file.
+    #[error("volume is already open in another engine (lock held on {})", .0.display())]
+    VolumeLocked(std::path::PathBuf),
+
+    /// The volume takes no writes: it was opened read-only or as of a past
+    /// time, or a WAL write or fsync failed. Only the error that stopped
+    /// writes carries the I/O error behind it.
+    #[error("volume is read-only: {reason}")]
+    VolumeReadOnly {
+        reason: String,
+        #[source]
+        source: Option<std::io::Error>,
+    },
+
+    /// The read asks for history `AdminApi::truncate_before` dropped.
+    #[error("LSN {requested} is below the truncation point {truncated_before}")]
+    Truncated { requested: Lsn, truncated_before: Lsn },
+
+    /// `AdminApi::truncate_before` would drop data something still needs.
+    #[error("cannot truncate before LSN {lsn}: {reason}")]
+    TruncateRefused { lsn: Lsn, reason: String },
+
+    /// Compute and storage can't agree on an API or wire version, or an
+    /// open asks for a page size other than the volume's.
+    #[error("incompatible peers: {0}")]
+    Incompatible(String),
+
     #[error("{0}")]
     Other(String),
 }
 
+impl StorageError {
+    /// `File` error for `op` on `path`, for `map_err`.
+    pub fn file(op: &'static str, path: &std::path::Path) -> impl FnOnce(std::io::Error) -> Self {
+        let path = path.to_path_buf();
+        move |source| Self::File { op, path, source }
+    }
+
+    /// A copy of the error, for when one failure is reported to several
+    /// callers. Errors that carry an `io::Error` keep its kind and message.
+    pub fn share(&self) -> Self {
+        match self {
+            Self::Io(io) => Self::Io(std::io::Error::new(io.kind(), io.to_string())),
+            Self::File { op, path, source } => Self::File {
+                op,
+                path: path.clone(),
+                source: std::io::Error::new(source.kind(), source.to_string()),
+            },
+            Self::Corruption(s) => Self::Corruption(s.clone()),
+            Self::CorruptRecord { lsn, location, problem } => {
+                Self::CorruptRecord { lsn: *lsn, location: *location, problem }
+            }
+            Self::PageNotFound { page_id, lsn } => Self::PageNotFound { page_id: *page_id, lsn: *lsn },
+            Self::LsnBeyondDurable { requested, durable } => {
+                Self::LsnBeyondDurable { requested: *requested, durable: *durable }
+            }
+            Self::PageOverflow { offset, len } => Self::PageOverflow { offset: *offset, len: *len },
+            Self::Unavailable(s) => Self::Unavailable(s.clone()),
+            Self::Partitioned { node } => Self::Partitioned { node: node.clone() },
+            Self::VolumeLocked(path) => Self::VolumeLocked(path.clone()),
+            Self::VolumeReadOnly { reason, source } => Self::VolumeReadOnly {
+                reason: reason.clone(),
+                source: source.as_ref().map(|e| std::io::Error::new(e.kind(), e.to_string())),
+            },
+            Self::Truncated { requested, truncated_before } => {
+                Self::Truncated { requested: *requested, truncated_before: *truncated_before }
+            }
+            Self::TruncateRefused { lsn, reason } => {
+                Self::TruncateRefused { lsn: *lsn, reason: reason.clone() }
+            }
+            Self::Incompatible(s) => Self::Incompatible(s.clone()),
+            Self::Other(s) => Self::Other(s.clone()),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Io(_) | Self::File { .. } => ErrorCode::Io,
+            Self::Corruption(_) | Self::CorruptRecord { .. } => ErrorCode::Corruption,
+            Self::PageNotFound { .. } => ErrorCode::PageNotFound,
+            Self::LsnBeyondDurable { .. } => ErrorCode::LsnBeyondDurable,
+            Self::PageOverflow { .. } => ErrorCode::PageOverflow,
+            Self::Unavailable(_) | Self::Partitioned { .. } => ErrorCode::Unavailable,
+            Self::VolumeLocked(_) => ErrorCode::VolumeLocked,
+            Self::VolumeReadOnly { .. } => ErrorCode::VolumeReadOnly,
+            Self::Incompatible(_) => ErrorCode::Incompatible,
+            Self::Truncated { .. } => ErrorCode::Truncated,
+            Self::TruncateRefused { .. } => ErrorCode::TruncateRefused,
+            Self::Other(_) => ErrorCode::Other,
+        }
+    }
+}
+
+/// Where in the WAL a record sits: a file offset, within a segment for
+/// segmented storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalLocation {
+    /// `None` for the single-file WAL.
+    pub segment_id: Option<u32>,
+    pub file_offset: u64,
+}
+
+impl fmt::Display for WalLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.segment_id {
+            Some(id) => write!(f, "segment {id} offset {}", self.file_offset),
+            None => write!(f, "WAL offset {}", self.file_offset),
+        }
+    }
+}
+
+/// Stable, transport-friendly classification of a `StorageError`, for an
+/// RPC layer to send instead of the error itself. Codes are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u16)]
+pub enum ErrorCode {
+    Io = 1,
+    Corruption = 2,
+    PageNotFound = 3,
+    LsnBeyondDurable = 4,
+    PageOverflow = 5,
+    Unavailable = 6,
+    VolumeLocked = 7,
+    Incompatible = 8,
+    VolumeReadOnly = 9,
+    Truncated = 10,
+    TruncateRefused = 11,
+    Other = 255,
+}
+
+impl ErrorCode {
+    /// The same request may succeed later without anything changing on the
+    /// caller's side.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::Unavailable | Self::VolumeLocked)
+    }
+}
+
+/// Reject a byte range that does not fit within a single page.
+pub fn check_page_range(offset: u16, len: usize, page_size: usize) -> Result<(), StorageError> {
+    if offset as usize + len > page_size {
+        return Err(StorageError::PageOverflow { offset, len });
+    }
+    Ok(())
+}
+
+impl fmt::Display for StorageStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Records:        {}", self.total_records)?;
+        for (label, tier) in [("Hot segments:  ", &self.hot), ("Cold segments: ", &self.cold)] {
+            write!(f, "{label} {} ({} bytes", tier.segments, tier.bytes)?;
+            if tier.disk_busy_us > 0 {
+                write!(f, ", {:.1} ms simulated I/O", tier.disk_busy_us as f64 / 1000.0)?;
+            }
+            writeln!(f, ")")?;
+        }
+        writeln!(f, "Pages:          {} of {} bytes", self.pages, self.page_size)?;
+        writeln!(f, "Chain length:   avg {:.1}, max {}", self.avg_chain_len, self.max_chain_len)?;
+        write!(
+            f,
+            "Page cache:     {} entries ({}), {} rolled forward, {} evicted, {} expired",
+            self.cache_entries,
+            self.cache_policy,
+            self.rolled_forward,
+            self.cache_evictions,
+            self.cache_expirations
+        )?;
+        if self.coalesced_reads > 0 {
+            write!(f, ", {} reads coalesced", self.coalesced_reads)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Page store:     {} images, {} reads served", self.page_images, self.image_reads)?;
+        if !self.hot_pages.is_empty() {
+            let hot: Vec<String> =
+                self.hot_pages.iter().map(|p| format!("pg{} {}r/{}w", p.page_id, p.reads, p.writes)).collect();
+            writeln!(f, "Hot pages:      {}", hot.join(", "))?;
+        }
+        writeln!(f, "Readers:        {} leased, min read point {}", self.readers, self.min_read_point)?;
+        writeln!(f, "Memory:         {}", self.memory)?;
+        let r = &self.recovery;
+        if r.clean_shutdown {
+            return write!(
+                f,
+                "Recovery:       skipped after clean shutdown, {} records indexed, VDL={}, {:.1}ms",
+                r.records, r.vdl, r.duration_ms
+            );
+        }
+        write!(
+            f,
+            "Recovery:       {} records, VCL={} VDL={}, {} bytes truncated, {:.1}ms",
+            r.records, r.vcl, r.vdl, r.truncated_bytes, r.duration_ms
+        )
+    }
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+        write!(f, "{:.1} MB", mb(self.total()))?;
+        if let Some(limit) = self.limit {
+            write!(f, " of {:.1} MB", mb(limit))?;
+        }
+        write!(
+            f,
+            " (page cache {:.1}, buffer pools {:.1}, LSN index {:.1}, page stats {:.1})",
+            mb(self.page_cache),
+            mb(self.buffer_pools),
+            mb(self.lsn_index),
+            mb(self.page_stats)
+        )
+    }
+}
+
+impl fmt::Display for QosClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            QosClass::Foreground => "fg",
+            QosClass::Background => "bg",
+        })
+    }
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("{}/s burst {}", self.rate, self.burst))
+    }
+}
+
+impl fmt::Display for QosReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let limit = |l: Option<RateLimit>| l.map_or("unlimited".to_string(), |l| l.to_string());
+        writeln!(f, "Foreground:  {}", limit(self.foreground_limit))?;
+        write!(f, "Background:  {} (waits for foreground requests)", limit(self.background_limit))?;
+        if self.callers.is_empty() {
+            return Ok(());
+        }
+        write!(
+            f,
+            "\n{:<10} {:<5} {:<16} {:>9} {:>9} {:>9} {:>10}",
+            "caller", "class", "own limit", "admitted", "throttled", "deferred", "waited"
+        )?;
+        for c in &self.callers {
+            let own = c.limit.map_or("-".to_string(), |l| l.to_string());
+            write!(
+                f,
+                "\n{:<10} {:<5} {:<16} {:>9} {:>9} {:>9} {:>8.1}ms",
+                c.caller, c.class, own, c.admitted, c.throttled, c.deferred, c.waited_ms
+            )?;
+        }
+        Ok(())
+    }
+}
+
 impl fmt::Display for DurabilityState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "DurabilityState(VCL={}, VDL={})", self.vcl, self.vdl)
@@ -0,0 +1,128 @@
+//! Binary wire format for what crosses the compute ↔ storage boundary:
+//! batches of redo records and durability states.
+//!
+//! Every message is an envelope — the magic `MA`, a format version and a
+//! message kind, one byte each after the magic — followed by the bincode
+//! encoding of the payload. A reader rejects other versions instead of
+//! misreading them, so the payload layout can change with the version.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{DurabilityState, RedoRecord, StorageError};
+
+/// Version written into every envelope; the only one `decode_*` accepts.
+pub const WIRE_VERSION: u8 = 1;
+
+const MAGIC: [u8; 2] = *b"MA";
+/// Magic, version and kind.
+const ENVELOPE_SIZE: usize = MAGIC.len() + 2;
+
+/// What an envelope carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MessageKind {
+    RedoBatch = 1,
+    DurabilityState = 2,
+}
+
+impl MessageKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Self::RedoBatch),
+            2 => Some(Self::DurabilityState),
+            _ => None,
+        }
+    }
+}
+
+pub fn encode_redo_batch(records: &[RedoRecord]) -> Vec<u8> {
+    encode(MessageKind::RedoBatch, &records)
+}
+
+pub fn decode_redo_batch(bytes: &[u8]) -> Result<Vec<RedoRecord>, StorageError> {
+    decode(MessageKind::RedoBatch, bytes)
+}
+
+pub fn encode_durability_state(state: &DurabilityState) -> Vec<u8> {
+    encode(MessageKind::DurabilityState, state)
+}
+
+pub fn decode_durability_state(bytes: &[u8]) -> Result<DurabilityState, StorageError> {
+    decode(MessageKind::DurabilityState, bytes)
+}
+
+/// The kind of message in `bytes`, after checking its envelope.
+pub fn peek_kind(bytes: &[u8]) -> Result<MessageKind, StorageError> {
+    if bytes.len() < ENVELOPE_SIZE || bytes[..MAGIC.len()] != MAGIC {
+        return Err(StorageError::Corruption("wire message has no envelope".into()));
+    }
+    let version = bytes[MAGIC.len()];
+    if version != WIRE_VERSION {
+        return Err(StorageError::Other(format!(
+            "unsupported wire version {version} (this build speaks {WIRE_VERSION})"
+        )));
+    }
+    let kind = bytes[MAGIC.len() + 1];
+    MessageKind::from_byte(kind)
+        .ok_or_else(|| StorageError::Corruption(format!("unknown wire message kind {kind}")))
+}
+
+fn encode<T: Serialize + ?Sized>(kind: MessageKind, payload: &T) -> Vec<u8> {
+    let mut out = Vec::with_capacity(ENVELOPE_SIZE + bincode::serialized_size(payload).unwrap_or(0) as usize);
+    out.extend_from_slice(&MAGIC);
+    out.push(WIRE_VERSION);
+    out.push(kind as u8);
+    // Serializing plain structs into a Vec can't fail
+    bincode::serialize_into(&mut out, payload).expect("wire payloads always serialize");
+    out
+}
+
+fn decode<T: DeserializeOwned>(kind: MessageKind, bytes: &[u8]) -> Result<T, StorageError> {
+    let found = peek_kind(bytes)?;
+    if found != kind {
+        return Err(StorageError::Corruption(format!("expected a {kind:?} wire message, got {found:?}")));
+    }
+    bincode::deserialize(&bytes[ENVELOPE_SIZE..])
+        .map_err(|e| StorageError::Corruption(format!("malformed {kind:?} wire message: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(lsn: u64, data: &[u8]) -> RedoRecord {
+        RedoRecord {
+            lsn,
+            page_id: 7,
+            offset: 16,
+            data: data.to_vec().into(),
+            prev_lsn: lsn - 1,
+            mtr_id: 3,
+            is_mtr_end: lsn == 2,
+            timestamp: 1_700_000_000_000_000,
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let batch = vec![record(1, b"hello"), record(2, &[0; 300])];
+        let bytes = encode_redo_batch(&batch);
+        assert_eq!(peek_kind(&bytes).unwrap(), MessageKind::RedoBatch);
+        assert_eq!(decode_redo_batch(&bytes).unwrap(), batch);
+
+        let state = decode_durability_state(&encode_durability_state(&DurabilityState { vcl: 9, vdl: 8 })).unwrap();
+        assert_eq!((state.vcl, state.vdl), (9, 8));
+    }
+
+    #[test]
+    fn test_rejects_bad_envelopes() {
+        let mut bytes = encode_redo_batch(&[record(1, b"x")]);
+        assert!(matches!(decode_durability_state(&bytes), Err(StorageError::Corruption(_))));
+        assert!(matches!(decode_redo_batch(&bytes[..bytes.len() - 1]), Err(StorageError::Corruption(_))));
+        assert!(matches!(decode_redo_batch(b"MA"), Err(StorageError::Corruption(_))));
+
+        bytes[2] = WIRE_VERSION + 1;
+        assert!(matches!(decode_redo_batch(&bytes), Err(StorageError::Other(_))));
+    }
+}
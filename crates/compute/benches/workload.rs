@@ -0,0 +1,65 @@
+//! YCSB-style workloads through the full compute → storage path. Comparing
+//! the zipfian and uniform runs shows how much key skew the buffer pool and
+//! page cache can absorb.
+//!
+//! Run with `cargo bench -p mini-aurora-compute --bench workload`.
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mini_aurora_compute::engine::ComputeEngine;
+use mini_aurora_compute::workload::{KeyDistribution, WorkloadOp, WorkloadSpec};
+use mini_aurora_storage::engine::StorageEngine;
+use tempfile::TempDir;
+
+/// Buffer pool much smaller than the record count, so skew matters.
+const BUFFER_POOL_PAGES: usize = 16;
+
+async fn run(compute: &ComputeEngine, spec: &WorkloadSpec) {
+    for op in spec.operations() {
+        match op {
+            WorkloadOp::Read { page_id } => {
+                compute.get(page_id).await.unwrap();
+            }
+            WorkloadOp::Update { page_id, offset, data } => {
+                compute.put(page_id, offset, data).await.unwrap();
+            }
+        }
+    }
+}
+
+fn bench_ycsb_b(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("ycsb_b");
+
+    let zipfian = WorkloadSpec {
+        record_count: 200,
+        operation_count: 500,
+        ..WorkloadSpec::preset("b").unwrap()
+    };
+    let uniform = WorkloadSpec {
+        distribution: KeyDistribution::Uniform,
+        ..zipfian.clone()
+    };
+
+    for (name, spec) in [("zipfian", zipfian), ("uniform", uniform)] {
+        let dir = TempDir::new().unwrap();
+        let storage = Arc::new(StorageEngine::open(&dir.path().join("bench.wal")).unwrap());
+        let compute = ComputeEngine::new(storage, BUFFER_POOL_PAGES);
+
+        // Load phase: every record exists before the timed runs
+        rt.block_on(async {
+            for page_id in 1..=spec.record_count {
+                compute.put(page_id, 0, vec![0u8; spec.value_size]).await.unwrap();
+            }
+        });
+
+        group.bench_with_input(BenchmarkId::from_parameter(name), &spec, |b, spec| {
+            b.to_async(&rt).iter(|| run(&compute, spec));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_ycsb_b);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+//! Page allocation bitmap, kept on a reserved metadata page.
+//!
+//! Page 0 holds one bit per page: bit `i % 8` of byte `i / 8` is set while
+//! page `i` is allocated. Page 0 itself is never handed out and is not for
+//! data. Each allocation or free is a one-byte write to page 0 in its own
+//! MTR, so after a crash the bitmap matches exactly the MTRs that became
+//! durable.
+
+use mini_aurora_common::{PageId, StorageError};
+
+/// The metadata page holding the bitmap.
+pub const ALLOCATOR_PAGE: PageId = 0;
+
+/// Pages the bitmap can track on a volume of `page_size`-byte pages, page
+/// 0 included.
+pub fn allocatable_pages(page_size: usize) -> PageId {
+    (page_size * 8) as PageId
+}
+
+/// Byte offset of `page_id`'s bit within the bitmap page, and its mask.
+pub(crate) fn bit(page_id: PageId) -> (u16, u8) {
+    ((page_id / 8) as u16, 1 << (page_id % 8))
+}
+
+pub(crate) fn is_allocated(bitmap: &[u8], page_id: PageId) -> bool {
+    let (byte, mask) = bit(page_id);
+    bitmap[byte as usize] & mask != 0
+}
+
+/// The lowest free page after the metadata page.
+pub(crate) fn first_free(bitmap: &[u8]) -> Option<PageId> {
+    bitmap.iter().enumerate().find_map(|(i, &byte)| {
+        let taken = if i == 0 { byte | 1 } else { byte };
+        (taken != u8::MAX).then(|| i as PageId * 8 + taken.trailing_ones() as PageId)
+    })
+}
+
+/// Reject pages the bitmap can't free: the metadata page and pages past the end.
+pub(crate) fn check_freeable(page_id: PageId, page_size: usize) -> Result<(), StorageError> {
+    let allocatable = allocatable_pages(page_size);
+    if page_id == ALLOCATOR_PAGE || page_id >= allocatable {
+        return Err(StorageError::Other(format!(
+            "page {page_id} is not allocatable (pages 1..{allocatable})"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mini_aurora_common::PAGE_SIZE;
+
+    #[test]
+    fn test_first_free_skips_metadata_page() {
+        let mut bitmap = [0u8; PAGE_SIZE];
+        assert_eq!(first_free(&bitmap), Some(1));
+        bitmap[0] = 0b1111_1110;
+        assert_eq!(first_free(&bitmap), Some(8));
+        bitmap[1] = 0b0000_0111;
+        assert_eq!(first_free(&bitmap), Some(11));
+        assert!(is_allocated(&bitmap, 10));
+        assert!(!is_allocated(&bitmap, 11));
+
+        bitmap.fill(u8::MAX);
+        assert_eq!(first_free(&bitmap), None);
+    }
+
+    #[test]
+    fn test_bit_positions() {
+        assert_eq!(bit(1), (0, 0b10));
+        assert_eq!(bit(8), (1, 1));
+        assert_eq!(bit(allocatable_pages(PAGE_SIZE) - 1), ((PAGE_SIZE - 1) as u16, 0x80));
+        assert!(check_freeable(0, PAGE_SIZE).is_err());
+        assert!(check_freeable(allocatable_pages(PAGE_SIZE), PAGE_SIZE).is_err());
+        assert!(check_freeable(1, PAGE_SIZE).is_ok());
+        assert!(check_freeable(allocatable_pages(4096), 4096).is_err());
+        assert!(check_freeable(allocatable_pages(4096), 16384).is_ok());
+    }
+}
@@ -0,0 +1,142 @@
+//! Write batching: `put`s arriving within a short window are sent to
+//! storage together, as one `append_redo` holding each of their MTRs.
+//! Every put in a batch waits for the whole batch, so batching trades
+//! latency for fewer, larger WAL appends and syncs.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use mini_aurora_common::{Lsn, RedoRecord, StorageError};
+use tokio::sync::{oneshot, Notify};
+
+/// How `put`s are batched; see [`ComputeEngine::with_write_batching`].
+///
+/// [`ComputeEngine::with_write_batching`]: crate::engine::ComputeEngine::with_write_batching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteBatching {
+    /// How long the first put of a batch waits for others to join it.
+    pub window: Duration,
+    /// Records that send a batch before its window is up.
+    pub max_records: usize,
+}
+
+impl Default for WriteBatching {
+    fn default() -> Self {
+        Self { window: Duration::from_millis(2), max_records: 64 }
+    }
+}
+
+/// Batches sent so far; see [`ComputeEngine::write_batch_stats`].
+///
+/// [`ComputeEngine::write_batch_stats`]: crate::engine::ComputeEngine::write_batch_stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteBatchStats {
+    /// `append_redo` calls made for batches.
+    pub batches: u64,
+    /// MTRs (one per put) sent in them.
+    pub mtrs: u64,
+    pub records: u64,
+    /// Batches sent early because they reached `max_records`.
+    pub full: u64,
+    /// Most MTRs sent in one batch.
+    pub largest: usize,
+}
+
+impl WriteBatchStats {
+    /// MTRs per `append_redo`; 0 before the first batch.
+    pub fn mean_mtrs(&self) -> f64 {
+        if self.batches == 0 {
+            0.0
+        } else {
+            self.mtrs as f64 / self.batches as f64
+        }
+    }
+}
+
+pub(crate) type BatchResult = Result<Lsn, StorageError>;
+
+/// The batch puts are joining, until its flush takes it.
+pub(crate) struct OpenBatch {
+    pub records: Vec<RedoRecord>,
+    pub mtrs: usize,
+    waiters: Vec<oneshot::Sender<BatchResult>>,
+    /// Wakes the flush early once the batch is full.
+    full: Arc<Notify>,
+}
+
+impl OpenBatch {
+    /// Hand every put in the batch the result of sending it.
+    pub fn reply(self, result: &BatchResult) {
+        for waiter in self.waiters {
+            let _ = waiter.send(share(result));
+        }
+    }
+}
+
+#[derive(Default)]
+struct BatcherState {
+    open: Option<OpenBatch>,
+    stats: WriteBatchStats,
+}
+
+pub(crate) struct WriteBatcher {
+    config: WriteBatching,
+    state: Mutex<BatcherState>,
+}
+
+impl WriteBatcher {
+    pub fn new(config: WriteBatching) -> Self {
+        Self { config, state: Mutex::default() }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.config.window
+    }
+
+    /// Add one MTR's records to the open batch, opening one if there is
+    /// none. Returns where its result will arrive and, for the put that
+    /// opened the batch, the notification its flush should wait on.
+    pub fn join(&self, records: Vec<RedoRecord>) -> (oneshot::Receiver<BatchResult>, Option<Arc<Notify>>) {
+        let (tx, rx) = oneshot::channel();
+        let mut state = self.state.lock().unwrap();
+        let opened = state.open.is_none();
+        let batch = state.open.get_or_insert_with(|| OpenBatch {
+            records: Vec::new(),
+            mtrs: 0,
+            waiters: Vec::new(),
+            full: Arc::new(Notify::new()),
+        });
+        batch.records.extend(records);
+        batch.mtrs += 1;
+        batch.waiters.push(tx);
+        if batch.records.len() >= self.config.max_records {
+            // Stored as a permit if the flush is not waiting yet
+            batch.full.notify_one();
+        }
+        (rx, opened.then(|| Arc::clone(&batch.full)))
+    }
+
+    /// Close the open batch for sending, counting it in the stats.
+    pub fn take(&self) -> Option<OpenBatch> {
+        let mut state = self.state.lock().unwrap();
+        let batch = state.open.take()?;
+        let stats = &mut state.stats;
+        stats.batches += 1;
+        stats.mtrs += batch.mtrs as u64;
+        stats.records += batch.records.len() as u64;
+        if batch.records.len() >= self.config.max_records {
+            stats.full += 1;
+        }
+        stats.largest = stats.largest.max(batch.mtrs);
+        Some(batch)
+    }
+
+    pub fn stats(&self) -> WriteBatchStats {
+        self.state.lock().unwrap().stats
+    }
+}
+
+/// A copy of a batch's result for one of its puts.
+fn share(result: &BatchResult) -> BatchResult {
+    result.as_ref().map(|vdl| *vdl).map_err(StorageError::share)
+}
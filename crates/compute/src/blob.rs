@@ -0,0 +1,80 @@
+//! Values larger than a page, stored across consecutive pages.
+//!
+//! The first page starts with the blob's length (`u32`, little-endian), and
+//! the bytes follow it, filling each page before moving to the next. The
+//! caller picks the first page and must keep the `blob_pages` range free of
+//! other data; `ComputeEngine::allocate_page` hands out single pages, not
+//! runs, so a blob's range is reserved by hand.
+
+use std::ops::Range;
+
+use mini_aurora_common::{Bytes, PageId, StorageError};
+
+/// Bytes of the length prefix on the first page.
+pub const BLOB_HEADER_SIZE: usize = 4;
+
+/// Pages of `page_size` bytes a blob of `len` bytes occupies when it starts
+/// at `start_page`.
+pub fn blob_pages(start_page: PageId, len: usize, page_size: usize) -> Range<PageId> {
+    let pages = (BLOB_HEADER_SIZE + len).div_ceil(page_size);
+    start_page..start_page + pages as PageId
+}
+
+/// One write per page of the blob, each starting at offset 0: the header and
+/// the start of the data on the first page, then the rest page by page.
+pub(crate) fn split_blob(
+    start_page: PageId,
+    data: &[u8],
+    page_size: usize,
+) -> Result<Vec<(PageId, Bytes)>, StorageError> {
+    let len = u32::try_from(data.len())
+        .map_err(|_| StorageError::Other(format!("blob of {} bytes exceeds the 4 GiB limit", data.len())))?;
+    let mut first = len.to_le_bytes().to_vec();
+    let head = data.len().min(page_size - BLOB_HEADER_SIZE);
+    first.extend_from_slice(&data[..head]);
+
+    let mut writes = vec![(start_page, Bytes::from(first))];
+    for (i, chunk) in data[head..].chunks(page_size).enumerate() {
+        writes.push((start_page + 1 + i as PageId, Bytes::from(chunk.to_vec())));
+    }
+    Ok(writes)
+}
+
+/// The length stored on a blob's first page.
+pub(crate) fn blob_len(first_page: &[u8]) -> usize {
+    let mut len = [0u8; BLOB_HEADER_SIZE];
+    len.copy_from_slice(&first_page[..BLOB_HEADER_SIZE]);
+    u32::from_le_bytes(len) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mini_aurora_common::PAGE_SIZE;
+
+    #[test]
+    fn test_split_fills_pages_in_order() {
+        let data: Vec<u8> = (0..2 * PAGE_SIZE).map(|i| i as u8).collect();
+        let writes = split_blob(10, &data, PAGE_SIZE).unwrap();
+        assert_eq!(writes.iter().map(|w| (w.0, w.1.len())).collect::<Vec<_>>(), [
+            (10, PAGE_SIZE),
+            (11, PAGE_SIZE),
+            (12, BLOB_HEADER_SIZE)
+        ]);
+        assert_eq!(blob_pages(10, data.len(), PAGE_SIZE), 10..13);
+        assert_eq!(blob_len(&writes[0].1), data.len());
+
+        let joined: Vec<u8> = writes.iter().flat_map(|w| w.1.iter().copied()).skip(BLOB_HEADER_SIZE).collect();
+        assert_eq!(joined, data);
+    }
+
+    #[test]
+    fn test_small_and_empty_blobs_take_one_page() {
+        assert_eq!(split_blob(1, b"hi", PAGE_SIZE).unwrap().len(), 1);
+        assert_eq!(split_blob(1, b"", PAGE_SIZE).unwrap().len(), 1);
+        assert_eq!(blob_pages(1, 0, PAGE_SIZE), 1..2);
+        assert_eq!(blob_pages(1, PAGE_SIZE - BLOB_HEADER_SIZE, PAGE_SIZE), 1..2);
+        assert_eq!(blob_pages(1, PAGE_SIZE - BLOB_HEADER_SIZE + 1, PAGE_SIZE), 1..3);
+        assert_eq!(blob_pages(1, PAGE_SIZE - BLOB_HEADER_SIZE, 4096), 1..3);
+    }
+}
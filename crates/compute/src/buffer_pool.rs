@@ -1,52 +1,156 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 
-use mini_aurora_common::{Lsn, Page, PageId};
+use std::sync::Arc;
+
+use mini_aurora_common::{
+    Lsn, MemoryBudget, MemoryCharge, MemoryKind, PageId, PageRef, RedoRecord, PAGE_SIZE,
+};
 
 /// Local buffer pool on the compute side.
 ///
 /// Caches pages read from storage at specific read-point LSNs. In Aurora's
 /// architecture, the compute node maintains a buffer cache to avoid repeated
 /// round-trips to storage for hot pages.
+///
+/// Eviction uses the CLOCK (second-chance) policy: a hit sets the page's
+/// reference bit, and the clock hand clears bits until it finds an
+/// unreferenced, unpinned victim. Pinned pages are never evicted. The pool is
+/// bounded by a byte budget; every resident page is charged its page size. A
+/// pool that shares a [`MemoryBudget`] also evicts while that is over its
+/// limit.
 pub struct BufferPool {
     pages: HashMap<PageId, CachedPage>,
-    capacity: usize,
+    /// Clock ring — front is where the hand currently points.
+    clock: VecDeque<PageId>,
+    budget_bytes: usize,
+    /// Bytes per page of the volume the pool caches.
+    page_size: usize,
+    memory: Option<MemoryCharge>,
+    stats: BufferPoolStats,
 }
 
 struct CachedPage {
-    page: Page,
+    page: PageRef,
     read_point: Lsn,
     access_count: u64,
+    referenced: bool,
+    pin_count: u32,
+    /// Brought in by read-ahead and not yet read.
+    prefetched: bool,
+}
+
+/// One resident page, as reported by [`BufferPool::entries`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferPoolEntry {
+    pub page_id: PageId,
+    pub read_point: Lsn,
+    pub access_count: u64,
+}
+
+/// Counters exposed for the REPL and metrics layer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub resident_pages: usize,
+    pub resident_bytes: usize,
+    pub budget_bytes: usize,
+    /// Pages brought in by prefetch.
+    pub prefetched: u64,
+    /// Prefetched pages that were later read.
+    pub prefetch_hits: u64,
 }
 
 impl BufferPool {
+    /// Create a pool that holds up to `capacity` pages.
     pub fn new(capacity: usize) -> Self {
+        Self::with_page_size(capacity, PAGE_SIZE)
+    }
+
+    /// Create a pool that holds up to `capacity` pages of `page_size` bytes.
+    pub fn with_page_size(capacity: usize, page_size: usize) -> Self {
+        Self { page_size, ..Self::with_budget_bytes(capacity * page_size) }
+    }
+
+    /// Create a pool bounded by a memory budget in bytes.
+    pub fn with_budget_bytes(budget_bytes: usize) -> Self {
         Self {
             pages: HashMap::new(),
-            capacity,
+            clock: VecDeque::new(),
+            budget_bytes,
+            page_size: PAGE_SIZE,
+            memory: None,
+            stats: BufferPoolStats {
+                budget_bytes,
+                ..Default::default()
+            },
         }
     }
 
+    /// Charge resident pages against a budget shared with other pools and
+    /// storage, and evict to keep it within its limit.
+    pub fn set_memory_budget(&mut self, budget: &Arc<MemoryBudget>) {
+        self.memory = Some(budget.charge(MemoryKind::BufferPools));
+        self.charge_memory();
+    }
+
     /// Get a page from the buffer pool if it exists and its read_point is ≥ the requested LSN.
-    pub fn get(&mut self, page_id: PageId, min_lsn: Lsn) -> Option<&Page> {
+    pub fn get(&mut self, page_id: PageId, min_lsn: Lsn) -> Option<PageRef> {
         if let Some(entry) = self.pages.get_mut(&page_id) {
             if entry.read_point >= min_lsn {
                 entry.access_count += 1;
-                return Some(&entry.page);
+                entry.referenced = true;
+                self.stats.hits += 1;
+                if entry.prefetched {
+                    entry.prefetched = false;
+                    self.stats.prefetch_hits += 1;
+                }
+                return Some(entry.page.clone());
             }
         }
+        self.stats.misses += 1;
         None
     }
 
     /// Insert or replace a page in the buffer pool.
-    pub fn insert(&mut self, page_id: PageId, read_point: Lsn, page: Page) {
-        // Simple eviction: if at capacity, remove the least accessed entry
-        if self.pages.len() >= self.capacity && !self.pages.contains_key(&page_id) {
-            if let Some((&evict_id, _)) = self
-                .pages
-                .iter()
-                .min_by_key(|(_, v)| v.access_count)
-            {
-                self.pages.remove(&evict_id);
+    ///
+    /// Evicts unpinned pages until the new page fits the budget. If every
+    /// resident page is pinned the pool temporarily runs over budget.
+    pub fn insert(&mut self, page_id: PageId, read_point: Lsn, page: impl Into<PageRef>) {
+        if let Some(entry) = self.pages.get_mut(&page_id) {
+            entry.page = page.into();
+            entry.read_point = read_point;
+            entry.access_count = 1;
+            entry.prefetched = false;
+            return;
+        }
+
+        self.insert_new(page_id, read_point, page.into(), false);
+    }
+
+    /// Insert a page fetched ahead of demand. A page that is already resident
+    /// is left alone, since it may be newer than the prefetched image.
+    /// Returns whether the page was inserted.
+    pub fn insert_prefetched(&mut self, page_id: PageId, read_point: Lsn, page: impl Into<PageRef>) -> bool {
+        if self.pages.contains_key(&page_id) {
+            return false;
+        }
+        self.insert_new(page_id, read_point, page.into(), true);
+        self.stats.prefetched += 1;
+        true
+    }
+
+    /// Whether a page is resident, regardless of its read point.
+    pub fn contains(&self, page_id: PageId) -> bool {
+        self.pages.contains_key(&page_id)
+    }
+
+    fn insert_new(&mut self, page_id: PageId, read_point: Lsn, page: PageRef, prefetched: bool) {
+        while self.resident_bytes() + self.page_size > self.budget_bytes || !self.fits_shared_budget() {
+            if !self.evict_one() {
+                break;
             }
         }
 
@@ -56,13 +160,115 @@ impl BufferPool {
                 page,
                 read_point,
                 access_count: 1,
+                referenced: false,
+                pin_count: 0,
+                prefetched,
             },
         );
+        self.clock.push_back(page_id);
+        self.charge_memory();
+    }
+
+    /// Pin a page so it cannot be evicted, returning it if it satisfies `min_lsn`.
+    /// Every successful `pin` must be paired with an `unpin`.
+    pub fn pin(&mut self, page_id: PageId, min_lsn: Lsn) -> Option<PageRef> {
+        let page = self.get(page_id, min_lsn)?;
+        if let Some(entry) = self.pages.get_mut(&page_id) {
+            entry.pin_count += 1;
+        }
+        Some(page)
+    }
+
+    /// Release one pin on a page. Unpinning an absent or unpinned page is a no-op.
+    pub fn unpin(&mut self, page_id: PageId) {
+        if let Some(entry) = self.pages.get_mut(&page_id) {
+            entry.pin_count = entry.pin_count.saturating_sub(1);
+        }
+    }
+
+    /// Whether a page is currently pinned.
+    pub fn is_pinned(&self, page_id: PageId) -> bool {
+        self.pages.get(&page_id).is_some_and(|e| e.pin_count > 0)
+    }
+
+    /// Apply a page's redo records to its resident image (write-through).
+    ///
+    /// The cached image must be exactly at `base_lsn` — the LSN just before the
+    /// records — so that no other write to the page can have slipped in between.
+    /// On success the entry advances to `new_lsn` and `true` is returned. If the
+    /// image is older, it is invalidated instead; an absent page is left absent.
+    pub fn apply_redo(
+        &mut self,
+        page_id: PageId,
+        base_lsn: Lsn,
+        new_lsn: Lsn,
+        records: &[RedoRecord],
+    ) -> bool {
+        let Some(entry) = self.pages.get_mut(&page_id) else {
+            return false;
+        };
+        if entry.read_point != base_lsn {
+            self.invalidate(page_id);
+            return false;
+        }
+
+        let page = Arc::make_mut(&mut entry.page);
+        for record in records.iter().filter(|r| r.page_id == page_id) {
+            let start = record.offset as usize;
+            page[start..start + record.data.len()].copy_from_slice(&record.data);
+        }
+        entry.read_point = new_lsn;
+        true
     }
 
     /// Invalidate a page (e.g., after writing to it).
     pub fn invalidate(&mut self, page_id: PageId) {
-        self.pages.remove(&page_id);
+        if self.pages.remove(&page_id).is_some() {
+            self.clock.retain(|&id| id != page_id);
+            self.charge_memory();
+        }
+    }
+
+    /// Drop every unpinned page, so the next reads go to storage. Counters
+    /// are kept; evictions don't count these. Returns the pages dropped.
+    pub fn clear(&mut self) -> usize {
+        let before = self.pages.len();
+        self.pages.retain(|_, e| e.pin_count > 0);
+        let pages = &self.pages;
+        self.clock.retain(|id| pages.contains_key(id));
+        self.charge_memory();
+        before - self.pages.len()
+    }
+
+    /// Resident page IDs, sorted ascending.
+    pub fn keys(&self) -> Vec<PageId> {
+        let mut keys: Vec<PageId> = self.pages.keys().copied().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Resident pages with their read points and access counts, sorted by page ID.
+    pub fn entries(&self) -> Vec<BufferPoolEntry> {
+        let mut entries: Vec<BufferPoolEntry> = self
+            .pages
+            .iter()
+            .map(|(&page_id, e)| BufferPoolEntry {
+                page_id,
+                read_point: e.read_point,
+                access_count: e.access_count,
+            })
+            .collect();
+        entries.sort_unstable_by_key(|e| e.page_id);
+        entries
+    }
+
+    /// Snapshot of hit/miss/eviction counters and residency.
+    pub fn stats(&self) -> BufferPoolStats {
+        BufferPoolStats {
+            resident_pages: self.pages.len(),
+            resident_bytes: self.resident_bytes(),
+            ..self.stats
+        }
     }
 
     pub fn len(&self) -> usize {
@@ -72,6 +278,74 @@ impl BufferPool {
     pub fn is_empty(&self) -> bool {
         self.pages.is_empty()
     }
+
+    fn resident_bytes(&self) -> usize {
+        self.pages.len() * self.page_size
+    }
+
+    fn fits_shared_budget(&self) -> bool {
+        self.memory.as_ref().is_none_or(|m| m.budget().fits(self.page_size as u64))
+    }
+
+    fn charge_memory(&mut self) {
+        let bytes = self.resident_bytes() as u64;
+        if let Some(memory) = &mut self.memory {
+            memory.set(bytes);
+        }
+    }
+
+    /// Advance the clock hand until one page is evicted.
+    /// Returns `false` if every resident page is pinned.
+    fn evict_one(&mut self) -> bool {
+        // Two full sweeps: the first may only clear reference bits.
+        let max_steps = self.clock.len() * 2;
+        for _ in 0..max_steps {
+            let Some(page_id) = self.clock.pop_front() else {
+                return false;
+            };
+            let entry = self
+                .pages
+                .get_mut(&page_id)
+                .expect("clock ring and page map out of sync");
+            if entry.pin_count > 0 {
+                self.clock.push_back(page_id);
+            } else if entry.referenced {
+                entry.referenced = false;
+                self.clock.push_back(page_id);
+            } else {
+                self.pages.remove(&page_id);
+                self.stats.evictions += 1;
+                self.charge_memory();
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl fmt::Display for BufferPoolStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.hits + self.misses;
+        let hit_pct = if total > 0 {
+            (self.hits as f64 / total as f64 * 100.0) as u64
+        } else {
+            0
+        };
+        write!(
+            f,
+            "hits={} misses={} ({}% hit) evictions={} resident={} pages / {} KB of {} KB \
+             prefetch={} used={}",
+            self.hits,
+            self.misses,
+            hit_pct,
+            self.evictions,
+            self.resident_pages,
+            self.resident_bytes / 1024,
+            self.budget_bytes / 1024,
+            self.prefetched,
+            self.prefetch_hits,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -88,7 +362,7 @@ mod tests {
     #[test]
     fn test_hit() {
         let mut pool = BufferPool::new(10);
-        let mut page = empty_page();
+        let mut page = empty_page(PAGE_SIZE);
         page[0] = 0xAB;
         pool.insert(1, 5, page);
 
@@ -100,7 +374,7 @@ mod tests {
     #[test]
     fn test_stale_read_point_misses() {
         let mut pool = BufferPool::new(10);
-        pool.insert(1, 5, empty_page());
+        pool.insert(1, 5, empty_page(PAGE_SIZE));
 
         // Requesting a newer read_point than cached → miss
         assert!(pool.get(1, 10).is_none());
@@ -112,7 +386,7 @@ mod tests {
     #[test]
     fn test_invalidate() {
         let mut pool = BufferPool::new(10);
-        pool.insert(1, 5, empty_page());
+        pool.insert(1, 5, empty_page(PAGE_SIZE));
         assert!(pool.get(1, 5).is_some());
 
         pool.invalidate(1);
@@ -122,17 +396,198 @@ mod tests {
     #[test]
     fn test_eviction() {
         let mut pool = BufferPool::new(2);
-        pool.insert(1, 1, empty_page());
-        pool.insert(2, 1, empty_page());
+        pool.insert(1, 1, empty_page(PAGE_SIZE));
+        pool.insert(2, 1, empty_page(PAGE_SIZE));
 
         // Access page 2 more to make page 1 the eviction target
         pool.get(2, 1);
         pool.get(2, 1);
 
-        pool.insert(3, 1, empty_page()); // should evict page 1
+        pool.insert(3, 1, empty_page(PAGE_SIZE)); // should evict page 1
 
         assert!(pool.get(1, 1).is_none());
         assert!(pool.get(2, 1).is_some());
         assert!(pool.get(3, 1).is_some());
     }
+
+    #[test]
+    fn test_clock_second_chance() {
+        let mut pool = BufferPool::new(2);
+        pool.insert(1, 1, empty_page(PAGE_SIZE));
+        pool.insert(2, 1, empty_page(PAGE_SIZE));
+        pool.get(1, 1); // page 1 referenced, page 2 not
+
+        pool.insert(3, 1, empty_page(PAGE_SIZE)); // hand skips page 1, evicts page 2
+        assert!(pool.get(2, 1).is_none());
+        assert!(pool.get(1, 1).is_some());
+
+        // Page 1's bit was cleared by the sweep above and then set again by the
+        // hit; page 3 has never been referenced, so it goes next.
+        pool.insert(4, 1, empty_page(PAGE_SIZE));
+        assert!(pool.get(3, 1).is_none());
+        assert!(pool.get(1, 1).is_some());
+    }
+
+    #[test]
+    fn test_pinned_page_not_evicted() {
+        let mut pool = BufferPool::new(1);
+        pool.insert(1, 1, empty_page(PAGE_SIZE));
+        assert!(pool.pin(1, 1).is_some());
+        assert!(pool.is_pinned(1));
+
+        // Everything is pinned: the pool runs over budget instead of evicting
+        pool.insert(2, 1, empty_page(PAGE_SIZE));
+        assert_eq!(pool.len(), 2);
+        assert!(pool.get(1, 1).is_some());
+
+        pool.unpin(1);
+        assert!(!pool.is_pinned(1));
+        pool.insert(3, 1, empty_page(PAGE_SIZE));
+        assert!(pool.stats().resident_bytes <= 2 * PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_byte_budget() {
+        let mut pool = BufferPool::with_budget_bytes(3 * PAGE_SIZE + 100);
+        for page_id in 1..=10 {
+            pool.insert(page_id, 1, empty_page(PAGE_SIZE));
+        }
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.stats().evictions, 7);
+    }
+
+    #[test]
+    fn test_apply_redo_write_through() {
+        let mut pool = BufferPool::new(10);
+        pool.insert(1, 4, empty_page(PAGE_SIZE));
+        let record = RedoRecord {
+            lsn: 5,
+            page_id: 1,
+            offset: 2,
+            data: vec![0xAB, 0xCD].into(),
+            prev_lsn: 0,
+            mtr_id: 1,
+            is_mtr_end: true,
+            timestamp: 0,
+        };
+
+        assert!(pool.apply_redo(1, 4, 5, std::slice::from_ref(&record)));
+        let page = pool.get(1, 5).unwrap();
+        assert_eq!(&page[2..4], &[0xAB, 0xCD]);
+
+        // Stale base: the image is dropped rather than patched
+        assert!(!pool.apply_redo(1, 3, 6, std::slice::from_ref(&record)));
+        assert!(pool.get(1, 1).is_none());
+        // Absent page stays absent
+        assert!(!pool.apply_redo(2, 5, 6, &[record]));
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_prefetch_stats() {
+        let mut pool = BufferPool::new(10);
+        pool.insert(1, 5, empty_page(PAGE_SIZE));
+        assert!(!pool.insert_prefetched(1, 3, empty_page(PAGE_SIZE))); // resident: kept
+        assert!(pool.insert_prefetched(2, 5, empty_page(PAGE_SIZE)));
+        assert!(pool.insert_prefetched(3, 5, empty_page(PAGE_SIZE)));
+
+        pool.get(2, 5);
+        pool.get(2, 5); // only the first read counts as a prefetch hit
+
+        let stats = pool.stats();
+        assert_eq!(stats.prefetched, 2);
+        assert_eq!(stats.prefetch_hits, 1);
+        assert!(pool.get(1, 5).is_some());
+    }
+
+    #[test]
+    fn test_keys_and_entries() {
+        let mut pool = BufferPool::new(10);
+        pool.insert(3, 7, empty_page(PAGE_SIZE));
+        pool.insert(1, 5, empty_page(PAGE_SIZE));
+        pool.get(1, 5);
+
+        assert_eq!(pool.keys(), vec![1, 3]);
+        assert_eq!(
+            pool.entries(),
+            vec![
+                BufferPoolEntry { page_id: 1, read_point: 5, access_count: 2 },
+                BufferPoolEntry { page_id: 3, read_point: 7, access_count: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut pool = BufferPool::new(4);
+        pool.insert(1, 5, empty_page(PAGE_SIZE));
+        pool.get(1, 5);
+        pool.get(1, 6);
+        pool.get(2, 1);
+
+        let stats = pool.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.evictions, 0);
+        assert_eq!(stats.resident_pages, 1);
+        assert_eq!(stats.resident_bytes, PAGE_SIZE);
+        assert_eq!(stats.budget_bytes, 4 * PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_pages_are_charged_their_page_size() {
+        let mut pool = BufferPool::with_page_size(2, 16384);
+        pool.insert(1, 1, empty_page(16384));
+        pool.insert(2, 1, empty_page(16384));
+        pool.insert(3, 1, empty_page(16384));
+
+        let stats = pool.stats();
+        assert_eq!((stats.resident_pages, stats.resident_bytes), (2, 2 * 16384));
+        assert_eq!((stats.budget_bytes, stats.evictions), (2 * 16384, 1));
+    }
+
+    #[test]
+    fn test_shared_memory_budget() {
+        let budget = Arc::new(MemoryBudget::new(Some(3 * PAGE_SIZE as u64)));
+        let mut a = BufferPool::new(10);
+        let mut b = BufferPool::new(10);
+        a.set_memory_budget(&budget);
+        b.set_memory_budget(&budget);
+
+        a.insert(1, 1, empty_page(PAGE_SIZE));
+        a.insert(2, 1, empty_page(PAGE_SIZE));
+        b.insert(1, 1, empty_page(PAGE_SIZE));
+        assert_eq!(budget.used(), 3 * PAGE_SIZE as u64);
+
+        // Pool A has used its share, so B evicts its own page to fit
+        b.insert(2, 1, empty_page(PAGE_SIZE));
+        b.insert(3, 1, empty_page(PAGE_SIZE));
+        assert_eq!((a.len(), b.len()), (2, 1));
+        assert_eq!(budget.usage().buffer_pools, 3 * PAGE_SIZE as u64);
+
+        drop(a);
+        assert_eq!(budget.used(), PAGE_SIZE as u64);
+    }
+
+    #[test]
+    fn test_clear_keeps_pinned_pages() {
+        let budget = Arc::new(MemoryBudget::new(None));
+        let mut pool = BufferPool::new(10);
+        pool.set_memory_budget(&budget);
+        for page_id in 1..=3 {
+            pool.insert(page_id, 1, empty_page(PAGE_SIZE));
+        }
+        pool.pin(2, 1).unwrap();
+
+        assert_eq!(pool.clear(), 2);
+        assert_eq!(pool.keys(), vec![2]);
+        assert_eq!(budget.used(), PAGE_SIZE as u64);
+        assert_eq!(pool.stats().evictions, 0);
+        assert!(pool.get(1, 1).is_none());
+
+        // Once unpinned it goes too
+        pool.unpin(2);
+        assert_eq!(pool.clear(), 1);
+        assert!(pool.is_empty());
+    }
 }
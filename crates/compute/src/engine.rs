@@ -1,10 +1,25 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
 
-use mini_aurora_common::{Lsn, Page, PageId, StorageApi, StorageError, PAGE_SIZE};
+use mini_aurora_common::{
+    check_page_range, AppendedRedo, Bytes, Capabilities, EngineObserver, Lsn, MemoryBudget, NoopObserver,
+    PageId, PageRef, RedoRecord, StorageApi, StorageError,
+};
 
-use crate::buffer_pool::BufferPool;
-use crate::transaction::MiniTransaction;
+use tracing::{debug, field, instrument, Span};
+
+use crate::allocator::{allocatable_pages, bit, check_freeable, first_free, is_allocated, ALLOCATOR_PAGE};
+use crate::batch::{OpenBatch, WriteBatchStats, WriteBatcher, WriteBatching};
+use crate::blob::{blob_len, blob_pages, split_blob, BLOB_HEADER_SIZE};
+use crate::buffer_pool::{BufferPool, BufferPoolEntry, BufferPoolStats};
+use crate::health::{Heartbeat, HeartbeatConfig, StorageHealth};
+use crate::lease::{ReadPoints, ReaderLease};
+use crate::session::Session;
+use crate::transaction::{MiniTransaction, MtrLimits};
 
 /// Compute engine — the "SQL layer" in Aurora's architecture.
 ///
@@ -13,7 +28,43 @@ use crate::transaction::MiniTransaction;
 /// Phase 2+ it goes over RPC.
 pub struct ComputeEngine {
     storage: Arc<dyn StorageApi>,
-    inner: Mutex<ComputeInner>,
+    /// Shared with background prefetch tasks.
+    inner: Arc<Mutex<ComputeInner>>,
+    write_policy: WritePolicy,
+    /// Pages to prefetch once sequential access is detected (0 = off).
+    read_ahead: usize,
+    observer: Arc<dyn EngineObserver>,
+    /// Set by `with_heartbeat`; storage calls fail fast while it reports
+    /// storage unhealthy.
+    heartbeat: Option<Heartbeat>,
+    /// Serializes read-modify-write of the allocation bitmap.
+    alloc_lock: Mutex<()>,
+    /// The node's and its sessions' read points. A std mutex, so sessions
+    /// can unpin theirs on drop.
+    read_points: Arc<std::sync::Mutex<ReadPoints>>,
+    /// Set by `with_reader_lease`.
+    reader_lease: Option<ReaderLease>,
+    /// Set by `with_write_batching`.
+    batcher: Option<Arc<WriteBatcher>>,
+    /// Bytes per page of storage's volume, as it said when the engine was
+    /// built.
+    page_size: usize,
+    /// Set by `with_mtr_limits`.
+    mtr_limits: MtrLimits,
+    /// Held shared by every append, and exclusively while an MTR goes out in
+    /// pieces, so none of this engine's CPLs lands between them.
+    append_gate: Arc<RwLock<()>>,
+}
+
+/// How `put` treats a written page that is resident in the buffer pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WritePolicy {
+    /// Drop the cached image; the next read materializes the page in storage.
+    #[default]
+    Invalidate,
+    /// Apply the redo to the cached image and advance it to the new VDL, so a
+    /// read-after-write is served locally.
+    WriteThrough,
 }
 
 struct ComputeInner {
@@ -21,33 +72,201 @@ struct ComputeInner {
     next_mtr_id: u64,
     /// Current read point (tracks VDL for consistent reads).
     read_point: Lsn,
+    /// Page of the previous `get`, for sequential-access detection.
+    last_get: Option<PageId>,
+    /// Highest page already requested by read-ahead in the current run.
+    prefetched_until: PageId,
+    /// What storage agreed to in the last `handshake`.
+    negotiated: Option<Capabilities>,
 }
 
 impl ComputeEngine {
     pub fn new(storage: Arc<dyn StorageApi>, buffer_pool_capacity: usize) -> Self {
+        let page_size = storage.page_size();
+        storage.attach_writer();
         Self {
             storage,
-            inner: Mutex::new(ComputeInner {
-                buffer_pool: BufferPool::new(buffer_pool_capacity),
+            inner: Arc::new(Mutex::new(ComputeInner {
+                buffer_pool: BufferPool::with_page_size(buffer_pool_capacity, page_size),
                 next_mtr_id: 1,
                 read_point: 0,
-            }),
+                last_get: None,
+                prefetched_until: 0,
+                negotiated: None,
+            })),
+            write_policy: WritePolicy::default(),
+            read_ahead: 0,
+            observer: Arc::new(NoopObserver),
+            heartbeat: None,
+            alloc_lock: Mutex::new(()),
+            read_points: Arc::default(),
+            reader_lease: None,
+            batcher: None,
+            page_size,
+            mtr_limits: MtrLimits::default(),
+            append_gate: Arc::default(),
         }
     }
 
+    /// Bytes per page of the volume this engine writes to.
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Prefetch the next `pages` pages in the background whenever `get` sees
+    /// two consecutive page IDs. Off (0) by default.
+    pub fn with_read_ahead(mut self, pages: usize) -> Self {
+        self.read_ahead = pages;
+        self
+    }
+
+    /// Set how writes update the buffer pool. Defaults to `Invalidate`.
+    pub fn with_write_policy(mut self, policy: WritePolicy) -> Self {
+        self.write_policy = policy;
+        self
+    }
+
+    /// Report every internal step to `observer`.
+    pub fn with_observer(mut self, observer: Arc<dyn EngineObserver>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Charge the buffer pool against `budget`, usually the one storage
+    /// charges its caches and indexes to, and evict while it is over its
+    /// limit.
+    pub fn with_memory_budget(self, budget: &Arc<MemoryBudget>) -> Self {
+        self.inner
+            .try_lock()
+            .expect("configured before first use")
+            .buffer_pool
+            .set_memory_budget(budget);
+        self
+    }
+
+    /// Ping storage in the background as `config` says, and fail storage
+    /// calls fast with `Unavailable` once too many beats are missed. Beats
+    /// go to the observer set so far, so call this after `with_observer`.
+    /// Must be called inside a Tokio runtime.
+    pub fn with_heartbeat(mut self, config: HeartbeatConfig) -> Self {
+        self.heartbeat = Some(Heartbeat::spawn(
+            Arc::clone(&self.storage),
+            config,
+            Arc::clone(&self.observer),
+        ));
+        self
+    }
+
+    /// Register the oldest read point of this node and its sessions with
+    /// storage as reader `name`, renewing the lease in the background, so
+    /// storage keeps the versions they may still read. Must be called
+    /// inside a Tokio runtime.
+    pub fn with_reader_lease(mut self, name: impl Into<String>, lease: Duration) -> Self {
+        self.reader_lease = Some(ReaderLease::spawn(
+            Arc::clone(&self.storage),
+            name.into(),
+            lease,
+            Arc::clone(&self.read_points),
+        ));
+        self
+    }
+
+    /// Send `put`s to storage in batches: the first put of a batch waits
+    /// up to `batching.window` for others, then all their MTRs go in one
+    /// `append_redo`. Each put still returns once its MTR is durable. Off
+    /// by default; `put_multi` and `commit` are never batched. Must be
+    /// called inside a Tokio runtime.
+    pub fn with_write_batching(mut self, batching: WriteBatching) -> Self {
+        self.batcher = Some(Arc::new(WriteBatcher::new(batching)));
+        self
+    }
+
+    /// Send an MTR whose redo goes over `limits` in several appends, as
+    /// `MiniTransaction::split` cuts it, instead of one of any size. Only
+    /// the last append carries a CPL, and this engine sends nothing else
+    /// until it lands, so a crash midway leaves none of the MTR durable.
+    /// Fails if other nodes write to the same storage: their CPLs could land
+    /// between the pieces.
+    pub fn with_mtr_limits(mut self, limits: MtrLimits) -> Result<Self, StorageError> {
+        if limits != MtrLimits::default() && self.storage.writers() > 1 {
+            return Err(StorageError::Other(format!(
+                "MTR limits need storage with one writer, and this one has {}",
+                self.storage.writers()
+            )));
+        }
+        self.mtr_limits = limits;
+        Ok(self)
+    }
+
+    pub fn mtr_limits(&self) -> MtrLimits {
+        self.mtr_limits
+    }
+
+    /// Batches sent so far; `None` without `with_write_batching`.
+    pub fn write_batch_stats(&self) -> Option<WriteBatchStats> {
+        self.batcher.as_ref().map(|batcher| batcher.stats())
+    }
+
+    pub fn write_policy(&self) -> WritePolicy {
+        self.write_policy
+    }
+
+    /// Storage's health as seen by heartbeats; `None` without `with_heartbeat`.
+    pub fn storage_health(&self) -> Option<StorageHealth> {
+        self.heartbeat.as_ref().map(Heartbeat::health)
+    }
+
+    /// Storage to call, unless heartbeats have marked it unhealthy.
+    pub(crate) fn storage(&self) -> Result<&dyn StorageApi, StorageError> {
+        if let Some(heartbeat) = &self.heartbeat {
+            heartbeat.check()?;
+        }
+        Ok(self.storage.as_ref())
+    }
+
+    /// Keep a copy of an MTR's records if the buffer pool will need them.
+    fn retain_for_write_through(&self, pieces: &[Vec<RedoRecord>]) -> Option<Vec<RedoRecord>> {
+        (self.write_policy == WritePolicy::WriteThrough).then(|| pieces.concat())
+    }
+
+    /// Append an MTR split by `MiniTransaction::split`, returning its LSNs
+    /// and the VDL once its last piece lands. The VDL only moves with that
+    /// piece's CPL.
+    async fn append_pieces(
+        &self,
+        storage: &dyn StorageApi,
+        mut pieces: Vec<Vec<RedoRecord>>,
+    ) -> Result<AppendedRedo, StorageError> {
+        if pieces.len() == 1 {
+            let _gate = self.append_gate.read().await;
+            return storage.append_redo_lsns(pieces.pop().unwrap()).await;
+        }
+        if storage.writers() > 1 {
+            return Err(StorageError::Other("a split MTR needs storage with one writer".into()));
+        }
+        let _gate = self.append_gate.write().await;
+        debug!(pieces = pieces.len(), "MTR split");
+        // Nothing else goes out in between, so the pieces' LSNs run on
+        let mut appended: Option<AppendedRedo> = None;
+        for piece in pieces {
+            let piece = storage.append_redo_lsns(piece).await?;
+            let first = appended.map_or(*piece.lsns.start(), |mtr| *mtr.lsns.start());
+            appended = Some(AppendedRedo { lsns: first..=*piece.lsns.end(), vdl: piece.vdl });
+        }
+        Ok(appended.unwrap())
+    }
+
     /// Write bytes to a page at a given offset. This is a single-record MTR.
+    #[instrument(level = "debug", skip(self, data), fields(mtr_id = field::Empty, vdl = field::Empty))]
     pub async fn put(
         &self,
         page_id: PageId,
         offset: u16,
-        data: Vec<u8>,
+        data: impl Into<Bytes>,
     ) -> Result<Lsn, StorageError> {
-        if offset as usize + data.len() > PAGE_SIZE {
-            return Err(StorageError::PageOverflow {
-                offset,
-                len: data.len(),
-            });
-        }
+        let data = data.into();
+        check_page_range(offset, data.len(), self.page_size)?;
+        let storage = self.storage()?;
 
         let mtr_id = {
             let mut inner = self.inner.lock().await;
@@ -55,120 +274,595 @@ impl ComputeEngine {
             inner.next_mtr_id += 1;
             id
         };
+        Span::current().record("mtr_id", mtr_id);
 
-        let mut mtr = MiniTransaction::new(mtr_id);
+        let mut mtr = MiniTransaction::new(mtr_id).with_limits(self.mtr_limits);
         mtr.write(page_id, offset, data);
-        let records = mtr.finish().unwrap();
+        let mut pieces = mtr.split()?;
+        self.observer.mtr_created(mtr_id, pieces.iter().map(Vec::len).sum());
+        if let (Some(batcher), 1) = (&self.batcher, pieces.len()) {
+            let vdl = self.put_batched(batcher, pieces.pop().unwrap()).await?;
+            Span::current().record("vdl", vdl);
+            return Ok(vdl);
+        }
+        let written = self.retain_for_write_through(&pieces);
 
-        let vdl = self.storage.append_redo(records).await?;
+        let appended = self.append_pieces(storage, pieces).await?;
+        let vdl = appended.vdl;
+        Span::current().record("vdl", vdl);
 
-        // Update read point and invalidate cache for this page
+        // Invalidate (or write through) cache for this page and update read point
         {
             let mut inner = self.inner.lock().await;
-            inner.read_point = vdl;
-            inner.buffer_pool.invalidate(page_id);
+            inner.update_written_pages(&[page_id], written.as_deref(), &appended, self.observer.as_ref());
+            self.advance_read_point(&mut inner, vdl);
         }
 
         Ok(vdl)
     }
 
+    /// Add a put's records to the open batch, starting its flush if this
+    /// put opened it, and wait for the batch to be sent.
+    async fn put_batched(
+        &self,
+        batcher: &Arc<WriteBatcher>,
+        records: Vec<RedoRecord>,
+    ) -> Result<Lsn, StorageError> {
+        let (result, opened) = batcher.join(records);
+        if let Some(full) = opened {
+            // A task of its own, so the batch is sent even if this put is dropped
+            let flush = BatchFlush {
+                batcher: Arc::clone(batcher),
+                storage: Arc::clone(&self.storage),
+                inner: Arc::clone(&self.inner),
+                read_points: Arc::clone(&self.read_points),
+                observer: Arc::clone(&self.observer),
+                write_policy: self.write_policy,
+                append_gate: Arc::clone(&self.append_gate),
+            };
+            tokio::spawn(async move {
+                let _ = tokio::time::timeout(flush.batcher.window(), full.notified()).await;
+                if let Some(batch) = flush.batcher.take() {
+                    flush.send(batch).await;
+                }
+            });
+        }
+        result.await.unwrap_or_else(|_| Err(StorageError::Other("write batch was dropped".into())))
+    }
+
     /// Execute a multi-record mini-transaction.
     /// `writes` is a list of (page_id, offset, data) tuples.
+    #[instrument(level = "debug", skip_all, fields(writes = writes.len()))]
     pub async fn put_multi(
         &self,
         writes: Vec<(PageId, u16, Vec<u8>)>,
     ) -> Result<Lsn, StorageError> {
         for &(_, offset, ref data) in &writes {
-            if offset as usize + data.len() > PAGE_SIZE {
-                return Err(StorageError::PageOverflow {
-                    offset,
-                    len: data.len(),
-                });
-            }
+            check_page_range(offset, data.len(), self.page_size)?;
         }
+        self.storage()?;
 
-        let mtr_id = {
-            let mut inner = self.inner.lock().await;
-            let id = inner.next_mtr_id;
-            inner.next_mtr_id += 1;
-            id
-        };
-
-        let mut mtr = MiniTransaction::new(mtr_id);
-        for (page_id, offset, data) in &writes {
-            mtr.write(*page_id, *offset, data.clone());
+        let mut mtr = self.begin().await;
+        for (page_id, offset, data) in writes {
+            mtr.write(page_id, offset, data);
         }
+        self.commit(mtr).await
+    }
 
-        let records = match mtr.finish() {
-            Some(r) => r,
-            None => return Ok(self.inner.lock().await.read_point),
-        };
+    /// Start a mini-transaction with the next MTR id, under this engine's
+    /// MTR limits. Stage writes (and savepoints) on it, then send it with
+    /// `commit`.
+    pub async fn begin(&self) -> MiniTransaction {
+        let mut inner = self.inner.lock().await;
+        let mtr_id = inner.next_mtr_id;
+        inner.next_mtr_id += 1;
+        MiniTransaction::new(mtr_id).with_limits(self.mtr_limits)
+    }
 
-        let vdl = self.storage.append_redo(records).await?;
+    /// Send a mini-transaction to storage: all of its writes become durable,
+    /// or none do. An empty MTR sends nothing and returns the read point.
+    /// One over its limits goes in several appends; see `with_mtr_limits`.
+    #[instrument(level = "debug", skip_all, fields(mtr_id = mtr.mtr_id(), writes = mtr.len(), vdl = field::Empty))]
+    pub async fn commit(&self, mtr: MiniTransaction) -> Result<Lsn, StorageError> {
+        let mtr_id = mtr.mtr_id();
+        mtr.check_page_range(self.page_size)?;
+        let pieces = mtr.split()?;
+        if pieces.is_empty() {
+            return Ok(self.inner.lock().await.read_point);
+        }
+        let records = || pieces.iter().flatten();
+        let storage = self.storage()?;
+        self.observer.mtr_created(mtr_id, records().count());
+        let page_ids: Vec<PageId> = records().map(|r| r.page_id).collect();
+        let written = self.retain_for_write_through(&pieces);
 
-        // Update read point and invalidate caches
+        let appended = self.append_pieces(storage, pieces).await?;
+        let vdl = appended.vdl;
+        Span::current().record("vdl", vdl);
+
+        // Invalidate (or write through) caches and update read point
         {
             let mut inner = self.inner.lock().await;
-            inner.read_point = vdl;
-            for (page_id, _, _) in &writes {
-                inner.buffer_pool.invalidate(*page_id);
-            }
+            inner.update_written_pages(&page_ids, written.as_deref(), &appended, self.observer.as_ref());
+            self.advance_read_point(&mut inner, vdl);
         }
 
         Ok(vdl)
     }
 
+    fn advance_read_point(&self, inner: &mut ComputeInner, read_point: Lsn) {
+        inner.advance_read_point(read_point, &self.read_points, self.observer.as_ref());
+    }
+
     /// Read a page at the current read point.
-    pub async fn get(&self, page_id: PageId) -> Result<Page, StorageError> {
-        let read_point = self.inner.lock().await.read_point;
-        self.get_at(page_id, read_point).await
+    #[instrument(level = "debug", skip(self), fields(read_point = field::Empty))]
+    pub async fn get(&self, page_id: PageId) -> Result<PageRef, StorageError> {
+        // Detached: any read-ahead finishes in the background
+        self.get_reading_ahead(page_id).await.0
+    }
+
+    /// `get`, plus the handle of the read-ahead it started, if any.
+    async fn get_reading_ahead(
+        &self,
+        page_id: PageId,
+    ) -> (Result<PageRef, StorageError>, Option<JoinHandle<()>>) {
+        let (read_point, ahead) = {
+            let mut inner = self.inner.lock().await;
+            (inner.read_point, self.detect_sequential(&mut inner, page_id))
+        };
+        Span::current().record("read_point", read_point);
+        let read_ahead = ahead.map(|pages| self.prefetch(pages));
+        (self.get_at(page_id, read_point).await, read_ahead)
+    }
+
+    /// Track consecutive `get`s and return the pages to read ahead, if any.
+    fn detect_sequential(&self, inner: &mut ComputeInner, page_id: PageId) -> Option<Range<PageId>> {
+        let sequential = inner.last_get.is_some_and(|last| last + 1 == page_id);
+        inner.last_get = Some(page_id);
+        if !sequential {
+            inner.prefetched_until = page_id;
+            return None;
+        }
+        if self.read_ahead == 0 {
+            return None;
+        }
+
+        let end = page_id + 1 + self.read_ahead as PageId;
+        let start = (page_id + 1).max(inner.prefetched_until + 1);
+        if start >= end {
+            return None;
+        }
+        inner.prefetched_until = end - 1;
+        Some(start..end)
+    }
+
+    /// Warm the buffer pool with `pages` at the current read point.
+    ///
+    /// Pages already resident are skipped and the rest are fetched with one
+    /// `get_pages` call on a background task; pages storage can't produce are
+    /// ignored, and nothing is fetched while storage is marked unhealthy.
+    /// Await the returned handle to wait for the prefetch to land.
+    pub fn prefetch(&self, pages: Range<PageId>) -> JoinHandle<()> {
+        // An empty range makes the task a no-op
+        let pages = if self.storage().is_ok() { pages } else { pages.start..pages.start };
+        let storage = Arc::clone(&self.storage);
+        let inner = Arc::clone(&self.inner);
+        let observer = Arc::clone(&self.observer);
+        debug!(start = pages.start, end = pages.end, "prefetch");
+        tokio::spawn(async move {
+            let (read_point, missing) = {
+                let inner = inner.lock().await;
+                let missing: Vec<PageId> =
+                    pages.filter(|&p| !inner.buffer_pool.contains(p)).collect();
+                (inner.read_point, missing)
+            };
+            if missing.is_empty() {
+                return;
+            }
+
+            let results = storage.get_pages(&missing, read_point).await;
+            let mut inner = inner.lock().await;
+            for (page_id, result) in missing.into_iter().zip(results) {
+                if let Ok(page) = result {
+                    if inner.buffer_pool.insert_prefetched(page_id, read_point, page) {
+                        observer.buffer_pool_inserted(page_id, read_point);
+                    }
+                }
+            }
+        })
     }
 
     /// Read a page at a specific LSN.
-    pub async fn get_at(&self, page_id: PageId, lsn: Lsn) -> Result<Page, StorageError> {
+    ///
+    /// Reads older than the current read point go straight to storage: the
+    /// buffer pool only holds images as of the read point, which may include
+    /// later writes, and historical versions are not worth caching.
+    #[instrument(level = "debug", skip(self), fields(hit = field::Empty))]
+    pub async fn get_at(&self, page_id: PageId, lsn: Lsn) -> Result<PageRef, StorageError> {
         // Check buffer pool first
         {
             let mut inner = self.inner.lock().await;
-            if let Some(page) = inner.buffer_pool.get(page_id, lsn) {
-                return Ok(*page);
+            if lsn < inner.read_point {
+                drop(inner);
+                return self.storage()?.get_page(page_id, lsn).await;
+            }
+            let cached = inner.buffer_pool.get(page_id, lsn);
+            self.observer.buffer_pool_lookup(page_id, lsn, cached.is_some());
+            Span::current().record("hit", cached.is_some());
+            if let Some(page) = cached {
+                return Ok(page);
             }
         }
 
         // Fetch from storage
-        let page = self.storage.get_page(page_id, lsn).await?;
+        let page = self.storage()?.get_page(page_id, lsn).await?;
 
         // Cache in buffer pool
         {
             let mut inner = self.inner.lock().await;
-            inner.buffer_pool.insert(page_id, lsn, page);
+            inner.buffer_pool.insert(page_id, lsn, page.clone());
+            self.observer.buffer_pool_inserted(page_id, lsn);
         }
 
         Ok(page)
     }
 
+    /// Read several pages at the current read point in one round trip: the
+    /// buffer pool is checked for all of them under one lock, the misses go
+    /// to storage in a single `get_pages` call, and what comes back is
+    /// cached under one more. Pages are returned in request order. If
+    /// storage fails any page, the pages that did arrive are still cached
+    /// and the first error is returned.
+    #[instrument(level = "debug", skip_all, fields(pages = page_ids.len(), misses = field::Empty))]
+    pub async fn get_multi(&self, page_ids: &[PageId]) -> Result<Vec<PageRef>, StorageError> {
+        let (read_point, mut pages, missing) = {
+            let mut inner = self.inner.lock().await;
+            let read_point = inner.read_point;
+            let mut missing = Vec::new();
+            let mut seen = HashSet::new();
+            let mut pages = Vec::with_capacity(page_ids.len());
+            for &page_id in page_ids {
+                let cached = inner.buffer_pool.get(page_id, read_point);
+                self.observer.buffer_pool_lookup(page_id, read_point, cached.is_some());
+                if cached.is_none() && seen.insert(page_id) {
+                    missing.push(page_id);
+                }
+                pages.push(cached);
+            }
+            (read_point, pages, missing)
+        };
+        Span::current().record("misses", missing.len());
+        if missing.is_empty() {
+            return Ok(pages.into_iter().flatten().collect());
+        }
+
+        let results = self.storage()?.get_pages(&missing, read_point).await;
+        let mut fetched = HashMap::with_capacity(missing.len());
+        let mut first_error = None;
+        {
+            let mut inner = self.inner.lock().await;
+            for (page_id, result) in missing.into_iter().zip(results) {
+                match result {
+                    Ok(page) => {
+                        inner.buffer_pool.insert(page_id, read_point, page.clone());
+                        self.observer.buffer_pool_inserted(page_id, read_point);
+                        fetched.insert(page_id, page);
+                    }
+                    Err(e) => {
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+        }
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+
+        for (page, page_id) in pages.iter_mut().zip(page_ids) {
+            if page.is_none() {
+                *page = fetched.get(page_id).cloned();
+            }
+        }
+        Ok(pages.into_iter().map(|page| page.expect("every miss was fetched")).collect())
+    }
+
+    /// Store a value of any size on consecutive pages from `start_page`, all
+    /// in one MTR, so a reader never sees part of it. The caller owns the
+    /// `blob_pages(start_page, data.len(), page_size)` range; see `crate::blob`.
+    #[instrument(level = "debug", skip(self, data), fields(len = data.len()))]
+    pub async fn put_blob(&self, start_page: PageId, data: &[u8]) -> Result<Lsn, StorageError> {
+        let writes = split_blob(start_page, data, self.page_size)?;
+        self.storage()?;
+
+        let mut mtr = self.begin().await;
+        for (page_id, chunk) in writes {
+            mtr.write(page_id, 0, chunk);
+        }
+        self.commit(mtr).await
+    }
+
+    /// Read back a value stored with `put_blob`: the first page for the
+    /// length, then the remaining pages in one `get_multi`.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_blob(&self, start_page: PageId) -> Result<Vec<u8>, StorageError> {
+        let first = self.get(start_page).await?;
+        let len = blob_len(&first[..]);
+        let head = len.min(self.page_size - BLOB_HEADER_SIZE);
+        let mut data = Vec::with_capacity(len);
+        data.extend_from_slice(&first[BLOB_HEADER_SIZE..BLOB_HEADER_SIZE + head]);
+
+        let rest: Vec<PageId> = blob_pages(start_page, len, self.page_size).skip(1).collect();
+        for page in self.get_multi(&rest).await? {
+            let take = (len - data.len()).min(self.page_size);
+            data.extend_from_slice(&page[..take]);
+        }
+        Ok(data)
+    }
+
+    /// Hand out the lowest free page and mark it allocated on the metadata
+    /// page in its own MTR. Allocations are serialized within this node;
+    /// like every write, they assume this node is the only writer.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn allocate_page(&self) -> Result<PageId, StorageError> {
+        let _guard = self.alloc_lock.lock().await;
+        let bitmap = self.get(ALLOCATOR_PAGE).await?;
+        let page_id = first_free(&bitmap[..]).ok_or_else(|| {
+            let usable = allocatable_pages(self.page_size) - 1;
+            StorageError::Other(format!("no free pages: all {usable} allocatable pages are in use"))
+        })?;
+        self.write_allocation_bit(&bitmap[..], page_id, true).await?;
+        debug!(page_id, "page allocated");
+        Ok(page_id)
+    }
+
+    /// Return a page from `allocate_page` to the free pool. Its contents are
+    /// left as they are; the next owner overwrites what it needs.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn free_page(&self, page_id: PageId) -> Result<Lsn, StorageError> {
+        check_freeable(page_id, self.page_size)?;
+        let _guard = self.alloc_lock.lock().await;
+        let bitmap = self.get(ALLOCATOR_PAGE).await?;
+        if !is_allocated(&bitmap[..], page_id) {
+            return Err(StorageError::Other(format!("page {page_id} is not allocated")));
+        }
+        self.write_allocation_bit(&bitmap[..], page_id, false).await
+    }
+
+    async fn write_allocation_bit(
+        &self,
+        bitmap: &[u8],
+        page_id: PageId,
+        allocated: bool,
+    ) -> Result<Lsn, StorageError> {
+        let (byte, mask) = bit(page_id);
+        let old = bitmap[byte as usize];
+        let new = if allocated { old | mask } else { old & !mask };
+        self.put(ALLOCATOR_PAGE, byte, vec![new]).await
+    }
+
+    /// Read `len` bytes at `offset` from a page at the current read point.
+    ///
+    /// Served from the buffer pool; a miss fetches the full page and caches
+    /// it, as `get` does, so the next read of any range of it is a hit.
+    pub async fn get_bytes(
+        &self,
+        page_id: PageId,
+        offset: u16,
+        len: usize,
+    ) -> Result<Vec<u8>, StorageError> {
+        check_page_range(offset, len, self.page_size)?;
+        let read_point = self.inner.lock().await.read_point;
+        let page = self.get_at(page_id, read_point).await?;
+        let start = offset as usize;
+        Ok(page[start..start + len].to_vec())
+    }
+
+    /// Buffer pool hit/miss/eviction counters and residency.
+    pub async fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.inner.lock().await.buffer_pool.stats()
+    }
+
+    /// Drop every page from the buffer pool, so the next reads go to
+    /// storage. Returns the pages dropped.
+    pub async fn clear_buffer_pool(&self) -> usize {
+        let dropped = self.inner.lock().await.buffer_pool.clear();
+        debug!(dropped, "buffer pool cleared");
+        dropped
+    }
+
+    /// Pages currently resident in the buffer pool.
+    pub async fn buffer_pool_entries(&self) -> Vec<BufferPoolEntry> {
+        self.inner.lock().await.buffer_pool.entries()
+    }
+
+    /// ID the next mini-transaction will get.
+    pub async fn next_mtr_id(&self) -> u64 {
+        self.inner.lock().await.next_mtr_id
+    }
+
     /// Get the current read point (VDL as seen by this compute node).
     pub async fn read_point(&self) -> Lsn {
         self.inner.lock().await.read_point
     }
 
+    /// Start a logical client of this node at its current read point. The
+    /// session moves its own read point and shares the buffer pool.
+    pub async fn session(&self) -> Session<'_> {
+        Session::new(self, self.read_point().await)
+    }
+
     /// Refresh the read point from storage's durability state.
+    #[instrument(level = "debug", skip(self))]
     pub async fn refresh_read_point(&self) -> Result<Lsn, StorageError> {
-        let state = self.storage.get_durability_state().await?;
+        let state = self.storage()?.get_durability_state().await?;
         let mut inner = self.inner.lock().await;
-        inner.read_point = state.vdl;
+        self.advance_read_point(&mut inner, state.vdl);
         Ok(state.vdl)
     }
+
+    /// What this compute node can use: it batches misses through
+    /// `get_pages`. It reads whole pages, to cache them, so it makes no
+    /// partial reads.
+    pub fn supported_capabilities() -> Capabilities {
+        Capabilities {
+            batch_reads: true,
+            ..Capabilities::baseline()
+        }
+    }
+
+    /// Agree with storage on versions and features before relying on them.
+    /// Fails with `StorageError::Incompatible` if the versions differ.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn handshake(&self) -> Result<Capabilities, StorageError> {
+        let agreed = self.storage()?.handshake(&Self::supported_capabilities()).await?;
+        debug!(%agreed, "storage handshake");
+        self.inner.lock().await.negotiated = Some(agreed.clone());
+        Ok(agreed)
+    }
+
+    /// What the last `handshake` agreed on, if there was one.
+    pub async fn capabilities(&self) -> Option<Capabilities> {
+        self.inner.lock().await.negotiated.clone()
+    }
+
+    /// Lowest read point of this node and its open sessions: the oldest LSN
+    /// it may still ask storage for.
+    pub fn oldest_read_point(&self) -> Lsn {
+        self.read_points.lock().unwrap().oldest()
+    }
+
+    /// Register `oldest_read_point` now instead of at the next renewal, e.g.
+    /// after pinning a session to an older LSN. Returns the LSN registered,
+    /// or `None` without `with_reader_lease`.
+    pub async fn renew_reader_lease(&self) -> Result<Option<Lsn>, StorageError> {
+        let Some(lease) = &self.reader_lease else {
+            return Ok(None);
+        };
+        let read_point = self.oldest_read_point();
+        self.storage()?.register_reader(&lease.name, read_point, lease.lease).await?;
+        Ok(Some(read_point))
+    }
+
+    /// Stop renewing and drop the registration, e.g. before a clean
+    /// shutdown. A node that just goes away lets its lease run out instead.
+    pub async fn release_reader_lease(&mut self) -> Result<(), StorageError> {
+        match self.reader_lease.take() {
+            Some(lease) => self.storage()?.deregister_reader(&lease.name).await,
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn read_points(&self) -> &std::sync::Mutex<ReadPoints> {
+        &self.read_points
+    }
+}
+
+impl Drop for ComputeEngine {
+    fn drop(&mut self) {
+        self.storage.detach_writer();
+    }
+}
+
+/// What sending a write batch needs from its engine.
+struct BatchFlush {
+    batcher: Arc<WriteBatcher>,
+    storage: Arc<dyn StorageApi>,
+    inner: Arc<Mutex<ComputeInner>>,
+    read_points: Arc<std::sync::Mutex<ReadPoints>>,
+    observer: Arc<dyn EngineObserver>,
+    write_policy: WritePolicy,
+    append_gate: Arc<RwLock<()>>,
+}
+
+impl BatchFlush {
+    /// Send `batch` as one `append_redo`, as `commit` sends one MTR, and
+    /// reply to its puts.
+    async fn send(&self, mut batch: OpenBatch) {
+        let records = std::mem::take(&mut batch.records);
+        self.observer.write_batch_flushed(batch.mtrs, records.len());
+        debug!(mtrs = batch.mtrs, records = records.len(), "write batch sent");
+        let page_ids: Vec<PageId> = records.iter().map(|r| r.page_id).collect();
+        let written = (self.write_policy == WritePolicy::WriteThrough).then(|| records.clone());
+
+        let result = {
+            let _gate = self.append_gate.read().await;
+            self.storage.append_redo_lsns(records).await
+        };
+        if let Ok(appended) = &result {
+            let mut inner = self.inner.lock().await;
+            inner.update_written_pages(&page_ids, written.as_deref(), appended, self.observer.as_ref());
+            inner.advance_read_point(appended.vdl, &self.read_points, self.observer.as_ref());
+        }
+        batch.reply(&result.map(|appended| appended.vdl));
+    }
+}
+
+impl ComputeInner {
+    fn advance_read_point(
+        &mut self,
+        read_point: Lsn,
+        read_points: &std::sync::Mutex<ReadPoints>,
+        observer: &dyn EngineObserver,
+    ) {
+        let old = std::mem::replace(&mut self.read_point, read_point);
+        read_points.lock().unwrap().set_node(read_point);
+        debug!(old, new = read_point, "read point advanced");
+        observer.read_point_updated(old, read_point);
+    }
+
+    /// Bring resident copies of written pages up to date after an append
+    /// became durable. With `written` records the pages are patched in place,
+    /// to the append's last LSN; without them (or if a patch is unsafe) they
+    /// are invalidated.
+    fn update_written_pages(
+        &mut self,
+        page_ids: &[PageId],
+        written: Option<&[RedoRecord]>,
+        appended: &AppendedRedo,
+        observer: &dyn EngineObserver,
+    ) {
+        let mut page_ids = page_ids.to_vec();
+        page_ids.sort_unstable();
+        page_ids.dedup();
+        match written {
+            Some(records) => {
+                // Only an image just before the append's first LSN can be
+                // patched: any later one may hold another append's writes
+                let base_lsn = appended.lsns.start().saturating_sub(1);
+                let new_lsn = *appended.lsns.end();
+                for page_id in page_ids {
+                    if self.buffer_pool.apply_redo(page_id, base_lsn, new_lsn, records) {
+                        observer.buffer_pool_written_through(page_id, new_lsn);
+                    } else {
+                        observer.buffer_pool_invalidated(page_id);
+                    }
+                }
+            }
+            None => {
+                for page_id in page_ids {
+                    self.buffer_pool.invalidate(page_id);
+                    observer.buffer_pool_invalidated(page_id);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mini_aurora_common::{DurabilityState, RedoRecord};
+    use mini_aurora_common::{empty_page, DurabilityState, RedoRecord, PAGE_SIZE, STORAGE_API_VERSION};
     use std::sync::Mutex as StdMutex;
+    use std::time::Duration;
 
     /// Mock storage for compute engine tests.
     struct MockStorage {
         inner: StdMutex<MockInner>,
+        /// Ignore heartbeats while set; other calls still succeed.
+        silent: std::sync::atomic::AtomicBool,
+        /// `get_pages` calls so far.
+        batches: std::sync::atomic::AtomicUsize,
+        page_size: usize,
     }
 
     struct MockInner {
@@ -185,14 +879,22 @@ mod tests {
                     next_lsn: 1,
                     vdl: 0,
                 }),
+                silent: Default::default(),
+                batches: Default::default(),
+                page_size: PAGE_SIZE,
             }
         }
+
+        fn with_page_size(page_size: usize) -> Self {
+            Self { page_size, ..Self::new() }
+        }
     }
 
     #[async_trait::async_trait]
     impl StorageApi for MockStorage {
-        async fn append_redo(&self, mut records: Vec<RedoRecord>) -> Result<Lsn, StorageError> {
+        async fn append_redo_lsns(&self, mut records: Vec<RedoRecord>) -> Result<AppendedRedo, StorageError> {
             let mut inner = self.inner.lock().unwrap();
+            let first_lsn = inner.next_lsn;
             for record in &mut records {
                 record.lsn = inner.next_lsn;
                 inner.next_lsn += 1;
@@ -201,12 +903,12 @@ mod tests {
                 inner.vdl = cpl.lsn;
             }
             inner.records.extend(records);
-            Ok(inner.vdl)
+            Ok(AppendedRedo { lsns: first_lsn..=inner.next_lsn - 1, vdl: inner.vdl })
         }
 
-        async fn get_page(&self, page_id: PageId, read_point: Lsn) -> Result<Page, StorageError> {
+        async fn get_page(&self, page_id: PageId, read_point: Lsn) -> Result<PageRef, StorageError> {
             let inner = self.inner.lock().unwrap();
-            let mut page = [0u8; PAGE_SIZE];
+            let mut page = empty_page(self.page_size);
             for record in &inner.records {
                 if record.page_id == page_id && record.lsn <= read_point {
                     let start = record.offset as usize;
@@ -214,7 +916,16 @@ mod tests {
                     page[start..end].copy_from_slice(&record.data);
                 }
             }
-            Ok(page)
+            Ok(page.into())
+        }
+
+        async fn get_pages(&self, page_ids: &[PageId], read_point: Lsn) -> Vec<Result<PageRef, StorageError>> {
+            self.batches.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let mut pages = Vec::new();
+            for &page_id in page_ids {
+                pages.push(self.get_page(page_id, read_point).await);
+            }
+            pages
         }
 
         async fn get_durability_state(&self) -> Result<DurabilityState, StorageError> {
@@ -224,6 +935,24 @@ mod tests {
                 vdl: inner.vdl,
             })
         }
+
+        async fn heartbeat(&self) -> Result<DurabilityState, StorageError> {
+            if self.silent.load(std::sync::atomic::Ordering::SeqCst) {
+                std::future::pending::<()>().await;
+            }
+            self.get_durability_state().await
+        }
+
+        fn page_size(&self) -> usize {
+            self.page_size
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                batch_reads: true,
+                ..Capabilities::baseline()
+            }
+        }
     }
 
     #[tokio::test]
@@ -272,6 +1001,358 @@ mod tests {
         assert_eq!(engine.read_point().await, 2);
     }
 
+    #[tokio::test]
+    async fn test_get_bytes() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage, 100);
+
+        engine.put(1, 10, b"partial".to_vec()).await.unwrap();
+        let bytes = engine.get_bytes(1, 10, 4).await.unwrap();
+        assert_eq!(bytes, b"part");
+
+        // The miss cached the full page, so other ranges of it are hits
+        assert!(engine.inner.lock().await.buffer_pool.contains(1));
+        let before = engine.buffer_pool_stats().await;
+        let bytes = engine.get_bytes(1, 14, 3).await.unwrap();
+        assert_eq!(bytes, b"ial");
+        assert_eq!(engine.buffer_pool_stats().await.hits, before.hits + 1);
+
+        let result = engine.get_bytes(1, PAGE_SIZE as u16 - 1, 2).await;
+        assert!(matches!(result, Err(StorageError::PageOverflow { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_get_at_older_lsn_bypasses_buffer_pool() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage, 100);
+
+        engine.put(1, 0, vec![0x01]).await.unwrap();
+        engine.put(1, 0, vec![0x02]).await.unwrap();
+        assert_eq!(engine.get(1).await.unwrap()[0], 0x02);
+
+        let old = engine.get_at(1, 1).await.unwrap();
+        assert_eq!(old[0], 0x01);
+        assert_eq!(engine.get(1).await.unwrap()[0], 0x02);
+
+        let stats = engine.buffer_pool_stats().await;
+        assert_eq!(stats.resident_pages, 1);
+    }
+
+    #[tokio::test]
+    async fn test_buffer_pool_stats() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage, 100);
+
+        engine.put(1, 0, vec![0x01]).await.unwrap();
+        engine.get(1).await.unwrap(); // miss, fetched from storage
+        engine.get(1).await.unwrap(); // hit
+
+        let stats = engine.buffer_pool_stats().await;
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.resident_pages, 1);
+    }
+
+    #[tokio::test]
+    async fn test_cleared_buffer_pool_reads_from_storage() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage, 100);
+
+        engine.put(1, 0, vec![0x01]).await.unwrap();
+        engine.get(1).await.unwrap();
+        assert_eq!(engine.clear_buffer_pool().await, 1);
+        assert_eq!(engine.clear_buffer_pool().await, 0);
+
+        assert_eq!(engine.get(1).await.unwrap()[0], 0x01);
+        let stats = engine.buffer_pool_stats().await;
+        assert_eq!((stats.hits, stats.misses, stats.resident_pages), (0, 2, 1));
+    }
+
+    #[tokio::test]
+    async fn test_write_through_serves_read_after_write() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage, 100).with_write_policy(WritePolicy::WriteThrough);
+
+        engine.put(1, 0, vec![0x01]).await.unwrap();
+        engine.get(1).await.unwrap(); // miss, caches page 1 at LSN 1
+
+        engine.put(1, 1, vec![0x02]).await.unwrap();
+        engine
+            .put_multi(vec![(1, 2, vec![0x03]), (1, 3, vec![0x04]), (2, 0, vec![0x05])])
+            .await
+            .unwrap();
+        let page = engine.get(1).await.unwrap();
+        assert_eq!(&page[0..4], &[0x01, 0x02, 0x03, 0x04]);
+
+        let stats = engine.buffer_pool_stats().await;
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        // Page 2 was never resident, so write-through doesn't add it
+        assert_eq!(stats.resident_pages, 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_through_uses_the_lsns_storage_assigned() {
+        let engine = ComputeEngine::new(Arc::new(MockStorage::new()), 100);
+        let mut inner = engine.inner.lock().await;
+        inner.buffer_pool.insert(1, 1, empty_page(PAGE_SIZE));
+        let record = RedoRecord {
+            lsn: 2,
+            page_id: 1,
+            offset: 0,
+            data: vec![0x07].into(),
+            prev_lsn: 1,
+            mtr_id: 2,
+            is_mtr_end: true,
+            timestamp: 0,
+        };
+
+        // Another writer's append took LSN 3 before this one returned
+        let appended = AppendedRedo { lsns: 2..=2, vdl: 3 };
+        inner.update_written_pages(&[1], Some(&[record]), &appended, &NoopObserver);
+        // The image is at this append's LSN, not the VDL, which may include
+        // the other writer's changes to the page
+        assert_eq!(inner.buffer_pool.get(1, 2).unwrap()[0], 0x07);
+        assert!(inner.buffer_pool.get(1, 3).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_explicit_prefetch() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage, 100);
+
+        for page_id in 1..=4 {
+            engine.put(page_id, 0, vec![page_id as u8]).await.unwrap();
+        }
+        engine.prefetch(1..5).await.unwrap();
+
+        let page = engine.get(3).await.unwrap();
+        assert_eq!(page[0], 3);
+        let stats = engine.buffer_pool_stats().await;
+        assert_eq!((stats.prefetched, stats.prefetch_hits), (4, 1));
+        assert_eq!(stats.misses, 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_multi_batches_misses() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(Arc::clone(&storage) as Arc<dyn StorageApi>, 100);
+
+        for page_id in 1..=4 {
+            engine.put(page_id, 0, vec![page_id as u8]).await.unwrap();
+        }
+        engine.get(2).await.unwrap();
+
+        // Page 2 is resident; 1, 3 and 4 (asked for twice) go to storage in one call
+        let pages = engine.get_multi(&[3, 2, 1, 4, 3]).await.unwrap();
+        assert_eq!(pages.iter().map(|p| p[0]).collect::<Vec<_>>(), [3, 2, 1, 4, 3]);
+        assert_eq!(storage.batches.load(std::sync::atomic::Ordering::SeqCst), 1);
+        let stats = engine.buffer_pool_stats().await;
+        assert_eq!((stats.hits, stats.misses), (1, 5));
+
+        // All resident now: no storage call
+        engine.get_multi(&[1, 2, 3, 4]).await.unwrap();
+        assert_eq!(storage.batches.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_keep_their_own_read_points() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(Arc::clone(&storage) as Arc<dyn StorageApi>, 100);
+        engine.put(1, 0, b"old".to_vec()).await.unwrap();
+
+        let mut reader = engine.session().await;
+        let mut writer = engine.session().await;
+        assert_eq!(&reader.get(1).await.unwrap()[..3], b"old");
+
+        // A write through the node moves the writer's read point, not the reader's
+        writer.put(1, 0, b"new".to_vec()).await.unwrap();
+        assert_eq!((reader.read_point(), writer.read_point()), (1, 2));
+        assert_eq!(&writer.get(1).await.unwrap()[..3], b"new");
+        assert_eq!(&reader.get(1).await.unwrap()[..3], b"old");
+
+        // Behind the node's read point, the reader goes to storage
+        let stats = reader.stats();
+        assert_eq!((stats.shared_reads, stats.direct_reads), (1, 1));
+        assert_eq!(writer.stats().writes, 1);
+
+        assert_eq!(reader.refresh().await.unwrap(), 2);
+        assert_eq!(&reader.get(1).await.unwrap()[..3], b"new");
+        assert_eq!(reader.stats().shared_reads, 2);
+
+        // A write by another client of storage leaves the node behind a refreshed session
+        let mut mtr = MiniTransaction::new(99);
+        mtr.write(1, 0, b"ext".to_vec());
+        storage.append_redo(mtr.finish().unwrap()).await.unwrap();
+        reader.refresh().await.unwrap();
+        assert_eq!(&reader.get(1).await.unwrap()[..3], b"ext");
+        assert_eq!(reader.stats().direct_reads, 2);
+        assert_eq!(&engine.get(1).await.unwrap()[..3], b"new");
+    }
+
+    #[tokio::test]
+    async fn test_commit_after_partial_rollback() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage, 100);
+        engine.put(2, 0, b"keep".to_vec()).await.unwrap();
+
+        let mut mtr = engine.begin().await;
+        mtr.write(1, 0, b"one".to_vec());
+        let sp = mtr.savepoint();
+        mtr.write(2, 0, b"gone".to_vec());
+        mtr.write(3, 0, b"gone".to_vec());
+        assert_eq!(mtr.rollback_to(sp).unwrap(), 2);
+        let vdl = engine.commit(mtr).await.unwrap();
+
+        // Only the write before the savepoint reached storage, as the MTR's CPL
+        assert_eq!(vdl, 2);
+        assert_eq!(&engine.get(1).await.unwrap()[..3], b"one");
+        assert_eq!(&engine.get(2).await.unwrap()[..4], b"keep");
+        assert_eq!(engine.get(3).await.unwrap()[0], 0);
+
+        // Rolling back everything commits nothing
+        let mut mtr = engine.begin().await;
+        let sp = mtr.savepoint();
+        mtr.write(1, 0, b"two".to_vec());
+        mtr.rollback_to(sp).unwrap();
+        assert_eq!(engine.commit(mtr).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_blob_roundtrip_in_one_mtr() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage, 100);
+        engine.put(13, 0, b"neighbour".to_vec()).await.unwrap();
+
+        let data: Vec<u8> = (0..20 * 1024).map(|i| (i % 251) as u8).collect();
+        let vdl = engine.put_blob(10, &data).await.unwrap();
+        // Three page records in one MTR, after the neighbour's single record
+        assert_eq!(vdl, 4);
+        assert_eq!(engine.get_blob(10).await.unwrap(), data);
+        assert_eq!(&engine.get(13).await.unwrap()[..9], b"neighbour");
+
+        // A shorter value over the same pages reads back at its own length
+        engine.put_blob(10, b"short").await.unwrap();
+        assert_eq!(engine.get_blob(10).await.unwrap(), b"short");
+    }
+
+    #[tokio::test]
+    async fn test_mtr_over_its_limits_is_sent_in_pieces() {
+        let storage = Arc::new(MockStorage::new());
+        let limits = MtrLimits::new(4, 1024).unwrap();
+        let engine = ComputeEngine::new(storage.clone(), 100)
+            .with_mtr_limits(limits)
+            .unwrap()
+            .with_write_policy(WritePolicy::WriteThrough);
+        engine.put(13, 0, b"neighbour".to_vec()).await.unwrap();
+
+        let data: Vec<u8> = (0..20 * 1024).map(|i| (i % 251) as u8).collect();
+        let vdl = engine.put_blob(10, &data).await.unwrap();
+        let records = storage.inner.lock().unwrap().records.clone();
+        let blob: Vec<&RedoRecord> = records.iter().filter(|r| r.mtr_id == records[1].mtr_id).collect();
+        // Three page writes cut into records of at most 1024 bytes as logged
+        assert!(blob.len() > 20, "{}", blob.len());
+        assert!(blob.iter().all(|r| r.data.len() + mini_aurora_common::LOG_ENTRY_HEADER_SIZE <= 1024));
+        assert_eq!(blob.iter().filter(|r| r.is_mtr_end).count(), 1);
+        assert_eq!(vdl, blob.last().unwrap().lsn);
+        assert_eq!(engine.get_blob(10).await.unwrap(), data);
+        assert_eq!(&engine.get(13).await.unwrap()[..9], b"neighbour");
+
+        // A single put larger than a record may carry is cut the same way
+        let vdl = engine.put(20, 100, vec![0x5A; 3000]).await.unwrap();
+        assert_eq!(vdl, storage.inner.lock().unwrap().next_lsn - 1);
+        let page = engine.get(20).await.unwrap();
+        assert!(page[100..3100].iter().all(|&b| b == 0x5A) && page[99] == 0 && page[3100] == 0);
+
+        // A write past the page fails before it is cut, with nothing sent
+        let sent = storage.inner.lock().unwrap().records.len();
+        let mut mtr = engine.begin().await;
+        mtr.write(21, u16::MAX - 2, vec![0xA5; 3000]);
+        assert!(matches!(engine.commit(mtr).await, Err(StorageError::PageOverflow { offset: 65533, .. })));
+        assert_eq!(storage.inner.lock().unwrap().records.len(), sent);
+    }
+
+    #[tokio::test]
+    async fn test_pages_follow_storage_page_size() {
+        let engine = ComputeEngine::new(Arc::new(MockStorage::with_page_size(4096)), 100);
+        assert_eq!(engine.page_size(), 4096);
+        assert!(matches!(engine.put(1, 4095, vec![0; 2]).await, Err(StorageError::PageOverflow { .. })));
+        engine.put(1, 4094, vec![0xEE, 0xFF]).await.unwrap();
+        assert_eq!(engine.get(1).await.unwrap().len(), 4096);
+
+        // 20 KiB spans six 4 KiB pages, not three 8 KiB ones
+        let data: Vec<u8> = (0..20 * 1024).map(|i| (i % 251) as u8).collect();
+        assert_eq!(blob_pages(10, data.len(), engine.page_size()), 10..16);
+        engine.put_blob(10, &data).await.unwrap();
+        assert_eq!(engine.get_blob(10).await.unwrap(), data);
+        assert_eq!(engine.buffer_pool_stats().await.budget_bytes, 100 * 4096);
+    }
+
+    #[tokio::test]
+    async fn test_allocate_and_free_pages() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage.clone(), 100);
+        assert_eq!(engine.allocate_page().await.unwrap(), 1);
+        assert_eq!(engine.allocate_page().await.unwrap(), 2);
+        assert_eq!(engine.allocate_page().await.unwrap(), 3);
+
+        engine.free_page(2).await.unwrap();
+        assert!(engine.free_page(2).await.is_err());
+        assert!(engine.free_page(ALLOCATOR_PAGE).await.is_err());
+        assert_eq!(engine.allocate_page().await.unwrap(), 2);
+
+        // The bitmap lives in storage, so a new node picks up where this one left off
+        let restarted = ComputeEngine::new(storage, 100);
+        restarted.refresh_read_point().await.unwrap();
+        assert_eq!(restarted.allocate_page().await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_agrees_on_shared_features() {
+        let engine = ComputeEngine::new(Arc::new(MockStorage::new()), 100);
+        assert_eq!(engine.capabilities().await, None);
+
+        // Storage batches reads but ships whole pages
+        let agreed = engine.handshake().await.unwrap();
+        assert!(agreed.batch_reads);
+        assert!(!agreed.partial_reads);
+        assert_eq!(engine.capabilities().await, Some(agreed));
+
+        let newer = Capabilities {
+            api_version: STORAGE_API_VERSION + 1,
+            ..Capabilities::baseline()
+        };
+        let err = newer.negotiate(&ComputeEngine::supported_capabilities()).unwrap_err();
+        assert!(matches!(err, StorageError::Incompatible(_)));
+    }
+
+    #[tokio::test]
+    async fn test_sequential_read_ahead() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage, 100).with_read_ahead(2);
+
+        for page_id in 1..=6 {
+            engine.put(page_id, 0, vec![page_id as u8]).await.unwrap();
+        }
+        let (page, read_ahead) = engine.get_reading_ahead(1).await;
+        page.unwrap();
+        assert!(read_ahead.is_none());
+
+        // Sequential: prefetches pages 3 and 4
+        let (page, read_ahead) = engine.get_reading_ahead(2).await;
+        page.unwrap();
+        read_ahead.unwrap().await.unwrap();
+        assert_eq!(engine.buffer_pool_stats().await.prefetched, 2);
+
+        // A hit, which reads ahead only page 5
+        let (page, read_ahead) = engine.get_reading_ahead(3).await;
+        assert_eq!(page.unwrap()[0], 3);
+        read_ahead.unwrap().await.unwrap();
+
+        let stats = engine.buffer_pool_stats().await;
+        assert_eq!(stats.prefetched, 3);
+        assert_eq!(stats.prefetch_hits, 1);
+    }
+
     #[tokio::test]
     async fn test_overflow_rejected() {
         let storage = Arc::new(MockStorage::new());
@@ -280,4 +1361,129 @@ mod tests {
         let result = engine.put(1, PAGE_SIZE as u16 - 1, vec![0; 2]).await;
         assert!(result.is_err());
     }
+
+    /// Records the compute-side hooks it receives, in order.
+    #[derive(Default)]
+    struct RecordingObserver {
+        calls: StdMutex<Vec<String>>,
+    }
+
+    impl EngineObserver for RecordingObserver {
+        fn mtr_created(&self, mtr_id: u64, num_records: usize) {
+            self.calls.lock().unwrap().push(format!("mtr {mtr_id} x{num_records}"));
+        }
+
+        fn read_point_updated(&self, old: Lsn, new: Lsn) {
+            self.calls.lock().unwrap().push(format!("read_point {old}->{new}"));
+        }
+
+        fn buffer_pool_lookup(&self, page_id: PageId, _read_point: Lsn, hit: bool) {
+            self.calls.lock().unwrap().push(format!("lookup pg{page_id} hit={hit}"));
+        }
+
+        fn buffer_pool_inserted(&self, page_id: PageId, read_point: Lsn) {
+            self.calls.lock().unwrap().push(format!("insert pg{page_id}@{read_point}"));
+        }
+
+        fn buffer_pool_written_through(&self, page_id: PageId, read_point: Lsn) {
+            self.calls.lock().unwrap().push(format!("write_through pg{page_id}@{read_point}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer_sees_each_step() {
+        let storage = Arc::new(MockStorage::new());
+        let observer = Arc::new(RecordingObserver::default());
+        let engine = ComputeEngine::new(storage, 100)
+            .with_write_policy(WritePolicy::WriteThrough)
+            .with_observer(observer.clone());
+
+        engine.put(1, 0, vec![0x01]).await.unwrap();
+        engine.get(1).await.unwrap();
+        engine.put(1, 1, vec![0x02]).await.unwrap();
+        engine.get(1).await.unwrap();
+
+        assert_eq!(
+            *observer.calls.lock().unwrap(),
+            [
+                "mtr 1 x1",
+                "read_point 0->1",
+                "lookup pg1 hit=false",
+                "insert pg1@1",
+                "mtr 2 x1",
+                "write_through pg1@2",
+                "read_point 1->2",
+                "lookup pg1 hit=true",
+            ]
+        );
+    }
+
+    /// Poll until heartbeats report storage as `healthy`.
+    async fn wait_for_health(engine: &ComputeEngine, healthy: bool) {
+        for _ in 0..500 {
+            if engine.storage_health().unwrap().healthy == healthy {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("storage never became healthy={healthy}");
+    }
+
+    #[tokio::test]
+    async fn test_missed_heartbeats_fail_fast() {
+        let storage = Arc::new(MockStorage::new());
+        let engine = ComputeEngine::new(storage.clone(), 100).with_heartbeat(HeartbeatConfig {
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_millis(5),
+            max_missed: 2,
+        });
+        engine.put(1, 0, vec![0x01]).await.unwrap();
+        engine.get(1).await.unwrap();
+
+        storage.silent.store(true, std::sync::atomic::Ordering::SeqCst);
+        wait_for_health(&engine, false).await;
+        let health = engine.storage_health().unwrap();
+        assert!(health.missed >= 2);
+        assert!(health.last_error.unwrap().contains("no heartbeat reply"));
+        let err = engine.put(2, 0, vec![0x02]).await.unwrap_err();
+        assert!(matches!(err, StorageError::Unavailable(_)));
+        assert!(engine.refresh_read_point().await.is_err());
+        // The buffer pool still serves what it holds
+        assert_eq!(engine.get(1).await.unwrap()[0], 0x01);
+        assert_eq!(engine.next_mtr_id().await, 2);
+
+        storage.silent.store(false, std::sync::atomic::Ordering::SeqCst);
+        wait_for_health(&engine, true).await;
+        let health = engine.storage_health().unwrap();
+        assert_eq!(health.durability.unwrap().vdl, 1);
+        engine.put(2, 0, vec![0x02]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_puts_share_one_append() {
+        let storage = Arc::new(MockStorage::new());
+        let batching = WriteBatching { window: Duration::from_millis(50), max_records: 3 };
+        let engine = ComputeEngine::new(storage.clone(), 100).with_write_batching(batching);
+
+        // A full batch goes at once, each put as its own MTR
+        let (a, b, c) = tokio::join!(
+            engine.put(1, 0, vec![0x01]),
+            engine.put(2, 0, vec![0x02]),
+            engine.put(3, 0, vec![0x03]),
+        );
+        assert_eq!((a.unwrap(), b.unwrap(), c.unwrap()), (3, 3, 3));
+        let mtrs: Vec<u64> = storage.inner.lock().unwrap().records.iter().map(|r| r.mtr_id).collect();
+        assert_eq!(mtrs, [1, 2, 3]);
+        assert!(storage.inner.lock().unwrap().records.iter().all(|r| r.is_mtr_end));
+
+        // One that never fills goes when its window is up
+        let (d, e) = tokio::join!(engine.put(1, 1, vec![0x04]), engine.put(4, 0, vec![0x05]));
+        assert_eq!((d.unwrap(), e.unwrap()), (5, 5));
+        let stats = engine.write_batch_stats().unwrap();
+        assert_eq!(stats, WriteBatchStats { batches: 2, mtrs: 5, records: 5, full: 1, largest: 3 });
+        assert_eq!(stats.mean_mtrs(), 2.5);
+
+        assert_eq!(engine.read_point().await, 5);
+        assert_eq!(engine.get(1).await.unwrap()[..2], [0x01, 0x04]);
+    }
 }
@@ -0,0 +1,205 @@
+//! Heartbeats from a compute node to storage.
+//!
+//! A background task pings storage every `interval` and storage answers
+//! with its durability state. Once `max_missed` beats in a row fail or time
+//! out, the node marks storage unhealthy and its storage calls fail fast
+//! with `StorageError::Unavailable` instead of waiting on a dead link. The
+//! next beat that gets through marks storage healthy again.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mini_aurora_common::{DurabilityState, EngineObserver, StorageApi, StorageError};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info};
+
+/// How often to ping storage and how much silence to tolerate.
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatConfig {
+    pub interval: Duration,
+    /// A beat with no answer after this long counts as missed.
+    pub timeout: Duration,
+    /// Missed beats in a row before storage is marked unhealthy.
+    pub max_missed: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_millis(500),
+            max_missed: 3,
+        }
+    }
+}
+
+/// What a compute node knows about storage from its heartbeats.
+#[derive(Debug, Clone)]
+pub struct StorageHealth {
+    pub healthy: bool,
+    /// Beats missed since the last one that got through.
+    pub missed: u32,
+    /// Beats storage answered.
+    pub acked: u64,
+    pub last_latency: Option<Duration>,
+    /// Durability state storage advertised in its last answer.
+    pub durability: Option<DurabilityState>,
+    /// Why the last beat was missed, while beats are being missed.
+    pub last_error: Option<String>,
+}
+
+impl Default for StorageHealth {
+    fn default() -> Self {
+        // Healthy until proven otherwise, so a node works before its first beat
+        Self {
+            healthy: true,
+            missed: 0,
+            acked: 0,
+            last_latency: None,
+            durability: None,
+            last_error: None,
+        }
+    }
+}
+
+/// A running heartbeat task and the health it keeps up to date. Dropping
+/// it stops the task.
+pub(crate) struct Heartbeat {
+    health: Arc<Mutex<StorageHealth>>,
+    task: JoinHandle<()>,
+}
+
+impl Heartbeat {
+    /// Start pinging `storage`. Must be called inside a Tokio runtime.
+    pub(crate) fn spawn(
+        storage: Arc<dyn StorageApi>,
+        config: HeartbeatConfig,
+        observer: Arc<dyn EngineObserver>,
+    ) -> Self {
+        let health = Arc::new(Mutex::new(StorageHealth::default()));
+        let shared = Arc::clone(&health);
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(config.interval);
+            // A slow beat delays the next one instead of bunching them up
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let started = Instant::now();
+                let result = match tokio::time::timeout(config.timeout, storage.heartbeat()).await {
+                    Ok(result) => result,
+                    Err(_) => Err(StorageError::Unavailable(format!(
+                        "no heartbeat reply within {}ms",
+                        config.timeout.as_millis()
+                    ))),
+                };
+                record_beat(&shared, config.max_missed, observer.as_ref(), result, started.elapsed());
+            }
+        });
+        Self { health, task }
+    }
+
+    pub(crate) fn health(&self) -> StorageHealth {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// `Unavailable` while storage is marked unhealthy.
+    pub(crate) fn check(&self) -> Result<(), StorageError> {
+        let health = self.health.lock().unwrap();
+        if health.healthy {
+            return Ok(());
+        }
+        Err(StorageError::Unavailable(format!(
+            "missed {} heartbeats, last: {}",
+            health.missed,
+            health.last_error.as_deref().unwrap_or("no reply")
+        )))
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Fold one beat's outcome into `health`, then report it to `observer`.
+fn record_beat(
+    health: &Mutex<StorageHealth>,
+    max_missed: u32,
+    observer: &dyn EngineObserver,
+    result: Result<DurabilityState, StorageError>,
+    latency: Duration,
+) {
+    let mut h = health.lock().unwrap();
+    let was_healthy = h.healthy;
+    match result {
+        Ok(state) => {
+            let vdl = state.vdl;
+            h.healthy = true;
+            h.missed = 0;
+            h.acked += 1;
+            h.last_latency = Some(latency);
+            h.durability = Some(state);
+            h.last_error = None;
+            drop(h);
+            debug!(latency_us = latency.as_micros() as u64, vdl, "heartbeat acked");
+            observer.heartbeat_acked(latency, vdl);
+            if !was_healthy {
+                info!("storage healthy again");
+                observer.storage_health_changed(true);
+            }
+        }
+        Err(e) => {
+            // `check` wraps the reason in another `Unavailable`
+            let reason = match e {
+                StorageError::Unavailable(reason) => reason,
+                other => other.to_string(),
+            };
+            debug!(error = %reason, "heartbeat missed");
+            h.missed += 1;
+            h.last_error = Some(reason);
+            if h.missed >= max_missed {
+                h.healthy = false;
+            }
+            let (missed, healthy) = (h.missed, h.healthy);
+            drop(h);
+            observer.heartbeat_missed(missed);
+            if was_healthy && !healthy {
+                info!(missed, "storage marked unhealthy");
+                observer.storage_health_changed(false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mini_aurora_common::NoopObserver;
+
+    fn down() -> Result<DurabilityState, StorageError> {
+        Err(StorageError::Unavailable("link down".to_string()))
+    }
+
+    #[test]
+    fn test_unhealthy_after_max_missed_and_back() {
+        let health = Mutex::new(StorageHealth::default());
+        let beat = |result| record_beat(&health, 2, &NoopObserver, result, Duration::from_millis(1));
+
+        beat(down());
+        assert!(health.lock().unwrap().healthy);
+        beat(down());
+        let h = health.lock().unwrap().clone();
+        assert!(!h.healthy);
+        assert_eq!(h.missed, 2);
+        assert_eq!(h.last_error.as_deref(), Some("link down"));
+
+        beat(Ok(DurabilityState { vcl: 7, vdl: 5 }));
+        let h = health.lock().unwrap().clone();
+        assert!(h.healthy);
+        assert_eq!((h.missed, h.acked), (0, 1));
+        assert_eq!(h.durability.map(|d| d.vdl), Some(5));
+        assert!(h.last_error.is_none());
+    }
+}
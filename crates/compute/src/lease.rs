@@ -0,0 +1,109 @@
+//! Reader leases: a compute node tells storage the oldest read point it or
+//! any of its sessions may still read at, so storage keeps the versions
+//! those reads see.
+//!
+//! A background task registers that read point every third of the lease,
+//! so it stays registered through a missed renewal or two. A node that
+//! dies stops renewing and its lease runs out in storage, so a crashed
+//! reader can't pin history forever.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use mini_aurora_common::{Lsn, StorageApi};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Read points a node may still read at: its own and its sessions'.
+#[derive(Debug, Default)]
+pub(crate) struct ReadPoints {
+    node: Lsn,
+    /// Session read point → sessions at it.
+    sessions: BTreeMap<Lsn, usize>,
+}
+
+impl ReadPoints {
+    pub(crate) fn set_node(&mut self, read_point: Lsn) {
+        self.node = read_point;
+    }
+
+    pub(crate) fn pin(&mut self, read_point: Lsn) {
+        *self.sessions.entry(read_point).or_insert(0) += 1;
+    }
+
+    pub(crate) fn unpin(&mut self, read_point: Lsn) {
+        if let Some(count) = self.sessions.get_mut(&read_point) {
+            *count -= 1;
+            if *count == 0 {
+                self.sessions.remove(&read_point);
+            }
+        }
+    }
+
+    /// The lowest read point still in use.
+    pub(crate) fn oldest(&self) -> Lsn {
+        self.sessions.keys().next().map_or(self.node, |&lsn| lsn.min(self.node))
+    }
+}
+
+/// A node's registration with storage: its name, the lease and the task
+/// renewing it. Dropping it stops renewing; the lease then runs out.
+pub(crate) struct ReaderLease {
+    pub(crate) name: String,
+    pub(crate) lease: Duration,
+    task: JoinHandle<()>,
+}
+
+impl ReaderLease {
+    /// Start renewing. Must be called inside a Tokio runtime.
+    pub(crate) fn spawn(
+        storage: Arc<dyn StorageApi>,
+        name: String,
+        lease: Duration,
+        read_points: Arc<Mutex<ReadPoints>>,
+    ) -> Self {
+        let reader = name.clone();
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(lease / 3);
+            loop {
+                ticker.tick().await;
+                let read_point = read_points.lock().unwrap().oldest();
+                // A failed renewal is retried at the next tick, well before the lease ends
+                if let Err(e) = storage.register_reader(&reader, read_point, lease).await {
+                    debug!(reader, read_point, error = %e, "reader lease renewal failed");
+                }
+            }
+        });
+        Self { name, lease, task }
+    }
+}
+
+impl Drop for ReaderLease {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oldest_covers_node_and_sessions() {
+        let mut points = ReadPoints::default();
+        points.set_node(5);
+        assert_eq!(points.oldest(), 5);
+
+        points.pin(3);
+        points.pin(3);
+        points.pin(8);
+        assert_eq!(points.oldest(), 3);
+        points.unpin(3);
+        assert_eq!(points.oldest(), 3);
+        points.unpin(3);
+        assert_eq!(points.oldest(), 5);
+        points.set_node(9);
+        assert_eq!(points.oldest(), 8);
+    }
+}
@@ -1,3 +1,10 @@
+pub mod allocator;
+pub mod batch;
+pub mod blob;
 pub mod buffer_pool;
 pub mod engine;
+pub mod health;
+pub mod lease;
+pub mod session;
 pub mod transaction;
+pub mod workload;
@@ -0,0 +1,106 @@
+//! Logical clients of one compute node.
+//!
+//! A `Session` has its own read point and counters but shares the node's
+//! buffer pool and storage link, so several clients of one node can sit at
+//! different consistency positions, like readers on separate replicas. The
+//! buffer pool only holds images as of the node's read point: a session at
+//! that read point reads through it, and a session behind or ahead of it
+//! reads straight from storage.
+
+use mini_aurora_common::{Bytes, Lsn, PageId, PageRef, StorageError};
+use tracing::{debug, instrument};
+
+use crate::engine::ComputeEngine;
+
+/// What one session has done.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Reads served through the shared buffer pool, at the node's read point.
+    pub shared_reads: u64,
+    /// Reads sent straight to storage, at a read point the pool can't serve.
+    pub direct_reads: u64,
+    pub writes: u64,
+    pub refreshes: u64,
+}
+
+/// A client of a `ComputeEngine` with its own read point; see
+/// `ComputeEngine::session`.
+pub struct Session<'a> {
+    engine: &'a ComputeEngine,
+    read_point: Lsn,
+    stats: SessionStats,
+}
+
+impl<'a> Session<'a> {
+    pub(crate) fn new(engine: &'a ComputeEngine, read_point: Lsn) -> Self {
+        engine.read_points().lock().unwrap().pin(read_point);
+        Self {
+            engine,
+            read_point,
+            stats: SessionStats::default(),
+        }
+    }
+
+    /// The LSN this session reads at. It moves only on `refresh`, `set_read_point`
+    /// and the session's own writes.
+    pub fn read_point(&self) -> Lsn {
+        self.read_point
+    }
+
+    pub fn stats(&self) -> SessionStats {
+        self.stats
+    }
+
+    /// Move this session's read point to storage's VDL. The node's read point
+    /// and other sessions stay where they are.
+    pub async fn refresh(&mut self) -> Result<Lsn, StorageError> {
+        let vdl = self.engine.storage()?.get_durability_state().await?.vdl;
+        debug!(old = self.read_point, new = vdl, "session read point refreshed");
+        self.move_to(vdl);
+        self.stats.refreshes += 1;
+        Ok(vdl)
+    }
+
+    /// Pin this session to an earlier (or any durable) LSN. With a reader
+    /// lease, storage learns of an earlier one at the node's next renewal;
+    /// `ComputeEngine::renew_reader_lease` tells it at once.
+    pub fn set_read_point(&mut self, read_point: Lsn) {
+        self.move_to(read_point);
+    }
+
+    /// Move the read point, keeping the node's count of session read points
+    /// in step.
+    fn move_to(&mut self, read_point: Lsn) {
+        let mut points = self.engine.read_points().lock().unwrap();
+        points.unpin(self.read_point);
+        points.pin(read_point);
+        self.read_point = read_point;
+    }
+
+    /// Read a page at this session's read point.
+    #[instrument(level = "debug", skip(self), fields(read_point = self.read_point))]
+    pub async fn get(&mut self, page_id: PageId) -> Result<PageRef, StorageError> {
+        if self.engine.read_point().await == self.read_point {
+            self.stats.shared_reads += 1;
+            self.engine.get_at(page_id, self.read_point).await
+        } else {
+            self.stats.direct_reads += 1;
+            self.engine.storage()?.get_page(page_id, self.read_point).await
+        }
+    }
+
+    /// Write through the node, then read at the write's VDL, so the session
+    /// sees its own writes.
+    pub async fn put(&mut self, page_id: PageId, offset: u16, data: impl Into<Bytes>) -> Result<Lsn, StorageError> {
+        let vdl = self.engine.put(page_id, offset, data).await?;
+        self.stats.writes += 1;
+        self.move_to(self.read_point.max(vdl));
+        Ok(vdl)
+    }
+}
+
+impl Drop for Session<'_> {
+    fn drop(&mut self) {
+        self.engine.read_points().lock().unwrap().unpin(self.read_point);
+    }
+}
@@ -1,11 +1,70 @@
-use mini_aurora_common::{PageId, RedoRecord};
+use mini_aurora_common::{check_page_range, Bytes, PageId, RedoRecord, StorageError, LOG_ENTRY_HEADER_SIZE};
+
+/// Caps on the redo one append of an MTR may carry, counting each record as
+/// logged: its header plus its data. An MTR over them is sent in pieces;
+/// see `MiniTransaction::split`. Unlimited by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MtrLimits {
+    max_records: usize,
+    max_bytes: usize,
+}
+
+impl Default for MtrLimits {
+    fn default() -> Self {
+        Self { max_records: usize::MAX, max_bytes: usize::MAX }
+    }
+}
+
+impl MtrLimits {
+    /// Fails if a piece couldn't hold one record with a byte of data, as a
+    /// write under such limits could never be logged.
+    pub fn new(max_records: usize, max_bytes: usize) -> Result<Self, StorageError> {
+        if max_records == 0 {
+            return Err(StorageError::Other("MTR limit of 0 records leaves no room for a write".into()));
+        }
+        if max_bytes <= LOG_ENTRY_HEADER_SIZE {
+            return Err(StorageError::Other(format!(
+                "MTR limit of {max_bytes} bytes leaves no room for data after a {LOG_ENTRY_HEADER_SIZE}-byte \
+                 record header"
+            )));
+        }
+        Ok(Self { max_records, max_bytes })
+    }
+
+    pub fn max_records(&self) -> usize {
+        self.max_records
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Data bytes one record may carry.
+    fn max_data(&self) -> usize {
+        self.max_bytes.saturating_sub(LOG_ENTRY_HEADER_SIZE)
+    }
+}
 
 /// Builder for a mini-transaction (MTR).
 ///
 /// Collects redo records and marks the last one as the CPL (consistency point).
+/// Nothing reaches storage before `finish`, so a rollback to a savepoint just
+/// drops the records staged after it.
 pub struct MiniTransaction {
     mtr_id: u64,
     records: Vec<RedoRecord>,
+    limits: MtrLimits,
+    /// Live savepoints, oldest first, with the records staged before each.
+    savepoints: Vec<(u32, usize)>,
+    next_savepoint: u32,
+}
+
+/// A point in an MTR's staged writes to roll back to; see
+/// `MiniTransaction::savepoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Savepoint {
+    mtr_id: u64,
+    id: u32,
 }
 
 impl MiniTransaction {
@@ -13,23 +72,62 @@ impl MiniTransaction {
         Self {
             mtr_id,
             records: Vec::new(),
+            limits: MtrLimits::default(),
+            savepoints: Vec::new(),
+            next_savepoint: 1,
         }
     }
 
+    /// Hold the MTR to `limits` when it is split for sending.
+    pub fn with_limits(mut self, limits: MtrLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     /// Add a page write to this mini-transaction.
     /// LSN and prev_lsn will be assigned by the storage engine.
-    pub fn write(&mut self, page_id: PageId, offset: u16, data: Vec<u8>) {
+    pub fn write(&mut self, page_id: PageId, offset: u16, data: impl Into<Bytes>) {
         self.records.push(RedoRecord {
             lsn: 0,     // assigned by storage
             page_id,
             offset,
-            data,
+            data: data.into(),
             prev_lsn: 0, // assigned by storage
             mtr_id: self.mtr_id,
             is_mtr_end: false,
+            timestamp: 0, // assigned by storage
         });
     }
 
+    /// Mark the writes staged so far, so `rollback_to` can discard the ones
+    /// that follow.
+    pub fn savepoint(&mut self) -> Savepoint {
+        let id = self.next_savepoint;
+        self.next_savepoint += 1;
+        self.savepoints.push((id, self.records.len()));
+        Savepoint { mtr_id: self.mtr_id, id }
+    }
+
+    /// Discard the writes staged after `savepoint`, and every savepoint taken
+    /// after it. `savepoint` itself stays usable. Returns how many writes
+    /// were discarded; fails for a savepoint of another MTR or one an
+    /// earlier rollback already discarded.
+    pub fn rollback_to(&mut self, savepoint: Savepoint) -> Result<usize, StorageError> {
+        let position = self
+            .savepoints
+            .iter()
+            .position(|&(id, _)| id == savepoint.id)
+            .filter(|_| savepoint.mtr_id == self.mtr_id)
+            .ok_or_else(|| {
+                StorageError::Other(format!("savepoint {} is not live in MTR {}", savepoint.id, self.mtr_id))
+            })?;
+        let staged = self.savepoints[position].1;
+        self.savepoints.truncate(position + 1);
+        let discarded = self.records.len() - staged;
+        self.records.truncate(staged);
+        Ok(discarded)
+    }
+
     /// Finalize the MTR: marks the last record as the CPL and returns all records.
     /// Returns `None` if the MTR is empty.
     pub fn finish(mut self) -> Option<Vec<RedoRecord>> {
@@ -41,6 +139,61 @@ impl MiniTransaction {
         }
     }
 
+    /// Fails for the first staged write that runs past the end of a page
+    /// of `page_size` bytes.
+    pub fn check_page_range(&self, page_size: usize) -> Result<(), StorageError> {
+        self.records.iter().try_for_each(|r| check_page_range(r.offset, r.data.len(), page_size))
+    }
+
+    /// Finalize the MTR into pieces within its limits, to be appended in
+    /// order. A write with more data than one record may carry becomes
+    /// writes of consecutive ranges of the same page. Only the last record
+    /// of the last piece is the CPL, so the pieces before it make nothing
+    /// durable on their own. Empty if the MTR is. Fails with `PageOverflow`
+    /// for a write whose ranges would run past the last offset of a page;
+    /// call `check_page_range` first to hold writes to the volume's pages.
+    pub fn split(self) -> Result<Vec<Vec<RedoRecord>>, StorageError> {
+        let limits = self.limits;
+        let max_data = limits.max_data().max(1);
+        let mut pieces: Vec<Vec<RedoRecord>> = Vec::new();
+        let mut bytes = 0;
+        for record in self.records {
+            let mut start = 0;
+            // An empty write is still one record
+            while start == 0 || start < record.data.len() {
+                let end = record.data.len().min(start.saturating_add(max_data));
+                let offset = u16::try_from(start).ok().and_then(|start| record.offset.checked_add(start));
+                let overflow = || StorageError::PageOverflow { offset: record.offset, len: record.data.len() };
+                let cut = RedoRecord {
+                    offset: offset.ok_or_else(overflow)?,
+                    data: record.data.slice(start..end),
+                    ..record.clone()
+                };
+                let logged = LOG_ENTRY_HEADER_SIZE + cut.data.len();
+                match pieces.last_mut() {
+                    Some(piece) if piece.len() < limits.max_records && bytes + logged <= limits.max_bytes => {
+                        piece.push(cut);
+                        bytes += logged;
+                    }
+                    _ => {
+                        pieces.push(vec![cut]);
+                        bytes = logged;
+                    }
+                }
+                start = end.max(1);
+            }
+        }
+        if let Some(last) = pieces.last_mut().and_then(|piece| piece.last_mut()) {
+            last.is_mtr_end = true;
+        }
+        Ok(pieces)
+    }
+
+    /// Pages of the staged writes, in write order.
+    pub fn page_ids(&self) -> Vec<PageId> {
+        self.records.iter().map(|r| r.page_id).collect()
+    }
+
     pub fn mtr_id(&self) -> u64 {
         self.mtr_id
     }
@@ -90,4 +243,97 @@ mod tests {
             assert_eq!(r.mtr_id, 7);
         }
     }
+
+    #[test]
+    fn test_split_within_limits() {
+        let limits = MtrLimits::new(2, LOG_ENTRY_HEADER_SIZE + 4).unwrap();
+        let mut mtr = MiniTransaction::new(9).with_limits(limits);
+        mtr.write(1, 0, vec![0x01]);
+        mtr.write(2, 10, vec![0x02; 6]);
+        mtr.write(3, 0, Vec::new());
+
+        let pieces = mtr.split().unwrap();
+        let records: Vec<&RedoRecord> = pieces.iter().flatten().collect();
+        assert!(records.iter().all(|r| r.mtr_id == 9));
+        // Only the very last record is the CPL
+        assert_eq!(records.iter().filter(|r| r.is_mtr_end).count(), 1);
+        assert!(records.last().unwrap().is_mtr_end);
+        let shape: Vec<Vec<(PageId, u16, usize)>> = pieces
+            .iter()
+            .map(|piece| piece.iter().map(|r| (r.page_id, r.offset, r.data.len())).collect())
+            .collect();
+        // The 6-byte write is cut at 4 bytes and continues at offset 14;
+        // no two records fit in the byte limit
+        assert_eq!(shape, [[(1, 0, 1)], [(2, 10, 4)], [(2, 14, 2)], [(3, 0, 0)]]);
+
+        let limits = MtrLimits::new(2, 4096).unwrap();
+        let mut mtr = MiniTransaction::new(9).with_limits(limits);
+        for page_id in 1..=5 {
+            mtr.write(page_id, 0, vec![0x01]);
+        }
+        let sizes: Vec<usize> = mtr.split().unwrap().iter().map(Vec::len).collect();
+        assert_eq!(sizes, [2, 2, 1]);
+    }
+
+    #[test]
+    fn test_unlimited_split_is_one_piece() {
+        let mut mtr = MiniTransaction::new(1);
+        for page_id in 0..100 {
+            mtr.write(page_id, 0, vec![0xAA; 512]);
+        }
+        let pieces = mtr.split().unwrap();
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].len(), 100);
+        assert!(MiniTransaction::new(2).split().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_past_the_last_page_offset_fails() {
+        let limits = MtrLimits::new(8, LOG_ENTRY_HEADER_SIZE + 4).unwrap();
+        let mut mtr = MiniTransaction::new(4).with_limits(limits);
+        mtr.write(1, u16::MAX - 2, vec![0xAA; 8]);
+        assert!(matches!(mtr.check_page_range(8192), Err(StorageError::PageOverflow { .. })));
+        // Unchecked, the second range would start past offset u16::MAX
+        let err = mtr.split().unwrap_err();
+        assert!(matches!(err, StorageError::PageOverflow { offset: 65533, len: 8 }), "{err}");
+    }
+
+    #[test]
+    fn test_limits_that_cannot_hold_a_write_are_rejected() {
+        assert!(MtrLimits::new(0, 4096).is_err());
+        let err = MtrLimits::new(8, LOG_ENTRY_HEADER_SIZE).unwrap_err();
+        assert!(err.to_string().contains("no room for data"), "{err}");
+        assert!(MtrLimits::new(1, LOG_ENTRY_HEADER_SIZE + 1).is_ok());
+    }
+
+    #[test]
+    fn test_rollback_to_savepoint() {
+        let mut mtr = MiniTransaction::new(3);
+        mtr.write(1, 0, vec![0x01]);
+        let sp1 = mtr.savepoint();
+        mtr.write(2, 0, vec![0x02]);
+        let sp2 = mtr.savepoint();
+        mtr.write(3, 0, vec![0x03]);
+
+        assert_eq!(mtr.rollback_to(sp1).unwrap(), 2);
+        // sp2 went with the writes after sp1; sp1 can be used again
+        assert!(mtr.rollback_to(sp2).is_err());
+        mtr.write(4, 0, vec![0x04]);
+        assert_eq!(mtr.rollback_to(sp1).unwrap(), 1);
+        assert!(MiniTransaction::new(4).rollback_to(sp1).is_err());
+
+        mtr.write(5, 0, vec![0x05]);
+        let records = mtr.finish().unwrap();
+        assert_eq!(records.iter().map(|r| r.page_id).collect::<Vec<_>>(), [1, 5]);
+        assert!(records[1].is_mtr_end);
+    }
+
+    #[test]
+    fn test_rollback_to_empty_savepoint() {
+        let mut mtr = MiniTransaction::new(1);
+        let sp = mtr.savepoint();
+        mtr.write(1, 0, vec![0x01]);
+        mtr.rollback_to(sp).unwrap();
+        assert!(mtr.finish().is_none());
+    }
 }
@@ -0,0 +1,313 @@
+use std::time::Duration;
+
+use mini_aurora_common::PageId;
+
+/// How workload keys (page IDs) are chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyDistribution {
+    /// Every page equally likely.
+    Uniform,
+    /// Skewed towards low page IDs; page 1 is the hottest. YCSB's default
+    /// skew constant is 0.99; larger `theta` is more skewed.
+    Zipfian { theta: f64 },
+}
+
+/// Declarative YCSB-style workload: which pages, what mix, how fast.
+///
+/// Pages are `1..=record_count`. Each operation is a read with probability
+/// `read_proportion`, otherwise an update of `value_size` bytes at offset 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkloadSpec {
+    pub record_count: u64,
+    pub operation_count: u64,
+    pub read_proportion: f64,
+    pub distribution: KeyDistribution,
+    /// Throttle to this rate; `None` runs as fast as possible.
+    pub target_ops_per_sec: Option<u64>,
+    pub value_size: usize,
+    /// Seed for the key and operation choices, so runs are reproducible.
+    pub seed: u64,
+}
+
+/// YCSB's default zipfian skew.
+pub const ZIPFIAN_THETA: f64 = 0.99;
+
+impl Default for WorkloadSpec {
+    fn default() -> Self {
+        Self {
+            record_count: 100,
+            operation_count: 1000,
+            read_proportion: 0.5,
+            distribution: KeyDistribution::Zipfian { theta: ZIPFIAN_THETA },
+            target_ops_per_sec: None,
+            value_size: 32,
+            seed: 42,
+        }
+    }
+}
+
+impl WorkloadSpec {
+    /// Standard YCSB core workloads by letter: `a` (50% reads), `b` (95%
+    /// reads), `c` (read-only). All use a zipfian key distribution.
+    pub fn preset(name: &str) -> Option<Self> {
+        let read_proportion = match name.to_ascii_lowercase().as_str() {
+            "a" => 0.5,
+            "b" => 0.95,
+            "c" => 1.0,
+            _ => return None,
+        };
+        Some(Self {
+            read_proportion,
+            ..Self::default()
+        })
+    }
+
+    /// Delay between operations needed to hit `target_ops_per_sec`.
+    pub fn op_interval(&self) -> Option<Duration> {
+        self.target_ops_per_sec
+            .filter(|&rate| rate > 0)
+            .map(|rate| Duration::from_secs_f64(1.0 / rate as f64))
+    }
+
+    /// Iterate over the operations of this workload.
+    pub fn operations(&self) -> WorkloadGenerator {
+        WorkloadGenerator::new(self.clone())
+    }
+}
+
+/// One generated operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkloadOp {
+    Read { page_id: PageId },
+    Update { page_id: PageId, offset: u16, data: Vec<u8> },
+}
+
+/// Deterministic stream of operations for a [`WorkloadSpec`].
+pub struct WorkloadGenerator {
+    spec: WorkloadSpec,
+    pages: PageSampler,
+    emitted: u64,
+}
+
+impl WorkloadGenerator {
+    fn new(spec: WorkloadSpec) -> Self {
+        Self {
+            pages: PageSampler::new(spec.distribution, 1, spec.record_count, spec.seed),
+            spec,
+            emitted: 0,
+        }
+    }
+}
+
+impl Iterator for WorkloadGenerator {
+    type Item = WorkloadOp;
+
+    fn next(&mut self) -> Option<WorkloadOp> {
+        if self.emitted >= self.spec.operation_count {
+            return None;
+        }
+        self.emitted += 1;
+
+        let is_read = self.pages.rng.next_f64() < self.spec.read_proportion;
+        let page_id = self.pages.next_page();
+        if is_read {
+            return Some(WorkloadOp::Read { page_id });
+        }
+        let fill = b'a' + (self.emitted % 26) as u8;
+        Some(WorkloadOp::Update {
+            page_id,
+            offset: 0,
+            data: vec![fill; self.spec.value_size],
+        })
+    }
+}
+
+/// Draws page IDs from `first..=last` with a [`KeyDistribution`]; with a
+/// zipfian distribution `first` is the hottest page. Deterministic for a
+/// given seed.
+pub struct PageSampler {
+    first: PageId,
+    count: u64,
+    rng: SplitMix64,
+    zipfian: Option<Zipfian>,
+}
+
+impl PageSampler {
+    pub fn new(distribution: KeyDistribution, first: PageId, last: PageId, seed: u64) -> Self {
+        let count = last.saturating_sub(first) + 1;
+        let zipfian = match distribution {
+            KeyDistribution::Zipfian { theta } => Some(Zipfian::new(count, theta)),
+            KeyDistribution::Uniform => None,
+        };
+        Self {
+            first,
+            count,
+            rng: SplitMix64(seed),
+            zipfian,
+        }
+    }
+
+    pub fn next_page(&mut self) -> PageId {
+        let rank = match &self.zipfian {
+            Some(z) => z.sample(self.rng.next_f64()),
+            None => self.rng.next_u64() % self.count,
+        };
+        self.first + rank
+    }
+}
+
+/// Zipfian rank generator (Gray et al., as used by YCSB). Returns ranks in
+/// `0..n`, with rank 0 the most popular.
+struct Zipfian {
+    n: u64,
+    theta: f64,
+    alpha: f64,
+    zeta_n: f64,
+    eta: f64,
+    /// Cumulative probabilities by rank. Gray's method only holds for
+    /// `theta < 1`, so larger skews invert this table instead.
+    cdf: Vec<f64>,
+}
+
+impl Zipfian {
+    fn new(n: u64, theta: f64) -> Self {
+        let n = n.max(1);
+        let zeta = |count: u64| (1..=count).map(|i| 1.0 / (i as f64).powf(theta)).sum::<f64>();
+        let zeta_n = zeta(n);
+        let zeta_2 = zeta(2.min(n));
+        let alpha = 1.0 / (1.0 - theta);
+        let eta = (1.0 - (2.0 / n as f64).powf(1.0 - theta)) / (1.0 - zeta_2 / zeta_n);
+        let cdf = if theta >= 1.0 {
+            (1..=n)
+                .scan(0.0, |sum, i| {
+                    *sum += 1.0 / (i as f64).powf(theta) / zeta_n;
+                    Some(*sum)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self { n, theta, alpha, zeta_n, eta, cdf }
+    }
+
+    fn sample(&self, u: f64) -> u64 {
+        if !self.cdf.is_empty() {
+            let rank = self.cdf.partition_point(|&p| p <= u) as u64;
+            return rank.min(self.n - 1);
+        }
+        let uz = u * self.zeta_n;
+        if uz < 1.0 {
+            return 0;
+        }
+        if uz < 1.0 + 0.5f64.powf(self.theta) {
+            return 1.min(self.n - 1);
+        }
+        let rank = (self.n as f64 * (self.eta * u - self.eta + 1.0).powf(self.alpha)) as u64;
+        rank.min(self.n - 1)
+    }
+}
+
+/// Small, fast, seedable PRNG — enough for workload generation without
+/// pulling in a dependency.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page_counts(spec: &WorkloadSpec) -> Vec<u64> {
+        let mut counts = vec![0u64; spec.record_count as usize + 1];
+        for op in spec.operations() {
+            let page_id = match op {
+                WorkloadOp::Read { page_id } | WorkloadOp::Update { page_id, .. } => page_id,
+            };
+            counts[page_id as usize] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_presets_and_mix() {
+        assert!(WorkloadSpec::preset("x").is_none());
+        let spec = WorkloadSpec::preset("B").unwrap();
+        let reads = spec
+            .operations()
+            .filter(|op| matches!(op, WorkloadOp::Read { .. }))
+            .count();
+        assert_eq!(spec.operations().count(), 1000);
+        assert!((900..=990).contains(&reads), "reads={reads}");
+
+        let read_only = WorkloadSpec::preset("c").unwrap();
+        assert!(read_only.operations().all(|op| matches!(op, WorkloadOp::Read { .. })));
+    }
+
+    #[test]
+    fn test_deterministic_for_seed() {
+        let spec = WorkloadSpec::default();
+        let a: Vec<WorkloadOp> = spec.operations().collect();
+        let b: Vec<WorkloadOp> = spec.operations().collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_zipfian_skews_towards_low_pages() {
+        let zipf = WorkloadSpec {
+            operation_count: 10_000,
+            ..WorkloadSpec::default()
+        };
+        let counts = page_counts(&zipf);
+        assert_eq!(counts[0], 0, "page IDs start at 1");
+        assert!(counts[1] > counts[50] * 5, "page 1={} page 50={}", counts[1], counts[50]);
+
+        let uniform = WorkloadSpec {
+            distribution: KeyDistribution::Uniform,
+            ..zipf
+        };
+        let counts = page_counts(&uniform);
+        let max = *counts[1..].iter().max().unwrap();
+        let min = *counts[1..].iter().min().unwrap();
+        assert!(max < min * 3, "uniform spread too wide: {min}..{max}");
+    }
+
+    #[test]
+    fn test_page_sampler_range_and_high_skew() {
+        let mut uniform = PageSampler::new(KeyDistribution::Uniform, 50, 59, 7);
+        assert!((0..1000).map(|_| uniform.next_page()).all(|p| (50..=59).contains(&p)));
+
+        // theta above 1 is outside Gray's method and uses the exact table
+        let mut zipf = PageSampler::new(KeyDistribution::Zipfian { theta: 1.1 }, 11, 110, 7);
+        let mut counts = vec![0u64; 100];
+        for _ in 0..10_000 {
+            let page = zipf.next_page();
+            assert!((11..=110).contains(&page), "page {page} out of range");
+            counts[(page - 11) as usize] += 1;
+        }
+        assert!(counts[0] > counts[1] && counts[1] > counts[9], "{:?}", &counts[..10]);
+        assert!(counts[0] > counts[99] * 50, "first={} last={}", counts[0], counts[99]);
+    }
+
+    #[test]
+    fn test_op_interval() {
+        let spec = WorkloadSpec {
+            target_ops_per_sec: Some(500),
+            ..WorkloadSpec::default()
+        };
+        assert_eq!(spec.op_interval(), Some(Duration::from_millis(2)));
+        assert_eq!(WorkloadSpec::default().op_interval(), None);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod materialize;
 pub mod page_cache;
+pub mod store;
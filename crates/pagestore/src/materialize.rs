@@ -1,11 +1,17 @@
-use mini_aurora_common::{empty_page, Page, PageId, RedoRecord, StorageError, PAGE_SIZE};
+use mini_aurora_common::{empty_page, Page, PageId, RedoRecord, StorageError};
+use tracing::instrument;
 
 /// Materialize a page by replaying a chain of redo records onto a zeroed page.
 ///
 /// Records must be in LSN order (oldest first). Each record writes its `data`
-/// at the specified `offset` within the page.
-pub fn materialize_page(page_id: PageId, records: &[RedoRecord]) -> Result<Page, StorageError> {
-    let mut page = empty_page();
+/// at the specified `offset` within a page of `page_size` bytes.
+#[instrument(level = "trace", skip(records), fields(records = records.len()))]
+pub fn materialize_page(
+    page_id: PageId,
+    records: &[RedoRecord],
+    page_size: usize,
+) -> Result<Page, StorageError> {
+    let mut page = empty_page(page_size);
 
     for record in records {
         debug_assert_eq!(record.page_id, page_id);
@@ -15,12 +21,29 @@ pub fn materialize_page(page_id: PageId, records: &[RedoRecord]) -> Result<Page,
     Ok(page)
 }
 
+/// Apply `records` to a copy of `base`, an image of the same page at the
+/// version just before the first of them. Gives the same image as replaying
+/// the whole chain, from a fraction of the records.
+pub fn roll_forward(base: &[u8], records: &[RedoRecord]) -> Result<Page, StorageError> {
+    let mut page: Page = base.into();
+    for record in records {
+        apply_redo(&mut page, record)?;
+    }
+    Ok(page)
+}
+
+/// CRC32 of a page image, kept beside it so a later read can tell the image
+/// hasn't changed since it was materialized.
+pub fn page_checksum(page: &[u8]) -> u32 {
+    crc32fast::hash(page)
+}
+
 /// Apply a single redo record to a page image.
-fn apply_redo(page: &mut Page, record: &RedoRecord) -> Result<(), StorageError> {
+fn apply_redo(page: &mut [u8], record: &RedoRecord) -> Result<(), StorageError> {
     let start = record.offset as usize;
     let end = start + record.data.len();
 
-    if end > PAGE_SIZE {
+    if end > page.len() {
         return Err(StorageError::PageOverflow {
             offset: record.offset,
             len: record.data.len(),
@@ -34,23 +57,25 @@ fn apply_redo(page: &mut Page, record: &RedoRecord) -> Result<(), StorageError>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use mini_aurora_common::PAGE_SIZE;
 
     fn make_redo(lsn: u64, page_id: PageId, offset: u16, data: Vec<u8>, prev_lsn: u64) -> RedoRecord {
         RedoRecord {
             lsn,
             page_id,
             offset,
-            data,
+            data: data.into(),
             prev_lsn,
             mtr_id: 1,
             is_mtr_end: true,
+            timestamp: 0,
         }
     }
 
     #[test]
     fn test_single_record() {
         let records = vec![make_redo(1, 1, 0, vec![0xAA, 0xBB, 0xCC], 0)];
-        let page = materialize_page(1, &records).unwrap();
+        let page = materialize_page(1, &records, PAGE_SIZE).unwrap();
         assert_eq!(page[0], 0xAA);
         assert_eq!(page[1], 0xBB);
         assert_eq!(page[2], 0xCC);
@@ -64,7 +89,7 @@ mod tests {
             make_redo(2, 1, 4, vec![0x33, 0x44], 1),
             make_redo(3, 1, 0, vec![0xFF], 2), // overwrites first byte
         ];
-        let page = materialize_page(1, &records).unwrap();
+        let page = materialize_page(1, &records, PAGE_SIZE).unwrap();
         assert_eq!(page[0], 0xFF); // overwritten
         assert_eq!(page[1], 0x22); // from first record
         assert_eq!(page[4], 0x33);
@@ -74,7 +99,7 @@ mod tests {
     #[test]
     fn test_write_at_end_of_page() {
         let records = vec![make_redo(1, 1, (PAGE_SIZE - 2) as u16, vec![0xEE, 0xFF], 0)];
-        let page = materialize_page(1, &records).unwrap();
+        let page = materialize_page(1, &records, PAGE_SIZE).unwrap();
         assert_eq!(page[PAGE_SIZE - 2], 0xEE);
         assert_eq!(page[PAGE_SIZE - 1], 0xFF);
     }
@@ -82,13 +107,40 @@ mod tests {
     #[test]
     fn test_overflow_rejected() {
         let records = vec![make_redo(1, 1, (PAGE_SIZE - 1) as u16, vec![0xAA, 0xBB], 0)];
-        let result = materialize_page(1, &records);
+        let result = materialize_page(1, &records, PAGE_SIZE);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_empty_chain_returns_zeroed_page() {
-        let page = materialize_page(1, &[]).unwrap();
-        assert_eq!(page, empty_page());
+        let page = materialize_page(1, &[], PAGE_SIZE).unwrap();
+        assert_eq!(page, empty_page(PAGE_SIZE));
+    }
+
+    #[test]
+    fn test_page_size_bounds_records() {
+        let records = vec![make_redo(1, 1, 4094, vec![0xEE, 0xFF], 0)];
+        let page = materialize_page(1, &records, 4096).unwrap();
+        assert_eq!((page.len(), page[4095]), (4096, 0xFF));
+
+        let records = vec![make_redo(1, 1, 4095, vec![0xEE, 0xFF], 0)];
+        assert!(materialize_page(1, &records, 4096).is_err());
+        assert!(materialize_page(1, &records, 16384).is_ok());
+    }
+
+    #[test]
+    fn test_roll_forward_matches_full_replay() {
+        let records = vec![
+            make_redo(1, 1, 0, vec![0x11, 0x22], 0),
+            make_redo(2, 1, 4, vec![0x33, 0x44], 1),
+            make_redo(3, 1, 1, vec![0xFF], 2),
+        ];
+        let base = materialize_page(1, &records[..1], PAGE_SIZE).unwrap();
+        let rolled = roll_forward(&base, &records[1..]).unwrap();
+        assert_eq!(rolled, materialize_page(1, &records, PAGE_SIZE).unwrap());
+        assert_eq!(base[1], 0x22, "the base image is left alone");
+
+        let overflow = make_redo(4, 1, (PAGE_SIZE - 1) as u16, vec![0xAA, 0xBB], 3);
+        assert!(roll_forward(&rolled, &[overflow]).is_err());
     }
 }
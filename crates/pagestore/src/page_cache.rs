@@ -1,47 +1,355 @@
-use mini_aurora_common::{Lsn, Page, PageId};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use mini_aurora_common::{Lsn, PageId, PageRef, StorageError, PAGE_SIZE};
+use moka::notification::RemovalCause;
+use moka::policy::EvictionPolicy;
 use moka::sync::Cache;
 
-/// Key for the page cache: (page_id, read_point_lsn).
-/// Different read points may see different versions of the same page.
+use crate::materialize::page_checksum;
+
+/// Key for the page cache: (page_id, page_version_lsn).
+///
+/// The version is the LSN of the newest redo record applied to the image, not
+/// the read point it was requested at. Every read point between one record of
+/// the page and the next sees the same image, so they all share one entry.
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub struct CacheKey {
     pub page_id: PageId,
-    pub lsn: Lsn,
+    pub version: Lsn,
 }
 
-/// LRU page cache backed by moka.
+/// Cached versions per page, kept in step with the moka cache.
+type VersionIndex = Arc<Mutex<HashMap<PageId, BTreeSet<Lsn>>>>;
+
+/// Checksums remembered per cached image, counting ones since evicted.
+const CHECKSUMS_PER_ENTRY: u64 = 4;
+/// Rough size of one remembered checksum: key, CRC and moka's bookkeeping.
+const CHECKSUM_BYTES: u64 = 64;
+
+/// Which entry the cache gives up when it is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Admit a new image only if it is likely to be read more often than the
+    /// one it would evict, so a one-off scan cannot flush the hot set.
+    #[default]
+    TinyLfu,
+    /// Admit every image and evict the least recently used.
+    Lru,
+}
+
+impl CachePolicy {
+    pub fn name(self) -> &'static str {
+        match self {
+            CachePolicy::TinyLfu => "tinylfu",
+            CachePolicy::Lru => "lru",
+        }
+    }
+}
+
+impl std::str::FromStr for CachePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tinylfu" => Ok(CachePolicy::TinyLfu),
+            "lru" => Ok(CachePolicy::Lru),
+            _ => Err(format!("unknown cache policy '{s}' (expected tinylfu or lru)")),
+        }
+    }
+}
+
+/// How the cache's capacity is counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheCapacity {
+    /// At most this many images, whatever their size.
+    Entries(u64),
+    /// At most this many bytes of images, each weighed by its length.
+    Bytes(u64),
+}
+
+/// Capacity, eviction policy and expiry of a `PageCache`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageCacheConfig {
+    pub capacity: CacheCapacity,
+    pub policy: CachePolicy,
+    /// Drop an image this long after it was inserted, even if still read.
+    pub ttl: Option<Duration>,
+}
+
+impl Default for PageCacheConfig {
+    fn default() -> Self {
+        Self { capacity: CacheCapacity::Entries(1024), policy: CachePolicy::default(), ttl: None }
+    }
+}
+
+#[derive(Clone)]
+struct CachedImage {
+    page: PageRef,
+    checksum: u32,
+}
+
+/// Page cache backed by moka, TinyLFU by default.
+///
+/// Pages are stored as shared `PageRef`s, so a hit hands out a refcount bump
+/// rather than a copy of the page. Each image is stored with its CRC32 and checked
+/// on every hit. The CRC of a version outlives its image for a while, so a
+/// later replay of the same version can be checked against it too.
 pub struct PageCache {
-    cache: Cache<CacheKey, Box<Page>>,
+    cache: Cache<CacheKey, CachedImage>,
+    versions: VersionIndex,
+    checksums: Cache<CacheKey, u32>,
+    /// Bytes per cached image, for `resident_bytes`.
+    page_size: usize,
+    config: PageCacheConfig,
+    /// Images dropped to make room, by moka or by `evict`.
+    evictions: Arc<AtomicU64>,
+    /// Images dropped because their TTL ran out.
+    expirations: Arc<AtomicU64>,
 }
 
 impl PageCache {
     /// Create a new page cache with the given maximum number of entries.
     pub fn new(max_entries: u64) -> Self {
-        Self {
-            cache: Cache::new(max_entries),
+        let capacity = CacheCapacity::Entries(max_entries);
+        Self::with_config(PageCacheConfig { capacity, ..Default::default() })
+    }
+
+    /// Create a page cache with the given capacity, policy and TTL.
+    pub fn with_config(config: PageCacheConfig) -> Self {
+        let versions: VersionIndex = Arc::default();
+        let evictions = Arc::new(AtomicU64::new(0));
+        let expirations = Arc::new(AtomicU64::new(0));
+        let listener_versions = Arc::clone(&versions);
+        let (listener_evictions, listener_expirations) = (Arc::clone(&evictions), Arc::clone(&expirations));
+        let policy = match config.policy {
+            CachePolicy::TinyLfu => EvictionPolicy::tiny_lfu(),
+            CachePolicy::Lru => EvictionPolicy::lru(),
+        };
+        let mut builder = Cache::builder().eviction_policy(policy);
+        // Remembered checksums are counted in entries either way
+        let max_entries = match config.capacity {
+            CacheCapacity::Entries(n) => {
+                builder = builder.max_capacity(n);
+                n
+            }
+            CacheCapacity::Bytes(n) => {
+                builder = builder.max_capacity(n).weigher(|_key: &CacheKey, image: &CachedImage| {
+                    u32::try_from(image.page.len()).unwrap_or(u32::MAX)
+                });
+                n / PAGE_SIZE as u64
+            }
+        };
+        if let Some(ttl) = config.ttl {
+            builder = builder.time_to_live(ttl);
         }
+        let cache = builder
+            .eviction_listener(move |key: Arc<CacheKey>, _image, cause| {
+                match cause {
+                    RemovalCause::Replaced => return,
+                    RemovalCause::Size => listener_evictions.fetch_add(1, Ordering::Relaxed),
+                    RemovalCause::Expired => listener_expirations.fetch_add(1, Ordering::Relaxed),
+                    RemovalCause::Explicit => 0,
+                };
+                let mut versions = listener_versions.lock().unwrap();
+                if let Some(set) = versions.get_mut(&key.page_id) {
+                    set.remove(&key.version);
+                    if set.is_empty() {
+                        versions.remove(&key.page_id);
+                    }
+                }
+            })
+            .build();
+        let checksums = Cache::new(max_entries.max(1) * CHECKSUMS_PER_ENTRY);
+        Self { cache, versions, checksums, page_size: PAGE_SIZE, config, evictions, expirations }
+    }
+
+    pub fn config(&self) -> &PageCacheConfig {
+        &self.config
     }
 
-    /// Try to get a cached page.
-    pub fn get(&self, page_id: PageId, lsn: Lsn) -> Option<Page> {
-        let key = CacheKey { page_id, lsn };
-        self.cache.get(&key).map(|boxed| *boxed)
+    /// Hold images of `page_size` bytes rather than `PAGE_SIZE`.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size;
+        self
     }
 
-    /// Insert a page into the cache.
-    pub fn insert(&self, page_id: PageId, lsn: Lsn, page: Page) {
-        let key = CacheKey { page_id, lsn };
-        self.cache.insert(key, Box::new(page));
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Get the cached image of an exact page version.
+    ///
+    /// An image that no longer matches its checksum is dropped and reported
+    /// as `Corruption`; the next read materializes it again.
+    pub fn get(&self, page_id: PageId, version: Lsn) -> Result<Option<PageRef>, StorageError> {
+        let key = CacheKey { page_id, version };
+        let Some(image) = self.cache.get(&key) else {
+            return Ok(None);
+        };
+        let actual = page_checksum(&image.page);
+        if actual != image.checksum {
+            self.cache.invalidate(&key);
+            return Err(StorageError::Corruption(format!(
+                "cached image of page {page_id} at LSN {version} has checksum {actual:#010x}, \
+                 expected {:#010x}",
+                image.checksum
+            )));
+        }
+        Ok(Some(image.page))
+    }
+
+    /// Check a freshly replayed image against the checksum recorded when the
+    /// same version was last materialized, if one is still remembered.
+    pub fn verify_replay(&self, page_id: PageId, version: Lsn, page: &[u8]) -> Result<(), StorageError> {
+        let Some(expected) = self.checksums.get(&CacheKey { page_id, version }) else {
+            return Ok(());
+        };
+        let actual = page_checksum(page);
+        if actual != expected {
+            return Err(StorageError::Corruption(format!(
+                "replaying page {page_id} to LSN {version} gave checksum {actual:#010x}, \
+                 expected {expected:#010x} from an earlier replay"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Get the newest cached version of a page at or below `read_point`.
+    ///
+    /// Returns the version LSN alongside the image. The cache only knows what
+    /// it holds: the caller must check against the page index that no newer
+    /// record of the page exists at or below `read_point`.
+    pub fn get_at_or_below(
+        &self,
+        page_id: PageId,
+        read_point: Lsn,
+    ) -> Result<Option<(Lsn, PageRef)>, StorageError> {
+        let candidates: Vec<Lsn> = {
+            let versions = self.versions.lock().unwrap();
+            let Some(set) = versions.get(&page_id) else {
+                return Ok(None);
+            };
+            set.range(..=read_point).rev().copied().collect()
+        };
+        // An entry can be evicted before the listener prunes the index.
+        for version in candidates {
+            if let Some(page) = self.get(page_id, version)? {
+                return Ok(Some((version, page)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Insert a page image under the LSN of the newest record applied to it,
+    /// with its checksum.
+    pub fn insert(&self, page_id: PageId, version: Lsn, page: impl Into<PageRef>) {
+        let page = page.into();
+        let key = CacheKey { page_id, version };
+        let checksum = page_checksum(&page);
+        self.versions
+            .lock()
+            .unwrap()
+            .entry(page_id)
+            .or_default()
+            .insert(version);
+        self.checksums.insert(key, checksum);
+        self.cache.insert(key, CachedImage { page, checksum });
     }
 
     /// Number of entries currently in the cache.
     pub fn len(&self) -> u64 {
+        // The count lags inserts until moka's pending maintenance runs
+        self.cache.run_pending_tasks();
         self.cache.entry_count()
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Images dropped to make room since the cache was created.
+    pub fn evictions(&self) -> u64 {
+        self.cache.run_pending_tasks();
+        self.evictions.load(Ordering::Relaxed)
+    }
+
+    /// Images dropped when their TTL ran out since the cache was created.
+    pub fn expirations(&self) -> u64 {
+        self.cache.run_pending_tasks();
+        self.expirations.load(Ordering::Relaxed)
+    }
+
+    /// Keys of the cached images, by page and then version.
+    pub fn keys(&self) -> Vec<CacheKey> {
+        self.cache.run_pending_tasks();
+        let versions = self.versions.lock().unwrap();
+        let mut keys: Vec<CacheKey> = versions
+            .iter()
+            .flat_map(|(&page_id, set)| set.iter().map(move |&version| CacheKey { page_id, version }))
+            .collect();
+        keys.sort_unstable_by_key(|key| (key.page_id, key.version));
+        keys
+    }
+
+    /// Bytes held by cached images and remembered checksums.
+    pub fn resident_bytes(&self) -> u64 {
+        self.checksums.run_pending_tasks();
+        self.len() * self.page_size as u64 + self.checksums.entry_count() * CHECKSUM_BYTES
+    }
+
+    /// Flip a byte of the newest cached image of a page, keeping its stored
+    /// checksum, as bit-rot would. For fault injection; returns the damaged
+    /// version.
+    pub fn corrupt(&self, page_id: PageId) -> Option<Lsn> {
+        let versions: Vec<Lsn> = {
+            let versions = self.versions.lock().unwrap();
+            versions.get(&page_id)?.iter().rev().copied().collect()
+        };
+        versions.into_iter().find(|&version| {
+            let key = CacheKey { page_id, version };
+            let Some(mut image) = self.cache.get(&key) else {
+                return false;
+            };
+            Arc::make_mut(&mut image.page)[0] ^= 0xFF;
+            self.cache.insert(key, image);
+            true
+        })
+    }
+
+    /// Drop every entry. Returns how many there were.
+    pub fn clear(&self) -> u64 {
+        let dropped = self.len();
+        self.cache.invalidate_all();
+        self.cache.run_pending_tasks();
+        self.versions.lock().unwrap().clear();
+        dropped
+    }
+
+    /// Drop up to `n` entries to free memory, older versions of a page
+    /// before the newest, which later reads are likelier to want. Returns
+    /// how many were dropped.
+    pub fn evict(&self, n: u64) -> u64 {
+        let victims: Vec<CacheKey> = {
+            let versions = self.versions.lock().unwrap();
+            let (mut older, mut newest) = (Vec::new(), Vec::new());
+            for (&page_id, set) in versions.iter() {
+                let mut lsns = set.iter().rev();
+                newest.extend(lsns.next().map(|&version| CacheKey { page_id, version }));
+                older.extend(lsns.map(|&version| CacheKey { page_id, version }));
+            }
+            older.into_iter().chain(newest).take(n as usize).collect()
+        };
+        // The listener prunes the version index, so it must not be held here
+        for key in &victims {
+            self.cache.invalidate(key);
+        }
+        self.cache.run_pending_tasks();
+        self.evictions.fetch_add(victims.len() as u64, Ordering::Relaxed);
+        victims.len() as u64
+    }
 }
 
 #[cfg(test)]
@@ -52,47 +360,178 @@ mod tests {
     #[test]
     fn test_cache_miss() {
         let cache = PageCache::new(100);
-        assert!(cache.get(1, 1).is_none());
+        assert!(cache.get(1, 1).unwrap().is_none());
     }
 
     #[test]
     fn test_cache_hit() {
         let cache = PageCache::new(100);
-        let mut page = empty_page();
+        let mut page = empty_page(PAGE_SIZE);
         page[0] = 0xAB;
 
-        cache.insert(1, 5, page);
+        cache.insert(1, 5, page.clone());
 
-        let retrieved = cache.get(1, 5).unwrap();
+        let retrieved = cache.get(1, 5).unwrap().unwrap();
         assert_eq!(retrieved[0], 0xAB);
     }
 
+    #[test]
+    fn test_hit_shares_image() {
+        let cache = PageCache::new(100);
+        let page: PageRef = empty_page(PAGE_SIZE).into();
+        cache.insert(1, 5, page.clone());
+
+        let retrieved = cache.get(1, 5).unwrap().unwrap();
+        assert!(std::sync::Arc::ptr_eq(&page, &retrieved));
+    }
+
     #[test]
     fn test_different_lsns_are_separate() {
         let cache = PageCache::new(100);
-        let mut page_v1 = empty_page();
+        let mut page_v1 = empty_page(PAGE_SIZE);
         page_v1[0] = 0x01;
-        let mut page_v2 = empty_page();
+        let mut page_v2 = empty_page(PAGE_SIZE);
         page_v2[0] = 0x02;
 
         cache.insert(1, 5, page_v1);
         cache.insert(1, 10, page_v2);
 
-        assert_eq!(cache.get(1, 5).unwrap()[0], 0x01);
-        assert_eq!(cache.get(1, 10).unwrap()[0], 0x02);
+        assert_eq!(cache.get(1, 5).unwrap().unwrap()[0], 0x01);
+        assert_eq!(cache.get(1, 10).unwrap().unwrap()[0], 0x02);
     }
 
     #[test]
     fn test_eviction() {
         let cache = PageCache::new(2);
-        let page = empty_page();
+        let page = empty_page(PAGE_SIZE);
 
-        cache.insert(1, 1, page);
-        cache.insert(2, 1, page);
-        cache.insert(3, 1, page); // should evict one
+        cache.insert(1, 1, page.clone());
+        cache.insert(2, 1, page.clone());
+        cache.insert(3, 1, page.clone()); // should evict one
 
         // moka eviction is async; we just verify it doesn't panic
         // and the newest entries are accessible
-        assert!(cache.get(3, 1).is_some());
+        assert!(cache.get(3, 1).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_size_evictions_are_counted() {
+        let cache = PageCache::new(4);
+        for page_id in 0..16 {
+            cache.insert(page_id, 1, empty_page(PAGE_SIZE));
+        }
+        assert!(cache.len() <= 4);
+        assert!(cache.evictions() >= 12 - 4, "{}", cache.evictions());
+        assert_eq!(cache.expirations(), 0);
+
+        let before = cache.evictions();
+        assert_eq!(cache.evict(2), 2);
+        assert_eq!(cache.evictions(), before + 2);
+    }
+
+    #[test]
+    fn test_byte_capacity_weighs_images() {
+        let config = PageCacheConfig { capacity: CacheCapacity::Bytes(4 * 4096), ..Default::default() };
+        let cache = PageCache::with_config(config).with_page_size(4096);
+        for page_id in 0..8 {
+            cache.insert(page_id, 1, empty_page(4096));
+        }
+        assert!(cache.len() <= 4, "{}", cache.len());
+    }
+
+    #[test]
+    fn test_ttl_expires_entries() {
+        let config = PageCacheConfig {
+            policy: CachePolicy::Lru,
+            ttl: Some(std::time::Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let cache = PageCache::with_config(config);
+        cache.insert(1, 5, empty_page(PAGE_SIZE));
+        std::thread::sleep(std::time::Duration::from_millis(60));
+
+        assert!(cache.get(1, 5).unwrap().is_none());
+        assert!(cache.get_at_or_below(1, 100).unwrap().is_none());
+        assert_eq!(cache.expirations(), 1);
+        assert_eq!(cache.evictions(), 0);
+    }
+
+    #[test]
+    fn test_policy_parses_by_name() {
+        for policy in [CachePolicy::TinyLfu, CachePolicy::Lru] {
+            assert_eq!(policy.name().parse::<CachePolicy>(), Ok(policy));
+        }
+        assert!("arc".parse::<CachePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_evict_drops_older_versions_first() {
+        let cache = PageCache::new(100);
+        let page = empty_page(PAGE_SIZE);
+        cache.insert(1, 3, page.clone());
+        cache.insert(1, 8, page.clone());
+        cache.insert(2, 5, page.clone());
+
+        assert_eq!(cache.evict(1), 1);
+        assert!(cache.get(1, 3).unwrap().is_none());
+        assert!(cache.get(1, 8).unwrap().is_some() && cache.get(2, 5).unwrap().is_some());
+        assert_eq!(cache.evict(10), 2);
+        assert!(cache.is_empty());
+        assert!(cache.get_at_or_below(1, 100).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_get_at_or_below_picks_newest_version() {
+        let cache = PageCache::new(100);
+        let mut page_v3 = empty_page(PAGE_SIZE);
+        page_v3[0] = 0x03;
+        let mut page_v8 = empty_page(PAGE_SIZE);
+        page_v8[0] = 0x08;
+
+        cache.insert(1, 3, page_v3);
+        cache.insert(1, 8, page_v8);
+
+        assert!(cache.get_at_or_below(1, 2).unwrap().is_none());
+        let (version, page) = cache.get_at_or_below(1, 7).unwrap().unwrap();
+        assert_eq!((version, page[0]), (3, 0x03));
+        let (version, page) = cache.get_at_or_below(1, 100).unwrap().unwrap();
+        assert_eq!((version, page[0]), (8, 0x08));
+        assert!(cache.get_at_or_below(2, 100).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_corrupt_image_fails_checksum_and_is_dropped() {
+        let cache = PageCache::new(100);
+        cache.insert(1, 5, empty_page(PAGE_SIZE));
+
+        assert_eq!(cache.corrupt(1), Some(5));
+        let err = cache.get(1, 5).unwrap_err();
+        assert!(matches!(err, StorageError::Corruption(_)));
+        assert!(err.to_string().contains("page 1 at LSN 5"), "{err}");
+        assert!(cache.get(1, 5).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_resident_bytes_follow_the_page_size() {
+        let small = PageCache::new(100).with_page_size(4096);
+        let large = PageCache::new(100).with_page_size(16384);
+        small.insert(1, 5, empty_page(4096));
+        large.insert(1, 5, empty_page(16384));
+        assert_eq!(small.resident_bytes(), 4096 + CHECKSUM_BYTES);
+        assert_eq!(large.resident_bytes(), 16384 + CHECKSUM_BYTES);
+    }
+
+    #[test]
+    fn test_verify_replay_against_remembered_checksum() {
+        let cache = PageCache::new(100);
+        let mut page = empty_page(PAGE_SIZE);
+        cache.insert(1, 5, page.clone());
+        cache.clear();
+
+        // The image is gone but its checksum is still known
+        assert!(cache.verify_replay(1, 5, &page).is_ok());
+        assert!(cache.verify_replay(2, 5, &page).is_ok());
+        page[10] = 0x01;
+        assert!(matches!(cache.verify_replay(1, 5, &page), Err(StorageError::Corruption(_))));
     }
 }
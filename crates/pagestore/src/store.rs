@@ -0,0 +1,255 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mini_aurora_common::{Lsn, PageId, PageRef, StorageError};
+use tracing::{debug, warn};
+
+use crate::materialize::page_checksum;
+
+/// Bytes before the image in a page file: its CRC32.
+const IMAGE_HEADER_SIZE: usize = 4;
+
+/// Consolidated page images on disk, keyed like the page cache by
+/// (page_id, version LSN), one file each. An image is the page as of its
+/// version, so a read that sees that version needs no WAL access, and a
+/// read of a later one replays only the records after it.
+///
+/// The WAL stays the source of truth: an image can always be rebuilt from
+/// the records, so files are written without an fsync, and one that fails
+/// its checksum is dropped.
+pub struct PageStore {
+    dir: PathBuf,
+    /// Stored versions per page.
+    versions: HashMap<PageId, BTreeSet<Lsn>>,
+    page_size: usize,
+}
+
+impl PageStore {
+    /// Open the store in `dir`, creating it if needed, for images of
+    /// `page_size` bytes. Files of another size, and any left half written
+    /// by a crash, are removed.
+    pub fn open(dir: &Path, page_size: usize) -> Result<Self, StorageError> {
+        fs::create_dir_all(dir).map_err(StorageError::file("create", dir))?;
+        let mut versions: HashMap<PageId, BTreeSet<Lsn>> = HashMap::new();
+        for entry in fs::read_dir(dir).map_err(StorageError::file("read", dir))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            match parse_image_filename(&name) {
+                Some(key) if entry.metadata()?.len() == (IMAGE_HEADER_SIZE + page_size) as u64 => {
+                    versions.entry(key.0).or_default().insert(key.1);
+                }
+                _ => fs::remove_file(entry.path())?,
+            }
+        }
+        Ok(Self { dir: dir.to_path_buf(), versions, page_size })
+    }
+
+    fn path(&self, page_id: PageId, version: Lsn) -> PathBuf {
+        self.dir.join(image_filename(page_id, version))
+    }
+
+    /// Whether the image of `page_id` at exactly `version` is stored.
+    pub fn contains(&self, page_id: PageId, version: Lsn) -> bool {
+        self.versions.get(&page_id).is_some_and(|set| set.contains(&version))
+    }
+
+    /// The newest stored image of `page_id` at or below `version`, with its
+    /// version. Like `PageCache::get_at_or_below`, the caller must replay
+    /// any records of the page between the two.
+    ///
+    /// An image that no longer matches its checksum is removed and reported
+    /// as `Corruption`; the next read replays the chain instead.
+    pub fn get_at_or_below(
+        &mut self,
+        page_id: PageId,
+        version: Lsn,
+    ) -> Result<Option<(Lsn, PageRef)>, StorageError> {
+        let newest = self.versions.get(&page_id).and_then(|set| set.range(..=version).next_back());
+        let Some(&stored) = newest else {
+            return Ok(None);
+        };
+        let path = self.path(page_id, stored);
+        let bytes = fs::read(&path).map_err(StorageError::file("read", &path))?;
+        let (header, image) = bytes.split_at(IMAGE_HEADER_SIZE.min(bytes.len()));
+        let expected = header.try_into().map(u32::from_le_bytes).unwrap_or_default();
+        let actual = page_checksum(image);
+        if image.len() != self.page_size || actual != expected {
+            self.remove(page_id, stored)?;
+            return Err(StorageError::Corruption(format!(
+                "stored image of page {page_id} at LSN {stored} has checksum {actual:#010x}, \
+                 expected {expected:#010x}"
+            )));
+        }
+        Ok(Some((stored, PageRef::from(image))))
+    }
+
+    /// Store `page` as the image of `page_id` at `version`, replacing any.
+    pub fn put(&mut self, page_id: PageId, version: Lsn, page: &[u8]) -> Result<(), StorageError> {
+        debug_assert_eq!(page.len(), self.page_size);
+        let path = self.path(page_id, version);
+        let tmp_path = path.with_extension("tmp");
+        let mut bytes = Vec::with_capacity(IMAGE_HEADER_SIZE + page.len());
+        bytes.extend_from_slice(&page_checksum(page).to_le_bytes());
+        bytes.extend_from_slice(page);
+        fs::write(&tmp_path, &bytes).map_err(StorageError::file("write", &tmp_path))?;
+        fs::rename(&tmp_path, &path).map_err(StorageError::file("rename", &tmp_path))?;
+        self.versions.entry(page_id).or_default().insert(version);
+        Ok(())
+    }
+
+    fn remove(&mut self, page_id: PageId, version: Lsn) -> Result<(), StorageError> {
+        if let Some(set) = self.versions.get_mut(&page_id) {
+            set.remove(&version);
+            if set.is_empty() {
+                self.versions.remove(&page_id);
+            }
+        }
+        let path = self.path(page_id, version);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(StorageError::file("remove", &path)(e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove the images of versions past `lsn`, which recovery cut from
+    /// the WAL: appends would reuse their LSNs. Returns how many went.
+    pub fn retain_through(&mut self, lsn: Lsn) -> Result<usize, StorageError> {
+        let past: Vec<(PageId, Lsn)> = self.keys().into_iter().filter(|&(_, version)| version > lsn).collect();
+        for &(page_id, version) in &past {
+            self.remove(page_id, version)?;
+        }
+        if !past.is_empty() {
+            warn!(images = past.len(), lsn, "page images past the recovered VDL removed");
+        }
+        Ok(past.len())
+    }
+
+    /// Remove the images no read at or past `lsn` starts from: each page
+    /// keeps its newest image at or below `lsn` and those after. Returns
+    /// how many went.
+    pub fn truncate_before(&mut self, lsn: Lsn) -> Result<usize, StorageError> {
+        let mut older = Vec::new();
+        for (&page_id, set) in &self.versions {
+            let below: Vec<Lsn> = set.range(..=lsn).copied().collect();
+            older.extend(below.iter().take(below.len().saturating_sub(1)).map(|&version| (page_id, version)));
+        }
+        for &(page_id, version) in &older {
+            self.remove(page_id, version)?;
+        }
+        debug!(images = older.len(), lsn, "page images truncated");
+        Ok(older.len())
+    }
+
+    /// Every stored (page_id, version), by page then version.
+    pub fn keys(&self) -> Vec<(PageId, Lsn)> {
+        let mut keys: Vec<(PageId, Lsn)> = self
+            .versions
+            .iter()
+            .flat_map(|(&page_id, set)| set.iter().map(move |&version| (page_id, version)))
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Images stored.
+    pub fn len(&self) -> usize {
+        self.versions.values().map(BTreeSet::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+
+    /// Flip a byte of the newest stored image of a page without updating
+    /// its checksum, as bit-rot would. Returns the damaged version, if the
+    /// page has an image.
+    pub fn corrupt(&self, page_id: PageId) -> Option<Lsn> {
+        let &version = self.versions.get(&page_id)?.last()?;
+        let path = self.path(page_id, version);
+        let mut bytes = fs::read(&path).ok()?;
+        bytes[IMAGE_HEADER_SIZE] ^= 0xFF;
+        fs::write(&path, bytes).ok()?;
+        Some(version)
+    }
+}
+
+/// Page store of a single-file WAL, next to it.
+pub fn store_path(wal_path: &Path) -> PathBuf {
+    let mut name = wal_path.as_os_str().to_owned();
+    name.push(".pages");
+    PathBuf::from(name)
+}
+
+fn image_filename(page_id: PageId, version: Lsn) -> String {
+    format!("pg{page_id:010}_{version:020}.page")
+}
+
+/// The (page_id, version) a page file is named for.
+fn parse_image_filename(name: &str) -> Option<(PageId, Lsn)> {
+    let (page_id, version) = name.strip_prefix("pg")?.strip_suffix(".page")?.split_once('_')?;
+    Some((page_id.parse().ok()?, version.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const PAGE: usize = 64;
+
+    fn image(byte: u8) -> Vec<u8> {
+        vec![byte; PAGE]
+    }
+
+    #[test]
+    fn test_images_are_found_at_or_below_a_version() {
+        let dir = TempDir::new().unwrap();
+        let mut store = PageStore::open(dir.path(), PAGE).unwrap();
+        store.put(1, 3, &image(3)).unwrap();
+        store.put(1, 7, &image(7)).unwrap();
+        store.put(2, 5, &image(5)).unwrap();
+
+        assert!(store.get_at_or_below(1, 2).unwrap().is_none());
+        let (version, page) = store.get_at_or_below(1, 6).unwrap().unwrap();
+        assert_eq!((version, &page[..]), (3, &image(3)[..]));
+        assert_eq!(store.get_at_or_below(1, 9).unwrap().unwrap().0, 7);
+
+        // The index is rebuilt from the filenames; stray files go
+        fs::write(dir.path().join("pg0000000001_00000000000000000009.tmp"), b"torn").unwrap();
+        drop(store);
+        let store = PageStore::open(dir.path(), PAGE).unwrap();
+        assert_eq!(store.keys(), [(1, 3), (1, 7), (2, 5)]);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_recovery_and_truncation_remove_images() {
+        let dir = TempDir::new().unwrap();
+        let mut store = PageStore::open(dir.path(), PAGE).unwrap();
+        for version in [2, 4, 6, 8] {
+            store.put(1, version, &image(version as u8)).unwrap();
+        }
+        store.put(2, 1, &image(1)).unwrap();
+
+        assert_eq!(store.retain_through(7).unwrap(), 1);
+        // pg1 keeps the image a read at 5 starts from, pg2 its only one
+        assert_eq!(store.truncate_before(5).unwrap(), 1);
+        assert_eq!(store.keys(), [(1, 4), (1, 6), (2, 1)]);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_damaged_image_is_dropped() {
+        let dir = TempDir::new().unwrap();
+        let mut store = PageStore::open(dir.path(), PAGE).unwrap();
+        store.put(1, 3, &image(3)).unwrap();
+        store.put(1, 7, &image(7)).unwrap();
+        assert_eq!(store.corrupt(1), Some(7));
+
+        let err = store.get_at_or_below(1, 9).unwrap_err();
+        assert!(matches!(err, StorageError::Corruption(_)), "{err}");
+        assert!(!store.contains(1, 7));
+        assert_eq!(store.get_at_or_below(1, 9).unwrap().unwrap().0, 3);
+    }
+}
@@ -0,0 +1,89 @@
+//! Append-path benchmarks for the single-file WAL. With one appender each
+//! batch waits out its own fsync; with several, the append pipeline encodes
+//! and writes batches while another is syncing, and batches that arrive
+//! during an fsync share the next one. The same appends to a WAL in
+//! memory show what the engine costs without the disk.
+//!
+//! Run with `cargo bench -p mini-aurora-storage --bench append_path`.
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mini_aurora_common::{RedoRecord, StorageApi};
+use mini_aurora_storage::engine::StorageEngine;
+use tempfile::TempDir;
+
+/// Appends each appender makes per iteration.
+const APPENDS: u64 = 32;
+
+fn batch(page_id: u64) -> Vec<RedoRecord> {
+    (0..4)
+        .map(|i| RedoRecord {
+            lsn: 0,
+            page_id,
+            offset: i * 64,
+            data: vec![page_id as u8; 64].into(),
+            prev_lsn: 0,
+            mtr_id: page_id,
+            is_mtr_end: i == 3,
+            timestamp: 0,
+        })
+        .collect()
+}
+
+fn bench_concurrent_appends(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread().worker_threads(8).enable_all().build().unwrap();
+    let mut group = c.benchmark_group("append_redo");
+
+    for appenders in [1u64, 4, 8] {
+        let dir = TempDir::new().unwrap();
+        let engine = Arc::new(StorageEngine::open(&dir.path().join("bench.wal")).unwrap());
+        group.throughput(Throughput::Elements(appenders * APPENDS));
+        group.bench_with_input(BenchmarkId::new("appenders", appenders), &appenders, |b, &appenders| {
+            b.to_async(&rt).iter(|| {
+                let engine = Arc::clone(&engine);
+                async move {
+                    let tasks: Vec<_> = (0..appenders)
+                        .map(|page_id| {
+                            let engine = Arc::clone(&engine);
+                            tokio::spawn(async move {
+                                for _ in 0..APPENDS {
+                                    engine.append_redo(batch(page_id + 1)).await.unwrap();
+                                }
+                            })
+                        })
+                        .collect();
+                    for task in tasks {
+                        task.await.unwrap();
+                    }
+                }
+            });
+        });
+        let stats = engine.append_pipeline_stats();
+        println!(
+            "{appenders} appenders: {} batches in {} fsyncs ({:.1} per fsync)",
+            stats.batches,
+            stats.syncs,
+            stats.batches as f64 / stats.syncs.max(1) as f64
+        );
+    }
+    group.finish();
+}
+
+fn bench_in_memory_appends(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let mut group = c.benchmark_group("append_redo_in_memory");
+    let engine = StorageEngine::open_in_memory().unwrap();
+    group.throughput(Throughput::Elements(APPENDS));
+    group.bench_function("appenders/1", |b| {
+        b.to_async(&rt).iter(|| async {
+            for _ in 0..APPENDS {
+                engine.append_redo(batch(1)).await.unwrap();
+            }
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_concurrent_appends, bench_in_memory_appends);
+criterion_main!(benches);
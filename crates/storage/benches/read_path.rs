@@ -0,0 +1,70 @@
+//! Read-path benchmarks: page cache hits should hand out a shared page image
+//! rather than copying 8 KiB per call.
+//!
+//! Run with `cargo bench -p mini-aurora-storage`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mini_aurora_common::{RedoRecord, StorageApi};
+use mini_aurora_storage::engine::StorageEngine;
+use tempfile::TempDir;
+
+fn populated_engine(dir: &TempDir, pages: u64) -> StorageEngine {
+    let engine = StorageEngine::open(&dir.path().join("bench.wal")).unwrap();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        for page_id in 1..=pages {
+            let records = vec![RedoRecord {
+                lsn: 0,
+                page_id,
+                offset: 0,
+                data: vec![page_id as u8; 64].into(),
+                prev_lsn: 0,
+                mtr_id: page_id,
+                is_mtr_end: true,
+                timestamp: 0,
+            }];
+            engine.append_redo(records).await.unwrap();
+        }
+    });
+    engine
+}
+
+fn bench_get_page_cached(c: &mut Criterion) {
+    let dir = TempDir::new().unwrap();
+    let engine = populated_engine(&dir, 16);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let vdl = engine.current_vdl();
+
+    // Warm the page cache
+    rt.block_on(async {
+        for page_id in 1..=16 {
+            engine.get_page(page_id, vdl).await.unwrap();
+        }
+    });
+
+    c.bench_function("get_page_cache_hit", |b| {
+        b.to_async(&rt).iter(|| async {
+            engine.get_page(7, vdl).await.unwrap();
+        });
+    });
+}
+
+fn bench_get_bytes_cached(c: &mut Criterion) {
+    let dir = TempDir::new().unwrap();
+    let engine = populated_engine(&dir, 16);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let vdl = engine.current_vdl();
+
+    rt.block_on(async {
+        engine.get_page(7, vdl).await.unwrap();
+    });
+
+    c.bench_function("get_bytes_cache_hit", |b| {
+        b.to_async(&rt).iter(|| async {
+            engine.get_bytes(7, 0, 16, vdl).await.unwrap();
+        });
+    });
+}
+
+criterion_group!(benches, bench_get_page_cached, bench_get_bytes_cached);
+criterion_main!(benches);
@@ -0,0 +1,164 @@
+//! Alerts: conditions an operator should act on, raised as the engine runs
+//! into them. The engine hands each one to an `AlertSink`; the default one
+//! logs it, and `RecentAlerts` also keeps the latest for the REPL.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::ErrorKind;
+use std::sync::Mutex;
+
+use mini_aurora_common::{StorageError, Timestamp};
+use tracing::{error, warn};
+
+/// Alerts `RecentAlerts::default` keeps.
+pub const RECENT_ALERTS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    /// A checksum failed: a WAL record, a sealed segment's footer, a cached
+    /// page image or a replay.
+    Corruption,
+    /// A reader's read point trails the VDL by more than the engine's lag
+    /// threshold, holding back every version it can still see.
+    ReaderLag,
+    /// A write failed because the disk is full.
+    DiskFull,
+    /// A WAL write or fsync failed, so the volume stopped taking writes.
+    ReadOnly,
+}
+
+impl AlertKind {
+    /// The alert an error calls for, if any. A volume going read-only is
+    /// raised once, by the error that carries the failure behind it.
+    pub fn for_error(err: &StorageError) -> Option<Self> {
+        match err {
+            StorageError::Corruption(_) | StorageError::CorruptRecord { .. } => Some(Self::Corruption),
+            StorageError::Io(e)
+            | StorageError::File { source: e, .. }
+            | StorageError::VolumeReadOnly { source: Some(e), .. }
+                if e.kind() == ErrorKind::StorageFull =>
+            {
+                Some(Self::DiskFull)
+            }
+            StorageError::VolumeReadOnly { source: Some(_), .. } => Some(Self::ReadOnly),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Corruption => "corruption",
+            Self::ReaderLag => "reader lag",
+            Self::DiskFull => "disk full",
+            Self::ReadOnly => "read-only",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub message: String,
+    /// When the engine raised it, in microseconds since the Unix epoch.
+    pub timestamp: Timestamp,
+}
+
+/// Where the engine sends its alerts. Called with the engine's locks
+/// released, from whichever task hit the condition.
+pub trait AlertSink: Send + Sync {
+    fn alert(&self, alert: &Alert);
+}
+
+/// Logs each alert: lag as a warning, the rest as errors.
+pub struct LogAlerts;
+
+impl AlertSink for LogAlerts {
+    fn alert(&self, alert: &Alert) {
+        match alert.kind {
+            AlertKind::ReaderLag => warn!(kind = %alert.kind, "{}", alert.message),
+            AlertKind::Corruption | AlertKind::DiskFull | AlertKind::ReadOnly => {
+                error!(kind = %alert.kind, "{}", alert.message)
+            }
+        }
+    }
+}
+
+/// Logs each alert like `LogAlerts`, and keeps the latest `capacity` of
+/// them for the REPL's `alerts` command.
+pub struct RecentAlerts {
+    capacity: usize,
+    alerts: Mutex<VecDeque<Alert>>,
+}
+
+impl RecentAlerts {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, alerts: Mutex::default() }
+    }
+
+    /// Kept alerts, oldest first.
+    pub fn recent(&self) -> Vec<Alert> {
+        self.alerts.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl Default for RecentAlerts {
+    fn default() -> Self {
+        Self::new(RECENT_ALERTS)
+    }
+}
+
+impl AlertSink for RecentAlerts {
+    fn alert(&self, alert: &Alert) {
+        LogAlerts.alert(alert);
+        let mut alerts = self.alerts.lock().unwrap();
+        if alerts.len() == self.capacity {
+            alerts.pop_front();
+        }
+        if self.capacity > 0 {
+            alerts.push_back(alert.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mini_aurora_common::{Lsn, WalLocation};
+
+    fn alert(kind: AlertKind, n: Lsn) -> Alert {
+        Alert { kind, message: format!("alert {n}"), timestamp: n }
+    }
+
+    #[test]
+    fn test_recent_alerts_keep_the_latest() {
+        let sink = RecentAlerts::new(2);
+        for n in 1..=3 {
+            sink.alert(&alert(AlertKind::Corruption, n));
+        }
+        assert_eq!(sink.recent(), vec![alert(AlertKind::Corruption, 2), alert(AlertKind::Corruption, 3)]);
+    }
+
+    #[test]
+    fn test_errors_that_raise_alerts() {
+        let corrupt = StorageError::CorruptRecord {
+            lsn: 4,
+            location: WalLocation { segment_id: None, file_offset: 0 },
+            problem: "failed its CRC check",
+        };
+        assert_eq!(AlertKind::for_error(&corrupt), Some(AlertKind::Corruption));
+        let full = StorageError::file("write", "test.wal".as_ref())(ErrorKind::StorageFull.into());
+        assert_eq!(AlertKind::for_error(&full), Some(AlertKind::DiskFull));
+        let other = StorageError::Io(ErrorKind::PermissionDenied.into());
+        assert_eq!(AlertKind::for_error(&other), None);
+        let stopped = |kind: Option<ErrorKind>| StorageError::VolumeReadOnly {
+            reason: "test".into(),
+            source: kind.map(ErrorKind::into),
+        };
+        assert_eq!(AlertKind::for_error(&stopped(Some(ErrorKind::StorageFull))), Some(AlertKind::DiskFull));
+        assert_eq!(AlertKind::for_error(&stopped(Some(ErrorKind::Other))), Some(AlertKind::ReadOnly));
+        assert_eq!(AlertKind::for_error(&stopped(None)), None);
+        assert_eq!(AlertKind::for_error(&StorageError::PageNotFound { page_id: 1, lsn: 1 }), None);
+    }
+}
@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use mini_aurora_common::{
+    EngineObserver, Lsn, PageId, RedoRecord, StorageError, TierStats, Timestamp, WalLocation,
+};
+use mini_aurora_wal::checksum::ChecksumKind;
+use mini_aurora_wal::memory::{MemoryWal, MEMORY_WAL_PATH};
+use mini_aurora_wal::reader::WalReader;
+use mini_aurora_wal::recovery::recover_in_memory;
+use mini_aurora_wal::segment::Tier;
+use mini_aurora_wal::time_index::TimeIndex;
+use mini_aurora_wal::volume::VolumeConfig;
+
+use super::{batch_bytes, lsn_range, table_bytes, Appended, ColdFetches, LogIndex, Recovered, WalBackend};
+
+/// The log in memory: appends are synchronous and nothing survives the
+/// engine, unless the caller holds another handle on `wal`.
+pub(crate) struct MemoryBackend {
+    wal: MemoryWal,
+    /// A WAL in memory has no settings beside it; the caller names them.
+    volume: VolumeConfig,
+    index: LogIndex,
+}
+
+impl MemoryBackend {
+    pub(crate) fn new(wal: MemoryWal, page_size: usize) -> Self {
+        Self { wal, volume: VolumeConfig { page_size, ..Default::default() }, index: LogIndex::default() }
+    }
+}
+
+impl WalBackend for MemoryBackend {
+    fn recover(&mut self, observer: &dyn EngineObserver) -> Result<Recovered, StorageError> {
+        let (index, recovered) = LogIndex::recovered(recover_in_memory(&mut self.wal, observer)?, false);
+        self.index = index;
+        Ok(recovered)
+    }
+
+    fn volume(&self) -> Result<Option<VolumeConfig>, StorageError> {
+        Ok(Some(self.volume))
+    }
+
+    fn set_volume(&mut self, volume: VolumeConfig) -> Result<(), StorageError> {
+        self.volume = volume;
+        Ok(())
+    }
+
+    /// Durable at once, so located at once.
+    fn append(
+        &mut self,
+        records: &[RedoRecord],
+        observer: &dyn EngineObserver,
+    ) -> Result<Appended, StorageError> {
+        let offset = self.wal.append(records);
+        let (first_lsn, last_lsn) = lsn_range(records);
+        observer.wal_appended(first_lsn, last_lsn, offset, batch_bytes(records));
+        observer.wal_synced(Duration::ZERO);
+        self.index.record(records, offset, observer);
+        Ok(Appended::Durable)
+    }
+
+    /// Every append is durable when it returns.
+    fn sync(&mut self, _observer: &dyn EngineObserver) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn locate(&self, lsn: Lsn) -> Option<WalLocation> {
+        self.index.locate(lsn)
+    }
+
+    fn locations(&self) -> Vec<(Lsn, WalLocation)> {
+        self.index.locations()
+    }
+
+    fn located(&self) -> usize {
+        self.index.offsets.len()
+    }
+
+    fn index_bytes(&self) -> u64 {
+        table_bytes(&self.index.offsets)
+    }
+
+    fn open_reader(&self, _segment_id: Option<u32>) -> Result<(WalReader, Option<Tier>), StorageError> {
+        Ok((self.wal.reader(), None))
+    }
+
+    fn collect_chain(
+        &self,
+        page_id: PageId,
+        start_lsn: Lsn,
+        read_point: Lsn,
+        floor: Lsn,
+        observer: &dyn EngineObserver,
+        _cold: &mut ColdFetches,
+    ) -> Result<Vec<RedoRecord>, StorageError> {
+        self.index.collect_chain(self.wal.reader(), page_id, start_lsn, read_point, floor, observer)
+    }
+
+    /// Nothing is deleted: the log is one buffer.
+    fn truncate_before(&mut self, lsn: Lsn, dropped: &[Lsn]) -> Result<Vec<(u32, u64)>, StorageError> {
+        self.index.truncate_before(lsn, dropped);
+        Ok(Vec::new())
+    }
+
+    fn truncated_before(&self) -> Lsn {
+        self.index.truncated_before
+    }
+
+    fn time_index(&self) -> &TimeIndex {
+        &self.index.time_index
+    }
+
+    fn rewind(&mut self, cpl: Option<(Lsn, Timestamp)>) {
+        self.index.rewind(cpl);
+    }
+
+    fn checksum(&self) -> ChecksumKind {
+        self.wal.checksum()
+    }
+
+    fn set_checksum(&mut self, checksum: ChecksumKind) {
+        self.wal.set_checksum(checksum);
+    }
+
+    fn active_path(&self) -> PathBuf {
+        PathBuf::from(MEMORY_WAL_PATH)
+    }
+
+    fn wal_file_size(&self) -> u64 {
+        self.wal.len()
+    }
+
+    fn tier_stats(&self) -> (TierStats, TierStats) {
+        (TierStats { segments: 1, bytes: self.wal.len(), ..Default::default() }, TierStats::default())
+    }
+}
@@ -0,0 +1,397 @@
+//! Where the storage engine keeps its WAL. The engine assigns LSNs, links
+//! each page's chain and owns the page indexes; a [`WalBackend`] writes the
+//! records, knows where each one is, reads them back, and finds them again
+//! when the volume is opened. Backends live in this crate: a new kind of
+//! WAL storage implements the trait here, and gets a `StorageEngine`
+//! constructor that opens the engine on it. One with hot and cold tiers
+//! implements [`TieredWal`] as well.
+
+mod memory;
+mod segmented;
+mod single_file;
+
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use mini_aurora_common::{
+    CompactReport, DurabilityState, EngineObserver, LogEntryHeader, Lsn, PageId, RedoRecord, StorageError,
+    TierStats, Timestamp, WalLocation, LOG_ENTRY_HEADER_SIZE,
+};
+use mini_aurora_wal::checksum::ChecksumKind;
+use mini_aurora_wal::footer::FooterCheck;
+use mini_aurora_wal::pipeline::PipelineStats;
+use mini_aurora_wal::reader::{ReadResult, WalReader};
+use mini_aurora_wal::recovery::RecoveryResult;
+use mini_aurora_wal::segment::{Promotion, ReaderPoolStats, SegmentMeta, Tier};
+use mini_aurora_wal::time_index::TimeIndex;
+use mini_aurora_wal::volume::VolumeConfig;
+use tokio::sync::oneshot;
+
+pub(crate) use memory::MemoryBackend;
+pub(crate) use segmented::{ColdFetches, SegmentedWal};
+pub(crate) use single_file::SingleFileWal;
+
+/// A record's segment and tier; `None` for a WAL that isn't segmented.
+pub(crate) type SegmentTier = Option<(u32, Tier)>;
+
+/// A reader left open on one WAL file, so reads that stay in the file don't
+/// reopen it.
+pub(crate) type OpenWal = Option<(WalReader, SegmentTier)>;
+
+/// What recovery found, for the engine to index. The backend keeps the
+/// records' locations itself.
+pub(crate) struct Recovered {
+    pub durability: DurabilityState,
+    pub page_index: HashMap<PageId, Lsn>,
+    pub page_versions: HashMap<PageId, Vec<Lsn>>,
+    pub truncated_bytes: u64,
+    /// The indexes came from a clean shutdown's sidecar, not a scan.
+    pub clean_shutdown: bool,
+    /// Pages the clean shutdown recorded as hottest, busiest first; none
+    /// after a scan.
+    pub hot_pages: Vec<PageId>,
+}
+
+/// How an append reaches the disk.
+pub(crate) enum Appended {
+    /// Durable and located before `append` returned.
+    Durable,
+    /// On its way: the receiver yields once it is durable or failed, and
+    /// `take_landed` hands the batch back.
+    Pending(oneshot::Receiver<io::Result<()>>),
+}
+
+/// A pending append that finished.
+pub(crate) struct LandedAppend {
+    pub records: Vec<RedoRecord>,
+    /// Where the first record is.
+    pub offset: u64,
+    /// False if writing or syncing it failed.
+    pub durable: bool,
+    /// From submitting the batch to the pipeline until it landed, waiting
+    /// for its group's write and fsync.
+    pub elapsed: Duration,
+}
+
+/// The engine calls these with its lock held, so a backend needs no
+/// locking of its own.
+pub(crate) trait WalBackend: Send {
+    /// Find the records again on open: load a clean shutdown's sidecar,
+    /// or scan the log and cut its torn tail. Called once, before any
+    /// other method but `volume`.
+    fn recover(&mut self, observer: &dyn EngineObserver) -> Result<Recovered, StorageError>;
+
+    /// Settings the volume was created with, if it has any yet.
+    fn volume(&self) -> Result<Option<VolumeConfig>, StorageError>;
+
+    fn set_volume(&mut self, volume: VolumeConfig) -> Result<(), StorageError>;
+
+    /// Write `records`, whose LSNs, chains and timestamps are set. A
+    /// backend that syncs them before returning locates them too.
+    fn append(
+        &mut self,
+        records: &[RedoRecord],
+        observer: &dyn EngineObserver,
+    ) -> Result<Appended, StorageError>;
+
+    /// Pending appends that finished since the last call, in LSN order.
+    fn take_landed(&mut self) -> Vec<LandedAppend> {
+        Vec::new()
+    }
+
+    /// Locate a durable batch from `take_landed`.
+    fn land(&mut self, _append: &LandedAppend, _observer: &dyn EngineObserver) {}
+
+    /// Wait for every pending append to finish.
+    fn flush(&mut self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Make every append so far durable. A failure here is never retried.
+    fn sync(&mut self, observer: &dyn EngineObserver) -> Result<(), StorageError>;
+
+    /// Whatever else a checkpoint does, after `sync`.
+    fn checkpoint(&mut self, _observer: &dyn EngineObserver) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Save what the next open needs to skip recovery, if the backend can.
+    fn close(
+        &mut self,
+        _durability: &DurabilityState,
+        _page_index: &HashMap<PageId, Lsn>,
+        _page_versions: &HashMap<PageId, Vec<Lsn>>,
+        _hot_pages: &[PageId],
+    ) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Where the record at `lsn` is, if it is durable.
+    fn locate(&self, lsn: Lsn) -> Option<WalLocation>;
+
+    /// Every located record.
+    fn locations(&self) -> Vec<(Lsn, WalLocation)>;
+
+    /// Records located.
+    fn located(&self) -> usize;
+
+    /// Bytes the locations take in memory.
+    fn index_bytes(&self) -> u64;
+
+    /// A reader of the file `segment_id` names, with its tier.
+    fn open_reader(&self, segment_id: Option<u32>) -> Result<(WalReader, Option<Tier>), StorageError>;
+
+    /// Give back a reader from `open_reader`.
+    fn release_reader(&self, _segment_id: Option<u32>, _reader: WalReader) {}
+
+    /// Read the entry at `location`, reusing `reader` if it is open on the
+    /// right file.
+    fn read_at(&self, location: WalLocation, reader: &mut OpenWal) -> Result<ReadResult, StorageError> {
+        let same_file = matches!(reader, Some((_, seg)) if seg.map(|(id, _)| id) == location.segment_id);
+        if !same_file {
+            self.release(reader.take());
+            let (wal, tier) = self.open_reader(location.segment_id)?;
+            *reader = Some((wal, location.segment_id.zip(tier)));
+        }
+        let (wal, _) = reader.as_mut().unwrap();
+        wal.seek_to(location.file_offset)?;
+        Ok(wal.read_entry()?)
+    }
+
+    /// Give back a reader left open by `read_at`.
+    fn release(&self, reader: OpenWal) {
+        if let Some((wal, segment)) = reader {
+            self.release_reader(segment.map(|(id, _)| id), wal);
+        }
+    }
+
+    /// The records of `page_id`'s chain from `start_lsn` down to just
+    /// above `floor`, oldest first, skipping any past `read_point`. Cold
+    /// segments come from `cold`; a backend without tiers has none.
+    fn collect_chain(
+        &self,
+        page_id: PageId,
+        start_lsn: Lsn,
+        read_point: Lsn,
+        floor: Lsn,
+        observer: &dyn EngineObserver,
+        cold: &mut ColdFetches,
+    ) -> Result<Vec<RedoRecord>, StorageError>;
+
+    /// Stop locating `dropped`, records no read at or past `lsn` needs,
+    /// and refuse reads below `lsn` from then on. A backend that can
+    /// deletes what holds no located record any more, returning each
+    /// segment deleted with its size.
+    fn truncate_before(&mut self, lsn: Lsn, dropped: &[Lsn]) -> Result<Vec<(u32, u64)>, StorageError>;
+
+    /// LSN the history was last truncated before; 0 if it never was.
+    fn truncated_before(&self) -> Lsn;
+
+    /// Times of the located CPLs.
+    fn time_index(&self) -> &TimeIndex;
+
+    /// Forget every record past `cpl`, for a point-in-time view. The log
+    /// keeps them.
+    fn rewind(&mut self, cpl: Option<(Lsn, Timestamp)>);
+
+    fn checksum(&self) -> ChecksumKind;
+
+    /// Checksum records appended from now on with `checksum`.
+    fn set_checksum(&mut self, checksum: ChecksumKind);
+
+    /// The file receiving appends.
+    fn active_path(&self) -> PathBuf;
+
+    /// Where the engine keeps consolidated page images beside the log;
+    /// `None` when the log has no home on disk.
+    fn page_store_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Size of the log, if it is one file.
+    fn wal_file_size(&self) -> u64 {
+        0
+    }
+
+    /// Segments and bytes in the hot and cold tiers.
+    fn tier_stats(&self) -> (TierStats, TierStats);
+
+    fn pipeline_stats(&self) -> PipelineStats {
+        PipelineStats::default()
+    }
+
+    /// What is wrong with the backend's own state, for the engine's
+    /// invariant checks.
+    fn invariant_violations(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The backend's hot/cold tiering, if it has any.
+    fn tiering(&self) -> Option<&dyn TieredWal> {
+        None
+    }
+
+    fn tiering_mut(&mut self) -> Option<&mut dyn TieredWal> {
+        None
+    }
+}
+
+/// Hot/cold tiering of a WAL kept in segments. A backend with tiers
+/// implements this too, and hands it out from `WalBackend::tiering`.
+pub(crate) trait TieredWal {
+    /// The WAL's segments with their tier and size.
+    fn segments(&self) -> Vec<SegmentMeta>;
+
+    /// Fetch the cold segments holding the `versions` above `floor`, newest
+    /// first as a chain walk visits them. `versions` is in LSN order.
+    fn plan_cold_fetches(&self, versions: &[Lsn], floor: Lsn) -> ColdFetches;
+
+    fn set_promote_on_read(&mut self, on: bool);
+
+    fn promote_on_read(&self) -> bool;
+
+    /// Promotion jobs for the cold segments a read walked, if reads
+    /// promote them.
+    fn promotions(&self, walked: &[u32]) -> Vec<Promotion>;
+
+    /// Install a finished promotion copy. Returns whether the segment was
+    /// promoted.
+    fn finish_promotion(&mut self, job: Promotion, copied: io::Result<()>) -> Result<bool, StorageError>;
+
+    fn reader_pool_stats(&self) -> ReaderPoolStats;
+
+    /// Cold segment reads served by segment read-ahead.
+    fn read_ahead_hits(&self) -> u64;
+
+    /// Remove files no segment uses any more, with the bytes each held.
+    fn collect_garbage(&self) -> Result<Vec<(PathBuf, u64)>, StorageError>;
+
+    /// Merge cold segments.
+    fn compact(&mut self) -> Result<CompactReport, StorageError>;
+
+    /// Cool every sealed segment.
+    fn cool_all(&mut self, observer: &dyn EngineObserver) -> Result<Vec<u32>, StorageError>;
+
+    /// Check a sealed segment against its footer; `None` if there is no
+    /// such segment.
+    fn verify_footer(&self, segment_id: u32) -> Result<Option<FooterCheck>, StorageError>;
+}
+
+/// First and last LSN of a batch.
+fn lsn_range(records: &[RedoRecord]) -> (Lsn, Lsn) {
+    (records.first().map_or(0, |r| r.lsn), records.last().map_or(0, |r| r.lsn))
+}
+
+/// Bytes `records` take in the WAL.
+fn batch_bytes(records: &[RedoRecord]) -> u64 {
+    records.iter().map(|r| LOG_ENTRY_HEADER_SIZE as u64 + r.data.len() as u64).sum()
+}
+
+/// Bytes a map's table takes, by capacity, not counting heap data its
+/// entries point to.
+pub(crate) fn table_bytes<K, V>(map: &HashMap<K, V>) -> u64 {
+    (map.capacity() * (std::mem::size_of::<(K, V)>() + 1)) as u64
+}
+
+/// What `f` returns, and how long it took; zero on wasm32, where std has
+/// no clock.
+pub(crate) fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let started = std::time::Instant::now();
+        let out = f();
+        (out, started.elapsed())
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        (f(), Duration::ZERO)
+    }
+}
+
+/// Locations and CPL times of a log kept in one place, as a single file or
+/// in memory.
+#[derive(Default)]
+struct LogIndex {
+    offsets: HashMap<Lsn, u64>,
+    time_index: TimeIndex,
+    /// Lost with the index unless a clean shutdown saves it: the log
+    /// itself keeps every record, so recovery finds the history again.
+    truncated_before: Lsn,
+}
+
+impl LogIndex {
+    /// Split what WAL recovery found into the index and what the engine keeps.
+    fn recovered(result: RecoveryResult, clean_shutdown: bool) -> (Self, Recovered) {
+        let RecoveryResult {
+            durability,
+            page_index,
+            lsn_offsets,
+            page_versions,
+            time_index,
+            truncated_bytes,
+        } = result;
+        let index = Self { offsets: lsn_offsets, time_index, truncated_before: 0 };
+        let recovered = Recovered {
+            durability,
+            page_index,
+            page_versions,
+            truncated_bytes,
+            clean_shutdown,
+            hot_pages: Vec::new(),
+        };
+        (index, recovered)
+    }
+
+    /// Locate `records`, durable and back to back from `offset`, and note
+    /// the times of their CPLs.
+    fn record(&mut self, records: &[RedoRecord], mut offset: u64, observer: &dyn EngineObserver) {
+        for record in records {
+            self.offsets.insert(record.lsn, offset);
+            observer.lsn_offset_recorded(record.lsn, offset);
+            offset += LOG_ENTRY_HEADER_SIZE as u64 + record.data.len() as u64;
+        }
+        for cpl in records.iter().filter(|r| r.is_mtr_end) {
+            self.time_index.record(cpl.lsn, cpl.timestamp);
+        }
+    }
+
+    fn locate(&self, lsn: Lsn) -> Option<WalLocation> {
+        self.offsets.get(&lsn).map(|&file_offset| WalLocation { segment_id: None, file_offset })
+    }
+
+    fn locations(&self) -> Vec<(Lsn, WalLocation)> {
+        self.offsets
+            .iter()
+            .map(|(&lsn, &file_offset)| (lsn, WalLocation { segment_id: None, file_offset }))
+            .collect()
+    }
+
+    fn collect_chain(
+        &self,
+        mut reader: WalReader,
+        page_id: PageId,
+        start_lsn: Lsn,
+        read_point: Lsn,
+        floor: Lsn,
+        observer: &dyn EngineObserver,
+    ) -> Result<Vec<RedoRecord>, StorageError> {
+        let on_step =
+            |hdr: &LogEntryHeader, skipped| observer.chain_step(page_id, hdr.lsn, hdr.prev_lsn, skipped);
+        Ok(reader.collect_page_chain_with(page_id, start_lsn, read_point, floor, &self.offsets, on_step)?)
+    }
+
+    fn truncate_before(&mut self, lsn: Lsn, dropped: &[Lsn]) {
+        for lsn in dropped {
+            self.offsets.remove(lsn);
+        }
+        self.truncated_before = self.truncated_before.max(lsn);
+    }
+
+    fn rewind(&mut self, cpl: Option<(Lsn, Timestamp)>) {
+        let cutoff = cpl.map_or(0, |(lsn, _)| lsn);
+        self.offsets.retain(|&lsn, _| lsn <= cutoff);
+        self.time_index.rewind(cpl);
+    }
+}
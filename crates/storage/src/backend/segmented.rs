@@ -0,0 +1,460 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use mini_aurora_common::{
+    CompactReport, DurabilityState, EngineObserver, Lsn, PageId, RedoRecord, StorageError, TierStats,
+    Timestamp, WalLocation,
+};
+use mini_aurora_wal::checksum::ChecksumKind;
+use mini_aurora_wal::footer::FooterCheck;
+use mini_aurora_wal::reader::{header_to_record, ReadResult, WalReader};
+use mini_aurora_wal::segment::{
+    LsnLocation, Promotion, ReaderPoolStats, RecoveryData, SegmentManager, SegmentMeta, Tier,
+};
+use mini_aurora_wal::sidecar::{self, Sidecar};
+use mini_aurora_wal::time_index::TimeIndex;
+use mini_aurora_wal::disk::TierDisks;
+use mini_aurora_wal::volume::VolumeConfig;
+
+use super::{batch_bytes, lsn_range, table_bytes, timed, Appended, Recovered, TieredWal, WalBackend};
+
+/// The WAL as segments with hot/cold tiering (Phase 2). Appends are
+/// synced before they return.
+pub(crate) struct SegmentedWal {
+    base_dir: PathBuf,
+    manager: Box<SegmentManager>,
+    lsn_offsets: HashMap<Lsn, LsnLocation>,
+}
+
+impl SegmentedWal {
+    /// The segmented WAL in `base_dir`, with at most `segment_size` bytes
+    /// per segment.
+    pub(crate) fn open(base_dir: &Path, segment_size: u64, disks: TierDisks) -> Result<Self, StorageError> {
+        let manager = SegmentManager::open(base_dir, segment_size, disks)?;
+        Ok(Self { base_dir: base_dir.to_path_buf(), manager: Box::new(manager), lsn_offsets: HashMap::new() })
+    }
+
+    /// Cool sealed segments until at most `keep_hot` stay hot.
+    fn cool(&mut self, keep_hot: usize, observer: &dyn EngineObserver) -> Result<Vec<u32>, StorageError> {
+        let cooled = self.manager.cool_segments(keep_hot)?;
+        for &segment_id in &cooled {
+            observer.segment_cooled(segment_id);
+        }
+        Ok(cooled)
+    }
+}
+
+impl WalBackend for SegmentedWal {
+    fn recover(&mut self, observer: &dyn EngineObserver) -> Result<Recovered, StorageError> {
+        let manager = &mut self.manager;
+        let mut hot_pages = Vec::new();
+        let (recovery, clean_shutdown) = match sidecar::take::<LsnLocation>(&manager.sidecar_path())? {
+            Some(saved) if saved.wal_len == manager.active_len() => {
+                manager.resume(&saved.lsn_offsets);
+                hot_pages = saved.hot_pages;
+                observer.recovery_index_rebuilt(saved.page_index.len(), saved.lsn_offsets.len());
+                let recovery = RecoveryData {
+                    durability: saved.durability,
+                    page_index: saved.page_index,
+                    lsn_offsets: saved.lsn_offsets,
+                    page_versions: saved.page_versions,
+                    truncated_bytes: 0,
+                };
+                (recovery, true)
+            }
+            _ => (manager.recover_observed(observer)?, false),
+        };
+        let RecoveryData { durability, page_index, lsn_offsets, page_versions, truncated_bytes } = recovery;
+        self.lsn_offsets = lsn_offsets;
+        Ok(Recovered { durability, page_index, page_versions, truncated_bytes, clean_shutdown, hot_pages })
+    }
+
+    /// Segments carry the settings in their headers.
+    fn volume(&self) -> Result<Option<VolumeConfig>, StorageError> {
+        Ok(Some(self.manager.volume()))
+    }
+
+    fn set_volume(&mut self, volume: VolumeConfig) -> Result<(), StorageError> {
+        self.manager.set_volume(volume).map_err(StorageError::file("write", &self.base_dir))
+    }
+
+    fn append(
+        &mut self,
+        records: &[RedoRecord],
+        observer: &dyn EngineObserver,
+    ) -> Result<Appended, StorageError> {
+        let mut rotations = Vec::new();
+        let locations = self.manager.append_batch_with(records, |sealed, new_id| {
+            rotations.push((sealed.id, new_id, sealed.lsn_range, sealed.size_bytes));
+        })?;
+        let (first_lsn, last_lsn) = lsn_range(records);
+        observer.wal_appended(first_lsn, last_lsn, 0, batch_bytes(records));
+        let (synced, elapsed) = timed(|| self.manager.sync());
+        synced?;
+        observer.wal_synced(elapsed);
+
+        // Rotation may leave more sealed segments hot than the budget
+        if !rotations.is_empty() {
+            for (sealed_id, new_id, range, bytes) in rotations {
+                observer.segment_rotated(sealed_id, new_id, range, bytes);
+            }
+            self.cool(self.manager.hot_segments(), observer)?;
+        }
+
+        for (record, loc) in records.iter().zip(locations.iter()) {
+            self.lsn_offsets.insert(record.lsn, *loc);
+            observer.lsn_offset_recorded(record.lsn, loc.file_offset);
+        }
+        Ok(Appended::Durable)
+    }
+
+    fn sync(&mut self, observer: &dyn EngineObserver) -> Result<(), StorageError> {
+        let (synced, elapsed) = timed(|| self.manager.sync());
+        synced?;
+        observer.wal_synced(elapsed);
+        Ok(())
+    }
+
+    /// Seal the active segment, so a checkpoint leaves it to be cooled.
+    fn checkpoint(&mut self, observer: &dyn EngineObserver) -> Result<(), StorageError> {
+        if let Some(sealed) = self.manager.seal()? {
+            observer.segment_rotated(sealed.id, sealed.id + 1, sealed.lsn_range, sealed.size_bytes);
+            self.cool(self.manager.hot_segments(), observer)?;
+        }
+        Ok(())
+    }
+
+    fn close(
+        &mut self,
+        durability: &DurabilityState,
+        page_index: &HashMap<PageId, Lsn>,
+        page_versions: &HashMap<PageId, Vec<Lsn>>,
+        hot_pages: &[PageId],
+    ) -> Result<(), StorageError> {
+        self.manager.flush()?;
+        let saved = Sidecar {
+            wal_len: self.manager.active_len(),
+            durability: durability.clone(),
+            page_index: page_index.clone(),
+            lsn_offsets: self.lsn_offsets.clone(),
+            page_versions: page_versions.clone(),
+            // The manifest keeps the time index and truncation point
+            time_index: None,
+            truncated_before: 0,
+            hot_pages: hot_pages.to_vec(),
+        };
+        sidecar::save(&self.manager.sidecar_path(), &saved)?;
+        Ok(())
+    }
+
+    fn locate(&self, lsn: Lsn) -> Option<WalLocation> {
+        self.lsn_offsets.get(&lsn).map(location)
+    }
+
+    fn locations(&self) -> Vec<(Lsn, WalLocation)> {
+        self.lsn_offsets.iter().map(|(&lsn, loc)| (lsn, location(loc))).collect()
+    }
+
+    fn located(&self) -> usize {
+        self.lsn_offsets.len()
+    }
+
+    fn index_bytes(&self) -> u64 {
+        table_bytes(&self.lsn_offsets)
+    }
+
+    fn open_reader(&self, segment_id: Option<u32>) -> Result<(WalReader, Option<Tier>), StorageError> {
+        let segment_id = segment_id.expect("segmented locations carry a segment");
+        let (reader, tier) = self.manager.open_segment_reader(segment_id)?;
+        Ok((reader, Some(tier)))
+    }
+
+    /// Hand the reader back to the segment manager's pool.
+    fn release_reader(&self, segment_id: Option<u32>, reader: WalReader) {
+        if let Some(segment_id) = segment_id {
+            self.manager.release_segment_reader(segment_id, reader);
+        }
+    }
+
+    /// Walks the chain across segments. A segment cooled since `cold` was
+    /// planned is fetched here, its latency left pending.
+    fn collect_chain(
+        &self,
+        page_id: PageId,
+        start_lsn: Lsn,
+        read_point: Lsn,
+        floor: Lsn,
+        observer: &dyn EngineObserver,
+        cold: &mut ColdFetches,
+    ) -> Result<Vec<RedoRecord>, StorageError> {
+        let manager = &self.manager;
+        let mut chain = Vec::new();
+        let mut current_lsn = start_lsn;
+        let mut last_segment_id: Option<u32> = None;
+        let mut reader_cache: Option<(WalReader, Tier)> = None;
+
+        while current_lsn > floor {
+            let loc = match self.lsn_offsets.get(&current_lsn) {
+                Some(loc) => loc,
+                None => break,
+            };
+
+            // Reuse reader if same segment, otherwise open new one
+            let need_new_reader = last_segment_id != Some(loc.segment_id);
+            if need_new_reader {
+                if let (Some(id), Some((reader, _))) = (last_segment_id, reader_cache.take()) {
+                    manager.release_segment_reader(id, reader);
+                }
+                let (reader, tier) = manager.open_segment_reader(loc.segment_id)?;
+                manager.record_read(loc.segment_id);
+                if tier == Tier::Cold {
+                    cold.walked.push(loc.segment_id);
+                }
+                // A cold segment not fetched for itself came in by read-ahead
+                if tier == Tier::Cold && !cold.requested.contains(&loc.segment_id) {
+                    if cold.fetched.contains(&loc.segment_id) {
+                        manager.record_read_ahead_hit();
+                    } else {
+                        cold.fetch(loc.segment_id, manager);
+                    }
+                }
+                reader_cache = Some((reader, tier));
+                last_segment_id = Some(loc.segment_id);
+            }
+
+            let (ref mut reader, _) = reader_cache.as_mut().unwrap();
+            reader.seek_to(loc.file_offset)?;
+            match reader.read_entry()? {
+                ReadResult::Entry(hdr, data) => {
+                    debug_assert_eq!(hdr.page_id, page_id);
+                    let skipped = hdr.lsn > read_point;
+                    observer.chain_step(page_id, hdr.lsn, hdr.prev_lsn, skipped);
+                    current_lsn = hdr.prev_lsn;
+                    if !skipped {
+                        chain.push(header_to_record(&hdr, data));
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        if let (Some(id), Some((reader, _))) = (last_segment_id, reader_cache) {
+            manager.release_segment_reader(id, reader);
+        }
+        chain.reverse(); // oldest first for replay
+        Ok(chain)
+    }
+
+    /// Deletes the sealed segments below `lsn` left holding no located
+    /// record. Refused if one of them has a copy in the hot tier, promoted
+    /// or being promoted, that hasn't cooled again: reads may still be
+    /// using it.
+    fn truncate_before(&mut self, lsn: Lsn, dropped: &[Lsn]) -> Result<Vec<(u32, u64)>, StorageError> {
+        let dropped: HashSet<Lsn> = dropped.iter().copied().collect();
+        let live: HashSet<u32> = self
+            .lsn_offsets
+            .iter()
+            .filter(|(lsn, _)| !dropped.contains(lsn))
+            .map(|(_, loc)| loc.segment_id)
+            .collect();
+        let segments = self.manager.segments();
+        let unneeded: Vec<u32> = segments
+            .iter()
+            .filter(|s| s.sealed && s.lsn_range.1 < lsn && !live.contains(&s.id))
+            .map(|s| s.id)
+            .collect();
+        let uncooled = segments.iter().find(|s| {
+            unneeded.contains(&s.id) && (s.promoted || self.manager.promoting(s.id))
+        });
+        if let Some(seg) = uncooled {
+            return Err(StorageError::TruncateRefused {
+                lsn,
+                reason: format!("segment {} has a hot copy that hasn't cooled; run `admin cool`", seg.id),
+            });
+        }
+
+        let removed = self.manager.truncate_before(lsn, &unneeded)?;
+        self.lsn_offsets.retain(|lsn, _| !dropped.contains(lsn));
+        Ok(removed)
+    }
+
+    /// The manifest keeps the truncation point.
+    fn truncated_before(&self) -> Lsn {
+        self.manager.truncated_before()
+    }
+
+    /// The segment manager keeps the time index in the manifest.
+    fn time_index(&self) -> &TimeIndex {
+        self.manager.time_index()
+    }
+
+    fn rewind(&mut self, cpl: Option<(Lsn, Timestamp)>) {
+        let cutoff = cpl.map_or(0, |(lsn, _)| lsn);
+        self.lsn_offsets.retain(|&lsn, _| lsn <= cutoff);
+        self.manager.rewind_time_index(cpl);
+    }
+
+    fn checksum(&self) -> ChecksumKind {
+        self.manager.checksum()
+    }
+
+    fn set_checksum(&mut self, checksum: ChecksumKind) {
+        self.manager.set_checksum(checksum);
+    }
+
+    fn active_path(&self) -> PathBuf {
+        self.manager.active_segment_path()
+    }
+
+    fn page_store_dir(&self) -> Option<PathBuf> {
+        Some(self.base_dir.join("pages"))
+    }
+
+    fn tier_stats(&self) -> (TierStats, TierStats) {
+        let mut hot = TierStats::default();
+        let mut cold = TierStats::default();
+        for segment in self.manager.segments() {
+            let tier = match segment.tier {
+                Tier::Hot => &mut hot,
+                Tier::Cold => &mut cold,
+            };
+            tier.segments += 1;
+            tier.bytes += segment.size_bytes;
+        }
+        let (hot_disk, cold_disk) = self.manager.disk_stats();
+        hot.disk_busy_us = hot_disk.busy.as_micros() as u64;
+        cold.disk_busy_us = cold_disk.busy.as_micros() as u64;
+        (hot, cold)
+    }
+
+    /// Segment filenames must locate LSNs as the manifest does.
+    fn invariant_violations(&self) -> Vec<String> {
+        self.manager.filename_mismatches()
+    }
+
+    fn tiering(&self) -> Option<&dyn TieredWal> {
+        Some(self)
+    }
+
+    fn tiering_mut(&mut self) -> Option<&mut dyn TieredWal> {
+        Some(self)
+    }
+}
+
+impl TieredWal for SegmentedWal {
+    fn segments(&self) -> Vec<SegmentMeta> {
+        self.manager.segments()
+    }
+
+    fn plan_cold_fetches(&self, versions: &[Lsn], floor: Lsn) -> ColdFetches {
+        let mut fetches = ColdFetches::default();
+        for lsn in versions.iter().rev().take_while(|&&lsn| lsn > floor) {
+            let Some(loc) = self.lsn_offsets.get(lsn) else { break };
+            let cold = self.manager.tier(loc.segment_id) == Some(Tier::Cold);
+            if cold && !fetches.fetched.contains(&loc.segment_id) {
+                fetches.fetch(loc.segment_id, &self.manager);
+            }
+        }
+        fetches
+    }
+
+    fn set_promote_on_read(&mut self, on: bool) {
+        self.manager.set_promote_on_read(on);
+    }
+
+    fn promote_on_read(&self) -> bool {
+        self.manager.promote_on_read()
+    }
+
+    fn promotions(&self, walked: &[u32]) -> Vec<Promotion> {
+        if !self.manager.promote_on_read() {
+            return Vec::new();
+        }
+        walked.iter().filter_map(|&id| self.manager.promotion(id)).collect()
+    }
+
+    fn finish_promotion(&mut self, job: Promotion, copied: io::Result<()>) -> Result<bool, StorageError> {
+        Ok(self.manager.finish_promotion(job, copied)?)
+    }
+
+    fn reader_pool_stats(&self) -> ReaderPoolStats {
+        self.manager.reader_pool_stats()
+    }
+
+    fn read_ahead_hits(&self) -> u64 {
+        self.manager.read_ahead_hits()
+    }
+
+    fn collect_garbage(&self) -> Result<Vec<(PathBuf, u64)>, StorageError> {
+        Ok(self.manager.collect_garbage()?)
+    }
+
+    /// Merge cold segments, pointing the records in them at their new file.
+    fn compact(&mut self) -> Result<CompactReport, StorageError> {
+        let segments_before = self.manager.segments().len();
+        let merges = self.manager.compact_cold()?;
+
+        let moved: HashMap<u32, (u32, u64)> = merges
+            .iter()
+            .flat_map(|m| m.sources.iter().map(move |&(id, shift)| (id, (m.into, shift))))
+            .collect();
+        for loc in self.lsn_offsets.values_mut() {
+            if let Some(&(into, shift)) = moved.get(&loc.segment_id) {
+                loc.segment_id = into;
+                loc.file_offset += shift;
+            }
+        }
+        Ok(CompactReport {
+            segments_before,
+            segments_after: self.manager.segments().len(),
+            bytes_rewritten: merges.iter().map(|m| m.bytes).sum(),
+        })
+    }
+
+    fn cool_all(&mut self, observer: &dyn EngineObserver) -> Result<Vec<u32>, StorageError> {
+        self.cool(0, observer)
+    }
+
+    fn verify_footer(&self, segment_id: u32) -> Result<Option<FooterCheck>, StorageError> {
+        Ok(self.manager.verify_footer(segment_id)?)
+    }
+}
+
+fn location(loc: &LsnLocation) -> WalLocation {
+    WalLocation { segment_id: Some(loc.segment_id), file_offset: loc.file_offset }
+}
+
+/// Cold segments fetched for one page read, before its chain walk.
+#[derive(Default)]
+pub(crate) struct ColdFetches {
+    /// Segments a fetch was made for.
+    requested: HashSet<u32>,
+    /// Those and the segments read-ahead brought along.
+    fetched: HashSet<u32>,
+    /// Each fetch's segment and modelled latency, not yet waited out.
+    pending: Vec<(u32, Duration)>,
+    /// Cold segments the chain walk read, in the order it read them.
+    pub(crate) walked: Vec<u32>,
+}
+
+impl ColdFetches {
+    fn fetch(&mut self, segment_id: u32, manager: &SegmentManager) {
+        let (fetched, latency) = manager.fetch_cold(segment_id);
+        self.requested.insert(segment_id);
+        self.fetched.extend(fetched);
+        self.pending.push((segment_id, latency));
+    }
+
+    /// Wait out the pending fetches, reporting each as it completes. Called
+    /// without the engine lock, so other operations go on meanwhile.
+    pub(crate) async fn wait(&mut self, observer: &dyn EngineObserver) {
+        for (segment_id, latency) in self.pending.drain(..) {
+            if !latency.is_zero() {
+                tokio::time::sleep(latency).await;
+            }
+            observer.cold_tier_read(segment_id, latency);
+        }
+    }
+}
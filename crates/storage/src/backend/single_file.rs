@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use mini_aurora_common::{
+    DurabilityState, EngineObserver, Lsn, PageId, RedoRecord, StorageError, TierStats, Timestamp, WalLocation,
+};
+use mini_aurora_pagestore::store::store_path;
+use mini_aurora_wal::checksum::ChecksumKind;
+use mini_aurora_wal::disk::Disk;
+use mini_aurora_wal::pipeline::{AppendPipeline, PipelineStats};
+use mini_aurora_wal::reader::WalReader;
+use mini_aurora_wal::recovery::{recover_observed, recover_read_only, RecoveryResult};
+use mini_aurora_wal::segment::Tier;
+use mini_aurora_wal::sidecar::{self, sidecar_path, Sidecar};
+use mini_aurora_wal::time_index::TimeIndex;
+use mini_aurora_wal::volume::{self, volume_path, VolumeConfig};
+use mini_aurora_wal::writer::WalWriter;
+use tokio::sync::oneshot;
+
+use super::{
+    batch_bytes, lsn_range, table_bytes, timed, Appended, ColdFetches, LandedAppend, LogIndex, Recovered,
+    WalBackend,
+};
+
+/// The WAL as one file, appended to by a pipeline that batches writes and
+/// fsyncs off the engine lock (the Phase 1 design).
+pub(crate) struct SingleFileWal {
+    wal_path: PathBuf,
+    disk: Arc<dyn Disk>,
+    /// Another process owns the file: recovery changes nothing on disk.
+    read_only: bool,
+    /// Started by `recover`, once the torn tail is cut.
+    pipeline: Option<AppendPipeline>,
+    index: LogIndex,
+    /// Batches the pipeline has finished with, in LSN order.
+    landed: Arc<Mutex<Vec<LandedAppend>>>,
+}
+
+impl SingleFileWal {
+    /// The WAL at `wal_path`, created empty if there is none, with appends
+    /// and fsyncs going through `disk`.
+    pub(crate) fn open(wal_path: &Path, disk: Arc<dyn Disk>, read_only: bool) -> Result<Self, StorageError> {
+        if !wal_path.exists() {
+            std::fs::File::create(wal_path).map_err(StorageError::file("create", wal_path))?;
+        }
+        Ok(Self {
+            wal_path: wal_path.to_path_buf(),
+            disk,
+            read_only,
+            pipeline: None,
+            index: LogIndex::default(),
+            landed: Arc::default(),
+        })
+    }
+
+    fn pipeline(&self) -> &AppendPipeline {
+        self.pipeline.as_ref().expect("the pipeline starts on recovery")
+    }
+
+    fn pipeline_mut(&mut self) -> &mut AppendPipeline {
+        self.pipeline.as_mut().expect("the pipeline starts on recovery")
+    }
+
+    fn wal_len(&self) -> Result<u64, StorageError> {
+        Ok(std::fs::metadata(&self.wal_path).map_err(StorageError::file("stat", &self.wal_path))?.len())
+    }
+}
+
+impl WalBackend for SingleFileWal {
+    fn recover(&mut self, observer: &dyn EngineObserver) -> Result<Recovered, StorageError> {
+        // Skip the scan if the engine was closed cleanly and the WAL is
+        // untouched since; a reader leaves the sidecar for the owner
+        let wal_len = self.wal_len()?;
+        let saved = if self.read_only { None } else { sidecar::take::<u64>(&sidecar_path(&self.wal_path))? };
+        let mut truncated_before = 0;
+        let mut hot_pages = Vec::new();
+        let (result, clean_shutdown) = match saved {
+            Some(saved) if saved.wal_len == wal_len => {
+                truncated_before = saved.truncated_before;
+                hot_pages = saved.hot_pages;
+                observer.recovery_index_rebuilt(saved.page_index.len(), saved.lsn_offsets.len());
+                let result = RecoveryResult {
+                    durability: saved.durability,
+                    page_index: saved.page_index,
+                    lsn_offsets: saved.lsn_offsets,
+                    page_versions: saved.page_versions,
+                    time_index: saved.time_index.unwrap_or_default(),
+                    truncated_bytes: 0,
+                };
+                (result, true)
+            }
+            _ if self.read_only => (recover_read_only(&self.wal_path)?, false),
+            _ => (recover_observed(&self.wal_path, observer)?, false),
+        };
+        let (index, recovered) = LogIndex::recovered(result, clean_shutdown);
+        self.index = LogIndex { truncated_before, ..index };
+
+        let pipeline = WalWriter::open_on(&self.wal_path, Arc::clone(&self.disk))
+            .and_then(AppendPipeline::start)
+            .map_err(StorageError::file("open", &self.wal_path))?;
+        self.pipeline = Some(pipeline);
+        Ok(Recovered { hot_pages, ..recovered })
+    }
+
+    /// A single WAL file has no header, so its settings sit beside it.
+    fn volume(&self) -> Result<Option<VolumeConfig>, StorageError> {
+        let settings_path = volume_path(&self.wal_path);
+        volume::load(&settings_path).map_err(StorageError::file("read", &settings_path))
+    }
+
+    fn set_volume(&mut self, volume: VolumeConfig) -> Result<(), StorageError> {
+        if self.read_only {
+            return Ok(());
+        }
+        let settings_path = volume_path(&self.wal_path);
+        volume::save(&settings_path, &volume).map_err(StorageError::file("write", &settings_path))
+    }
+
+    /// Encoding, writing and syncing happen in the pipeline, off the lock;
+    /// the batch is located once it lands.
+    fn append(
+        &mut self,
+        records: &[RedoRecord],
+        observer: &dyn EngineObserver,
+    ) -> Result<Appended, StorageError> {
+        let landed = Arc::clone(&self.landed);
+        let pipeline = self.pipeline_mut();
+        let offset = pipeline.end();
+        let (landed_tx, landed_rx) = oneshot::channel();
+        let batch = records.to_vec();
+        let submitted = Instant::now();
+        pipeline.submit(records.to_vec(), Box::new(move |result| {
+            let durable = result.is_ok();
+            let elapsed = submitted.elapsed();
+            landed.lock().unwrap().push(LandedAppend { records: batch, offset, durable, elapsed });
+            let _ = landed_tx.send(result);
+        }))?;
+        let (first_lsn, last_lsn) = lsn_range(records);
+        observer.wal_appended(first_lsn, last_lsn, offset, batch_bytes(records));
+        Ok(Appended::Pending(landed_rx))
+    }
+
+    fn take_landed(&mut self) -> Vec<LandedAppend> {
+        std::mem::take(&mut *self.landed.lock().unwrap())
+    }
+
+    fn land(&mut self, append: &LandedAppend, observer: &dyn EngineObserver) {
+        observer.wal_synced(append.elapsed);
+        self.index.record(&append.records, append.offset, observer);
+    }
+
+    fn flush(&mut self) -> Result<(), StorageError> {
+        Ok(self.pipeline_mut().flush()?)
+    }
+
+    fn sync(&mut self, observer: &dyn EngineObserver) -> Result<(), StorageError> {
+        let (flushed, elapsed) = timed(|| self.flush());
+        flushed?;
+        observer.wal_synced(elapsed);
+        Ok(())
+    }
+
+    fn close(
+        &mut self,
+        durability: &DurabilityState,
+        page_index: &HashMap<PageId, Lsn>,
+        page_versions: &HashMap<PageId, Vec<Lsn>>,
+        hot_pages: &[PageId],
+    ) -> Result<(), StorageError> {
+        let saved = Sidecar {
+            wal_len: std::fs::metadata(&self.wal_path)?.len(),
+            durability: durability.clone(),
+            page_index: page_index.clone(),
+            lsn_offsets: self.index.offsets.clone(),
+            page_versions: page_versions.clone(),
+            time_index: Some(self.index.time_index.clone()),
+            truncated_before: self.index.truncated_before,
+            hot_pages: hot_pages.to_vec(),
+        };
+        sidecar::save(&sidecar_path(&self.wal_path), &saved)?;
+        Ok(())
+    }
+
+    fn locate(&self, lsn: Lsn) -> Option<WalLocation> {
+        self.index.locate(lsn)
+    }
+
+    fn locations(&self) -> Vec<(Lsn, WalLocation)> {
+        self.index.locations()
+    }
+
+    fn located(&self) -> usize {
+        self.index.offsets.len()
+    }
+
+    fn index_bytes(&self) -> u64 {
+        table_bytes(&self.index.offsets)
+    }
+
+    fn open_reader(&self, _segment_id: Option<u32>) -> Result<(WalReader, Option<Tier>), StorageError> {
+        let reader = WalReader::open(&self.wal_path).map_err(StorageError::file("open", &self.wal_path))?;
+        Ok((reader, None))
+    }
+
+    fn collect_chain(
+        &self,
+        page_id: PageId,
+        start_lsn: Lsn,
+        read_point: Lsn,
+        floor: Lsn,
+        observer: &dyn EngineObserver,
+        _cold: &mut ColdFetches,
+    ) -> Result<Vec<RedoRecord>, StorageError> {
+        let (reader, _) = self.open_reader(None)?;
+        self.index.collect_chain(reader, page_id, start_lsn, read_point, floor, observer)
+    }
+
+    /// Nothing is deleted: the log is one file.
+    fn truncate_before(&mut self, lsn: Lsn, dropped: &[Lsn]) -> Result<Vec<(u32, u64)>, StorageError> {
+        self.index.truncate_before(lsn, dropped);
+        Ok(Vec::new())
+    }
+
+    fn truncated_before(&self) -> Lsn {
+        self.index.truncated_before
+    }
+
+    fn time_index(&self) -> &TimeIndex {
+        &self.index.time_index
+    }
+
+    fn rewind(&mut self, cpl: Option<(Lsn, Timestamp)>) {
+        self.index.rewind(cpl);
+    }
+
+    fn checksum(&self) -> ChecksumKind {
+        self.pipeline().checksum()
+    }
+
+    fn set_checksum(&mut self, checksum: ChecksumKind) {
+        self.pipeline_mut().set_checksum(checksum);
+    }
+
+    fn active_path(&self) -> PathBuf {
+        self.wal_path.clone()
+    }
+
+    fn page_store_dir(&self) -> Option<PathBuf> {
+        Some(store_path(&self.wal_path))
+    }
+
+    fn wal_file_size(&self) -> u64 {
+        self.wal_len().unwrap_or(0)
+    }
+
+    fn tier_stats(&self) -> (TierStats, TierStats) {
+        let hot = TierStats { segments: 1, bytes: self.wal_file_size(), ..Default::default() };
+        (hot, TierStats::default())
+    }
+
+    fn pipeline_stats(&self) -> PipelineStats {
+        self.pipeline().stats()
+    }
+}
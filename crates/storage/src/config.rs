@@ -1,5 +1,6 @@
 use std::path::PathBuf;
-use std::time::Duration;
+
+pub use mini_aurora_wal::disk::{DiskProfile, TierDisks};
 
 /// Which storage backend to use.
 pub enum StoragePreset {
@@ -7,14 +8,20 @@ pub enum StoragePreset {
     Base,
     /// Segmented WAL with hot/cold tiering.
     Tiered(TieredConfig),
+    /// WAL in memory, gone when the engine is dropped.
+    Memory,
 }
 
 /// Configuration for the tiered storage variant.
 pub struct TieredConfig {
     /// Maximum bytes per segment before rotation.
     pub segment_size_bytes: u64,
-    /// Artificial latency injected on cold-tier reads.
-    pub cold_latency: Duration,
+    /// Simulated disks of the hot and cold tiers.
+    pub disks: TierDisks,
     /// Base directory for segment files (hot/ and cold/ subdirs created within).
     pub base_dir: PathBuf,
+    /// Bytes per page for a new volume; an existing one must have been
+    /// created with them. `None` creates `PAGE_SIZE` pages, or takes an
+    /// existing volume's.
+    pub page_size: Option<usize>,
 }
@@ -0,0 +1,139 @@
+//! Materializations in flight, so concurrent misses on one page version
+//! walk its redo chain once.
+//!
+//! A read only releases the engine lock while it waits for cold segments,
+//! and that is when a second reader of the same version would otherwise
+//! start a walk of its own. The first reader leads; the others wait for it
+//! and take the image or error it lands, not the page cache's copy, which
+//! admission may have turned away. If the leader is dropped before it
+//! lands, they wake all the same and one of them leads again.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use mini_aurora_common::{Lsn, PageId, PageRef, StorageError};
+use tokio::sync::watch;
+
+/// What a leader's read came to.
+type Landed = Option<Result<PageRef, StorageError>>;
+
+/// Leaders by page version. A follower holds a receiver of its leader's
+/// channel, which carries the leader's result and closes when its
+/// `Flight` is dropped.
+#[derive(Default)]
+pub struct ReadFlights {
+    flights: Mutex<HashMap<(PageId, Lsn), watch::Receiver<Landed>>>,
+    coalesced: AtomicU64,
+}
+
+/// What a reader that missed the cache does next.
+pub enum Joined<'a> {
+    /// Materialize the version, then `land` the result for followers.
+    Lead(Flight<'a>),
+    /// Wait for the leader's result.
+    Follow(Landing),
+}
+
+/// A leader's claim on a page version.
+pub struct Flight<'a> {
+    flights: &'a ReadFlights,
+    key: (PageId, Lsn),
+    done: watch::Sender<Landed>,
+}
+
+/// A follower's wait for its leader.
+pub struct Landing(watch::Receiver<Landed>);
+
+impl ReadFlights {
+    /// Lead the materialization of `version` of `page_id`, or follow the
+    /// reader already doing it.
+    pub fn join(&self, page_id: PageId, version: Lsn) -> Joined<'_> {
+        let key = (page_id, version);
+        let mut flights = self.flights.lock().unwrap();
+        if let Some(done) = flights.get(&key) {
+            self.coalesced.fetch_add(1, Ordering::Relaxed);
+            return Joined::Follow(Landing(done.clone()));
+        }
+        let (done, receiver) = watch::channel(None);
+        flights.insert(key, receiver);
+        Joined::Lead(Flight { flights: self, key, done })
+    }
+
+    /// Reads that waited for another reader's walk instead of their own.
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+}
+
+impl Flight<'_> {
+    /// Hand the leader's result to every follower.
+    pub fn land(self, result: &Result<PageRef, StorageError>) {
+        let shared = result.as_ref().map(PageRef::clone).map_err(StorageError::share);
+        self.done.send_replace(Some(shared));
+    }
+}
+
+impl Landing {
+    /// The leader's result, or `None` if it was dropped without one.
+    pub async fn wait(mut self) -> Option<Result<PageRef, StorageError>> {
+        loop {
+            if let Some(result) = self.0.borrow_and_update().as_ref() {
+                return Some(result.as_ref().map(PageRef::clone).map_err(StorageError::share));
+            }
+            self.0.changed().await.ok()?;
+        }
+    }
+}
+
+impl Drop for Flight<'_> {
+    fn drop(&mut self) {
+        self.flights.flights.lock().unwrap().remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_followers_take_the_leaders_result() {
+        let flights = ReadFlights::default();
+        let Joined::Lead(flight) = flights.join(1, 5) else {
+            panic!("first reader should lead");
+        };
+        let Joined::Follow(first) = flights.join(1, 5) else {
+            panic!("second reader should follow");
+        };
+        let page = PageRef::from(vec![7u8; 8]);
+        flight.land(&Ok(page.clone()));
+        assert_eq!(first.wait().await.unwrap().unwrap(), page);
+
+        let Joined::Lead(flight) = flights.join(2, 5) else {
+            panic!("first reader should lead");
+        };
+        let Joined::Follow(second) = flights.join(2, 5) else {
+            panic!("second reader should follow");
+        };
+        flight.land(&Err(StorageError::PageNotFound { page_id: 2, lsn: 5 }));
+        assert!(matches!(second.wait().await, Some(Err(StorageError::PageNotFound { page_id: 2, .. }))));
+    }
+
+    #[tokio::test]
+    async fn test_followers_wake_when_the_leader_drops() {
+        let flights = ReadFlights::default();
+        let Joined::Lead(flight) = flights.join(1, 5) else {
+            panic!("first reader should lead");
+        };
+        let Joined::Follow(landing) = flights.join(1, 5) else {
+            panic!("second reader should follow");
+        };
+        assert!(matches!(flights.join(1, 6), Joined::Lead(_)));
+        assert_eq!(flights.coalesced(), 1);
+
+        drop(flight);
+        assert!(landing.wait().await.is_none());
+        // The version is free to lead again
+        assert!(matches!(flights.join(1, 5), Joined::Lead(_)));
+    }
+}
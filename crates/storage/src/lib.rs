@@ -1,2 +1,8 @@
+pub mod alerts;
+mod backend;
 pub mod config;
 pub mod engine;
+mod flights;
+pub mod lock;
+pub mod qos;
+pub mod readers;
@@ -0,0 +1,415 @@
+//! Admission control for storage requests.
+//!
+//! Each caller names itself and its `QosClass` when asking to be admitted.
+//! Callers draw from their own token bucket, sized by their own limit or
+//! their class's, so one busy caller can't use up another's budget.
+//! Background callers also wait until no foreground request is in flight.
+//!
+//! The engine admits every data request itself. A request's caller is
+//! named with `as_caller` around the call, or by sending requests through
+//! a `Tagged` storage; anything else is admitted as `ANONYMOUS`, in the
+//! foreground class.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use mini_aurora_common::{
+    AppendedRedo, Capabilities, CallerQos, DurabilityState, Lsn, PageId, PageRef, QosClass, QosReport,
+    QosTarget, RateLimit, RedoRecord, StorageApi, StorageError,
+};
+use tokio::sync::Notify;
+use tracing::debug;
+
+/// Caller name the engine's own scrubber is admitted under.
+pub const SCRUBBER: &str = "scrubber";
+
+/// Caller name for requests that don't say who sent them.
+pub const ANONYMOUS: &str = "anonymous";
+
+/// Who the request running on this task is for, and whether it has been
+/// admitted already.
+#[derive(Clone)]
+struct Request {
+    caller: String,
+    class: QosClass,
+    admitted: bool,
+}
+
+tokio::task_local! {
+    static REQUEST: Request;
+}
+
+/// Run `call`, with any storage request it makes admitted as `caller` in
+/// `class`. Inside a request that was already admitted, `call` just runs.
+pub async fn as_caller<F: Future>(caller: &str, class: QosClass, call: F) -> F::Output {
+    if REQUEST.try_with(|request| request.admitted).unwrap_or(false) {
+        return call.await;
+    }
+    let request = Request { caller: caller.to_string(), class, admitted: false };
+    REQUEST.scope(request, call).await
+}
+
+/// Per-caller token buckets plus foreground-first priority. Shared by
+/// every caller of one storage engine.
+#[derive(Default)]
+pub struct Qos {
+    state: Mutex<QosState>,
+    /// Woken when the last in-flight foreground request finishes.
+    foreground_idle: Notify,
+}
+
+#[derive(Default)]
+struct QosState {
+    foreground_limit: Option<RateLimit>,
+    background_limit: Option<RateLimit>,
+    callers: BTreeMap<String, Caller>,
+    foreground_in_flight: usize,
+}
+
+struct Caller {
+    class: QosClass,
+    limit: Option<RateLimit>,
+    /// Starts full; refilled at the limit's rate up to its burst.
+    tokens: f64,
+    refilled: Instant,
+    admitted: u64,
+    throttled: u64,
+    deferred: u64,
+    waited: Duration,
+}
+
+impl Caller {
+    fn new(class: QosClass) -> Self {
+        Self {
+            class,
+            limit: None,
+            tokens: f64::INFINITY,
+            refilled: Instant::now(),
+            admitted: 0,
+            throttled: 0,
+            deferred: 0,
+            waited: Duration::ZERO,
+        }
+    }
+
+    /// Take a token, or say how long until one is due.
+    fn take_token(&mut self, limit: Option<RateLimit>, now: Instant) -> Result<(), Duration> {
+        let Some(limit) = limit else {
+            return Ok(());
+        };
+        let rate = limit.rate.max(1) as f64;
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(limit.burst.max(1) as f64);
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+    }
+}
+
+/// Why a request isn't admitted yet.
+enum Wait {
+    Foreground,
+    Token(Duration),
+}
+
+/// An admitted request. A foreground permit holds back background callers
+/// until it is dropped.
+pub struct QosPermit {
+    qos: Arc<Qos>,
+    caller: String,
+    class: QosClass,
+}
+
+impl QosPermit {
+    /// Run `call` as the request this permit admitted, so the engine
+    /// doesn't admit the storage requests it makes a second time.
+    pub async fn covering<F: Future>(&self, call: F) -> F::Output {
+        let request = Request { caller: self.caller.clone(), class: self.class, admitted: true };
+        REQUEST.scope(request, call).await
+    }
+}
+
+impl Drop for QosPermit {
+    fn drop(&mut self) {
+        if self.class != QosClass::Foreground {
+            return;
+        }
+        let mut state = self.qos.state.lock().unwrap();
+        state.foreground_in_flight -= 1;
+        if state.foreground_in_flight == 0 {
+            self.qos.foreground_idle.notify_waiters();
+        }
+    }
+}
+
+impl Qos {
+    /// Wait until `caller` may send a request, and hold the returned permit
+    /// while it runs.
+    pub async fn admit(self: &Arc<Self>, caller: &str, class: QosClass) -> QosPermit {
+        let started = Instant::now();
+        let (mut throttled, mut deferred) = (false, false);
+        loop {
+            // Registered before checking, so a wakeup in between isn't lost
+            let mut idle = pin!(self.foreground_idle.notified());
+            idle.as_mut().enable();
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let limit = state.limit_for(caller, class);
+                if class == QosClass::Background && state.foreground_in_flight > 0 {
+                    Wait::Foreground
+                } else {
+                    let entry = state
+                        .callers
+                        .entry(caller.to_string())
+                        .or_insert_with(|| Caller::new(class));
+                    entry.class = class;
+                    match entry.take_token(limit, Instant::now()) {
+                        Ok(()) => {
+                            entry.admitted += 1;
+                            entry.throttled += throttled as u64;
+                            entry.deferred += deferred as u64;
+                            entry.waited += started.elapsed();
+                            if class == QosClass::Foreground {
+                                state.foreground_in_flight += 1;
+                            }
+                            return QosPermit {
+                                qos: Arc::clone(self),
+                                caller: caller.to_string(),
+                                class,
+                            };
+                        }
+                        Err(due) => Wait::Token(due),
+                    }
+                }
+            };
+            match wait {
+                Wait::Foreground => {
+                    deferred = true;
+                    debug!(caller, "deferred behind foreground requests");
+                    idle.await;
+                }
+                Wait::Token(due) => {
+                    throttled = true;
+                    debug!(caller, wait_us = due.as_micros() as u64, "rate limited");
+                    tokio::time::sleep(due).await;
+                }
+            }
+        }
+    }
+
+    /// Run `request` once it is admitted, as the caller `as_caller` named
+    /// or as `ANONYMOUS`, holding the permit until it finishes. A request
+    /// made from inside another admitted one isn't admitted again.
+    pub async fn enforce<F: Future>(self: &Arc<Self>, request: F) -> F::Output {
+        let current = REQUEST.try_with(Request::clone).ok();
+        if current.as_ref().is_some_and(|r| r.admitted) {
+            return request.await;
+        }
+        let (caller, class) = current
+            .map(|r| (r.caller, r.class))
+            .unwrap_or_else(|| (ANONYMOUS.to_string(), QosClass::Foreground));
+        let permit = self.admit(&caller, class).await;
+        permit.covering(request).await
+    }
+
+    pub fn set_limit(&self, target: QosTarget, limit: Option<RateLimit>) {
+        let mut state = self.state.lock().unwrap();
+        match target {
+            QosTarget::Class(QosClass::Foreground) => state.foreground_limit = limit,
+            QosTarget::Class(QosClass::Background) => state.background_limit = limit,
+            QosTarget::Caller(name) => {
+                // Limit a caller before its first request too; the class
+                // is corrected when it is admitted
+                state
+                    .callers
+                    .entry(name)
+                    .or_insert_with(|| Caller::new(QosClass::Foreground))
+                    .limit = limit;
+            }
+        }
+    }
+
+    pub fn report(&self) -> QosReport {
+        let state = self.state.lock().unwrap();
+        QosReport {
+            foreground_limit: state.foreground_limit,
+            background_limit: state.background_limit,
+            callers: state
+                .callers
+                .iter()
+                .map(|(name, c)| CallerQos {
+                    caller: name.clone(),
+                    class: c.class,
+                    limit: c.limit,
+                    admitted: c.admitted,
+                    throttled: c.throttled,
+                    deferred: c.deferred,
+                    waited_ms: c.waited.as_secs_f64() * 1000.0,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Storage whose data requests are all admitted as one caller, including
+/// those a compute engine sends from tasks of its own.
+pub struct Tagged {
+    storage: Arc<dyn StorageApi>,
+    caller: String,
+    class: QosClass,
+}
+
+impl Tagged {
+    pub fn new(storage: Arc<dyn StorageApi>, caller: &str, class: QosClass) -> Self {
+        Self { storage, caller: caller.to_string(), class }
+    }
+}
+
+#[async_trait]
+impl StorageApi for Tagged {
+    async fn append_redo_lsns(&self, records: Vec<RedoRecord>) -> Result<AppendedRedo, StorageError> {
+        as_caller(&self.caller, self.class, self.storage.append_redo_lsns(records)).await
+    }
+
+    async fn get_page(&self, page_id: PageId, read_point: Lsn) -> Result<PageRef, StorageError> {
+        as_caller(&self.caller, self.class, self.storage.get_page(page_id, read_point)).await
+    }
+
+    async fn get_bytes(
+        &self,
+        page_id: PageId,
+        offset: u16,
+        len: usize,
+        read_point: Lsn,
+    ) -> Result<Vec<u8>, StorageError> {
+        let call = self.storage.get_bytes(page_id, offset, len, read_point);
+        as_caller(&self.caller, self.class, call).await
+    }
+
+    async fn get_pages(
+        &self,
+        page_ids: &[PageId],
+        read_point: Lsn,
+    ) -> Vec<Result<PageRef, StorageError>> {
+        as_caller(&self.caller, self.class, self.storage.get_pages(page_ids, read_point)).await
+    }
+
+    async fn get_durability_state(&self) -> Result<DurabilityState, StorageError> {
+        as_caller(&self.caller, self.class, self.storage.get_durability_state()).await
+    }
+
+    async fn heartbeat(&self) -> Result<DurabilityState, StorageError> {
+        self.storage.heartbeat().await
+    }
+
+    fn page_size(&self) -> usize {
+        self.storage.page_size()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.storage.capabilities()
+    }
+
+    async fn handshake(&self, compute: &Capabilities) -> Result<Capabilities, StorageError> {
+        self.storage.handshake(compute).await
+    }
+
+    async fn register_reader(
+        &self,
+        reader: &str,
+        read_point: Lsn,
+        lease: Duration,
+    ) -> Result<(), StorageError> {
+        self.storage.register_reader(reader, read_point, lease).await
+    }
+
+    async fn deregister_reader(&self, reader: &str) -> Result<(), StorageError> {
+        self.storage.deregister_reader(reader).await
+    }
+
+    fn attach_writer(&self) {
+        self.storage.attach_writer();
+    }
+
+    fn detach_writer(&self) {
+        self.storage.detach_writer();
+    }
+
+    fn writers(&self) -> usize {
+        self.storage.writers()
+    }
+}
+
+impl QosState {
+    fn limit_for(&self, caller: &str, class: QosClass) -> Option<RateLimit> {
+        let own = self.callers.get(caller).and_then(|c| c.limit);
+        own.or(match class {
+            QosClass::Foreground => self.foreground_limit,
+            QosClass::Background => self.background_limit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limit_throttles_after_burst() {
+        let qos = Arc::new(Qos::default());
+        qos.set_limit(QosTarget::Class(QosClass::Background), Some(RateLimit { rate: 100, burst: 2 }));
+
+        let started = Instant::now();
+        for _ in 0..4 {
+            drop(qos.admit("bg A", QosClass::Background).await);
+        }
+        // Two from the burst, then one every 10ms
+        assert!(started.elapsed() >= Duration::from_millis(15));
+        // Foreground callers aren't limited by the background class
+        drop(qos.admit("A", QosClass::Foreground).await);
+
+        let report = qos.report();
+        let bg = &report.callers[1];
+        assert_eq!((bg.caller.as_str(), bg.admitted, bg.throttled), ("bg A", 4, 2));
+        assert_eq!((report.callers[0].caller.as_str(), report.callers[0].throttled), ("A", 0));
+    }
+
+    #[tokio::test]
+    async fn test_background_waits_for_foreground() {
+        let qos = Arc::new(Qos::default());
+        let fg = qos.admit("A", QosClass::Foreground).await;
+
+        let bg = tokio::spawn({
+            let qos = Arc::clone(&qos);
+            async move { drop(qos.admit("bg B", QosClass::Background).await) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!bg.is_finished());
+
+        drop(fg);
+        bg.await.unwrap();
+        let report = qos.report();
+        let b = report.callers.iter().find(|c| c.caller == "bg B").unwrap();
+        assert_eq!((b.admitted, b.deferred), (1, 1));
+        assert!(b.waited_ms >= 15.0);
+    }
+
+    #[test]
+    fn test_caller_limit_overrides_class() {
+        let qos = Qos::default();
+        let slow = RateLimit { rate: 1, burst: 1 };
+        qos.set_limit(QosTarget::Class(QosClass::Foreground), Some(RateLimit { rate: 50, burst: 5 }));
+        qos.set_limit(QosTarget::Caller("B".to_string()), Some(slow));
+        let state = qos.state.lock().unwrap();
+        assert_eq!(state.limit_for("B", QosClass::Foreground), Some(slow));
+        assert_eq!(state.limit_for("A", QosClass::Foreground).map(|l| l.rate), Some(50));
+        assert_eq!(state.limit_for("A", QosClass::Background), None);
+    }
+}
@@ -0,0 +1,92 @@
+//! Read points registered by compute nodes, each held under a lease.
+//!
+//! Storage may only discard a page version once no reader can still ask for
+//! it: every version above the minimum read point, and the newest one at or
+//! below it, must stay. A reader that stops renewing — it crashed, or lost
+//! its link — drops out when its lease runs out, so it can't pin history
+//! forever.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use mini_aurora_common::Lsn;
+
+/// One registered reader, as reported by `ReaderLeases::leases`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReaderLease {
+    pub reader: String,
+    pub read_point: Lsn,
+    /// Time left before the lease runs out.
+    pub remaining: Duration,
+}
+
+/// Live leases by reader name. Expired ones are dropped whenever the
+/// registry is read.
+#[derive(Default)]
+pub struct ReaderLeases {
+    leases: Mutex<BTreeMap<String, (Lsn, Instant)>>,
+}
+
+impl ReaderLeases {
+    /// Register `reader` at `read_point` for `lease`, replacing any earlier
+    /// registration.
+    pub fn register(&self, reader: &str, read_point: Lsn, lease: Duration) {
+        let expires = Instant::now() + lease;
+        self.leases.lock().unwrap().insert(reader.to_string(), (read_point, expires));
+    }
+
+    /// Returns whether `reader` was registered.
+    pub fn deregister(&self, reader: &str) -> bool {
+        self.leases.lock().unwrap().remove(reader).is_some()
+    }
+
+    /// The lowest read point under a live lease.
+    pub fn min_read_point(&self) -> Option<Lsn> {
+        self.live().values().map(|&(read_point, _)| read_point).min()
+    }
+
+    pub fn leases(&self) -> Vec<ReaderLease> {
+        let now = Instant::now();
+        self.live()
+            .iter()
+            .map(|(reader, &(read_point, expires))| ReaderLease {
+                reader: reader.clone(),
+                read_point,
+                remaining: expires.saturating_duration_since(now),
+            })
+            .collect()
+    }
+
+    /// The registry after dropping expired leases.
+    fn live(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, (Lsn, Instant)>> {
+        let now = Instant::now();
+        let mut leases = self.leases.lock().unwrap();
+        leases.retain(|_, &mut (_, expires)| expires > now);
+        leases
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_read_point_ignores_expired_leases() {
+        let leases = ReaderLeases::default();
+        assert_eq!(leases.min_read_point(), None);
+
+        leases.register("A", 7, Duration::from_secs(60));
+        leases.register("B", 3, Duration::ZERO);
+        assert_eq!(leases.min_read_point(), Some(7));
+        assert_eq!(leases.leases().len(), 1);
+
+        // Renewing moves the read point
+        leases.register("A", 9, Duration::from_secs(60));
+        leases.register("C", 5, Duration::from_secs(60));
+        assert_eq!(leases.min_read_point(), Some(5));
+        assert!(leases.deregister("C"));
+        assert!(!leases.deregister("C"));
+        assert_eq!(leases.min_read_point(), Some(9));
+    }
+}
@@ -0,0 +1,67 @@
+//! Write-path cost of each entry checksum on large payloads: encoding
+//! alone (headers, checksums and the copy into one buffer, as the append
+//! pipeline's encode stage does), and encoding plus the write to a file.
+//!
+//! Run with `cargo bench -p mini-aurora-wal --bench checksum`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use mini_aurora_common::RedoRecord;
+use mini_aurora_wal::checksum::ChecksumKind;
+use mini_aurora_wal::writer::{encode_entries, WalWriter};
+use tempfile::NamedTempFile;
+
+/// Records per batch.
+const BATCH: u64 = 16;
+
+fn batch(payload: usize) -> Vec<RedoRecord> {
+    (1..=BATCH)
+        .map(|lsn| RedoRecord {
+            lsn,
+            page_id: lsn,
+            offset: 0,
+            data: (0..payload).map(|i| (i as u64 * lsn) as u8).collect::<Vec<u8>>().into(),
+            prev_lsn: 0,
+            mtr_id: lsn,
+            is_mtr_end: true,
+            timestamp: 0,
+        })
+        .collect()
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    for payload in [8 * 1024, 60 * 1024] {
+        let records = batch(payload);
+        group.throughput(Throughput::Bytes(BATCH * payload as u64));
+        for kind in ChecksumKind::ALL {
+            group.bench_with_input(BenchmarkId::new(kind.name(), payload), &records, |b, records| {
+                b.iter(|| encode_entries(records, kind));
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_append(c: &mut Criterion) {
+    let mut group = c.benchmark_group("append");
+    let payload = 60 * 1024;
+    let records = batch(payload);
+    group.throughput(Throughput::Bytes(BATCH * payload as u64));
+    for kind in ChecksumKind::ALL {
+        group.bench_function(kind.name(), |b| {
+            b.iter_batched(
+                || {
+                    let file = NamedTempFile::new().unwrap();
+                    let writer = WalWriter::open(file.path()).unwrap().with_checksum(kind);
+                    (file, writer)
+                },
+                |(_file, mut writer)| writer.append_batch(&records).unwrap(),
+                BatchSize::PerIteration,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode, bench_append);
+criterion_main!(benches);
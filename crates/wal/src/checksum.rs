@@ -0,0 +1,152 @@
+//! Entry checksums. Each entry's flags byte says which algorithm covers it
+//! (bits 1-2; zero is CRC32, what every entry used before there was a
+//! choice), so a log can switch algorithm between appends and old logs
+//! read as they always did.
+//!
+//! The checksum field is four bytes: xxHash64 is folded to 32 bits.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crc32fast::Hasher;
+use xxhash_rust::xxh64::Xxh64;
+
+/// Bits of an entry's flags byte that hold its [`ChecksumKind`].
+const KIND_SHIFT: u8 = 1;
+const KIND_MASK: u8 = 0b11 << KIND_SHIFT;
+
+/// A checksum over an entry: its header without the checksum field, then
+/// its payload.
+pub trait Checksum: Send + Sync {
+    fn entry(&self, header_without_checksum: &[u8], data: &[u8]) -> u32;
+}
+
+/// CRC32 (IEEE), the original algorithm.
+pub struct Crc32;
+
+/// CRC32C (Castagnoli), using SSE4.2 or the ARM CRC instructions where the
+/// CPU has them.
+pub struct Crc32c;
+
+/// xxHash64 with seed 0, its two halves XORed together.
+pub struct XxHash64;
+
+impl Checksum for Crc32 {
+    fn entry(&self, header_without_checksum: &[u8], data: &[u8]) -> u32 {
+        let mut hasher = Hasher::new();
+        hasher.update(header_without_checksum);
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+impl Checksum for Crc32c {
+    fn entry(&self, header_without_checksum: &[u8], data: &[u8]) -> u32 {
+        crc32c::crc32c_append(crc32c::crc32c(header_without_checksum), data)
+    }
+}
+
+impl Checksum for XxHash64 {
+    fn entry(&self, header_without_checksum: &[u8], data: &[u8]) -> u32 {
+        let mut hasher = Xxh64::new(0);
+        hasher.update(header_without_checksum);
+        hasher.update(data);
+        let hash = hasher.digest();
+        (hash ^ (hash >> 32)) as u32
+    }
+}
+
+/// Which [`Checksum`] an entry carries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChecksumKind {
+    #[default]
+    Crc32,
+    Crc32c,
+    XxHash64,
+}
+
+impl ChecksumKind {
+    pub const ALL: [ChecksumKind; 3] = [Self::Crc32, Self::Crc32c, Self::XxHash64];
+
+    pub fn checksum(self) -> &'static dyn Checksum {
+        match self {
+            Self::Crc32 => &Crc32,
+            Self::Crc32c => &Crc32c,
+            Self::XxHash64 => &XxHash64,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Crc32 => "crc32",
+            Self::Crc32c => "crc32c",
+            Self::XxHash64 => "xxhash64",
+        }
+    }
+
+    /// `flags` with this kind recorded in it.
+    pub fn tag(self, flags: u8) -> u8 {
+        let code = match self {
+            Self::Crc32 => 0,
+            Self::Crc32c => 1,
+            Self::XxHash64 => 2,
+        };
+        (flags & !KIND_MASK) | (code << KIND_SHIFT)
+    }
+
+    /// The kind an entry's flags record; `None` for a code no algorithm
+    /// has, which only damage produces.
+    pub fn from_flags(flags: u8) -> Option<Self> {
+        match (flags & KIND_MASK) >> KIND_SHIFT {
+            0 => Some(Self::Crc32),
+            1 => Some(Self::Crc32c),
+            2 => Some(Self::XxHash64),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ChecksumKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ChecksumKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| format!("unknown checksum '{s}' (expected crc32, crc32c or xxhash64)"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kinds_roundtrip_through_flags() {
+        for kind in ChecksumKind::ALL {
+            for mtr_end in [0, 1] {
+                let flags = kind.tag(mtr_end);
+                assert_eq!(ChecksumKind::from_flags(flags), Some(kind));
+                assert_eq!(flags & 1, mtr_end);
+            }
+            assert_eq!(kind.name().parse(), Ok(kind));
+        }
+        assert_eq!(ChecksumKind::from_flags(0b111), None);
+        assert!("md5".parse::<ChecksumKind>().is_err());
+    }
+
+    #[test]
+    fn test_known_values() {
+        // Check values for "123456789"
+        assert_eq!(Crc32.entry(b"1234", b"56789"), 0xCBF4_3926);
+        assert_eq!(Crc32c.entry(b"1234", b"56789"), 0xE306_9283);
+        let hash = xxhash_rust::xxh64::xxh64(b"123456789", 0);
+        assert_eq!(XxHash64.entry(b"12345", b"6789"), (hash ^ (hash >> 32)) as u32);
+    }
+}
@@ -0,0 +1,448 @@
+//! Disk cost models for WAL I/O.
+//!
+//! Readers and writers report every entry read, append and fsync to a
+//! [`Disk`], which decides what it costs. [`HostDisk`] charges nothing, so
+//! only the host's own disk is felt. [`SimDisk`] charges by a
+//! [`DiskProfile`]: a seek (or first-byte) latency when an access does not
+//! continue where the previous one ended, transfer time at the profile's
+//! bandwidth, and a fixed cost per fsync, and sleeps for it. Demos then show
+//! the same hot-vs-cold gap on a laptop SSD as on a tmpfs. [`FaultyDisk`]
+//! makes writes or fsyncs fail, to test what happens when the disk fills up
+//! or goes bad.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const MB: u64 = 1024 * 1024;
+
+/// Where WAL I/O is charged. Each method returns the time the access was
+/// modelled to take.
+pub trait Disk: Send + Sync {
+    /// `len` bytes read from `file` at `offset`.
+    fn read(&self, file: &Path, offset: u64, len: u64) -> Duration;
+    /// `len` bytes written to `file` at `offset`.
+    fn write(&self, file: &Path, offset: u64, len: u64) -> Duration;
+    /// An fsync of `file`.
+    fn sync(&self, file: &Path) -> Duration;
+    /// Accesses charged so far.
+    fn stats(&self) -> DiskStats;
+    /// The error the next `op` on `file` fails with instead of reaching the
+    /// host, if it should fail.
+    fn fault(&self, _file: &Path, _op: DiskOp) -> Option<io::Error> {
+        None
+    }
+}
+
+/// An access a [`Disk`] can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskOp {
+    Write,
+    Sync,
+}
+
+/// The host's disk with no modelled cost.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HostDisk;
+
+impl Disk for HostDisk {
+    fn read(&self, _file: &Path, _offset: u64, _len: u64) -> Duration {
+        Duration::ZERO
+    }
+
+    fn write(&self, _file: &Path, _offset: u64, _len: u64) -> Duration {
+        Duration::ZERO
+    }
+
+    fn sync(&self, _file: &Path) -> Duration {
+        Duration::ZERO
+    }
+
+    fn stats(&self) -> DiskStats {
+        DiskStats::default()
+    }
+}
+
+/// Performance of a simulated device. A bandwidth of zero is unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskProfile {
+    pub name: &'static str,
+    /// Paid by an access that does not continue the previous one; for an
+    /// object store, the time to first byte of a request.
+    pub seek: Duration,
+    /// Bytes per second.
+    pub read_bandwidth: u64,
+    /// Bytes per second.
+    pub write_bandwidth: u64,
+    pub fsync: Duration,
+}
+
+impl DiskProfile {
+    /// No modelled cost: the host's disk as is.
+    pub const fn host() -> Self {
+        Self { name: "host", seek: Duration::ZERO, read_bandwidth: 0, write_bandwidth: 0, fsync: Duration::ZERO }
+    }
+
+    /// A local NVMe SSD.
+    pub const fn ssd() -> Self {
+        Self {
+            name: "ssd",
+            seek: Duration::from_micros(100),
+            read_bandwidth: 500 * MB,
+            write_bandwidth: 400 * MB,
+            fsync: Duration::from_millis(1),
+        }
+    }
+
+    /// A spinning disk.
+    pub const fn hdd() -> Self {
+        Self {
+            name: "hdd",
+            seek: Duration::from_millis(8),
+            read_bandwidth: 150 * MB,
+            write_bandwidth: 150 * MB,
+            fsync: Duration::from_millis(10),
+        }
+    }
+
+    /// Remote object storage: a long time to first byte, then fast reads.
+    pub const fn object_store() -> Self {
+        Self {
+            name: "object-store",
+            seek: Duration::from_millis(50),
+            read_bandwidth: 100 * MB,
+            write_bandwidth: 50 * MB,
+            fsync: Duration::from_millis(20),
+        }
+    }
+
+    /// This profile with another seek latency.
+    pub const fn with_seek(self, seek: Duration) -> Self {
+        Self { seek, ..self }
+    }
+
+    pub fn is_host(&self) -> bool {
+        *self == Self::host()
+    }
+
+    /// A disk charging this profile's costs: a [`HostDisk`] for the host
+    /// profile, a sleeping [`SimDisk`] otherwise.
+    pub fn disk(self) -> Arc<dyn Disk> {
+        if self.is_host() {
+            Arc::new(HostDisk)
+        } else {
+            Arc::new(SimDisk::new(self))
+        }
+    }
+
+    /// Like [`disk`](Self::disk), but the [`SimDisk`] only accounts each
+    /// cost, for callers that wait it out themselves.
+    pub fn accounting_disk(self) -> Arc<dyn Disk> {
+        if self.is_host() {
+            Arc::new(HostDisk)
+        } else {
+            Arc::new(SimDisk::accounting_only(self))
+        }
+    }
+
+    fn read_cost(&self, seek: bool, len: u64) -> Duration {
+        let seek = if seek { self.seek } else { Duration::ZERO };
+        seek + transfer_time(len, self.read_bandwidth)
+    }
+
+    fn write_cost(&self, seek: bool, len: u64) -> Duration {
+        let seek = if seek { self.seek } else { Duration::ZERO };
+        seek + transfer_time(len, self.write_bandwidth)
+    }
+}
+
+impl Default for DiskProfile {
+    fn default() -> Self {
+        Self::host()
+    }
+}
+
+impl FromStr for DiskProfile {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "host" => Ok(Self::host()),
+            "ssd" => Ok(Self::ssd()),
+            "hdd" => Ok(Self::hdd()),
+            "object-store" => Ok(Self::object_store()),
+            _ => Err(format!("unknown disk profile '{name}' (expected host, ssd, hdd or object-store)")),
+        }
+    }
+}
+
+impl fmt::Display for DiskProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_host() {
+            return f.write_str(self.name);
+        }
+        let bandwidth = |bytes: u64| if bytes == 0 { "unlimited".to_string() } else { format!("{} MB/s", bytes / MB) };
+        write!(
+            f,
+            "{} (seek {:?}, read {}, write {}, fsync {:?})",
+            self.name,
+            self.seek,
+            bandwidth(self.read_bandwidth),
+            bandwidth(self.write_bandwidth),
+            self.fsync
+        )
+    }
+}
+
+/// Disk profiles of the two storage tiers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TierDisks {
+    /// Holds the active segment and recently sealed ones.
+    pub hot: DiskProfile,
+    /// Holds cooled segments, fetched whole on a read.
+    pub cold: DiskProfile,
+}
+
+/// Accesses a disk has charged and the time they were modelled to take.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskStats {
+    pub reads: u64,
+    pub bytes_read: u64,
+    pub writes: u64,
+    pub bytes_written: u64,
+    pub syncs: u64,
+    /// Accesses that paid the seek latency.
+    pub seeks: u64,
+    pub busy: Duration,
+}
+
+/// A device simulated from a [`DiskProfile`]. It has one head, shared by
+/// every file on it, so interleaving files costs seeks as on real hardware.
+pub struct SimDisk {
+    profile: DiskProfile,
+    /// Sleep for each modelled cost, or only account it.
+    sleep: bool,
+    state: Mutex<SimState>,
+}
+
+#[derive(Default)]
+struct SimState {
+    /// The file and offset the last access ended at.
+    head: Option<(PathBuf, u64)>,
+    stats: DiskStats,
+}
+
+impl SimDisk {
+    /// A disk that sleeps for what each access costs.
+    pub fn new(profile: DiskProfile) -> Self {
+        Self { profile, sleep: true, state: Mutex::default() }
+    }
+
+    /// A disk that only accounts costs, for tests and fast replays.
+    pub fn accounting_only(profile: DiskProfile) -> Self {
+        Self { sleep: false, ..Self::new(profile) }
+    }
+
+    pub fn profile(&self) -> DiskProfile {
+        self.profile
+    }
+
+    /// Move the head to `offset` in `file`, returning whether that took a
+    /// seek, and leave it `len` bytes further on.
+    fn position(state: &mut SimState, file: &Path, offset: u64, len: u64) -> bool {
+        let seek = !matches!(&state.head, Some((f, end)) if f == file && *end == offset);
+        if seek {
+            state.stats.seeks += 1;
+        }
+        state.head = Some((file.to_path_buf(), offset + len));
+        seek
+    }
+
+    fn charge(&self, state: &mut SimState, cost: Duration) -> Duration {
+        state.stats.busy += cost;
+        if self.sleep && !cost.is_zero() {
+            thread::sleep(cost);
+        }
+        cost
+    }
+}
+
+impl Disk for SimDisk {
+    fn read(&self, file: &Path, offset: u64, len: u64) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let seek = Self::position(&mut state, file, offset, len);
+        state.stats.reads += 1;
+        state.stats.bytes_read += len;
+        self.charge(&mut state, self.profile.read_cost(seek, len))
+    }
+
+    fn write(&self, file: &Path, offset: u64, len: u64) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let seek = Self::position(&mut state, file, offset, len);
+        state.stats.writes += 1;
+        state.stats.bytes_written += len;
+        self.charge(&mut state, self.profile.write_cost(seek, len))
+    }
+
+    fn sync(&self, _file: &Path) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        state.stats.syncs += 1;
+        self.charge(&mut state, self.profile.fsync)
+    }
+
+    fn stats(&self) -> DiskStats {
+        self.state.lock().unwrap().stats
+    }
+}
+
+/// Another disk whose writes or fsyncs fail on demand. A failing access
+/// never reaches the host: a failed write leaves the file as it was, and a
+/// failed fsync leaves earlier writes in the host's page cache, unsynced.
+pub struct FaultyDisk {
+    inner: Arc<dyn Disk>,
+    /// Accesses that fail, and the kind of error they fail with.
+    faults: Mutex<Vec<(DiskOp, io::ErrorKind)>>,
+}
+
+impl FaultyDisk {
+    /// `inner` with no faults yet.
+    pub fn new(inner: Arc<dyn Disk>) -> Self {
+        Self { inner, faults: Mutex::default() }
+    }
+
+    /// Fail every `op` from now on with an error of `kind`, until `heal`.
+    pub fn fail(&self, op: DiskOp, kind: io::ErrorKind) {
+        let mut faults = self.faults.lock().unwrap();
+        faults.retain(|(failing, _)| *failing != op);
+        faults.push((op, kind));
+    }
+
+    /// Let every access through again.
+    pub fn heal(&self) {
+        self.faults.lock().unwrap().clear();
+    }
+}
+
+impl Disk for FaultyDisk {
+    fn read(&self, file: &Path, offset: u64, len: u64) -> Duration {
+        self.inner.read(file, offset, len)
+    }
+
+    fn write(&self, file: &Path, offset: u64, len: u64) -> Duration {
+        self.inner.write(file, offset, len)
+    }
+
+    fn sync(&self, file: &Path) -> Duration {
+        self.inner.sync(file)
+    }
+
+    fn stats(&self) -> DiskStats {
+        self.inner.stats()
+    }
+
+    fn fault(&self, file: &Path, op: DiskOp) -> Option<io::Error> {
+        let faults = self.faults.lock().unwrap();
+        let &(_, kind) = faults.iter().find(|(failing, _)| *failing == op)?;
+        let access = match op {
+            DiskOp::Write => "write",
+            DiskOp::Sync => "fsync",
+        };
+        Some(io::Error::new(kind, format!("injected {access} fault on {}", file.display())))
+    }
+}
+
+fn transfer_time(len: u64, bandwidth: u64) -> Duration {
+    if bandwidth == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_nanos((len as u128 * 1_000_000_000 / bandwidth as u128) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> DiskProfile {
+        DiskProfile {
+            name: "test",
+            seek: Duration::from_millis(5),
+            read_bandwidth: MB,
+            write_bandwidth: MB / 2,
+            fsync: Duration::from_millis(2),
+        }
+    }
+
+    #[test]
+    fn test_sequential_reads_pay_one_seek() {
+        let disk = SimDisk::accounting_only(profile());
+        let file = Path::new("a.seg");
+        assert_eq!(disk.read(file, 0, MB), Duration::from_millis(1005));
+        assert_eq!(disk.read(file, MB, MB / 4), Duration::from_millis(250));
+        // Back to the start: a seek again
+        assert_eq!(disk.read(file, 0, 0), Duration::from_millis(5));
+
+        let stats = disk.stats();
+        assert_eq!((stats.reads, stats.bytes_read, stats.seeks), (3, MB + MB / 4, 2));
+        assert_eq!(stats.busy, Duration::from_millis(1260));
+    }
+
+    #[test]
+    fn test_switching_files_moves_the_head() {
+        let disk = SimDisk::accounting_only(profile());
+        let (a, b) = (Path::new("a.seg"), Path::new("b.seg"));
+        disk.write(a, 0, 100);
+        disk.write(a, 100, 100);
+        disk.read(b, 200, 100);
+        disk.write(a, 200, 100);
+        assert_eq!(disk.stats().seeks, 3);
+    }
+
+    #[test]
+    fn test_writes_and_syncs_use_their_own_costs() {
+        let disk = SimDisk::accounting_only(profile());
+        let file = Path::new("a.seg");
+        assert_eq!(disk.write(file, 0, MB / 2), Duration::from_millis(1005));
+        assert_eq!(disk.sync(file), Duration::from_millis(2));
+        let stats = disk.stats();
+        assert_eq!((stats.writes, stats.bytes_written, stats.syncs), (1, MB / 2, 1));
+    }
+
+    #[test]
+    fn test_sleeping_disk_takes_the_modelled_time() {
+        let disk = SimDisk::new(DiskProfile::host().with_seek(Duration::from_millis(20)));
+        let started = std::time::Instant::now();
+        disk.read(Path::new("a.seg"), 0, 10);
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_profiles_parse_by_name() {
+        assert_eq!("object-store".parse::<DiskProfile>(), Ok(DiskProfile::object_store()));
+        assert!("floppy".parse::<DiskProfile>().unwrap_err().contains("unknown disk profile"));
+        assert!(DiskProfile::host().is_host());
+        assert!(!DiskProfile::host().with_seek(Duration::from_millis(1)).is_host());
+        assert_eq!(DiskProfile::host().to_string(), "host");
+    }
+
+    #[test]
+    fn test_faulty_disk_fails_until_healed() {
+        let disk = FaultyDisk::new(Arc::new(SimDisk::accounting_only(profile())));
+        let file = Path::new("a.seg");
+        assert!(disk.fault(file, DiskOp::Write).is_none());
+        disk.fail(DiskOp::Sync, io::ErrorKind::StorageFull);
+        assert!(disk.fault(file, DiskOp::Write).is_none());
+        assert_eq!(disk.fault(file, DiskOp::Sync).unwrap().kind(), io::ErrorKind::StorageFull);
+        assert_eq!(disk.fault(file, DiskOp::Sync).unwrap().kind(), io::ErrorKind::StorageFull);
+
+        // Costs still go to the disk underneath
+        assert_eq!(disk.sync(file), Duration::from_millis(2));
+        assert_eq!(disk.stats().syncs, 1);
+
+        disk.heal();
+        assert!(disk.fault(file, DiskOp::Sync).is_none());
+    }
+}
@@ -0,0 +1,217 @@
+//! Segment footers: a fixed-size trailer written when a segment is sealed,
+//! summarising its records. Checking a footer takes one sequential read of
+//! the segment and no per-record work, so whole-segment damage (a torn
+//! copy, a truncated upload, flipped bits anywhere) shows up cheaply.
+//!
+//! The footer is shorter than an entry header, so a forward scan reaching
+//! it ends there as it would at a torn tail.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crc32fast::Hasher;
+use mini_aurora_common::{Lsn, LOG_ENTRY_HEADER_SIZE};
+
+use crate::disk::Disk;
+
+/// First bytes of every footer.
+pub const FOOTER_MAGIC: [u8; 8] = *b"MAWALFTR";
+
+/// Bytes in an encoded footer.
+pub const FOOTER_SIZE: usize = 48;
+
+/// What a sealed segment held when its footer was written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentFooter {
+    pub record_count: u64,
+    pub lsn_range: (Lsn, Lsn),
+    /// Bytes of records before the footer.
+    pub records_len: u64,
+    /// CRC32 of those bytes.
+    pub crc32: u32,
+}
+
+/// Outcome of checking a sealed segment against its footer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FooterCheck {
+    /// The records match the footer.
+    Intact,
+    /// The segment was sealed before segments had footers.
+    Missing,
+    /// What no longer matches.
+    Damaged(String),
+}
+
+impl SegmentFooter {
+    /// Summarise `records`, the whole of a segment's records.
+    pub fn for_records(records: &[u8], lsn_range: (Lsn, Lsn)) -> Self {
+        let mut hasher = Hasher::new();
+        hasher.update(records);
+        Self {
+            record_count: count_records(records).unwrap_or(0),
+            lsn_range,
+            records_len: records.len() as u64,
+            crc32: hasher.finalize(),
+        }
+    }
+
+    pub fn encode(&self) -> [u8; FOOTER_SIZE] {
+        let mut buf = [0u8; FOOTER_SIZE];
+        buf[0..8].copy_from_slice(&FOOTER_MAGIC);
+        buf[8..16].copy_from_slice(&self.record_count.to_le_bytes());
+        buf[16..24].copy_from_slice(&self.lsn_range.0.to_le_bytes());
+        buf[24..32].copy_from_slice(&self.lsn_range.1.to_le_bytes());
+        buf[32..40].copy_from_slice(&self.records_len.to_le_bytes());
+        buf[40..44].copy_from_slice(&self.crc32.to_le_bytes());
+        let crc = crc32fast::hash(&buf[..FOOTER_SIZE - 4]);
+        buf[44..48].copy_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    /// Decode a footer, or `None` if `buf` does not start with the magic.
+    /// A footer whose own CRC fails decodes to an error.
+    pub fn decode(buf: &[u8; FOOTER_SIZE]) -> Option<Result<Self, String>> {
+        if buf[0..8] != FOOTER_MAGIC {
+            return None;
+        }
+        let u64_at = |i: usize| u64::from_le_bytes(buf[i..i + 8].try_into().unwrap());
+        let crc = u32::from_le_bytes(buf[44..48].try_into().unwrap());
+        if crc != crc32fast::hash(&buf[..FOOTER_SIZE - 4]) {
+            return Some(Err("footer checksum mismatch".to_string()));
+        }
+        Some(Ok(Self {
+            record_count: u64_at(8),
+            lsn_range: (u64_at(16), u64_at(24)),
+            records_len: u64_at(32),
+            crc32: u32::from_le_bytes(buf[40..44].try_into().unwrap()),
+        }))
+    }
+}
+
+/// Append `footer` to the segment at `path`, charging the write to `disk`.
+/// Durable once the file is next synced.
+pub fn append_footer(path: &Path, footer: &SegmentFooter, disk: &dyn Disk) -> Result<(), std::io::Error> {
+    let mut file = OpenOptions::new().append(true).open(path)?;
+    let offset = file.metadata()?.len();
+    file.write_all(&footer.encode())?;
+    disk.write(path, offset, FOOTER_SIZE as u64);
+    Ok(())
+}
+
+/// Read the segment at `path`, charged to `disk`, and check it against its
+/// footer and the LSN range the manifest gives it. `expected` says whether
+/// the segment was sealed with a footer: if so, one that is gone counts as
+/// damage.
+pub fn verify_footer(
+    path: &Path,
+    lsn_range: (Lsn, Lsn),
+    expected: bool,
+    disk: &dyn Disk,
+) -> Result<FooterCheck, std::io::Error> {
+    let bytes = fs::read(path)?;
+    disk.read(path, 0, bytes.len() as u64);
+    let Some(split) = bytes.len().checked_sub(FOOTER_SIZE) else {
+        return Ok(missing(expected));
+    };
+    let (records, trailer) = bytes.split_at(split);
+    let footer = match SegmentFooter::decode(trailer.try_into().unwrap()) {
+        None => return Ok(missing(expected)),
+        Some(Err(e)) => return Ok(FooterCheck::Damaged(e)),
+        Some(Ok(footer)) => footer,
+    };
+
+    let actual = SegmentFooter::for_records(records, lsn_range);
+    let damage = if footer.records_len != actual.records_len {
+        format!("{} bytes of records, footer says {}", actual.records_len, footer.records_len)
+    } else if footer.crc32 != actual.crc32 {
+        "records checksum mismatch".to_string()
+    } else if footer.record_count != actual.record_count {
+        format!("{} records, footer says {}", actual.record_count, footer.record_count)
+    } else if footer.lsn_range != lsn_range {
+        format!("LSNs {:?} in the manifest, footer says {:?}", lsn_range, footer.lsn_range)
+    } else {
+        return Ok(FooterCheck::Intact);
+    };
+    Ok(FooterCheck::Damaged(damage))
+}
+
+fn missing(expected: bool) -> FooterCheck {
+    if expected {
+        FooterCheck::Damaged("footer missing".to_string())
+    } else {
+        FooterCheck::Missing
+    }
+}
+
+/// Entries in `records`, from their headers alone; `None` if the last one
+/// runs past the end.
+fn count_records(records: &[u8]) -> Option<u64> {
+    let mut offset = 0;
+    let mut count = 0;
+    while offset < records.len() {
+        let header = records.get(offset..offset + LOG_ENTRY_HEADER_SIZE)?;
+        let data_len = u16::from_le_bytes(header[18..20].try_into().unwrap()) as usize;
+        offset += LOG_ENTRY_HEADER_SIZE + data_len;
+        count += 1;
+    }
+    (offset == records.len()).then_some(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::HostDisk;
+    use crate::writer::WalWriter;
+    use mini_aurora_common::RedoRecord;
+    use tempfile::NamedTempFile;
+
+    fn write_segment(path: &Path) -> SegmentFooter {
+        let records: Vec<RedoRecord> = (1..=3)
+            .map(|lsn| RedoRecord {
+                lsn, page_id: 1, offset: 0, data: vec![lsn as u8; 8].into(),
+                prev_lsn: lsn - 1, mtr_id: lsn, is_mtr_end: true, timestamp: 0,
+            })
+            .collect();
+        let mut writer = WalWriter::open(path).unwrap();
+        writer.append_batch(&records).unwrap();
+        writer.sync().unwrap();
+        SegmentFooter::for_records(&fs::read(path).unwrap(), (1, 3))
+    }
+
+    #[test]
+    fn test_footer_roundtrip() {
+        let tmp = NamedTempFile::new().unwrap();
+        let footer = write_segment(tmp.path());
+        assert_eq!(footer.record_count, 3);
+        assert_eq!(SegmentFooter::decode(&footer.encode()), Some(Ok(footer)));
+
+        let mut buf = footer.encode();
+        buf[20] ^= 0xFF;
+        assert_eq!(SegmentFooter::decode(&buf), Some(Err("footer checksum mismatch".to_string())));
+        assert_eq!(SegmentFooter::decode(&[0; FOOTER_SIZE]), None);
+    }
+
+    #[test]
+    fn test_verify_footer_detects_damage_anywhere() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path();
+        assert_eq!(verify_footer(path, (0, 0), false, &HostDisk).unwrap(), FooterCheck::Missing);
+
+        let footer = write_segment(path);
+        assert_eq!(verify_footer(path, (1, 3), false, &HostDisk).unwrap(), FooterCheck::Missing);
+        assert!(matches!(verify_footer(path, (1, 3), true, &HostDisk).unwrap(), FooterCheck::Damaged(_)));
+        append_footer(path, &footer, &HostDisk).unwrap();
+        assert_eq!(verify_footer(path, (1, 3), true, &HostDisk).unwrap(), FooterCheck::Intact);
+
+        // The manifest and footer disagree on the LSNs
+        assert!(matches!(verify_footer(path, (1, 4), true, &HostDisk).unwrap(), FooterCheck::Damaged(_)));
+
+        // A flipped bit in the middle record's payload
+        let mut bytes = fs::read(path).unwrap();
+        bytes[2 * LOG_ENTRY_HEADER_SIZE + 8] ^= 0x01;
+        fs::write(path, &bytes).unwrap();
+        let check = verify_footer(path, (1, 3), true, &HostDisk).unwrap();
+        assert_eq!(check, FooterCheck::Damaged("records checksum mismatch".to_string()));
+    }
+}
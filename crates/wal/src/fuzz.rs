@@ -0,0 +1,364 @@
+//! Randomized checks of WAL recovery: write a record sequence, damage the
+//! file (a cut, then flipped bytes), recover it, and compare what recovery
+//! kept with what the undamaged prefix of the file allows.
+//!
+//! Every entry is CRC-protected and recovery stops at the first bad or torn
+//! one, so the entries that survive are exactly those before the first
+//! damaged byte. From them follow the VCL (end of the contiguous LSN run),
+//! the VDL (last CPL at or below it) and the records recovery must keep.
+//! The exception is a flip in the first entry: recovery may then refuse the
+//! log as unrecognisable, and must leave it as it was.
+//! The proptest suite in this module and `mini-aurora fuzz` both run cases
+//! through `check_case`.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use mini_aurora_common::{Lsn, PageId, RedoRecord, Timestamp, LOG_ENTRY_HEADER_SIZE};
+
+use crate::recovery::recover;
+use crate::writer::WalWriter;
+
+/// Pages records write to; few, so chains get long.
+const FUZZ_PAGES: u64 = 4;
+const MAX_MTRS: usize = 12;
+const MAX_MTR_RECORDS: usize = 4;
+const MAX_PAYLOAD: usize = 32;
+
+/// One generated WAL and the damage done to it before recovery.
+#[derive(Debug, Clone)]
+pub struct FuzzCase {
+    /// Records in file order. LSNs count up from 1 but skip records that
+    /// were dropped, leaving gaps.
+    pub records: Vec<RedoRecord>,
+    /// Cut the file to this many bytes.
+    pub truncate_to: Option<u64>,
+    /// (file offset, XOR mask) flips, applied after the cut; ones past the
+    /// end of the file are ignored.
+    pub flips: Vec<(u64, u8)>,
+}
+
+/// What recovery made of a case that passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzOutcome {
+    pub written: usize,
+    /// Entries before the first damaged byte.
+    pub intact: usize,
+    /// Records recovery kept: those at or below the VDL.
+    pub recovered: usize,
+    pub vcl: Lsn,
+    pub vdl: Lsn,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FuzzError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// Recovery broke one of its guarantees.
+    #[error("{0}")]
+    Property(String),
+}
+
+impl FuzzCase {
+    /// Records for `mtrs`, each a list of (page, payload) writes, with LSNs
+    /// from 1 and `prev_lsn` linking each page's chain. Every MTR ends in a
+    /// CPL except the last when `torn_tail`.
+    pub fn records(mtrs: &[Vec<(PageId, Vec<u8>)>], torn_tail: bool) -> Vec<RedoRecord> {
+        let mut latest: HashMap<PageId, Lsn> = HashMap::new();
+        let mut records = Vec::new();
+        for (i, writes) in mtrs.iter().enumerate() {
+            let complete = !(torn_tail && i == mtrs.len() - 1);
+            for (j, (page_id, data)) in writes.iter().enumerate() {
+                let lsn = records.len() as Lsn + 1;
+                records.push(RedoRecord {
+                    lsn,
+                    page_id: *page_id,
+                    offset: 0,
+                    data: data.clone().into(),
+                    prev_lsn: latest.insert(*page_id, lsn).unwrap_or(0),
+                    mtr_id: i as u64 + 1,
+                    is_mtr_end: complete && j == writes.len() - 1,
+                    timestamp: 1_700_000_000_000_000 + lsn as Timestamp,
+                });
+            }
+        }
+        records
+    }
+
+    /// A case drawn from `seed`, the same one for the same seed.
+    pub fn generate(seed: u64) -> Self {
+        let mut rng = SplitMix64(seed);
+        let mtrs: Vec<Vec<(PageId, Vec<u8>)>> = (0..rng.below(MAX_MTRS as u64 + 1))
+            .map(|_| {
+                (0..1 + rng.below(MAX_MTR_RECORDS as u64))
+                    .map(|_| {
+                        let len = rng.below(MAX_PAYLOAD as u64 + 1) as usize;
+                        (1 + rng.below(FUZZ_PAGES), (0..len).map(|_| rng.next_u64() as u8).collect())
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut records = Self::records(&mtrs, rng.below(3) == 0);
+        if !records.is_empty() && rng.below(5) == 0 {
+            records.remove(rng.below(records.len() as u64) as usize);
+        }
+
+        let len = wal_len(&records);
+        let truncate_to = (rng.below(3) == 0).then(|| rng.below(len + 1));
+        let flips = (0..rng.below(3))
+            .filter(|_| len > 0)
+            .map(|_| (rng.below(len), 1 + rng.below(255) as u8))
+            .collect();
+        Self { records, truncate_to, flips }
+    }
+
+    /// The records whose entries lie wholly before the first damaged byte.
+    pub fn intact(&self) -> &[RedoRecord] {
+        let cut = self.truncate_to.unwrap_or(u64::MAX);
+        let first_flip = self.flips.iter().map(|&(offset, _)| offset).filter(|&offset| offset < cut).min();
+        let damaged_at = first_flip.unwrap_or(u64::MAX).min(cut);
+        let mut end = 0;
+        let count = self
+            .records
+            .iter()
+            .take_while(|record| {
+                end += entry_len(record);
+                end <= damaged_at
+            })
+            .count();
+        &self.records[..count]
+    }
+}
+
+/// Bytes `records` take in the WAL.
+pub fn wal_len(records: &[RedoRecord]) -> u64 {
+    records.iter().map(entry_len).sum()
+}
+
+fn entry_len(record: &RedoRecord) -> u64 {
+    (LOG_ENTRY_HEADER_SIZE + record.data.len()) as u64
+}
+
+/// Write `case` to a fresh WAL at `path`, damage it, recover it twice and
+/// check recovery against what the intact entries allow:
+/// - the VCL ends the contiguous run of intact LSNs and the VDL is the last
+///   CPL at or below it;
+/// - exactly the intact records up to the VDL are indexed, at their offsets;
+/// - every recovered MTR is complete, so none is resurrected without its CPL;
+/// - the file is cut just past the VDL, and recovering again keeps the same
+///   records with the VCL now at the VDL;
+/// - a refusal comes only from a flip in a whole first entry, and leaves the
+///   file untouched.
+pub fn check_case(path: &Path, case: &FuzzCase) -> Result<FuzzOutcome, FuzzError> {
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    let mut writer = WalWriter::open(path)?;
+    writer.append_batch(&case.records)?;
+    writer.sync()?;
+    drop(writer);
+    damage(path, case)?;
+
+    let intact = case.intact();
+    let present: BTreeSet<Lsn> = intact.iter().map(|r| r.lsn).collect();
+    let vcl = (1..).take_while(|lsn| present.contains(lsn)).last().unwrap_or(0);
+    let vdl = intact.iter().filter(|r| r.is_mtr_end && r.lsn <= vcl).map(|r| r.lsn).max().unwrap_or(0);
+    let mut offset = 0;
+    let mut expected: HashMap<Lsn, u64> = HashMap::new();
+    for record in intact {
+        if record.lsn <= vdl {
+            expected.insert(record.lsn, offset);
+        }
+        offset += entry_len(record);
+    }
+
+    let damaged_len = fs::metadata(path)?.len();
+    let result = match recover(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+            let first_len = case.records.first().map_or(0, entry_len);
+            let flipped = case.flips.iter().any(|&(offset, _)| offset < first_len);
+            if !intact.is_empty() || !flipped || damaged_len < first_len {
+                return Err(property(format!("recovery refused a log it should have read: {e}")));
+            }
+            if fs::metadata(path)?.len() != damaged_len {
+                return Err(property("recovery refused the log but still cut it".to_string()));
+            }
+            return Ok(FuzzOutcome { written: case.records.len(), intact: 0, recovered: 0, vcl: 0, vdl: 0 });
+        }
+        result => result?,
+    };
+    let found = (result.durability.vcl, result.durability.vdl);
+    if found != (vcl, vdl) {
+        return Err(property(format!("recovered (VCL, VDL) {found:?}, expected ({vcl}, {vdl})")));
+    }
+    if result.lsn_offsets != expected {
+        return Err(property(format!(
+            "recovered records {:?}, expected {:?}",
+            sorted(&result.lsn_offsets),
+            sorted(&expected)
+        )));
+    }
+    let ended: BTreeSet<u64> = intact
+        .iter()
+        .filter(|r| r.is_mtr_end && expected.contains_key(&r.lsn))
+        .map(|r| r.mtr_id)
+        .collect();
+    if let Some(record) = intact.iter().find(|r| expected.contains_key(&r.lsn) && !ended.contains(&r.mtr_id)) {
+        return Err(property(format!("L{} of incomplete MTR {} was recovered", record.lsn, record.mtr_id)));
+    }
+    let mut newest: HashMap<PageId, Lsn> = HashMap::new();
+    for record in intact.iter().filter(|r| r.lsn <= vdl) {
+        newest.insert(record.page_id, record.lsn);
+    }
+    if result.page_index != newest {
+        return Err(property(format!("recovered page index {:?}, expected {newest:?}", result.page_index)));
+    }
+
+    let cut_at = intact.iter().find(|r| r.lsn == vdl).map_or(0, |r| expected[&r.lsn] + entry_len(r));
+    let len = fs::metadata(path)?.len();
+    if len != cut_at {
+        return Err(property(format!("WAL is {len} bytes after recovery, expected a cut at {cut_at}")));
+    }
+    let again = recover(path)?;
+    let found = (again.durability.vcl, again.durability.vdl);
+    if found != (vdl, vdl) || again.lsn_offsets != expected {
+        return Err(property(format!(
+            "second recovery found (VCL, VDL) {found:?} and records {:?}",
+            sorted(&again.lsn_offsets)
+        )));
+    }
+
+    Ok(FuzzOutcome {
+        written: case.records.len(),
+        intact: intact.len(),
+        recovered: expected.len(),
+        vcl,
+        vdl,
+    })
+}
+
+/// Apply the case's cut and byte flips to the WAL at `path`.
+fn damage(path: &Path, case: &FuzzCase) -> Result<(), std::io::Error> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    if let Some(len) = case.truncate_to {
+        file.set_len(len)?;
+    }
+    let len = file.metadata()?.len();
+    for &(offset, mask) in case.flips.iter().filter(|&&(offset, _)| offset < len) {
+        let mut byte = [0u8];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut byte)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(&[byte[0] ^ mask])?;
+    }
+    file.sync_all()
+}
+
+fn property(message: String) -> FuzzError {
+    FuzzError::Property(message)
+}
+
+fn sorted(offsets: &HashMap<Lsn, u64>) -> Vec<(Lsn, u64)> {
+    let mut offsets: Vec<_> = offsets.iter().map(|(&lsn, &offset)| (lsn, offset)).collect();
+    offsets.sort_unstable();
+    offsets
+}
+
+/// Small seedable PRNG, so a seed reproduces its case without a dependency.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, n)`; 0 when `n` is 0.
+    fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            self.next_u64() % n
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::sample::Index;
+    use tempfile::TempDir;
+
+    fn mtrs() -> impl Strategy<Value = Vec<Vec<(PageId, Vec<u8>)>>> {
+        let write = (1..=FUZZ_PAGES, prop::collection::vec(any::<u8>(), 0..=MAX_PAYLOAD));
+        prop::collection::vec(prop::collection::vec(write, 1..=MAX_MTR_RECORDS), 0..=MAX_MTRS)
+    }
+
+    /// Records, maybe one dropped, then a cut and flips placed within them.
+    fn cases() -> impl Strategy<Value = FuzzCase> {
+        (
+            mtrs(),
+            any::<bool>(),
+            any::<Option<Index>>(),
+            any::<Option<Index>>(),
+            prop::collection::vec((any::<Index>(), 1..=u8::MAX), 0..3),
+        )
+            .prop_map(|(mtrs, torn_tail, dropped, cut, flips)| {
+                let mut records = FuzzCase::records(&mtrs, torn_tail);
+                if let (Some(index), false) = (dropped, records.is_empty()) {
+                    records.remove(index.index(records.len()));
+                }
+                let len = wal_len(&records) as usize;
+                FuzzCase {
+                    truncate_to: cut.map(|index| index.index(len + 1) as u64),
+                    flips: flips
+                        .into_iter()
+                        .filter(|_| len > 0)
+                        .map(|(index, mask)| (index.index(len) as u64, mask))
+                        .collect(),
+                    records,
+                }
+            })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(256))]
+
+        #[test]
+        fn prop_recovery_keeps_exactly_the_durable_prefix(case in cases()) {
+            let dir = TempDir::new().unwrap();
+            if let Err(e) = check_case(&dir.path().join("fuzz.wal"), &case) {
+                prop_assert!(false, "{e}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_cases_pass() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("fuzz.wal");
+        for seed in 0..64 {
+            let case = FuzzCase::generate(seed);
+            assert_eq!(FuzzCase::generate(seed).records, case.records);
+            check_case(&path, &case).unwrap_or_else(|e| panic!("seed {seed}: {e}"));
+        }
+    }
+
+    #[test]
+    fn test_flip_in_torn_mtr_keeps_earlier_cpl() {
+        let dir = TempDir::new().unwrap();
+        let mtrs = vec![vec![(1, b"a".to_vec())], vec![(2, b"bb".to_vec()), (1, b"c".to_vec())]];
+        let records = FuzzCase::records(&mtrs, false);
+        // Damage the last record, so its MTR loses its CPL
+        let flip_at = wal_len(&records[..2]) + 3;
+        let case = FuzzCase { records, truncate_to: None, flips: vec![(flip_at, 0x40)] };
+        let outcome = check_case(&dir.path().join("fuzz.wal"), &case).unwrap();
+        assert_eq!(outcome, FuzzOutcome { written: 3, intact: 2, recovered: 1, vcl: 2, vdl: 1 });
+    }
+}
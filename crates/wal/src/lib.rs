@@ -1,4 +1,13 @@
+pub mod checksum;
+pub mod disk;
+pub mod footer;
+pub mod fuzz;
+pub mod memory;
+pub mod pipeline;
 pub mod reader;
 pub mod recovery;
 pub mod segment;
+pub mod sidecar;
+pub mod time_index;
+pub mod volume;
 pub mod writer;
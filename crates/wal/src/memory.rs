@@ -0,0 +1,152 @@
+//! A WAL kept in memory, for builds with no filesystem such as wasm32 in a
+//! browser. Entries are encoded as in a WAL file, checksums and all, so a
+//! [`WalReader`] over it reads them back with the same checks, and its
+//! bytes are a valid WAL file.
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, RwLock, RwLockWriteGuard};
+
+use mini_aurora_common::RedoRecord;
+
+use crate::checksum::ChecksumKind;
+use crate::disk::HostDisk;
+use crate::reader::WalReader;
+use crate::writer::encode_entries;
+
+/// Stands in for a file path where a [`MemoryWal`] needs one, e.g. in logs.
+pub const MEMORY_WAL_PATH: &str = "<memory>";
+
+/// An append-only WAL in memory. Appends are durable as soon as they
+/// return, and gone with the process. A clone is another handle on the
+/// same log, as a path is on a WAL file, with its own checksum setting.
+#[derive(Default, Clone)]
+pub struct MemoryWal {
+    bytes: Arc<RwLock<Vec<u8>>>,
+    checksum: ChecksumKind,
+}
+
+impl MemoryWal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checksum the entries appended from now on with `checksum`.
+    pub fn with_checksum(mut self, checksum: ChecksumKind) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    pub fn set_checksum(&mut self, checksum: ChecksumKind) {
+        self.checksum = checksum;
+    }
+
+    pub fn checksum(&self) -> ChecksumKind {
+        self.checksum
+    }
+
+    /// Append `records`, returning the offset the first of them lands at.
+    pub fn append(&mut self, records: &[RedoRecord]) -> u64 {
+        let encoded = encode_entries(records, self.checksum);
+        let mut bytes = self.bytes.write().unwrap();
+        let offset = bytes.len() as u64;
+        bytes.extend_from_slice(&encoded);
+        offset
+    }
+
+    /// Cut the log to its first `len` bytes, as recovery does to a torn tail.
+    pub fn truncate(&mut self, len: u64) {
+        self.bytes.write().unwrap().truncate(len as usize);
+    }
+
+    /// The log's bytes, to damage them as a failing disk would.
+    pub fn bytes_mut(&self) -> RwLockWriteGuard<'_, Vec<u8>> {
+        self.bytes.write().unwrap()
+    }
+
+    /// Bytes appended so far.
+    pub fn len(&self) -> u64 {
+        self.bytes.read().unwrap().len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A reader over the log, which also sees what is appended later.
+    pub fn reader(&self) -> WalReader {
+        let file = MemoryFile { bytes: Arc::clone(&self.bytes), position: 0 };
+        WalReader::over(Box::new(file), Path::new(MEMORY_WAL_PATH), Arc::new(HostDisk))
+    }
+
+    /// A copy of the log, laid out as a WAL file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bytes.read().unwrap().clone()
+    }
+}
+
+/// A read position in a [`MemoryWal`].
+struct MemoryFile {
+    bytes: Arc<RwLock<Vec<u8>>>,
+    position: u64,
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes = self.bytes.read().unwrap();
+        let start = (self.position as usize).min(bytes.len());
+        let len = buf.len().min(bytes.len() - start);
+        buf[..len].copy_from_slice(&bytes[start..start + len]);
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => (self.bytes.read().unwrap().len() as u64).checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = position
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start of the WAL"))?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mini_aurora_common::LOG_ENTRY_HEADER_SIZE;
+    use tempfile::NamedTempFile;
+
+    fn record(lsn: u64, prev_lsn: u64) -> RedoRecord {
+        RedoRecord {
+            lsn, page_id: 7, offset: 0, data: vec![lsn as u8; 8].into(),
+            prev_lsn, mtr_id: lsn, is_mtr_end: true, timestamp: lsn,
+        }
+    }
+
+    #[test]
+    fn test_entries_read_back_as_from_a_file() {
+        let mut wal = MemoryWal::new().with_checksum(ChecksumKind::Crc32c);
+        assert!(wal.is_empty());
+        assert_eq!(wal.append(&[record(1, 0), record(2, 1)]), 0);
+        let mut reader = wal.reader();
+        assert_eq!(reader.scan_all().unwrap().len(), 2);
+
+        // A reader sees later appends, and can walk a page's chain
+        let offset = wal.append(&[record(3, 2)]);
+        assert_eq!(offset, 2 * (LOG_ENTRY_HEADER_SIZE + 8) as u64);
+        let (_, offsets) = reader.build_index().unwrap();
+        let chain = reader.collect_page_chain(7, 3, 2, &offsets).unwrap();
+        assert_eq!(chain.iter().map(|r| r.lsn).collect::<Vec<_>>(), [1, 2]);
+
+        // Its bytes are a WAL file
+        let tmp = NamedTempFile::new().unwrap();
+        std::fs::write(tmp.path(), wal.to_bytes()).unwrap();
+        let from_file = WalReader::open(tmp.path()).unwrap().scan_all().unwrap();
+        assert_eq!(from_file, reader.scan_all().unwrap());
+    }
+}
@@ -0,0 +1,340 @@
+//! Pipelined appends to one WAL file. A batch passes through four stages,
+//! each on its own thread and joined to the next by a channel:
+//!
+//! 1. encode: headers and checksums, into one buffer per batch;
+//! 2. write: the buffer is appended to the file;
+//! 3. sync: one fsync, in the writer's `SyncMode`, covers every batch
+//!    written since the last one;
+//! 4. publish: each batch's callback runs, in submission order.
+//!
+//! So one batch is encoded while another is written and a third synced,
+//! and batches that reach the sync stage during an fsync share the next.
+//! Once a write or fsync fails the file's tail is unknown: every batch
+//! behind it fails too, and so does every later `submit`.
+
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use mini_aurora_common::{RedoRecord, LOG_ENTRY_HEADER_SIZE};
+
+use crate::checksum::ChecksumKind;
+use crate::disk::{Disk, DiskOp};
+use crate::writer::{encode_entries, SyncMode, WalWriter};
+
+/// Called once a batch is durable, or with why it never will be.
+pub type OnDurable = Box<dyn FnOnce(io::Result<()>) + Send>;
+
+/// Work a pipeline has done so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PipelineStats {
+    /// Non-empty batches written.
+    pub batches: u64,
+    pub bytes: u64,
+    /// Fewer than `batches` when batches shared an fsync.
+    pub syncs: u64,
+}
+
+/// A WAL writer split into stages; see the module docs.
+pub struct AppendPipeline {
+    /// `None` once the pipeline is shutting down.
+    jobs: Option<Sender<Job>>,
+    stages: Vec<JoinHandle<()>>,
+    shared: Arc<Shared>,
+    /// Where the next submitted batch lands.
+    end: u64,
+    /// What batches submitted from now on are checksummed with.
+    checksum: ChecksumKind,
+}
+
+#[derive(Default)]
+struct Shared {
+    /// Why the pipeline stopped, once a write or fsync failed, with the
+    /// kind of the error, so a full disk still reads as one.
+    failed: Mutex<Option<(io::ErrorKind, String)>>,
+    batches: AtomicU64,
+    bytes: AtomicU64,
+    syncs: AtomicU64,
+}
+
+impl Shared {
+    fn failure(&self) -> Option<io::Error> {
+        let failed = self.failed.lock().unwrap();
+        failed.as_ref().map(|(kind, reason)| io::Error::new(*kind, reason.clone()))
+    }
+
+    /// Stop the pipeline because `stage` failed; returns the reason.
+    fn fail(&self, stage: &str, error: io::Error) -> io::Error {
+        let reason = format!("WAL {stage} failed: {error}");
+        self.failed.lock().unwrap().get_or_insert((error.kind(), reason));
+        self.failure().expect("pipeline has failed")
+    }
+}
+
+struct Job {
+    records: Vec<RedoRecord>,
+    checksum: ChecksumKind,
+    done: OnDurable,
+}
+
+struct Encoded {
+    bytes: Vec<u8>,
+    entries: u64,
+    done: OnDurable,
+}
+
+struct Written {
+    result: Result<(), io::Error>,
+    done: OnDurable,
+}
+
+impl AppendPipeline {
+    /// Start the stages, taking over `writer`'s file.
+    pub fn start(writer: WalWriter) -> Result<Self, io::Error> {
+        let (file, disk) = writer.sync_handle()?;
+        let path = writer.path().to_path_buf();
+        let end = writer.end();
+        let checksum = writer.checksum();
+        let sync_mode = writer.sync_mode();
+        let shared = Arc::<Shared>::default();
+
+        let (jobs, job_rx) = mpsc::channel();
+        let (encoded_tx, encoded_rx) = mpsc::channel();
+        let (written_tx, written_rx) = mpsc::channel();
+        let (synced_tx, synced_rx) = mpsc::channel();
+        let stages = vec![
+            spawn("wal-encode", move || encode_stage(job_rx, encoded_tx))?,
+            spawn("wal-write", {
+                let shared = Arc::clone(&shared);
+                move || write_stage(writer, encoded_rx, written_tx, &shared)
+            })?,
+            spawn("wal-sync", {
+                let shared = Arc::clone(&shared);
+                move || sync_stage(file, path, disk, sync_mode, written_rx, synced_tx, &shared)
+            })?,
+            spawn("wal-publish", move || publish_stage(synced_rx))?,
+        ];
+        Ok(Self { jobs: Some(jobs), stages, shared, end, checksum })
+    }
+
+    /// Queue `records` for appending; `done` runs once they are durable.
+    /// Returns the file offset the first of them lands at.
+    pub fn submit(&mut self, records: Vec<RedoRecord>, done: OnDurable) -> Result<u64, io::Error> {
+        if let Some(failure) = self.shared.failure() {
+            return Err(failure);
+        }
+        let offset = self.end;
+        let len: usize = records.iter().map(|r| LOG_ENTRY_HEADER_SIZE + r.data.len()).sum();
+        self.jobs
+            .as_ref()
+            .expect("pipeline is running")
+            .send(Job { records, checksum: self.checksum, done })
+            .map_err(|_| io::Error::other("WAL append pipeline stopped"))?;
+        self.end += len as u64;
+        Ok(offset)
+    }
+
+    /// Wait until everything submitted so far is durable, fsyncing the file
+    /// even if nothing is.
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        let (tx, rx) = mpsc::channel();
+        self.submit(Vec::new(), Box::new(move |result| {
+            let _ = tx.send(result);
+        }))?;
+        rx.recv().map_err(|_| io::Error::other("WAL append pipeline stopped"))?
+    }
+
+    /// Checksum batches submitted from now on with `checksum`.
+    pub fn set_checksum(&mut self, checksum: ChecksumKind) {
+        self.checksum = checksum;
+    }
+
+    pub fn checksum(&self) -> ChecksumKind {
+        self.checksum
+    }
+
+    /// Where the next submitted batch lands.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    pub fn stats(&self) -> PipelineStats {
+        PipelineStats {
+            batches: self.shared.batches.load(Ordering::Relaxed),
+            bytes: self.shared.bytes.load(Ordering::Relaxed),
+            syncs: self.shared.syncs.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for AppendPipeline {
+    /// Let the batches already submitted land, then stop the stages.
+    fn drop(&mut self) {
+        self.jobs = None;
+        for stage in self.stages.drain(..) {
+            let _ = stage.join();
+        }
+    }
+}
+
+fn spawn(name: &str, stage: impl FnOnce() + Send + 'static) -> Result<JoinHandle<()>, io::Error> {
+    thread::Builder::new().name(name.to_string()).spawn(stage)
+}
+
+fn encode_stage(jobs: Receiver<Job>, encoded: Sender<Encoded>) {
+    for Job { records, checksum, done } in jobs {
+        let bytes = encode_entries(&records, checksum);
+        if encoded.send(Encoded { bytes, entries: records.len() as u64, done }).is_err() {
+            return;
+        }
+    }
+}
+
+fn write_stage(mut writer: WalWriter, encoded: Receiver<Encoded>, written: Sender<Written>, shared: &Shared) {
+    for Encoded { bytes, entries, done } in encoded {
+        let result = match shared.failure() {
+            Some(failure) => Err(failure),
+            None => writer.append_encoded(&bytes, entries).map_err(|e| shared.fail("write", e)),
+        };
+        if result.is_ok() && entries > 0 {
+            shared.batches.fetch_add(1, Ordering::Relaxed);
+            shared.bytes.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        }
+        if written.send(Written { result, done }).is_err() {
+            return;
+        }
+    }
+}
+
+fn sync_stage(
+    file: File,
+    path: PathBuf,
+    disk: Arc<dyn Disk>,
+    sync_mode: SyncMode,
+    written: Receiver<Written>,
+    synced: Sender<Vec<Written>>,
+    shared: &Shared,
+) {
+    while let Ok(first) = written.recv() {
+        // Everything written while the last fsync ran shares this one
+        let mut group = vec![first];
+        group.extend(written.try_iter());
+        if group.iter().any(|w| w.result.is_ok()) {
+            shared.syncs.fetch_add(1, Ordering::Relaxed);
+            // A failed fsync is never retried: the kernel may have dropped
+            // the dirty pages already, so a second one could pass and lie
+            let synced = disk.fault(&path, DiskOp::Sync).map_or_else(|| sync_mode.sync(&file), Err);
+            if let Err(e) = synced {
+                let failure = shared.fail("fsync", e);
+                for w in group.iter_mut().filter(|w| w.result.is_ok()) {
+                    w.result = Err(io::Error::new(failure.kind(), failure.to_string()));
+                }
+            } else {
+                disk.sync(&path);
+            }
+        }
+        if synced.send(group).is_err() {
+            return;
+        }
+    }
+}
+
+fn publish_stage(synced: Receiver<Vec<Written>>) {
+    for Written { result, done } in synced.into_iter().flatten() {
+        done(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::{DiskProfile, FaultyDisk, HostDisk};
+    use crate::reader::WalReader;
+    use tempfile::NamedTempFile;
+
+    fn batch(lsns: std::ops::RangeInclusive<u64>) -> Vec<RedoRecord> {
+        lsns.map(|lsn| RedoRecord {
+            lsn, page_id: lsn % 3, offset: 0, data: vec![lsn as u8; 16].into(),
+            prev_lsn: 0, mtr_id: lsn, is_mtr_end: true, timestamp: 0,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_batches_land_in_order() {
+        let tmp = NamedTempFile::new().unwrap();
+        let mut pipeline = AppendPipeline::start(WalWriter::open(tmp.path()).unwrap()).unwrap();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut offsets = Vec::new();
+        for i in 0..10 {
+            let order = Arc::clone(&order);
+            let done: OnDurable = Box::new(move |result| {
+                result.unwrap();
+                order.lock().unwrap().push(i);
+            });
+            offsets.push(pipeline.submit(batch(i * 2 + 1..=i * 2 + 2), done).unwrap());
+        }
+        pipeline.flush().unwrap();
+        assert_eq!(*order.lock().unwrap(), (0..10).collect::<Vec<_>>());
+
+        let entries = WalReader::open(tmp.path()).unwrap().scan_all().unwrap();
+        let lsns: Vec<u64> = entries.iter().map(|(header, _)| header.lsn).collect();
+        assert_eq!(lsns, (1..=20).collect::<Vec<_>>());
+        let entry_len = (LOG_ENTRY_HEADER_SIZE + 16) as u64;
+        assert_eq!(offsets, (0..10).map(|i| i * 2 * entry_len).collect::<Vec<_>>());
+        assert_eq!(pipeline.end(), std::fs::metadata(tmp.path()).unwrap().len());
+        assert_eq!(pipeline.stats().batches, 10);
+    }
+
+    #[test]
+    fn test_batches_written_during_an_fsync_share_the_next() {
+        let tmp = NamedTempFile::new().unwrap();
+        let writer = WalWriter::open_on(tmp.path(), DiskProfile::hdd().disk()).unwrap();
+        let mut pipeline = AppendPipeline::start(writer).unwrap();
+        for i in 0..8 {
+            pipeline.submit(batch(i + 1..=i + 1), Box::new(|result| result.unwrap())).unwrap();
+        }
+        pipeline.flush().unwrap();
+        let stats = pipeline.stats();
+        assert_eq!(stats.batches, 8);
+        assert!(stats.syncs < stats.batches, "{stats:?}");
+    }
+
+    #[test]
+    fn test_failed_fsync_is_never_retried() {
+        let tmp = NamedTempFile::new().unwrap();
+        let disk = Arc::new(FaultyDisk::new(Arc::new(HostDisk)));
+        let writer = WalWriter::open_on(tmp.path(), disk.clone()).unwrap();
+        let mut pipeline = AppendPipeline::start(writer).unwrap();
+        pipeline.submit(batch(1..=2), Box::new(|result| result.unwrap())).unwrap();
+        pipeline.flush().unwrap();
+
+        disk.fail(DiskOp::Sync, io::ErrorKind::Other);
+        let (tx, rx) = mpsc::channel();
+        pipeline.submit(batch(3..=4), Box::new(move |result| tx.send(result).unwrap())).unwrap();
+        let err = rx.recv().unwrap().unwrap_err();
+        assert!(err.to_string().starts_with("WAL fsync failed"), "{err}");
+
+        // The disk recovering doesn't bring the pipeline back
+        disk.heal();
+        assert!(pipeline.submit(batch(5..=5), Box::new(|_| {})).is_err());
+        assert!(pipeline.flush().is_err());
+    }
+
+    #[test]
+    fn test_full_disk_keeps_its_error_kind() {
+        let tmp = NamedTempFile::new().unwrap();
+        let disk = Arc::new(FaultyDisk::new(Arc::new(HostDisk)));
+        let writer = WalWriter::open_on(tmp.path(), disk.clone()).unwrap();
+        let mut pipeline = AppendPipeline::start(writer).unwrap();
+        disk.fail(DiskOp::Write, io::ErrorKind::StorageFull);
+        let (tx, rx) = mpsc::channel();
+        pipeline.submit(batch(1..=2), Box::new(move |result| tx.send(result).unwrap())).unwrap();
+        assert_eq!(rx.recv().unwrap().unwrap_err().kind(), io::ErrorKind::StorageFull);
+        assert_eq!(std::fs::metadata(tmp.path()).unwrap().len(), 0);
+    }
+}
@@ -1,16 +1,33 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use crc32fast::Hasher;
 use mini_aurora_common::{LogEntryHeader, Lsn, PageId, RedoRecord, LOG_ENTRY_HEADER_SIZE};
 
-/// Reads and iterates over WAL entries on disk.
+use crate::checksum::ChecksumKind;
+use crate::disk::{Disk, HostDisk};
+
+/// Page → latest LSN index plus LSN → file offset map, as built by
+/// [`WalReader::build_index`].
+pub type WalIndex = (HashMap<PageId, Lsn>, HashMap<Lsn, u64>);
+
+/// Reads and iterates over WAL entries on disk, or in a
+/// [`MemoryWal`](crate::memory::MemoryWal), charging each entry read to
+/// its [`Disk`].
 pub struct WalReader {
-    pub(crate) file: File,
+    pub(crate) file: Box<dyn WalSource>,
+    path: PathBuf,
+    disk: Arc<dyn Disk>,
 }
 
+/// Bytes a [`WalReader`] decodes entries from: a file, or a WAL in memory.
+pub(crate) trait WalSource: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> WalSource for T {}
+
 /// Outcome of trying to read one log entry.
 #[derive(Debug)]
 pub enum ReadResult {
@@ -22,10 +39,85 @@ pub enum ReadResult {
     Corrupted { file_offset: u64 },
 }
 
+/// Why a [`WalIter`] stopped before the end of the log.
+#[derive(Debug, thiserror::Error)]
+pub enum WalError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The entry at `file_offset` failed its CRC check.
+    #[error("corrupt WAL entry at offset {file_offset}")]
+    Corrupted { file_offset: u64 },
+}
+
+/// Forward iterator over the entries of a [`WalReader`], from
+/// [`WalReader::iter`] or [`WalReader::iter_range`].
+///
+/// Ends at EOF or at a torn entry. A corrupt entry or I/O error is yielded
+/// once as an `Err`, after which the iterator is done.
+pub struct WalIter<'a> {
+    reader: &'a mut WalReader,
+    position: u64,
+    end: Option<u64>,
+    seeked: bool,
+    done: bool,
+}
+
+impl WalIter<'_> {
+    /// File offset of the next entry: the end of the last one yielded.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+impl Iterator for WalIter<'_> {
+    type Item = Result<(LogEntryHeader, Vec<u8>), WalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.end.is_some_and(|end| self.position >= end) {
+            return None;
+        }
+        if !self.seeked {
+            if let Err(e) = self.reader.seek_to(self.position) {
+                self.done = true;
+                return Some(Err(e.into()));
+            }
+            self.seeked = true;
+        }
+        match self.reader.read_entry() {
+            Ok(ReadResult::Entry(hdr, data)) => {
+                self.position += (LOG_ENTRY_HEADER_SIZE + data.len()) as u64;
+                Some(Ok((hdr, data)))
+            }
+            Ok(ReadResult::Eof) => {
+                self.done = true;
+                None
+            }
+            Ok(ReadResult::Corrupted { file_offset }) => {
+                self.done = true;
+                Some(Err(WalError::Corrupted { file_offset }))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e.into()))
+            }
+        }
+    }
+}
+
 impl WalReader {
     pub fn open(path: &Path) -> Result<Self, std::io::Error> {
+        Self::open_on(path, Arc::new(HostDisk))
+    }
+
+    /// Open a WAL file whose reads are charged to `disk`.
+    pub fn open_on(path: &Path, disk: Arc<dyn Disk>) -> Result<Self, std::io::Error> {
         let file = File::open(path)?;
-        Ok(Self { file })
+        Ok(Self::over(Box::new(file), path, disk))
+    }
+
+    /// A reader of `source`, charged to `disk` as if it were `path`.
+    pub(crate) fn over(source: Box<dyn WalSource>, path: &Path, disk: Arc<dyn Disk>) -> Self {
+        Self { file: source, path: path.to_path_buf(), disk }
     }
 
     /// Seek to a specific file offset.
@@ -37,13 +129,21 @@ impl WalReader {
     /// Read one entry starting at the file's current position.
     pub fn read_entry(&mut self) -> Result<ReadResult, std::io::Error> {
         let file_offset = self.file.stream_position()?;
+        let (result, len) = self.decode_entry(file_offset)?;
+        if len > 0 {
+            self.disk.read(&self.path, file_offset, len);
+        }
+        Ok(result)
+    }
 
+    /// Decode the entry at `file_offset`, returning it and the bytes read.
+    fn decode_entry(&mut self, file_offset: u64) -> Result<(ReadResult, u64), std::io::Error> {
         // Read header bytes
         let mut hdr_buf = [0u8; LOG_ENTRY_HEADER_SIZE];
         match self.file.read_exact(&mut hdr_buf) {
             Ok(()) => {}
             Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                return Ok(ReadResult::Eof);
+                return Ok((ReadResult::Eof, 0));
             }
             Err(e) => return Err(e),
         }
@@ -55,28 +155,42 @@ impl WalReader {
         match self.file.read_exact(&mut data) {
             Ok(()) => {}
             Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                return Ok(ReadResult::Eof);
+                return Ok((ReadResult::Eof, LOG_ENTRY_HEADER_SIZE as u64));
             }
             Err(e) => return Err(e),
         }
+        let len = (LOG_ENTRY_HEADER_SIZE + data.len()) as u64;
 
-        // Verify CRC
-        let expected_crc = compute_crc(&hdr_buf[..LOG_ENTRY_HEADER_SIZE - 4], &data);
-        if header.crc32 != expected_crc {
-            return Ok(ReadResult::Corrupted { file_offset });
+        // Verify the checksum, by the algorithm the entry names
+        let Some(checksum) = ChecksumKind::from_flags(header.flags) else {
+            return Ok((ReadResult::Corrupted { file_offset }, len));
+        };
+        if header.crc32 != checksum.checksum().entry(&hdr_buf[..LOG_ENTRY_HEADER_SIZE - 4], &data) {
+            return Ok((ReadResult::Corrupted { file_offset }, len));
         }
 
-        Ok(ReadResult::Entry(header, data))
+        Ok((ReadResult::Entry(header, data), len))
+    }
+
+    /// Iterate over every entry from the start of the file.
+    pub fn iter(&mut self) -> WalIter<'_> {
+        WalIter { reader: self, position: 0, end: None, seeked: false, done: false }
+    }
+
+    /// Iterate over the entries starting in `offsets`. `offsets.start` must
+    /// be an entry boundary, such as an offset from [`WalReader::build_index`].
+    pub fn iter_range(&mut self, offsets: Range<u64>) -> WalIter<'_> {
+        WalIter { reader: self, position: offsets.start, end: Some(offsets.end), seeked: false, done: false }
     }
 
     /// Scan the entire WAL forward, returning all valid entries.
     pub fn scan_all(&mut self) -> Result<Vec<(LogEntryHeader, Vec<u8>)>, std::io::Error> {
-        self.file.seek(SeekFrom::Start(0))?;
         let mut entries = Vec::new();
-        loop {
-            match self.read_entry()? {
-                ReadResult::Entry(hdr, data) => entries.push((hdr, data)),
-                ReadResult::Eof | ReadResult::Corrupted { .. } => break,
+        for entry in self.iter() {
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(WalError::Io(e)) => return Err(e),
+                Err(WalError::Corrupted { .. }) => break,
             }
         }
         Ok(entries)
@@ -84,24 +198,23 @@ impl WalReader {
 
     /// Build a page→latest-LSN index by scanning the entire WAL.
     /// Also returns a mapping from LSN → file offset for chain-walking.
-    pub fn build_index(
-        &mut self,
-    ) -> Result<(HashMap<PageId, Lsn>, HashMap<Lsn, u64>), std::io::Error> {
-        self.file.seek(SeekFrom::Start(0))?;
+    pub fn build_index(&mut self) -> Result<WalIndex, std::io::Error> {
         let mut page_latest: HashMap<PageId, Lsn> = HashMap::new();
         let mut lsn_offset: HashMap<Lsn, u64> = HashMap::new();
 
+        let mut entries = self.iter();
         loop {
-            let offset = self.file.stream_position()?;
-            match self.read_entry()? {
-                ReadResult::Entry(hdr, _data) => {
+            let offset = entries.position();
+            match entries.next() {
+                Some(Ok((hdr, _data))) => {
                     lsn_offset.insert(hdr.lsn, offset);
                     let entry = page_latest.entry(hdr.page_id).or_insert(0);
                     if hdr.lsn > *entry {
                         *entry = hdr.lsn;
                     }
                 }
-                ReadResult::Eof | ReadResult::Corrupted { .. } => break,
+                Some(Err(WalError::Io(e))) => return Err(e),
+                Some(Err(WalError::Corrupted { .. })) | None => break,
             }
         }
         Ok((page_latest, lsn_offset))
@@ -117,31 +230,29 @@ impl WalReader {
         start_lsn: Lsn,
         target_lsn: Lsn,
         lsn_offset: &HashMap<Lsn, u64>,
+    ) -> Result<Vec<RedoRecord>, std::io::Error> {
+        self.collect_page_chain_with(page_id, start_lsn, target_lsn, 0, lsn_offset, |_, _| {})
+    }
+
+    /// Like `collect_page_chain`, calling `on_step` with each header read
+    /// along the way and whether it was skipped for being past `target_lsn`.
+    /// Starting at the newest record ≤ `target_lsn` reads no skipped records.
+    /// The walk stops at `floor` or below, the version of a base image the
+    /// records are replayed onto; 0 walks the whole chain.
+    pub fn collect_page_chain_with(
+        &mut self,
+        page_id: PageId,
+        start_lsn: Lsn,
+        target_lsn: Lsn,
+        floor: Lsn,
+        lsn_offset: &HashMap<Lsn, u64>,
+        mut on_step: impl FnMut(&LogEntryHeader, bool),
     ) -> Result<Vec<RedoRecord>, std::io::Error> {
         let mut chain = Vec::new();
         let mut current_lsn = start_lsn;
 
-        // Walk backwards through the chain, but only include records ≤ target_lsn
-        while current_lsn != 0 {
-            if current_lsn > target_lsn {
-                // Need to find the right starting point — scan forward to find
-                // the latest LSN for this page that is ≤ target_lsn.
-                // This means start_lsn was too high; we need to walk from there.
-                // Actually, we should still walk backward to find records ≤ target_lsn.
-                let offset = match lsn_offset.get(&current_lsn) {
-                    Some(&off) => off,
-                    None => break,
-                };
-                self.file.seek(SeekFrom::Start(offset))?;
-                match self.read_entry()? {
-                    ReadResult::Entry(hdr, _data) => {
-                        current_lsn = hdr.prev_lsn;
-                        continue;
-                    }
-                    _ => break,
-                }
-            }
-
+        // Walk backwards through the chain, keeping only records ≤ target_lsn
+        while current_lsn > floor {
             let offset = match lsn_offset.get(&current_lsn) {
                 Some(&off) => off,
                 None => break,
@@ -150,10 +261,12 @@ impl WalReader {
             match self.read_entry()? {
                 ReadResult::Entry(hdr, data) => {
                     debug_assert_eq!(hdr.page_id, page_id);
-                    let record = header_to_record(&hdr, data);
-                    let prev = hdr.prev_lsn;
-                    chain.push(record);
-                    current_lsn = prev;
+                    let skipped = hdr.lsn > target_lsn;
+                    on_step(&hdr, skipped);
+                    current_lsn = hdr.prev_lsn;
+                    if !skipped {
+                        chain.push(header_to_record(&hdr, data));
+                    }
                 }
                 _ => break,
             }
@@ -173,8 +286,9 @@ pub fn decode_header(buf: &[u8; LOG_ENTRY_HEADER_SIZE]) -> LogEntryHeader {
         data_len: u16::from_le_bytes(buf[18..20].try_into().unwrap()),
         prev_lsn: u64::from_le_bytes(buf[20..28].try_into().unwrap()),
         mtr_id: u64::from_le_bytes(buf[28..36].try_into().unwrap()),
-        flags: buf[36],
-        crc32: u32::from_le_bytes(buf[37..41].try_into().unwrap()),
+        timestamp: u64::from_le_bytes(buf[36..44].try_into().unwrap()),
+        flags: buf[44],
+        crc32: u32::from_le_bytes(buf[45..49].try_into().unwrap()),
     }
 }
 
@@ -184,24 +298,20 @@ pub fn header_to_record(hdr: &LogEntryHeader, data: Vec<u8>) -> RedoRecord {
         lsn: hdr.lsn,
         page_id: hdr.page_id,
         offset: hdr.offset,
-        data,
+        data: data.into(),
         prev_lsn: hdr.prev_lsn,
         mtr_id: hdr.mtr_id,
         is_mtr_end: hdr.is_mtr_end(),
+        timestamp: hdr.timestamp,
     }
 }
 
-fn compute_crc(header_without_crc: &[u8], data: &[u8]) -> u32 {
-    let mut hasher = Hasher::new();
-    hasher.update(header_without_crc);
-    hasher.update(data);
-    hasher.finalize()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::disk::{DiskProfile, SimDisk};
     use crate::writer::WalWriter;
+    use std::time::Duration;
     use tempfile::NamedTempFile;
 
     #[test]
@@ -213,10 +323,11 @@ mod tests {
             lsn: 1,
             page_id: 42,
             offset: 100,
-            data: vec![0xDE, 0xAD],
+            data: vec![0xDE, 0xAD].into(),
             prev_lsn: 0,
             mtr_id: 1,
             is_mtr_end: true,
+            timestamp: 0,
         };
 
         let mut writer = WalWriter::open(&path).unwrap();
@@ -237,6 +348,34 @@ mod tests {
         assert!(hdr.is_mtr_end());
     }
 
+    #[test]
+    fn test_each_entry_is_checked_by_its_own_checksum() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+        let mut writer = WalWriter::open(&path).unwrap();
+        for (lsn, kind) in (1..).zip(ChecksumKind::ALL) {
+            writer.set_checksum(kind);
+            writer.append(&RedoRecord {
+                lsn, page_id: 1, offset: 0, data: vec![lsn as u8; 8].into(),
+                prev_lsn: lsn - 1, mtr_id: lsn, is_mtr_end: true, timestamp: 0,
+            }).unwrap();
+        }
+        writer.sync().unwrap();
+        drop(writer);
+        let entries = WalReader::open(&path).unwrap().scan_all().unwrap();
+        assert_eq!(entries.len(), 3);
+
+        // An algorithm code no checksum has reads as damage
+        let entry_len = (LOG_ENTRY_HEADER_SIZE + 8) as u64;
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[entry_len as usize + 44] |= 0b110;
+        std::fs::write(&path, &bytes).unwrap();
+        let mut reader = WalReader::open(&path).unwrap();
+        reader.seek_to(entry_len).unwrap();
+        let damaged = reader.read_entry().unwrap();
+        assert!(matches!(damaged, ReadResult::Corrupted { file_offset } if file_offset == entry_len));
+    }
+
     #[test]
     fn test_roundtrip_batch() {
         let tmp = NamedTempFile::new().unwrap();
@@ -247,10 +386,11 @@ mod tests {
                 lsn: i,
                 page_id: 10,
                 offset: (i as u16) * 8,
-                data: vec![i as u8; 6],
+                data: vec![i as u8; 6].into(),
                 prev_lsn: if i == 1 { 0 } else { i - 1 },
                 mtr_id: 1,
                 is_mtr_end: i == 3,
+                timestamp: 0,
             })
             .collect();
 
@@ -277,9 +417,9 @@ mod tests {
         let path = tmp.path().to_path_buf();
 
         let records = vec![
-            RedoRecord { lsn: 1, page_id: 1, offset: 0, data: vec![1], prev_lsn: 0, mtr_id: 1, is_mtr_end: false },
-            RedoRecord { lsn: 2, page_id: 2, offset: 0, data: vec![2], prev_lsn: 0, mtr_id: 1, is_mtr_end: false },
-            RedoRecord { lsn: 3, page_id: 1, offset: 4, data: vec![3], prev_lsn: 1, mtr_id: 1, is_mtr_end: true },
+            RedoRecord { lsn: 1, page_id: 1, offset: 0, data: vec![1].into(), prev_lsn: 0, mtr_id: 1, is_mtr_end: false, timestamp: 0 },
+            RedoRecord { lsn: 2, page_id: 2, offset: 0, data: vec![2].into(), prev_lsn: 0, mtr_id: 1, is_mtr_end: false, timestamp: 0 },
+            RedoRecord { lsn: 3, page_id: 1, offset: 4, data: vec![3].into(), prev_lsn: 1, mtr_id: 1, is_mtr_end: true, timestamp: 0 },
         ];
 
         let mut writer = WalWriter::open(&path).unwrap();
@@ -303,11 +443,11 @@ mod tests {
         // Page 1: LSN 1 → LSN 3 → LSN 5
         // Page 2: LSN 2 → LSN 4
         let records = vec![
-            RedoRecord { lsn: 1, page_id: 1, offset: 0, data: vec![0xA], prev_lsn: 0, mtr_id: 1, is_mtr_end: true },
-            RedoRecord { lsn: 2, page_id: 2, offset: 0, data: vec![0xB], prev_lsn: 0, mtr_id: 2, is_mtr_end: true },
-            RedoRecord { lsn: 3, page_id: 1, offset: 1, data: vec![0xC], prev_lsn: 1, mtr_id: 3, is_mtr_end: true },
-            RedoRecord { lsn: 4, page_id: 2, offset: 1, data: vec![0xD], prev_lsn: 2, mtr_id: 4, is_mtr_end: true },
-            RedoRecord { lsn: 5, page_id: 1, offset: 2, data: vec![0xE], prev_lsn: 3, mtr_id: 5, is_mtr_end: true },
+            RedoRecord { lsn: 1, page_id: 1, offset: 0, data: vec![0xA].into(), prev_lsn: 0, mtr_id: 1, is_mtr_end: true, timestamp: 0 },
+            RedoRecord { lsn: 2, page_id: 2, offset: 0, data: vec![0xB].into(), prev_lsn: 0, mtr_id: 2, is_mtr_end: true, timestamp: 0 },
+            RedoRecord { lsn: 3, page_id: 1, offset: 1, data: vec![0xC].into(), prev_lsn: 1, mtr_id: 3, is_mtr_end: true, timestamp: 0 },
+            RedoRecord { lsn: 4, page_id: 2, offset: 1, data: vec![0xD].into(), prev_lsn: 2, mtr_id: 4, is_mtr_end: true, timestamp: 0 },
+            RedoRecord { lsn: 5, page_id: 1, offset: 2, data: vec![0xE].into(), prev_lsn: 3, mtr_id: 5, is_mtr_end: true, timestamp: 0 },
         ];
 
         let mut writer = WalWriter::open(&path).unwrap();
@@ -332,6 +472,94 @@ mod tests {
         assert_eq!(chain[1].lsn, 3);
     }
 
+    #[test]
+    fn test_io_is_charged_to_the_disk() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+        let disk = Arc::new(SimDisk::accounting_only(DiskProfile::host().with_seek(Duration::from_millis(1))));
+
+        // Page 1: LSN 1 → LSN 3 → LSN 5, between the records of page 2
+        let records: Vec<RedoRecord> = (1..=5)
+            .map(|lsn| RedoRecord {
+                lsn, page_id: 2 - lsn % 2, offset: 0, data: vec![lsn as u8].into(),
+                prev_lsn: lsn.saturating_sub(2), mtr_id: lsn, is_mtr_end: true, timestamp: 0,
+            })
+            .collect();
+        let mut writer = WalWriter::open_on(&path, disk.clone()).unwrap();
+        writer.append_batch(&records).unwrap();
+        writer.sync().unwrap();
+
+        // A forward scan seeks once; a chain walk seeks for every record
+        let mut reader = WalReader::open_on(&path, disk.clone()).unwrap();
+        let (_page_latest, lsn_offset) = reader.build_index().unwrap();
+        reader.collect_page_chain(1, 5, 5, &lsn_offset).unwrap();
+
+        let stats = disk.stats();
+        let entry = LOG_ENTRY_HEADER_SIZE as u64 + 1;
+        assert_eq!((stats.writes, stats.bytes_written, stats.syncs), (1, 5 * entry, 1));
+        assert_eq!((stats.reads, stats.bytes_read), (8, 8 * entry));
+        assert_eq!(stats.seeks, 5);
+        assert_eq!(stats.busy, Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_iter_stops_after_a_corrupt_entry() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+
+        let records: Vec<RedoRecord> = (1..=3)
+            .map(|lsn| RedoRecord {
+                lsn, page_id: 1, offset: 0, data: vec![lsn as u8; 4].into(),
+                prev_lsn: lsn - 1, mtr_id: lsn, is_mtr_end: true, timestamp: 0,
+            })
+            .collect();
+        let mut writer = WalWriter::open(&path).unwrap();
+        writer.append_batch(&records).unwrap();
+        writer.sync().unwrap();
+        drop(writer);
+
+        // Flip a payload byte of LSN 2
+        let entry = LOG_ENTRY_HEADER_SIZE as u64 + 4;
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[(entry + LOG_ENTRY_HEADER_SIZE as u64) as usize] ^= 0xFF;
+        std::fs::write(&path, bytes).unwrap();
+
+        let mut reader = WalReader::open(&path).unwrap();
+        let mut entries = reader.iter();
+        assert_eq!(entries.next().unwrap().unwrap().0.lsn, 1);
+        assert_eq!(entries.position(), entry);
+        let corrupt = entries.next();
+        assert!(matches!(corrupt, Some(Err(WalError::Corrupted { file_offset })) if file_offset == entry));
+        assert!(entries.next().is_none());
+        assert_eq!(reader.scan_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_iter_range_yields_entries_starting_in_the_range() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+
+        let records: Vec<RedoRecord> = (1..=5)
+            .map(|lsn| RedoRecord {
+                lsn, page_id: lsn, offset: 0, data: vec![lsn as u8].into(),
+                prev_lsn: 0, mtr_id: lsn, is_mtr_end: true, timestamp: 0,
+            })
+            .collect();
+        let mut writer = WalWriter::open(&path).unwrap();
+        writer.append_batch(&records).unwrap();
+        writer.sync().unwrap();
+        drop(writer);
+
+        let mut reader = WalReader::open(&path).unwrap();
+        let (_page_latest, lsn_offset) = reader.build_index().unwrap();
+        let lsns: Vec<Lsn> = reader
+            .iter_range(lsn_offset[&2]..lsn_offset[&4] + 1)
+            .map(|entry| entry.unwrap().0.lsn)
+            .collect();
+        assert_eq!(lsns, vec![2, 3, 4]);
+        assert_eq!(reader.iter().count(), 5);
+    }
+
     #[test]
     fn test_detect_truncated_entry() {
         let tmp = NamedTempFile::new().unwrap();
@@ -341,10 +569,11 @@ mod tests {
             lsn: 1,
             page_id: 1,
             offset: 0,
-            data: vec![0xAA; 100],
+            data: vec![0xAA; 100].into(),
             prev_lsn: 0,
             mtr_id: 1,
             is_mtr_end: true,
+            timestamp: 0,
         };
 
         let mut writer = WalWriter::open(&path).unwrap();
@@ -1,10 +1,13 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs::OpenOptions;
 use std::path::Path;
 
-use mini_aurora_common::{DurabilityState, Lsn, PageId};
+use mini_aurora_common::{DurabilityState, EngineObserver, Lsn, NoopObserver, PageId, Timestamp};
+use tracing::{info, instrument, warn};
 
-use crate::reader::{ReadResult, WalReader};
+use crate::memory::MemoryWal;
+use crate::reader::{WalError, WalReader};
+use crate::time_index::TimeIndex;
 
 /// Result of WAL recovery: the durable state plus rebuilt indexes.
 #[derive(Debug)]
@@ -15,6 +18,13 @@ pub struct RecoveryResult {
     pub page_index: HashMap<PageId, Lsn>,
     /// LSN → file offset mapping (only includes records ≤ VDL).
     pub lsn_offsets: HashMap<Lsn, u64>,
+    /// Page → LSNs of the records in its redo chain, oldest first (only
+    /// records ≤ VDL).
+    pub page_versions: HashMap<PageId, Vec<Lsn>>,
+    /// Times of the CPLs ≤ VDL.
+    pub time_index: TimeIndex,
+    /// Bytes cut from the end of the WAL.
+    pub truncated_bytes: u64,
 }
 
 /// Perform WAL recovery:
@@ -22,20 +32,59 @@ pub struct RecoveryResult {
 /// 2. Compute VCL (highest contiguous LSN).
 /// 3. Compute VDL (highest CPL ≤ VCL).
 /// 4. Truncate the WAL at VDL (discard incomplete MTRs).
-/// 5. Rebuild page→LSN index from surviving records.
+/// 5. Rebuild page→LSN index from surviving records, leaving out the
+///    pieces of split MTRs that were given up on.
 pub fn recover(wal_path: &Path) -> Result<RecoveryResult, std::io::Error> {
-    let mut reader = WalReader::open(wal_path)?;
+    recover_observed(wal_path, &NoopObserver)
+}
+
+/// `recover`, reporting each phase to `observer`.
+#[instrument(skip_all, fields(path = %wal_path.display()))]
+pub fn recover_observed(wal_path: &Path, observer: &dyn EngineObserver) -> Result<RecoveryResult, std::io::Error> {
+    recover_inner(Log::File { path: wal_path, truncate: true }, observer)
+}
+
+/// `recover` without step 4: the WAL is left untouched, for a reader while
+/// another process owns it and may be mid-append. Records past the VDL,
+/// torn or not, are only left out of the indexes.
+#[instrument(skip_all, fields(path = %wal_path.display()))]
+pub fn recover_read_only(wal_path: &Path) -> Result<RecoveryResult, std::io::Error> {
+    recover_inner(Log::File { path: wal_path, truncate: false }, &NoopObserver)
+}
+
+/// `recover_observed` for a WAL in memory, as left by an engine that
+/// crashed: the same steps, with the tail cut from `wal`.
+#[instrument(skip_all)]
+pub fn recover_in_memory(
+    wal: &mut MemoryWal,
+    observer: &dyn EngineObserver,
+) -> Result<RecoveryResult, std::io::Error> {
+    recover_inner(Log::Memory(wal), observer)
+}
+
+/// The log recovery scans, and where it cuts the tail.
+enum Log<'a> {
+    /// Cut only if `truncate`.
+    File { path: &'a Path, truncate: bool },
+    Memory(&'a mut MemoryWal),
+}
+
+fn recover_inner(log: Log<'_>, observer: &dyn EngineObserver) -> Result<RecoveryResult, std::io::Error> {
+    let mut reader = match &log {
+        Log::File { path, .. } => WalReader::open(path)?,
+        Log::Memory(wal) => wal.reader(),
+    };
 
     // Phase 1: Scan all valid entries
     let mut all_lsns = BTreeSet::new();
     let mut cpls = BTreeSet::new(); // MTR completion points
     let mut entries: Vec<ScannedEntry> = Vec::new();
 
-    reader.seek_start()?;
+    let mut scan = reader.iter();
     loop {
-        let file_offset = reader.stream_position()?;
-        match reader.read_entry()? {
-            ReadResult::Entry(hdr, _data) => {
+        let file_offset = scan.position();
+        match scan.next() {
+            Some(Ok((hdr, _data))) => {
                 all_lsns.insert(hdr.lsn);
                 if hdr.is_mtr_end() {
                     cpls.insert(hdr.lsn);
@@ -43,16 +92,29 @@ pub fn recover(wal_path: &Path) -> Result<RecoveryResult, std::io::Error> {
                 entries.push(ScannedEntry {
                     lsn: hdr.lsn,
                     page_id: hdr.page_id,
+                    mtr_id: hdr.mtr_id,
                     file_offset,
-                    is_mtr_end: hdr.is_mtr_end(),
+                    end_offset: scan.position(),
+                    cpl_timestamp: hdr.is_mtr_end().then_some(hdr.timestamp),
                 });
+                observer.recovery_entry_scanned(hdr.lsn, hdr.page_id, file_offset, hdr.is_mtr_end());
+            }
+            Some(Err(WalError::Io(e))) => return Err(e),
+            // A first entry that is whole but fails its checksum is no
+            // torn write: the log is in an older layout, or not a WAL.
+            // Cutting past the VDL would delete all of it
+            Some(Err(WalError::Corrupted { file_offset: 0 })) => return Err(unrecognised_log()),
+            Some(Err(WalError::Corrupted { file_offset })) => {
+                warn!(file_offset, "corrupt WAL entry, ignoring the rest of the log");
+                break;
             }
-            ReadResult::Eof | ReadResult::Corrupted { .. } => break,
+            None => break,
         }
     }
 
     // Phase 2: Compute VCL — highest LSN N where all 1..=N are present
     let vcl = compute_vcl(&all_lsns);
+    observer.recovery_vcl_computed(vcl);
 
     // Phase 3: Compute VDL — highest CPL ≤ VCL
     let vdl = cpls
@@ -61,38 +123,67 @@ pub fn recover(wal_path: &Path) -> Result<RecoveryResult, std::io::Error> {
         .find(|&&lsn| lsn <= vcl)
         .copied()
         .unwrap_or(0);
+    observer.recovery_vdl_computed(vdl);
 
     // Phase 4: Truncate WAL at VDL
-    // Find the file offset just past the VDL entry
-    let truncate_at = if vdl == 0 {
-        0
-    } else {
-        find_end_of_entry(wal_path, vdl, &entries)?
+    // Cut just past the VDL entry, dropping any torn or garbage tail with it
+    let truncate_at = entries
+        .iter()
+        .find(|e| e.lsn == vdl)
+        .map_or(0, |e| e.end_offset);
+
+    let truncated_bytes = match log {
+        Log::File { path, truncate: true } => {
+            let file = OpenOptions::new().write(true).open(path)?;
+            let truncated_bytes = file.metadata()?.len().saturating_sub(truncate_at);
+            file.set_len(truncate_at)?;
+            drop(file);
+            observer.recovery_truncated(truncate_at, truncated_bytes);
+            truncated_bytes
+        }
+        Log::File { truncate: false, .. } => 0,
+        Log::Memory(wal) => {
+            let truncated_bytes = wal.len().saturating_sub(truncate_at);
+            wal.truncate(truncate_at);
+            observer.recovery_truncated(truncate_at, truncated_bytes);
+            truncated_bytes
+        }
     };
 
-    let file = OpenOptions::new().write(true).open(wal_path)?;
-    file.set_len(truncate_at)?;
-    drop(file);
-
     // Phase 5: Rebuild indexes from surviving entries (LSN ≤ VDL)
+    let abandoned = abandoned_pieces(entries.iter().map(|e| (e.lsn, e.mtr_id, e.cpl_timestamp.is_some())));
     let mut page_index: HashMap<PageId, Lsn> = HashMap::new();
     let mut lsn_offsets: HashMap<Lsn, u64> = HashMap::new();
+    let mut page_versions: HashMap<PageId, Vec<Lsn>> = HashMap::new();
+    let mut time_index = TimeIndex::default();
 
     for entry in &entries {
         if entry.lsn > vdl {
             break;
         }
         lsn_offsets.insert(entry.lsn, entry.file_offset);
+        if let Some(timestamp) = entry.cpl_timestamp {
+            time_index.record(entry.lsn, timestamp);
+        }
+        if abandoned.contains(&entry.lsn) {
+            continue;
+        }
         let latest = page_index.entry(entry.page_id).or_insert(0);
         if entry.lsn > *latest {
             *latest = entry.lsn;
         }
+        page_versions.entry(entry.page_id).or_default().push(entry.lsn);
     }
+    observer.recovery_index_rebuilt(page_index.len(), lsn_offsets.len());
 
+    info!(entries = entries.len(), vcl, vdl, truncated_bytes, "WAL recovered");
     Ok(RecoveryResult {
         durability: DurabilityState { vcl, vdl },
         page_index,
         lsn_offsets,
+        page_versions,
+        time_index,
+        truncated_bytes,
     })
 }
 
@@ -100,9 +191,36 @@ pub fn recover(wal_path: &Path) -> Result<RecoveryResult, std::io::Error> {
 struct ScannedEntry {
     lsn: Lsn,
     page_id: PageId,
+    mtr_id: u64,
     file_offset: u64,
-    #[allow(dead_code)]
-    is_mtr_end: bool,
+    /// File offset just past the entry.
+    end_offset: u64,
+    /// Set on CPLs.
+    cpl_timestamp: Option<Timestamp>,
+}
+
+/// LSNs of split MTRs whose writer gave up before the last piece, given
+/// `(lsn, mtr_id, is_mtr_end)` of each entry in log order. The pieces go to
+/// the log back to back, so an MTR's records with no CPL before the next
+/// MTR's were never finished, even if a later CPL makes them durable.
+pub(crate) fn abandoned_pieces(entries: impl IntoIterator<Item = (Lsn, u64, bool)>) -> HashSet<Lsn> {
+    let mut abandoned = HashSet::new();
+    let mut open: Option<(u64, Vec<Lsn>)> = None;
+    for (lsn, mtr_id, is_mtr_end) in entries {
+        match &mut open {
+            Some((open_id, lsns)) if *open_id == mtr_id => lsns.push(lsn),
+            _ => {
+                if let Some((_, lsns)) = open.take() {
+                    abandoned.extend(lsns);
+                }
+                open = Some((mtr_id, vec![lsn]));
+            }
+        }
+        if is_mtr_end {
+            open = None;
+        }
+    }
+    abandoned
 }
 
 /// Compute VCL: highest N such that all LSNs 1..=N are present.
@@ -117,28 +235,6 @@ fn compute_vcl(lsns: &BTreeSet<Lsn>) -> Lsn {
     expected - 1
 }
 
-/// Find the byte offset in the WAL file immediately after the entry with the given LSN.
-fn find_end_of_entry(
-    wal_path: &Path,
-    target_lsn: Lsn,
-    entries: &[ScannedEntry],
-) -> Result<u64, std::io::Error> {
-    // Find the entry with this LSN
-    for (i, entry) in entries.iter().enumerate() {
-        if entry.lsn == target_lsn {
-            // The end is the start of the next entry, or we need to compute from the entry
-            if i + 1 < entries.len() {
-                return Ok(entries[i + 1].file_offset);
-            } else {
-                // Last entry — use file size (the reader stopped after this entry)
-                let metadata = std::fs::metadata(wal_path)?;
-                return Ok(metadata.len());
-            }
-        }
-    }
-    Ok(0)
-}
-
 // Helper methods on WalReader for recovery
 impl WalReader {
     pub fn seek_start(&mut self) -> Result<(), std::io::Error> {
@@ -153,9 +249,20 @@ impl WalReader {
     }
 }
 
+/// Recovery's refusal of a log whose first entry is whole but fails its
+/// checksum.
+pub(crate) fn unrecognised_log() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "WAL does not start with a valid entry: it is in an older format or not a WAL \
+         (refusing to truncate it)",
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::checksum::{Checksum, Crc32};
     use crate::writer::WalWriter;
     use mini_aurora_common::{RedoRecord, LOG_ENTRY_HEADER_SIZE};
     use tempfile::NamedTempFile;
@@ -165,10 +272,11 @@ mod tests {
             lsn,
             page_id,
             offset: 0,
-            data: vec![lsn as u8; 4],
+            data: vec![lsn as u8; 4].into(),
             prev_lsn,
             mtr_id,
             is_mtr_end: is_end,
+            timestamp: 1_000 + lsn,
         }
     }
 
@@ -194,6 +302,9 @@ mod tests {
         assert_eq!(result.durability.vdl, 3);
         assert_eq!(result.page_index[&1], 3);
         assert_eq!(result.page_index[&2], 2);
+        assert_eq!(result.page_versions[&1], [1, 3]);
+        assert_eq!(result.page_versions[&2], [2]);
+        assert_eq!(result.time_index.latest(), Some((3, 1_003)));
     }
 
     #[test]
@@ -226,6 +337,35 @@ mod tests {
         assert!(!result.lsn_offsets.contains_key(&5));
     }
 
+    #[test]
+    fn test_recovery_skips_abandoned_pieces() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+
+        // MTR 1's first piece (1,2), MTR 2 in full (3), then MTR 3 in two
+        // pieces (4,5)
+        let records = vec![
+            make_record(1, 1, 0, 1, false),
+            make_record(2, 2, 0, 1, false),
+            make_record(3, 2, 0, 2, true),
+            make_record(4, 1, 0, 3, false),
+            make_record(5, 1, 4, 3, true),
+        ];
+
+        let mut writer = WalWriter::open(&path).unwrap();
+        writer.append_batch(&records).unwrap();
+        writer.sync().unwrap();
+        drop(writer);
+
+        let result = recover(&path).unwrap();
+        assert_eq!(result.durability.vdl, 5);
+        // MTR 1's records stay in the log but no page reads them
+        assert_eq!(result.lsn_offsets.len(), 5);
+        assert_eq!(result.page_versions[&1], [4, 5]);
+        assert_eq!(result.page_versions[&2], [3]);
+        assert_eq!(result.page_index[&2], 3);
+    }
+
     #[test]
     fn test_recovery_truncated_record() {
         let tmp = NamedTempFile::new().unwrap();
@@ -254,6 +394,46 @@ mod tests {
         assert_eq!(result.lsn_offsets.len(), 1);
     }
 
+    #[test]
+    fn test_read_only_recovery_leaves_torn_tail() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+
+        let mut writer = WalWriter::open(&path).unwrap();
+        writer.append_batch(&[make_record(1, 1, 0, 1, true), make_record(2, 2, 0, 2, false)]).unwrap();
+        writer.sync().unwrap();
+        drop(writer);
+        let len = std::fs::metadata(&path).unwrap().len();
+
+        // Record 2 is an open MTR, as a writer mid-append would leave it
+        let result = recover_read_only(&path).unwrap();
+        assert_eq!(result.durability.vdl, 1);
+        assert_eq!(result.lsn_offsets.len(), 1);
+        assert_eq!(result.truncated_bytes, 0);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+    }
+
+    #[test]
+    fn test_recovery_truncates_trailing_garbage() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+
+        let mut writer = WalWriter::open(&path).unwrap();
+        writer.append_batch(&[make_record(1, 1, 0, 1, true)]).unwrap();
+        writer.sync().unwrap();
+        drop(writer);
+        let valid_len = std::fs::metadata(&path).unwrap().len();
+
+        // Garbage shorter than a header after the last (VDL) entry
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.extend_from_slice(&[0xA5; 7]);
+        std::fs::write(&path, bytes).unwrap();
+
+        let result = recover(&path).unwrap();
+        assert_eq!(result.durability.vdl, 1);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), valid_len);
+    }
+
     #[test]
     fn test_recovery_gap_in_lsns() {
         let tmp = NamedTempFile::new().unwrap();
@@ -284,6 +464,35 @@ mod tests {
         assert_eq!(result.durability.vdl, 2);
     }
 
+    #[test]
+    fn test_recovery_refuses_a_log_in_the_old_layout() {
+        let tmp = NamedTempFile::new().unwrap();
+        let path = tmp.path().to_path_buf();
+        let mut writer = WalWriter::open(&path).unwrap();
+        writer.append_batch(&[make_record(1, 1, 0, 1, true), make_record(2, 2, 0, 2, true)]).unwrap();
+        writer.sync().unwrap();
+        drop(writer);
+
+        // Rewrite each entry with the 41-byte header that had no timestamp,
+        // checksummed the way that layout was
+        let current = std::fs::read(&path).unwrap();
+        let mut old = Vec::new();
+        for entry in current.chunks(LOG_ENTRY_HEADER_SIZE + 4) {
+            let mut header = [&entry[..36], &entry[44..45]].concat();
+            let data = &entry[LOG_ENTRY_HEADER_SIZE..];
+            let crc = Crc32.entry(&header, data);
+            header.extend_from_slice(&crc.to_le_bytes());
+            old.extend_from_slice(&header);
+            old.extend_from_slice(data);
+        }
+        std::fs::write(&path, &old).unwrap();
+
+        let err = recover(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("older format"));
+        assert_eq!(std::fs::read(&path).unwrap(), old, "the log must be left as it was");
+    }
+
     #[test]
     fn test_recovery_empty_wal() {
         let tmp = NamedTempFile::new().unwrap();
@@ -296,4 +505,23 @@ mod tests {
         assert_eq!(result.durability.vdl, 0);
         assert!(result.page_index.is_empty());
     }
+
+    #[test]
+    fn test_in_memory_recovery_matches_a_file() {
+        let mut wal = MemoryWal::new();
+        wal.append(&[make_record(1, 1, 0, 1, false), make_record(2, 2, 0, 1, true)]);
+        wal.append(&[make_record(3, 1, 1, 2, false)]); // no CPL — incomplete MTR
+        wal.bytes_mut().extend_from_slice(&[0xA5; 7]);
+        let tmp = NamedTempFile::new().unwrap();
+        std::fs::write(tmp.path(), wal.to_bytes()).unwrap();
+
+        let from_file = recover(tmp.path()).unwrap();
+        let result = recover_in_memory(&mut wal, &NoopObserver).unwrap();
+        assert_eq!((result.durability.vcl, result.durability.vdl), (3, 2));
+        assert_eq!(from_file.durability.vdl, 2);
+        assert_eq!(result.lsn_offsets, from_file.lsn_offsets);
+        assert_eq!(result.page_versions, from_file.page_versions);
+        assert_eq!(result.truncated_bytes, LOG_ENTRY_HEADER_SIZE as u64 + 4 + 7);
+        assert_eq!(wal.to_bytes(), std::fs::read(tmp.path()).unwrap());
+    }
 }
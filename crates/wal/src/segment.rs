@@ -1,17 +1,37 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::thread;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use mini_aurora_common::{DurabilityState, Lsn, PageId, RedoRecord, LOG_ENTRY_HEADER_SIZE};
+use mini_aurora_common::{
+    DurabilityState, EngineObserver, Lsn, NoopObserver, PageId, RedoRecord, Timestamp,
+    LOG_ENTRY_HEADER_SIZE,
+};
 use serde::{Deserialize, Serialize};
-
-use crate::reader::WalReader;
+use tracing::{debug, info, instrument, warn};
+
+use crate::checksum::ChecksumKind;
+use crate::disk::{Disk, DiskStats, HostDisk, TierDisks};
+use crate::footer::{self, FooterCheck, SegmentFooter};
+use crate::reader::{WalError, WalReader};
+use crate::time_index::TimeIndex;
+use crate::volume::VolumeConfig;
 use crate::writer::WalWriter;
 
 pub type SegmentId = u32;
 
+/// Older cold segments fetched alongside a cold read by default.
+pub const DEFAULT_COLD_READ_AHEAD: usize = 1;
+
+/// Sealed segments kept in the hot tier by default; older ones are cooled.
+pub const DEFAULT_HOT_SEGMENTS: usize = 2;
+
+/// Idle segment readers kept open by default.
+pub const DEFAULT_READER_POOL_SIZE: usize = 8;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Tier {
     Hot,
@@ -26,19 +46,65 @@ pub struct SegmentMeta {
     pub lsn_range: (Lsn, Lsn),
     pub size_bytes: u64,
     pub sealed: bool,
+    /// Set when a cold segment is copied back to the hot tier on a read;
+    /// cleared when it cools again.
+    #[serde(default)]
+    pub promoted: bool,
+    /// Sealed with a [`SegmentFooter`]; false for segments sealed before
+    /// footers were written.
+    #[serde(default)]
+    pub footer: bool,
+    /// Chain walks that read the segment, decayed by each cooling pass.
+    /// Kept in memory only.
+    #[serde(skip)]
+    pub reads: u64,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl SegmentMeta {
+    /// The LSN the segment starts at: from its filename, or the first
+    /// record's for a legacy name.
+    pub fn first_lsn(&self) -> Lsn {
+        parse_segment_filename(&self.filename).map_or(self.lsn_range.0, |(_, lsn)| lsn)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct LsnLocation {
     pub segment_id: SegmentId,
     pub file_offset: u64,
 }
 
+/// A run of cold segments rewritten as one file by `compact_cold`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentMerge {
+    /// Id kept by the merged segment: the oldest in the run.
+    pub into: SegmentId,
+    /// Each merged segment and the offset its bytes now start at.
+    pub sources: Vec<(SegmentId, u64)>,
+    pub bytes: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Manifest {
     pub segments: Vec<SegmentMeta>,
     /// The ID of the currently active (unsealed) segment.
     pub active_segment_id: SegmentId,
+    /// The LSN the active segment's filename starts at. `None` in manifests
+    /// from before filenames carried it: the active segment then keeps its
+    /// old name until it is sealed.
+    #[serde(default)]
+    pub active_first_lsn: Option<Lsn>,
+    /// Times of the CPLs, as of the last save.
+    #[serde(default)]
+    pub time_index: TimeIndex,
+    /// Settings the volume was created with; the defaults in manifests
+    /// from before there were settings.
+    #[serde(default)]
+    pub volume: VolumeConfig,
+    /// Records below this LSN may be gone: `truncate_before` deleted the
+    /// segments holding them. 0 if the log was never truncated.
+    #[serde(default)]
+    pub truncated_before: Lsn,
 }
 
 impl Manifest {
@@ -46,6 +112,10 @@ impl Manifest {
         Self {
             segments: Vec::new(),
             active_segment_id: 1,
+            active_first_lsn: Some(1),
+            time_index: TimeIndex::default(),
+            volume: VolumeConfig::default(),
+            truncated_before: 0,
         }
     }
 }
@@ -58,15 +128,108 @@ pub struct SegmentManager {
     active_writer: WalWriter,
     active_segment_id: SegmentId,
     active_first_lsn: Option<Lsn>,
+    active_last_lsn: Option<Lsn>,
     active_bytes_written: u64,
     max_segment_bytes: u64,
-    cold_latency: Duration,
+    hot_disk: Arc<dyn Disk>,
+    /// Accounts cold I/O without sleeping; cold reads wait in `fetch_cold`'s
+    /// caller.
+    cold_disk: Arc<dyn Disk>,
     base_dir: PathBuf,
+    /// How many older cold segments a cold read brings along.
+    cold_read_ahead: usize,
+    /// Cold segment reads served by an earlier read-ahead (no latency paid).
+    read_ahead_hits: AtomicU64,
+    /// Sealed segments that stay hot when cooling.
+    hot_segments: usize,
+    /// Copy cold segments back to the hot tier when they are read.
+    promote_on_read: bool,
+    /// Per-segment read counts and in-flight promotions.
+    tiering: Mutex<TieringState>,
+    /// Open readers returned by callers, for reuse by the next reads.
+    readers: Mutex<ReaderPool>,
+    /// Sealed segments checked against their footers since their file last
+    /// changed.
+    footer_checks: Mutex<HashMap<SegmentId, FooterCheck>>,
+}
+
+/// Reader pool activity; see [`SegmentManager::reader_pool_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReaderPoolStats {
+    /// Readers handed out from the pool.
+    pub hits: u64,
+    /// Readers opened because none for the segment was idle.
+    pub opens: u64,
+    /// Readers idle in the pool now.
+    pub idle: usize,
+}
+
+/// Idle segment readers, most recently returned first. Readers seek before
+/// every read, so one left anywhere in its file can be reused as is.
+struct ReaderPool {
+    idle: VecDeque<(SegmentId, WalReader)>,
+    capacity: usize,
+    hits: u64,
+    opens: u64,
+}
+
+impl ReaderPool {
+    fn new(capacity: usize) -> Self {
+        Self { idle: VecDeque::new(), capacity, hits: 0, opens: 0 }
+    }
+
+    fn take(&mut self, segment_id: SegmentId) -> Option<WalReader> {
+        let i = self.idle.iter().position(|(id, _)| *id == segment_id)?;
+        self.hits += 1;
+        self.idle.remove(i).map(|(_, reader)| reader)
+    }
+
+    fn put(&mut self, segment_id: SegmentId, reader: WalReader) {
+        self.idle.push_front((segment_id, reader));
+        self.idle.truncate(self.capacity);
+    }
+
+    /// Close the idle readers of segments whose file or tier changed.
+    fn invalidate(&mut self, segments: &[SegmentId]) {
+        self.idle.retain(|(id, _)| !segments.contains(id));
+    }
+}
+
+#[derive(Default)]
+struct TieringState {
+    reads: HashMap<SegmentId, u64>,
+    promoting: HashSet<SegmentId>,
+}
+
+/// A cold segment being copied back to the hot tier, from
+/// [`SegmentManager::promotion`]. [`copy`](Self::copy) does the I/O without
+/// the manager, so it can run while the manager serves other requests;
+/// [`SegmentManager::finish_promotion`] then installs the copy.
+pub struct Promotion {
+    pub segment_id: SegmentId,
+    filename: String,
+    src: PathBuf,
+    tmp: PathBuf,
+    hot_disk: Arc<dyn Disk>,
+}
+
+impl Promotion {
+    /// Copy the cold file into the hot tier under a temporary name. The
+    /// cold read that triggered the promotion already paid for fetching
+    /// it, so only the hot write and fsync are charged.
+    pub fn copy(&self) -> Result<(), std::io::Error> {
+        let len = fs::copy(&self.src, &self.tmp)?;
+        fs::File::open(&self.tmp)?.sync_all()?;
+        self.hot_disk.write(&self.tmp, 0, len);
+        self.hot_disk.sync(&self.tmp);
+        Ok(())
+    }
 }
 
 impl SegmentManager {
-    /// Open or create a segmented WAL in `base_dir`.
-    pub fn open(base_dir: &Path, max_segment_bytes: u64, cold_latency: Duration) -> Result<Self, std::io::Error> {
+    /// Open or create a segmented WAL in `base_dir`, charging each tier's
+    /// I/O to a disk with its profile in `disks`.
+    pub fn open(base_dir: &Path, max_segment_bytes: u64, disks: TierDisks) -> Result<Self, std::io::Error> {
         let hot_dir = base_dir.join("hot");
         let cold_dir = base_dir.join("cold");
         fs::create_dir_all(&hot_dir)?;
@@ -83,8 +246,9 @@ impl SegmentManager {
         // Open (or create) the active segment for appending.
         // WalWriter::open uses create+append mode, so existing data is preserved.
         let active_segment_id = manifest.active_segment_id;
-        let active_path = hot_dir.join(segment_filename(active_segment_id));
-        let active_writer = WalWriter::open(&active_path)?;
+        let active_path = hot_dir.join(active_filename(&manifest));
+        let hot_disk = disks.hot.disk();
+        let active_writer = WalWriter::open_on(&active_path, hot_disk.clone())?;
         let active_bytes_written = fs::metadata(&active_path).map(|m| m.len()).unwrap_or(0);
 
         Ok(Self {
@@ -94,23 +258,44 @@ impl SegmentManager {
             active_writer,
             active_segment_id,
             active_first_lsn: None,
+            active_last_lsn: None,
             active_bytes_written,
             max_segment_bytes,
-            cold_latency,
+            hot_disk,
+            cold_disk: disks.cold.accounting_disk(),
             base_dir: base_dir.to_path_buf(),
+            cold_read_ahead: DEFAULT_COLD_READ_AHEAD,
+            read_ahead_hits: AtomicU64::new(0),
+            hot_segments: DEFAULT_HOT_SEGMENTS,
+            promote_on_read: false,
+            tiering: Mutex::default(),
+            readers: Mutex::new(ReaderPool::new(DEFAULT_READER_POOL_SIZE)),
+            footer_checks: Mutex::default(),
         })
     }
 
     /// Append a batch of records, rotating segments as needed.
     /// Returns LsnLocations for each record.
     pub fn append_batch(&mut self, records: &[RedoRecord]) -> Result<Vec<LsnLocation>, std::io::Error> {
+        self.append_batch_with(records, |_, _| {})
+    }
+
+    /// Like `append_batch`, calling `on_rotate` with the sealed segment and
+    /// the new segment's id each time the batch fills a segment.
+    pub fn append_batch_with(
+        &mut self,
+        records: &[RedoRecord],
+        mut on_rotate: impl FnMut(&SegmentMeta, SegmentId),
+    ) -> Result<Vec<LsnLocation>, std::io::Error> {
         let mut locations = Vec::with_capacity(records.len());
 
         for record in records {
             // Check if we need to rotate before writing
             let entry_size = LOG_ENTRY_HEADER_SIZE as u64 + record.data.len() as u64;
             if self.active_bytes_written > 0 && self.active_bytes_written + entry_size > self.max_segment_bytes {
-                self.rotate()?;
+                let (_, new_id) = self.rotate(record.lsn)?;
+                let sealed = self.manifest.segments.last().expect("rotate records the sealed segment");
+                on_rotate(sealed, new_id);
             }
 
             let file_offset = self.active_bytes_written;
@@ -118,6 +303,7 @@ impl SegmentManager {
             if self.active_first_lsn.is_none() {
                 self.active_first_lsn = Some(record.lsn);
             }
+            self.active_last_lsn = Some(record.lsn);
 
             locations.push(LsnLocation {
                 segment_id: self.active_segment_id,
@@ -126,6 +312,9 @@ impl SegmentManager {
 
             self.active_writer.append(record)?;
             self.active_bytes_written += entry_size;
+            if record.is_mtr_end {
+                self.manifest.time_index.record(record.lsn, record.timestamp);
+            }
         }
 
         Ok(locations)
@@ -136,42 +325,90 @@ impl SegmentManager {
         self.active_writer.sync()
     }
 
-    /// Seal the current segment and open a new one.
+    /// Fsync the active segment and save the manifest, for a clean shutdown.
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.active_writer.sync()?;
+        self.save_manifest()
+    }
+
+    /// Where a clean shutdown saves the indexes.
+    pub fn sidecar_path(&self) -> PathBuf {
+        self.base_dir.join("index.json")
+    }
+
+    /// Bytes in the active segment.
+    pub fn active_len(&self) -> u64 {
+        self.active_bytes_written
+    }
+
+    /// Pick up where a clean shutdown left off, in place of `recover`:
+    /// take the active segment's LSN range from the saved locations.
+    pub fn resume(&mut self, lsn_offsets: &HashMap<Lsn, LsnLocation>) {
+        let active = lsn_offsets
+            .iter()
+            .filter(|(_, loc)| loc.segment_id == self.active_segment_id)
+            .map(|(&lsn, _)| lsn);
+        self.active_first_lsn = active.clone().min();
+        self.active_last_lsn = active.max();
+    }
+
+    /// Seal the current segment and open a new one, named for `first_lsn`:
+    /// the LSN of the next record appended.
     /// Returns (sealed_id, new_id).
-    pub fn rotate(&mut self) -> Result<(SegmentId, SegmentId), std::io::Error> {
+    #[instrument(skip(self), fields(sealed_id = self.active_segment_id))]
+    pub fn rotate(&mut self, first_lsn: Lsn) -> Result<(SegmentId, SegmentId), std::io::Error> {
         let sealed_id = self.active_segment_id;
 
-        // Sync before sealing
+        // Footer, then sync before sealing
+        let footer = self.active_footer()?;
+        footer::append_footer(&self.active_segment_path(), &footer, &*self.hot_disk)?;
         self.active_writer.sync()?;
 
         // Record the sealed segment in the manifest
-        let sealed_filename = segment_filename(sealed_id);
-        let first_lsn = self.active_first_lsn.unwrap_or(0);
+        let sealed_filename = active_filename(&self.manifest);
         let sealed_meta = SegmentMeta {
             id: sealed_id,
             filename: sealed_filename,
             tier: Tier::Hot,
-            lsn_range: (first_lsn, first_lsn), // updated by caller via update_sealed_lsn_range
+            lsn_range: self.active_lsn_range(),
             size_bytes: self.active_bytes_written,
             sealed: true,
+            promoted: false,
+            footer: true,
+            reads: 0,
         };
         self.manifest.segments.push(sealed_meta);
 
         // Open new segment
         let new_id = sealed_id + 1;
         self.manifest.active_segment_id = new_id;
+        self.manifest.active_first_lsn = Some(first_lsn);
         self.save_manifest()?;
 
-        let new_path = self.hot_dir.join(segment_filename(new_id));
-        self.active_writer = WalWriter::open(&new_path)?;
+        let new_path = self.hot_dir.join(segment_filename(new_id, first_lsn));
+        self.active_writer = WalWriter::open_on(&new_path, self.hot_disk.clone())?
+            .with_checksum(self.active_writer.checksum());
 
         self.active_segment_id = new_id;
         self.active_first_lsn = None;
+        self.active_last_lsn = None;
+        info!(new_id, sealed_bytes = self.active_bytes_written, "segment rotated");
         self.active_bytes_written = 0;
 
         Ok((sealed_id, new_id))
     }
 
+    /// Times of the CPLs appended so far.
+    pub fn time_index(&self) -> &TimeIndex {
+        &self.manifest.time_index
+    }
+
+    /// Forget the times of CPLs after `cpl`; the change is saved with the
+    /// manifest.
+    pub fn rewind_time_index(&mut self, cpl: Option<(Lsn, Timestamp)>) {
+        self.manifest.time_index.rewind(cpl);
+    }
+
     /// Update the LSN range on a sealed segment.
     pub fn update_sealed_lsn_range(&mut self, segment_id: SegmentId, lsn_range: (Lsn, Lsn)) {
         for seg in &mut self.manifest.segments {
@@ -181,23 +418,139 @@ impl SegmentManager {
         }
     }
 
+    /// Every segment, oldest first, ending with the active (unsealed) one.
+    pub fn segments(&self) -> Vec<SegmentMeta> {
+        let mut segments = self.manifest.segments.clone();
+        for seg in &mut segments {
+            seg.reads = self.reads(seg.id);
+        }
+        segments.push(SegmentMeta {
+            id: self.active_segment_id,
+            filename: active_filename(&self.manifest),
+            tier: Tier::Hot,
+            lsn_range: self.active_lsn_range(),
+            size_bytes: self.active_bytes_written,
+            sealed: false,
+            promoted: false,
+            footer: false,
+            reads: self.reads(self.active_segment_id),
+        });
+        segments
+    }
+
+    /// Segments whose filename disagrees with the manifest: another id, a
+    /// first LSN other than the first record's, or one not past the end of
+    /// the segment before. Empty when every name can be trusted to locate
+    /// LSNs; legacy names are not checked.
+    pub fn filename_mismatches(&self) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        let mut prev_last = 0;
+        for seg in self.segments() {
+            if let Some((id, first_lsn)) = parse_segment_filename(&seg.filename) {
+                if id != seg.id {
+                    mismatches.push(format!("seg{}: file {} names segment {id}", seg.id, seg.filename));
+                }
+                if seg.lsn_range.0 != 0 && first_lsn != seg.lsn_range.0 {
+                    mismatches.push(format!(
+                        "seg{}: file {} starts at L{first_lsn} but its first record is L{}",
+                        seg.id, seg.filename, seg.lsn_range.0
+                    ));
+                }
+                if first_lsn <= prev_last {
+                    mismatches.push(format!(
+                        "seg{}: file {} starts at L{first_lsn}, within the segment before (up to L{prev_last})",
+                        seg.id, seg.filename
+                    ));
+                }
+            }
+            prev_last = prev_last.max(seg.lsn_range.1);
+        }
+        mismatches
+    }
+
+    /// Footer for the active segment as it stands. The writer keeps its CRC
+    /// unless it opened a non-empty file, after a restart; then the file is
+    /// read back.
+    fn active_footer(&self) -> Result<SegmentFooter, std::io::Error> {
+        let lsn_range = self.active_lsn_range();
+        if let Some((crc32, record_count)) = self.active_writer.contents_crc() {
+            let records_len = self.active_bytes_written;
+            return Ok(SegmentFooter { record_count, lsn_range, records_len, crc32 });
+        }
+        let path = self.active_segment_path();
+        let records = fs::read(&path)?;
+        self.hot_disk.read(&path, 0, records.len() as u64);
+        Ok(SegmentFooter::for_records(&records, lsn_range))
+    }
+
+    /// First and last LSN written to the active segment; (0, 0) when empty.
+    fn active_lsn_range(&self) -> (Lsn, Lsn) {
+        let first = self.active_first_lsn.unwrap_or(0);
+        (first, self.active_last_lsn.unwrap_or(first))
+    }
+
+    /// Path of the segment currently being appended to.
+    pub fn active_segment_path(&self) -> PathBuf {
+        self.hot_dir.join(active_filename(&self.manifest))
+    }
+
     /// Open a reader for a given segment. Returns the reader and its tier.
+    /// An idle reader from the pool is reused if there is one; hand readers
+    /// back with [`release_segment_reader`](Self::release_segment_reader).
+    ///
+    /// Hot reads are charged to the hot disk entry by entry. A cold segment
+    /// is read from the copy a [`fetch_cold`](Self::fetch_cold) paid for,
+    /// so its reader charges nothing.
     pub fn open_segment_reader(&self, segment_id: SegmentId) -> Result<(WalReader, Tier), std::io::Error> {
+        if let Some(tier) = self.tier(segment_id) {
+            if let Some(reader) = self.readers.lock().unwrap().take(segment_id) {
+                return Ok((reader, tier));
+            }
+        }
+        let opened = self.open_segment_file(segment_id)?;
+        self.readers.lock().unwrap().opens += 1;
+        Ok(opened)
+    }
+
+    /// Return a reader from `open_segment_reader` to the pool, closing the
+    /// least recently returned one if the pool is full.
+    pub fn release_segment_reader(&self, segment_id: SegmentId, reader: WalReader) {
+        if self.tier(segment_id).is_some() {
+            self.readers.lock().unwrap().put(segment_id, reader);
+        }
+    }
+
+    /// Set how many idle readers the pool keeps open. Zero disables it.
+    pub fn set_reader_pool_size(&mut self, readers: usize) {
+        let pool = self.readers.get_mut().unwrap();
+        pool.capacity = readers;
+        pool.idle.truncate(readers);
+    }
+
+    /// Readers reused and opened so far, and those idle now.
+    pub fn reader_pool_stats(&self) -> ReaderPoolStats {
+        let pool = self.readers.lock().unwrap();
+        ReaderPoolStats { hits: pool.hits, opens: pool.opens, idle: pool.idle.len() }
+    }
+
+    fn open_segment_file(&self, segment_id: SegmentId) -> Result<(WalReader, Tier), std::io::Error> {
         // Check if it's the active segment
         if segment_id == self.active_segment_id {
-            let path = self.hot_dir.join(segment_filename(segment_id));
-            let reader = WalReader::open(&path)?;
+            let path = self.active_segment_path();
+            let reader = WalReader::open_on(&path, self.hot_disk.clone())?;
             return Ok((reader, Tier::Hot));
         }
 
         // Check manifest for sealed segments
         for seg in &self.manifest.segments {
             if seg.id == segment_id {
-                let path = match seg.tier {
-                    Tier::Hot => self.hot_dir.join(&seg.filename),
-                    Tier::Cold => self.cold_dir.join(&seg.filename),
+                if !self.footer_checks.lock().unwrap().contains_key(&segment_id) {
+                    self.verify_footer(segment_id)?;
+                }
+                let reader = match seg.tier {
+                    Tier::Hot => WalReader::open_on(&self.hot_dir.join(&seg.filename), self.hot_disk.clone())?,
+                    Tier::Cold => WalReader::open_on(&self.cold_dir.join(&seg.filename), Arc::new(HostDisk))?,
                 };
-                let reader = WalReader::open(&path)?;
                 return Ok((reader, seg.tier));
             }
         }
@@ -208,134 +561,615 @@ impl SegmentManager {
         ))
     }
 
+    /// Read sealed `segment_id` whole and check it against its footer.
+    /// `None` if there is no such sealed segment. Each segment is checked
+    /// this way on its first read, and on every scrub.
+    pub fn verify_footer(&self, segment_id: SegmentId) -> Result<Option<FooterCheck>, std::io::Error> {
+        let Some(seg) = self.manifest.segments.iter().find(|s| s.id == segment_id) else {
+            return Ok(None);
+        };
+        // Cold reads are paid for by `fetch_cold`, as for `open_segment_reader`
+        let (path, disk): (PathBuf, &dyn Disk) = match seg.tier {
+            Tier::Hot => (self.hot_dir.join(&seg.filename), &*self.hot_disk),
+            Tier::Cold => (self.cold_dir.join(&seg.filename), &HostDisk),
+        };
+        let check = footer::verify_footer(&path, seg.lsn_range, seg.footer, disk)?;
+        if let FooterCheck::Damaged(damage) = &check {
+            warn!(segment_id, %damage, "segment does not match its footer");
+        }
+        self.footer_checks.lock().unwrap().insert(segment_id, check.clone());
+        Ok(Some(check))
+    }
+
+    /// The last footer check of `segment_id`, if it was checked since its
+    /// file last changed.
+    pub fn footer_check(&self, segment_id: SegmentId) -> Option<FooterCheck> {
+        self.footer_checks.lock().unwrap().get(&segment_id).cloned()
+    }
+
+    /// Drop the idle readers and footer checks of segments whose file or
+    /// tier changed.
+    fn forget_files(&mut self, segments: &[SegmentId]) {
+        self.readers.get_mut().unwrap().invalidate(segments);
+        self.footer_checks.get_mut().unwrap().retain(|id, _| !segments.contains(id));
+    }
+
+    /// The tier of a sealed or active segment.
+    pub fn tier(&self, segment_id: SegmentId) -> Option<Tier> {
+        if segment_id == self.active_segment_id {
+            return Some(Tier::Hot);
+        }
+        self.manifest.segments.iter().find(|s| s.id == segment_id).map(|s| s.tier)
+    }
+
     /// Move old sealed segments from hot to cold.
-    /// Keeps the most recent `keep_hot` sealed segments in hot tier.
+    /// Keeps `keep_hot` sealed segments in the hot tier: the most read ones,
+    /// and among equally read ones the most recent. Read counts are halved
+    /// on every pass, so a segment read often long ago cools eventually.
     pub fn cool_segments(&mut self, keep_hot: usize) -> Result<Vec<SegmentId>, std::io::Error> {
         let mut cooled = Vec::new();
-        let sealed_hot: Vec<usize> = self.manifest.segments.iter()
+        let mut sealed_hot: Vec<(u64, SegmentId, usize)> = self.manifest.segments.iter()
             .enumerate()
             .filter(|(_, s)| s.sealed && s.tier == Tier::Hot)
-            .map(|(i, _)| i)
+            .map(|(i, s)| (self.reads(s.id), s.id, i))
             .collect();
 
-        if sealed_hot.len() <= keep_hot {
-            return Ok(cooled);
+        if sealed_hot.len() > keep_hot {
+            sealed_hot.sort();
+            let to_cool = sealed_hot.len() - keep_hot;
+            for &(_, seg_id, idx) in sealed_hot.iter().take(to_cool) {
+                let seg = &self.manifest.segments[idx];
+                let src = self.hot_dir.join(&seg.filename);
+                let dst = self.cold_dir.join(&seg.filename);
+                fs::rename(&src, &dst)?;
+                // The upload to the cold tier
+                self.cold_disk.write(&dst, 0, seg.size_bytes);
+                self.cold_disk.sync(&dst);
+                let seg = &mut self.manifest.segments[idx];
+                seg.tier = Tier::Cold;
+                seg.promoted = false;
+                cooled.push(seg_id);
+            }
+            cooled.sort();
+            self.forget_files(&cooled);
         }
 
-        let to_cool = sealed_hot.len() - keep_hot;
-        for &idx in sealed_hot.iter().take(to_cool) {
-            let seg = &self.manifest.segments[idx];
-            let src = self.hot_dir.join(&seg.filename);
-            let dst = self.cold_dir.join(&seg.filename);
-            fs::rename(&src, &dst)?;
-            let seg_id = seg.id;
-            self.manifest.segments[idx].tier = Tier::Cold;
-            cooled.push(seg_id);
-        }
+        self.tiering.lock().unwrap().reads.retain(|_, reads| {
+            *reads /= 2;
+            *reads > 0
+        });
 
         if !cooled.is_empty() {
             self.save_manifest()?;
+            info!(?cooled, "segments moved to cold tier");
         }
 
         Ok(cooled)
     }
 
-    /// Inject cold-tier latency (blocking sleep).
-    pub fn inject_cold_latency(&self) {
-        if !self.cold_latency.is_zero() {
-            thread::sleep(self.cold_latency);
+    /// Count a chain walk reading `segment_id`.
+    pub fn record_read(&self, segment_id: SegmentId) {
+        *self.tiering.lock().unwrap().reads.entry(segment_id).or_default() += 1;
+    }
+
+    /// Chain walks that read `segment_id`, as decayed by cooling.
+    pub fn reads(&self, segment_id: SegmentId) -> u64 {
+        self.tiering.lock().unwrap().reads.get(&segment_id).copied().unwrap_or(0)
+    }
+
+    /// Whether cold segments are promoted back to the hot tier when read.
+    pub fn promote_on_read(&self) -> bool {
+        self.promote_on_read
+    }
+
+    /// Promote cold segments back to the hot tier when they are read.
+    pub fn set_promote_on_read(&mut self, promote: bool) {
+        self.promote_on_read = promote;
+    }
+
+    /// Checksum records appended from now on with `checksum`, in this
+    /// segment and the ones after it.
+    pub fn set_checksum(&mut self, checksum: ChecksumKind) {
+        self.active_writer.set_checksum(checksum);
+    }
+
+    pub fn checksum(&self) -> ChecksumKind {
+        self.active_writer.checksum()
+    }
+
+    /// Settings the volume was created with, from the manifest.
+    pub fn volume(&self) -> VolumeConfig {
+        self.manifest.volume
+    }
+
+    /// Record `volume` as the volume's settings, saving the manifest. Only
+    /// for a volume with no records yet, which nothing has been written for.
+    pub fn set_volume(&mut self, volume: VolumeConfig) -> Result<(), std::io::Error> {
+        self.manifest.volume = volume;
+        self.save_manifest()
+    }
+
+    /// Start promoting cold `segment_id` back to the hot tier. `None` if it
+    /// is not cold or is already being promoted.
+    pub fn promotion(&self, segment_id: SegmentId) -> Option<Promotion> {
+        let seg = self.manifest.segments.iter().find(|s| s.id == segment_id && s.tier == Tier::Cold)?;
+        if !self.tiering.lock().unwrap().promoting.insert(segment_id) {
+            return None;
+        }
+        Some(Promotion {
+            segment_id,
+            filename: seg.filename.clone(),
+            src: self.cold_dir.join(&seg.filename),
+            tmp: self.hot_dir.join(format!("{}.promote", seg.filename)),
+            hot_disk: self.hot_disk.clone(),
+        })
+    }
+
+    /// Install a promotion whose [`copy`](Promotion::copy) returned
+    /// `copied`: the segment moves to the hot tier and its cold file is
+    /// removed. Returns whether it was promoted. If the copy failed, or the
+    /// segment was compacted or dropped meanwhile, the copy is discarded.
+    pub fn finish_promotion(
+        &mut self,
+        job: Promotion,
+        copied: Result<(), std::io::Error>,
+    ) -> Result<bool, std::io::Error> {
+        self.tiering.lock().unwrap().promoting.remove(&job.segment_id);
+        let idx = self.manifest.segments.iter().position(|s| {
+            s.id == job.segment_id && s.tier == Tier::Cold && s.filename == job.filename
+        });
+        let idx = match (idx, copied) {
+            (Some(idx), Ok(())) => idx,
+            (_, copied) => {
+                if let Err(e) = copied {
+                    warn!(segment_id = job.segment_id, error = %e, "segment promotion failed");
+                }
+                let _ = fs::remove_file(&job.tmp);
+                return Ok(false);
+            }
+        };
+
+        // A crash before the manifest save leaves the hot copy unreferenced,
+        // and one after it the cold file: either is collected as garbage.
+        fs::rename(&job.tmp, self.hot_dir.join(&job.filename))?;
+        let seg = &mut self.manifest.segments[idx];
+        seg.tier = Tier::Hot;
+        seg.promoted = true;
+        self.forget_files(&[job.segment_id]);
+        self.save_manifest()?;
+        fs::remove_file(&job.src)?;
+        info!(segment_id = job.segment_id, "segment promoted to hot tier");
+        Ok(true)
+    }
+
+    /// Seal the active segment if it holds any records, as `rotate` does
+    /// when it fills up. Returns the sealed segment.
+    pub fn seal(&mut self) -> Result<Option<SegmentMeta>, std::io::Error> {
+        if self.active_bytes_written == 0 {
+            return Ok(None);
+        }
+        let next_lsn = self.active_last_lsn.map_or(1, |lsn| lsn + 1);
+        self.rotate(next_lsn)?;
+        Ok(self.manifest.segments.last().cloned())
+    }
+
+    /// Rewrite each run of two or more adjacent sealed cold segments as one
+    /// file. The merged segment keeps the oldest id in its run, so callers
+    /// must move `LsnLocation`s in the other segments by their offsets.
+    ///
+    /// The manifest switches to the merged files in a single save; a crash
+    /// on either side of it only leaves files for `collect_garbage`.
+    pub fn compact_cold(&mut self) -> Result<Vec<SegmentMerge>, std::io::Error> {
+        let old = &self.manifest.segments;
+        let mut segments = Vec::with_capacity(old.len());
+        let mut merges = Vec::new();
+        let mut obsolete = Vec::new();
+        let mut i = 0;
+        while i < old.len() {
+            let mut end = i;
+            while end < old.len() && old[end].sealed && old[end].tier == Tier::Cold {
+                end += 1;
+            }
+            if end - i < 2 {
+                segments.push(old[i].clone());
+                i += 1;
+                continue;
+            }
+            let (merged, merge) = self.merge_cold_run(&old[i..end])?;
+            obsolete.extend(old[i..end].iter().map(|s| self.cold_dir.join(&s.filename)));
+            segments.push(merged);
+            merges.push(merge);
+            i = end;
+        }
+        if merges.is_empty() {
+            return Ok(merges);
+        }
+
+        self.manifest.segments = segments;
+        self.save_manifest()?;
+        let merged: Vec<SegmentId> = merges.iter().flat_map(|m| m.sources.iter().map(|&(id, _)| id)).collect();
+        self.forget_files(&merged);
+        for path in obsolete {
+            fs::remove_file(path)?;
+        }
+        info!(merges = merges.len(), segments = self.manifest.segments.len() + 1, "cold segments compacted");
+        Ok(merges)
+    }
+
+    /// Delete the sealed segments in `segments`, none of them promoting,
+    /// whose records are all below `lsn` and no longer needed. The manifest
+    /// records `lsn` first, so recovery expects the LSNs gone; the files go
+    /// once it is saved, and `collect_garbage` removes any a crash leaves.
+    /// Returns each deleted segment with its size.
+    pub fn truncate_before(
+        &mut self,
+        lsn: Lsn,
+        segments: &[SegmentId],
+    ) -> Result<Vec<(SegmentId, u64)>, std::io::Error> {
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.manifest.segments)
+            .into_iter()
+            .partition(|s| segments.contains(&s.id) && s.sealed && s.lsn_range.1 < lsn);
+        self.manifest.segments = kept;
+        self.manifest.truncated_before = self.manifest.truncated_before.max(lsn);
+        self.save_manifest()?;
+        let ids: Vec<SegmentId> = removed.iter().map(|s| s.id).collect();
+        self.forget_files(&ids);
+        self.tiering.get_mut().unwrap().reads.retain(|id, _| !ids.contains(id));
+        for seg in &removed {
+            let dir = match seg.tier {
+                Tier::Hot => &self.hot_dir,
+                Tier::Cold => &self.cold_dir,
+            };
+            fs::remove_file(dir.join(&seg.filename))?;
+        }
+        if !removed.is_empty() {
+            info!(segments = removed.len(), lsn, "segments truncated");
+        }
+        Ok(removed.into_iter().map(|s| (s.id, s.size_bytes)).collect())
+    }
+
+    /// LSN the log was last truncated before; see `truncate_before`.
+    pub fn truncated_before(&self) -> Lsn {
+        self.manifest.truncated_before
+    }
+
+    /// Whether a promotion of `segment_id` to the hot tier is in flight.
+    pub fn promoting(&self, segment_id: SegmentId) -> bool {
+        self.tiering.lock().unwrap().promoting.contains(&segment_id)
+    }
+
+    /// Concatenate `run` into a new cold file, not yet in the manifest.
+    fn merge_cold_run(&self, run: &[SegmentMeta]) -> Result<(SegmentMeta, SegmentMerge), std::io::Error> {
+        let (first, last) = (&run[0], &run[run.len() - 1]);
+        let filename = format!("wal_{:06}-{:06}_{:010}.seg", first.id, last.id, first.first_lsn());
+        let path = self.cold_dir.join(&filename);
+        let tmp_path = self.cold_dir.join(format!("{filename}.tmp"));
+
+        // The sources' records, without their footers, under one new footer
+        let mut records = Vec::new();
+        let mut sources = Vec::with_capacity(run.len());
+        for seg in run {
+            sources.push((seg.id, records.len() as u64));
+            let src_path = self.cold_dir.join(&seg.filename);
+            let mut bytes = fs::read(&src_path)?;
+            self.cold_disk.read(&src_path, 0, bytes.len() as u64);
+            bytes.truncate(seg.size_bytes as usize);
+            records.extend_from_slice(&bytes);
+        }
+        let offset = records.len() as u64;
+        let lsn_range = (first.lsn_range.0, last.lsn_range.1);
+        let footer = SegmentFooter::for_records(&records, lsn_range);
+        let mut out = fs::File::create(&tmp_path)?;
+        out.write_all(&records)?;
+        out.write_all(&footer.encode())?;
+        self.cold_disk.write(&tmp_path, 0, offset + footer::FOOTER_SIZE as u64);
+        out.sync_all()?;
+        self.cold_disk.sync(&tmp_path);
+        fs::rename(&tmp_path, &path)?;
+
+        let meta = SegmentMeta {
+            id: first.id,
+            filename,
+            tier: Tier::Cold,
+            lsn_range,
+            size_bytes: offset,
+            sealed: true,
+            promoted: false,
+            footer: true,
+            reads: 0,
+        };
+        let merge = SegmentMerge {
+            into: first.id,
+            sources,
+            bytes: offset,
+        };
+        Ok((meta, merge))
+    }
+
+    /// Delete segment files the manifest no longer refers to, and an
+    /// interrupted manifest save. Returns each removed file and its size.
+    /// Copies of promotions still in flight are kept.
+    pub fn collect_garbage(&self) -> Result<Vec<(PathBuf, u64)>, std::io::Error> {
+        let active = active_filename(&self.manifest);
+        let promoting: HashSet<String> = {
+            let tiering = self.tiering.lock().unwrap();
+            self.manifest.segments.iter()
+                .filter(|s| tiering.promoting.contains(&s.id))
+                .map(|s| format!("{}.promote", s.filename))
+                .collect()
+        };
+        let mut removed = Vec::new();
+        for (dir, tier) in [(&self.hot_dir, Tier::Hot), (&self.cold_dir, Tier::Cold)] {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let referenced = (tier == Tier::Hot && (name == active || promoting.contains(&name)))
+                    || self.manifest.segments.iter().any(|s| s.tier == tier && s.filename == name);
+                if !referenced && name.starts_with("wal_") {
+                    removed.push((entry.path(), entry.metadata()?.len()));
+                }
+            }
+        }
+        let manifest_tmp = self.base_dir.join("manifest.json.tmp");
+        if manifest_tmp.exists() {
+            removed.push((manifest_tmp.clone(), fs::metadata(&manifest_tmp)?.len()));
+        }
+        removed.sort();
+        for (path, _) in &removed {
+            fs::remove_file(path)?;
         }
+        if !removed.is_empty() {
+            info!(files = removed.len(), "unreferenced WAL files removed");
+        }
+        Ok(removed)
+    }
+
+    /// Sealed segments kept hot by `cool_segments` callers.
+    pub fn hot_segments(&self) -> usize {
+        self.hot_segments
+    }
+
+    /// Set how many of the newest sealed segments stay hot.
+    pub fn set_hot_segments(&mut self, segments: usize) {
+        self.hot_segments = segments;
+    }
+
+    /// Fetch `segment_id` and its [`cold_read_ahead_set`](Self::cold_read_ahead_set)
+    /// from the cold tier in one request. Returns the segments fetched and
+    /// the modelled time, which the cold disk only accounts: waiting it out
+    /// is up to the caller, so it need not hold any lock meanwhile.
+    pub fn fetch_cold(&self, segment_id: SegmentId) -> (Vec<SegmentId>, Duration) {
+        let set = self.cold_read_ahead_set(segment_id);
+        let fetched = self.manifest.segments.iter().filter(|s| set.contains(&s.id));
+        let bytes = fetched.clone().map(|s| s.size_bytes).sum();
+        let filename = fetched.clone().find(|s| s.id == segment_id).map(|s| s.filename.clone());
+        let path = self.cold_dir.join(filename.unwrap_or_else(|| legacy_segment_filename(segment_id)));
+        let cost = self.cold_disk.read(&path, 0, bytes);
+        debug!(segment_id, bytes, latency_us = cost.as_micros() as u64, "cold tier read");
+        (set, cost)
+    }
+
+    /// Set how many older cold segments are fetched with each cold read.
+    /// Zero disables read-ahead.
+    pub fn set_cold_read_ahead(&mut self, segments: usize) {
+        self.cold_read_ahead = segments;
+    }
+
+    /// Segments fetched in one cold-tier round trip when `segment_id` is read:
+    /// the segment itself plus the next older cold segments, which a backwards
+    /// chain walk is likely to visit next.
+    pub fn cold_read_ahead_set(&self, segment_id: SegmentId) -> Vec<SegmentId> {
+        let mut set = vec![segment_id];
+        set.extend(
+            self.manifest
+                .segments
+                .iter()
+                .rev()
+                .filter(|s| s.id < segment_id && s.tier == Tier::Cold)
+                .take(self.cold_read_ahead)
+                .map(|s| s.id),
+        );
+        set
     }
 
-    /// Get the cold latency duration.
-    pub fn cold_latency(&self) -> Duration {
-        self.cold_latency
+    /// Count a cold read that was already covered by read-ahead.
+    pub fn record_read_ahead_hit(&self) {
+        self.read_ahead_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Cold segment reads served by read-ahead so far.
+    pub fn read_ahead_hits(&self) -> u64 {
+        self.read_ahead_hits.load(Ordering::Relaxed)
+    }
+
+    /// Accesses charged to the hot and cold disks so far.
+    pub fn disk_stats(&self) -> (DiskStats, DiskStats) {
+        (self.hot_disk.stats(), self.cold_disk.stats())
     }
 
     /// Recover: scan all segments to rebuild indexes.
+    ///
+    /// Like single-file recovery, the active segment is truncated just past
+    /// the VDL record, discarding incomplete MTRs, torn writes and trailing
+    /// garbage so new appends are readable on the next recovery. Sealed
+    /// segments holding records past the VDL are cut back to it as well,
+    /// and dropped if nothing in them survives.
     pub fn recover(&mut self) -> Result<RecoveryData, std::io::Error> {
-        let mut page_index: HashMap<PageId, Lsn> = HashMap::new();
-        let mut lsn_offsets: HashMap<Lsn, LsnLocation> = HashMap::new();
-        let mut all_lsns = std::collections::BTreeSet::new();
-        let mut cpls = std::collections::BTreeSet::new();
+        self.recover_observed(&NoopObserver)
+    }
+
+    /// `recover`, reporting each phase to `observer`.
+    #[instrument(skip_all, fields(base_dir = %self.base_dir.display()))]
+    pub fn recover_observed(&mut self, observer: &dyn EngineObserver) -> Result<RecoveryData, std::io::Error> {
+        let mut data = RecoveryData::default();
+        let mut entries = Vec::new();
+        // Recovery may cut, drop or rename any segment file
+        self.readers.get_mut().unwrap().idle.clear();
+        self.footer_checks.get_mut().unwrap().clear();
 
         // Collect sealed segment info first to avoid borrow conflict
-        let sealed_info: Vec<(PathBuf, SegmentId)> = self.manifest.segments.iter().map(|seg| {
+        let sealed_info: Vec<(PathBuf, SegmentId, Tier)> = self.manifest.segments.iter().map(|seg| {
             let path = match seg.tier {
                 Tier::Hot => self.hot_dir.join(&seg.filename),
                 Tier::Cold => self.cold_dir.join(&seg.filename),
             };
-            (path, seg.id)
+            (path, seg.id, seg.tier)
         }).collect();
 
         // Scan sealed segments in order
-        for (path, seg_id) in &sealed_info {
+        for (path, seg_id, tier) in &sealed_info {
             if !path.exists() {
                 continue;
             }
-            let mut reader = WalReader::open(path)?;
-            self.scan_segment(&mut reader, *seg_id, &mut all_lsns, &mut cpls, &mut lsn_offsets, &mut page_index)?;
+            let disk = match tier {
+                Tier::Hot => self.hot_disk.clone(),
+                Tier::Cold => self.cold_disk.clone(),
+            };
+            let mut reader = WalReader::open_on(path, disk)?;
+            scan_segment(&mut reader, *seg_id, &mut entries, observer)?;
         }
 
         // Scan active segment
-        let active_path = self.hot_dir.join(segment_filename(self.active_segment_id));
+        let mut active_path = self.active_segment_path();
         if active_path.exists() {
-            let mut reader = WalReader::open(&active_path)?;
-            self.scan_segment(&mut reader, self.active_segment_id, &mut all_lsns, &mut cpls, &mut lsn_offsets, &mut page_index)?;
-            self.active_bytes_written = fs::metadata(&active_path).map(|m| m.len()).unwrap_or(0);
+            let mut reader = WalReader::open_on(&active_path, self.hot_disk.clone())?;
+            scan_segment(&mut reader, self.active_segment_id, &mut entries, observer)?;
         }
 
         // Compute VCL and VDL
-        let vcl = compute_vcl(&all_lsns);
-        let vdl = cpls.iter().rev().find(|&&lsn| lsn <= vcl).copied().unwrap_or(0);
+        let all_lsns = entries.iter().map(|e| e.lsn).collect();
+        let vcl = compute_vcl(&all_lsns, self.manifest.truncated_before);
+        observer.recovery_vcl_computed(vcl);
+        let vdl = entries
+            .iter()
+            .filter(|e| e.is_mtr_end && e.lsn <= vcl)
+            .map(|e| e.lsn)
+            .max()
+            .unwrap_or(0);
+        observer.recovery_vdl_computed(vdl);
+
+        // Truncate the active segment at VDL
+        let keep_bytes = entries
+            .iter()
+            .filter(|e| e.location.segment_id == self.active_segment_id && e.lsn <= vdl)
+            .map(|e| e.end_offset)
+            .max()
+            .unwrap_or(0);
+        if active_path.exists() {
+            let file = fs::OpenOptions::new().write(true).open(&active_path)?;
+            data.truncated_bytes = file.metadata()?.len().saturating_sub(keep_bytes);
+            file.set_len(keep_bytes)?;
+            observer.recovery_truncated(keep_bytes, data.truncated_bytes);
+            self.active_writer = WalWriter::open_on(&active_path, self.hot_disk.clone())?
+                .with_checksum(self.active_writer.checksum());
+        }
+        self.active_bytes_written = keep_bytes;
+
+        // An active segment left empty is renamed for the LSN appends resume
+        // at, which recovery may have moved back. The old file goes only once
+        // the manifest names the new one.
+        let next_lsn = vdl + 1;
+        if keep_bytes == 0 && self.manifest.active_first_lsn != Some(next_lsn) {
+            let new_path = self.hot_dir.join(segment_filename(self.active_segment_id, next_lsn));
+            self.active_writer = WalWriter::open_on(&new_path, self.hot_disk.clone())?
+                .with_checksum(self.active_writer.checksum());
+            self.manifest.active_first_lsn = Some(next_lsn);
+            self.save_manifest()?;
+            if active_path.exists() {
+                fs::remove_file(&active_path)?;
+            }
+            active_path = new_path;
+            debug!(path = %active_path.display(), "empty active segment renamed");
+        }
 
-        Ok(RecoveryData {
-            durability: DurabilityState { vcl, vdl },
-            page_index,
-            lsn_offsets,
-        })
-    }
+        // A batch that rotated before its CPL was written leaves records
+        // past the VDL in sealed segments too. Cut those the same way, or
+        // appends reusing their LSNs would collide with them next recovery
+        let segments = std::mem::take(&mut self.manifest.segments);
+        let mut cut_sealed = false;
+        for (mut seg, (path, ..)) in segments.into_iter().zip(&sealed_info) {
+            if seg.lsn_range.1 <= vdl {
+                self.manifest.segments.push(seg);
+                continue;
+            }
+            cut_sealed = true;
+            let kept = entries.iter().filter(|e| e.location.segment_id == seg.id && e.lsn <= vdl);
+            let (last_lsn, keep_bytes) = kept.fold((0, 0), |(lsn, end), e| (lsn.max(e.lsn), end.max(e.end_offset)));
+            if path.exists() {
+                // Records cut; the footer is not counted
+                let file = fs::OpenOptions::new().write(true).open(path)?;
+                data.truncated_bytes += seg.size_bytes.saturating_sub(keep_bytes);
+                file.set_len(keep_bytes)?;
+            }
+            if keep_bytes == 0 {
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+                continue;
+            }
+            seg.lsn_range.1 = last_lsn;
+            seg.size_bytes = keep_bytes;
+            // The cut took the footer with it; seal what is left afresh
+            let footer = SegmentFooter::for_records(&fs::read(path)?, seg.lsn_range);
+            footer::append_footer(path, &footer, &HostDisk)?;
+            seg.footer = true;
+            self.manifest.segments.push(seg);
+        }
+        if cut_sealed {
+            self.save_manifest()?;
+            warn!(vdl, "records past the VDL cut from sealed segments");
+        }
 
-    fn scan_segment(
-        &mut self,
-        reader: &mut WalReader,
-        segment_id: SegmentId,
-        all_lsns: &mut std::collections::BTreeSet<Lsn>,
-        cpls: &mut std::collections::BTreeSet<Lsn>,
-        lsn_offsets: &mut HashMap<Lsn, LsnLocation>,
-        page_index: &mut HashMap<PageId, Lsn>,
-    ) -> Result<(), std::io::Error> {
-        loop {
-            let file_offset = reader.stream_position()?;
-            match reader.read_entry()? {
-                crate::reader::ReadResult::Entry(hdr, _data) => {
-                    all_lsns.insert(hdr.lsn);
-                    if hdr.is_mtr_end() {
-                        cpls.insert(hdr.lsn);
-                    }
-                    lsn_offsets.insert(hdr.lsn, LsnLocation {
-                        segment_id,
-                        file_offset,
-                    });
-                    let entry = page_index.entry(hdr.page_id).or_insert(0);
-                    if hdr.lsn > *entry {
-                        *entry = hdr.lsn;
-                    }
-                    if segment_id == self.active_segment_id && self.active_first_lsn.is_none() {
-                        self.active_first_lsn = Some(hdr.lsn);
-                    }
+        // Rebuild indexes from surviving entries (LSN ≤ VDL), leaving out
+        // the pieces of split MTRs that were given up on
+        let abandoned =
+            crate::recovery::abandoned_pieces(entries.iter().map(|e| (e.lsn, e.mtr_id, e.is_mtr_end)));
+        let mut cpls = Vec::new();
+        for entry in entries.iter().filter(|e| e.lsn <= vdl) {
+            data.lsn_offsets.insert(entry.lsn, entry.location);
+            if entry.location.segment_id == self.active_segment_id {
+                if self.active_first_lsn.is_none() {
+                    self.active_first_lsn = Some(entry.lsn);
                 }
-                _ => break,
+                self.active_last_lsn = Some(entry.lsn);
+            }
+            if abandoned.contains(&entry.lsn) {
+                continue;
+            }
+            let latest = data.page_index.entry(entry.page_id).or_insert(0);
+            if entry.lsn > *latest {
+                *latest = entry.lsn;
+            }
+            data.page_versions.entry(entry.page_id).or_default().push(entry.lsn);
+            if entry.is_mtr_end {
+                cpls.push((entry.lsn, entry.timestamp));
             }
         }
-        Ok(())
+        for versions in data.page_versions.values_mut() {
+            versions.sort_unstable();
+        }
+        cpls.sort_unstable();
+        self.manifest.time_index = TimeIndex::from_cpls(cpls);
+        observer.recovery_index_rebuilt(data.page_index.len(), data.lsn_offsets.len());
+
+        info!(
+            segments = sealed_info.len() + 1,
+            entries = data.lsn_offsets.len(),
+            vcl,
+            vdl,
+            truncated_bytes = data.truncated_bytes,
+            "segmented WAL recovered"
+        );
+
+        data.durability = DurabilityState { vcl, vdl };
+        Ok(data)
     }
 
     fn save_manifest(&self) -> Result<(), std::io::Error> {
         let manifest_path = self.base_dir.join("manifest.json");
         let tmp_path = self.base_dir.join("manifest.json.tmp");
         let content = serde_json::to_string_pretty(&self.manifest)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            .map_err(std::io::Error::other)?;
         fs::write(&tmp_path, content)?;
         fs::rename(&tmp_path, &manifest_path)?;
         Ok(())
@@ -343,19 +1177,117 @@ impl SegmentManager {
 }
 
 /// Data returned from segment recovery.
+#[derive(Default)]
 pub struct RecoveryData {
     pub durability: DurabilityState,
     pub page_index: HashMap<PageId, Lsn>,
     pub lsn_offsets: HashMap<Lsn, LsnLocation>,
+    /// Page → LSNs of the records in its redo chain, oldest first.
+    pub page_versions: HashMap<PageId, Vec<Lsn>>,
+    /// Bytes cut from the end of the active segment.
+    pub truncated_bytes: u64,
 }
 
-fn segment_filename(id: SegmentId) -> String {
+/// A valid entry found while scanning a segment.
+struct ScannedEntry {
+    lsn: Lsn,
+    page_id: PageId,
+    location: LsnLocation,
+    /// File offset just past the entry.
+    end_offset: u64,
+    mtr_id: u64,
+    is_mtr_end: bool,
+    timestamp: Timestamp,
+}
+
+/// Read entries from the start of a segment until its end or the first
+/// torn or corrupt entry. A corrupt first entry in the log's first segment
+/// fails the scan instead, as in single-file recovery.
+fn scan_segment(
+    reader: &mut WalReader,
+    segment_id: SegmentId,
+    entries: &mut Vec<ScannedEntry>,
+    observer: &dyn EngineObserver,
+) -> Result<(), std::io::Error> {
+    let mut scan = reader.iter();
+    loop {
+        let file_offset = scan.position();
+        match scan.next() {
+            Some(Ok((hdr, _data))) => {
+                observer.recovery_entry_scanned(hdr.lsn, hdr.page_id, file_offset, hdr.is_mtr_end());
+                entries.push(ScannedEntry {
+                    lsn: hdr.lsn,
+                    page_id: hdr.page_id,
+                    location: LsnLocation { segment_id, file_offset },
+                    end_offset: scan.position(),
+                    mtr_id: hdr.mtr_id,
+                    is_mtr_end: hdr.is_mtr_end(),
+                    timestamp: hdr.timestamp,
+                });
+            }
+            Some(Err(WalError::Io(e))) => return Err(e),
+            Some(Err(WalError::Corrupted { file_offset: 0 })) if entries.is_empty() => {
+                return Err(crate::recovery::unrecognised_log());
+            }
+            Some(Err(WalError::Corrupted { .. })) | None => break,
+        }
+    }
+    Ok(())
+}
+
+/// Name of a segment whose first record is `first_lsn`. Ordering the
+/// names by it finds the segment holding any LSN without a manifest; see
+/// [`find_segment_file`].
+fn segment_filename(id: SegmentId, first_lsn: Lsn) -> String {
+    format!("wal_{:06}_{:010}.seg", id, first_lsn)
+}
+
+/// Name of a segment created before names carried the first LSN.
+fn legacy_segment_filename(id: SegmentId) -> String {
     format!("wal_{:06}.seg", id)
 }
 
-fn compute_vcl(lsns: &std::collections::BTreeSet<Lsn>) -> Lsn {
-    let mut expected = 1u64;
-    for &lsn in lsns {
+fn active_filename(manifest: &Manifest) -> String {
+    match manifest.active_first_lsn {
+        Some(lsn) => segment_filename(manifest.active_segment_id, lsn),
+        None => legacy_segment_filename(manifest.active_segment_id),
+    }
+}
+
+/// The first segment id and first LSN in a segment filename, for a plain
+/// (`wal_<id>_<lsn>.seg`) or merged (`wal_<id>-<id>_<lsn>.seg`) segment.
+/// `None` for legacy names without an LSN and for anything else.
+pub fn parse_segment_filename(name: &str) -> Option<(SegmentId, Lsn)> {
+    let stem = name.strip_prefix("wal_")?.strip_suffix(".seg")?;
+    let (ids, lsn) = stem.split_once('_')?;
+    let first_id = ids.split_once('-').map_or(ids, |(first, _)| first);
+    Some((first_id.parse().ok()?, lsn.parse().ok()?))
+}
+
+/// The segment file in `base_dir` that holds `lsn`, found from the
+/// filenames alone: the hot or cold segment with the greatest first LSN at
+/// or below it. Useful when the manifest is lost or suspect; the LSN may
+/// still lie past the end of the file found. Legacy names are skipped.
+pub fn find_segment_file(base_dir: &Path, lsn: Lsn) -> Result<Option<PathBuf>, std::io::Error> {
+    let mut files = Vec::new();
+    for dir in [base_dir.join("hot"), base_dir.join("cold")] {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if let Some((_, first_lsn)) = parse_segment_filename(&entry.file_name().to_string_lossy()) {
+                files.push((first_lsn, entry.path()));
+            }
+        }
+    }
+    files.sort();
+    let i = files.partition_point(|(first_lsn, _)| *first_lsn <= lsn);
+    Ok(i.checked_sub(1).map(|i| files.swap_remove(i).1))
+}
+
+/// The highest LSN every LSN up to is present, counting from
+/// `truncated_before`: the segments below it may have been deleted.
+fn compute_vcl(lsns: &std::collections::BTreeSet<Lsn>, truncated_before: Lsn) -> Lsn {
+    let mut expected = truncated_before.max(1);
+    for &lsn in lsns.range(expected..) {
         if lsn != expected {
             return expected - 1;
         }
@@ -367,6 +1299,7 @@ fn compute_vcl(lsns: &std::collections::BTreeSet<Lsn>) -> Lsn {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::disk::DiskProfile;
     use tempfile::TempDir;
 
     fn make_record(lsn: Lsn, page_id: PageId, prev_lsn: Lsn, is_end: bool) -> RedoRecord {
@@ -374,17 +1307,18 @@ mod tests {
             lsn,
             page_id,
             offset: 0,
-            data: vec![lsn as u8; 10],
+            data: vec![lsn as u8; 10].into(),
             prev_lsn,
             mtr_id: 1,
             is_mtr_end: is_end,
+            timestamp: 0,
         }
     }
 
     #[test]
     fn test_segment_manager_basic() {
         let dir = TempDir::new().unwrap();
-        let mut mgr = SegmentManager::open(dir.path(), 4096, Duration::ZERO).unwrap();
+        let mut mgr = SegmentManager::open(dir.path(), 4096, TierDisks::default()).unwrap();
 
         let records = vec![
             make_record(1, 1, 0, false),
@@ -401,8 +1335,8 @@ mod tests {
     #[test]
     fn test_segment_rotation() {
         let dir = TempDir::new().unwrap();
-        // Very small segment size to force rotation (each record ~51 bytes)
-        let mut mgr = SegmentManager::open(dir.path(), 100, Duration::ZERO).unwrap();
+        // Very small segment size to force rotation (each record ~59 bytes)
+        let mut mgr = SegmentManager::open(dir.path(), 100, TierDisks::default()).unwrap();
 
         let r1 = make_record(1, 1, 0, true);
         let r2 = make_record(2, 2, 0, true);
@@ -420,39 +1354,141 @@ mod tests {
         assert_eq!(loc2[0].segment_id + 1, loc3[0].segment_id);
     }
 
+    #[test]
+    fn test_rotation_reports_sealed_lsn_range() {
+        let dir = TempDir::new().unwrap();
+        // Two ~61-byte records fit per 150-byte segment
+        let mut mgr = SegmentManager::open(dir.path(), 150, TierDisks::default()).unwrap();
+
+        let records: Vec<RedoRecord> = (1..=5).map(|i| make_record(i, 1, i - 1, true)).collect();
+        let mut rotations = Vec::new();
+        mgr.append_batch_with(&records, |sealed, new_id| {
+            rotations.push((sealed.id, sealed.lsn_range, new_id));
+        })
+        .unwrap();
+
+        assert_eq!(rotations, vec![(1, (1, 2), 2), (2, (3, 4), 3)]);
+        let segments = mgr.segments();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[2].lsn_range, (5, 5));
+        assert!(!segments[2].sealed);
+    }
+
     #[test]
     fn test_segment_recovery() {
         let dir = TempDir::new().unwrap();
 
         {
-            let mut mgr = SegmentManager::open(dir.path(), 100, Duration::ZERO).unwrap();
+            let mut mgr = SegmentManager::open(dir.path(), 100, TierDisks::default()).unwrap();
             let records: Vec<RedoRecord> = (1..=5).map(|i| {
-                make_record(i, (i % 3) + 1, if i > 1 { i - 1 } else { 0 }, i == 5)
+                make_record(i, (i % 3) + 1, i.saturating_sub(1), i == 5)
             }).collect();
 
             for r in &records {
-                mgr.append_batch(&[r.clone()]).unwrap();
+                mgr.append_batch(std::slice::from_ref(r)).unwrap();
             }
             mgr.sync().unwrap();
         }
 
         // Reopen and recover
-        let mut mgr = SegmentManager::open(dir.path(), 100, Duration::ZERO).unwrap();
+        let mut mgr = SegmentManager::open(dir.path(), 100, TierDisks::default()).unwrap();
         let data = mgr.recover().unwrap();
 
         assert_eq!(data.durability.vcl, 5);
         assert_eq!(data.durability.vdl, 5);
         assert_eq!(data.lsn_offsets.len(), 5);
         assert!(!data.page_index.is_empty());
+        // Pages 2, 3, 1, 2, 3
+        assert_eq!(data.page_versions[&1], [3]);
+        assert_eq!(data.page_versions[&2], [1, 4]);
+        assert_eq!(data.page_versions[&3], [2, 5]);
+    }
+
+    #[test]
+    fn test_segment_recovery_truncates_tail() {
+        let dir = TempDir::new().unwrap();
+        let active_path = dir.path().join("hot").join(segment_filename(1, 1));
+
+        {
+            let mut mgr = SegmentManager::open(dir.path(), 4096, TierDisks::default()).unwrap();
+            // LSN 2 belongs to an MTR that never completed
+            mgr.append_batch(&[make_record(1, 1, 0, true), make_record(2, 2, 0, false)]).unwrap();
+            mgr.sync().unwrap();
+        }
+        let valid_len = fs::metadata(&active_path).unwrap().len();
+        let mut bytes = fs::read(&active_path).unwrap();
+        bytes.extend_from_slice(&[0xA5; 7]);
+        fs::write(&active_path, bytes).unwrap();
+
+        {
+            let mut mgr = SegmentManager::open(dir.path(), 4096, TierDisks::default()).unwrap();
+            let data = mgr.recover().unwrap();
+            assert_eq!(data.durability.vdl, 1);
+            assert_eq!(data.lsn_offsets.len(), 1);
+            assert!(!data.page_index.contains_key(&2));
+            let entry_len = valid_len / 2;
+            assert_eq!(data.truncated_bytes, entry_len + 7);
+
+            // Appends after recovery land right after the VDL record
+            mgr.append_batch(&[make_record(2, 2, 0, true)]).unwrap();
+            mgr.sync().unwrap();
+        }
+
+        let mut mgr = SegmentManager::open(dir.path(), 4096, TierDisks::default()).unwrap();
+        let data = mgr.recover().unwrap();
+        assert_eq!(data.durability.vdl, 2);
+        assert_eq!(data.truncated_bytes, 0);
+    }
+
+    #[test]
+    fn test_segment_recovery_cuts_sealed_segments_past_vdl() {
+        let dir = TempDir::new().unwrap();
+        {
+            // Two records per segment; LSNs 2-5 belong to an MTR that never
+            // completed, and rotate twice on the way
+            let mut mgr = SegmentManager::open(dir.path(), 130, TierDisks::default()).unwrap();
+            let records: Vec<RedoRecord> = (1..=5).map(|lsn| make_record(lsn, 1, lsn - 1, lsn == 1)).collect();
+            mgr.append_batch(&records).unwrap();
+            mgr.sync().unwrap();
+            assert_eq!(mgr.segments().len(), 3);
+        }
+
+        {
+            let mut mgr = SegmentManager::open(dir.path(), 130, TierDisks::default()).unwrap();
+            let data = mgr.recover().unwrap();
+            assert_eq!((data.durability.vcl, data.durability.vdl), (5, 1));
+            assert_eq!(data.truncated_bytes, 4 * 59);
+            // Segment 1 keeps LSN 1; segment 2 held nothing durable, and
+            // active segment 3 is empty
+            let segments = mgr.segments();
+            assert_eq!(segments.len(), 2);
+            assert_eq!((segments[0].lsn_range, segments[0].size_bytes), ((1, 1), 59));
+            assert_eq!((segments[1].id, segments[1].size_bytes), (3, 0));
+            assert!(!dir.path().join("hot").join(segment_filename(2, 3)).exists());
+            // The empty active segment is renamed for where appends resume
+            assert_eq!(segments[1].filename, segment_filename(3, 2));
+            assert!(!dir.path().join("hot").join(segment_filename(3, 5)).exists());
+
+            // LSN 2 is handed out again, and must not meet the old one
+            mgr.append_batch(&[make_record(2, 1, 1, true)]).unwrap();
+            mgr.sync().unwrap();
+        }
+
+        let mut mgr = SegmentManager::open(dir.path(), 130, TierDisks::default()).unwrap();
+        let data = mgr.recover().unwrap();
+        assert_eq!((data.durability.vcl, data.durability.vdl), (2, 2));
+        assert!(mgr.filename_mismatches().is_empty());
+        assert_eq!(data.page_versions[&1], vec![1, 2]);
+        assert_eq!(data.lsn_offsets[&2].segment_id, 3);
     }
 
     #[test]
     fn test_cool_segments() {
         let dir = TempDir::new().unwrap();
-        let mut mgr = SegmentManager::open(dir.path(), 60, Duration::ZERO).unwrap();
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
 
         for i in 1..=10u64 {
-            let r = make_record(i, 1, if i > 1 { i - 1 } else { 0 }, true);
+            let r = make_record(i, 1, i.saturating_sub(1), true);
             mgr.append_batch(&[r]).unwrap();
         }
         mgr.sync().unwrap();
@@ -469,10 +1505,422 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cold_read_ahead_set() {
+        let dir = TempDir::new().unwrap();
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+
+        for i in 1..=6u64 {
+            let r = make_record(i, 1, i.saturating_sub(1), true);
+            mgr.append_batch(&[r]).unwrap();
+        }
+        mgr.sync().unwrap();
+        let cooled = mgr.cool_segments(1).unwrap();
+        assert!(cooled.len() >= 3, "expected several cold segments, got {cooled:?}");
+
+        let newest_cold = *cooled.last().unwrap();
+        assert_eq!(
+            mgr.cold_read_ahead_set(newest_cold),
+            vec![newest_cold, cooled[cooled.len() - 2]]
+        );
+
+        mgr.set_cold_read_ahead(0);
+        assert_eq!(mgr.cold_read_ahead_set(newest_cold), vec![newest_cold]);
+    }
+
+    #[test]
+    fn test_fetch_cold_is_one_round_trip_for_the_read_ahead_set() {
+        let dir = TempDir::new().unwrap();
+        let cold = DiskProfile::host().with_seek(Duration::from_millis(2));
+        let disks = TierDisks { hot: DiskProfile::host(), cold };
+        let mut mgr = SegmentManager::open(dir.path(), 60, disks).unwrap();
+
+        for i in 1..=6u64 {
+            let r = make_record(i, 1, i.saturating_sub(1), true);
+            mgr.append_batch(&[r]).unwrap();
+        }
+        mgr.sync().unwrap();
+        let cooled = mgr.cool_segments(1).unwrap();
+        assert!(cooled.len() >= 2, "expected several cold segments, got {cooled:?}");
+        // Cooling uploads each segment
+        let (_, uploaded) = mgr.disk_stats();
+        assert_eq!((uploaded.writes, uploaded.syncs), (cooled.len() as u64, cooled.len() as u64));
+
+        let newest_cold = *cooled.last().unwrap();
+        let (fetched, latency) = mgr.fetch_cold(newest_cold);
+        assert_eq!(fetched, mgr.cold_read_ahead_set(newest_cold));
+        assert_eq!(fetched.len(), 2);
+        assert_eq!(latency, Duration::from_millis(2));
+
+        let (hot, cold) = mgr.disk_stats();
+        assert_eq!(hot, DiskStats::default());
+        assert_eq!(cold.reads, 1);
+        assert_eq!(cold.bytes_read, 2 * (LOG_ENTRY_HEADER_SIZE as u64 + 10));
+    }
+
+    #[test]
+    fn test_compact_cold_merges_runs() {
+        let dir = TempDir::new().unwrap();
+        // One 59-byte record per segment
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        let mut locs = Vec::new();
+        for i in 1..=5u64 {
+            locs.extend(mgr.append_batch(&[make_record(i, 1, i - 1, true)]).unwrap());
+        }
+        mgr.sync().unwrap();
+        assert_eq!(mgr.cool_segments(1).unwrap(), [1, 2, 3]);
+
+        let merges = mgr.compact_cold().unwrap();
+        assert_eq!(merges, [SegmentMerge { into: 1, sources: vec![(1, 0), (2, 59), (3, 118)], bytes: 177 }]);
+        let segments = mgr.segments();
+        assert_eq!(segments.iter().map(|s| s.id).collect::<Vec<_>>(), [1, 4, 5]);
+        assert_eq!(segments[0].filename, "wal_000001-000003_0000000001.seg");
+        assert_eq!(segments[0].lsn_range, (1, 3));
+        let cold_files = fs::read_dir(&mgr.cold_dir).unwrap().count();
+        assert_eq!(cold_files, 1);
+        assert!(mgr.compact_cold().unwrap().is_empty());
+
+        // LSN 3 now sits at its old offset plus its segment's shift
+        let (mut reader, tier) = mgr.open_segment_reader(1).unwrap();
+        assert_eq!(tier, Tier::Cold);
+        reader.seek_to(locs[2].file_offset + 118).unwrap();
+        match reader.read_entry().unwrap() {
+            crate::reader::ReadResult::Entry(hdr, _data) => assert_eq!(hdr.lsn, 3),
+            _ => panic!("expected entry"),
+        }
+
+        // Recovery finds every record through the merged file
+        drop(mgr);
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        let data = mgr.recover().unwrap();
+        assert_eq!(data.durability.vdl, 5);
+        assert_eq!(data.page_versions[&1], [1, 2, 3, 4, 5]);
+        assert_eq!(data.lsn_offsets[&3].segment_id, 1);
+        assert_eq!(data.lsn_offsets[&3].file_offset, 118);
+        assert_eq!(mgr.time_index().latest().map(|(lsn, _)| lsn), Some(5));
+    }
+
+    #[test]
+    fn test_truncated_segments_leave_a_gap_recovery_accepts() {
+        let dir = TempDir::new().unwrap();
+        // One 59-byte record per segment, each on its own page
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        for i in 1..=5u64 {
+            mgr.append_batch(&[make_record(i, i, 0, true)]).unwrap();
+        }
+        mgr.sync().unwrap();
+
+        // Segment 4 is past the truncation point, and the active one is never dropped
+        let removed = mgr.truncate_before(3, &[1, 3, 4, 5]).unwrap();
+        assert_eq!(removed, [(1, 59)]);
+        assert_eq!(mgr.truncated_before(), 3);
+        assert!(!dir.path().join("hot").join(segment_filename(1, 1)).exists());
+        assert_eq!(mgr.segments().iter().map(|s| s.id).collect::<Vec<_>>(), [2, 3, 4, 5]);
+
+        drop(mgr);
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        let data = mgr.recover().unwrap();
+        assert_eq!(data.durability.vdl, 5);
+        assert!(!data.lsn_offsets.contains_key(&1));
+        assert_eq!(data.page_index.len(), 4);
+    }
+
+    #[test]
+    fn test_sealed_segments_are_checked_against_their_footers() {
+        let dir = TempDir::new().unwrap();
+        // One 59-byte record per segment
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        for i in 1..=4u64 {
+            mgr.append_batch(&[make_record(i, 1, i - 1, true)]).unwrap();
+        }
+        mgr.sync().unwrap();
+        let seg1 = dir.path().join("hot").join(segment_filename(1, 1));
+        assert_eq!(fs::metadata(&seg1).unwrap().len(), 59 + footer::FOOTER_SIZE as u64);
+        assert!(mgr.segments().iter().all(|s| s.footer == s.sealed));
+
+        // The first read of a segment checks it
+        assert_eq!(mgr.footer_check(1), None);
+        let (reader, _) = mgr.open_segment_reader(1).unwrap();
+        mgr.release_segment_reader(1, reader);
+        assert_eq!(mgr.footer_check(1), Some(FooterCheck::Intact));
+
+        let mut bytes = fs::read(&seg1).unwrap();
+        bytes[LOG_ENTRY_HEADER_SIZE] ^= 0xFF;
+        fs::write(&seg1, &bytes).unwrap();
+        let damaged = FooterCheck::Damaged("records checksum mismatch".to_string());
+        assert_eq!(mgr.verify_footer(1).unwrap(), Some(damaged.clone()));
+        assert_eq!(mgr.footer_check(1), Some(damaged));
+        bytes[LOG_ENTRY_HEADER_SIZE] ^= 0xFF;
+        fs::write(&seg1, &bytes).unwrap();
+
+        // A merged segment gets one footer over all its records
+        assert_eq!(mgr.cool_segments(0).unwrap(), [1, 2, 3]);
+        assert_eq!(mgr.footer_check(1), None);
+        mgr.compact_cold().unwrap();
+        assert_eq!(mgr.verify_footer(1).unwrap(), Some(FooterCheck::Intact));
+        // The active segment has none yet
+        assert_eq!(mgr.verify_footer(4).unwrap(), None);
+
+        // Restarting with a non-empty active segment reads it back to seal it
+        drop(mgr);
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        mgr.recover().unwrap();
+        mgr.append_batch(&[make_record(5, 1, 4, true)]).unwrap();
+        assert_eq!(mgr.verify_footer(4).unwrap(), Some(FooterCheck::Intact));
+    }
+
+    #[test]
+    fn test_cooling_keeps_the_most_read_segments_hot() {
+        let dir = TempDir::new().unwrap();
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        for i in 1..=4u64 {
+            mgr.append_batch(&[make_record(i, 1, i - 1, true)]).unwrap();
+        }
+        mgr.sync().unwrap();
+        let sealed: Vec<SegmentId> = mgr.manifest.segments.iter().map(|s| s.id).collect();
+        assert!(sealed.len() >= 3, "expected several sealed segments, got {sealed:?}");
+
+        // The oldest segment is read the most, so it outlives newer ones
+        for _ in 0..4 {
+            mgr.record_read(sealed[0]);
+        }
+        let cooled = mgr.cool_segments(1).unwrap();
+        assert_eq!(cooled, sealed[1..].to_vec());
+        assert_eq!(mgr.tier(sealed[0]), Some(Tier::Hot));
+        // Each pass halves the counts
+        assert_eq!(mgr.reads(sealed[0]), 2);
+        assert_eq!(mgr.segments()[0].reads, 2);
+    }
+
+    #[test]
+    fn test_promotion_moves_a_cold_segment_back_to_hot() {
+        let dir = TempDir::new().unwrap();
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        for i in 1..=3u64 {
+            mgr.append_batch(&[make_record(i, 1, i - 1, true)]).unwrap();
+        }
+        mgr.sync().unwrap();
+        mgr.cool_segments(0).unwrap();
+
+        let job = mgr.promotion(1).unwrap();
+        // One promotion per segment at a time, and only of cold ones
+        assert!(mgr.promotion(1).is_none());
+        assert!(mgr.promotion(mgr.active_segment_id).is_none());
+        let copied = job.copy();
+        // The copy in flight is not garbage
+        assert!(mgr.collect_garbage().unwrap().is_empty());
+        assert!(mgr.finish_promotion(job, copied).unwrap());
+
+        let seg = mgr.segments().into_iter().find(|s| s.id == 1).unwrap();
+        assert_eq!((seg.tier, seg.promoted), (Tier::Hot, true));
+        assert!(mgr.hot_dir.join(&seg.filename).exists());
+        assert!(!mgr.cold_dir.join(&seg.filename).exists());
+        assert!(mgr.collect_garbage().unwrap().is_empty());
+
+        // Promotion survives a reopen; cooling again clears it
+        drop(mgr);
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        assert_eq!(mgr.recover().unwrap().durability.vdl, 3);
+        assert_eq!(mgr.tier(1), Some(Tier::Hot));
+        assert_eq!(mgr.cool_segments(0).unwrap(), vec![1]);
+        assert!(!mgr.segments()[0].promoted);
+    }
+
+    #[test]
+    fn test_promotion_of_a_compacted_segment_is_discarded() {
+        let dir = TempDir::new().unwrap();
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        for i in 1..=3u64 {
+            mgr.append_batch(&[make_record(i, 1, i - 1, true)]).unwrap();
+        }
+        mgr.sync().unwrap();
+        mgr.cool_segments(0).unwrap();
+
+        let job = mgr.promotion(1).unwrap();
+        let copied = job.copy();
+        mgr.compact_cold().unwrap();
+        assert!(!mgr.finish_promotion(job, copied).unwrap());
+        assert_eq!(mgr.tier(1), Some(Tier::Cold));
+        assert!(mgr.collect_garbage().unwrap().is_empty());
+        // The segment can be promoted again
+        assert!(mgr.promotion(1).is_some());
+    }
+
+    #[test]
+    fn test_filenames_locate_lsns_without_the_manifest() {
+        let dir = TempDir::new().unwrap();
+        // Two records per segment
+        let mut mgr = SegmentManager::open(dir.path(), 130, TierDisks::default()).unwrap();
+        let records: Vec<RedoRecord> = (1..=5).map(|lsn| make_record(lsn, 1, lsn - 1, true)).collect();
+        mgr.append_batch(&records).unwrap();
+        mgr.sync().unwrap();
+        mgr.cool_segments(0).unwrap();
+        // Sealing names the next segment for the next LSN
+        mgr.seal().unwrap();
+
+        let names: Vec<String> = mgr.segments().into_iter().map(|s| s.filename).collect();
+        assert_eq!(
+            names,
+            [segment_filename(1, 1), segment_filename(2, 3), segment_filename(3, 5), segment_filename(4, 6)]
+        );
+        assert_eq!(parse_segment_filename(&names[1]), Some((2, 3)));
+        assert_eq!(parse_segment_filename("wal_000001-000003_0000000001.seg"), Some((1, 1)));
+        assert_eq!(parse_segment_filename("wal_000001.seg"), None);
+        assert!(mgr.filename_mismatches().is_empty());
+
+        drop(mgr);
+        fs::remove_file(dir.path().join("manifest.json")).unwrap();
+        let found = |lsn| find_segment_file(dir.path(), lsn).unwrap().map(|p| p.file_name().unwrap().to_owned());
+        assert_eq!(found(0), None);
+        assert_eq!(found(2).unwrap(), names[0].as_str());
+        assert_eq!(found(3).unwrap(), names[1].as_str());
+        assert_eq!(found(5).unwrap(), names[2].as_str());
+        assert_eq!(found(9).unwrap(), names[3].as_str());
+    }
+
+    #[test]
+    fn test_manifest_without_first_lsns_still_opens() {
+        let dir = TempDir::new().unwrap();
+        {
+            let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+            for i in 1..=2u64 {
+                mgr.append_batch(&[make_record(i, 1, i - 1, true)]).unwrap();
+            }
+            mgr.flush().unwrap();
+        }
+        // Rewrite the volume as an older version laid it out
+        let manifest_path = dir.path().join("manifest.json");
+        let content = fs::read_to_string(&manifest_path).unwrap();
+        let mut manifest: serde_json::Value = serde_json::from_str(&content).unwrap();
+        manifest.as_object_mut().unwrap().remove("active_first_lsn");
+        manifest["segments"][0]["filename"] = legacy_segment_filename(1).into();
+        fs::write(&manifest_path, manifest.to_string()).unwrap();
+        let hot = dir.path().join("hot");
+        fs::rename(hot.join(segment_filename(1, 1)), hot.join(legacy_segment_filename(1))).unwrap();
+        fs::rename(hot.join(segment_filename(2, 2)), hot.join(legacy_segment_filename(2))).unwrap();
+
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        assert_eq!(mgr.recover().unwrap().durability.vdl, 2);
+        // The old active segment keeps its name when sealed; the next gets a new one
+        mgr.append_batch(&[make_record(3, 1, 2, true)]).unwrap();
+        let names: Vec<String> = mgr.segments().into_iter().map(|s| s.filename).collect();
+        assert_eq!(names, [legacy_segment_filename(1), legacy_segment_filename(2), segment_filename(3, 3)]);
+        assert!(mgr.filename_mismatches().is_empty());
+        assert!(mgr.collect_garbage().unwrap().is_empty());
+        assert_eq!(mgr.recover().unwrap().durability.vdl, 3);
+    }
+
+    #[test]
+    fn test_volume_settings_live_in_the_manifest() {
+        let dir = TempDir::new().unwrap();
+        {
+            let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+            assert_eq!(mgr.volume(), VolumeConfig::default());
+            mgr.set_volume(VolumeConfig { page_size: 4096, ..Default::default() }).unwrap();
+            mgr.append_batch(&[make_record(1, 1, 0, true)]).unwrap();
+            mgr.flush().unwrap();
+        }
+        let mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        assert_eq!(mgr.volume().page_size, 4096);
+
+        // A manifest from before there were settings reads as the defaults
+        let manifest_path = dir.path().join("manifest.json");
+        let mut manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        manifest.as_object_mut().unwrap().remove("volume");
+        fs::write(&manifest_path, manifest.to_string()).unwrap();
+        let mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        assert_eq!(mgr.volume(), VolumeConfig::default());
+    }
+
+    #[test]
+    fn test_reader_pool_reuses_readers_until_the_file_changes() {
+        let dir = TempDir::new().unwrap();
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        for i in 1..=4u64 {
+            mgr.append_batch(&[make_record(i, 1, i - 1, true)]).unwrap();
+        }
+        mgr.sync().unwrap();
+        let read_lsn = |reader: &mut WalReader| match reader.read_entry().unwrap() {
+            crate::reader::ReadResult::Entry(hdr, _) => hdr.lsn,
+            other => panic!("expected an entry, got {other:?}"),
+        };
+
+        let (mut reader, tier) = mgr.open_segment_reader(1).unwrap();
+        assert_eq!((read_lsn(&mut reader), tier), (1, Tier::Hot));
+        mgr.release_segment_reader(1, reader);
+        // The pooled reader is handed out again, wherever it was left
+        let (mut reader, _) = mgr.open_segment_reader(1).unwrap();
+        reader.seek_to(0).unwrap();
+        assert_eq!(read_lsn(&mut reader), 1);
+        mgr.release_segment_reader(1, reader);
+        assert_eq!(mgr.reader_pool_stats(), ReaderPoolStats { hits: 1, opens: 1, idle: 1 });
+
+        // Cooling moves the file, so its reader is closed
+        mgr.cool_segments(0).unwrap();
+        assert_eq!(mgr.reader_pool_stats().idle, 0);
+        let (reader, tier) = mgr.open_segment_reader(1).unwrap();
+        assert_eq!((tier, mgr.reader_pool_stats().opens), (Tier::Cold, 2));
+        mgr.release_segment_reader(1, reader);
+        let (reader, _) = mgr.open_segment_reader(2).unwrap();
+        mgr.release_segment_reader(2, reader);
+
+        // So does compaction, which rewrites it
+        mgr.compact_cold().unwrap();
+        assert_eq!(mgr.reader_pool_stats().idle, 0);
+        let (mut reader, _) = mgr.open_segment_reader(1).unwrap();
+        reader.seek_to(LOG_ENTRY_HEADER_SIZE as u64 + 10).unwrap();
+        assert_eq!(read_lsn(&mut reader), 2);
+        mgr.release_segment_reader(1, reader);
+
+        // A full pool closes the least recently returned reader
+        mgr.set_reader_pool_size(1);
+        let (reader, _) = mgr.open_segment_reader(mgr.active_segment_id).unwrap();
+        mgr.release_segment_reader(mgr.active_segment_id, reader);
+        assert_eq!(mgr.reader_pool_stats().idle, 1);
+        let hits = mgr.reader_pool_stats().hits;
+        let _ = mgr.open_segment_reader(1).unwrap();
+        assert_eq!(mgr.reader_pool_stats().hits, hits);
+    }
+
+    #[test]
+    fn test_collect_garbage_removes_unreferenced_files() {
+        let dir = TempDir::new().unwrap();
+        let mut mgr = SegmentManager::open(dir.path(), 60, TierDisks::default()).unwrap();
+        for i in 1..=3u64 {
+            mgr.append_batch(&[make_record(i, 1, i - 1, true)]).unwrap();
+        }
+        mgr.sync().unwrap();
+        mgr.cool_segments(0).unwrap();
+
+        // What a crash mid-compaction leaves behind
+        fs::write(mgr.cold_dir.join("wal_000001-000002.seg.tmp"), [0u8; 10]).unwrap();
+        fs::write(mgr.hot_dir.join(segment_filename(1, 1)), [0u8; 4]).unwrap();
+        fs::write(dir.path().join("manifest.json.tmp"), "{").unwrap();
+
+        let removed: Vec<(String, u64)> = mgr
+            .collect_garbage()
+            .unwrap()
+            .into_iter()
+            .map(|(path, bytes)| (path.file_name().unwrap().to_string_lossy().into_owned(), bytes))
+            .collect();
+        assert_eq!(
+            removed,
+            [
+                ("wal_000001-000002.seg.tmp".to_string(), 10),
+                ("wal_000001_0000000001.seg".to_string(), 4),
+                ("manifest.json.tmp".to_string(), 1),
+            ]
+        );
+        assert!(mgr.collect_garbage().unwrap().is_empty());
+        let data = mgr.recover().unwrap();
+        assert_eq!(data.durability.vdl, 3);
+    }
+
     #[test]
     fn test_segment_reader() {
         let dir = TempDir::new().unwrap();
-        let mut mgr = SegmentManager::open(dir.path(), 4096, Duration::ZERO).unwrap();
+        let mut mgr = SegmentManager::open(dir.path(), 4096, TierDisks::default()).unwrap();
 
         let records = vec![
             make_record(1, 1, 0, true),
@@ -0,0 +1,120 @@
+//! Clean-shutdown sidecar: the indexes recovery would rebuild, written by a
+//! graceful close so the next open can load them instead of scanning the
+//! WAL. Opening consumes the file, so a crash after that leaves none and the
+//! open after it runs full recovery.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mini_aurora_common::{DurabilityState, Lsn, PageId};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::time_index::TimeIndex;
+
+/// Indexes saved at close. `L` locates a record: a file offset for the
+/// single-file WAL, a segment and offset for segmented storage.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Sidecar<L> {
+    /// Length of the WAL file (the active segment when segmented) at close.
+    /// Any other length on open means the WAL changed after close.
+    pub wal_len: u64,
+    pub durability: DurabilityState,
+    pub page_index: HashMap<PageId, Lsn>,
+    pub lsn_offsets: HashMap<Lsn, L>,
+    /// Page → LSNs of its redo chain, oldest first.
+    pub page_versions: HashMap<PageId, Vec<Lsn>>,
+    /// Times of the CPLs for the single-file WAL; segmented storage keeps
+    /// them in its manifest.
+    pub time_index: Option<TimeIndex>,
+    /// Where the single-file WAL's history was last truncated; segmented
+    /// storage keeps it in its manifest.
+    #[serde(default)]
+    pub truncated_before: Lsn,
+    /// Pages accessed most before close, busiest first, for the next open
+    /// to warm its page cache with.
+    #[serde(default)]
+    pub hot_pages: Vec<PageId>,
+}
+
+/// Sidecar of a single-file WAL, next to it.
+pub fn sidecar_path(wal_path: &Path) -> PathBuf {
+    let mut name = wal_path.as_os_str().to_owned();
+    name.push(".index");
+    PathBuf::from(name)
+}
+
+/// Write `sidecar` to `path`, replacing any earlier one in a single rename.
+pub fn save<L: Serialize>(path: &Path, sidecar: &Sidecar<L>) -> Result<(), std::io::Error> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+    let content = serde_json::to_vec(sidecar).map_err(std::io::Error::other)?;
+    fs::write(&tmp_path, content)?;
+    fs::File::open(&tmp_path)?.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Read and delete the sidecar at `path`. Returns `None` if there is none,
+/// or if it doesn't parse.
+pub fn take<L: DeserializeOwned>(path: &Path) -> Result<Option<Sidecar<L>>, std::io::Error> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    fs::remove_file(path)?;
+    match serde_json::from_slice(&content) {
+        Ok(sidecar) => Ok(Some(sidecar)),
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "unreadable clean-shutdown index, running full recovery");
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_take() {
+        let dir = TempDir::new().unwrap();
+        let path = sidecar_path(&dir.path().join("test.wal"));
+        assert_eq!(path.file_name().unwrap(), "test.wal.index");
+        assert!(take::<u64>(&path).unwrap().is_none());
+
+        let sidecar = Sidecar {
+            wal_len: 84,
+            durability: DurabilityState { vcl: 2, vdl: 2 },
+            page_index: HashMap::from([(7, 2)]),
+            lsn_offsets: HashMap::from([(1, 0u64), (2, 42)]),
+            page_versions: HashMap::from([(7, vec![1, 2])]),
+            time_index: Some(TimeIndex::from_cpls([(2, 1_700_000_000_000_000)])),
+            truncated_before: 0,
+            hot_pages: vec![7],
+        };
+        save(&path, &sidecar).unwrap();
+        let loaded = take::<u64>(&path).unwrap().unwrap();
+        assert_eq!(loaded.wal_len, 84);
+        assert_eq!(loaded.durability.vdl, 2);
+        assert_eq!(loaded.lsn_offsets, sidecar.lsn_offsets);
+        assert_eq!(loaded.page_versions, sidecar.page_versions);
+        assert_eq!(loaded.hot_pages, [7]);
+        assert_eq!(loaded.time_index, sidecar.time_index);
+        // Taking it clears the clean-shutdown flag
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_unreadable_sidecar_is_ignored() {
+        let dir = TempDir::new().unwrap();
+        let path = sidecar_path(&dir.path().join("test.wal"));
+        fs::write(&path, b"{\"wal_len\":").unwrap();
+        assert!(take::<u64>(&path).unwrap().is_none());
+        assert!(!path.exists());
+    }
+}
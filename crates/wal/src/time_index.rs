@@ -0,0 +1,117 @@
+//! Sparse LSN → timestamp index over consistency points, for reads by time.
+//!
+//! Keeping every CPL's time would grow with the log, so the index samples
+//! one CPL in `TIME_SAMPLE_INTERVAL` plus the newest. A lookup either lands
+//! on an answer or narrows it to the CPLs between two samples, whose headers
+//! the caller reads.
+
+use mini_aurora_common::{Lsn, Timestamp};
+use serde::{Deserialize, Serialize};
+
+/// CPLs per sample.
+pub const TIME_SAMPLE_INTERVAL: usize = 64;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeIndex {
+    /// The first CPL and every `TIME_SAMPLE_INTERVAL`th one after it, in LSN
+    /// (and so time) order.
+    samples: Vec<(Lsn, Timestamp)>,
+    /// The newest CPL, sampled or not.
+    latest: Option<(Lsn, Timestamp)>,
+    /// CPLs recorded since the last sample.
+    since_sample: usize,
+}
+
+/// What the index alone knows about the newest CPL at or before a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeLookup {
+    /// That CPL, or `None` when the time is before the first one.
+    Found(Option<(Lsn, Timestamp)>),
+    /// It is `from` or a CPL after it and before LSN `to`.
+    Between { from: (Lsn, Timestamp), to: Lsn },
+}
+
+impl TimeIndex {
+    /// Build an index from CPLs in LSN order.
+    pub fn from_cpls(cpls: impl IntoIterator<Item = (Lsn, Timestamp)>) -> Self {
+        let mut index = Self::default();
+        for (lsn, timestamp) in cpls {
+            index.record(lsn, timestamp);
+        }
+        index
+    }
+
+    /// Note a new CPL; LSNs and timestamps must not go backwards.
+    pub fn record(&mut self, lsn: Lsn, timestamp: Timestamp) {
+        if self.samples.is_empty() || self.since_sample + 1 >= TIME_SAMPLE_INTERVAL {
+            self.samples.push((lsn, timestamp));
+            self.since_sample = 0;
+        } else {
+            self.since_sample += 1;
+        }
+        self.latest = Some((lsn, timestamp));
+    }
+
+    /// The newest CPL.
+    pub fn latest(&self) -> Option<(Lsn, Timestamp)> {
+        self.latest
+    }
+
+    pub fn samples(&self) -> &[(Lsn, Timestamp)] {
+        &self.samples
+    }
+
+    pub fn lookup(&self, timestamp: Timestamp) -> TimeLookup {
+        let Some(latest) = self.latest else {
+            return TimeLookup::Found(None);
+        };
+        if latest.1 <= timestamp {
+            return TimeLookup::Found(Some(latest));
+        }
+        match self.samples.partition_point(|&(_, t)| t <= timestamp) {
+            0 => TimeLookup::Found(None),
+            n => TimeLookup::Between {
+                from: self.samples[n - 1],
+                to: self.samples.get(n).map_or(latest.0, |&(lsn, _)| lsn),
+            },
+        }
+    }
+
+    /// Forget every CPL after `cpl`, which becomes the newest.
+    pub fn rewind(&mut self, cpl: Option<(Lsn, Timestamp)>) {
+        let cutoff = cpl.map_or(0, |(lsn, _)| lsn);
+        self.samples.retain(|&(lsn, _)| lsn <= cutoff);
+        self.latest = cpl;
+        // The CPLs since the last sample aren't known; sample again soon
+        self.since_sample = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_every_interval() {
+        let n = TIME_SAMPLE_INTERVAL as u64;
+        let index = TimeIndex::from_cpls((1..=2 * n + 5).map(|lsn| (lsn, lsn * 10)));
+        assert_eq!(index.samples(), [(1, 10), (n + 1, (n + 1) * 10), (2 * n + 1, (2 * n + 1) * 10)]);
+        assert_eq!(index.latest(), Some((2 * n + 5, (2 * n + 5) * 10)));
+
+        assert_eq!(index.lookup(5), TimeLookup::Found(None));
+        assert_eq!(index.lookup(10_000), TimeLookup::Found(index.latest()));
+        assert_eq!(index.lookup(25), TimeLookup::Between { from: (1, 10), to: n + 1 });
+        assert_eq!(index.lookup((2 * n + 2) * 10), TimeLookup::Between { from: (2 * n + 1, (2 * n + 1) * 10), to: 2 * n + 5 });
+    }
+
+    #[test]
+    fn test_rewind() {
+        let n = TIME_SAMPLE_INTERVAL as u64;
+        let mut index = TimeIndex::from_cpls((1..=2 * n).map(|lsn| (lsn, lsn * 10)));
+        index.rewind(Some((5, 50)));
+        assert_eq!(index.samples(), [(1, 10)]);
+        assert_eq!(index.lookup(100), TimeLookup::Found(Some((5, 50))));
+        index.rewind(None);
+        assert_eq!(index.lookup(100), TimeLookup::Found(None));
+    }
+}
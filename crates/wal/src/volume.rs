@@ -0,0 +1,105 @@
+//! Volume settings, fixed when a volume is created. Segmented storage keeps
+//! them in its manifest. A single WAL file has no header to hold them, so
+//! they go in a `.volume` file next to it. A volume from before there were
+//! settings has neither, and uses the defaults it was written with. Its
+//! entries may be in an older layout, which recovery refuses rather than
+//! reads as garbage.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mini_aurora_common::{PAGE_SIZE, WAL_FORMAT};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VolumeConfig {
+    /// Bytes per page, one of `PAGE_SIZES`. Every record must fit in it.
+    pub page_size: usize,
+    /// `WAL_FORMAT` of the volume's entries. Settings saved before it was
+    /// recorded were written with format 2, the first to have settings.
+    #[serde(default = "first_format_with_settings")]
+    pub format: u32,
+}
+
+impl Default for VolumeConfig {
+    fn default() -> Self {
+        Self { page_size: PAGE_SIZE, format: WAL_FORMAT }
+    }
+}
+
+fn first_format_with_settings() -> u32 {
+    2
+}
+
+impl VolumeConfig {
+    /// Fails for a volume whose entries this build can't read.
+    pub fn check_format(&self) -> Result<(), std::io::Error> {
+        if self.format == WAL_FORMAT {
+            return Ok(());
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("volume is in WAL format {}, this build reads format {WAL_FORMAT}", self.format),
+        ))
+    }
+}
+
+/// Settings file of a single-file WAL, next to it.
+pub fn volume_path(wal_path: &Path) -> PathBuf {
+    let mut name = wal_path.as_os_str().to_owned();
+    name.push(".volume");
+    PathBuf::from(name)
+}
+
+/// Write `config` to `path`, replacing any earlier one in a single rename.
+pub fn save(path: &Path, config: &VolumeConfig) -> Result<(), std::io::Error> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+    let content = serde_json::to_vec(config).map_err(std::io::Error::other)?;
+    fs::write(&tmp_path, content)?;
+    fs::File::open(&tmp_path)?.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Read the settings at `path`; `None` if there are none. Unlike a sidecar
+/// they can't be rebuilt from the WAL, so one that doesn't parse is an error.
+pub fn load(path: &Path) -> Result<Option<VolumeConfig>, std::io::Error> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = TempDir::new().unwrap();
+        let path = volume_path(&dir.path().join("test.wal"));
+        assert_eq!(path.file_name().unwrap(), "test.wal.volume");
+        assert_eq!(load(&path).unwrap(), None);
+
+        let config = VolumeConfig { page_size: 16384, ..Default::default() };
+        save(&path, &config).unwrap();
+        assert_eq!(load(&path).unwrap(), Some(config));
+
+        // Settings saved before the format was recorded
+        fs::write(&path, r#"{"page_size":4096}"#).unwrap();
+        let old = load(&path).unwrap().unwrap();
+        assert_eq!(old.format, 2);
+        assert!(old.check_format().is_ok());
+        let newer = VolumeConfig { format: WAL_FORMAT + 1, ..Default::default() };
+        assert!(newer.check_format().unwrap_err().to_string().contains("WAL format"));
+
+        fs::write(&path, "{").unwrap();
+        assert_eq!(load(&path).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -1,32 +1,146 @@
+use std::fmt;
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::io::{self, BufWriter, Write};
+use std::str::FromStr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crc32fast::Hasher;
 use mini_aurora_common::{RedoRecord, LOG_ENTRY_HEADER_SIZE};
 
+use crate::checksum::ChecksumKind;
+use crate::disk::{Disk, DiskOp, HostDisk};
+
+/// How a WAL file is made durable. Either way the data goes through to the
+/// device, not only into its volatile write cache: the standard library
+/// issues `fcntl(F_FULLFSYNC)` on macOS and iOS, where a plain `fsync`
+/// stops at the drive's cache, and `FlushFileBuffers` on Windows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncMode {
+    /// The file's data and all of its metadata (`File::sync_all`).
+    #[default]
+    Full,
+    /// The file's data and only the metadata needed to read it back, such
+    /// as its length (`File::sync_data`). Skips e.g. the modification time,
+    /// so it can be cheaper; the same call as `Full` on macOS and Windows.
+    Data,
+}
+
+impl SyncMode {
+    /// Sync `file` this way.
+    pub fn sync(self, file: &File) -> io::Result<()> {
+        match self {
+            Self::Full => file.sync_all(),
+            Self::Data => file.sync_data(),
+        }
+    }
+
+    /// The call it comes down to on this platform.
+    pub fn system_call(self) -> &'static str {
+        if cfg!(target_vendor = "apple") {
+            "fcntl(F_FULLFSYNC)"
+        } else if cfg!(windows) {
+            "FlushFileBuffers"
+        } else {
+            match self {
+                Self::Full => "fsync",
+                Self::Data => "fdatasync",
+            }
+        }
+    }
+}
+
+impl FromStr for SyncMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "full" => Ok(Self::Full),
+            "data" => Ok(Self::Data),
+            _ => Err(format!("unknown sync mode '{name}' (expected full or data)")),
+        }
+    }
+}
+
+impl fmt::Display for SyncMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Full => "full",
+            Self::Data => "data",
+        })
+    }
+}
+
 /// Append-only WAL writer. Each entry is a fixed-size header followed by
-/// variable-length data, protected by a CRC32 checksum.
+/// variable-length data, protected by a checksum (CRC32 unless set with
+/// [`with_checksum`](Self::with_checksum)). Appends and fsyncs are charged
+/// to the writer's [`Disk`], and fsyncs use its [`SyncMode`].
 pub struct WalWriter {
     writer: BufWriter<File>,
+    path: PathBuf,
+    disk: Arc<dyn Disk>,
+    /// Where the next append lands.
+    end: u64,
+    /// CRC32 of the file and the entries in it, kept while the writer has
+    /// seen every byte: it created the file or opened it empty.
+    contents: Option<(Hasher, u64)>,
+    checksum: ChecksumKind,
+    sync_mode: SyncMode,
 }
 
 impl WalWriter {
     /// Open (or create) a WAL file for appending.
     pub fn open(path: &Path) -> Result<Self, std::io::Error> {
+        Self::open_on(path, Arc::new(HostDisk))
+    }
+
+    /// Open (or create) a WAL file whose appends are charged to `disk`.
+    pub fn open_on(path: &Path, disk: Arc<dyn Disk>) -> Result<Self, std::io::Error> {
         let file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(path)?;
+        let end = file.metadata()?.len();
         Ok(Self {
             writer: BufWriter::new(file),
+            path: path.to_path_buf(),
+            disk,
+            end,
+            contents: (end == 0).then(|| (Hasher::new(), 0)),
+            checksum: ChecksumKind::default(),
+            sync_mode: SyncMode::default(),
         })
     }
 
+    /// Checksum the entries this writer appends with `checksum`.
+    pub fn with_checksum(mut self, checksum: ChecksumKind) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    pub fn set_checksum(&mut self, checksum: ChecksumKind) {
+        self.checksum = checksum;
+    }
+
+    pub fn checksum(&self) -> ChecksumKind {
+        self.checksum
+    }
+
+    /// Make appends durable with `sync_mode`.
+    pub fn with_sync_mode(mut self, sync_mode: SyncMode) -> Self {
+        self.sync_mode = sync_mode;
+        self
+    }
+
+    pub fn sync_mode(&self) -> SyncMode {
+        self.sync_mode
+    }
+
     /// Append a single redo record to the WAL and flush to disk.
     pub fn append(&mut self, record: &RedoRecord) -> Result<(), std::io::Error> {
-        let header_bytes = encode_header(record);
-        let crc = compute_crc(&header_bytes[..LOG_ENTRY_HEADER_SIZE - 4], &record.data);
+        self.check(DiskOp::Write)?;
+        let header_bytes = encode_header(record, self.checksum);
+        let crc = self.checksum.checksum().entry(&header_bytes[..LOG_ENTRY_HEADER_SIZE - 4], &record.data);
 
         // Write header (without CRC) + CRC + data
         self.writer
@@ -34,36 +148,117 @@ impl WalWriter {
         self.writer.write_all(&crc.to_le_bytes())?;
         self.writer.write_all(&record.data)?;
         self.writer.flush()?;
+        self.track(&header_bytes[..LOG_ENTRY_HEADER_SIZE - 4], crc, &record.data);
+        self.charge_write((LOG_ENTRY_HEADER_SIZE + record.data.len()) as u64);
         Ok(())
     }
 
     /// Append a batch of redo records, flushing once at the end.
     pub fn append_batch(&mut self, records: &[RedoRecord]) -> Result<(), std::io::Error> {
+        self.check(DiskOp::Write)?;
+        let mut len = 0;
         for record in records {
-            let header_bytes = encode_header(record);
-            let crc = compute_crc(&header_bytes[..LOG_ENTRY_HEADER_SIZE - 4], &record.data);
+            len += (LOG_ENTRY_HEADER_SIZE + record.data.len()) as u64;
+            let header_bytes = encode_header(record, self.checksum);
+            let crc = self.checksum.checksum().entry(&header_bytes[..LOG_ENTRY_HEADER_SIZE - 4], &record.data);
             self.writer
                 .write_all(&header_bytes[..LOG_ENTRY_HEADER_SIZE - 4])?;
             self.writer.write_all(&crc.to_le_bytes())?;
             self.writer.write_all(&record.data)?;
+            self.track(&header_bytes[..LOG_ENTRY_HEADER_SIZE - 4], crc, &record.data);
         }
         self.writer.flush()?;
+        self.charge_write(len);
         Ok(())
     }
 
-    /// Fsync the underlying file to ensure durability.
+    /// Append entries already encoded by [`encode_entries`], flushing once.
+    /// `entries` is how many `bytes` holds.
+    pub fn append_encoded(&mut self, bytes: &[u8], entries: u64) -> Result<(), std::io::Error> {
+        self.check(DiskOp::Write)?;
+        self.writer.write_all(bytes)?;
+        self.writer.flush()?;
+        if let Some((hasher, count)) = &mut self.contents {
+            hasher.update(bytes);
+            *count += entries;
+        }
+        self.charge_write(bytes.len() as u64);
+        Ok(())
+    }
+
+    /// Fsync the underlying file, as the writer's [`SyncMode`] says, to
+    /// ensure durability.
     pub fn sync(&mut self) -> Result<(), std::io::Error> {
         self.writer.flush()?;
-        self.writer.get_ref().sync_all()
+        self.check(DiskOp::Sync)?;
+        self.sync_mode.sync(self.writer.get_ref())?;
+        self.disk.sync(&self.path);
+        Ok(())
+    }
+
+    /// CRC32 of everything in the file and the number of entries, if the
+    /// writer has seen all of it; see [`SegmentFooter`](crate::footer::SegmentFooter).
+    pub fn contents_crc(&self) -> Option<(u32, u64)> {
+        self.contents.as_ref().map(|(hasher, entries)| (hasher.clone().finalize(), *entries))
+    }
+
+    /// Where the next append lands.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A second handle on the file and the disk it is charged to, for
+    /// fsyncing it while this writer appends.
+    pub fn sync_handle(&self) -> Result<(File, Arc<dyn Disk>), std::io::Error> {
+        Ok((self.writer.get_ref().try_clone()?, Arc::clone(&self.disk)))
+    }
+
+    /// Fail like the disk says `op` would, before doing it.
+    fn check(&self, op: DiskOp) -> Result<(), std::io::Error> {
+        self.disk.fault(&self.path, op).map_or(Ok(()), Err)
+    }
+
+    fn track(&mut self, header_without_crc: &[u8], crc: u32, data: &[u8]) {
+        if let Some((hasher, entries)) = &mut self.contents {
+            hasher.update(header_without_crc);
+            hasher.update(&crc.to_le_bytes());
+            hasher.update(data);
+            *entries += 1;
+        }
+    }
+
+    fn charge_write(&mut self, len: u64) {
+        if len > 0 {
+            self.disk.write(&self.path, self.end, len);
+            self.end += len;
+        }
     }
 }
 
+/// Encode `records` as they would be appended, checksums included.
+pub fn encode_entries(records: &[RedoRecord], checksum: ChecksumKind) -> Vec<u8> {
+    let len = records.iter().map(|r| LOG_ENTRY_HEADER_SIZE + r.data.len()).sum();
+    let mut buf = Vec::with_capacity(len);
+    for record in records {
+        let header_bytes = encode_header(record, checksum);
+        let crc = checksum.checksum().entry(&header_bytes[..LOG_ENTRY_HEADER_SIZE - 4], &record.data);
+        buf.extend_from_slice(&header_bytes[..LOG_ENTRY_HEADER_SIZE - 4]);
+        buf.extend_from_slice(&crc.to_le_bytes());
+        buf.extend_from_slice(&record.data);
+    }
+    buf
+}
+
 /// Encode a redo record into the on-disk header bytes (LOG_ENTRY_HEADER_SIZE).
 /// The last 4 bytes (CRC) are zeroed — caller fills them in.
-fn encode_header(record: &RedoRecord) -> [u8; LOG_ENTRY_HEADER_SIZE] {
+fn encode_header(record: &RedoRecord, checksum: ChecksumKind) -> [u8; LOG_ENTRY_HEADER_SIZE] {
     let mut buf = [0u8; LOG_ENTRY_HEADER_SIZE];
     let data_len = record.data.len() as u16;
-    let flags: u8 = if record.is_mtr_end { 1 } else { 0 };
+    let flags = checksum.tag(if record.is_mtr_end { 1 } else { 0 });
 
     buf[0..8].copy_from_slice(&record.lsn.to_le_bytes());
     buf[8..16].copy_from_slice(&record.page_id.to_le_bytes());
@@ -71,19 +266,12 @@ fn encode_header(record: &RedoRecord) -> [u8; LOG_ENTRY_HEADER_SIZE] {
     buf[18..20].copy_from_slice(&data_len.to_le_bytes());
     buf[20..28].copy_from_slice(&record.prev_lsn.to_le_bytes());
     buf[28..36].copy_from_slice(&record.mtr_id.to_le_bytes());
-    buf[36] = flags;
-    // bytes 37..41 = CRC32 (left as zero; caller writes separately)
+    buf[36..44].copy_from_slice(&record.timestamp.to_le_bytes());
+    buf[44] = flags;
+    // bytes 45..49 = CRC32 (left as zero; caller writes separately)
     buf
 }
 
-/// Compute CRC32 over the header bytes (excluding the CRC field) and the data.
-fn compute_crc(header_without_crc: &[u8], data: &[u8]) -> u32 {
-    let mut hasher = Hasher::new();
-    hasher.update(header_without_crc);
-    hasher.update(data);
-    hasher.finalize()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,10 +286,11 @@ mod tests {
             lsn: 1,
             page_id: 42,
             offset: 100,
-            data: vec![0xAB, 0xCD],
+            data: vec![0xAB, 0xCD].into(),
             prev_lsn: 0,
             mtr_id: 1,
             is_mtr_end: true,
+            timestamp: 0,
         };
 
         let mut writer = WalWriter::open(&path).unwrap();
@@ -125,10 +314,11 @@ mod tests {
                 lsn: i,
                 page_id: 1,
                 offset: (i as u16) * 10,
-                data: vec![i as u8; 4],
+                data: vec![i as u8; 4].into(),
                 prev_lsn: if i == 1 { 0 } else { i - 1 },
                 mtr_id: 1,
                 is_mtr_end: i == 5,
+                timestamp: 0,
             })
             .collect();
 
@@ -143,4 +333,71 @@ mod tests {
         let metadata = std::fs::metadata(&path).unwrap();
         assert_eq!(metadata.len(), expected_size);
     }
+
+    #[test]
+    fn test_append_encoded_matches_append_batch() {
+        let records: Vec<RedoRecord> = (1..=3)
+            .map(|i| RedoRecord {
+                lsn: i,
+                page_id: i,
+                offset: 0,
+                data: vec![i as u8; 6].into(),
+                prev_lsn: 0,
+                mtr_id: i,
+                is_mtr_end: true,
+                timestamp: 0,
+            })
+            .collect();
+        let batch = NamedTempFile::new().unwrap();
+        let mut writer = WalWriter::open(batch.path()).unwrap();
+        writer.append_batch(&records).unwrap();
+
+        let encoded = NamedTempFile::new().unwrap();
+        let mut encoded_writer = WalWriter::open(encoded.path()).unwrap();
+        encoded_writer.append_encoded(&encode_entries(&records, ChecksumKind::Crc32), 3).unwrap();
+
+        assert_eq!(std::fs::read(batch.path()).unwrap(), std::fs::read(encoded.path()).unwrap());
+        assert_eq!(writer.contents_crc(), encoded_writer.contents_crc());
+        assert_eq!(encoded_writer.end(), std::fs::metadata(encoded.path()).unwrap().len());
+    }
+
+    #[test]
+    fn test_sync_modes() {
+        let tmp = NamedTempFile::new().unwrap();
+        let record = RedoRecord {
+            lsn: 1, page_id: 1, offset: 0, data: vec![1].into(),
+            prev_lsn: 0, mtr_id: 1, is_mtr_end: true, timestamp: 0,
+        };
+        for mode in [SyncMode::Full, SyncMode::Data] {
+            let mut writer = WalWriter::open(tmp.path()).unwrap().with_sync_mode(mode);
+            assert_eq!(writer.sync_mode(), mode);
+            writer.append(&record).unwrap();
+            writer.sync().unwrap();
+            assert_eq!(mode.to_string().parse(), Ok(mode));
+        }
+        assert_eq!(SyncMode::default(), SyncMode::Full);
+        assert!("none".parse::<SyncMode>().unwrap_err().contains("unknown sync mode"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_sync_calls_on_linux() {
+        assert_eq!(SyncMode::Full.system_call(), "fsync");
+        assert_eq!(SyncMode::Data.system_call(), "fdatasync");
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn test_sync_calls_on_macos() {
+        // A plain fsync would stop at the drive's cache
+        assert_eq!(SyncMode::Full.system_call(), "fcntl(F_FULLFSYNC)");
+        assert_eq!(SyncMode::Data.system_call(), "fcntl(F_FULLFSYNC)");
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_sync_calls_on_windows() {
+        assert_eq!(SyncMode::Full.system_call(), "FlushFileBuffers");
+        assert_eq!(SyncMode::Data.system_call(), "FlushFileBuffers");
+    }
 }
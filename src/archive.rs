@@ -0,0 +1,124 @@
+//! Sessions packed into a single file for moving between machines: a
+//! zstd-compressed tar holding a small JSON header and everything in the
+//! session directory (WAL or segments, manifest and sidecar indexes).
+
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context};
+use mini_aurora_common::Timestamp;
+use serde::{Deserialize, Serialize};
+
+use crate::data_dir::{DataDir, Session};
+use crate::viz::clock::now_micros;
+
+/// Bumped whenever the archive layout changes; `import` refuses newer ones.
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+/// The header, always the archive's first entry.
+const INFO_ENTRY: &str = "mini-aurora.json";
+/// Prefix of the session's files inside the archive.
+const DATA_PREFIX: &str = "session";
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveInfo {
+    pub format_version: u32,
+    /// Name of the exported session; `import` uses it unless told otherwise.
+    pub session: String,
+    pub exported_at: Timestamp,
+    /// `mini-aurora` version that wrote the archive.
+    pub exported_by: String,
+}
+
+/// Pack `session` into `out`. Returns the archive's size in bytes.
+pub fn export(session: &Session, out: &Path) -> anyhow::Result<u64> {
+    let info = ArchiveInfo {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        session: session.name().to_string(),
+        exported_at: now_micros(),
+        exported_by: env!("CARGO_PKG_VERSION").to_string(),
+    };
+    let json = serde_json::to_vec_pretty(&info)?;
+
+    let file = File::create(out).with_context(|| format!("Cannot create {}", out.display()))?;
+    let mut tar = tar::Builder::new(zstd::Encoder::new(file, ZSTD_LEVEL)?);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(info.exported_at / 1_000_000);
+    tar.append_data(&mut header, INFO_ENTRY, json.as_slice())?;
+    tar.append_dir_all(DATA_PREFIX, session.dir())
+        .with_context(|| format!("Cannot read session {}", session.dir().display()))?;
+    tar.into_inner()?.finish()?;
+    Ok(fs::metadata(out)?.len())
+}
+
+/// Unpack an archive from `export` into a session of `data_dir`, named
+/// `name` or else after the exported session. An existing session with data
+/// is only replaced with `replace`. Returns the session and how many files
+/// were restored.
+pub fn import(
+    archive: &Path,
+    data_dir: &DataDir,
+    name: Option<&str>,
+    replace: bool,
+) -> anyhow::Result<(Session, ArchiveInfo, usize)> {
+    let file = File::open(archive).with_context(|| format!("Cannot open {}", archive.display()))?;
+    let mut tar = tar::Archive::new(zstd::Decoder::new(file)?);
+    let mut entries = tar.entries()?;
+
+    let info: ArchiveInfo = match entries.next() {
+        Some(entry) if entry.as_ref().is_ok_and(|e| e.path().is_ok_and(|p| p == Path::new(INFO_ENTRY))) => {
+            serde_json::from_reader(entry?).context("Malformed archive header")?
+        }
+        Some(Err(e)) => return Err(e).context("Not a mini-aurora archive"),
+        _ => bail!("{} is not a mini-aurora archive", archive.display()),
+    };
+    if info.format_version > ARCHIVE_FORMAT_VERSION {
+        bail!(
+            "Archive format {} is newer than this build reads ({ARCHIVE_FORMAT_VERSION}); written by mini-aurora {}",
+            info.format_version,
+            info.exported_by
+        );
+    }
+
+    let name = name.unwrap_or(&info.session);
+    let existing = data_dir.root().join(name);
+    if !replace && fs::read_dir(&existing).is_ok_and(|mut d| d.next().is_some()) {
+        bail!("Session {name} already has data; replace it with --fresh or pick another name with --session");
+    }
+    let session = data_dir.session(name, replace)?;
+
+    let mut files = 0;
+    for entry in entries {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let dest = session.dir().join(session_relative(&path)?);
+        match entry.header().entry_type() {
+            kind if kind.is_dir() => {
+                fs::create_dir_all(&dest)?;
+                continue;
+            }
+            kind if kind.is_file() => {}
+            _ => bail!("Unsupported archive entry {} (only files and directories)", path.display()),
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&dest).with_context(|| format!("Cannot write {}", dest.display()))?;
+        files += 1;
+    }
+    Ok((session, info, files))
+}
+
+/// Path of an archive entry within the session directory. Anything outside
+/// the session prefix, absolute or climbing with `..` is rejected.
+fn session_relative(path: &Path) -> anyhow::Result<PathBuf> {
+    let rel = path
+        .strip_prefix(DATA_PREFIX)
+        .with_context(|| format!("Unexpected archive entry {}", path.display()))?;
+    if !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+        bail!("Unsafe archive entry {}", path.display());
+    }
+    Ok(rel.to_path_buf())
+}
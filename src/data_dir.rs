@@ -0,0 +1,218 @@
+//! Where the binary keeps its WALs. Every mode opens a named session, a
+//! directory under the data dir holding a single-file WAL (base preset) or
+//! a segment directory (tiered preset), so a later run can resume it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context};
+use mini_aurora_pagestore::store::store_path;
+use mini_aurora_wal::sidecar::sidecar_path;
+use mini_aurora_wal::volume::volume_path;
+
+/// WAL file of the base preset, inside a session directory.
+const WAL_FILE: &str = "aurora.wal";
+/// Segment directory of the tiered preset, inside a session directory.
+const TIERED_DIR: &str = "tiered";
+
+/// Root directory that sessions live in.
+#[derive(Debug, Clone)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl DataDir {
+    /// `--data-dir` if given, otherwise the platform's per-user data
+    /// directory: `$XDG_DATA_HOME/mini-aurora`, `~/.local/share/mini-aurora`
+    /// or `%LOCALAPPDATA%\mini-aurora`, falling back to the temp dir.
+    pub fn resolve(flag: Option<&str>) -> Self {
+        let root = match flag {
+            Some(dir) => PathBuf::from(dir),
+            None => default_root(),
+        };
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Open session `name`, creating its directory. With `wipe`, whatever an
+    /// earlier run left there is deleted first.
+    pub fn session(&self, name: &str, wipe: bool) -> anyhow::Result<Session> {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            bail!("Invalid session name {name:?}");
+        }
+        let dir = self.root.join(name);
+        if wipe && dir.exists() {
+            fs::remove_dir_all(&dir).with_context(|| format!("Cannot wipe session {}", dir.display()))?;
+        }
+        fs::create_dir_all(&dir).with_context(|| format!("Cannot create session {}", dir.display()))?;
+        Ok(Session {
+            name: name.to_string(),
+            dir,
+            data_dir: self.clone(),
+        })
+    }
+
+    /// Every session in the data dir, sorted by name.
+    pub fn sessions(&self) -> anyhow::Result<Vec<SessionInfo>> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Cannot read {}", self.root.display())),
+        };
+        let mut sessions = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let dir = entry.path();
+            let mut presets = Vec::new();
+            if dir.join(WAL_FILE).exists() {
+                presets.push("base");
+            }
+            if dir.join(TIERED_DIR).exists() {
+                presets.push("tiered");
+            }
+            let (bytes, modified) = dir_usage(&dir)?;
+            sessions.push(SessionInfo {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                presets,
+                bytes,
+                modified,
+            });
+        }
+        sessions.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(sessions)
+    }
+}
+
+fn default_root() -> PathBuf {
+    let env_dir = |var: &str| std::env::var_os(var).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let base = env_dir("XDG_DATA_HOME")
+        .or_else(|| env_dir("HOME").map(|home| home.join(".local").join("share")))
+        .or_else(|| env_dir("LOCALAPPDATA"))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("mini-aurora")
+}
+
+/// Total size and newest modification time of the files under `dir`.
+fn dir_usage(dir: &Path) -> anyhow::Result<(u64, Option<SystemTime>)> {
+    let mut bytes = 0;
+    let mut newest: Option<SystemTime> = None;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        let (len, modified) = if meta.is_dir() {
+            dir_usage(&entry.path())?
+        } else {
+            (meta.len(), meta.modified().ok())
+        };
+        bytes += len;
+        newest = newest.max(modified);
+    }
+    Ok((bytes, newest))
+}
+
+/// An open session directory.
+#[derive(Debug, Clone)]
+pub struct Session {
+    name: String,
+    dir: PathBuf,
+    data_dir: DataDir,
+}
+
+impl Session {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn data_dir(&self) -> &DataDir {
+        &self.data_dir
+    }
+
+    /// WAL file used by the base preset.
+    pub fn wal_path(&self) -> PathBuf {
+        self.dir.join(WAL_FILE)
+    }
+
+    /// Segment directory used by the tiered preset.
+    pub fn tiered_dir(&self) -> PathBuf {
+        self.dir.join(TIERED_DIR)
+    }
+
+    /// Delete the session's WAL and segments, keeping its directory, so the
+    /// next engine opened on it starts empty.
+    pub fn clear(&self) -> anyhow::Result<()> {
+        let wal = self.wal_path();
+        let pages = store_path(&wal);
+        for path in [sidecar_path(&wal), volume_path(&wal), wal] {
+            if path.exists() {
+                fs::remove_file(&path).with_context(|| format!("Cannot remove {}", path.display()))?;
+            }
+        }
+        if pages.exists() {
+            fs::remove_dir_all(&pages).with_context(|| format!("Cannot remove {}", pages.display()))?;
+        }
+        let tiered = self.tiered_dir();
+        if tiered.exists() {
+            fs::remove_dir_all(&tiered).with_context(|| format!("Cannot remove {}", tiered.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Delete the session's data; used by the demos, which leave nothing behind.
+    pub fn remove(self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A session found on disk, for `sessions list`.
+#[derive(Debug)]
+pub struct SessionInfo {
+    pub name: String,
+    /// Presets with data in the session (`base`, `tiered`).
+    pub presets: Vec<&'static str>,
+    pub bytes: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Table of sessions for `sessions list`, marking the `current` one.
+pub fn format_sessions(root: &Path, sessions: &[SessionInfo], current: &str) -> String {
+    let mut out = format!("Sessions in {}:\n", root.display());
+    if sessions.is_empty() {
+        out.push_str("  (none)\n");
+        return out;
+    }
+    let width = sessions.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    for s in sessions {
+        let marker = if s.name == current { '*' } else { ' ' };
+        let presets = if s.presets.is_empty() { "empty".to_string() } else { s.presets.join("+") };
+        let age = s
+            .modified
+            .and_then(|m| SystemTime::now().duration_since(m).ok())
+            .map_or_else(|| "-".to_string(), format_age);
+        out.push_str(&format!(
+            "{marker} {:<width$}  {presets:<11} {:>9}B  modified {age}\n",
+            s.name, s.bytes
+        ));
+    }
+    out
+}
+
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
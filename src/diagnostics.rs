@@ -0,0 +1,52 @@
+//! Storage errors as the REPLs print them: the error, then what to do
+//! about it when there is something to do.
+
+use mini_aurora_common::StorageError;
+
+/// `e` followed by a suggested fix, if it has one.
+pub fn explain(e: &StorageError) -> String {
+    match hint(e) {
+        Some(hint) => format!("{e} — {hint}"),
+        None => e.to_string(),
+    }
+}
+
+/// `explain` for errors with a `StorageError` anywhere in their chain.
+pub fn explain_any(e: &anyhow::Error) -> String {
+    match e.chain().find_map(|cause| cause.downcast_ref::<StorageError>()).and_then(hint) {
+        Some(hint) => format!("{e:#} — {hint}"),
+        None => format!("{e:#}"),
+    }
+}
+
+fn hint(e: &StorageError) -> Option<String> {
+    let hint = match e {
+        StorageError::LsnBeyondDurable { durable, .. } => {
+            format!("run `refresh` to read at the VDL, or pick an LSN ≤ {durable}")
+        }
+        StorageError::PageNotFound { page_id, .. } => {
+            format!("nothing was written to it by then; `put {page_id} <offset> <text>` creates it")
+        }
+        StorageError::PageOverflow { .. } => {
+            "a write must end within its page; `stats` shows the volume's page size".to_string()
+        }
+        StorageError::Unavailable(reason) if reason.contains("crashed") => "run `recover`".to_string(),
+        StorageError::Corruption(_) | StorageError::CorruptRecord { .. } => {
+            "run `admin scrub` to list every damaged record".to_string()
+        }
+        StorageError::VolumeLocked(_) => {
+            "close the other mini-aurora using this session, pick another with --session, \
+             or look inside with `inspect`"
+                .to_string()
+        }
+        StorageError::VolumeReadOnly { reason, .. } if reason.starts_with("writes stopped") => {
+            "reads still work; free space or fix the disk, then reopen the session to write again".to_string()
+        }
+        StorageError::Truncated { truncated_before, .. } => {
+            format!("`admin truncate` dropped that history; read at L{truncated_before} or later")
+        }
+        StorageError::File { path, .. } => format!("check that {} exists and is writable", path.display()),
+        _ => return None,
+    };
+    Some(hint)
+}
@@ -0,0 +1,198 @@
+//! Line editing for the REPLs: persistent history, Ctrl-R search and tab
+//! completion of commands, node labels and page IDs.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use mini_aurora_common::PageId;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Context, Editor, Helper};
+
+/// Commands of the plain `repl`, for completion.
+pub const REPL_COMMANDS: &[&str] = &[
+    "admin", "alerts", "asof", "chain", "diff", "get", "heatmap", "hex", "invariants", "put", "quit", "sessions",
+    "source", "state", "stats", "warmup",
+];
+
+/// Commands of `viz-repl`, for completion.
+pub const VIZ_REPL_COMMANDS: &[&str] = &[
+    "admin", "alerts", "asof", "bg", "bufpool", "chain", "corrupt", "crash", "delay", "diff", "get", "health",
+    "heatmap", "hex", "invariants", "metrics", "node", "oplog", "promote", "put", "qos", "quit", "record", "recover",
+    "refresh", "replay", "sessions", "source", "state", "stats", "step", "stoprecord", "trace", "viz",
+    "warmup",
+];
+
+/// Commands of `inspect`, for completion.
+pub const INSPECT_COMMANDS: &[&str] = &["chain", "get", "heatmap", "quit", "refresh", "state", "stats"];
+
+/// Subcommands of `admin`, one per `AdminApi` operation.
+pub const ADMIN_OPS: &[&str] = &[
+    "checkpoint", "compact", "cool", "flush-cache", "gc", "prefetch", "scrub", "stats", "truncate",
+];
+
+/// Commands of `tutorial`, for completion.
+pub const TUTORIAL_COMMANDS: &[&str] = &[
+    "corrupt", "crash", "get", "help", "hint", "mput", "node", "put", "quit", "recover", "refresh",
+    "reset", "skip", "state",
+];
+
+/// History file in the home directory, shared by every prompt.
+const HISTORY_FILE: &str = ".mini-aurora_history";
+const HISTORY_SIZE: usize = 1000;
+
+/// Completes the word under the cursor from the REPL's commands and the
+/// node labels and page IDs that exist right now.
+pub struct ReplHelper {
+    commands: &'static [&'static str],
+    /// Node labels, kept up to date by the REPL as nodes are added.
+    nodes: Arc<Mutex<Vec<String>>>,
+    /// Pages written so far, asked for on each completion.
+    pages: Box<dyn Fn() -> Vec<PageId> + Send>,
+}
+
+impl ReplHelper {
+    pub fn new(commands: &'static [&'static str], pages: impl Fn() -> Vec<PageId> + Send + 'static) -> Self {
+        Self {
+            commands,
+            nodes: Arc::default(),
+            pages: Box::new(pages),
+        }
+    }
+
+    pub fn with_nodes(mut self, nodes: Arc<Mutex<Vec<String>>>) -> Self {
+        self.nodes = nodes;
+        self
+    }
+
+    /// Words that can follow `prev`, the complete words before the cursor.
+    fn candidates(&self, prev: &[&str]) -> Vec<String> {
+        if prev.first().is_some_and(|cmd| !self.commands.contains(cmd)) {
+            return Vec::new();
+        }
+        let nodes = || self.nodes.lock().unwrap().clone();
+        let with = |mut words: Vec<String>, extra: &[&str]| {
+            words.extend(extra.iter().map(|w| w.to_string()));
+            words
+        };
+        match prev {
+            [] => with(Vec::new(), self.commands),
+            ["put" | "get" | "chain" | "hex" | "diff"] => {
+                (self.pages)().iter().map(|p| p.to_string()).collect()
+            }
+            ["node"] => with(nodes(), &["add"]),
+            ["bufpool"] => with(nodes(), &["clear"]),
+            ["bufpool", "clear"] | ["bg", "stop"] => nodes(),
+            ["bg"] => with(nodes(), &["stop", "list"]),
+            ["bg", _] => with(Vec::new(), &["write", "read", "mixed"]),
+            ["viz"] => with(Vec::new(), &["on", "off", "filter", "lanes", "metrics"]),
+            ["viz", "filter"] => with(Vec::new(), &["off"]),
+            ["viz", "lanes" | "metrics"] => with(Vec::new(), &["on", "off"]),
+            ["step"] => with(Vec::new(), &["on", "off"]),
+            ["trace"] => with(Vec::new(), &["tail"]),
+            ["crash"] => with(Vec::new(), &["storage"]),
+            ["corrupt"] => with(Vec::new(), &["wal"]),
+            ["sessions"] => with(Vec::new(), &["list"]),
+            ["admin"] => with(Vec::new(), ADMIN_OPS),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.rfind(' ').map_or(0, |i| i + 1);
+        let prev: Vec<&str> = before[..start].split_whitespace().collect();
+        let word = &before[start..];
+
+        let mut matches: Vec<String> = self
+            .candidates(&prev)
+            .into_iter()
+            .filter(|c| c.starts_with(word))
+            .collect();
+        matches.sort();
+        matches.dedup();
+        let pairs = matches
+            .into_iter()
+            .map(|m| Pair { replacement: format!("{m} "), display: m })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// What the user entered at a prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Line(String),
+    /// Ctrl-C, which the prompt's raw mode turns into a key, not a signal.
+    Interrupted,
+}
+
+/// A rustyline editor whose history persists across sessions.
+pub struct LineEditor {
+    editor: Editor<ReplHelper, FileHistory>,
+    history_path: Option<PathBuf>,
+}
+
+impl LineEditor {
+    pub fn new(helper: ReplHelper) -> rustyline::Result<Self> {
+        let config = Config::builder()
+            .max_history_size(HISTORY_SIZE)?
+            .history_ignore_dups(true)?
+            .history_ignore_space(true)
+            .completion_type(CompletionType::List)
+            .build();
+        let mut editor = Editor::with_config(config)?;
+        editor.set_helper(Some(helper));
+
+        let history_path = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        if let Some(path) = &history_path {
+            // A missing file just means no history yet
+            let _ = editor.load_history(path);
+        }
+        Ok(Self { editor, history_path })
+    }
+
+    /// Read one line. Ctrl-C discards the line being typed and returns an
+    /// empty one; `None` means input has ended (Ctrl-D or EOF).
+    pub fn read_line(&mut self, prompt: &str) -> Option<String> {
+        match self.read_input(prompt)? {
+            Input::Line(line) => Some(line),
+            Input::Interrupted => Some(String::new()),
+        }
+    }
+
+    /// Like `read_line`, but tells Ctrl-C apart from an empty line.
+    pub fn read_input(&mut self, prompt: &str) -> Option<Input> {
+        match self.editor.readline(prompt) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    let _ = self.editor.add_history_entry(line.as_str());
+                    if let Some(path) = &self.history_path {
+                        let _ = self.editor.append_history(path);
+                    }
+                }
+                Some(Input::Line(line))
+            }
+            Err(ReadlineError::Interrupted) => Some(Input::Interrupted),
+            Err(_) => None,
+        }
+    }
+}
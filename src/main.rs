@@ -1,25 +1,60 @@
-use std::collections::HashMap;
-use std::fmt;
-use std::io::{self, Write};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-
-use mini_aurora_common::{PageId, StorageApi, PAGE_SIZE};
-use mini_aurora_compute::engine::ComputeEngine;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use mini_aurora_common::{
+    check_page_size, AdminApi, Lsn, NoopObserver, PageId, PageRef, QosClass, QosTarget, RateLimit, StorageApi,
+    StorageError, Timestamp,
+};
+use mini_aurora_compute::batch::WriteBatching;
+use mini_aurora_compute::engine::{ComputeEngine, WritePolicy};
+use mini_aurora_compute::health::{HeartbeatConfig, StorageHealth};
+use mini_aurora_pagestore::page_cache::{CacheCapacity, PageCacheConfig};
+use mini_aurora_storage::alerts::{Alert, RecentAlerts};
+use mini_aurora_storage::config::{DiskProfile, TierDisks};
 use mini_aurora_storage::engine::StorageEngine;
-use tokio_util::sync::CancellationToken;
-
+use mini_aurora_wal::checksum::ChecksumKind;
+use mini_aurora_wal::fuzz::{check_case, FuzzCase};
+use mini_aurora_wal::segment::Tier;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+mod data_dir;
+mod diagnostics;
 mod viz;
 
 use viz::compute::VizComputeEngine;
 use viz::engine::VizStorageEngine;
-use viz::events::VizConfig;
-use viz::renderer::VizRenderer;
-use viz::tracer::JsonTracer;
-
+use viz::events::{EventFilter, VizConfig};
+use viz::chain::format_chain;
+use viz::clock::{format_utc, now_micros, parse_time};
+use viz::heatmap::format_heatmap;
+use viz::metrics::format_elapsed;
+use viz::oplog::{oplog_header, OpLog};
+use viz::hexdump::{format_diff, hexdump, used_range};
+use viz::renderer::{InputTurn, StepGate, VizRenderer};
+use viz::tracer::{TraceOptions, TraceRing, Tracer};
+
+mod archive;
+mod line_editor;
 mod scenario;
+mod trace_stats;
+mod tui;
+mod tutor;
+mod tutorial;
+#[cfg(feature = "web")]
+mod web;
+mod worker;
+
+use data_dir::{format_sessions, Session};
+use diagnostics::{explain, explain_any};
+use line_editor::{Input, LineEditor, ReplHelper, ADMIN_OPS, INSPECT_COMMANDS, REPL_COMMANDS, VIZ_REPL_COMMANDS};
+use scenario::{ScenarioRecorder, ScenarioStep};
+use tutor::{NodeSnapshot, PageSnapshot, RuleTutor, Snapshot, Suggestion, Tutor};
+use worker::{WorkerHandle, WorkerHost, WorkerKind, WorkerOptions};
 
 // ---------------------------------------------------------------------------
 // Viz REPL types
@@ -28,61 +63,86 @@ mod scenario;
 struct ReplState {
     nodes: HashMap<String, Arc<VizComputeEngine>>,
     current_node: String,
-    suggestions: Vec<String>,
+    suggestions: Vec<Suggestion>,
+    tutor: Box<dyn Tutor>,
     workers: HashMap<String, WorkerHandle>,
     storage: Arc<VizStorageEngine>,
     renderer: Arc<Mutex<VizRenderer>>,
-    bg_page_counter: Arc<AtomicU64>,
-    bg_output_tx: tokio::sync::mpsc::UnboundedSender<String>,
+    worker_host: WorkerHost,
+    recording: Option<ScenarioRecorder>,
+    trace_ring: TraceRing,
+    /// Buffer pool policy for nodes added with `node add`.
+    write_policy: WritePolicy,
+    /// Heartbeat settings for every node; `None` with `--heartbeat-ms 0`.
+    heartbeat: Option<HeartbeatConfig>,
+    /// Put batching for every node; `None` without `--batch-window-ms`.
+    batching: Option<WriteBatching>,
+    session: Session,
 }
 
-/// RAII guard: suppresses viz rendering while held, restores on drop.
-/// With viz disabled, VizStorageEngine render calls become no-ops (no
-/// thread::sleep under the storage mutex), so operations complete in
-/// microseconds instead of seconds.
-struct VizGuard {
-    renderer: Arc<Mutex<VizRenderer>>,
-    was_enabled: bool,
+/// Flags that shape the storage and compute engines a mode opens, parsed
+/// once from the command line.
+struct EngineOptions {
+    /// `base`, `tiered` or `memory`.
+    preset: String,
+    segment_size: u64,
+    disks: TierDisks,
+    write_policy: WritePolicy,
+    read_ahead: usize,
+    /// 0 turns heartbeats off.
+    heartbeat_ms: u64,
+    batching: Option<WriteBatching>,
+    memory_limit: Option<u64>,
+    checksum: ChecksumKind,
+    page_cache: PageCacheConfig,
+    page_size: Option<usize>,
 }
 
-impl VizGuard {
-    fn suppress(renderer: &Arc<Mutex<VizRenderer>>) -> Self {
-        let was_enabled = {
-            let mut r = renderer.lock().unwrap();
-            let e = r.config_mut().enabled;
-            r.config_mut().enabled = false;
-            e
-        };
-        Self { renderer: renderer.clone(), was_enabled }
-    }
+/// Flags for the REPLs themselves, parsed once from the command line.
+struct ReplOptions {
+    delay_ms: u64,
+    color: bool,
+    trace: TraceOptions,
+    /// Script to run before the first prompt.
+    init: Option<String>,
+    warmup: bool,
 }
 
-impl Drop for VizGuard {
-    fn drop(&mut self) {
-        self.renderer.lock().unwrap().config_mut().enabled = self.was_enabled;
+/// Events kept in memory for `trace tail`.
+const TRACE_RING_CAPACITY: usize = 1000;
+/// Pages per line of `heatmap` output before the grid wraps.
+const HEATMAP_PAGES_PER_LINE: usize = 10;
+/// Hottest pages `admin prefetch` reads when not given a count.
+const PREFETCH_PAGES: usize = 10;
+/// Pages `warmup` and `--warmup` preload when not given a count.
+const WARMUP_PAGES: usize = 32;
+/// Printed for a `viz` command missing or misspelling its subcommand.
+const VIZ_USAGE: &str = "Usage: viz on|off, viz filter <terms>|off, viz lanes|metrics on|off";
+/// Lease each viz-repl node holds on its read point; renewed every third of it.
+const READER_LEASE: Duration = Duration::from_secs(30);
+
+impl ReplState {
+    /// Add a step to the session recording, if one is running.
+    fn record(&mut self, step: ScenarioStep) {
+        if let Some(recorder) = self.recording.as_mut() {
+            recorder.push(step);
+        }
     }
-}
-
-struct WorkerHandle {
-    cancel: CancellationToken,
-    kind: WorkerKind,
-    interval_ms: u64,
-    task: tokio::task::JoinHandle<()>,
-}
 
-#[derive(Clone, Copy)]
-enum WorkerKind {
-    Write,
-    Read,
-    Mixed,
-}
+    async fn stop_workers(&mut self) {
+        for (label, handle) in self.workers.drain() {
+            handle.stop().await;
+            println!("Stopped bg worker on Node {label}");
+        }
+    }
 
-impl fmt::Display for WorkerKind {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            WorkerKind::Write => write!(f, "write"),
-            WorkerKind::Read => write!(f, "read"),
-            WorkerKind::Mixed => write!(f, "mixed"),
+    fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recording.take() {
+            let path = recorder.path().display().to_string();
+            match recorder.finish(&node_labels(self)) {
+                Ok(count) => println!("Saved {count} steps to {path}"),
+                Err(e) => println!("Error: {e}"),
+            }
         }
     }
 }
@@ -99,38 +159,211 @@ enum CommandOutcome {
     None,
 }
 
+/// Structured logs go to stderr, filtered by `RUST_LOG` (default
+/// `default_level`). Span close events carry each operation's duration.
+fn init_logging(default_level: &str) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
+async fn main() -> std::process::ExitCode {
+    match run().await {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", explain_any(&e));
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
 
+async fn run() -> anyhow::Result<()> {
     let args: Vec<String> = std::env::args().collect();
     let cmd = args.get(1).map(|s| s.as_str()).unwrap_or("demo");
+    // Fuzzing damages WALs on purpose; recovery's warnings about it are noise
+    init_logging(if cmd == "fuzz" { "error" } else { "warn" });
 
     // Parse optional flags
-    let delay_ms = parse_flag_value(&args, "--delay").unwrap_or(300);
-    let no_color = args.iter().any(|a| a == "--no-color");
-    let trace_json_path = parse_flag_string(&args, "--trace-json");
-    let preset = parse_flag_string(&args, "--preset").unwrap_or_else(|| "base".to_string());
-    let segment_size = parse_flag_value(&args, "--segment-size").unwrap_or(4096);
-    let cold_latency_ms = parse_flag_value(&args, "--cold-latency-ms").unwrap_or(50);
+    let repl = ReplOptions {
+        delay_ms: parse_flag_value(&args, "--delay").unwrap_or(300),
+        color: !args.iter().any(|a| a == "--no-color"),
+        trace: TraceOptions {
+            json: parse_flag_string(&args, "--trace-json").map(PathBuf::from),
+            binary: parse_flag_string(&args, "--trace-bin").map(PathBuf::from),
+            rotate_bytes: parse_flag_value(&args, "--trace-rotate-mb").map(|mb| mb * 1024 * 1024),
+        },
+        init: parse_flag_string(&args, "--init"),
+        warmup: args.iter().any(|a| a == "--warmup"),
+    };
+    let checksum = match parse_flag_string(&args, "--checksum") {
+        Some(name) => name.parse().map_err(|e| anyhow::anyhow!("--checksum: {e}"))?,
+        None => ChecksumKind::default(),
+    };
+    let mut page_cache = PageCacheConfig::default();
+    if let Some(name) = parse_flag_string(&args, "--cache-policy") {
+        page_cache.policy = name.parse().map_err(|e| anyhow::anyhow!("--cache-policy: {e}"))?;
+    }
+    if let Some(mb) = parse_flag_value(&args, "--cache-mb") {
+        page_cache.capacity = CacheCapacity::Bytes(mb * 1024 * 1024);
+    }
+    page_cache.ttl = parse_flag_value(&args, "--cache-ttl-ms").map(Duration::from_millis);
+    let page_size = parse_flag_value(&args, "--page-size").map(|size| size as usize);
+    if let Some(size) = page_size {
+        check_page_size(size).context("--page-size")?;
+    }
+    let engine = EngineOptions {
+        preset: parse_flag_string(&args, "--preset").unwrap_or_else(|| "base".to_string()),
+        segment_size: parse_flag_value(&args, "--segment-size").unwrap_or(4096),
+        disks: parse_tier_disks(&args)?,
+        write_policy: if args.iter().any(|a| a == "--write-through") {
+            WritePolicy::WriteThrough
+        } else {
+            WritePolicy::Invalidate
+        },
+        read_ahead: parse_flag_value(&args, "--read-ahead").unwrap_or(0) as usize,
+        heartbeat_ms: parse_flag_value(&args, "--heartbeat-ms").unwrap_or(1000),
+        batching: parse_flag_value(&args, "--batch-window-ms")
+            .filter(|&ms| ms > 0)
+            .map(|ms| WriteBatching { window: Duration::from_millis(ms), ..WriteBatching::default() }),
+        memory_limit: parse_flag_value(&args, "--memory-mb").map(|mb| mb * 1024 * 1024),
+        checksum,
+        page_cache,
+        page_size,
+    };
+    let data_dir = data_dir::DataDir::resolve(parse_flag_string(&args, "--data-dir").as_deref());
+    let session_name = parse_flag_string(&args, "--session");
+    // Each mode either resumes its session or starts clean; --keep and
+    // --fresh override that
+    let open_session = |default_name: &str, wipe_by_default: bool| {
+        let wipe = if args.iter().any(|a| a == "--fresh") {
+            true
+        } else if args.iter().any(|a| a == "--keep") {
+            false
+        } else {
+            wipe_by_default
+        };
+        data_dir.session(session_name.as_deref().unwrap_or(default_name), wipe)
+    };
 
     match cmd {
-        "demo" => run_demo().await?,
-        "repl" => run_repl().await?,
-        "viz-demo" => run_viz_demo(delay_ms, !no_color).await?,
-        "viz-repl" => run_viz_repl(delay_ms, !no_color, trace_json_path, &preset, segment_size, cold_latency_ms).await?,
+        "demo" => run_demo(open_session("demo", true)?).await?,
+        "repl" => run_repl(open_session("repl", false)?, &engine, &repl).await?,
+        "viz-demo" => run_viz_demo(open_session("viz-demo", true)?, repl.delay_ms, repl.color).await?,
+        "viz-repl" => run_viz_repl(open_session("viz-repl", false)?, &engine, &repl).await?,
         "scenario" => {
             let scenario_path = args.get(2).cloned().unwrap_or_else(|| {
-                eprintln!("Usage: mini-aurora scenario <file.toml> [--preset base|tiered] [--trace-json path]");
+                eprintln!("Usage: mini-aurora scenario <file.toml> [--preset base|tiered|memory] [--trace-json path] [--report path.json|path.xml]");
+                std::process::exit(1);
+            });
+            let report_path = parse_flag_string(&args, "--report");
+            scenario::run_scenario_cli(
+                &open_session("scenario", true)?, &scenario_path, report_path.as_deref(), &engine, &repl.trace,
+                repl.color,
+            ).await?;
+        }
+        "compare" => {
+            let usage = "Usage: mini-aurora compare --preset <a> --preset <b> --scenario <file.toml>";
+            let Some(scenario_path) = parse_flag_string(&args, "--scenario") else {
+                eprintln!("{usage}");
+                std::process::exit(1);
+            };
+            let presets = match parse_flag_strings(&args, "--preset").as_slice() {
+                [] => ["base".to_string(), "tiered".to_string()],
+                [a, b] if a != b => [a.clone(), b.clone()],
+                _ => anyhow::bail!("compare needs two different presets\n{usage}"),
+            };
+            // Each preset gets its own fresh session, so both start empty
+            let name = session_name.as_deref().unwrap_or("compare");
+            let sessions = [
+                data_dir.session(&format!("{name}-{}", presets[0]), true)?,
+                data_dir.session(&format!("{name}-{}", presets[1]), true)?,
+            ];
+            scenario::run_compare_cli(
+                [&sessions[0], &sessions[1]], &scenario_path, [&presets[0], &presets[1]], engine.segment_size,
+                engine.disks,
+            ).await?;
+        }
+        "tutorial" => {
+            let lesson = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(1);
+            let session = open_session("tutorial", true)?;
+            tutorial::run_tutorial(&session, lesson, engine.disks).await?;
+            session.remove();
+        }
+        "tui" => {
+            let session = open_session("tui", true)?;
+            let EngineOptions { segment_size, disks, write_policy, .. } = engine;
+            tui::run_tui(&session, &repl.trace, &engine.preset, segment_size, disks, write_policy).await?
+        }
+        #[cfg(feature = "web")]
+        "web" => {
+            let port = parse_flag_value(&args, "--port").unwrap_or(8080) as u16;
+            let session = open_session("web", true)?;
+            let EngineOptions { segment_size, disks, write_policy, .. } = engine;
+            web::run_web(&session, &repl.trace, &engine.preset, segment_size, disks, write_policy, port).await?
+        }
+        "inspect" => run_inspect(open_session("repl", false)?).await?,
+        "export" => {
+            let (Some(name), Some(out)) = (args.get(2), args.get(3)) else {
+                eprintln!("Usage: mini-aurora export <session> <file.tar.zst> [--data-dir <dir>]");
+                std::process::exit(1);
+            };
+            if !data_dir.root().join(name).is_dir() {
+                anyhow::bail!("No session {name} in {}", data_dir.root().display());
+            }
+            let session = data_dir.session(name, false)?;
+            let bytes = archive::export(&session, std::path::Path::new(out))?;
+            println!("Exported session {name} to {out} ({bytes}B)");
+        }
+        "import" => {
+            let Some(file) = args.get(2) else {
+                eprintln!("Usage: mini-aurora import <file.tar.zst> [--session <name>] [--fresh] [--data-dir <dir>]");
+                std::process::exit(1);
+            };
+            let replace = args.iter().any(|a| a == "--fresh");
+            let (session, info, files) =
+                archive::import(std::path::Path::new(file), &data_dir, session_name.as_deref(), replace)?;
+            println!(
+                "Imported session {} ({files} files, exported by mini-aurora {}) into {}",
+                session.name(),
+                info.exported_by,
+                session.dir().display()
+            );
+        }
+        "fuzz" => {
+            let cases = parse_flag_value(&args, "--cases").unwrap_or(1000);
+            let seed = parse_flag_value(&args, "--seed").unwrap_or_else(now_micros);
+            run_fuzz(open_session("fuzz", true)?, cases, seed)?;
+        }
+        "trace-stats" => {
+            let trace_path = args.get(2).cloned().unwrap_or_else(|| {
+                eprintln!("Usage: mini-aurora trace-stats <trace.jsonl>");
                 std::process::exit(1);
             });
-            scenario::run_scenario_cli(&scenario_path, &preset, trace_json_path.as_deref(), segment_size, cold_latency_ms).await?;
+            let stats = trace_stats::TraceStats::from_file(std::path::Path::new(&trace_path))?;
+            print!("{stats}");
         }
         _ => {
-            eprintln!("Usage: mini-aurora [demo|repl|viz-demo|viz-repl|scenario] [--delay <ms>] [--no-color]");
-            eprintln!("       [--preset base|tiered] [--trace-json path]");
-            eprintln!("       [--segment-size <bytes>] [--cold-latency-ms <ms>]");
+            eprintln!("Usage: mini-aurora [demo|repl|viz-demo|viz-repl|tui|tutorial|scenario|compare|trace-stats] [--delay <ms>] [--no-color]");
+            eprintln!("       [--preset base|tiered|memory] [--trace-json path] [--report path.json|path.xml]");
+            eprintln!("       [--segment-size <bytes>] [--cold-latency-ms <ms>] [--write-through]");
+            eprintln!("       [--hot-disk <profile>] [--cold-disk <profile>]   (host, ssd, hdd, object-store)");
+            eprintln!("       [--read-ahead <pages>] [--heartbeat-ms <ms>] [--memory-mb <mb>] [--init <file>]");
+            eprintln!("       [--batch-window-ms <ms>] [--checksum crc32|crc32c|xxhash64]");
+            eprintln!("       [--page-size 4096|8192|16384|32768] [--warmup]");
+            eprintln!("       [--cache-policy tinylfu|lru] [--cache-mb <mb>] [--cache-ttl-ms <ms>]   (repl)");
+            eprintln!("       [--data-dir <dir>] [--session <name>] [--keep|--fresh]");
             eprintln!("       mini-aurora scenario <file.toml> [flags...]");
+            eprintln!("       mini-aurora compare --preset <a> --preset <b> --scenario <file.toml>");
+            eprintln!("       mini-aurora tutorial [lesson]");
+            eprintln!("       mini-aurora inspect [--session <name>]   (read-only, alongside a writer)");
+            eprintln!("       mini-aurora export <session> <file.tar.zst>");
+            eprintln!("       mini-aurora import <file.tar.zst> [--session <name>] [--fresh]");
+            eprintln!("       mini-aurora web [--port <port>]   (build with --features web)");
+            eprintln!("       mini-aurora fuzz [--cases <n>] [--seed <seed>]   (WAL recovery property checks)");
             std::process::exit(1);
         }
     }
@@ -149,17 +382,63 @@ fn parse_flag_string(args: &[String], flag: &str) -> Option<String> {
     args.iter()
         .position(|a| a == flag)
         .and_then(|i| args.get(i + 1))
-        .map(|v| v.clone())
+        .cloned()
 }
 
-async fn run_demo() -> anyhow::Result<()> {
-    println!("=== Mini-Aurora Demo ===\n");
+/// Every value of a flag that may be given more than once.
+fn parse_flag_strings(args: &[String], flag: &str) -> Vec<String> {
+    args.windows(2).filter(|pair| pair[0] == flag).map(|pair| pair[1].clone()).collect()
+}
 
-    let wal_path = PathBuf::from("/tmp/mini-aurora-demo.wal");
-    // Start fresh for demo
-    let _ = std::fs::remove_file(&wal_path);
+/// Tier disk profiles from `--hot-disk` and `--cold-disk` (host and
+/// object-store by default), with `--cold-latency-ms` replacing the cold
+/// profile's time to first byte.
+fn parse_tier_disks(args: &[String]) -> anyhow::Result<TierDisks> {
+    let profile = |flag: &str, default: DiskProfile| match parse_flag_string(args, flag) {
+        Some(name) => name.parse().map_err(|e| anyhow::anyhow!("{flag}: {e}")),
+        None => Ok(default),
+    };
+    let hot = profile("--hot-disk", DiskProfile::host())?;
+    let mut cold = profile("--cold-disk", DiskProfile::object_store())?;
+    if let Some(ms) = parse_flag_value(args, "--cold-latency-ms") {
+        cold = cold.with_seek(Duration::from_millis(ms));
+    }
+    Ok(TierDisks { hot, cold })
+}
 
-    let storage = Arc::new(StorageEngine::open(&wal_path)?);
+/// `fuzz`: run `cases` random WAL recovery cases, seeded `seed`,
+/// `seed + 1`, ..., and stop at the first that breaks a recovery property.
+fn run_fuzz(session: Session, cases: u64, seed: u64) -> anyhow::Result<()> {
+    println!("=== WAL recovery fuzzing: {cases} cases from seed {seed} ===");
+    let wal_path = session.wal_path();
+    let (mut records, mut intact, mut recovered, mut damaged) = (0, 0, 0, 0);
+    for case_seed in seed..seed.saturating_add(cases) {
+        let case = FuzzCase::generate(case_seed);
+        match check_case(&wal_path, &case) {
+            Ok(outcome) => {
+                records += outcome.written;
+                intact += outcome.intact;
+                recovered += outcome.recovered;
+                damaged += usize::from(outcome.intact < outcome.written);
+            }
+            Err(e) => {
+                println!("Case {case_seed} failed: {e}");
+                println!("{case:#?}");
+                println!("Reproduce with: mini-aurora fuzz --seed {case_seed} --cases 1");
+                anyhow::bail!("WAL recovery broke a property");
+            }
+        }
+    }
+    session.remove();
+    println!("All {cases} cases passed: {records} records written, {damaged} WALs damaged");
+    println!("{intact} records intact before the damage, {recovered} recovered up to the VDL");
+    Ok(())
+}
+
+async fn run_demo(session: Session) -> anyhow::Result<()> {
+    println!("=== Mini-Aurora Demo ===\n");
+
+    let storage = Arc::new(StorageEngine::open(&session.wal_path())?);
     let compute = ComputeEngine::new(storage.clone(), 256);
 
     // 1. Write some data
@@ -191,7 +470,7 @@ async fn run_demo() -> anyhow::Result<()> {
 
     for pid in 3..=5 {
         let page = compute.get(pid).await?;
-        let end = page.iter().position(|&b| b == 0).unwrap_or(PAGE_SIZE);
+        let end = page.iter().position(|&b| b == 0).unwrap_or(page.len());
         println!(
             "Page {pid}: {:?}",
             String::from_utf8_lossy(&page[..end])
@@ -213,33 +492,63 @@ async fn run_demo() -> anyhow::Result<()> {
     );
 
     // Clean up
-    let _ = std::fs::remove_file(&wal_path);
+    drop(compute);
+    drop(storage);
+    session.remove();
     println!("\nDemo complete.");
     Ok(())
 }
 
-async fn run_repl() -> anyhow::Result<()> {
+async fn run_repl(session: Session, engine: &EngineOptions, options: &ReplOptions) -> anyhow::Result<()> {
     println!("=== Mini-Aurora REPL ===");
-    println!("Commands: put <page> <offset> <text>, get <page>, state, quit\n");
-
-    let wal_path = PathBuf::from("/tmp/mini-aurora-repl.wal");
-    let storage = Arc::new(StorageEngine::open(&wal_path)?);
-    let compute = ComputeEngine::new(storage.clone(), 256);
+    println!("Commands: put <page> <offset> <text>, get <page> [page ...], state, stats, heatmap, chain <page>,\n          hex <page> [offset] [len], diff <page> <lsn1> <lsn2>, asof <page> <time>,\n          admin <op>, warmup [n], alerts, invariants [on|off], source <file>,\n          sessions list, quit\n");
+
+    println!("Session {} in {}", session.name(), session.dir().display());
+    let wal_path = session.wal_path();
+    let alerts = Arc::new(RecentAlerts::default());
+    let storage = match engine.page_size {
+        Some(page_size) => StorageEngine::open_with_page_size(&wal_path, page_size, Arc::new(NoopObserver))?,
+        None => StorageEngine::open(&wal_path)?,
+    };
+    let storage = Arc::new(storage.with_page_cache(engine.page_cache).with_alert_sink(alerts.clone()));
+    storage.set_checksum(engine.checksum);
+    let memory = storage.memory_budget();
+    memory.set_limit(engine.memory_limit);
+    let compute = ComputeEngine::new(storage.clone(), 256)
+        .with_write_policy(engine.write_policy)
+        .with_read_ahead(engine.read_ahead)
+        .with_memory_budget(&memory);
 
     // Refresh read point from any prior session
     compute.refresh_read_point().await?;
+    if options.warmup {
+        run_warmup(storage.as_ref(), WARMUP_PAGES).await;
+    }
 
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+    // The prompt reads Ctrl-C as a key; one pressed during a command is
+    // ignored so the command finishes and storage still closes on quit
+    let _sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())?;
 
-    loop {
-        print!("aurora> ");
-        stdout.flush()?;
+    let pages_storage = storage.clone();
+    let mut editor = LineEditor::new(ReplHelper::new(REPL_COMMANDS, move || {
+        pages_storage.page_stats().into_keys().collect()
+    }))?;
 
-        let mut line = String::new();
-        if stdin.read_line(&mut line)? == 0 {
-            break;
-        }
+    let mut pending = match options.init.as_deref() {
+        Some(path) => read_script(path)?,
+        None => VecDeque::new(),
+    };
+    loop {
+        let line = match pending.pop_front() {
+            Some(line) => {
+                println!("aurora> {line}");
+                line
+            }
+            None => match editor.read_line("aurora> ") {
+                Some(line) => line,
+                None => break,
+            },
+        };
         let parts: Vec<&str> = line.trim().splitn(4, ' ').collect();
         if parts.is_empty() || parts[0].is_empty() {
             continue;
@@ -262,12 +571,19 @@ async fn run_repl() -> anyhow::Result<()> {
                 let data = parts[3].as_bytes().to_vec();
                 match compute.put(page_id, offset, data).await {
                     Ok(vdl) => println!("OK (VDL={vdl})"),
-                    Err(e) => println!("Error: {e}"),
+                    Err(e) => println!("Error: {}", explain(&e)),
                 }
             }
+            "get" if parts.len() > 2 => match parse_page_ids(&parts[1..]) {
+                Some(page_ids) => match compute.get_multi(&page_ids).await {
+                    Ok(pages) => print_pages(&page_ids, &pages),
+                    Err(e) => println!("Error: {}", explain(&e)),
+                },
+                None => println!("Invalid page_id"),
+            },
             "get" => {
                 if parts.len() < 2 {
-                    println!("Usage: get <page_id>");
+                    println!("Usage: get <page_id> [page_id ...]");
                     continue;
                 }
                 let page_id: PageId = match parts[1].parse() {
@@ -276,47 +592,171 @@ async fn run_repl() -> anyhow::Result<()> {
                 };
                 match compute.get(page_id).await {
                     Ok(page) => {
-                        let end = page.iter().position(|&b| b == 0).unwrap_or(PAGE_SIZE);
+                        let end = page.iter().position(|&b| b == 0).unwrap_or(page.len());
                         if end == 0 {
                             println!("(empty page)");
                         } else {
                             println!("{:?}", String::from_utf8_lossy(&page[..end]));
                         }
                     }
-                    Err(e) => println!("Error: {e}"),
+                    Err(e) => println!("Error: {}", explain(&e)),
                 }
             }
             "state" => {
                 match storage.get_durability_state().await {
                     Ok(s) => println!("{s}"),
-                    Err(e) => println!("Error: {e}"),
+                    Err(e) => println!("Error: {}", explain(&e)),
                 }
             }
+            "stats" => println!("{}", storage.stats()),
+            "alerts" => print_alerts(&alerts.recent()),
+            "invariants" => run_invariants_command(
+                &parts,
+                storage.invariant_checks(),
+                |on| storage.set_invariant_checks(on),
+                || Ok(storage.invariant_violations()),
+            ),
+            "admin" => {
+                run_admin_command(storage.as_ref(), &parts).await;
+            }
+            "warmup" => match parts.get(1).map_or(Some(WARMUP_PAGES), |n| n.parse().ok()) {
+                Some(limit) => {
+                    run_warmup(storage.as_ref(), limit).await;
+                }
+                None => println!("Usage: warmup [pages]"),
+            },
+            "heatmap" => {
+                print!("{}", format_heatmap(&storage.page_stats(), HEATMAP_PAGES_PER_LINE, options.color))
+            }
+            "chain" => match parts.get(1).map(|p| p.parse::<PageId>()) {
+                Some(Ok(page_id)) => match storage.page_history(page_id) {
+                    Ok(history) => print!("{}", format_chain(page_id, &history)),
+                    Err(e) => println!("Error: {}", explain(&e)),
+                },
+                _ => println!("Usage: chain <page_id>"),
+            },
+            "hex" => match parse_hex_args(&parts, compute.page_size()) {
+                Some((page_id, range)) => match compute.get(page_id).await {
+                    Ok(page) => print!("{}", hexdump(&page[..], range.unwrap_or_else(|| used_range(&page[..], 0)))),
+                    Err(e) => println!("Error: {}", explain(&e)),
+                },
+                None => println!("Usage: hex <page_id> [offset] [len]"),
+            },
+            "diff" => match parse_diff_args(&parts) {
+                Some((page_id, old, new)) => {
+                    match (compute.get_at(page_id, old).await, compute.get_at(page_id, new).await) {
+                        (Ok(a), Ok(b)) => print!("{}", format_diff(&a[..], &b[..], &format!("L{old}"), &format!("L{new}"))),
+                        (Err(e), _) | (_, Err(e)) => println!("Error: {}", explain(&e)),
+                    }
+                }
+                None => println!("Usage: diff <page_id> <lsn1> <lsn2>"),
+            },
+            "asof" => match parse_asof_args(&parts) {
+                Some((page_id, timestamp)) => match storage.lsn_for_time(timestamp) {
+                    Ok(0) => println!("No consistency point at or before {} UTC", format_utc(timestamp)),
+                    Ok(lsn) => match compute.get_at(page_id, lsn).await {
+                        Ok(page) => println!("pg{page_id} as of {} UTC (L{lsn}): {}", format_utc(timestamp), page_text(&page[..])),
+                        Err(e) => println!("Error: {}", explain(&e)),
+                    },
+                    Err(e) => println!("Error: {}", explain(&e)),
+                },
+                None => println!("Usage: asof <page_id> <-Ns|-Nm|-Nh|HH:MM[:SS[.mmm]]>"),
+            },
+            "source" => match parts.get(1).map(|path| read_script(path)) {
+                Some(Ok(lines)) => {
+                    for line in lines.into_iter().rev() {
+                        pending.push_front(line);
+                    }
+                }
+                Some(Err(e)) => println!("Error: {e:#}"),
+                None => println!("Usage: source <file>"),
+            },
+            "sessions" => print_sessions(&parts, &session),
             "quit" | "exit" | "q" => break,
             other => println!("Unknown command: {other}"),
         }
     }
 
+    report_close(storage.close());
     println!("Bye!");
     Ok(())
 }
 
+/// Read-only view of a session another process is writing: pages, state
+/// and stats come straight from the WAL, so a `repl` on the same session
+/// can keep appending. `refresh` reopens the WAL to pick up its new MTRs.
+async fn run_inspect(session: Session) -> anyhow::Result<()> {
+    println!("=== Mini-Aurora inspector (read-only) ===");
+    println!("Commands: get <page> [page ...], state, stats, heatmap, chain <page>, refresh, quit\n");
+
+    let wal_path = session.wal_path();
+    let mut storage = Arc::new(StorageEngine::open_read_only(&wal_path).with_context(|| {
+        format!("Cannot inspect session {}: it has no single-file WAL", session.name())
+    })?);
+    println!("Session {} in {} at VDL={}", session.name(), session.dir().display(), storage.current_vdl());
+
+    let mut editor = LineEditor::new(ReplHelper::new(INSPECT_COMMANDS, Vec::new))?;
+    while let Some(line) = editor.read_line("inspect> ") {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts.first().copied() {
+            None => {}
+            Some("get") => match parse_page_ids(&parts[1..]).filter(|ids| !ids.is_empty()) {
+                Some(page_ids) => {
+                    let vdl = storage.current_vdl();
+                    for (page_id, page) in page_ids.iter().zip(storage.get_pages(&page_ids, vdl).await) {
+                        match page {
+                            Ok(page) => println!("pg{page_id}: {}", page_text(&page[..])),
+                            Err(e) => println!("pg{page_id}: Error: {}", explain(&e)),
+                        }
+                    }
+                }
+                None => println!("Usage: get <page_id> [page_id ...]"),
+            },
+            Some("state") => match storage.get_durability_state().await {
+                Ok(s) => println!("{s}"),
+                Err(e) => println!("Error: {}", explain(&e)),
+            },
+            Some("stats") => println!("{}", storage.stats()),
+            Some("heatmap") => print!("{}", format_heatmap(&storage.page_stats(), HEATMAP_PAGES_PER_LINE, false)),
+            Some("chain") => match parts.get(1).map(|p| p.parse::<PageId>()) {
+                Some(Ok(page_id)) => match storage.page_history(page_id) {
+                    Ok(history) => print!("{}", format_chain(page_id, &history)),
+                    Err(e) => println!("Error: {}", explain(&e)),
+                },
+                _ => println!("Usage: chain <page_id>"),
+            },
+            Some("refresh") => match StorageEngine::open_read_only(&wal_path) {
+                Ok(reopened) => {
+                    let old = storage.current_vdl();
+                    storage = Arc::new(reopened);
+                    println!("VDL {old} -> {}", storage.current_vdl());
+                }
+                Err(e) => println!("Error: {}", explain(&e)),
+            },
+            Some("quit" | "exit" | "q") => break,
+            Some(other) => println!("Unknown command: {other}"),
+        }
+    }
+    Ok(())
+}
+
 // ---------------------------------------------------------------------------
 // Viz modes
 // ---------------------------------------------------------------------------
 
-async fn run_viz_demo(delay_ms: u64, color: bool) -> anyhow::Result<()> {
+async fn run_viz_demo(session: Session, delay_ms: u64, color: bool) -> anyhow::Result<()> {
     let config = VizConfig {
         step_delay: Duration::from_millis(delay_ms),
         color,
         enabled: true,
+        step: false,
+        lanes: false,
+        metrics_strip: false,
     };
     let renderer = Arc::new(Mutex::new(VizRenderer::new(config)));
 
-    let wal_path = PathBuf::from("/tmp/mini-aurora-viz-demo.wal");
-    let _ = std::fs::remove_file(&wal_path);
 
-    let storage = Arc::new(VizStorageEngine::open(&wal_path, renderer.clone())?);
+    let storage = Arc::new(VizStorageEngine::open(&session.wal_path(), None, renderer.clone())?);
     let node_a = VizComputeEngine::new(storage.clone(), 256, renderer.clone(), "A".to_string());
     let node_b = VizComputeEngine::new(storage.clone(), 256, renderer.clone(), "B".to_string());
 
@@ -324,10 +764,8 @@ async fn run_viz_demo(delay_ms: u64, color: bool) -> anyhow::Result<()> {
     node_a.put(1, 0, b"Hello".to_vec()).await?;
 
     // Phase 2: Read Isolation — Node B at rp=0 can't see pg1
-    match node_b.get(1).await {
-        Err(_) => { /* expected: page not found at rp=0 */ }
-        Ok(_) => {}
-    }
+    // Expected to fail: page not found at rp=0
+    let _ = node_b.get(1).await;
     node_b.refresh_read_point().await?;
     let page = node_b.get(1).await?;
     println!(
@@ -359,7 +797,7 @@ async fn run_viz_demo(delay_ms: u64, color: bool) -> anyhow::Result<()> {
     node_b.refresh_read_point().await?;
     for pid in 2..=3 {
         let page = node_b.get(pid).await?;
-        let end = page.iter().position(|&b| b == 0).unwrap_or(PAGE_SIZE);
+        let end = page.iter().position(|&b| b == 0).unwrap_or(page.len());
         println!(
             "  Node B reads pg{pid}: {:?}",
             String::from_utf8_lossy(&page[..end])
@@ -367,51 +805,96 @@ async fn run_viz_demo(delay_ms: u64, color: bool) -> anyhow::Result<()> {
     }
 
     // Clean up
-    let _ = std::fs::remove_file(&wal_path);
+    drop((node_a, node_b, storage));
+    session.remove();
     println!("\nViz demo complete.");
     Ok(())
 }
 
-async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, preset: &str, segment_size: u64, cold_latency_ms: u64) -> anyhow::Result<()> {
+async fn run_viz_repl(session: Session, engine: &EngineOptions, options: &ReplOptions) -> anyhow::Result<()> {
+    let preset = engine.preset.as_str();
     println!("=== Mini-Aurora Viz REPL (preset: {preset}) ===");
-    println!("Commands: put <page> <offset> <text>, get <page>, refresh");
-    println!("          node A|B, state, metrics, bg <node> write|read|mixed <ms>");
-    println!("          bg stop <node>, bg list, viz on|off, delay <ms>");
-    println!("          1/2/3 (run suggestion), quit\n");
+    println!("Commands: put <page> <offset> <text>, get <page> [page ...], refresh");
+    println!("          node <label>, node add <label>, state, stats, metrics, heatmap, chain <page>");
+    println!("          bufpool [node], bufpool clear [node], alerts, warmup [n]");
+    println!("          hex <page> [offset] [len], diff <page> <lsn1> <lsn2>, asof <page> <time>");
+    println!("          bg <node> write|read|mixed <ms>");
+    println!("          bg stop <node>, bg list, viz on|off, delay <ms>, step on|off, replay, health");
+    println!("          viz filter <terms>|off, viz lanes|metrics on|off");
+    println!("          crash storage, corrupt wal <bytes>|-<bytes>, corrupt cache <page>, recover");
+    println!("          admin checkpoint|gc|compact|cool|scrub|stats|flush-cache|prefetch [n]|truncate <lsn>");
+    println!("          qos, qos limit <fg|bg|caller> <rate> [burst]|off, invariants [on|off], promote [on|off]");
+    println!("          record <file.toml>, stoprecord, trace tail [n], oplog [n], source <file>");
+    println!("          sessions list, 1/2/3 (run suggestion), quit\n");
+    println!("Session {} in {}", session.name(), session.dir().display());
 
     let config = VizConfig {
-        step_delay: Duration::from_millis(delay_ms),
-        color,
+        step_delay: Duration::from_millis(options.delay_ms),
+        color: options.color,
         enabled: true,
+        step: false,
+        lanes: false,
+        metrics_strip: false,
     };
     let mut renderer_inner = VizRenderer::new(config);
-    if let Some(ref path) = trace_json {
-        let tracer = JsonTracer::open(std::path::Path::new(path))?;
-        renderer_inner.set_tracer(tracer);
-        println!("Tracing events to: {path}");
-    }
+    // The ring backs `trace tail`; files are added only when requested
+    let trace_ring = TraceRing::new(TRACE_RING_CAPACITY);
+    let mut tracer = Tracer::new();
+    tracer.add_sink(trace_ring.clone());
+    options.trace.add_sinks(&mut tracer)?;
+    renderer_inner.set_tracer(tracer);
+    let step_gate = StepGate::default();
+    renderer_inner.set_step_gate(step_gate.clone());
+    let cancel = renderer_inner.cancel_flag();
     let renderer = Arc::new(Mutex::new(renderer_inner));
 
+    // Ctrl-C stops drawing the running command and lets it finish without
+    // pausing; the main loop then stops the workers and closes storage
+    let interrupted = Arc::new(tokio::sync::Notify::new());
+    let interrupt: Arc<dyn Fn() + Send + Sync> = {
+        let (cancel, gate, interrupted) = (cancel.clone(), step_gate.clone(), interrupted.clone());
+        Arc::new(move || {
+            cancel.store(true, Ordering::SeqCst);
+            gate.interrupt();
+            interrupted.notify_one();
+        })
+    };
+    let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())?;
+    let on_sigint = interrupt.clone();
+    tokio::spawn(async move {
+        while sigint.recv().await.is_some() {
+            on_sigint();
+        }
+    });
+
+    let EngineOptions { segment_size, disks, page_size, write_policy, batching, .. } = *engine;
     let storage: Arc<VizStorageEngine> = match preset {
         "tiered" => {
-            let base_dir = PathBuf::from("/tmp/mini-aurora-viz-tiered");
-            let _ = std::fs::remove_dir_all(&base_dir);
-            let cold_latency = Duration::from_millis(cold_latency_ms);
-            println!("Tiered storage: segment_size={segment_size}B, cold_latency={cold_latency_ms}ms");
-            Arc::new(VizStorageEngine::open_tiered(&base_dir, segment_size, cold_latency, renderer.clone())?)
+            let base_dir = session.tiered_dir();
+            println!("Tiered storage: segment_size={segment_size}B, hot disk: {}, cold disk: {}", disks.hot, disks.cold);
+            Arc::new(VizStorageEngine::open_tiered(&base_dir, segment_size, disks, page_size, renderer.clone())?)
+        }
+        "memory" => {
+            println!("In-memory storage: nothing is written to disk");
+            Arc::new(VizStorageEngine::open_in_memory(page_size, renderer.clone())?)
         }
         _ => {
-            let wal_path = PathBuf::from("/tmp/mini-aurora-viz-repl.wal");
-            Arc::new(VizStorageEngine::open(&wal_path, renderer.clone())?)
+            Arc::new(VizStorageEngine::open(&session.wal_path(), page_size, renderer.clone())?)
         }
     };
-
-    let node_a = Arc::new(VizComputeEngine::new(
-        storage.clone(), 256, renderer.clone(), "A".to_string(),
-    ));
-    let node_b = Arc::new(VizComputeEngine::new(
-        storage.clone(), 256, renderer.clone(), "B".to_string(),
-    ));
+    storage.memory_budget().set_limit(engine.memory_limit);
+    storage.set_checksum(engine.checksum);
+
+    let heartbeat = (engine.heartbeat_ms > 0).then(|| {
+        let interval = Duration::from_millis(engine.heartbeat_ms);
+        HeartbeatConfig {
+            interval,
+            timeout: HeartbeatConfig::default().timeout.min(interval),
+            ..HeartbeatConfig::default()
+        }
+    });
+    let node_a = Arc::new(viz_node(&storage, &renderer, "A", write_policy, heartbeat, batching));
+    let node_b = Arc::new(viz_node(&storage, &renderer, "B", write_policy, heartbeat, batching));
 
     node_a.refresh_read_point().await?;
     node_b.refresh_read_point().await?;
@@ -426,43 +909,82 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
         nodes,
         current_node: "A".to_string(),
         suggestions: Vec::new(),
+        tutor: Box::new(RuleTutor::default()),
         workers: HashMap::new(),
+        worker_host: WorkerHost::new(storage.clone(), renderer.clone(), bg_output_tx),
         storage,
         renderer,
-        bg_page_counter: Arc::new(AtomicU64::new(100)),
-        bg_output_tx,
+        recording: None,
+        trace_ring,
+        write_policy,
+        heartbeat,
+        batching,
+        session,
     };
 
     // --- Async stdin: OS thread + mpsc channel ---
     let (line_tx, mut line_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
     let prompt_str = Arc::new(std::sync::Mutex::new("A> ".to_string()));
     let prompt_for_thread = prompt_str.clone();
+    let gate_for_thread = step_gate.clone();
+    let interrupt_for_thread = interrupt.clone();
+    let node_names = Arc::new(Mutex::new(node_labels(&state)));
+    let pages_storage = state.storage.clone();
+    let mut editor = LineEditor::new(
+        ReplHelper::new(VIZ_REPL_COMMANDS, move || pages_storage.page_stats().into_keys().collect())
+            .with_nodes(node_names.clone()),
+    )?;
 
     std::thread::spawn(move || {
-        let stdin = io::stdin();
-        loop {
-            {
-                let p = prompt_for_thread.lock().unwrap();
-                print!("{}", *p);
-                let _ = io::stdout().flush();
-            }
-            let mut line = String::new();
-            match stdin.read_line(&mut line) {
-                Ok(0) | Err(_) => break, // EOF or error
-                Ok(_) => {
-                    if line_tx.send(line).is_err() {
-                        break;
-                    }
+        // Only read when the REPL is idle or a step pause wants a keypress,
+        // so the prompt never lands in the middle of a command's output
+        while let Some(turn) = gate_for_thread.turn() {
+            let (prompt, stepping) = match turn {
+                InputTurn::Command => (prompt_for_thread.lock().unwrap().clone(), false),
+                InputTurn::Step(hint) => (hint, true),
+            };
+            let line = match editor.read_input(&prompt) {
+                Some(Input::Line(line)) => line,
+                // The prompt's raw mode swallows the signal, so a step
+                // pause has to pass Ctrl-C on itself
+                Some(Input::Interrupted) if stepping => {
+                    interrupt_for_thread();
+                    continue;
                 }
+                Some(Input::Interrupted) => String::new(),
+                None => break, // EOF or error
+            };
+            // Lines typed at a step-mode pause advance the viz
+            if !gate_for_thread.offer(&line) && line_tx.send(line).is_err() {
+                break;
             }
         }
+        gate_for_thread.close();
     });
 
+    if options.warmup && run_warmup(state.storage.as_ref(), WARMUP_PAGES).await {
+        emit_snapshot(&state).await;
+    }
+
     // Print initial suggestions
+    update_suggestions(&mut state, &CommandOutcome::None).await;
     print_suggestions(&state);
 
-    // --- Main loop: select on stdin + bg output ---
+    // Reflow the frame when the terminal is resized (SIGWINCH)
+    let mut resize = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::window_change())?;
+
+    // --- Main loop: select on stdin + bg output + resizes ---
+    let mut commands_received = 0;
+    let mut pending = match options.init.as_deref() {
+        Some(path) => read_script(path)?,
+        None => VecDeque::new(),
+    };
     loop {
+        step_gate.arm(false);
+        // Hold the prompt back until a sourced script has run to the end
+        if pending.is_empty() {
+            step_gate.finished(commands_received);
+        }
         // Drain any bg output that queued up (e.g. during a slow viz command)
         while let Ok(msg) = bg_output_rx.try_recv() {
             println!("{msg}");
@@ -471,9 +993,21 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
         tokio::select! {
             biased; // prefer user input over bg output
 
-            line = line_rx.recv() => {
+            line = async {
+                match pending.pop_front() {
+                    Some(l) => Some((l, true)),
+                    None => line_rx.recv().await.map(|l| (l, false)),
+                }
+            } => {
                 let line = match line {
-                    Some(l) => l,
+                    Some((l, true)) => {
+                        println!("{}{l}", *prompt_str.lock().unwrap());
+                        l
+                    }
+                    Some((l, false)) => {
+                        commands_received += 1;
+                        l
+                    }
                     None => break,
                 };
 
@@ -485,7 +1019,7 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
                 // Check for suggestion shortcut (1, 2, 3)
                 let cmd = if let Ok(n) = trimmed.parse::<usize>() {
                     if n >= 1 && n <= state.suggestions.len() {
-                        let resolved = state.suggestions[n - 1].clone();
+                        let resolved = state.suggestions[n - 1].command.clone();
                         println!(">>> {resolved}");
                         resolved
                     } else {
@@ -500,6 +1034,7 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
                     continue;
                 }
 
+                step_gate.arm(true);
                 let outcome = match parts[0] {
                     "put" => {
                         if parts.len() < 4 {
@@ -518,22 +1053,56 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
                             if let Some(w) = state.workers.get(&state.current_node) {
                                 println!("(warning: node {} has active bg {} worker)", state.current_node, w.kind);
                             }
+                            state.record(ScenarioStep::Put {
+                                page_id,
+                                offset,
+                                data: parts[3].to_string(),
+                                node: Some(state.current_node.clone()),
+                                allow_error: false,
+                            });
                             let compute = state.nodes[&state.current_node].clone();
-                            match compute.put(page_id, offset, data).await {
+                            let started = start_command_timer(&state);
+                            let result = compute.put(page_id, offset, data).await;
+                            let outcome = match result {
                                 Ok(vdl) => {
                                     println!("OK (VDL={vdl})");
                                     CommandOutcome::Put { page_id }
                                 }
                                 Err(e) => {
-                                    println!("Error: {e}");
+                                    println!("Error: {}", explain(&e));
                                     CommandOutcome::None
                                 }
+                            };
+                            print_command_time(&state, started);
+                            outcome
+                        }
+                    }
+                    "get" if parts.len() > 2 => {
+                        match parse_page_ids(&parts[1..]) {
+                            Some(page_ids) => {
+                                for &page_id in &page_ids {
+                                    state.record(ScenarioStep::Get {
+                                        page_id,
+                                        node: Some(state.current_node.clone()),
+                                        expect: None,
+                                        allow_error: false,
+                                    });
+                                }
+                                let compute = state.nodes[&state.current_node].clone();
+                                let started = start_command_timer(&state);
+                                match compute.get_multi(&page_ids).await {
+                                    Ok(pages) => print_pages(&page_ids, &pages),
+                                    Err(e) => println!("Error: {}", explain(&e)),
+                                }
+                                print_command_time(&state, started);
                             }
+                            None => println!("Invalid page_id"),
                         }
+                        CommandOutcome::None
                     }
                     "get" => {
                         if parts.len() < 2 {
-                            println!("Usage: get <page_id>");
+                            println!("Usage: get <page_id> [page_id ...]");
                             CommandOutcome::None
                         } else {
                             let page_id: PageId = match parts[1].parse() {
@@ -543,10 +1112,18 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
                             if let Some(w) = state.workers.get(&state.current_node) {
                                 println!("(warning: node {} has active bg {} worker)", state.current_node, w.kind);
                             }
+                            state.record(ScenarioStep::Get {
+                                page_id,
+                                node: Some(state.current_node.clone()),
+                                expect: None,
+                                allow_error: false,
+                            });
                             let compute = state.nodes[&state.current_node].clone();
-                            match compute.get(page_id).await {
+                            let started = start_command_timer(&state);
+                            let result = compute.get(page_id).await;
+                            let outcome = match result {
                                 Ok(page) => {
-                                    let end = page.iter().position(|&b| b == 0).unwrap_or(PAGE_SIZE);
+                                    let end = page.iter().position(|&b| b == 0).unwrap_or(page.len());
                                     if end == 0 {
                                         println!("(empty page)");
                                     } else {
@@ -555,24 +1132,38 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
                                     CommandOutcome::GetSuccess { page_id }
                                 }
                                 Err(e) => {
-                                    println!("Error: {e}");
+                                    println!("Error: {}", explain(&e));
                                     CommandOutcome::GetFailure { page_id }
                                 }
-                            }
+                            };
+                            print_command_time(&state, started);
+                            outcome
                         }
                     }
                     "refresh" => {
+                        state.record(ScenarioStep::Refresh {
+                            node: Some(state.current_node.clone()),
+                            allow_error: false,
+                        });
                         let compute = state.nodes[&state.current_node].clone();
                         match compute.refresh_read_point().await {
                             Ok(rp) => println!("read_point -> {rp}"),
-                            Err(e) => println!("Error: {e}"),
+                            Err(e) => println!("Error: {}", explain(&e)),
                         }
                         CommandOutcome::Refresh
                     }
                     "node" => {
                         if parts.len() < 2 {
-                            println!("Usage: node A|B");
+                            println!("Usage: node <label>, node add <label>");
                             CommandOutcome::None
+                        } else if parts[1] == "add" {
+                            match parts.get(2) {
+                                Some(label) => add_node(&mut state, &label.to_uppercase(), &prompt_str).await,
+                                None => {
+                                    println!("Usage: node add <label>");
+                                    CommandOutcome::None
+                                }
+                            }
                         } else {
                             let target = parts[1].to_uppercase();
                             if state.nodes.contains_key(&target) {
@@ -581,7 +1172,7 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
                                 println!("Switched to Node {}", state.current_node);
                                 CommandOutcome::NodeSwitch
                             } else {
-                                println!("Unknown node: {}. Available: A, B", parts[1]);
+                                println!("Unknown node: {}. Available: {}", parts[1], node_labels(&state).join(", "));
                                 CommandOutcome::None
                             }
                         }
@@ -590,32 +1181,226 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
                         match state.storage.get_durability_state().await {
                             Ok(s) => {
                                 println!("{s}");
-                                let compute = state.nodes[&state.current_node].clone();
-                                let rp = compute.read_point().await;
-                                state.storage.emit_state_snapshot(
-                                    state.current_node.clone(), rp, 0, Vec::new(),
-                                );
+                                emit_snapshot(&state).await;
                             }
-                            Err(e) => println!("Error: {e}"),
+                            Err(e) => println!("Error: {}", explain(&e)),
                         }
                         CommandOutcome::None
                     }
                     "metrics" => {
-                        let r = state.renderer.lock().unwrap();
-                        match r.metrics_summary() {
-                            Some(summary) => println!("{summary}"),
-                            None => println!("Metrics not available."),
+                        {
+                            let r = state.renderer.lock().unwrap();
+                            match r.metrics_summary() {
+                                Some(summary) => println!("{summary}"),
+                                None => println!("Metrics not available."),
+                            }
+                        }
+                        let mut labels: Vec<&String> = state.nodes.keys().collect();
+                        labels.sort();
+                        for label in labels {
+                            let stats = state.nodes[label].buffer_pool_stats().await;
+                            println!("Node {label} buffer pool: {stats}");
+                        }
+                        println!("Storage cold read-ahead hits: {}", state.storage.cold_read_ahead_hits());
+                        CommandOutcome::None
+                    }
+                    "chain" => {
+                        match parts.get(1).map(|p| p.parse::<PageId>()) {
+                            Some(Ok(page_id)) => match state.storage.page_history(page_id) {
+                                Ok(history) => print!("{}", format_chain(page_id, &history)),
+                                Err(e) => println!("Error: {}", explain(&e)),
+                            },
+                            _ => println!("Usage: chain <page_id>"),
+                        }
+                        CommandOutcome::None
+                    }
+                    "hex" => {
+                        match parse_hex_args(&parts, state.storage.page_size()) {
+                            Some((page_id, range)) => {
+                                let compute = state.nodes[&state.current_node].clone();
+                                match compute.get(page_id).await {
+                                    Ok(page) => {
+                                        let range = range.unwrap_or_else(|| used_range(&page[..], 0));
+                                        print!("{}", hexdump(&page[..], range));
+                                    }
+                                    Err(e) => println!("Error: {}", explain(&e)),
+                                }
+                            }
+                            None => println!("Usage: hex <page_id> [offset] [len]"),
+                        }
+                        CommandOutcome::None
+                    }
+                    "diff" => {
+                        match parse_diff_args(&parts) {
+                            Some((page_id, old, new)) => {
+                                let compute = state.nodes[&state.current_node].clone();
+                                match (compute.get_at(page_id, old).await, compute.get_at(page_id, new).await) {
+                                    (Ok(a), Ok(b)) => {
+                                        print!("{}", format_diff(&a[..], &b[..], &format!("L{old}"), &format!("L{new}")));
+                                    }
+                                    (Err(e), _) | (_, Err(e)) => println!("Error: {}", explain(&e)),
+                                }
+                            }
+                            None => println!("Usage: diff <page_id> <lsn1> <lsn2>"),
+                        }
+                        CommandOutcome::None
+                    }
+                    "asof" => {
+                        match parse_asof_args(&parts) {
+                            Some((page_id, timestamp)) => match state.storage.lsn_for_time(timestamp) {
+                                Ok(0) => println!("No consistency point at or before {} UTC", format_utc(timestamp)),
+                                Ok(lsn) => {
+                                    let compute = state.nodes[&state.current_node].clone();
+                                    match compute.get_at(page_id, lsn).await {
+                                        Ok(page) => {
+                                            let time = format_utc(timestamp);
+                                            println!("pg{page_id} as of {time} UTC (L{lsn}): {}", page_text(&page[..]));
+                                        }
+                                        Err(e) => println!("Error: {}", explain(&e)),
+                                    }
+                                }
+                                Err(e) => println!("Error: {}", explain(&e)),
+                            },
+                            None => println!("Usage: asof <page_id> <-Ns|-Nm|-Nh|HH:MM[:SS[.mmm]]>"),
+                        }
+                        CommandOutcome::None
+                    }
+                    "sessions" => {
+                        print_sessions(&parts, &state.session);
+                        CommandOutcome::None
+                    }
+                    "stats" => {
+                        println!("{}", state.storage.stats());
+                        CommandOutcome::None
+                    }
+                    "health" => {
+                        print_health(&state);
+                        CommandOutcome::None
+                    }
+                    "promote" => {
+                        run_promote_command(&parts, &state.storage, preset);
+                        CommandOutcome::None
+                    }
+                    "invariants" => {
+                        run_invariants_command(
+                            &parts,
+                            state.storage.invariant_checks(),
+                            |on| state.storage.set_invariant_checks(on),
+                            || state.storage.invariant_violations(),
+                        );
+                        CommandOutcome::None
+                    }
+                    "qos" => {
+                        run_qos_command(state.storage.as_ref(), &parts).await;
+                        CommandOutcome::None
+                    }
+                    "heatmap" => {
+                        print!(
+                            "{}",
+                            format_heatmap(&state.storage.page_stats(), HEATMAP_PAGES_PER_LINE, options.color)
+                        );
+                        CommandOutcome::None
+                    }
+                    "alerts" => {
+                        print_alerts(&state.storage.recent_alerts());
+                        CommandOutcome::None
+                    }
+                    "bufpool" => {
+                        let clear = parts.get(1) == Some(&"clear");
+                        let target = parts
+                            .get(if clear { 2 } else { 1 })
+                            .map(|s| s.to_uppercase())
+                            .unwrap_or_else(|| state.current_node.clone());
+                        match state.nodes.get(&target) {
+                            Some(compute) if clear => {
+                                let dropped = compute.clear_buffer_pool().await;
+                                println!("Dropped {dropped} page(s) from node {target}'s buffer pool");
+                                emit_snapshot(&state).await;
+                            }
+                            Some(compute) => {
+                                let entries = compute.buffer_pool_entries().await;
+                                println!("Node {target} buffer pool: {}", compute.buffer_pool_stats().await);
+                                if entries.is_empty() {
+                                    println!("  (empty)");
+                                }
+                                for e in entries {
+                                    println!(
+                                        "  pg{:<6} @L{:<6} accesses={}",
+                                        e.page_id, e.read_point, e.access_count
+                                    );
+                                }
+                            }
+                            None => println!("Unknown node: {target}. Available: A, B"),
                         }
                         CommandOutcome::None
                     }
                     "bg" => {
                         handle_bg_command(&parts, &mut state).await
                     }
+                    "crash" | "corrupt" | "recover" => {
+                        handle_fault_command(&parts, &mut state).await
+                    }
+                    "admin" => {
+                        // Show the flushed cache and reshaped segments in the panel
+                        if run_admin_command(state.storage.as_ref(), &parts).await {
+                            emit_snapshot(&state).await;
+                        }
+                        CommandOutcome::None
+                    }
+                    "warmup" => {
+                        match parts.get(1).map_or(Some(WARMUP_PAGES), |n| n.parse().ok()) {
+                            Some(limit) => {
+                                if run_warmup(state.storage.as_ref(), limit).await {
+                                    emit_snapshot(&state).await;
+                                }
+                            }
+                            None => println!("Usage: warmup [pages]"),
+                        }
+                        CommandOutcome::None
+                    }
                     "viz" => {
                         if parts.len() < 2 {
-                            println!("Usage: viz on|off");
+                            println!("{VIZ_USAGE}");
                         } else {
                             match parts[1] {
+                                "filter" => {
+                                    let pattern = parts[2..].join(" ");
+                                    let mut r = state.renderer.lock().unwrap();
+                                    if pattern.trim() == "off" {
+                                        r.set_filter(None);
+                                        println!("Viz filter cleared.");
+                                    } else if let Some(filter) = EventFilter::parse(&pattern) {
+                                        println!("Viz filter: {filter}");
+                                        r.set_filter(Some(filter));
+                                    } else {
+                                        match r.filter() {
+                                            Some(filter) => println!("Viz filter: {filter}"),
+                                            None => println!("No viz filter (usage: viz filter wal vdl -chain | off)"),
+                                        }
+                                    }
+                                }
+                                "lanes" => match parts.get(2) {
+                                    Some(&"on") => {
+                                        state.renderer.lock().unwrap().config_mut().lanes = true;
+                                        println!("Event log split into one lane per node.");
+                                    }
+                                    Some(&"off") => {
+                                        state.renderer.lock().unwrap().config_mut().lanes = false;
+                                        println!("Event log lanes off.");
+                                    }
+                                    _ => println!("Usage: viz lanes on|off"),
+                                },
+                                "metrics" => match parts.get(2) {
+                                    Some(&"on") => {
+                                        state.renderer.lock().unwrap().config_mut().metrics_strip = true;
+                                        println!("Metrics strip on: live rates below the frame.");
+                                    }
+                                    Some(&"off") => {
+                                        state.renderer.lock().unwrap().config_mut().metrics_strip = false;
+                                        println!("Metrics strip off.");
+                                    }
+                                    _ => println!("Usage: viz metrics on|off"),
+                                },
                                 "on" => {
                                     state.renderer.lock().unwrap().config_mut().enabled = true;
                                     println!("Visualization enabled.");
@@ -624,7 +1409,7 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
                                     state.renderer.lock().unwrap().config_mut().enabled = false;
                                     println!("Visualization disabled.");
                                 }
-                                _ => println!("Usage: viz on|off"),
+                                _ => println!("{VIZ_USAGE}"),
                             }
                         }
                         CommandOutcome::None
@@ -644,27 +1429,104 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
                         }
                         CommandOutcome::None
                     }
-                    "quit" | "exit" | "q" => {
-                        for (label, handle) in state.workers.drain() {
-                            handle.cancel.cancel();
-                            let _ = handle.task.await;
-                            println!("Stopped bg worker on Node {label}");
+                    "step" => {
+                        match parts.get(1) {
+                            Some(&"on") => {
+                                state.renderer.lock().unwrap().config_mut().step = true;
+                                println!("Step mode on: Enter advances each step, c finishes the operation.");
+                            }
+                            Some(&"off") => {
+                                state.renderer.lock().unwrap().config_mut().step = false;
+                                println!("Step mode off.");
+                            }
+                            _ => println!("Usage: step on|off"),
+                        }
+                        CommandOutcome::None
+                    }
+                    "replay" => {
+                        if !state.renderer.lock().unwrap().replay() {
+                            println!("Nothing to replay (run a command with viz on first).");
+                        }
+                        CommandOutcome::None
+                    }
+                    "record" => {
+                        match parts.get(1) {
+                            None => println!("Usage: record <file.toml>"),
+                            Some(path) => {
+                                state.stop_recording();
+                                match ScenarioRecorder::start(PathBuf::from(path)) {
+                                    Ok(recorder) => {
+                                        println!("Recording to {path} (stoprecord to save)");
+                                        state.recording = Some(recorder);
+                                    }
+                                    Err(e) => println!("Error: {e}"),
+                                }
+                            }
+                        }
+                        CommandOutcome::None
+                    }
+                    "trace" => {
+                        match (parts.get(1), parts.get(2).map(|n| n.parse::<usize>())) {
+                            (Some(&"tail"), None) => print_trace_tail(&state.trace_ring, 20),
+                            (Some(&"tail"), Some(Ok(n))) => print_trace_tail(&state.trace_ring, n),
+                            _ => println!("Usage: trace tail [n]"),
+                        }
+                        CommandOutcome::None
+                    }
+                    "oplog" => {
+                        match parts.get(1).map(|n| n.parse::<usize>()) {
+                            None => print_oplog(&state.renderer.lock().unwrap().oplog(), 20),
+                            Some(Ok(n)) => print_oplog(&state.renderer.lock().unwrap().oplog(), n),
+                            Some(Err(_)) => println!("Usage: oplog [n]"),
                         }
-                        break;
+                        CommandOutcome::None
                     }
+                    "stoprecord" => {
+                        if state.recording.is_none() {
+                            println!("Not recording.");
+                        }
+                        state.stop_recording();
+                        CommandOutcome::None
+                    }
+                    "source" => match parts.get(1) {
+                        Some(path) => match read_script(path) {
+                            Ok(lines) => {
+                                for line in lines.into_iter().rev() {
+                                    pending.push_front(line);
+                                }
+                                CommandOutcome::None
+                            }
+                            Err(e) => {
+                                println!("Error: {e:#}");
+                                CommandOutcome::None
+                            }
+                        },
+                        None => {
+                            println!("Usage: source <file>");
+                            CommandOutcome::None
+                        }
+                    },
+                    "quit" | "exit" | "q" => break,
                     other => {
                         println!("Unknown command: {other}");
                         CommandOutcome::None
                     }
                 };
 
+                if cancel.load(Ordering::SeqCst) {
+                    cancel_command(&mut state).await;
+                }
+
                 // Drain bg output that accumulated during the (possibly slow) viz command
                 while let Ok(msg) = bg_output_rx.try_recv() {
                     println!("{msg}");
                 }
 
-                update_suggestions(&mut state, &outcome);
-                print_suggestions(&state);
+                *node_names.lock().unwrap() = node_labels(&state);
+                update_suggestions(&mut state, &outcome).await;
+                if pending.is_empty() {
+                    print_suggestions(&state);
+                }
             }
 
             // Stream bg output while idle (user hasn't pressed Enter yet)
@@ -673,92 +1535,616 @@ async fn run_viz_repl(delay_ms: u64, color: bool, trace_json: Option<String>, pr
                     println!("{msg}");
                 }
             }
+
+            _ = resize.recv() => {
+                state.renderer.lock().unwrap().redraw();
+            }
+
+            // Ctrl-C while idle but not at the prompt, e.g. with piped
+            // input; one during a command was handled when it finished
+            _ = interrupted.notified() => {
+                if cancel.load(Ordering::SeqCst) {
+                    cancel_command(&mut state).await;
+                }
+            }
         }
     }
 
+    // Quit and end of input shut down the same way
+    state.stop_recording();
+    state.stop_workers().await;
+    report_close(state.storage.close());
     println!("Bye!");
     Ok(())
 }
 
-fn other_node(current: &str) -> &'static str {
-    if current == "A" { "B" } else { "A" }
-}
-
-fn update_suggestions(state: &mut ReplState, outcome: &CommandOutcome) {
-    state.suggestions.clear();
-    match outcome {
-        CommandOutcome::Put { page_id } => {
-            state.suggestions.push(format!("get {page_id}"));
-            state.suggestions.push(format!("put {page_id} 0 updated"));
-            state.suggestions.push(format!("node {}", other_node(&state.current_node)));
-        }
-        CommandOutcome::GetSuccess { page_id } => {
-            state.suggestions.push(format!("put {page_id} 0 new-data"));
-            state.suggestions.push(format!("get {}", page_id + 1));
-            state.suggestions.push("refresh".to_string());
-        }
-        CommandOutcome::GetFailure { page_id } => {
-            state.suggestions.push("refresh".to_string());
-            state.suggestions.push(format!("put {page_id} 0 Hello"));
-            state.suggestions.push(format!("node {}", other_node(&state.current_node)));
-        }
-        CommandOutcome::Refresh => {
-            state.suggestions.push("get 1".to_string());
-            state.suggestions.push("state".to_string());
-            state.suggestions.push(format!("node {}", other_node(&state.current_node)));
-        }
-        CommandOutcome::NodeSwitch => {
-            state.suggestions.push("refresh".to_string());
-            state.suggestions.push("get 1".to_string());
-            state.suggestions.push("state".to_string());
-        }
-        CommandOutcome::BgStarted { node } => {
-            let other = if node == "A" { "B" } else { "A" };
-            state.suggestions.push(format!("bg {other} mixed 500"));
-            state.suggestions.push("bg list".to_string());
-            state.suggestions.push(format!("bg stop {node}"));
-        }
-        CommandOutcome::BgStopped { node: _ } => {
-            state.suggestions.push("bg list".to_string());
-            state.suggestions.push("get 1".to_string());
-            state.suggestions.push("state".to_string());
-        }
-        CommandOutcome::None => {
-            // Keep previous suggestions or show defaults
-            if state.suggestions.is_empty() {
-                state.suggestions.push("put 1 0 Hello".to_string());
-            }
+fn print_trace_tail(ring: &TraceRing, n: usize) {
+    let records = ring.tail(n);
+    if records.is_empty() {
+        println!("No traced events yet.");
+    }
+    for r in records {
+        let op = match (r.op_id, r.op, &r.node) {
+            (Some(id), Some(op), Some(node)) => format!("op#{id} {node} {op}"),
+            _ => "-".to_string(),
+        };
+        println!(
+            "  #{:<6} +{:>6}µs  {op:<18} {}",
+            r.seq, r.delta_us, VizRenderer::format_one_liner(&r.event)
+        );
+    }
+}
+
+fn print_oplog(log: &OpLog, n: usize) {
+    let entries = log.tail(n);
+    if entries.is_empty() {
+        println!("No operations yet.");
+        return;
+    }
+    println!("{}", oplog_header());
+    for entry in entries {
+        println!("{entry}");
+    }
+}
+
+/// `hex <page_id> [offset] [len]`: the page and, if an offset was given,
+/// the byte range to dump, within a page of `page_size` bytes. A missing
+/// length runs to the last used byte.
+fn parse_hex_args(parts: &[&str], page_size: usize) -> Option<(PageId, Option<std::ops::Range<usize>>)> {
+    let page_id = parts.get(1)?.parse().ok()?;
+    let range = match (parts.get(2), parts.get(3)) {
+        (None, _) => None,
+        (Some(offset), len) => {
+            let offset: usize = offset.parse().ok().filter(|&o| o < page_size)?;
+            Some(match len {
+                Some(len) => offset..(offset + len.parse::<usize>().ok()?).min(page_size),
+                None => offset..page_size,
+            })
         }
+    };
+    Some((page_id, range))
+}
+
+/// Commands from a `source`/`--init` file, one per line; blank lines and
+/// `#` comments are skipped.
+fn read_script(path: &str) -> anyhow::Result<VecDeque<String>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Cannot read script {path}"))?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Start timing a `put` or `get` typed at the prompt. The phase breakdown
+/// is skipped while bg workers run, as their events would be mixed in.
+fn start_command_timer(state: &ReplState) -> Instant {
+    if state.workers.is_empty() {
+        state.renderer.lock().unwrap().start_phase_timer();
+    }
+    Instant::now()
+}
+
+/// Print the command's wall time, broken down by phase when viz is off.
+fn print_command_time(state: &ReplState, started: Instant) {
+    let elapsed = format_elapsed(started.elapsed());
+    match state.renderer.lock().unwrap().take_phase_timer() {
+        Some(phases) if !phases.is_empty() => println!("Took {elapsed}: {phases}"),
+        _ => println!("Took {elapsed}"),
+    }
+}
+
+/// `sessions list`: the sessions in the data dir, marking the open one.
+fn print_sessions(parts: &[&str], session: &Session) {
+    if !matches!(parts.get(1), None | Some(&"list")) {
+        println!("Usage: sessions list");
+        return;
+    }
+    let data_dir = session.data_dir();
+    match data_dir.sessions() {
+        Ok(sessions) => print!("{}", format_sessions(data_dir.root(), &sessions, session.name())),
+        Err(e) => println!("Error: {e:#}"),
+    }
+}
+
+/// `diff <page_id> <lsn1> <lsn2>`.
+fn parse_diff_args(parts: &[&str]) -> Option<(PageId, Lsn, Lsn)> {
+    match parts {
+        [_, page, old, new] => Some((page.parse().ok()?, old.parse().ok()?, new.parse().ok()?)),
+        _ => None,
+    }
+}
+
+/// `asof <page_id> <time>`; see `clock::parse_time` for the time forms.
+fn parse_asof_args(parts: &[&str]) -> Option<(PageId, Timestamp)> {
+    match parts {
+        [_, page, time] => Some((page.parse().ok()?, parse_time(time, now_micros())?)),
+        _ => None,
+    }
+}
+
+fn parse_page_ids(args: &[&str]) -> Option<Vec<PageId>> {
+    args.iter().map(|arg| arg.parse().ok()).collect()
+}
+
+/// One `pgN: text` line per page read by a multi-page `get`.
+fn print_pages(page_ids: &[PageId], pages: &[PageRef]) {
+    for (page_id, page) in page_ids.iter().zip(pages) {
+        println!("pg{page_id}: {}", page_text(&page[..]));
+    }
+}
+
+/// A page's text up to its first zero byte, quoted, as `get` prints it.
+fn page_text(page: &[u8]) -> String {
+    match page.iter().position(|&b| b == 0).unwrap_or(page.len()) {
+        0 => "(empty page)".to_string(),
+        end => format!("{:?}", String::from_utf8_lossy(&page[..end])),
+    }
+}
+
+/// Labels of every compute node, sorted.
+fn node_labels(state: &ReplState) -> Vec<String> {
+    let mut labels: Vec<String> = state.nodes.keys().cloned().collect();
+    labels.sort();
+    labels
+}
+
+/// Start another compute node on the shared storage and switch to it.
+async fn add_node(
+    state: &mut ReplState,
+    label: &str,
+    prompt_str: &Mutex<String>,
+) -> CommandOutcome {
+    if state.nodes.contains_key(label) {
+        println!("Node {label} already exists");
+        return CommandOutcome::None;
+    }
+    let node = Arc::new(viz_node(&state.storage, &state.renderer, label, state.write_policy, state.heartbeat, state.batching));
+    let read_point = match node.refresh_read_point().await {
+        Ok(rp) => rp,
+        Err(e) => {
+            println!("Error: {}", explain(&e));
+            return CommandOutcome::None;
+        }
+    };
+    state.nodes.insert(label.to_string(), node);
+    state.current_node = label.to_string();
+    *prompt_str.lock().unwrap() = format!("{label}> ");
+    println!("Added Node {label} at read point L{read_point}; switched to it");
+    CommandOutcome::NodeSwitch
+}
+
+/// Ask the tutor for the next suggestions, given the cluster as it is now.
+async fn update_suggestions(state: &mut ReplState, outcome: &CommandOutcome) {
+    let snapshot = tutor_snapshot(state, outcome).await;
+    state.suggestions = state.tutor.suggest(&snapshot);
+}
+
+/// Pages whose chains are looked up for the tutor; beyond this the
+/// snapshot only has page stats.
+const TUTOR_HISTORY_PAGES: usize = 64;
+
+async fn tutor_snapshot(state: &ReplState, outcome: &CommandOutcome) -> Snapshot {
+    let last_page = match outcome {
+        CommandOutcome::Put { page_id }
+        | CommandOutcome::GetSuccess { page_id }
+        | CommandOutcome::GetFailure { page_id } => Some(*page_id),
+        _ => None,
+    };
+    let mut nodes = Vec::new();
+    for label in node_labels(state) {
+        let compute = &state.nodes[&label];
+        nodes.push(NodeSnapshot {
+            read_point: compute.read_point().await,
+            cached: compute.buffer_pool_entries().await.iter().map(|e| (e.page_id, e.read_point)).collect(),
+            label,
+        });
+    }
+    let vdl = state.storage.get_durability_state().await.ok().map(|d| d.vdl);
+    let mut pages = BTreeMap::new();
+    if vdl.is_some() {
+        for (i, (page_id, stats)) in state.storage.page_stats().into_iter().enumerate() {
+            let history = if i < TUTOR_HISTORY_PAGES {
+                state.storage.page_history(page_id).unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            pages.insert(page_id, PageSnapshot {
+                latest_lsn: history.first().map_or(0, |e| e.lsn),
+                chain_len: stats.chain_len,
+                cold_segment: history.iter().find_map(|e| match e.segment {
+                    Some((id, Tier::Cold)) => Some(id),
+                    _ => None,
+                }),
+            });
+        }
+    }
+    let mut workers: Vec<String> = state.workers.keys().cloned().collect();
+    workers.sort();
+    Snapshot {
+        current_node: state.current_node.clone(),
+        nodes,
+        vdl,
+        pages,
+        workers,
+        last_page,
     }
 }
 
 fn print_suggestions(state: &ReplState) {
-    if state.suggestions.is_empty() {
+    for (i, s) in state.suggestions.iter().enumerate() {
+        let pad = 24usize.saturating_sub(s.command.len());
+        println!("  [{}] {}{}\u{2014} {}", i + 1, s.command, " ".repeat(pad), s.why);
+    }
+}
+
+/// A viz-repl compute node with the REPL's write policy, heartbeats and
+/// put batching, sharing storage's memory budget and holding a reader lease.
+fn viz_node(
+    storage: &Arc<VizStorageEngine>,
+    renderer: &Arc<Mutex<VizRenderer>>,
+    label: &str,
+    write_policy: WritePolicy,
+    heartbeat: Option<HeartbeatConfig>,
+    batching: Option<WriteBatching>,
+) -> VizComputeEngine {
+    let mut node = VizComputeEngine::new(storage.clone(), 256, renderer.clone(), label.to_string())
+        .with_write_policy(write_policy)
+        .with_memory_budget()
+        .with_reader_lease(READER_LEASE);
+    if let Some(batching) = batching {
+        node = node.with_write_batching(batching);
+    }
+    match heartbeat {
+        Some(config) => node.with_heartbeat(config),
+        None => node,
+    }
+}
+
+/// `invariants [on|off]`: check the storage engine's invariants now, or
+/// after every operation from here on.
+fn run_invariants_command(
+    parts: &[&str],
+    checks_on: bool,
+    set_checks: impl FnOnce(bool),
+    violations: impl FnOnce() -> Result<Vec<String>, StorageError>,
+) {
+    match parts.get(1) {
+        Some(&"on") => {
+            set_checks(true);
+            println!("Checking storage invariants after every operation; a violation panics with a dump");
+        }
+        Some(&"off") => {
+            set_checks(false);
+            println!("Storage invariant checks off");
+        }
+        Some(_) => println!("Usage: invariants [on|off]"),
+        None => {
+            let checks = if checks_on { "on" } else { "off" };
+            match violations() {
+                Ok(violations) if violations.is_empty() => {
+                    println!("Storage invariants hold (checks after every operation: {checks})")
+                }
+                Ok(violations) => {
+                    println!("{} storage invariant violations:", violations.len());
+                    for violation in violations {
+                        println!("  - {violation}");
+                    }
+                }
+                Err(e) => println!("Error: {}", explain(&e)),
+            }
+        }
+    }
+}
+
+/// `promote [on|off]`: whether cold segments a read fetches are copied back
+/// to the hot tier.
+fn run_promote_command(parts: &[&str], storage: &VizStorageEngine, preset: &str) {
+    if preset != "tiered" {
+        println!("Promotion needs tiered storage (--preset tiered)");
         return;
     }
-    let descs = |cmd: &str| -> &str {
-        if cmd.starts_with("get ") { return "read the page"; }
-        if cmd.starts_with("put ") && cmd.contains("updated") { return "overwrite with new data"; }
-        if cmd.starts_with("put ") && cmd.contains("new-data") { return "overwrite with new data"; }
-        if cmd.starts_with("put ") && cmd.contains("Hello") { return "write data to the page"; }
-        if cmd.starts_with("put ") { return "write to a page"; }
-        if cmd == "refresh" { return "advance read_point to VDL"; }
-        if cmd.starts_with("node ") { return "switch compute node"; }
-        if cmd == "state" { return "show durability watermarks"; }
-        if cmd.starts_with("bg stop") { return "stop the background worker"; }
-        if cmd == "bg list" { return "show running workers"; }
-        if cmd.starts_with("bg ") { return "start background worker"; }
-        ""
+    match parts.get(1) {
+        Some(&"on") => {
+            storage.set_promote_on_read(true);
+            println!("Cold segments a read fetches are promoted back to the hot tier");
+        }
+        Some(&"off") => {
+            storage.set_promote_on_read(false);
+            println!("Cold segments stay cold when read");
+        }
+        Some(_) => println!("Usage: promote [on|off]"),
+        None => println!("Promotion on read: {}", if storage.promote_on_read() { "on" } else { "off" }),
+    }
+}
+
+/// `health`: what each node's heartbeats say about storage.
+fn print_health(state: &ReplState) {
+    let Some(config) = state.heartbeat else {
+        println!("Heartbeats are off (--heartbeat-ms 0)");
+        return;
     };
-    for (i, cmd) in state.suggestions.iter().enumerate() {
-        let desc = descs(cmd);
-        let pad = 24usize.saturating_sub(cmd.len());
-        if desc.is_empty() {
-            println!("  [{}] {}", i + 1, cmd);
-        } else {
-            println!("  [{}] {}{}\u{2014} {}", i + 1, cmd, " ".repeat(pad), desc);
+    println!(
+        "Heartbeat every {}ms, {}ms timeout, unhealthy after {} missed",
+        config.interval.as_millis(),
+        config.timeout.as_millis(),
+        config.max_missed
+    );
+    for label in node_labels(state) {
+        if let Some(health) = state.nodes[&label].storage_health() {
+            println!("  Node {label}: {}", format_health(&health));
+        }
+    }
+}
+
+fn format_health(h: &StorageHealth) -> String {
+    let status = if h.healthy { "healthy" } else { "UNHEALTHY" };
+    let mut line = format!("{status}, {} acked", h.acked);
+    if let Some(latency) = h.last_latency {
+        line.push_str(&format!(", last took {}", format_elapsed(latency)));
+    }
+    if let Some(d) = &h.durability {
+        line.push_str(&format!(", storage at VCL={} VDL={}", d.vcl, d.vdl));
+    }
+    if let Some(error) = &h.last_error {
+        line.push_str(&format!("; {} missed: {error}", h.missed));
+    }
+    line
+}
+
+/// Finish a Ctrl-C: stop the bg workers, close storage so killing the
+/// process now loses nothing, then redraw the panel and return to the prompt.
+async fn cancel_command(state: &mut ReplState) {
+    println!("\nInterrupted; rendering cancelled");
+    state.stop_workers().await;
+    report_close(state.storage.close());
+    state.renderer.lock().unwrap().take_cancel();
+}
+
+/// Tell the user whether the next start can skip WAL recovery.
+fn report_close(result: Result<(), StorageError>) {
+    match result {
+        Ok(()) => println!("Storage closed cleanly; the next start skips WAL recovery."),
+        Err(e) => println!("Storage not closed cleanly ({e}); the next start runs full recovery."),
+    }
+}
+
+/// Redraw the panel from storage's state and the current node's view.
+async fn emit_snapshot(state: &ReplState) {
+    state.nodes[&state.current_node].emit_state_snapshot().await;
+}
+
+/// `warmup [n]` and `--warmup`: preload up to `limit` of the pages hottest
+/// at the last clean shutdown into storage's page cache. Returns whether it
+/// succeeded.
+async fn run_warmup(admin: &dyn AdminApi, limit: usize) -> bool {
+    match admin.warmup(limit).await {
+        Ok(read) => {
+            println!("Warmed up the page cache with {read} hot page(s)");
+            true
+        }
+        Err(e) => {
+            println!("Error: {}", explain(&e));
+            false
+        }
+    }
+}
+
+/// `admin <op>`: run one `AdminApi` operation and print its result.
+/// Returns whether it succeeded.
+async fn run_admin_command(admin: &dyn AdminApi, parts: &[&str]) -> bool {
+    let result = match parts.get(1).copied() {
+        Some("checkpoint") => admin.checkpoint().await.map(|lsn| format!("Checkpoint at L{lsn}")),
+        Some("gc") => admin.gc().await.map(|r| {
+            if r.removed.is_empty() {
+                "No unreferenced files".to_string()
+            } else {
+                format!("Removed {} file(s), {} bytes:\n  {}", r.removed.len(), r.bytes_freed, r.removed.join("\n  "))
+            }
+        }),
+        Some("compact") => admin.compact().await.map(|r| {
+            if r.segments_before == r.segments_after {
+                "No adjacent cold segments to merge".to_string()
+            } else {
+                format!(
+                    "Merged cold segments: {} -> {} segments, {} bytes rewritten",
+                    r.segments_before, r.segments_after, r.bytes_rewritten
+                )
+            }
+        }),
+        Some("cool") => admin.cool().await.map(|cooled| {
+            if cooled.is_empty() {
+                "No sealed hot segments".to_string()
+            } else {
+                let ids: Vec<String> = cooled.iter().map(|id| format!("seg{id}")).collect();
+                format!("Cooled {}", ids.join(", "))
+            }
+        }),
+        Some("scrub") => admin.scrub().await.map(|r| {
+            let mut checked = format!("Scrubbed {} records ({} bytes)", r.records_checked, r.bytes_checked);
+            if r.segments_verified > 0 {
+                checked += &format!(", {} segments by footer", r.segments_verified);
+            }
+            if !r.damaged_segments.is_empty() {
+                let ids: Vec<String> = r.damaged_segments.iter().map(|id| format!("seg{id}")).collect();
+                checked += &format!(", damaged: {}", ids.join(", "));
+            }
+            if r.corrupt.is_empty() {
+                format!("{checked}: every CRC matches")
+            } else {
+                let lsns: Vec<String> = r.corrupt.iter().map(|lsn| format!("L{lsn}")).collect();
+                format!("{checked}: {} corrupt: {}", r.corrupt.len(), lsns.join(", "))
+            }
+        }),
+        Some("stats") => admin.stats().await.map(|stats| stats.to_string()),
+        Some("flush-cache") => admin.flush_cache().await.map(|n| format!("Dropped {n} page cache entries")),
+        Some("prefetch") => {
+            let Some(limit) = parts.get(2).map_or(Some(PREFETCH_PAGES), |n| n.parse().ok()) else {
+                println!("Usage: admin prefetch [pages]");
+                return false;
+            };
+            admin.prefetch(limit).await.map(|n| format!("Prefetched {n} hot page(s) into the page cache"))
+        }
+        Some("truncate") => {
+            let Some(lsn) = parts.get(2).and_then(|lsn| lsn.parse().ok()) else {
+                println!("Usage: admin truncate <lsn>");
+                return false;
+            };
+            admin.truncate_before(lsn).await.map(|r| {
+                let mut msg = format!(
+                    "History before L{}: {} version(s) and {} record(s) dropped",
+                    r.lsn, r.versions_dropped, r.records_dropped
+                );
+                if !r.segments_removed.is_empty() {
+                    let ids: Vec<String> = r.segments_removed.iter().map(|id| format!("seg{id}")).collect();
+                    msg += &format!(", deleted {} ({} bytes)", ids.join(", "), r.bytes_freed);
+                }
+                msg
+            })
+        }
+        _ => {
+            println!("Usage: admin {}", ADMIN_OPS.join("|"));
+            return false;
+        }
+    };
+    match result {
+        Ok(msg) => {
+            println!("{msg}");
+            true
+        }
+        Err(e) => {
+            println!("Error: {}", explain(&e));
+            false
+        }
+    }
+}
+
+/// `alerts`: what storage has alerted about, oldest first.
+fn print_alerts(alerts: &[Alert]) {
+    if alerts.is_empty() {
+        println!("No alerts");
+    }
+    for alert in alerts {
+        println!("{}  {:<10}  {}", format_utc(alert.timestamp), alert.kind, alert.message);
+    }
+}
+
+/// `qos`: show admission counters, or `qos limit <fg|bg|caller> <rate>
+/// [burst]|off` to change a rate limit.
+async fn run_qos_command(admin: &dyn AdminApi, parts: &[&str]) {
+    let result = match parts.get(1..).unwrap_or_default() {
+        [] => admin.qos().await.map(|report| report.to_string()),
+        ["limit", target, limit @ ..] => {
+            let Some(limit) = parse_rate_limit(limit) else {
+                println!("Usage: qos limit <fg|bg|caller> <rate> [burst] | off");
+                return;
+            };
+            let (target, name) = match *target {
+                "fg" => (QosTarget::Class(QosClass::Foreground), "Foreground class".to_string()),
+                "bg" => (QosTarget::Class(QosClass::Background), "Background class".to_string()),
+                caller => (QosTarget::Caller(caller.to_string()), format!("Caller {caller}")),
+            };
+            let shown = limit.map_or("unlimited".to_string(), |l| l.to_string());
+            admin.set_rate_limit(target, limit).await.map(|()| format!("{name}: {shown}"))
+        }
+        _ => {
+            println!("Usage: qos | qos limit <fg|bg|caller> <rate> [burst] | off");
+            return;
+        }
+    };
+    match result {
+        Ok(msg) => println!("{msg}"),
+        Err(e) => println!("Error: {}", explain(&e)),
+    }
+}
+
+/// `<rate> [burst]` or `off`; the burst defaults to one second's worth.
+fn parse_rate_limit(args: &[&str]) -> Option<Option<RateLimit>> {
+    let positive = |s: &str| s.parse::<u32>().ok().filter(|&n| n > 0);
+    match args {
+        ["off"] => Some(None),
+        [rate] => positive(rate).map(|rate| Some(RateLimit { rate, burst: rate })),
+        [rate, burst] => Some(Some(RateLimit { rate: positive(rate)?, burst: positive(burst)? })),
+        _ => None,
+    }
+}
+
+/// `crash storage`, `corrupt wal <bytes>|-<bytes>`, `corrupt cache <page>`
+/// and `recover`.
+async fn handle_fault_command(parts: &[&str], state: &mut ReplState) -> CommandOutcome {
+    match (parts[0], parts.get(1).copied()) {
+        ("crash", Some("storage")) => {
+            state.storage.crash();
+            state.record(ScenarioStep::CrashStorage);
+            println!("Storage crashed: page index, LSN offsets and page cache are gone.");
+            println!("Reads and writes fail until `recover` replays the WAL.");
+        }
+        ("corrupt", Some("wal")) => {
+            let arg = parts.get(2).copied().unwrap_or("");
+            let result = match arg.strip_prefix('-').map(str::parse::<u64>) {
+                Some(Ok(bytes)) => state.storage.truncate_wal(bytes).map(|(path, len)| {
+                    format!("Truncated {} to {len} bytes", path.display())
+                }),
+                Some(Err(_)) => {
+                    println!("Usage: corrupt wal <bytes>|-<bytes>");
+                    return CommandOutcome::None;
+                }
+                None => match arg.parse::<usize>() {
+                    Ok(bytes) => state.storage.append_wal_garbage(bytes).map(|path| {
+                        format!("Appended {bytes} garbage bytes to {}", path.display())
+                    }),
+                    Err(_) => {
+                        println!("Usage: corrupt wal <bytes>|-<bytes>");
+                        return CommandOutcome::None;
+                    }
+                },
+            };
+            match result {
+                Ok(msg) => println!("{msg}"),
+                Err(e) => println!("Error: {}", explain(&e)),
+            }
+        }
+        ("corrupt", Some("cache")) => {
+            let Some(page_id) = parts.get(2).and_then(|p| p.parse::<PageId>().ok()) else {
+                println!("Usage: corrupt cache <page>");
+                return CommandOutcome::None;
+            };
+            match state.storage.corrupt_cached_page(page_id) {
+                Ok(Some(version)) => println!(
+                    "Flipped a byte of page {page_id}'s cached image at L{version}; its checksum no longer matches"
+                ),
+                Ok(None) => println!("Page {page_id} isn't in storage's page cache; `get` it from a node first"),
+                Err(e) => println!("Error: {}", explain(&e)),
+            }
+        }
+        ("recover", _) => {
+            let before = state.storage.wal_len().unwrap_or(0);
+            match state.storage.restart() {
+                Ok(durability) => {
+                    state.record(ScenarioStep::RestartStorage);
+                    let after = state.storage.wal_len().unwrap_or(0);
+                    println!(
+                        "Recovered: VCL={} VDL={}; WAL {before}B -> {after}B ({} bytes truncated)",
+                        durability.vcl,
+                        durability.vdl,
+                        before.saturating_sub(after),
+                    );
+                    for label in node_labels(state) {
+                        let rp = state.nodes[&label].read_point().await;
+                        if rp > durability.vdl {
+                            println!("Node {label} read point L{rp} is past the recovered VDL; `refresh` on it to move back");
+                        }
+                    }
+                    emit_snapshot(state).await;
+                }
+                Err(e) => println!("Error: recovery failed: {e}"),
+            }
         }
+        ("crash", _) => println!("Usage: crash storage"),
+        _ => println!("Usage: corrupt wal <bytes>|-<bytes> | corrupt cache <page>"),
     }
+    CommandOutcome::None
 }
 
 async fn handle_bg_command(parts: &[&str], state: &mut ReplState) -> CommandOutcome {
@@ -775,7 +2161,7 @@ async fn handle_bg_command(parts: &[&str], state: &mut ReplState) -> CommandOutc
                 println!("No background workers running.");
             } else {
                 for (label, w) in &state.workers {
-                    println!("  Node {label}: {} every {}ms", w.kind, w.interval_ms);
+                    println!("  Node {label}: {} every {}ms{}", w.kind, w.interval_ms, w.options);
                 }
             }
             CommandOutcome::None
@@ -787,9 +2173,9 @@ async fn handle_bg_command(parts: &[&str], state: &mut ReplState) -> CommandOutc
             }
             let target = parts[2].to_uppercase();
             if let Some(handle) = state.workers.remove(&target) {
-                handle.cancel.cancel();
-                let _ = handle.task.await;
+                handle.stop().await;
                 println!("Stopped bg worker on Node {target}");
+                state.record(ScenarioStep::BgStop { node: target.clone() });
                 CommandOutcome::BgStopped { node: target }
             } else {
                 println!("No worker running on Node {target}");
@@ -797,9 +2183,9 @@ async fn handle_bg_command(parts: &[&str], state: &mut ReplState) -> CommandOutc
             }
         }
         _ => {
-            // bg <node> write|read|mixed <ms>
+            // bg <node> write|read|mixed <ms> [pages=a..b] [size=n] [dist=...]
             if parts.len() < 4 {
-                println!("Usage: bg <node> write|read|mixed <ms>");
+                println!("Usage: bg <node> write|read|mixed <ms> [pages=1..100] [size=64] [dist=uniform|zipf[:theta]]");
                 return CommandOutcome::None;
             }
             let target = parts[1].to_uppercase();
@@ -807,12 +2193,10 @@ async fn handle_bg_command(parts: &[&str], state: &mut ReplState) -> CommandOutc
                 println!("Unknown node: {target}. Available: A, B");
                 return CommandOutcome::None;
             }
-            let kind = match parts[2] {
-                "write" => WorkerKind::Write,
-                "read" => WorkerKind::Read,
-                "mixed" => WorkerKind::Mixed,
-                other => {
-                    println!("Unknown worker kind: {other}. Use write|read|mixed");
+            let kind = match WorkerKind::parse(parts[2]) {
+                Some(kind) => kind,
+                None => {
+                    println!("Unknown worker kind: {}. Use write|read|mixed", parts[2]);
                     return CommandOutcome::None;
                 }
             };
@@ -827,107 +2211,39 @@ async fn handle_bg_command(parts: &[&str], state: &mut ReplState) -> CommandOutc
                     return CommandOutcome::None;
                 }
             };
+            let words: Vec<&str> = parts.get(4).map_or_else(Vec::new, |rest| rest.split_whitespace().collect());
+            let options = match WorkerOptions::parse(&words) {
+                Ok(options) => options,
+                Err(e) => {
+                    println!("{e}");
+                    return CommandOutcome::None;
+                }
+            };
 
             // Stop existing worker on this node if any
             if let Some(handle) = state.workers.remove(&target) {
-                handle.cancel.cancel();
-                let _ = handle.task.await;
+                handle.stop().await;
                 println!("Stopped previous worker on Node {target}");
             }
 
-            let cancel = CancellationToken::new();
-            let bg_counter = state.bg_page_counter.clone();
-            let cancel_clone = cancel.clone();
-            let node_label = target.clone();
-            let renderer_for_bg = state.renderer.clone();
-            let bg_tx = state.bg_output_tx.clone();
-
-            // Non-viz ComputeEngine: shares storage but never touches the
-            // renderer at the compute level (no set_active, no render_op_header,
-            // no event emissions). Storage-level renders are suppressed via
-            // VizGuard so the storage mutex is held for microseconds, not seconds.
-            let storage_for_bg: Arc<dyn StorageApi> = state.storage.clone();
-            let bg_compute = ComputeEngine::new(storage_for_bg, 256);
-            {
-                let _guard = VizGuard::suppress(&state.renderer);
-                let _ = bg_compute.refresh_read_point().await;
-            }
-
-            let task = tokio::spawn(async move {
-                let mut cycle: u64 = 0;
-                loop {
-                    if cancel_clone.is_cancelled() {
-                        break;
-                    }
-
-                    // Scope the VizGuard so viz is restored before the sleep
-                    {
-                        let _guard = VizGuard::suppress(&renderer_for_bg);
-                        match kind {
-                            WorkerKind::Write => {
-                                let pg = bg_counter.fetch_add(1, Ordering::Relaxed);
-                                match bg_compute.put(pg, 0, format!("bg-{pg}").into_bytes()).await {
-                                    Ok(vdl) => { let _ = bg_tx.send(format!("[bg {node_label}] PUT pg{pg} OK (VDL={vdl})")); }
-                                    Err(e) => { let _ = bg_tx.send(format!("[bg {node_label}] PUT pg{pg} Error: {e}")); }
-                                }
-                            }
-                            WorkerKind::Read => {
-                                let pg = (cycle % 10) + 1;
-                                match bg_compute.get(pg).await {
-                                    Ok(page) => {
-                                        let end = page.iter().position(|&b| b == 0).unwrap_or(PAGE_SIZE);
-                                        let preview = if end == 0 {
-                                            "(empty)".to_string()
-                                        } else {
-                                            let s = String::from_utf8_lossy(&page[..end.min(20)]);
-                                            format!("{:?}", s)
-                                        };
-                                        let _ = bg_tx.send(format!("[bg {node_label}] GET pg{pg} -> {preview}"));
-                                    }
-                                    Err(e) => { let _ = bg_tx.send(format!("[bg {node_label}] GET pg{pg} Error: {e}")); }
-                                }
-                            }
-                            WorkerKind::Mixed => {
-                                if cycle % 2 == 0 {
-                                    match bg_compute.refresh_read_point().await {
-                                        Ok(rp) => { let _ = bg_tx.send(format!("[bg {node_label}] REFRESH -> rp={rp}")); }
-                                        Err(e) => { let _ = bg_tx.send(format!("[bg {node_label}] REFRESH Error: {e}")); }
-                                    }
-                                } else {
-                                    let pg = ((cycle / 2) % 10) + 1;
-                                    match bg_compute.get(pg).await {
-                                        Ok(page) => {
-                                            let end = page.iter().position(|&b| b == 0).unwrap_or(PAGE_SIZE);
-                                            let preview = if end == 0 {
-                                                "(empty)".to_string()
-                                            } else {
-                                                let s = String::from_utf8_lossy(&page[..end.min(20)]);
-                                                format!("{:?}", s)
-                                            };
-                                            let _ = bg_tx.send(format!("[bg {node_label}] GET pg{pg} -> {preview}"));
-                                        }
-                                        Err(e) => { let _ = bg_tx.send(format!("[bg {node_label}] GET pg{pg} Error: {e}")); }
-                                    }
-                                }
-                            }
-                        }
-                    } // _guard dropped: viz restored before sleep
-
-                    cycle += 1;
-                    tokio::select! {
-                        _ = cancel_clone.cancelled() => break,
-                        _ = tokio::time::sleep(Duration::from_millis(interval_ms)) => {}
-                    }
-                }
-            });
-
-            state.workers.insert(target.clone(), WorkerHandle {
-                cancel,
+            let handle = worker::spawn_worker(
+                target.clone(),
+                kind,
+                interval_ms,
+                options.clone(),
+                &state.worker_host,
+            ).await;
+            state.workers.insert(target.clone(), handle);
+            let (pages, size, dist) = options.to_fields();
+            state.record(ScenarioStep::Bg {
+                node: target.clone(),
                 kind,
                 interval_ms,
-                task,
+                pages,
+                size,
+                dist,
             });
-            println!("Started bg {kind} worker on Node {target} every {interval_ms}ms");
+            println!("Started bg {kind} worker on Node {target} every {interval_ms}ms{options}");
             CommandOutcome::BgStarted { node: target }
         }
     }
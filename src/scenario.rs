@@ -1,28 +1,46 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use serde::Deserialize;
+use mini_aurora_common::StorageStats;
+use mini_aurora_compute::workload::{KeyDistribution, WorkloadOp, WorkloadSpec, ZIPFIAN_THETA};
+use mini_aurora_storage::config::TierDisks;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
 
+use crate::data_dir::Session;
+use crate::EngineOptions;
 use crate::viz::compute::VizComputeEngine;
 use crate::viz::engine::VizStorageEngine;
-use crate::viz::events::VizConfig;
+use crate::viz::events::{EventFilter, VizConfig};
+use crate::viz::metrics::{format_elapsed, MetricsSummary, Op};
 use crate::viz::renderer::VizRenderer;
-use crate::viz::tracer::JsonTracer;
+use crate::viz::tracer::{TraceOptions, Tracer};
+use crate::worker::{self, WorkerHandle, WorkerHost, WorkerKind, WorkerOptions};
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Scenario {
+    /// Compute nodes to start; `["A", "B"]` when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<String>,
+    /// Optional for files that only exist to be included.
+    #[serde(default)]
     pub meta: ScenarioMeta,
+    /// Files whose steps run before this file's own, relative to this file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default)]
     pub steps: Vec<ScenarioStep>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct ScenarioMeta {
     pub name: String,
     pub description: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(tag = "op")]
 pub enum ScenarioStep {
     #[serde(rename = "put")]
@@ -31,15 +49,44 @@ pub enum ScenarioStep {
         offset: u16,
         data: String,
         node: Option<String>,
+        /// Keep going if the write fails. Without it, a failed operation
+        /// stops the scenario unless a nemesis fault is in effect.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        allow_error: bool,
+    },
+    /// Several writes in one mini-transaction: all of them become durable,
+    /// or none do.
+    #[serde(rename = "put_multi")]
+    PutMulti {
+        writes: Vec<PageWrite>,
+        node: Option<String>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        allow_error: bool,
+    },
+    /// Stage writes, savepoints and rollbacks to them on one
+    /// mini-transaction, then commit what is left.
+    #[serde(rename = "mtr")]
+    Mtr {
+        ops: Vec<MtrOp>,
+        node: Option<String>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        allow_error: bool,
     },
     #[serde(rename = "get")]
     Get {
         page_id: u64,
         node: Option<String>,
+        /// Assert the page reads back as this text (up to the first zero byte).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expect: Option<String>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        allow_error: bool,
     },
     #[serde(rename = "refresh")]
     Refresh {
         node: Option<String>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        allow_error: bool,
     },
     #[serde(rename = "sleep_ms")]
     SleepMs {
@@ -50,132 +97,988 @@ pub enum ScenarioStep {
         count: u64,
         steps: Vec<ScenarioStep>,
     },
+    #[serde(rename = "workload")]
+    Workload(WorkloadStep),
+    /// Start a background worker on `node`, replacing any already running.
+    /// `pages` (`"1..100"`), `size` and `dist` (`"zipf:1.1"`) shape the
+    /// load as in the REPL's `bg` command.
+    #[serde(rename = "bg")]
+    Bg {
+        node: String,
+        kind: WorkerKind,
+        interval_ms: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pages: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dist: Option<String>,
+    },
+    #[serde(rename = "bg_stop")]
+    BgStop {
+        node: String,
+    },
+    /// Splice in another file's steps here. Expanded by `load_scenario`.
+    #[serde(rename = "include")]
+    Include {
+        path: String,
+    },
+    /// Change the visualization for the steps that follow: start or stop
+    /// drawing, set the step delay, or filter the event log as `viz
+    /// filter` does (`"off"` clears it). Omitted fields are left as they
+    /// are.
+    #[serde(rename = "viz")]
+    Viz {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        enabled: Option<bool>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        step_delay_ms: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter: Option<String>,
+    },
+
+    // ── Nemesis (fault injection) ─────────────────────────────────────
+    /// Storage stops serving; in-memory state is lost.
+    #[serde(rename = "crash_storage")]
+    CrashStorage,
+    /// Re-run recovery from disk and resume serving.
+    #[serde(rename = "restart_storage")]
+    RestartStorage,
+    /// Flip the byte at `offset` of the WAL (active segment when tiered).
+    #[serde(rename = "corrupt_wal")]
+    CorruptWal {
+        offset: u64,
+    },
+    /// Cut the listed nodes off from storage.
+    #[serde(rename = "partition")]
+    Partition {
+        nodes: Vec<String>,
+    },
+    /// Cut `bytes` off the end of the WAL (active segment when tiered), as
+    /// a crash in the middle of a write would.
+    #[serde(rename = "truncate_wal")]
+    TruncateWal {
+        bytes: u64,
+    },
+    /// Undo every partition and injected latency.
+    #[serde(rename = "heal")]
+    Heal,
+    /// Delay storage round trips from the listed nodes (all when omitted).
+    #[serde(rename = "inject_latency")]
+    InjectLatency {
+        ms: u64,
+        nodes: Option<Vec<String>>,
+    },
 }
 
-/// Run a scenario from the CLI.
+impl ScenarioStep {
+    /// The step's `op` tag, as written in the scenario file.
+    fn op_name(&self) -> &'static str {
+        match self {
+            ScenarioStep::Put { .. } => "put",
+            ScenarioStep::PutMulti { .. } => "put_multi",
+            ScenarioStep::Mtr { .. } => "mtr",
+            ScenarioStep::Get { .. } => "get",
+            ScenarioStep::Refresh { .. } => "refresh",
+            ScenarioStep::SleepMs { .. } => "sleep_ms",
+            ScenarioStep::Repeat { .. } => "repeat",
+            ScenarioStep::Workload(_) => "workload",
+            ScenarioStep::Bg { .. } => "bg",
+            ScenarioStep::BgStop { .. } => "bg_stop",
+            ScenarioStep::Include { .. } => "include",
+            ScenarioStep::Viz { .. } => "viz",
+            ScenarioStep::CrashStorage => "crash_storage",
+            ScenarioStep::RestartStorage => "restart_storage",
+            ScenarioStep::CorruptWal { .. } => "corrupt_wal",
+            ScenarioStep::TruncateWal { .. } => "truncate_wal",
+            ScenarioStep::Partition { .. } => "partition",
+            ScenarioStep::Heal => "heal",
+            ScenarioStep::InjectLatency { .. } => "inject_latency",
+        }
+    }
+
+    /// The node a step targets, if it targets exactly one.
+    pub(crate) fn target_node<'a>(&'a self, default_node: &'a str) -> Option<&'a str> {
+        match self {
+            ScenarioStep::Put { node, .. }
+            | ScenarioStep::PutMulti { node, .. }
+            | ScenarioStep::Mtr { node, .. }
+            | ScenarioStep::Get { node, .. }
+            | ScenarioStep::Refresh { node, .. } => Some(node.as_deref().unwrap_or(default_node)),
+            ScenarioStep::Workload(step) => Some(step.node.as_deref().unwrap_or(default_node)),
+            ScenarioStep::Bg { node, .. } | ScenarioStep::BgStop { node } => Some(node),
+            _ => None,
+        }
+    }
+
+    /// Whether the step may fail without stopping the scenario.
+    fn allows_error(&self) -> bool {
+        match self {
+            ScenarioStep::Put { allow_error, .. }
+            | ScenarioStep::PutMulti { allow_error, .. }
+            | ScenarioStep::Mtr { allow_error, .. }
+            | ScenarioStep::Get { allow_error, .. }
+            | ScenarioStep::Refresh { allow_error, .. } => *allow_error,
+            _ => false,
+        }
+    }
+}
+
+/// One write of a `put_multi` step.
+#[derive(Serialize, Deserialize)]
+pub struct PageWrite {
+    pub page_id: u64,
+    #[serde(default)]
+    pub offset: u16,
+    pub data: String,
+}
+
+/// One operation of an `mtr` step. Savepoints are named; rolling back to
+/// one drops the writes staged after it.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub enum MtrOp {
+    #[serde(rename = "write")]
+    Write(PageWrite),
+    #[serde(rename = "savepoint")]
+    Savepoint { name: String },
+    #[serde(rename = "rollback_to")]
+    RollbackTo { name: String },
+}
+
+/// A YCSB-style workload step. Unset fields fall back to the `preset`
+/// (`a`, `b` or `c`), or to `WorkloadSpec::default()` without one.
+#[derive(Serialize, Deserialize)]
+pub struct WorkloadStep {
+    pub preset: Option<String>,
+    pub records: Option<u64>,
+    pub operations: Option<u64>,
+    pub read_proportion: Option<f64>,
+    /// `uniform` or `zipfian`.
+    pub distribution: Option<String>,
+    pub zipfian_theta: Option<f64>,
+    pub target_ops_per_sec: Option<u64>,
+    pub value_size: Option<usize>,
+    pub seed: Option<u64>,
+    /// Write every record once before the timed run (YCSB's load phase).
+    #[serde(default = "default_true")]
+    pub load: bool,
+    pub node: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl WorkloadStep {
+    fn spec(&self) -> anyhow::Result<WorkloadSpec> {
+        let mut spec = match &self.preset {
+            Some(name) => WorkloadSpec::preset(name)
+                .ok_or_else(|| anyhow::anyhow!("Unknown workload preset: {name} (expected a, b or c)"))?,
+            None => WorkloadSpec::default(),
+        };
+        if let Some(records) = self.records {
+            spec.record_count = records;
+        }
+        if let Some(operations) = self.operations {
+            spec.operation_count = operations;
+        }
+        if let Some(read_proportion) = self.read_proportion {
+            spec.read_proportion = read_proportion;
+        }
+        let theta = self.zipfian_theta.unwrap_or(ZIPFIAN_THETA);
+        spec.distribution = match self.distribution.as_deref() {
+            None | Some("zipfian") => KeyDistribution::Zipfian { theta },
+            Some("uniform") => KeyDistribution::Uniform,
+            Some(other) => anyhow::bail!("Unknown key distribution: {other} (expected uniform or zipfian)"),
+        };
+        if self.target_ops_per_sec.is_some() {
+            spec.target_ops_per_sec = self.target_ops_per_sec;
+        }
+        if let Some(value_size) = self.value_size {
+            spec.value_size = value_size;
+        }
+        if let Some(seed) = self.seed {
+            spec.seed = seed;
+        }
+        Ok(spec)
+    }
+}
+
+/// Load a scenario file, splicing in the steps of every file it includes.
+/// Include paths are relative to the including file; cycles are an error.
+pub fn load_scenario(path: &Path) -> anyhow::Result<Scenario> {
+    let mut scenario = load_with_includes(path, &mut Vec::new())?;
+    if scenario.meta.name.is_empty() {
+        scenario.meta.name = path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+    Ok(scenario)
+}
+
+/// `stack` holds the canonical paths of the files currently being loaded.
+fn load_with_includes(path: &Path, stack: &mut Vec<PathBuf>) -> anyhow::Result<Scenario> {
+    let canonical = path.canonicalize()
+        .with_context(|| format!("Cannot open scenario {}", path.display()))?;
+    if let Some(pos) = stack.iter().position(|p| *p == canonical) {
+        let cycle: Vec<String> = stack[pos..].iter().chain([&canonical])
+            .map(|p| p.display().to_string())
+            .collect();
+        anyhow::bail!("Include cycle: {}", cycle.join(" -> "));
+    }
+
+    let toml_content = std::fs::read_to_string(&canonical)?;
+    let mut scenario: Scenario = toml::from_str(&toml_content)
+        .with_context(|| format!("Invalid scenario {}", path.display()))?;
+    let base_dir = canonical.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    stack.push(canonical);
+    let mut steps = Vec::new();
+    for include in std::mem::take(&mut scenario.include) {
+        steps.extend(load_with_includes(&base_dir.join(include), stack)?.steps);
+    }
+    steps.extend(expand_includes(std::mem::take(&mut scenario.steps), &base_dir, stack)?);
+    stack.pop();
+
+    scenario.steps = steps;
+    Ok(scenario)
+}
+
+/// Replace `include` steps (including those inside `repeat`) with the
+/// included file's steps.
+fn expand_includes(
+    steps: Vec<ScenarioStep>,
+    base_dir: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> anyhow::Result<Vec<ScenarioStep>> {
+    let mut expanded = Vec::with_capacity(steps.len());
+    for step in steps {
+        match step {
+            ScenarioStep::Include { path } => {
+                expanded.extend(load_with_includes(&base_dir.join(path), stack)?.steps);
+            }
+            ScenarioStep::Repeat { count, steps } => expanded.push(ScenarioStep::Repeat {
+                count,
+                steps: expand_includes(steps, base_dir, stack)?,
+            }),
+            other => expanded.push(other),
+        }
+    }
+    Ok(expanded)
+}
+
+/// Captures REPL commands as scenario steps, for `record` / `stoprecord`.
+pub struct ScenarioRecorder {
+    path: PathBuf,
+    steps: Vec<ScenarioStep>,
+    last_step: Instant,
+    /// Nodes with a recorded bg worker still running.
+    active_bg: Vec<String>,
+}
+
+impl ScenarioRecorder {
+    /// Start recording to `path`. The file is created now so a bad path
+    /// fails immediately rather than at `stoprecord`.
+    pub fn start(path: PathBuf) -> anyhow::Result<Self> {
+        std::fs::File::create(&path)
+            .with_context(|| format!("Cannot create {}", path.display()))?;
+        Ok(Self {
+            path,
+            steps: Vec::new(),
+            last_step: Instant::now(),
+            active_bg: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a step. While bg workers are running, the think time since
+    /// the previous step is kept as a `sleep_ms` so replay gives the
+    /// workers the same amount of time.
+    pub fn push(&mut self, step: ScenarioStep) {
+        let gap = self.last_step.elapsed().as_millis() as u64;
+        self.last_step = Instant::now();
+        if !self.active_bg.is_empty() && gap > 0 {
+            self.steps.push(ScenarioStep::SleepMs { value: gap });
+        }
+        match &step {
+            ScenarioStep::Bg { node, .. } if !self.active_bg.contains(node) => {
+                self.active_bg.push(node.clone());
+            }
+            ScenarioStep::BgStop { node } => self.active_bg.retain(|n| n != node),
+            _ => {}
+        }
+        self.steps.push(step);
+    }
+
+    /// Write the recorded steps as a scenario file, declaring `nodes` when
+    /// the session used more than the default pair. Returns the step count.
+    pub fn finish(self, nodes: &[String]) -> anyhow::Result<usize> {
+        let name = self.path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "recorded".to_string());
+        let nodes = if nodes == DEFAULT_NODES { Vec::new() } else { nodes.to_vec() };
+        let scenario = Scenario {
+            nodes,
+            meta: ScenarioMeta {
+                name,
+                description: Some("Recorded viz-repl session".to_string()),
+            },
+            include: Vec::new(),
+            steps: self.steps,
+        };
+        let count = scenario.steps.len();
+        std::fs::write(&self.path, toml::to_string(&scenario)?)
+            .with_context(|| format!("Cannot write {}", self.path.display()))?;
+        Ok(count)
+    }
+}
+
+/// Nodes started when a scenario doesn't list its own.
+const DEFAULT_NODES: [&str; 2] = ["A", "B"];
+
+/// Run a scenario from the CLI, on storage opened as `engine` says.
 pub async fn run_scenario_cli(
+    session: &Session,
     scenario_path: &str,
-    preset: &str,
-    trace_json: Option<&str>,
-    segment_size: u64,
-    cold_latency_ms: u64,
+    report: Option<&str>,
+    engine: &EngineOptions,
+    trace: &TraceOptions,
+    color: bool,
 ) -> anyhow::Result<()> {
-    let toml_content = std::fs::read_to_string(scenario_path)?;
-    let scenario: Scenario = toml::from_str(&toml_content)?;
+    let scenario = load_scenario(Path::new(scenario_path))?;
+    let EngineOptions { ref preset, segment_size, disks, .. } = *engine;
 
     println!("=== Scenario: {} (preset: {preset}) ===", scenario.meta.name);
     if let Some(ref desc) = scenario.meta.description {
         println!("{desc}");
     }
 
+    let mut run = run_headless(session, &scenario, preset, trace, segment_size, disks, Some(color)).await?;
+    let assertions_failed = run.assertions_failed();
+    let errors = run.errors();
+    if let Some(path) = report {
+        run.report(&scenario.meta.name, preset).write(Path::new(path))?;
+        println!("Report written to: {path}");
+    }
+    run.result?;
+
+    // Print final metrics
+    if let Some(summary) = run.metrics {
+        println!("\n=== Metrics ===");
+        println!("{summary}");
+    }
+    println!("\n=== Storage ===");
+    println!("{}", run.storage);
+
+    if errors > 0 {
+        anyhow::bail!("{errors} step(s) failed with an unexpected error");
+    }
+    if assertions_failed > 0 {
+        anyhow::bail!("{assertions_failed} assertion(s) failed");
+    }
+    println!("\nScenario complete.");
+    Ok(())
+}
+
+/// Run the same scenario against two presets, each on a fresh volume in
+/// its own session, and print their metrics side by side.
+pub async fn run_compare_cli(
+    sessions: [&Session; 2],
+    scenario_path: &str,
+    presets: [&str; 2],
+    segment_size: u64,
+    disks: TierDisks,
+) -> anyhow::Result<()> {
+    let scenario = load_scenario(Path::new(scenario_path))?;
+    println!("=== Compare: {} ({} vs {}) ===", scenario.meta.name, presets[0], presets[1]);
+
+    let mut runs = Vec::with_capacity(2);
+    for (session, preset) in sessions.into_iter().zip(presets) {
+        println!("\n--- Running on {preset} ---");
+        // Both runs would write the same trace file, so neither traces, and
+        // neither draws
+        let trace = TraceOptions::default();
+        let mut run = run_headless(session, &scenario, preset, &trace, segment_size, disks, None).await?;
+        std::mem::replace(&mut run.result, Ok(())).with_context(|| format!("{preset} run failed"))?;
+        runs.push(run);
+    }
+
+    println!();
+    print!("{}", MetricsDiff::new(presets, &runs[0], &runs[1]));
+    Ok(())
+}
+
+/// What a headless run of a scenario left behind.
+struct ScenarioRun {
+    /// Whether the steps ran to the end; `Failed` assertions don't stop them.
+    result: anyhow::Result<()>,
+    steps: Vec<StepResult>,
+    duration_ms: f64,
+    storage: StorageStats,
+    metrics: Option<MetricsSummary>,
+}
+
+impl ScenarioRun {
+    fn assertions_failed(&self) -> usize {
+        self.steps.iter().filter(|step| matches!(step.outcome, StepOutcome::Failed(_))).count()
+    }
+
+    /// Steps whose operation failed with no fault in effect and no
+    /// `allow_error`.
+    fn errors(&self) -> usize {
+        self.steps.iter().filter(|step| matches!(step.outcome, StepOutcome::Error(_))).count()
+    }
+
+    /// The `--report` for this run, taking its step results.
+    fn report(&mut self, scenario: &str, preset: &str) -> ScenarioReport {
+        let (assertions_failed, errors) = (self.assertions_failed(), self.errors());
+        ScenarioReport {
+            scenario: scenario.to_string(),
+            preset: preset.to_string(),
+            passed: self.result.is_ok() && assertions_failed == 0 && errors == 0,
+            steps_run: self.steps.len(),
+            assertions_failed,
+            errors,
+            duration_ms: self.duration_ms,
+            storage: self.storage.clone(),
+            steps: std::mem::take(&mut self.steps),
+        }
+    }
+}
+
+/// Run `scenario` on `preset` in `session` with rendering off. With `viz`,
+/// its `viz` steps can turn drawing on, in color if `viz` is `Some(true)`;
+/// without, they are skipped.
+async fn run_headless(
+    session: &Session,
+    scenario: &Scenario,
+    preset: &str,
+    trace: &TraceOptions,
+    segment_size: u64,
+    disks: TierDisks,
+    viz: Option<bool>,
+) -> anyhow::Result<ScenarioRun> {
     // Set up viz infrastructure with rendering disabled (headless)
     let config = VizConfig {
         step_delay: Duration::ZERO,
-        color: false,
+        color: viz.unwrap_or(false),
         enabled: false,
+        step: false,
+        lanes: false,
+        metrics_strip: false,
     };
     let mut renderer_inner = VizRenderer::new(config);
-    if let Some(path) = trace_json {
-        let tracer = JsonTracer::open(Path::new(path))?;
+    if trace.json.is_some() || trace.binary.is_some() {
+        let mut tracer = Tracer::new();
+        trace.add_sinks(&mut tracer)?;
         renderer_inner.set_tracer(tracer);
-        println!("Tracing events to: {path}");
     }
     let renderer = Arc::new(Mutex::new(renderer_inner));
 
-    let storage: Arc<VizStorageEngine> = match preset {
+    let storage = open_storage(session, preset, segment_size, disks, &renderer)?;
+
+    let labels: Vec<String> = if scenario.nodes.is_empty() {
+        DEFAULT_NODES.iter().map(|l| l.to_string()).collect()
+    } else {
+        scenario.nodes.iter().map(|l| l.to_uppercase()).collect()
+    };
+
+    // Background workers report through a channel, like in the viz REPL
+    let (bg_output_tx, mut bg_output_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let bg_printer = tokio::spawn(async move {
+        while let Some(msg) = bg_output_rx.recv().await {
+            println!("  {msg}");
+        }
+    });
+
+    let mut cluster = Cluster::start(storage, &labels, renderer.clone(), bg_output_tx).await?;
+    cluster.viz_steps = viz.is_some();
+
+    let started = Instant::now();
+    let result = execute_steps(&scenario.steps, &cluster).await;
+    let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+    cluster.stop_workers().await;
+    let steps = std::mem::take(&mut *cluster.results.lock().unwrap());
+    let storage = cluster.storage.stats();
+    drop(cluster);
+    let _ = bg_printer.await;
+
+    let metrics = renderer.lock().unwrap().metrics_summary();
+    Ok(ScenarioRun { result, steps, duration_ms, storage, metrics })
+}
+
+/// Two runs' metrics side by side, with how the second differs from the
+/// first: a relative change for counts and times, points for hit rates.
+struct MetricsDiff {
+    presets: [String; 2],
+    rows: Vec<[String; 4]>,
+}
+
+impl MetricsDiff {
+    fn new(presets: [&str; 2], a: &ScenarioRun, b: &ScenarioRun) -> Self {
+        let mut rows = Vec::new();
+        let mut count = |metric: &str, a: u64, b: u64, show: &dyn Fn(u64) -> String| {
+            rows.push([metric.to_string(), show(a), show(b), relative_change(a as f64, b as f64)]);
+        };
+        let ms = |ms: u64| format!("{ms}ms");
+        count("wall time", a.duration_ms as u64, b.duration_ms as u64, &ms);
+
+        let empty = MetricsSummary::default();
+        let (ma, mb) = (a.metrics.as_ref().unwrap_or(&empty), b.metrics.as_ref().unwrap_or(&empty));
+        let us = |us: u64| format_elapsed(Duration::from_micros(us));
+        for op in [Op::Put, Op::Get] {
+            let (la, lb) = (ma.latency(op), mb.latency(op));
+            if la.count() + lb.count() == 0 {
+                continue;
+            }
+            for (name, q) in [("p50", 0.50), ("p99", 0.99)] {
+                count(&format!("{op} {name}"), la.percentile_us(q), lb.percentile_us(q), &us);
+            }
+        }
+        let kb = |bytes: u64| format!("{:.1} KB", bytes as f64 / 1024.0);
+        count("WAL written", ma.wal_bytes_written, mb.wal_bytes_written, &kb);
+        let plain = |n: u64| n.to_string();
+        count("cold reads", ma.cold_reads, mb.cold_reads, &plain);
+        count("materializations", ma.materialize_count, mb.materialize_count, &plain);
+        count("cold segments", a.storage.cold.segments as u64, b.storage.cold.segments as u64, &plain);
+
+        let mut pct = |metric: &str, a: u64, b: u64| {
+            let change = if a == b { "=".to_string() } else { format!("{:+} pts", b as i64 - a as i64) };
+            rows.push([metric.to_string(), format!("{a}%"), format!("{b}%"), change]);
+        };
+        pct("page cache hits", ma.cache_hit_pct(), mb.cache_hit_pct());
+        pct("buffer pool hits", ma.buffer_pool_hit_pct(), mb.buffer_pool_hit_pct());
+
+        let failed = (a.assertions_failed() as u64, b.assertions_failed() as u64);
+        if failed != (0, 0) {
+            rows.push([
+                "failed asserts".to_string(),
+                failed.0.to_string(),
+                failed.1.to_string(),
+                relative_change(failed.0 as f64, failed.1 as f64),
+            ]);
+        }
+        Self { presets: presets.map(str::to_string), rows }
+    }
+}
+
+impl std::fmt::Display for MetricsDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = |col: usize, header: &str| {
+            let cells = self.rows.iter().map(|row| row[col].chars().count());
+            cells.chain([header.chars().count()]).max().unwrap_or(0)
+        };
+        let headers = ["metric", self.presets[0].as_str(), self.presets[1].as_str(), "change"];
+        let widths: Vec<usize> = headers.iter().enumerate().map(|(col, h)| width(col, h)).collect();
+        let line = |f: &mut std::fmt::Formatter<'_>, row: [&str; 4]| {
+            writeln!(
+                f,
+                "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}",
+                row[0], row[1], row[2], row[3],
+                w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3],
+            )
+        };
+        line(f, headers)?;
+        for row in &self.rows {
+            line(f, [&row[0], &row[1], &row[2], &row[3]])?;
+        }
+        Ok(())
+    }
+}
+
+/// How far `b` is from `a`, as a signed percentage.
+fn relative_change(a: f64, b: f64) -> String {
+    if a == b {
+        "=".to_string()
+    } else if a == 0.0 {
+        "new".to_string()
+    } else {
+        format!("{:+.0}%", (b - a) / a * 100.0)
+    }
+}
+
+/// Open the storage engine for `preset` in `session`.
+pub(crate) fn open_storage(
+    session: &Session,
+    preset: &str,
+    segment_size: u64,
+    disks: TierDisks,
+    renderer: &Arc<Mutex<VizRenderer>>,
+) -> anyhow::Result<Arc<VizStorageEngine>> {
+    let storage = match preset {
         "tiered" => {
-            let base_dir = PathBuf::from("/tmp/mini-aurora-scenario-tiered");
-            let _ = std::fs::remove_dir_all(&base_dir);
-            let cold_latency = Duration::from_millis(cold_latency_ms);
-            println!("Tiered storage: segment_size={segment_size}B, cold_latency={cold_latency_ms}ms");
-            Arc::new(VizStorageEngine::open_tiered(&base_dir, segment_size, cold_latency, renderer.clone())?)
+            let base_dir = session.tiered_dir();
+            println!("Tiered storage: segment_size={segment_size}B, hot disk: {}, cold disk: {}", disks.hot, disks.cold);
+            VizStorageEngine::open_tiered(&base_dir, segment_size, disks, None, renderer.clone())?
         }
-        _ => {
-            let wal_path = PathBuf::from("/tmp/mini-aurora-scenario.wal");
-            let _ = std::fs::remove_file(&wal_path);
-            Arc::new(VizStorageEngine::open(&wal_path, renderer.clone())?)
+        "memory" => {
+            println!("In-memory storage: nothing is written to disk");
+            VizStorageEngine::open_in_memory(None, renderer.clone())?
         }
+        _ => VizStorageEngine::open(&session.wal_path(), None, renderer.clone())?,
     };
+    Ok(Arc::new(storage))
+}
 
-    let node_a = Arc::new(VizComputeEngine::new(
-        storage.clone(), 256, renderer.clone(), "A".to_string(),
-    ));
-    let node_b = Arc::new(VizComputeEngine::new(
-        storage.clone(), 256, renderer.clone(), "B".to_string(),
-    ));
+/// How a single step ended up.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", content = "message", rename_all = "lowercase")]
+pub(crate) enum StepOutcome {
+    Ok,
+    /// The operation returned an error while a nemesis fault was in
+    /// effect, or on a step with `allow_error`. Not a failure.
+    #[serde(rename = "expected_error")]
+    Expected(String),
+    /// The step failed otherwise, which stops the scenario.
+    Error(String),
+    /// An `expect` assertion did not hold.
+    Failed(String),
+}
 
-    node_a.refresh_read_point().await?;
-    node_b.refresh_read_point().await?;
+#[derive(Serialize)]
+struct StepResult {
+    /// Execution order, counting every step run inside `repeat`s.
+    index: usize,
+    op: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node: Option<String>,
+    #[serde(flatten)]
+    outcome: StepOutcome,
+    duration_ms: f64,
+}
 
-    let nodes: std::collections::HashMap<String, Arc<VizComputeEngine>> = [
-        ("A".to_string(), node_a),
-        ("B".to_string(), node_b),
-    ].into();
+/// Machine-readable scenario results, written by `--report`.
+#[derive(Serialize)]
+struct ScenarioReport {
+    scenario: String,
+    preset: String,
+    passed: bool,
+    steps_run: usize,
+    assertions_failed: usize,
+    errors: usize,
+    duration_ms: f64,
+    storage: StorageStats,
+    steps: Vec<StepResult>,
+}
 
-    let default_node = "A".to_string();
+impl ScenarioReport {
+    /// Write as JUnit XML if `path` ends in `.xml`, JSON otherwise.
+    fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let body = if path.extension().is_some_and(|ext| ext == "xml") {
+            self.to_junit()
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        std::fs::write(path, body).with_context(|| format!("Cannot write report {}", path.display()))
+    }
 
-    execute_steps(&scenario.steps, &nodes, &default_node).await?;
+    /// One `<testcase>` per step. Assertion failures are JUnit failures and
+    /// unexpected errors JUnit errors; expected errors are attached as
+    /// `<system-out>`.
+    fn to_junit(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+            xml_escape(&self.scenario),
+            self.steps_run,
+            self.assertions_failed,
+            self.errors,
+            self.duration_ms / 1000.0,
+        ));
+        for step in &self.steps {
+            let name = match &step.node {
+                Some(node) => format!("{:03} {} [{node}]", step.index, step.op),
+                None => format!("{:03} {}", step.index, step.op),
+            };
+            xml.push_str(&format!(
+                "  <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                xml_escape(&self.scenario),
+                xml_escape(&name),
+                step.duration_ms / 1000.0,
+            ));
+            match &step.outcome {
+                StepOutcome::Ok => xml.push_str("/>\n"),
+                StepOutcome::Expected(msg) => xml.push_str(&format!(
+                    ">\n    <system-out>{}</system-out>\n  </testcase>\n",
+                    xml_escape(msg),
+                )),
+                StepOutcome::Error(msg) => xml.push_str(&format!(
+                    ">\n    <error message=\"{}\"/>\n  </testcase>\n",
+                    xml_escape(msg),
+                )),
+                StepOutcome::Failed(msg) => xml.push_str(&format!(
+                    ">\n    <failure message=\"{}\"/>\n  </testcase>\n",
+                    xml_escape(msg),
+                )),
+            }
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+}
 
-    // Print final metrics
-    let r = renderer.lock().unwrap();
-    if let Some(summary) = r.metrics_summary() {
-        println!("\n=== Metrics ===");
-        println!("{summary}");
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Nemesis faults in effect, under which operations are expected to fail.
+#[derive(Default)]
+struct Faults {
+    /// Storage crashed or its WAL was damaged, and it hasn't restarted since.
+    storage: bool,
+    /// Nodes partitioned or slowed down since the last `heal`.
+    nodes: Vec<String>,
+}
+
+impl Faults {
+    fn affect(&self, node: &str) -> bool {
+        self.storage || self.nodes.iter().any(|n| n == node)
     }
 
-    println!("\nScenario complete.");
-    Ok(())
+    fn impair(&mut self, node: &str) {
+        if !self.nodes.iter().any(|n| n == node) {
+            self.nodes.push(node.to_string());
+        }
+    }
 }
 
-fn execute_steps<'a>(
+/// The storage engine and compute nodes a scenario runs against.
+pub(crate) struct Cluster {
+    pub(crate) storage: Arc<VizStorageEngine>,
+    pub(crate) nodes: HashMap<String, Arc<VizComputeEngine>>,
+    default_node: String,
+    renderer: Arc<Mutex<VizRenderer>>,
+    workers: Mutex<HashMap<String, WorkerHandle>>,
+    worker_host: WorkerHost,
+    results: Mutex<Vec<StepResult>>,
+    faults: Mutex<Faults>,
+    /// Whether `viz` steps change the renderer; a comparison skips them so
+    /// drawing doesn't count in its timings.
+    viz_steps: bool,
+}
+
+impl Cluster {
+    /// Start a compute node per label on `storage`, each reading at the
+    /// current VDL. The first label is the default node.
+    pub(crate) async fn start(
+        storage: Arc<VizStorageEngine>,
+        labels: &[String],
+        renderer: Arc<Mutex<VizRenderer>>,
+        bg_output_tx: tokio::sync::mpsc::UnboundedSender<String>,
+    ) -> anyhow::Result<Self> {
+        let mut nodes = HashMap::new();
+        for label in labels {
+            let node = Arc::new(VizComputeEngine::new(
+                storage.clone(), 256, renderer.clone(), label.clone(),
+            ));
+            node.refresh_read_point().await?;
+            nodes.insert(label.clone(), node);
+        }
+        Ok(Self {
+            worker_host: WorkerHost::new(storage.clone(), renderer.clone(), bg_output_tx),
+            storage,
+            nodes,
+            default_node: labels[0].clone(),
+            renderer,
+            workers: Mutex::new(HashMap::new()),
+            results: Mutex::new(Vec::new()),
+            faults: Mutex::default(),
+            viz_steps: true,
+        })
+    }
+
+    /// Resolve a step's `node` field (or the default node) to its engine.
+    fn node<'a>(&'a self, node: &'a Option<String>) -> anyhow::Result<(&'a str, &'a Arc<VizComputeEngine>)> {
+        let node_key = node.as_deref().unwrap_or(&self.default_node);
+        let compute = self.nodes.get(node_key)
+            .ok_or_else(|| anyhow::anyhow!("Unknown node: {node_key}"))?;
+        Ok((node_key, compute))
+    }
+
+    /// An operation of `step` on `node` failed with `error`: expected while a
+    /// nemesis fault affects the node or if the step allows it, and fatal
+    /// otherwise.
+    fn op_failed(&self, step: &ScenarioStep, node: &str, error: String) -> anyhow::Result<StepOutcome> {
+        if step.allows_error() || self.faults.lock().unwrap().affect(node) {
+            return Ok(StepOutcome::Expected(error));
+        }
+        anyhow::bail!("{} on node {node} failed with no fault in effect: {error}", step.op_name())
+    }
+
+    async fn stop_worker(&self, node: &str) -> bool {
+        let handle = self.workers.lock().unwrap().remove(node);
+        match handle {
+            Some(handle) => {
+                handle.stop().await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) async fn stop_workers(&self) {
+        let handles: Vec<_> = self.workers.lock().unwrap().drain().collect();
+        for (label, handle) in handles {
+            handle.stop().await;
+            println!("  stopped bg worker on node {label}");
+        }
+    }
+
+    /// Resolve a list of node labels; `None` means every node.
+    fn node_set(&self, nodes: Option<&[String]>) -> anyhow::Result<Vec<(&str, &Arc<VizComputeEngine>)>> {
+        match nodes {
+            Some(labels) => labels
+                .iter()
+                .map(|label| {
+                    let (key, compute) = self.nodes.get_key_value(label.as_str())
+                        .ok_or_else(|| anyhow::anyhow!("Unknown node: {label}"))?;
+                    Ok((key.as_str(), compute))
+                })
+                .collect(),
+            None => {
+                let mut all: Vec<_> = self.nodes.iter().map(|(k, v)| (k.as_str(), v)).collect();
+                all.sort_by_key(|(k, _)| *k);
+                Ok(all)
+            }
+        }
+    }
+}
+
+pub(crate) fn execute_steps<'a>(
     steps: &'a [ScenarioStep],
-    nodes: &'a std::collections::HashMap<String, Arc<VizComputeEngine>>,
-    default_node: &'a str,
+    cluster: &'a Cluster,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + 'a>> {
     Box::pin(async move {
         for step in steps {
-            execute_step(step, nodes, default_node).await?;
+            // `repeat` is a container: its inner steps are reported instead
+            if let ScenarioStep::Repeat { .. } = step {
+                execute_step(step, cluster).await?;
+                continue;
+            }
+            let started = Instant::now();
+            let result = execute_step(step, cluster).await;
+            let outcome = match &result {
+                Ok(outcome) => outcome.clone(),
+                Err(e) => StepOutcome::Error(e.to_string()),
+            };
+            let mut results = cluster.results.lock().unwrap();
+            let index = results.len() + 1;
+            results.push(StepResult {
+                index,
+                op: step.op_name(),
+                node: step.target_node(&cluster.default_node).map(str::to_string),
+                outcome,
+                duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+            });
+            drop(results);
+            result?;
         }
         Ok(())
     })
 }
 
-async fn execute_step(
-    step: &ScenarioStep,
-    nodes: &std::collections::HashMap<String, Arc<VizComputeEngine>>,
-    default_node: &str,
-) -> anyhow::Result<()> {
+pub(crate) async fn execute_step(step: &ScenarioStep, cluster: &Cluster) -> anyhow::Result<StepOutcome> {
     match step {
-        ScenarioStep::Put { page_id, offset, data, node } => {
-            let node_key = node.as_deref().unwrap_or(default_node);
-            let compute = nodes.get(node_key)
-                .ok_or_else(|| anyhow::anyhow!("Unknown node: {node_key}"))?;
-            let vdl = compute.put(*page_id, *offset, data.as_bytes().to_vec()).await?;
-            println!("  [{node_key}] PUT pg{page_id} @{offset} {:?} -> VDL={vdl}", data);
-        }
-        ScenarioStep::Get { page_id, node } => {
-            let node_key = node.as_deref().unwrap_or(default_node);
-            let compute = nodes.get(node_key)
-                .ok_or_else(|| anyhow::anyhow!("Unknown node: {node_key}"))?;
-            match compute.get(*page_id).await {
+        ScenarioStep::Put { page_id, offset, data, node, .. } => {
+            let (node_key, compute) = cluster.node(node)?;
+            match compute.put(*page_id, *offset, data.as_bytes().to_vec()).await {
+                Ok(vdl) => println!("  [{node_key}] PUT pg{page_id} @{offset} {:?} -> VDL={vdl}", data),
+                Err(e) => {
+                    println!("  [{node_key}] PUT pg{page_id} @{offset} {:?} -> Error: {e}", data);
+                    return cluster.op_failed(step, node_key, e.to_string());
+                }
+            }
+        }
+        ScenarioStep::PutMulti { writes, node, .. } => {
+            let (node_key, compute) = cluster.node(node)?;
+            let pages: Vec<String> = writes.iter().map(|w| format!("pg{}", w.page_id)).collect();
+            let writes = writes.iter().map(|w| (w.page_id, w.offset, w.data.as_bytes().to_vec())).collect();
+            match compute.put_multi(writes).await {
+                Ok(vdl) => println!("  [{node_key}] PUT MULTI [{}] -> VDL={vdl}", pages.join(", ")),
+                Err(e) => {
+                    println!("  [{node_key}] PUT MULTI [{}] -> Error: {e}", pages.join(", "));
+                    return cluster.op_failed(step, node_key, e.to_string());
+                }
+            }
+        }
+        ScenarioStep::Mtr { ops, node, .. } => {
+            let (node_key, compute) = cluster.node(node)?;
+            let mut mtr = compute.begin().await;
+            let mut savepoints = HashMap::new();
+            for op in ops {
+                match op {
+                    MtrOp::Write(w) => mtr.write(w.page_id, w.offset, w.data.as_bytes().to_vec()),
+                    MtrOp::Savepoint { name } => {
+                        savepoints.insert(name.as_str(), mtr.savepoint());
+                    }
+                    MtrOp::RollbackTo { name } => {
+                        let rolled_back = match savepoints.get(name.as_str()) {
+                            Some(&savepoint) => mtr.rollback_to(savepoint).map_err(|e| e.to_string()),
+                            None => Err(format!("no savepoint named {name:?}")),
+                        };
+                        match rolled_back {
+                            Ok(discarded) => println!("  [{node_key}] ROLLBACK TO {name} -> {discarded} write(s) discarded"),
+                            Err(e) => {
+                                println!("  [{node_key}] ROLLBACK TO {name} -> Error: {e}");
+                                return cluster.op_failed(step, node_key, e);
+                            }
+                        }
+                    }
+                }
+            }
+            let pages: Vec<String> = mtr.page_ids().iter().map(|p| format!("pg{p}")).collect();
+            match compute.commit(mtr).await {
+                Ok(vdl) => println!("  [{node_key}] COMMIT MTR [{}] -> VDL={vdl}", pages.join(", ")),
+                Err(e) => {
+                    println!("  [{node_key}] COMMIT MTR [{}] -> Error: {e}", pages.join(", "));
+                    return cluster.op_failed(step, node_key, e.to_string());
+                }
+            }
+        }
+        ScenarioStep::Get { page_id, node, expect, .. } => {
+            let (node_key, compute) = cluster.node(node)?;
+            let read = match compute.get(*page_id).await {
                 Ok(page) => {
-                    let end = page.iter().position(|&b| b == 0).unwrap_or(mini_aurora_common::PAGE_SIZE);
+                    let end = page.iter().position(|&b| b == 0).unwrap_or(page.len());
                     let preview = if end == 0 {
                         "(empty)".to_string()
                     } else {
                         format!("{:?}", String::from_utf8_lossy(&page[..end.min(40)]))
                     };
                     println!("  [{node_key}] GET pg{page_id} -> {preview}");
+                    Ok(String::from_utf8_lossy(&page[..end]).into_owned())
                 }
-                Err(e) => println!("  [{node_key}] GET pg{page_id} -> Error: {e}"),
-            }
+                Err(e) => {
+                    println!("  [{node_key}] GET pg{page_id} -> Error: {e}");
+                    Err(e.to_string())
+                }
+            };
+            let failure = match (expect, read) {
+                (None, Ok(_)) => return Ok(StepOutcome::Ok),
+                (None, Err(e)) => return cluster.op_failed(step, node_key, e),
+                (Some(want), Ok(got)) if got == *want => return Ok(StepOutcome::Ok),
+                (Some(want), Ok(got)) => format!("pg{page_id}: expected {want:?}, got {got:?}"),
+                (Some(want), Err(e)) => format!("pg{page_id}: expected {want:?}, got error: {e}"),
+            };
+            println!("  ASSERT FAILED {failure}");
+            return Ok(StepOutcome::Failed(failure));
         }
-        ScenarioStep::Refresh { node } => {
-            let node_key = node.as_deref().unwrap_or(default_node);
-            let compute = nodes.get(node_key)
-                .ok_or_else(|| anyhow::anyhow!("Unknown node: {node_key}"))?;
-            let rp = compute.refresh_read_point().await?;
-            println!("  [{node_key}] REFRESH -> rp={rp}");
+        ScenarioStep::Refresh { node, .. } => {
+            let (node_key, compute) = cluster.node(node)?;
+            match compute.refresh_read_point().await {
+                Ok(rp) => println!("  [{node_key}] REFRESH -> rp={rp}"),
+                Err(e) => {
+                    println!("  [{node_key}] REFRESH -> Error: {e}");
+                    return cluster.op_failed(step, node_key, e.to_string());
+                }
+            }
         }
         ScenarioStep::SleepMs { value } => {
             println!("  sleep {value}ms");
@@ -187,9 +1090,289 @@ async fn execute_step(
                 if *count <= 10 || i % (*count / 5).max(1) == 0 {
                     println!("    iteration {}/{count}", i + 1);
                 }
-                execute_steps(steps, nodes, default_node).await?;
+                execute_steps(steps, cluster).await?;
+            }
+        }
+        ScenarioStep::Workload(step) => {
+            let (node_key, compute) = cluster.node(&step.node)?;
+            let spec = step.spec()?;
+            run_workload(compute, node_key, &spec, step.load).await?;
+        }
+        ScenarioStep::Bg { node, kind, interval_ms, pages, size, dist } => {
+            let node_key = node.to_uppercase();
+            if !cluster.nodes.contains_key(&node_key) {
+                anyhow::bail!("Unknown node: {node}");
+            }
+            let options = WorkerOptions::from_fields(pages.as_deref(), *size, dist.as_deref())
+                .map_err(|e| anyhow::anyhow!("bg step on {node}: {e}"))?;
+            cluster.stop_worker(&node_key).await;
+            let handle = worker::spawn_worker(
+                node_key.clone(),
+                *kind,
+                *interval_ms,
+                options.clone(),
+                &cluster.worker_host,
+            ).await;
+            cluster.workers.lock().unwrap().insert(node_key.clone(), handle);
+            println!("  bg {kind} worker on node {node_key} every {interval_ms}ms{options}");
+        }
+        ScenarioStep::BgStop { node } => {
+            let node_key = node.to_uppercase();
+            if cluster.stop_worker(&node_key).await {
+                println!("  stopped bg worker on node {node_key}");
+            }
+        }
+        ScenarioStep::Include { path } => {
+            unreachable!("include of {path} should have been expanded by load_scenario")
+        }
+        ScenarioStep::Viz { enabled, step_delay_ms, filter } => {
+            if !cluster.viz_steps {
+                return Ok(StepOutcome::Ok);
+            }
+            let mut changes = Vec::new();
+            let was_enabled = {
+                let mut renderer = cluster.renderer.lock().unwrap();
+                let was_enabled = renderer.config_mut().enabled;
+                if let Some(ms) = step_delay_ms {
+                    renderer.config_mut().step_delay = Duration::from_millis(*ms);
+                    changes.push(format!("step delay {ms}ms"));
+                }
+                if let Some(pattern) = filter {
+                    let filter = EventFilter::parse(pattern).filter(|_| pattern.trim() != "off");
+                    changes.push(match &filter {
+                        Some(filter) => format!("filter {filter}"),
+                        None => "filter off".to_string(),
+                    });
+                    renderer.set_filter(filter);
+                }
+                if let Some(enabled) = enabled {
+                    renderer.config_mut().enabled = *enabled;
+                    changes.push(if *enabled { "on" } else { "off" }.to_string());
+                }
+                was_enabled
+            };
+            println!("  viz {}", changes.join(", "));
+            // The panel doesn't follow events while drawing is off
+            if *enabled == Some(true) && !was_enabled {
+                for (_, compute) in cluster.node_set(None)? {
+                    compute.emit_state_snapshot().await;
+                }
+            }
+        }
+        ScenarioStep::CrashStorage => {
+            cluster.storage.crash();
+            cluster.faults.lock().unwrap().storage = true;
+            println!("  NEMESIS crash storage");
+        }
+        ScenarioStep::RestartStorage => {
+            let state = cluster.storage.restart()?;
+            cluster.faults.lock().unwrap().storage = false;
+            println!("  NEMESIS restart storage -> recovered {state}");
+        }
+        ScenarioStep::CorruptWal { offset } => {
+            let path = cluster.storage.corrupt_wal(*offset)?;
+            cluster.faults.lock().unwrap().storage = true;
+            println!("  NEMESIS corrupt byte {offset} of {}", path.display());
+        }
+        ScenarioStep::TruncateWal { bytes } => {
+            let (path, len) = cluster.storage.truncate_wal(*bytes)?;
+            cluster.faults.lock().unwrap().storage = true;
+            println!("  NEMESIS truncate {} to {len} bytes (-{bytes}B)", path.display());
+        }
+        ScenarioStep::Partition { nodes } => {
+            for (label, compute) in cluster.node_set(Some(nodes))? {
+                compute.set_partitioned(true);
+                cluster.faults.lock().unwrap().impair(label);
+                println!("  NEMESIS partition node {label} from storage");
+            }
+        }
+        ScenarioStep::Heal => {
+            for (_, compute) in cluster.node_set(None)? {
+                compute.set_partitioned(false);
+                compute.set_network_latency(Duration::ZERO);
+            }
+            cluster.faults.lock().unwrap().nodes.clear();
+            println!("  NEMESIS heal: partitions and injected latency cleared");
+        }
+        ScenarioStep::InjectLatency { ms, nodes } => {
+            for (label, compute) in cluster.node_set(nodes.as_deref())? {
+                compute.set_network_latency(Duration::from_millis(*ms));
+                cluster.faults.lock().unwrap().impair(label);
+                println!("  NEMESIS +{ms}ms storage latency on node {label}");
+            }
+        }
+    }
+    Ok(StepOutcome::Ok)
+}
+
+async fn run_workload(
+    compute: &VizComputeEngine,
+    node_key: &str,
+    spec: &WorkloadSpec,
+    load: bool,
+) -> anyhow::Result<()> {
+    let dist = match spec.distribution {
+        KeyDistribution::Uniform => "uniform".to_string(),
+        KeyDistribution::Zipfian { theta } => format!("zipfian(θ={theta})"),
+    };
+    println!(
+        "  [{node_key}] WORKLOAD {} ops over {} pages, {:.0}% reads, {dist}",
+        spec.operation_count,
+        spec.record_count,
+        spec.read_proportion * 100.0,
+    );
+
+    if load {
+        for page_id in 1..=spec.record_count {
+            compute.put(page_id, 0, vec![b'0'; spec.value_size]).await?;
+        }
+        println!("  [{node_key}] loaded {} pages", spec.record_count);
+    }
+
+    let interval = spec.op_interval();
+    let start = tokio::time::Instant::now();
+    let (mut reads, mut updates) = (0u64, 0u64);
+    for (i, op) in spec.operations().enumerate() {
+        if let Some(interval) = interval {
+            tokio::time::sleep_until(start + interval * i as u32).await;
+        }
+        match op {
+            WorkloadOp::Read { page_id } => {
+                reads += 1;
+                // Without a load phase some pages may never have been written
+                match compute.get(page_id).await {
+                    Ok(_) | Err(mini_aurora_common::StorageError::PageNotFound { .. }) => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            WorkloadOp::Update { page_id, offset, data } => {
+                updates += 1;
+                compute.put(page_id, offset, data).await?;
             }
         }
     }
+
+    let elapsed = start.elapsed();
+    let ops_per_sec = (reads + updates) as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+    println!(
+        "  [{node_key}] workload done: {reads} reads, {updates} updates in {:.1}ms ({ops_per_sec:.0} ops/s)",
+        elapsed.as_secs_f64() * 1000.0,
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_dir::DataDir;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, name: &str, toml: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, toml).unwrap();
+        path
+    }
+
+    fn put(page_id: u64) -> String {
+        format!("[[steps]]\nop = \"put\"\npage_id = {page_id}\noffset = 0\ndata = \"pg{page_id}\"\n")
+    }
+
+    fn pages(scenario: &Scenario) -> Vec<u64> {
+        scenario
+            .steps
+            .iter()
+            .map(|step| match step {
+                ScenarioStep::Put { page_id, .. } => *page_id,
+                other => panic!("unexpected {} step", other.op_name()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_include_cycle_between_two_files() {
+        let dir = TempDir::new().unwrap();
+        let a = write(dir.path(), "a.toml", "include = [\"b.toml\"]\n");
+        write(dir.path(), "b.toml", "[[steps]]\nop = \"include\"\npath = \"a.toml\"\n");
+
+        let err = load_scenario(&a).err().unwrap().to_string();
+        assert!(err.starts_with("Include cycle: "), "{err}");
+        assert!(err.contains("a.toml -> ") && err.contains("b.toml -> ") && err.ends_with("a.toml"), "{err}");
+    }
+
+    #[test]
+    fn test_file_including_itself() {
+        let dir = TempDir::new().unwrap();
+        let path = write(dir.path(), "self.toml", &format!("include = [\"self.toml\"]\n{}", put(1)));
+
+        let err = load_scenario(&path).err().unwrap().to_string();
+        // The cycle is the file and itself
+        assert!(err.starts_with("Include cycle: "), "{err}");
+        assert_eq!(err.matches("self.toml").count(), 2, "{err}");
+    }
+
+    #[test]
+    fn test_nested_includes_resolve_relative_to_their_file() {
+        let dir = TempDir::new().unwrap();
+        let main = write(
+            dir.path(),
+            "main.toml",
+            &format!("include = [\"setup/mid.toml\"]\n{}", put(3)),
+        );
+        // `leaf.toml` is beside `mid.toml`, not the file that included it
+        write(
+            dir.path(),
+            "setup/mid.toml",
+            &format!("{}[[steps]]\nop = \"include\"\npath = \"leaf.toml\"\n", put(1)),
+        );
+        write(dir.path(), "setup/leaf.toml", &put(2));
+
+        let scenario = load_scenario(&main).unwrap();
+        assert_eq!(pages(&scenario), [1, 2, 3]);
+        assert_eq!(scenario.meta.name, "main");
+
+        // A leaf beside `main.toml` instead is not found
+        std::fs::rename(dir.path().join("setup/leaf.toml"), dir.path().join("leaf.toml")).unwrap();
+        assert!(load_scenario(&main).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_report_counts_and_step_lines() {
+        let dir = TempDir::new().unwrap();
+        let session = DataDir::resolve(dir.path().to_str()).session("test", true).unwrap();
+        let toml = format!(
+            "nodes = [\"A\"]\n{}{}{}{}",
+            put(1),
+            "[[steps]]\nop = \"get\"\npage_id = 1\nexpect = \"pg1\"\n",
+            "[[steps]]\nop = \"get\"\npage_id = 1\nexpect = \"other\"\n",
+            "[[steps]]\nop = \"get\"\npage_id = 9\nallow_error = true\n",
+        );
+        let path = write(dir.path(), "small.toml", &toml);
+        let scenario = load_scenario(&path).unwrap();
+
+        let trace = TraceOptions::default();
+        let mut run = run_headless(&session, &scenario, "memory", &trace, 0, TierDisks::default(), None)
+            .await
+            .unwrap();
+        // A failed assertion doesn't stop the run
+        assert!(run.result.is_ok());
+        let report = run.report(&scenario.meta.name, "memory");
+        assert!(!report.passed);
+        assert_eq!((report.steps_run, report.assertions_failed, report.errors), (4, 1, 0));
+
+        let outcomes: Vec<_> =
+            report.steps.iter().map(|step| (step.index, step.op, step.outcome.clone())).collect();
+        assert_eq!(outcomes[0], (1, "put", StepOutcome::Ok));
+        assert_eq!(outcomes[1], (2, "get", StepOutcome::Ok));
+        assert!(matches!(outcomes[2], (3, "get", StepOutcome::Failed(_))));
+        assert!(matches!(outcomes[3], (4, "get", StepOutcome::Expected(_))));
+
+        let junit = report.to_junit();
+        assert!(junit.contains("tests=\"4\" failures=\"1\" errors=\"0\""));
+        let cases: Vec<&str> = junit.lines().filter(|line| line.contains("<testcase")).collect();
+        assert_eq!(cases.len(), 4);
+        assert!(cases[0].contains("name=\"001 put [A]\"") && cases[0].ends_with("/>"));
+        assert!(junit.contains("<failure message="));
+        assert!(junit.contains("<system-out>"));
+    }
+}
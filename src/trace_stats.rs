@@ -0,0 +1,343 @@
+//! `trace-stats`: aggregate a `--trace-json` or `--trace-bin` file into a
+//! summary.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::viz::tracer::{read_binary_trace, BINARY_TRACE_MAGIC};
+
+/// Number of slices the timeline summary is split into.
+const TIMELINE_BUCKETS: usize = 10;
+
+/// Summary statistics over one trace.
+#[derive(Default)]
+pub struct TraceStats {
+    events: u64,
+    skipped_lines: u64,
+    first_us: Option<u64>,
+    last_us: u64,
+    event_counts: BTreeMap<String, u64>,
+    /// Steps per operation, keyed by operation kind.
+    op_steps: BTreeMap<String, Vec<u64>>,
+    /// Operations per node, for traces that record the node.
+    node_ops: BTreeMap<String, u64>,
+    /// Operation in progress.
+    current_op: Option<CurrentOp>,
+    /// Start time of every operation, for the timeline.
+    op_starts: Vec<(String, u64)>,
+    chain_lengths: Vec<u64>,
+    buffer_pool: HitCounter,
+    page_cache: HitCounter,
+    cold_reads: BTreeMap<u64, (u64, u64)>,
+}
+
+struct CurrentOp {
+    kind: String,
+    /// `op_id` from the trace, when present.
+    id: Option<u64>,
+    steps: u64,
+}
+
+#[derive(Default)]
+struct HitCounter {
+    hits: u64,
+    lookups: u64,
+}
+
+impl HitCounter {
+    fn record(&mut self, hit: bool) {
+        self.lookups += 1;
+        if hit {
+            self.hits += 1;
+        }
+    }
+}
+
+impl fmt::Display for HitCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.lookups == 0 {
+            return write!(f, "no lookups");
+        }
+        let rate = self.hits as f64 / self.lookups as f64 * 100.0;
+        write!(f, "{rate:.1}% ({}/{} hits)", self.hits, self.lookups)
+    }
+}
+
+impl TraceStats {
+    /// Read and aggregate a trace, either binary or newline-delimited JSON.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let mut file = std::fs::File::open(path)?;
+        let mut magic = [0u8; BINARY_TRACE_MAGIC.len()];
+        let is_binary = file.read_exact(&mut magic).is_ok() && &magic == BINARY_TRACE_MAGIC;
+        if is_binary {
+            let mut stats = Self::default();
+            for record in read_binary_trace(path)? {
+                stats.add(&serde_json::to_value(&record)?);
+            }
+            stats.finish_op();
+            return Ok(stats);
+        }
+
+        let file = std::fs::File::open(path)?;
+        Ok(Self::from_json_lines(BufReader::new(file))?)
+    }
+
+    /// Aggregate a newline-delimited JSON trace. Lines that aren't a trace
+    /// entry, such as one cut short by a crash, are counted and skipped.
+    fn from_json_lines(reader: impl BufRead) -> std::io::Result<Self> {
+        let mut stats = Self::default();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Value>(&line) {
+                Ok(entry) => stats.add(&entry),
+                Err(_) => stats.skipped_lines += 1,
+            }
+        }
+        stats.finish_op();
+        Ok(stats)
+    }
+
+    /// Add one trace line: `{"seq":..,"timestamp_us":..,"event":..}`.
+    fn add(&mut self, entry: &Value) {
+        let Some((name, body)) = entry.get("event").and_then(split_event) else {
+            self.skipped_lines += 1;
+            return;
+        };
+        let ts = entry.get("timestamp_us").and_then(Value::as_u64).unwrap_or(self.last_us);
+        self.events += 1;
+        self.first_us.get_or_insert(ts);
+        self.last_us = ts;
+        *self.event_counts.entry(name.to_string()).or_default() += 1;
+
+        if entry.get("op_id").is_some() {
+            // Enriched trace: operations are tagged explicitly
+            match entry.get("op_id").and_then(Value::as_u64) {
+                Some(id) if self.current_op.as_ref().and_then(|op| op.id) != Some(id) => {
+                    let kind = entry.get("op").and_then(Value::as_str).unwrap_or("unknown");
+                    let node = entry.get("node").and_then(Value::as_str).unwrap_or("?");
+                    *self.node_ops.entry(node.to_string()).or_default() += 1;
+                    self.start_op(kind, Some(id), ts);
+                }
+                Some(_) => {}
+                None => self.finish_op(),
+            }
+        } else {
+            // Older traces have no operation boundaries: a put begins with
+            // MtrCreated and a get with its buffer pool lookup.
+            match name {
+                "MtrCreated" => self.start_op("put", None, ts),
+                "BufferPoolLookup" => self.start_op("get", None, ts),
+                _ => {}
+            }
+        }
+        if let Some(op) = self.current_op.as_mut() {
+            if name != "StateSnapshot" {
+                op.steps += 1;
+            }
+        }
+
+        let field = |key: &str| body.and_then(|b| b.get(key));
+        match name {
+            "BufferPoolLookup" => self.buffer_pool.record(field("hit").and_then(Value::as_bool).unwrap_or(false)),
+            "PageCacheLookup" => self.page_cache.record(field("hit").and_then(Value::as_bool).unwrap_or(false)),
+            "ChainCollected" => {
+                if let Some(len) = field("chain_len").and_then(Value::as_u64) {
+                    self.chain_lengths.push(len);
+                }
+            }
+            "ColdTierRead" => {
+                let segment = field("segment_id").and_then(Value::as_u64).unwrap_or(0);
+                let latency = field("latency_ms").and_then(Value::as_u64).unwrap_or(0);
+                let entry = self.cold_reads.entry(segment).or_default();
+                entry.0 += 1;
+                entry.1 += latency;
+            }
+            _ => {}
+        }
+    }
+
+    fn start_op(&mut self, kind: &str, id: Option<u64>, ts: u64) {
+        self.finish_op();
+        self.current_op = Some(CurrentOp {
+            kind: kind.to_string(),
+            id,
+            steps: 0,
+        });
+        self.op_starts.push((kind.to_string(), ts));
+    }
+
+    fn finish_op(&mut self) {
+        if let Some(op) = self.current_op.take() {
+            self.op_steps.entry(op.kind).or_default().push(op.steps);
+        }
+    }
+
+    fn duration_us(&self) -> u64 {
+        self.last_us.saturating_sub(self.first_us.unwrap_or(0))
+    }
+}
+
+/// An event is either `"Name"` (unit variant) or `{"Name": {...}}`.
+fn split_event(event: &Value) -> Option<(&str, Option<&Value>)> {
+    match event {
+        Value::String(name) => Some((name.as_str(), None)),
+        Value::Object(map) => map.iter().next().map(|(name, body)| (name.as_str(), Some(body))),
+        _ => None,
+    }
+}
+
+/// `min / mean / p50 / p95 / max` of a sample.
+fn distribution(values: &[u64]) -> String {
+    if values.is_empty() {
+        return "-".to_string();
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let pct = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+    let mean = sorted.iter().sum::<u64>() as f64 / sorted.len() as f64;
+    format!(
+        "min={} mean={mean:.1} p50={} p95={} max={}",
+        sorted[0],
+        pct(0.5),
+        pct(0.95),
+        sorted[sorted.len() - 1],
+    )
+}
+
+impl fmt::Display for TraceStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let duration_s = self.duration_us() as f64 / 1_000_000.0;
+        writeln!(f, "Events: {} over {duration_s:.3}s", self.events)?;
+        if self.skipped_lines > 0 {
+            writeln!(f, "Skipped {} unparseable lines", self.skipped_lines)?;
+        }
+
+        writeln!(f, "\n-- Operations --")?;
+        if self.op_steps.is_empty() {
+            writeln!(f, "  (none)")?;
+        }
+        for (kind, steps) in &self.op_steps {
+            writeln!(f, "  {kind:<7} n={:<6} steps: {}", steps.len(), distribution(steps))?;
+        }
+        for (node, count) in &self.node_ops {
+            writeln!(f, "  node {node}: {count} ops")?;
+        }
+
+        writeln!(f, "\n-- Caches --")?;
+        writeln!(f, "  Buffer pool: {}", self.buffer_pool)?;
+        writeln!(f, "  Page cache:  {}", self.page_cache)?;
+
+        writeln!(f, "\n-- Redo chains --")?;
+        writeln!(f, "  Collected: {}  length: {}", self.chain_lengths.len(), distribution(&self.chain_lengths))?;
+        let mut buckets: BTreeMap<u64, u64> = BTreeMap::new();
+        for &len in &self.chain_lengths {
+            // Power-of-two buckets: 0, 1, 2-3, 4-7, ...
+            let lower = if len == 0 { 0 } else { 1 << len.ilog2() };
+            *buckets.entry(lower).or_default() += 1;
+        }
+        for (lower, count) in buckets {
+            let range = if lower <= 1 { lower.to_string() } else { format!("{lower}-{}", lower * 2 - 1) };
+            writeln!(f, "  {range:>9} | {count}")?;
+        }
+
+        writeln!(f, "\n-- Cold reads --")?;
+        if self.cold_reads.is_empty() {
+            writeln!(f, "  (none)")?;
+        }
+        for (segment, (count, latency_ms)) in &self.cold_reads {
+            writeln!(f, "  seg{segment:<4} reads={count:<5} latency={latency_ms}ms")?;
+        }
+
+        writeln!(f, "\n-- Timeline --")?;
+        let Some(first) = self.first_us else {
+            return writeln!(f, "  (empty)");
+        };
+        let slice_us = (self.duration_us() / TIMELINE_BUCKETS as u64).max(1);
+        let mut slices: Vec<BTreeMap<&str, u64>> = vec![BTreeMap::new(); TIMELINE_BUCKETS];
+        for (kind, ts) in &self.op_starts {
+            let slot = (((ts - first) / slice_us) as usize).min(TIMELINE_BUCKETS - 1);
+            *slices[slot].entry(kind.as_str()).or_default() += 1;
+        }
+        for (i, counts) in slices.iter().enumerate() {
+            let from = (i as u64 * slice_us) as f64 / 1000.0;
+            let to = ((i as u64 + 1) * slice_us) as f64 / 1000.0;
+            let range = format!("{from:.1}-{to:.1}ms");
+            let ops: Vec<String> = counts.iter().map(|(kind, n)| format!("{kind}={n}")).collect();
+            writeln!(f, "  {range:>18} | {}", ops.join(" "))?;
+        }
+
+        writeln!(f, "\n-- Events by type --")?;
+        for (name, count) in &self.event_counts {
+            writeln!(f, "  {name:<24} {count}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(trace: &str) -> TraceStats {
+        TraceStats::from_json_lines(trace.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_aggregates_a_trace_skipping_bad_lines() {
+        let trace = r#"{"seq":0,"timestamp_us":1000,"event":{"MtrCreated":{"mtr_id":1,"records":1}}}
+{"seq":1,"timestamp_us":1100,"event":{"BufferPoolLookup":{"page_id":1,"read_point":1,"hit":false}}}
+not json
+
+{"seq":2,"timestamp_us":1200,"event":{"ChainCollected":{"page_id":1,"chain_len":3}}}
+{"seq":3,"timestamp_us":1300,"event":{"BufferPoolLookup":{"page_id":1,"read_point":1,"hit":true}}}
+{"seq":4,"timestamp_us":1350}
+{"seq":5,"timestamp_us":14"#;
+        let stats = stats(trace);
+
+        // The plain text, the line without an event and the cut-off last
+        // line are skipped; the blank line isn't counted
+        assert_eq!((stats.events, stats.skipped_lines), (4, 3));
+        assert_eq!(stats.duration_us(), 300);
+        assert_eq!(stats.op_steps["put"], [1]);
+        assert_eq!(stats.op_steps["get"], [2, 1]);
+        assert_eq!((stats.buffer_pool.hits, stats.buffer_pool.lookups), (1, 2));
+        assert_eq!(stats.chain_lengths, [3]);
+
+        let summary = stats.to_string();
+        assert!(summary.contains("Events: 4 over 0.000s"));
+        assert!(summary.contains("Skipped 3 unparseable lines"));
+        assert!(summary.contains("Buffer pool: 50.0% (1/2 hits)"));
+    }
+
+    #[test]
+    fn test_tagged_operations_count_per_node() {
+        let trace = r#"{"timestamp_us":10,"op_id":1,"op":"put","node":"A","event":{"MtrCreated":{"mtr_id":1}}}
+{"timestamp_us":20,"op_id":1,"op":"put","node":"A","event":"StateSnapshot"}
+{"timestamp_us":30,"op_id":2,"op":"get","node":"B","event":{"ColdTierRead":{"segment_id":4,"latency_ms":7}}}
+{"timestamp_us":40,"op_id":null,"event":{"PageCacheLookup":{"page_id":1,"version":1,"hit":true}}}"#;
+        let stats = stats(trace);
+
+        assert_eq!(stats.skipped_lines, 0);
+        // Snapshots aren't steps, and an untagged event ends the operation
+        assert_eq!(stats.op_steps["put"], [1]);
+        assert_eq!(stats.op_steps["get"], [1]);
+        assert_eq!(stats.node_ops["A"], 1);
+        assert_eq!(stats.node_ops["B"], 1);
+        assert_eq!(stats.cold_reads[&4], (1, 7));
+        assert_eq!(stats.page_cache.hits, 1);
+    }
+
+    #[test]
+    fn test_empty_trace() {
+        let stats = stats("");
+        assert_eq!(stats.events, 0);
+        assert!(stats.to_string().contains("(empty)"));
+    }
+}
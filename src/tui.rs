@@ -0,0 +1,649 @@
+//! `tui` mode: a full-screen dashboard over the same two-node cluster as the
+//! viz REPL, built on ratatui.
+//!
+//! The frame renderer is switched off; every event reaches the dashboard as
+//! a `TraceRecord` over a channel sink, so the event log, node panels and
+//! metrics all come from the same stream the trace files see.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mini_aurora_common::{Lsn, PageId};
+use mini_aurora_compute::engine::WritePolicy;
+use mini_aurora_storage::config::TierDisks;
+use mini_aurora_wal::segment::Tier;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Sparkline, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::data_dir::Session;
+use crate::diagnostics::explain;
+use crate::viz::compute::VizComputeEngine;
+use crate::viz::engine::VizStorageEngine;
+use crate::viz::events::{VizConfig, VizEvent};
+use crate::viz::heatmap::{HeatGrid, HEAT_LEVELS};
+use crate::viz::metrics::Op;
+use crate::viz::renderer::VizRenderer;
+use crate::viz::state::VizState;
+use crate::viz::tracer::{TraceOptions, TraceRecord, Tracer};
+use crate::worker::{self, WorkerHandle, WorkerHost, WorkerKind, WorkerOptions};
+
+/// Lines kept in the scrollable event log.
+const LOG_CAPACITY: usize = 5000;
+/// Seconds of history shown in each sparkline.
+const SPARK_SECONDS: usize = 60;
+/// How long to wait for a key before redrawing.
+const TICK: Duration = Duration::from_millis(50);
+/// Pages per heatmap line, and the most pages the heatmap panel shows.
+const HEATMAP_COLUMNS: usize = 5;
+const HEATMAP_MAX_PAGES: usize = 10;
+/// Cell background per heat level.
+const HEAT_COLORS: [Color; HEAT_LEVELS] = [Color::Reset, Color::Blue, Color::Cyan, Color::Yellow, Color::Red];
+
+/// Per-second counters for the sparklines, newest last.
+struct Rates {
+    events: VecDeque<u64>,
+    puts: VecDeque<u64>,
+    gets: VecDeque<u64>,
+    bucket_started: Instant,
+}
+
+impl Rates {
+    fn new() -> Self {
+        Self {
+            events: VecDeque::from([0]),
+            puts: VecDeque::from([0]),
+            gets: VecDeque::from([0]),
+            bucket_started: Instant::now(),
+        }
+    }
+
+    /// Start new buckets for every second that has passed.
+    fn roll(&mut self) {
+        while self.bucket_started.elapsed() >= Duration::from_secs(1) {
+            self.bucket_started += Duration::from_secs(1);
+            for series in [&mut self.events, &mut self.puts, &mut self.gets] {
+                if series.len() == SPARK_SECONDS {
+                    series.pop_front();
+                }
+                series.push_back(0);
+            }
+        }
+    }
+
+    fn bump(series: &mut VecDeque<u64>) {
+        if let Some(last) = series.back_mut() {
+            *last += 1;
+        }
+    }
+}
+
+struct App {
+    preset: String,
+    storage: Arc<VizStorageEngine>,
+    renderer: Arc<Mutex<VizRenderer>>,
+    nodes: BTreeMap<String, Arc<VizComputeEngine>>,
+    current_node: String,
+    workers: HashMap<String, WorkerHandle>,
+    worker_host: WorkerHost,
+    /// Cluster state rebuilt from the trace stream.
+    state: VizState,
+    log: VecDeque<String>,
+    /// Lines scrolled up from the bottom; 0 follows new events.
+    scroll: usize,
+    last_op_id: Option<u64>,
+    rates: Rates,
+    input: String,
+    status: String,
+    quit: bool,
+}
+
+impl App {
+    fn push_log(&mut self, line: String) {
+        if self.log.len() == LOG_CAPACITY {
+            self.log.pop_front();
+        }
+        self.log.push_back(line);
+        if self.scroll > 0 {
+            // Keep the view still while the user is reading history
+            self.scroll = (self.scroll + 1).min(self.log.len().saturating_sub(1));
+        }
+    }
+
+    /// Fold one trace record into the dashboard.
+    fn ingest(&mut self, record: TraceRecord) {
+        self.rates.roll();
+        Rates::bump(&mut self.rates.events);
+
+        if let Some(node) = &record.node {
+            if *node != self.state.active_node {
+                self.state.set_active_node(node);
+            }
+        }
+        if record.op_id.is_some() && record.op_id != self.last_op_id {
+            match record.op {
+                Some(Op::Put) => Rates::bump(&mut self.rates.puts),
+                Some(Op::Get) => Rates::bump(&mut self.rates.gets),
+                _ => {}
+            }
+            let tag = match (record.op_id, &record.node, record.op) {
+                (Some(id), Some(node), Some(op)) => format!("── op #{id} node {node} {op}"),
+                _ => "──".to_string(),
+            };
+            self.push_log(tag);
+        }
+        self.last_op_id = record.op_id;
+
+        self.state.apply(&record.event);
+        if !matches!(record.event, VizEvent::StateSnapshot { .. }) {
+            let line = VizRenderer::format_one_liner(&record.event);
+            self.push_log(format!("{:>6} {line}", record.seq));
+        }
+    }
+
+    fn switch_node(&mut self) {
+        let labels: Vec<&String> = self.nodes.keys().collect();
+        let idx = labels.iter().position(|l| **l == self.current_node).unwrap_or(0);
+        self.current_node = labels[(idx + 1) % labels.len()].clone();
+        self.status = format!("Switched to node {}", self.current_node);
+    }
+
+    async fn handle_key(&mut self, key: KeyEvent) {
+        let page = 10;
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Enter => {
+                let line = std::mem::take(&mut self.input);
+                self.run_command(line.trim()).await;
+            }
+            KeyCode::Esc => {
+                if self.input.is_empty() {
+                    self.quit = true;
+                } else {
+                    self.input.clear();
+                }
+            }
+            KeyCode::Tab => self.switch_node(),
+            KeyCode::Up => self.scroll_by(1),
+            KeyCode::Down => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageUp => self.scroll_by(page),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(page),
+            KeyCode::Home => self.scroll = self.log.len().saturating_sub(1),
+            KeyCode::End => self.scroll = 0,
+            _ => {}
+        }
+    }
+
+    fn scroll_by(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.log.len().saturating_sub(1));
+    }
+
+    async fn run_command(&mut self, line: &str) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let node = self.nodes[&self.current_node].clone();
+        self.status = match parts.as_slice() {
+            [] => return,
+            ["quit"] | ["exit"] | ["q"] => {
+                self.quit = true;
+                return;
+            }
+            ["put", page, offset, text @ ..] if !text.is_empty() => {
+                match (page.parse(), offset.parse::<u16>()) {
+                    (Ok(page_id), Ok(offset)) => {
+                        match node.put(page_id, offset, text.join(" ").into_bytes()).await {
+                            Ok(vdl) => format!("PUT pg{page_id} OK (VDL={vdl})"),
+                            Err(e) => format!("Error: {}", explain(&e)),
+                        }
+                    }
+                    _ => "Usage: put <page> <offset> <text>".to_string(),
+                }
+            }
+            ["get", page] => match page.parse() {
+                Ok(page_id) => match node.get(page_id).await {
+                    Ok(data) => {
+                        let end = data.iter().rposition(|&b| b != 0).map(|i| i + 1).unwrap_or(0);
+                        format!("pg{page_id}: {:?}", String::from_utf8_lossy(&data[..end.min(64)]))
+                    }
+                    Err(e) => format!("Error: {}", explain(&e)),
+                },
+                Err(_) => "Usage: get <page>".to_string(),
+            },
+            ["refresh"] => match node.refresh_read_point().await {
+                Ok(vdl) => format!("Read point refreshed to {vdl}"),
+                Err(e) => format!("Error: {}", explain(&e)),
+            },
+            ["node", label] => {
+                let label = label.to_uppercase();
+                if self.nodes.contains_key(&label) {
+                    self.current_node = label;
+                    format!("Switched to node {}", self.current_node)
+                } else {
+                    format!("Unknown node: {label}")
+                }
+            }
+            ["bg", kind, rest @ ..] => {
+                // An interval, if given, comes before the key=value options
+                let (interval_ms, words) = rest
+                    .split_first()
+                    .and_then(|(ms, words)| Some((ms.parse().ok()?, words)))
+                    .unwrap_or((100, rest));
+                match (WorkerKind::parse(kind), WorkerOptions::parse(words)) {
+                    (Some(kind), Ok(options)) => self.start_worker(kind, interval_ms, options).await,
+                    (Some(_), Err(e)) => e,
+                    (None, _) => "Usage: bg write|read|mixed [ms] [pages=a..b] [size=n] [dist=...]".to_string(),
+                }
+            }
+            ["bgstop"] => match self.workers.remove(&self.current_node) {
+                Some(handle) => {
+                    handle.stop().await;
+                    format!("Stopped background worker on node {}", self.current_node)
+                }
+                None => format!("No background worker on node {}", self.current_node),
+            },
+            ["clear"] => {
+                self.log.clear();
+                self.scroll = 0;
+                "Event log cleared".to_string()
+            }
+            _ => format!("Unknown command: {line}"),
+        };
+    }
+
+    async fn start_worker(&mut self, kind: WorkerKind, interval_ms: u64, options: WorkerOptions) -> String {
+        if let Some(old) = self.workers.remove(&self.current_node) {
+            old.stop().await;
+        }
+        let handle = worker::spawn_worker(
+            self.current_node.clone(),
+            kind,
+            interval_ms,
+            options.clone(),
+            &self.worker_host,
+        )
+        .await;
+        self.workers.insert(self.current_node.clone(), handle);
+        format!("Started {kind} worker on node {} every {interval_ms}ms{options}", self.current_node)
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(8),
+                Constraint::Length(5),
+                Constraint::Length(3),
+            ])
+            .split(frame.area());
+
+        self.draw_header(frame, rows[0]);
+
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(30), Constraint::Length(36)])
+            .split(rows[1]);
+        self.draw_log(frame, body[0]);
+        self.draw_side(frame, body[1]);
+
+        self.draw_sparklines(frame, rows[2]);
+        self.draw_input(frame, rows[3]);
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: Rect) {
+        let header = Line::from(vec![
+            Span::styled(" Mini-Aurora ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("preset={} node={} ", self.preset, self.current_node)),
+            Span::styled(
+                "Tab: node  ↑↓/PgUp/PgDn/Home/End: scroll  Esc: quit",
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]);
+        frame.render_widget(Paragraph::new(header), area);
+    }
+
+    fn draw_log(&self, frame: &mut Frame, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let end = self.log.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(height);
+        let lines: Vec<Line> = self
+            .log
+            .range(start..end)
+            .map(|l| {
+                if l.starts_with('─') {
+                    Line::styled(l.as_str(), Style::default().fg(Color::Cyan))
+                } else {
+                    Line::raw(l.as_str())
+                }
+            })
+            .collect();
+        let title = if self.scroll == 0 {
+            format!(" Events ({}) ", self.log.len())
+        } else {
+            format!(" Events ({}, {} up) ", self.log.len(), self.scroll)
+        };
+        let log = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(log, area);
+    }
+
+    fn draw_side(&self, frame: &mut Frame, area: Rect) {
+        let mut constraints: Vec<Constraint> =
+            self.state.nodes.keys().map(|_| Constraint::Length(5)).collect();
+        constraints.push(Constraint::Length(7));
+        constraints.push(Constraint::Min(4));
+        let heatmap = self.heatmap_lines();
+        constraints.push(Constraint::Length(heatmap.len() as u16 + 2));
+        let panels = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints)
+            .split(area);
+
+        for (i, (label, node)) in self.state.nodes.iter().enumerate() {
+            let mut lines = vec![
+                Line::raw(format!("read point: L{}  next MTR: {}", node.read_point, node.next_mtr)),
+                Line::raw(format!("buffer: {}", page_list(&node.buffer_pool))),
+            ];
+            if *label == self.state.active_node && !self.state.interaction.is_empty() {
+                lines.push(Line::styled(
+                    self.state.interaction.clone(),
+                    Style::default().fg(Color::Yellow),
+                ));
+            }
+            let mut block = Block::default().borders(Borders::ALL).title(format!(" Node {label} "));
+            if *label == self.current_node {
+                block = block.border_style(Style::default().fg(Color::Green));
+            }
+            frame.render_widget(
+                Paragraph::new(lines).block(block).wrap(Wrap { trim: true }),
+                panels[i],
+            );
+        }
+
+        let shared = &self.state.shared;
+        let mut index: Vec<_> = shared.page_index.iter().collect();
+        index.sort();
+        let index = index
+            .iter()
+            .map(|(page, lsn)| format!("pg{page}→L{lsn}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let storage = vec![
+            Line::raw(format!("VCL: {}  VDL: {}  next: {}", shared.vcl, shared.vdl, shared.next_lsn)),
+            Line::raw(format!(
+                "cache: {}  offsets: {}",
+                shared.page_cache_count, shared.lsn_offset_count
+            )),
+            Line::raw(format!("index: {index}")),
+        ];
+        let n = self.state.nodes.len();
+        frame.render_widget(
+            Paragraph::new(storage)
+                .block(Block::default().borders(Borders::ALL).title(" Storage "))
+                .wrap(Wrap { trim: true }),
+            panels[n],
+        );
+
+        let mut wal = vec![Line::raw(match shared.wal_lsn_range {
+            Some((first, last)) => format!("L{first}..L{last}  {} bytes", shared.wal_file_size),
+            None => format!("empty  {} bytes", shared.wal_file_size),
+        })];
+        for seg in self.storage.segments() {
+            let tier = match seg.tier {
+                Tier::Hot if seg.promoted => "HOT\u{2191}",
+                Tier::Hot => "HOT ",
+                Tier::Cold => "COLD",
+            };
+            let sealed = if seg.sealed { "sealed" } else { "active" };
+            wal.push(Line::raw(format!(
+                "seg{:<3} {tier} L{}..L{} {}B {sealed}",
+                seg.id, seg.lsn_range.0, seg.lsn_range.1, seg.size_bytes
+            )));
+        }
+        frame.render_widget(
+            Paragraph::new(wal).block(Block::default().borders(Borders::ALL).title(" WAL ")),
+            panels[n + 1],
+        );
+
+        frame.render_widget(
+            Paragraph::new(heatmap)
+                .block(Block::default().borders(Borders::ALL).title(" Heatmap (writes/reads/chain) ")),
+            panels[n + 2],
+        );
+    }
+
+    /// The busiest pages' writes, reads, last access and chain lengths,
+    /// colored by heat relative to every page.
+    fn heatmap_lines(&self) -> Vec<Line<'static>> {
+        let mut stats = self.storage.page_stats();
+        if stats.is_empty() {
+            return vec![Line::styled("no pages yet", Style::default().fg(Color::DarkGray))];
+        }
+        let grid = HeatGrid::new(&stats);
+        let total = grid.pages.len();
+        if total > HEATMAP_MAX_PAGES {
+            let mut busiest: Vec<_> = stats.iter().map(|(&page, s)| (s.writes + s.reads, page)).collect();
+            busiest.sort_unstable_by(|a, b| b.cmp(a));
+            let keep: Vec<_> = busiest.iter().take(HEATMAP_MAX_PAGES).map(|&(_, page)| page).collect();
+            stats.retain(|page, _| keep.contains(page));
+        }
+        let shown: Vec<usize> = grid
+            .pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| stats.contains_key(page))
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut lines = Vec::new();
+        for chunk in shown.chunks(HEATMAP_COLUMNS) {
+            let header: String = chunk.iter().map(|&i| format!("{:>5}", format!("pg{}", grid.pages[i]))).collect();
+            lines.push(Line::styled(format!("{:<7}{header}", ""), Style::default().fg(Color::DarkGray)));
+            for row in &grid.rows {
+                let mut spans = vec![Span::raw(format!("{:<7}", row.label))];
+                for &i in chunk {
+                    let (value, level) = row.cells[i];
+                    let style = if level == 0 {
+                        Style::default().fg(Color::DarkGray)
+                    } else {
+                        Style::default().fg(Color::Black).bg(HEAT_COLORS[level])
+                    };
+                    spans.push(Span::raw(" "));
+                    spans.push(Span::styled(format!("{value:>4}"), style));
+                }
+                lines.push(Line::from(spans));
+            }
+        }
+        if total > shown.len() {
+            lines.push(Line::styled(
+                format!("busiest {} of {total} pages", shown.len()),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        lines
+    }
+
+    fn draw_sparklines(&self, frame: &mut Frame, area: Rect) {
+        let cols = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(25),
+                Constraint::Percentage(25),
+                Constraint::Percentage(25),
+                Constraint::Percentage(25),
+            ])
+            .split(area);
+        let series = [
+            ("events/s", &self.rates.events, Color::Cyan),
+            ("puts/s", &self.rates.puts, Color::Green),
+            ("gets/s", &self.rates.gets, Color::Yellow),
+        ];
+        for (i, (name, data, color)) in series.into_iter().enumerate() {
+            let last = data.back().copied().unwrap_or(0);
+            // Show the most recent seconds that fit in the panel
+            let width = cols[i].width.saturating_sub(2) as usize;
+            let shown = data.iter().skip(data.len().saturating_sub(width)).copied();
+            let spark = Sparkline::default()
+                .block(Block::default().borders(Borders::ALL).title(format!(" {name} {last} ")))
+                .data(shown)
+                .style(Style::default().fg(color));
+            frame.render_widget(spark, cols[i]);
+        }
+
+        let summary = self.renderer.lock().unwrap().metrics_summary();
+        let lines = match summary {
+            Some(s) => {
+                let hit_pct =
+                    |hits: u64, misses: u64| (hits * 100).checked_div(hits + misses).unwrap_or(0);
+                vec![
+                    Line::raw(format!("writes: {}  reads: {}", s.write_count, s.read_count)),
+                    Line::raw(format!(
+                        "cache: {}%  buf: {}%",
+                        hit_pct(s.page_cache_hits, s.page_cache_misses),
+                        hit_pct(s.buffer_pool_hits, s.buffer_pool_misses)
+                    )),
+                    Line::raw(format!("WAL: {} bytes", s.wal_bytes_written)),
+                ]
+            }
+            None => Vec::new(),
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Metrics ")),
+            cols[3],
+        );
+    }
+
+    fn draw_input(&self, frame: &mut Frame, area: Rect) {
+        let title = if self.status.is_empty() {
+            " put <pg> <off> <text> | get <pg> | refresh | node <X> | bg <kind> [ms] | bgstop | clear | quit "
+                .to_string()
+        } else {
+            format!(" {} ", self.status)
+        };
+        let input = Paragraph::new(format!("{}> {}", self.current_node, self.input))
+            .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(input, area);
+        let cursor_x = area.x + 1 + (self.current_node.len() + 2 + self.input.chars().count()) as u16;
+        frame.set_cursor_position((cursor_x.min(area.right().saturating_sub(2)), area.y + 1));
+    }
+}
+
+/// "pg1 pg2 ..." or "(empty)".
+fn page_list(pages: &[(PageId, Lsn)]) -> String {
+    if pages.is_empty() {
+        "(empty)".to_string()
+    } else {
+        pages.iter().map(|(p, l)| format!("pg{p}@L{l}")).collect::<Vec<_>>().join(" ")
+    }
+}
+
+pub async fn run_tui(
+    session: &Session,
+    trace: &TraceOptions,
+    preset: &str,
+    segment_size: u64,
+    disks: TierDisks,
+    write_policy: WritePolicy,
+) -> anyhow::Result<()> {
+    // The dashboard draws everything itself; the renderer only traces
+    let config = VizConfig {
+        step_delay: Duration::ZERO,
+        color: false,
+        enabled: false,
+        step: false,
+        lanes: false,
+        metrics_strip: false,
+    };
+    let mut renderer_inner = VizRenderer::new(config);
+    let (record_tx, record_rx) = mpsc::channel::<TraceRecord>();
+    let mut tracer = Tracer::new();
+    tracer.add_sink(record_tx);
+    trace.add_sinks(&mut tracer)?;
+    renderer_inner.set_tracer(tracer);
+    let renderer = Arc::new(Mutex::new(renderer_inner));
+
+    let storage: Arc<VizStorageEngine> = match preset {
+        "tiered" => {
+            let base_dir = session.tiered_dir();
+            Arc::new(VizStorageEngine::open_tiered(&base_dir, segment_size, disks, None, renderer.clone())?)
+        }
+        "memory" => Arc::new(VizStorageEngine::open_in_memory(None, renderer.clone())?),
+        _ => {
+            Arc::new(VizStorageEngine::open(&session.wal_path(), None, renderer.clone())?)
+        }
+    };
+
+    let mut state = VizState::default();
+    let mut nodes = BTreeMap::new();
+    for label in ["A", "B"] {
+        let node = Arc::new(
+            VizComputeEngine::new(storage.clone(), 256, renderer.clone(), label.to_string())
+                .with_write_policy(write_policy),
+        );
+        node.refresh_read_point().await?;
+        state.register_node(label);
+        nodes.insert(label.to_string(), node);
+    }
+
+    let (bg_output_tx, mut bg_output_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let mut app = App {
+        preset: preset.to_string(),
+        worker_host: WorkerHost::new(storage.clone(), renderer.clone(), bg_output_tx),
+        storage,
+        renderer,
+        nodes,
+        current_node: "A".to_string(),
+        workers: HashMap::new(),
+        state,
+        log: VecDeque::new(),
+        scroll: 0,
+        last_op_id: None,
+        rates: Rates::new(),
+        input: String::new(),
+        status: String::new(),
+        quit: false,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app, &record_rx, &mut bg_output_rx).await;
+    ratatui::restore();
+
+    for (_, handle) in app.workers.drain() {
+        handle.stop().await;
+    }
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    records: &mpsc::Receiver<TraceRecord>,
+    bg_output: &mut tokio::sync::mpsc::UnboundedReceiver<String>,
+) -> anyhow::Result<()> {
+    while !app.quit {
+        while let Ok(record) = records.try_recv() {
+            app.ingest(record);
+        }
+        while let Ok(line) = bg_output.try_recv() {
+            app.push_log(line);
+        }
+        app.rates.roll();
+
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    app.handle_key(key).await;
+                }
+            }
+        }
+    }
+    Ok(())
+}
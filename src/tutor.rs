@@ -0,0 +1,251 @@
+//! Picks the viz REPL's numbered suggestions from the cluster's actual
+//! state: a node lagging the VDL, a stale buffer pool entry, a page whose
+//! chain reaches into the cold tier, and so on. Each suggestion carries a
+//! line on what running it will show.
+
+use std::collections::BTreeMap;
+
+use mini_aurora_common::{Lsn, PageId};
+
+/// A command to offer, and why it is worth running now.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub command: String,
+    pub why: String,
+}
+
+impl Suggestion {
+    fn new(command: impl Into<String>, why: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            why: why.into(),
+        }
+    }
+}
+
+/// What the tutor can see, gathered after every command.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub current_node: String,
+    pub nodes: Vec<NodeSnapshot>,
+    /// `None` while storage is crashed.
+    pub vdl: Option<Lsn>,
+    pub pages: BTreeMap<PageId, PageSnapshot>,
+    /// Nodes with a running bg worker.
+    pub workers: Vec<String>,
+    /// Page the last command read or wrote.
+    pub last_page: Option<PageId>,
+}
+
+#[derive(Debug)]
+pub struct NodeSnapshot {
+    pub label: String,
+    pub read_point: Lsn,
+    /// Buffer pool contents: page and the read point it was cached at.
+    pub cached: Vec<(PageId, Lsn)>,
+}
+
+#[derive(Debug, Default)]
+pub struct PageSnapshot {
+    pub latest_lsn: Lsn,
+    pub chain_len: usize,
+    /// A cold segment holding one of the page's records, if any.
+    pub cold_segment: Option<u32>,
+}
+
+impl Snapshot {
+    fn node(&self, label: &str) -> Option<&NodeSnapshot> {
+        self.nodes.iter().find(|n| n.label == label)
+    }
+
+    fn current(&self) -> Option<&NodeSnapshot> {
+        self.node(&self.current_node)
+    }
+}
+
+/// Chooses suggestions from a snapshot. The REPL holds one as a trait
+/// object so other teaching strategies can be dropped in.
+pub trait Tutor: Send {
+    fn suggest(&self, snapshot: &Snapshot) -> Vec<Suggestion>;
+}
+
+/// Chains at least this long are worth drawing with `chain`.
+const LONG_CHAIN: usize = 4;
+
+/// Default tutor: each rule looks for one teachable situation, and the
+/// first `limit` distinct commands win, so rules are ordered from most to
+/// least instructive.
+pub struct RuleTutor {
+    limit: usize,
+}
+
+impl Default for RuleTutor {
+    fn default() -> Self {
+        Self { limit: 3 }
+    }
+}
+
+type Rule = fn(&Snapshot) -> Option<Suggestion>;
+
+const RULES: &[Rule] = &[
+    storage_down,
+    nothing_written,
+    read_point_lag,
+    stale_buffer,
+    cold_read,
+    long_chain,
+    other_node_lag,
+    follow_up,
+    other_node_view,
+    workers_running,
+    watermarks,
+    heatmap,
+];
+
+impl Tutor for RuleTutor {
+    fn suggest(&self, snapshot: &Snapshot) -> Vec<Suggestion> {
+        let mut out: Vec<Suggestion> = Vec::new();
+        for rule in RULES {
+            if out.len() == self.limit {
+                break;
+            }
+            if let Some(s) = rule(snapshot) {
+                if !out.iter().any(|o| o.command == s.command) {
+                    out.push(s);
+                }
+            }
+        }
+        out
+    }
+}
+
+fn storage_down(s: &Snapshot) -> Option<Suggestion> {
+    s.vdl.is_none().then(|| {
+        Suggestion::new("recover", "storage is down; recovery rescans the WAL and recomputes VCL/VDL")
+    })
+}
+
+fn nothing_written(s: &Snapshot) -> Option<Suggestion> {
+    (s.vdl.is_some() && s.pages.is_empty()).then(|| {
+        Suggestion::new("put 1 0 Hello", "nothing written yet; a put becomes one redo record in the WAL")
+    })
+}
+
+fn read_point_lag(s: &Snapshot) -> Option<Suggestion> {
+    let vdl = s.vdl?;
+    let node = s.current()?;
+    (node.read_point < vdl).then(|| {
+        Suggestion::new(
+            "refresh",
+            format!(
+                "Node {} reads at L{} but storage is durable to L{vdl}; newer records stay invisible until refresh",
+                node.label, node.read_point
+            ),
+        )
+    })
+}
+
+fn stale_buffer(s: &Snapshot) -> Option<Suggestion> {
+    let node = s.current()?;
+    node.cached.iter().find_map(|&(page_id, cached_at)| {
+        let latest = s.pages.get(&page_id)?.latest_lsn;
+        (latest > cached_at).then(|| {
+            Suggestion::new(
+                format!("get {page_id}"),
+                format!(
+                    "Node {} cached pg{page_id} at L{cached_at} but it changed at L{latest}; see which version read point L{} gets",
+                    node.label, node.read_point
+                ),
+            )
+        })
+    })
+}
+
+fn cold_read(s: &Snapshot) -> Option<Suggestion> {
+    let node = s.current()?;
+    s.pages.iter().find_map(|(&page_id, page)| {
+        let segment = page.cold_segment?;
+        let cached = node.cached.iter().any(|&(p, _)| p == page_id);
+        (!cached && page.latest_lsn <= node.read_point).then(|| {
+            Suggestion::new(
+                format!("get {page_id}"),
+                format!("pg{page_id} has records in cold seg{segment}; the read pays the cold-tier latency"),
+            )
+        })
+    })
+}
+
+fn long_chain(s: &Snapshot) -> Option<Suggestion> {
+    let (page_id, page) = s.pages.iter().max_by_key(|(_, p)| p.chain_len)?;
+    (page.chain_len >= LONG_CHAIN).then(|| {
+        Suggestion::new(
+            format!("chain {page_id}"),
+            format!("pg{page_id}'s redo chain is {} records long; an uncached read replays all of them", page.chain_len),
+        )
+    })
+}
+
+fn other_node_lag(s: &Snapshot) -> Option<Suggestion> {
+    let vdl = s.vdl?;
+    let node = s
+        .nodes
+        .iter()
+        .filter(|n| n.label != s.current_node && n.read_point < vdl)
+        .min_by_key(|n| n.read_point)?;
+    Some(Suggestion::new(
+        format!("node {}", node.label),
+        format!(
+            "Node {} is still at L{}, {} behind; its reads show the older versions",
+            node.label,
+            node.read_point,
+            vdl - node.read_point
+        ),
+    ))
+}
+
+fn follow_up(s: &Snapshot) -> Option<Suggestion> {
+    let page_id = s.last_page?;
+    let node = s.current()?;
+    let Some(page) = s.pages.get(&page_id) else {
+        return Some(Suggestion::new(
+            format!("put {page_id} 0 Hello"),
+            format!("pg{page_id} has never been written; its first record starts a new chain"),
+        ));
+    };
+    if page.latest_lsn > node.read_point {
+        // `read_point_lag` covers this
+        return None;
+    }
+    let cached = node.cached.iter().any(|&(p, _)| p == page_id);
+    Some(if cached {
+        Suggestion::new(
+            format!("put {page_id} 0 updated"),
+            format!("append a record linked to pg{page_id}'s previous one by prev_lsn"),
+        )
+    } else {
+        Suggestion::new(
+            format!("get {page_id}"),
+            format!("materialize pg{page_id} by replaying its redo chain"),
+        )
+    })
+}
+
+fn other_node_view(s: &Snapshot) -> Option<Suggestion> {
+    let other = s.nodes.iter().find(|n| n.label != s.current_node)?;
+    Some(Suggestion::new(
+        format!("node {}", other.label),
+        format!("Node {} has its own buffer pool and read point", other.label),
+    ))
+}
+
+fn workers_running(s: &Snapshot) -> Option<Suggestion> {
+    (!s.workers.is_empty()).then(|| Suggestion::new("bg list", "see what the background workers are doing"))
+}
+
+fn watermarks(s: &Snapshot) -> Option<Suggestion> {
+    s.vdl.map(|vdl| Suggestion::new("state", format!("VCL/VDL watermarks (VDL is L{vdl})")))
+}
+
+fn heatmap(s: &Snapshot) -> Option<Suggestion> {
+    (!s.pages.is_empty()).then(|| Suggestion::new("heatmap", "writes, reads, last access and chain length"))
+}
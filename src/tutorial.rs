@@ -0,0 +1,563 @@
+//! `mini-aurora tutorial`: a fixed sequence of lessons, each run against a
+//! fresh cluster. A lesson sets the stage with scenario steps, then hands
+//! the user a list of checkpoints; a checkpoint passes only once the user's
+//! command did what it asked, judged by the same `expect` assertions
+//! scenario files use.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mini_aurora_common::{Lsn, PageId, StorageApi};
+use mini_aurora_storage::config::TierDisks;
+
+use crate::data_dir::Session;
+use crate::diagnostics::explain_any;
+use crate::line_editor::{LineEditor, ReplHelper, TUTORIAL_COMMANDS};
+use crate::scenario::{self, Cluster, PageWrite, ScenarioStep, StepOutcome};
+use crate::viz::engine::VizStorageEngine;
+use crate::viz::events::VizConfig;
+use crate::viz::metrics::format_elapsed;
+use crate::viz::renderer::VizRenderer;
+
+/// Every lesson runs with two compute nodes.
+const NODES: [&str; 2] = ["A", "B"];
+/// Segment size for tiered lessons, small enough that a few dozen writes
+/// seal segments and push the oldest into the cold tier.
+const TUTORIAL_SEGMENT_SIZE: u64 = 256;
+
+struct Lesson {
+    title: &'static str,
+    intro: &'static str,
+    /// Run on the tiered preset instead of a single WAL file.
+    tiered: bool,
+    setup: fn() -> Vec<ScenarioStep>,
+    checkpoints: &'static [Checkpoint],
+    outro: &'static str,
+}
+
+struct Checkpoint {
+    task: &'static str,
+    hint: &'static str,
+    goal: Goal,
+}
+
+/// What the user's command has to do for a checkpoint to pass.
+#[derive(Clone, Copy)]
+enum Goal {
+    /// A successful put to `page_id` from `node`.
+    Put { node: &'static str, page_id: PageId },
+    /// A successful multi-page put touching exactly `pages` from `node`.
+    PutMulti { node: &'static str, pages: &'static [PageId] },
+    /// A get of `page_id` on `node` that reads back `expect`.
+    Read { node: &'static str, page_id: PageId, expect: &'static str },
+    /// A get of `page_id` on `node` that fails.
+    ReadFails { node: &'static str, page_id: PageId },
+    Refresh { node: &'static str },
+    /// Any successful command that leaves the VDL at or past this LSN.
+    VdlAtLeast(Lsn),
+    Crash,
+    TruncateWal,
+    Recover,
+    /// The `state` command.
+    State,
+}
+
+/// How the user's last command measured up against the checkpoint.
+enum Verdict {
+    Passed,
+    /// Ran fine but wasn't what the checkpoint asks for.
+    NotYet,
+    /// The right kind of command that didn't have the right effect.
+    Wrong(String),
+}
+
+impl Goal {
+    fn node(&self) -> Option<&'static str> {
+        match *self {
+            Goal::Put { node, .. }
+            | Goal::PutMulti { node, .. }
+            | Goal::Read { node, .. }
+            | Goal::ReadFails { node, .. }
+            | Goal::Refresh { node } => Some(node),
+            _ => None,
+        }
+    }
+
+    /// Whether `step` is the command this goal is waiting for, ignoring
+    /// which node it ran on.
+    fn matches(&self, step: &ScenarioStep) -> bool {
+        match (*self, step) {
+            (Goal::Put { page_id, .. }, ScenarioStep::Put { page_id: p, .. }) => page_id == *p,
+            (Goal::PutMulti { pages, .. }, ScenarioStep::PutMulti { writes, .. }) => {
+                let mut written: Vec<PageId> = writes.iter().map(|w| w.page_id).collect();
+                written.sort_unstable();
+                written.dedup();
+                written == pages
+            }
+            (Goal::Read { page_id, .. } | Goal::ReadFails { page_id, .. }, ScenarioStep::Get { page_id: p, .. }) => {
+                page_id == *p
+            }
+            (Goal::Refresh { .. }, ScenarioStep::Refresh { .. })
+            | (Goal::Crash, ScenarioStep::CrashStorage)
+            | (Goal::TruncateWal, ScenarioStep::TruncateWal { .. })
+            | (Goal::Recover, ScenarioStep::RestartStorage) => true,
+            _ => false,
+        }
+    }
+
+    /// Turn the awaited get into an assertion on the expected contents.
+    fn arm(&self, step: &mut ScenarioStep) {
+        if !self.matches(step) || step.target_node(NODES[0]) != self.node() {
+            return;
+        }
+        if let (Goal::Read { expect, .. }, ScenarioStep::Get { expect: slot, .. }) = (*self, step) {
+            *slot = Some(expect.to_string());
+        }
+    }
+
+    async fn judge(&self, step: &ScenarioStep, outcome: &StepOutcome, cluster: &Cluster) -> Verdict {
+        if let Goal::VdlAtLeast(lsn) = *self {
+            let vdl = cluster.storage.get_durability_state().await.map_or(0, |d| d.vdl);
+            return if *outcome == StepOutcome::Ok && vdl >= lsn { Verdict::Passed } else { Verdict::NotYet };
+        }
+        if !self.matches(step) {
+            return Verdict::NotYet;
+        }
+        if let Some(want) = self.node() {
+            let ran_on = step.target_node(NODES[0]).unwrap_or(NODES[0]);
+            if ran_on != want {
+                return Verdict::Wrong(format!("that ran on node {ran_on}; this step is for node {want} (`node {want}`)"));
+            }
+        }
+        match (self, outcome) {
+            (Goal::ReadFails { page_id, .. }, StepOutcome::Ok) => {
+                Verdict::Wrong(format!("the read of pg{page_id} succeeded"))
+            }
+            (Goal::ReadFails { .. }, StepOutcome::Expected(_) | StepOutcome::Error(_)) => Verdict::Passed,
+            (_, StepOutcome::Ok) => Verdict::Passed,
+            (_, StepOutcome::Expected(e) | StepOutcome::Error(e) | StepOutcome::Failed(e)) => {
+                Verdict::Wrong(e.clone())
+            }
+        }
+    }
+}
+
+fn put(page_id: PageId, data: &str) -> ScenarioStep {
+    let (data, node) = (data.to_string(), Some("A".to_string()));
+    ScenarioStep::Put { page_id, offset: 0, data, node, allow_error: false }
+}
+
+fn get(node: &str, page_id: PageId) -> ScenarioStep {
+    ScenarioStep::Get { page_id, node: Some(node.to_string()), expect: None, allow_error: false }
+}
+
+fn refresh(node: &str) -> ScenarioStep {
+    ScenarioStep::Refresh { node: Some(node.to_string()), allow_error: false }
+}
+
+const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "Durability watermarks",
+        intro: "Every write becomes a redo record with a log sequence number (LSN). Storage tracks two\n\
+                watermarks: VCL, the highest LSN with no gaps below it, and VDL, the highest\n\
+                mini-transaction end (CPL) at or below VCL. A write is durable once VDL covers it.",
+        tiered: false,
+        setup: Vec::new,
+        checkpoints: &[
+            Checkpoint {
+                task: "Write something to page 1.",
+                hint: "put 1 0 hello",
+                goal: Goal::Put { node: "A", page_id: 1 },
+            },
+            Checkpoint {
+                task: "Look at the watermarks with `state`.",
+                hint: "state",
+                goal: Goal::State,
+            },
+            Checkpoint {
+                task: "Keep writing (any pages) until VDL reaches L3.",
+                hint: "put 2 0 world, then one more put",
+                goal: Goal::VdlAtLeast(3),
+            },
+        ],
+        outro: "Each put was a single-record mini-transaction, so every record was a CPL and VDL\n\
+                moved with VCL.",
+    },
+    Lesson {
+        title: "Read isolation",
+        intro: "A compute node reads at its read point: the VDL it last saw. Storage replays a page's\n\
+                redo chain only up to that LSN, so a node keeps a consistent view until it refreshes.\n\
+                Node A has written pg1 twice; node B refreshed between the two writes.",
+        tiered: false,
+        setup: || vec![put(1, "v1"), refresh("B"), put(1, "v2")],
+        checkpoints: &[
+            Checkpoint {
+                task: "Switch to node B and read page 1. It should still say \"v1\".",
+                hint: "node B, then get 1",
+                goal: Goal::Read { node: "B", page_id: 1, expect: "v1" },
+            },
+            Checkpoint {
+                task: "Move B's read point up to the current VDL.",
+                hint: "refresh",
+                goal: Goal::Refresh { node: "B" },
+            },
+            Checkpoint {
+                task: "Read page 1 on B again; now it should say \"v2\".",
+                hint: "get 1",
+                goal: Goal::Read { node: "B", page_id: 1, expect: "v2" },
+            },
+        ],
+        outro: "Nothing was locked: both versions stayed in the redo chain, and each read point\n\
+                picked the one it could see.",
+    },
+    Lesson {
+        title: "Stale buffers",
+        intro: "Each node caches pages in its own buffer pool, tagged with the read point they were\n\
+                read at. Node B has cached pg2 as \"old\"; node A has since written \"new\".",
+        tiered: false,
+        setup: || vec![put(2, "old"), refresh("B"), get("B", 2), put(2, "new")],
+        checkpoints: &[
+            Checkpoint {
+                task: "On node B, read page 2. The buffer pool still serves \"old\".",
+                hint: "node B, then get 2",
+                goal: Goal::Read { node: "B", page_id: 2, expect: "old" },
+            },
+            Checkpoint {
+                task: "Refresh B's read point.",
+                hint: "refresh",
+                goal: Goal::Refresh { node: "B" },
+            },
+            Checkpoint {
+                task: "Read page 2 on B again. The cached copy is older than the new read point, so\n\
+                       it is a miss and B fetches \"new\" from storage.",
+                hint: "get 2",
+                goal: Goal::Read { node: "B", page_id: 2, expect: "new" },
+            },
+        ],
+        outro: "Writers never reach into other nodes' caches. A cached page is only used while it is\n\
+                at least as new as the reader's read point.",
+    },
+    Lesson {
+        title: "MTR atomicity",
+        intro: "A mini-transaction (MTR) can write several pages; only its last record is a CPL.\n\
+                If a crash tears the MTR, recovery sets VDL to the last CPL before it and the\n\
+                records that did reach disk are thrown away. Pages 5 and 6 hold \"left-v1\" and\n\
+                \"right-v1\".",
+        tiered: false,
+        setup: || vec![put(5, "left-v1"), put(6, "right-v1")],
+        checkpoints: &[
+            Checkpoint {
+                task: "Update both pages in one MTR.",
+                hint: "mput 5:left-v2 6:right-v2",
+                goal: Goal::PutMulti { node: "A", pages: &[5, 6] },
+            },
+            Checkpoint {
+                task: "Tear the MTR: cut the last few bytes off the WAL, as a crash mid-write would.",
+                hint: "corrupt wal -10",
+                goal: Goal::TruncateWal,
+            },
+            Checkpoint {
+                task: "Run recovery and watch VCL and VDL.",
+                hint: "recover",
+                goal: Goal::Recover,
+            },
+            Checkpoint {
+                task: "Node A's read point is now past the recovered VDL. Bring it back.",
+                hint: "refresh",
+                goal: Goal::Refresh { node: "A" },
+            },
+            Checkpoint {
+                task: "Read page 5. Its v2 record was intact on disk, yet it reads \"left-v1\".",
+                hint: "get 5",
+                goal: Goal::Read { node: "A", page_id: 5, expect: "left-v1" },
+            },
+        ],
+        outro: "pg5's record survived the tear but sat above VDL, so it was truncated with the rest\n\
+                of the MTR. Either both pages change or neither does.",
+    },
+    Lesson {
+        title: "Crash recovery",
+        intro: "Storage keeps its page index and cache in memory; the WAL is the only thing on disk.\n\
+                After a crash, recovery scans the WAL, recomputes VCL and VDL and rebuilds the index.\n\
+                Page 7 holds \"durable\".",
+        tiered: false,
+        setup: || vec![put(7, "durable")],
+        checkpoints: &[
+            Checkpoint {
+                task: "Crash storage.",
+                hint: "crash storage",
+                goal: Goal::Crash,
+            },
+            Checkpoint {
+                task: "Try to read page 7. It is not in A's buffer pool, so the read has to fail.",
+                hint: "get 7",
+                goal: Goal::ReadFails { node: "A", page_id: 7 },
+            },
+            Checkpoint {
+                task: "Recover storage from the WAL.",
+                hint: "recover",
+                goal: Goal::Recover,
+            },
+            Checkpoint {
+                task: "Read page 7 again.",
+                hint: "get 7",
+                goal: Goal::Read { node: "A", page_id: 7, expect: "durable" },
+            },
+        ],
+        outro: "Everything acknowledged before the crash was at or below VDL, so recovery brought\n\
+                it all back.",
+    },
+    Lesson {
+        title: "Tiering",
+        intro: "With the tiered preset the WAL is split into segments. Sealed segments beyond the\n\
+                newest few move to a cold tier that is slow to read. Page 8 was written first and\n\
+                pages 10-29 since, so pg8's only record now sits in a cold segment.",
+        tiered: true,
+        setup: || {
+            let mut steps = vec![put(8, "first")];
+            steps.extend((10..30).map(|page_id| put(page_id, "fill")));
+            steps
+        },
+        checkpoints: &[
+            Checkpoint {
+                task: "Read page 8 and note how long it took.",
+                hint: "get 8",
+                goal: Goal::Read { node: "A", page_id: 8, expect: "first" },
+            },
+            Checkpoint {
+                task: "Read page 29, written last and still in a hot segment.",
+                hint: "get 29",
+                goal: Goal::Read { node: "A", page_id: 29, expect: "fill" },
+            },
+            Checkpoint {
+                task: "Read page 8 again. It is in A's buffer pool now, so no cold read.",
+                hint: "get 8",
+                goal: Goal::Read { node: "A", page_id: 8, expect: "first" },
+            },
+        ],
+        outro: "Only the first read of pg8 paid the cold-tier latency; the buffer pool hides it\n\
+                after that.",
+    },
+];
+
+/// A line typed at the tutorial prompt.
+enum Input {
+    Step(ScenarioStep),
+    Node(String),
+    State,
+    Hint,
+    Skip,
+    Reset,
+    Help,
+    Quit,
+}
+
+const HELP: &str = "Commands: put <page> <offset> <text>, mput <page>:<text> ..., get <page>, refresh\n\
+                    \x20         node <label>, state, crash storage, corrupt wal -<bytes>, recover\n\
+                    \x20         hint, skip (next lesson), reset (restart lesson), help, quit";
+
+fn parse_input(line: &str, node: &str) -> Result<Input, String> {
+    let node = Some(node.to_string());
+    // A learner's step that fails is part of the lesson, not the end of it
+    let allow_error = true;
+    let parts: Vec<&str> = line.splitn(4, ' ').collect();
+    let page = |i: usize| -> Result<PageId, String> {
+        parts.get(i).and_then(|p| p.parse().ok()).ok_or_else(|| "expected a page number".to_string())
+    };
+    Ok(match parts[0] {
+        "put" if parts.len() == 4 => {
+            let offset = parts[2].parse().map_err(|_| "expected an offset".to_string())?;
+            let data = parts[3].to_string();
+            Input::Step(ScenarioStep::Put { page_id: page(1)?, offset, data, node, allow_error })
+        }
+        "put" => return Err("Usage: put <page> <offset> <text>".to_string()),
+        "mput" => {
+            let writes = line
+                .split_whitespace()
+                .skip(1)
+                .map(|w| {
+                    let (page_id, data) = w.split_once(':').ok_or_else(|| format!("expected <page>:<text>, got {w:?}"))?;
+                    let page_id = page_id.parse().map_err(|_| format!("bad page number in {w:?}"))?;
+                    Ok(PageWrite { page_id, offset: 0, data: data.to_string() })
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            if writes.is_empty() {
+                return Err("Usage: mput <page>:<text> [<page>:<text> ...]".to_string());
+            }
+            Input::Step(ScenarioStep::PutMulti { writes, node, allow_error })
+        }
+        "get" => Input::Step(ScenarioStep::Get { page_id: page(1)?, node, expect: None, allow_error }),
+        "refresh" => Input::Step(ScenarioStep::Refresh { node, allow_error }),
+        "node" => match parts.get(1) {
+            Some(label) => Input::Node(label.to_uppercase()),
+            None => return Err("Usage: node <label>".to_string()),
+        },
+        "state" => Input::State,
+        "crash" if parts.get(1) == Some(&"storage") => Input::Step(ScenarioStep::CrashStorage),
+        "corrupt" if parts.get(1) == Some(&"wal") => {
+            match parts.get(2).and_then(|b| b.strip_prefix('-')).and_then(|b| b.parse().ok()) {
+                Some(bytes) => Input::Step(ScenarioStep::TruncateWal { bytes }),
+                None => return Err("Usage: corrupt wal -<bytes>".to_string()),
+            }
+        }
+        "recover" => Input::Step(ScenarioStep::RestartStorage),
+        "hint" => Input::Hint,
+        "skip" => Input::Skip,
+        "reset" => Input::Reset,
+        "help" => Input::Help,
+        "quit" | "exit" => Input::Quit,
+        other => return Err(format!("Unknown command: {other} (try `help`)")),
+    })
+}
+
+/// How a lesson ended.
+enum LessonEnd {
+    Completed,
+    Skipped,
+    Reset,
+    Quit,
+}
+
+/// Run the lessons in order, starting at `first` (1-based).
+pub async fn run_tutorial(session: &Session, first: usize, disks: TierDisks) -> anyhow::Result<()> {
+    println!("=== Mini-Aurora Tutorial ===");
+    for (i, lesson) in LESSONS.iter().enumerate() {
+        println!("  {}. {}", i + 1, lesson.title);
+    }
+    println!("{HELP}");
+
+    // Page completion follows whichever lesson's storage is open
+    let storage_slot: Arc<Mutex<Option<Arc<VizStorageEngine>>>> = Arc::default();
+    let pages_slot = storage_slot.clone();
+    let mut editor = LineEditor::new(ReplHelper::new(TUTORIAL_COMMANDS, move || {
+        pages_slot.lock().unwrap().as_ref().map(|s| s.page_stats().into_keys().collect()).unwrap_or_default()
+    }))?;
+
+    let mut index = first.clamp(1, LESSONS.len()) - 1;
+    while index < LESSONS.len() {
+        let lesson = &LESSONS[index];
+        session.clear()?;
+        println!("\n── Lesson {}/{}: {} ──", index + 1, LESSONS.len(), lesson.title);
+        println!("{}\n", lesson.intro);
+
+        let renderer = Arc::new(Mutex::new(VizRenderer::new(VizConfig {
+            step_delay: Duration::ZERO,
+            color: false,
+            enabled: false,
+            step: false,
+            lanes: false,
+            metrics_strip: false,
+        })));
+        let preset = if lesson.tiered { "tiered" } else { "base" };
+        let storage = scenario::open_storage(session, preset, TUTORIAL_SEGMENT_SIZE, disks, &renderer)?;
+        *storage_slot.lock().unwrap() = Some(storage.clone());
+        // The tutorial starts no bg workers, so their output goes nowhere
+        let (bg_output_tx, _bg_output_rx) = tokio::sync::mpsc::unbounded_channel();
+        let labels: Vec<String> = NODES.iter().map(|l| l.to_string()).collect();
+        let cluster = Cluster::start(storage, &labels, renderer, bg_output_tx).await?;
+        scenario::execute_steps(&(lesson.setup)(), &cluster).await?;
+
+        let end = run_lesson(lesson, &cluster, &mut editor).await?;
+        drop(cluster);
+        *storage_slot.lock().unwrap() = None;
+        match end {
+            LessonEnd::Completed => {
+                println!("\n{}", lesson.outro);
+                index += 1;
+            }
+            LessonEnd::Skipped => index += 1,
+            LessonEnd::Reset => {}
+            LessonEnd::Quit => return Ok(()),
+        }
+    }
+    println!("\nAll lessons done. `mini-aurora viz-repl` lets you keep exploring.");
+    Ok(())
+}
+
+async fn run_lesson(lesson: &Lesson, cluster: &Cluster, editor: &mut LineEditor) -> anyhow::Result<LessonEnd> {
+    let mut node = NODES[0].to_string();
+    let total = lesson.checkpoints.len();
+    for (i, checkpoint) in lesson.checkpoints.iter().enumerate() {
+        println!("\n[{}/{total}] {}", i + 1, checkpoint.task);
+        loop {
+            let Some(line) = editor.read_line(&format!("tutorial [{node}]> ")) else {
+                return Ok(LessonEnd::Quit);
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let input = match parse_input(line, &node) {
+                Ok(input) => input,
+                Err(msg) => {
+                    println!("{msg}");
+                    continue;
+                }
+            };
+            let verdict = match input {
+                Input::Step(mut step) => {
+                    checkpoint.goal.arm(&mut step);
+                    let started = Instant::now();
+                    let outcome = scenario::execute_step(&step, cluster).await.unwrap_or_else(|e| {
+                        println!("  Error: {}", explain_any(&e));
+                        StepOutcome::Error(e.to_string())
+                    });
+                    println!("  took {}", format_elapsed(started.elapsed()));
+                    checkpoint.goal.judge(&step, &outcome, cluster).await
+                }
+                Input::Node(label) => {
+                    if cluster.nodes.contains_key(&label) {
+                        println!("Switched to node {label}");
+                        node = label;
+                    } else {
+                        println!("Unknown node: {label} (nodes: {})", NODES.join(", "));
+                    }
+                    Verdict::NotYet
+                }
+                Input::State => {
+                    print_state(cluster).await;
+                    if matches!(checkpoint.goal, Goal::State) { Verdict::Passed } else { Verdict::NotYet }
+                }
+                Input::Hint => {
+                    println!("Try: {}", checkpoint.hint);
+                    continue;
+                }
+                Input::Help => {
+                    println!("{HELP}");
+                    continue;
+                }
+                Input::Skip => return Ok(LessonEnd::Skipped),
+                Input::Reset => return Ok(LessonEnd::Reset),
+                Input::Quit => return Ok(LessonEnd::Quit),
+            };
+            match verdict {
+                Verdict::Passed => {
+                    println!("✓ Checkpoint passed");
+                    break;
+                }
+                Verdict::NotYet => {}
+                Verdict::Wrong(why) => println!("✗ Not quite: {why}. Hint: {}", checkpoint.hint),
+            }
+        }
+    }
+    Ok(LessonEnd::Completed)
+}
+
+/// Watermarks, plus each node's read point and buffer pool.
+async fn print_state(cluster: &Cluster) {
+    match cluster.storage.get_durability_state().await {
+        Ok(d) => println!("  Storage: VCL=L{} VDL=L{}", d.vcl, d.vdl),
+        Err(e) => println!("  Storage: {e}"),
+    }
+    for label in NODES {
+        let compute = &cluster.nodes[label];
+        let cached: Vec<String> = compute
+            .buffer_pool_entries()
+            .await
+            .iter()
+            .map(|e| format!("pg{}@L{}", e.page_id, e.read_point))
+            .collect();
+        let cached = if cached.is_empty() { "(empty)".to_string() } else { cached.join(" ") };
+        println!("  Node {label}: read point L{}, buffer pool {cached}", compute.read_point().await);
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::events::VizEvent;
+use super::renderer::VizRenderer;
+
+/// An event on its way to the renderer, numbered in the order its hook
+/// fired.
+pub struct QueuedEvent {
+    pub seq: u64,
+    /// When the hook fired, so traces and phase timings don't include the
+    /// wait to be rendered.
+    pub at: Instant,
+    /// The node the event was taken for, if any.
+    pub node: Option<String>,
+    pub event: VizEvent,
+}
+
+enum Message {
+    Event(Box<QueuedEvent>),
+    /// Reply on `done` once every event numbered below `through` is rendered.
+    Flush { through: u64, done: mpsc::Sender<()> },
+}
+
+/// Carries events from every node's hooks to a dedicated render thread.
+///
+/// Hooks fire with engine locks held, and rendering an event can sleep for
+/// `step_delay`, so publishing never waits: the render thread draws each
+/// event, and feeds the metrics and tracer (and through the tracer the
+/// TUI, web dashboard and trace files), while the engines carry on. One
+/// bus is shared by storage and every node, so events render in the order
+/// their hooks fired across all of them.
+#[derive(Clone)]
+pub struct EventBus {
+    next_seq: Arc<AtomicU64>,
+    tx: mpsc::Sender<Message>,
+    /// Nanoseconds callers have spent waiting on the renderer.
+    waited: Arc<AtomicU64>,
+}
+
+impl EventBus {
+    /// Start the render thread for `renderer`. It exits once every handle
+    /// on the bus is dropped.
+    pub fn start(renderer: Arc<Mutex<VizRenderer>>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("viz-render".to_string())
+            .spawn(move || render_loop(rx, &renderer))
+            .expect("failed to spawn the viz render thread");
+        Self { next_seq: Arc::new(AtomicU64::new(0)), tx, waited: Arc::new(AtomicU64::new(0)) }
+    }
+
+    pub fn publish(&self, event: VizEvent, node: Option<String>) {
+        let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
+        let queued = QueuedEvent { seq, at: Instant::now(), node, event };
+        // The render thread only stops if rendering panicked
+        let _ = self.tx.send(Message::Event(Box::new(queued)));
+    }
+
+    /// Wait until every event published so far has been rendered, so an
+    /// operation's steps are drawn before its result is shown or the next
+    /// header replaces them. Must not be called with an engine lock or the
+    /// renderer held.
+    pub fn flush(&self) {
+        self.wait_for(|| {
+            let through = self.next_seq.load(Ordering::SeqCst);
+            let (done, rendered) = mpsc::channel();
+            if self.tx.send(Message::Flush { through, done }).is_ok() {
+                let _ = rendered.recv();
+            }
+        });
+    }
+
+    /// Run `f`, which waits on the renderer, counting its time in
+    /// `waited`.
+    pub fn wait_for<T>(&self, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let out = f();
+        self.waited.fetch_add(started.elapsed().as_nanos() as u64, Ordering::SeqCst);
+        out
+    }
+
+    /// Total time callers have spent in `flush` or `wait_for`. Rendering
+    /// otherwise runs alongside the engines, so operation timers subtract
+    /// only the growth of this value.
+    pub fn waited(&self) -> Duration {
+        Duration::from_nanos(self.waited.load(Ordering::SeqCst))
+    }
+}
+
+/// Render events in sequence order. Two hooks racing to publish can arrive
+/// out of order, so early events wait until the ones before them arrive.
+fn render_loop(rx: mpsc::Receiver<Message>, renderer: &Mutex<VizRenderer>) {
+    let mut next_seq = 0;
+    let mut early = BTreeMap::new();
+    let mut flushes: Vec<(u64, mpsc::Sender<()>)> = Vec::new();
+    for message in rx {
+        match message {
+            Message::Event(queued) => {
+                early.insert(queued.seq, *queued);
+                while let Some(queued) = early.remove(&next_seq) {
+                    renderer.lock().unwrap().render(&queued.event, queued.node, queued.at);
+                    next_seq += 1;
+                }
+            }
+            Message::Flush { through, done } => flushes.push((through, done)),
+        }
+        flushes.retain(|(through, done)| {
+            if *through > next_seq {
+                return true;
+            }
+            let _ = done.send(());
+            false
+        });
+    }
+}
@@ -0,0 +1,52 @@
+//! ASCII diagram of a page's redo chain for the REPL `chain` command.
+
+use mini_aurora_common::PageId;
+use mini_aurora_storage::engine::HistoryEntry;
+use mini_aurora_wal::segment::Tier;
+
+use super::clock::format_utc;
+
+/// Draw the chain newest first, one line per record (with its append time,
+/// UTC), linked by prev_lsn:
+///
+/// ```text
+/// pg1 ─▶ L4 @112    12:00:05.120  seg2 HOT   write [16..19)     mtr#2 CPL
+///         │ prev
+///         ▼
+///        L1 @0      12:00:01.004  seg1 COLD  write [0..4)       mtr#1 CPL
+///         ╧ start of chain
+/// ```
+pub fn format_chain(page_id: PageId, history: &[HistoryEntry]) -> String {
+    let mut out = format!(
+        "Redo chain for pg{page_id}: {} record{}, newest first\n",
+        history.len(),
+        if history.len() == 1 { "" } else { "s" }
+    );
+    let lead = format!("pg{page_id} ─▶ ");
+    let indent = " ".repeat(lead.chars().count());
+    let lsn_width = history.iter().map(|e| e.lsn.to_string().len()).max().unwrap_or(1) + 1;
+
+    for (i, entry) in history.iter().enumerate() {
+        let location = match entry.segment {
+            Some((id, Tier::Hot)) => format!("seg{id} HOT "),
+            Some((id, Tier::Cold)) => format!("seg{id} COLD"),
+            None => "wal".to_string(),
+        };
+        let range = format!("[{}..{})", entry.offset, entry.offset as usize + entry.len as usize);
+        out.push_str(&format!(
+            "{}{:<lsn_width$} @{:<6} {}  {location:<10} write {range:<12} mtr#{}{}\n",
+            if i == 0 { &lead } else { &indent },
+            format!("L{}", entry.lsn),
+            entry.file_offset,
+            format_utc(entry.timestamp),
+            entry.mtr_id,
+            if entry.is_mtr_end { " CPL" } else { "" },
+        ));
+        if entry.prev_lsn == 0 {
+            out.push_str(&format!("{indent} ╧ start of chain\n"));
+        } else {
+            out.push_str(&format!("{indent} │ prev\n{indent} ▼\n"));
+        }
+    }
+    out
+}
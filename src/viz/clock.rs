@@ -0,0 +1,57 @@
+//! Wall-clock times for redo records, for the REPL `chain` and `asof`
+//! commands. Times are UTC; storage timestamps are microseconds since the
+//! Unix epoch.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use mini_aurora_common::Timestamp;
+
+const MICROS_PER_SEC: u64 = 1_000_000;
+const SECS_PER_DAY: u64 = 86_400;
+
+pub fn now_micros() -> Timestamp {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_micros() as Timestamp)
+}
+
+/// Time of day as `HH:MM:SS.mmm`.
+pub fn format_utc(timestamp: Timestamp) -> String {
+    let secs = timestamp / MICROS_PER_SEC % SECS_PER_DAY;
+    let millis = timestamp % MICROS_PER_SEC / 1000;
+    format!("{:02}:{:02}:{:02}.{millis:03}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Parse a point in time relative to `now`: either an age (`-30s`, `-5m`,
+/// `-2h`) or a UTC time of day (`HH:MM[:SS[.mmm]]`), taken as the most
+/// recent such moment.
+pub fn parse_time(s: &str, now: Timestamp) -> Option<Timestamp> {
+    if let Some(age) = s.strip_prefix('-') {
+        let (n, unit) = age.split_at(age.find(|c: char| !c.is_ascii_digit())?);
+        let secs = n.parse::<u64>().ok()?
+            * match unit {
+                "s" => 1,
+                "m" => 60,
+                "h" => 3600,
+                _ => return None,
+            };
+        return Some(now.saturating_sub(secs * MICROS_PER_SEC));
+    }
+
+    let (hms, millis) = match s.split_once('.') {
+        Some((hms, ms)) if ms.len() <= 3 => (hms, format!("{ms:0<3}").parse::<u64>().ok()?),
+        Some(_) => return None,
+        None => (s, 0),
+    };
+    let fields: Vec<u64> = hms.split(':').map(|f| f.parse().ok()).collect::<Option<_>>()?;
+    let (h, m, sec) = match fields[..] {
+        [h, m] => (h, m, 0),
+        [h, m, sec] => (h, m, sec),
+        _ => return None,
+    };
+    if h >= 24 || m >= 60 || sec >= 60 {
+        return None;
+    }
+    let time_of_day = ((h * 60 + m) * 60 + sec) * MICROS_PER_SEC + millis * 1000;
+    let midnight = now - now % (SECS_PER_DAY * MICROS_PER_SEC);
+    let today = midnight + time_of_day;
+    Some(if today <= now { today } else { today.saturating_sub(SECS_PER_DAY * MICROS_PER_SEC) })
+}
@@ -1,29 +1,157 @@
+use std::future::Future;
 use std::sync::{Arc, Mutex};
-
-use mini_aurora_common::{Lsn, Page, PageId, StorageApi, StorageError, PAGE_SIZE};
-use mini_aurora_compute::buffer_pool::BufferPool;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use mini_aurora_common::{
+    AppendedRedo, Bytes, Capabilities, DurabilityState, EngineObserver, Lsn, PageId, PageRef, QosClass,
+    RedoRecord, StorageApi, StorageError,
+};
+use mini_aurora_compute::batch::WriteBatching;
+use mini_aurora_compute::buffer_pool::{BufferPoolEntry, BufferPoolStats};
+use mini_aurora_compute::engine::{ComputeEngine, WritePolicy};
+use mini_aurora_compute::health::{HeartbeatConfig, StorageHealth};
 use mini_aurora_compute::transaction::MiniTransaction;
-use tokio::sync::Mutex as TokioMutex;
+use mini_aurora_storage::qos::as_caller;
 
+use super::bus::EventBus;
 use super::engine::VizStorageEngine;
-use super::events::VizEvent;
+use super::metrics::Op;
+use super::observer::{on_behalf_of, RendererObserver, TeeObserver};
+use super::oplog::OpRecorder;
 use super::renderer::VizRenderer;
 
-/// Compute engine with visualization events emitted between each internal step.
+/// `ComputeEngine` node whose internal steps are rendered as `VizEvent`s.
 ///
-/// Mirrors `ComputeEngine` from `crates/compute/src/engine.rs` but uses
-/// `VizStorageEngine` and emits events through a shared renderer.
+/// Adds what the visualization needs around each operation — a header, a
+/// traced operation scope, latency timing and a closing state snapshot —
+/// and a simulated network link to storage for scenario faults. Steps go
+/// out on storage's `EventBus`, so they render in order with the steps
+/// storage takes for them.
 pub struct VizComputeEngine {
+    engine: ComputeEngine,
     storage: Arc<VizStorageEngine>,
-    inner: TokioMutex<ComputeInner>,
+    link: Arc<NetworkLink>,
     renderer: Arc<Mutex<VizRenderer>>,
+    bus: EventBus,
     label: String,
+    /// Writes each operation to the renderer's oplog.
+    ops: Arc<OpRecorder>,
+}
+
+/// Simulated link between this node and storage, driven by scenario faults.
+#[derive(Debug, Clone, Copy, Default)]
+struct NetworkSim {
+    partitioned: bool,
+    latency: Duration,
 }
 
-struct ComputeInner {
-    buffer_pool: BufferPool,
-    next_mtr_id: u64,
-    read_point: Lsn,
+/// The node's path to storage: every call crosses the simulated network,
+/// and the steps storage takes for it are tagged with the node. Reads
+/// served from the buffer pool never reach it.
+struct NetworkLink {
+    storage: Arc<VizStorageEngine>,
+    label: String,
+    sim: Mutex<NetworkSim>,
+}
+
+impl NetworkLink {
+    async fn cross(&self) -> Result<(), StorageError> {
+        let sim = *self.sim.lock().unwrap();
+        if sim.partitioned {
+            return Err(StorageError::Partitioned { node: self.label.clone() });
+        }
+        if !sim.latency.is_zero() {
+            tokio::time::sleep(sim.latency).await;
+        }
+        Ok(())
+    }
+
+    /// Cross the network, then send `call` to storage as this node: its
+    /// steps are tagged with the node, and storage admits it as a
+    /// foreground caller.
+    async fn hop<F: Future>(&self, call: F) -> Result<F::Output, StorageError> {
+        self.cross().await?;
+        Ok(as_caller(&self.label, QosClass::Foreground, on_behalf_of(&self.label, call)).await)
+    }
+}
+
+#[async_trait]
+impl StorageApi for NetworkLink {
+    async fn append_redo_lsns(&self, records: Vec<RedoRecord>) -> Result<AppendedRedo, StorageError> {
+        self.hop(self.storage.append_redo_lsns(records)).await?
+    }
+
+    async fn get_page(&self, page_id: PageId, read_point: Lsn) -> Result<PageRef, StorageError> {
+        self.hop(self.storage.get_page(page_id, read_point)).await?
+    }
+
+    async fn get_durability_state(&self) -> Result<DurabilityState, StorageError> {
+        self.hop(self.storage.get_durability_state()).await?
+    }
+
+    /// The volume fixes its page size, so asking doesn't cross the network.
+    fn page_size(&self) -> usize {
+        self.storage.page_size()
+    }
+
+    /// Heartbeats cross the network but skip admission control, so a rate
+    /// limit can't make storage look dead.
+    async fn heartbeat(&self) -> Result<DurabilityState, StorageError> {
+        self.cross().await?;
+        on_behalf_of(&self.label, self.storage.heartbeat()).await
+    }
+
+    async fn handshake(&self, compute: &Capabilities) -> Result<Capabilities, StorageError> {
+        self.cross().await?;
+        on_behalf_of(&self.label, self.storage.handshake(compute)).await
+    }
+
+    /// Like heartbeats, lease renewals skip admission control, so a rate
+    /// limit can't let a live reader's lease run out.
+    async fn register_reader(
+        &self,
+        reader: &str,
+        read_point: Lsn,
+        lease: Duration,
+    ) -> Result<(), StorageError> {
+        self.cross().await?;
+        on_behalf_of(&self.label, self.storage.register_reader(reader, read_point, lease)).await
+    }
+
+    async fn deregister_reader(&self, reader: &str) -> Result<(), StorageError> {
+        self.cross().await?;
+        on_behalf_of(&self.label, self.storage.deregister_reader(reader)).await
+    }
+
+    fn attach_writer(&self) {
+        self.storage.attach_writer();
+    }
+
+    fn detach_writer(&self) {
+        self.storage.detach_writer();
+    }
+
+    fn writers(&self) -> usize {
+        self.storage.writers()
+    }
+}
+
+/// Ends the current traced operation on drop, including on early returns,
+/// once the steps it published have been rendered and its latency
+/// waterfall drawn.
+struct OpScope<'a> {
+    renderer: &'a Mutex<VizRenderer>,
+    bus: &'a EventBus,
+}
+
+impl Drop for OpScope<'_> {
+    fn drop(&mut self) {
+        self.bus.flush();
+        let mut renderer = self.renderer.lock().unwrap();
+        renderer.render_latency_waterfall();
+        renderer.end_operation();
+    }
 }
 
 impl VizComputeEngine {
@@ -33,95 +161,147 @@ impl VizComputeEngine {
         renderer: Arc<Mutex<VizRenderer>>,
         label: String,
     ) -> Self {
-        renderer.lock().unwrap().register_node(&label);
+        let oplog = {
+            let mut renderer = renderer.lock().unwrap();
+            renderer.register_node(&label);
+            renderer.oplog()
+        };
+        let ops = Arc::new(OpRecorder::new(label.clone(), oplog));
+        let link = Arc::new(NetworkLink {
+            storage: storage.clone(),
+            label: label.clone(),
+            sim: Mutex::new(NetworkSim::default()),
+        });
+        let bus = storage.bus();
+        let observer: Arc<dyn EngineObserver> = Arc::new(TeeObserver::new(
+            Arc::new(RendererObserver::for_node(bus.clone(), &label)),
+            ops.clone(),
+        ));
+        let engine = ComputeEngine::new(link.clone(), buffer_pool_capacity).with_observer(observer);
         Self {
+            engine,
             storage,
-            inner: TokioMutex::new(ComputeInner {
-                buffer_pool: BufferPool::new(buffer_pool_capacity),
-                next_mtr_id: 1,
-                read_point: 0,
-            }),
+            link,
             renderer,
+            bus,
             label,
+            ops,
         }
     }
 
-    fn emit(&self, event: &VizEvent) {
-        self.renderer.lock().unwrap().render(event);
+    /// Set how writes update the buffer pool. Defaults to `Invalidate`.
+    pub fn with_write_policy(mut self, policy: WritePolicy) -> Self {
+        self.engine = self.engine.with_write_policy(policy);
+        self
+    }
+
+    /// Send this node's puts to storage in batches.
+    pub fn with_write_batching(mut self, batching: WriteBatching) -> Self {
+        self.engine = self.engine.with_write_batching(batching);
+        self
+    }
+
+    /// Charge this node's buffer pool against storage's memory budget.
+    pub fn with_memory_budget(mut self) -> Self {
+        self.engine = self.engine.with_memory_budget(&self.storage.memory_budget());
+        self
+    }
+
+    /// Ping storage across the simulated link, so partitions and added
+    /// latency show up as missed beats.
+    pub fn with_heartbeat(mut self, config: HeartbeatConfig) -> Self {
+        self.engine = self.engine.with_heartbeat(config);
+        self
+    }
+
+    /// Register this node's oldest read point with storage under its label,
+    /// renewed across the simulated link.
+    pub fn with_reader_lease(mut self, lease: Duration) -> Self {
+        self.engine = self.engine.with_reader_lease(self.label.clone(), lease);
+        self
     }
 
     fn render_op_header(&self, op: &str) {
-        self.renderer.lock().unwrap().render_operation_header(op);
+        self.bus.wait_for(|| self.renderer.lock().unwrap().render_operation_header(op));
     }
 
     fn reset_steps(&self) {
         self.renderer.lock().unwrap().reset_steps(None);
     }
 
-    fn set_active(&self) {
-        self.renderer.lock().unwrap().set_active_node(&self.label);
+    /// Cut (or restore) this node's link to storage. Reads served from the
+    /// buffer pool still succeed while partitioned.
+    pub fn set_partitioned(&self, partitioned: bool) {
+        self.link.sim.lock().unwrap().partitioned = partitioned;
     }
 
-    /// Write bytes to a page at a given offset. Single-record MTR.
-    pub async fn put(
-        &self,
-        page_id: PageId,
-        offset: u16,
-        data: Vec<u8>,
-    ) -> Result<Lsn, StorageError> {
-        if offset as usize + data.len() > PAGE_SIZE {
-            return Err(StorageError::PageOverflow {
-                offset,
-                len: data.len(),
-            });
-        }
+    /// Add a delay to every storage round trip from this node.
+    pub fn set_network_latency(&self, latency: Duration) {
+        self.link.sim.lock().unwrap().latency = latency;
+    }
 
-        self.set_active();
-        self.render_op_header(&format!(
-            "Node {}: PUT pg{page_id} offset={offset} {:?}",
-            self.label,
-            String::from_utf8_lossy(&data)
-        ));
-        self.reset_steps();
+    /// Start timing an operation. Time spent waiting on the renderer is
+    /// tracked alongside so it can be subtracted from the latency.
+    fn start_timer(&self) -> (Instant, Duration) {
+        (Instant::now(), self.bus.waited())
+    }
 
-        let mtr_id = {
-            let mut inner = self.inner.lock().await;
-            let id = inner.next_mtr_id;
-            inner.next_mtr_id += 1;
-            id
-        };
+    fn elapsed(&self, (started, waited_before): (Instant, Duration)) -> Duration {
+        let viz = self.bus.waited().saturating_sub(waited_before);
+        started.elapsed().saturating_sub(viz)
+    }
 
-        // Step: Create MTR
-        let mut mtr = MiniTransaction::new(mtr_id);
-        mtr.write(page_id, offset, data);
-        let records = mtr.finish().unwrap();
+    fn record_latency(&self, op: Op, timer: (Instant, Duration)) {
+        let elapsed = self.elapsed(timer);
+        self.renderer.lock().unwrap().record_latency(&self.label, op, elapsed);
+        self.ops.finish(None, elapsed);
+    }
 
-        self.emit(&VizEvent::MtrCreated {
-            mtr_id,
-            num_records: records.len(),
-        });
+    /// Log a failed operation; failures are left out of the latency metrics.
+    fn op_failed(&self, error: &StorageError, timer: (Instant, Duration)) {
+        self.ops.finish(Some(error), self.elapsed(timer));
+    }
 
-        // StorageEngine handles its own event emissions
-        let vdl = self.storage.append_redo(records).await?;
+    /// Make this node the active one and start a traced operation, which
+    /// ends when the returned scope is dropped.
+    fn begin_op(&self, op: Op, target: String, header: &str) -> OpScope<'_> {
+        self.ops.begin(op, target);
+        // Steps published by a heartbeat or another node are drawn before
+        // this operation's header
+        self.bus.flush();
+        {
+            let mut renderer = self.renderer.lock().unwrap();
+            renderer.set_active_node(&self.label);
+            renderer.begin_operation(&self.label, op);
+        }
+        self.render_op_header(header);
+        self.reset_steps();
+        OpScope { renderer: &self.renderer, bus: &self.bus }
+    }
 
-        // Step: Invalidate buffer pool + update read point
-        let old_read_point = {
-            let mut inner = self.inner.lock().await;
-            let old = inner.read_point;
-            inner.read_point = vdl;
-            inner.buffer_pool.invalidate(page_id);
-            old
-        };
+    /// Write bytes to a page at a given offset. Single-record MTR.
+    pub async fn put(
+        &self,
+        page_id: PageId,
+        offset: u16,
+        data: impl Into<Bytes>,
+    ) -> Result<Lsn, StorageError> {
+        let timer = self.start_timer();
+        let data = data.into();
+        let _op = self.begin_op(
+            Op::Put,
+            format!("pg{page_id}"),
+            &format!(
+                "Node {}: PUT pg{page_id} offset={offset} {:?}",
+                self.label,
+                String::from_utf8_lossy(&data)
+            ),
+        );
 
-        self.emit(&VizEvent::BufferPoolInvalidate { page_id });
-        self.emit(&VizEvent::UpdateReadPoint {
-            old: old_read_point,
-            new: vdl,
-        });
+        let vdl = self.engine.put(page_id, offset, data).await.inspect_err(|e| self.op_failed(e, timer))?;
 
-        // Show final state diagram
         self.emit_state_snapshot().await;
-
+        self.record_latency(Op::Put, timer);
         Ok(vdl)
     }
 
@@ -130,145 +310,168 @@ impl VizComputeEngine {
         &self,
         writes: Vec<(PageId, u16, Vec<u8>)>,
     ) -> Result<Lsn, StorageError> {
-        for &(_, offset, ref data) in &writes {
-            if offset as usize + data.len() > PAGE_SIZE {
-                return Err(StorageError::PageOverflow {
-                    offset,
-                    len: data.len(),
-                });
-            }
-        }
-
+        let timer = self.start_timer();
         let pages_str: Vec<String> = writes.iter().map(|(pid, _, _)| format!("pg{pid}")).collect();
-        self.set_active();
-        self.render_op_header(&format!(
-            "Node {}: PUT MULTI [{}]",
-            self.label,
-            pages_str.join(", ")
-        ));
-        self.reset_steps();
-
-        let mtr_id = {
-            let mut inner = self.inner.lock().await;
-            let id = inner.next_mtr_id;
-            inner.next_mtr_id += 1;
-            id
-        };
-
-        let mut mtr = MiniTransaction::new(mtr_id);
-        for (page_id, offset, data) in &writes {
-            mtr.write(*page_id, *offset, data.clone());
-        }
-        let records = match mtr.finish() {
-            Some(r) => r,
-            None => return Ok(self.inner.lock().await.read_point),
-        };
+        let _op = self.begin_op(
+            Op::Put,
+            pages_str.join(","),
+            &format!("Node {}: PUT MULTI [{}]", self.label, pages_str.join(", ")),
+        );
 
-        self.emit(&VizEvent::MtrCreated {
-            mtr_id,
-            num_records: records.len(),
-        });
+        let vdl = self.engine.put_multi(writes).await.inspect_err(|e| self.op_failed(e, timer))?;
 
-        let vdl = self.storage.append_redo(records).await?;
+        self.emit_state_snapshot().await;
+        self.record_latency(Op::Put, timer);
+        Ok(vdl)
+    }
 
-        let old_read_point = {
-            let mut inner = self.inner.lock().await;
-            let old = inner.read_point;
-            inner.read_point = vdl;
-            for (page_id, _, _) in &writes {
-                inner.buffer_pool.invalidate(*page_id);
-            }
-            old
-        };
+    /// Start a mini-transaction to stage writes and savepoints on.
+    pub async fn begin(&self) -> MiniTransaction {
+        self.engine.begin().await
+    }
 
-        for (page_id, _, _) in &writes {
-            self.emit(&VizEvent::BufferPoolInvalidate { page_id: *page_id });
-        }
+    /// Send a staged mini-transaction to storage.
+    pub async fn commit(&self, mtr: MiniTransaction) -> Result<Lsn, StorageError> {
+        let timer = self.start_timer();
+        let pages_str: Vec<String> = mtr.page_ids().iter().map(|pid| format!("pg{pid}")).collect();
+        let _op = self.begin_op(
+            Op::Put,
+            pages_str.join(","),
+            &format!("Node {}: COMMIT MTR #{} [{}]", self.label, mtr.mtr_id(), pages_str.join(", ")),
+        );
 
-        self.emit(&VizEvent::UpdateReadPoint {
-            old: old_read_point,
-            new: vdl,
-        });
+        let vdl = self.engine.commit(mtr).await.inspect_err(|e| self.op_failed(e, timer))?;
 
         self.emit_state_snapshot().await;
-
+        self.record_latency(Op::Put, timer);
         Ok(vdl)
     }
 
     /// Read a page at the current read point.
-    pub async fn get(&self, page_id: PageId) -> Result<Page, StorageError> {
-        let read_point = self.inner.lock().await.read_point;
+    pub async fn get(&self, page_id: PageId) -> Result<PageRef, StorageError> {
+        let timer = self.start_timer();
+        let read_point = self.engine.read_point().await;
+        let _op = self.begin_op(
+            Op::Get,
+            format!("pg{page_id}"),
+            &format!("Node {}: GET pg{page_id} @L{read_point}", self.label),
+        );
 
-        self.set_active();
-        self.render_op_header(&format!(
-            "Node {}: GET pg{page_id} @L{read_point}",
-            self.label
-        ));
-        self.reset_steps();
+        let page = self.engine.get(page_id).await.inspect_err(|e| self.op_failed(e, timer))?;
 
-        // Step: Buffer pool lookup
-        {
-            let mut inner = self.inner.lock().await;
-            if let Some(page) = inner.buffer_pool.get(page_id, read_point) {
-                self.emit(&VizEvent::BufferPoolLookup {
-                    page_id,
-                    read_point,
-                    hit: true,
-                });
-                return Ok(*page);
-            }
-        }
+        self.emit_state_snapshot().await;
+        self.record_latency(Op::Get, timer);
+        Ok(page)
+    }
 
-        self.emit(&VizEvent::BufferPoolLookup {
-            page_id,
-            read_point,
-            hit: false,
-        });
+    /// Read several pages at the current read point in one storage round trip.
+    pub async fn get_multi(&self, page_ids: &[PageId]) -> Result<Vec<PageRef>, StorageError> {
+        let timer = self.start_timer();
+        let read_point = self.engine.read_point().await;
+        let pages_str: Vec<String> = page_ids.iter().map(|pid| format!("pg{pid}")).collect();
+        let _op = self.begin_op(
+            Op::Get,
+            pages_str.join(","),
+            &format!("Node {}: GET MULTI [{}] @L{read_point}", self.label, pages_str.join(", ")),
+        );
 
-        // Fetch from storage (VizStorageEngine emits its own events)
-        let page = self.storage.get_page(page_id, read_point).await?;
+        let pages = self.engine.get_multi(page_ids).await.inspect_err(|e| self.op_failed(e, timer))?;
 
-        // Step: Buffer pool insert
-        {
-            let mut inner = self.inner.lock().await;
-            inner.buffer_pool.insert(page_id, read_point, page);
-        }
+        self.emit_state_snapshot().await;
+        self.record_latency(Op::Get, timer);
+        Ok(pages)
+    }
 
-        self.emit(&VizEvent::BufferPoolInsert { page_id, read_point });
+    /// Read a page as of `lsn`, which may be older than the read point.
+    pub async fn get_at(&self, page_id: PageId, lsn: Lsn) -> Result<PageRef, StorageError> {
+        let timer = self.start_timer();
+        let _op = self.begin_op(
+            Op::Get,
+            format!("pg{page_id}@L{lsn}"),
+            &format!("Node {}: GET pg{page_id} @L{lsn}", self.label),
+        );
 
-        self.emit_state_snapshot().await;
+        let page = self.engine.get_at(page_id, lsn).await.inspect_err(|e| self.op_failed(e, timer))?;
 
+        self.emit_state_snapshot().await;
+        self.record_latency(Op::Get, timer);
         Ok(page)
     }
 
+    pub async fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.engine.buffer_pool_stats().await
+    }
+
+    pub async fn buffer_pool_entries(&self) -> Vec<BufferPoolEntry> {
+        self.engine.buffer_pool_entries().await
+    }
+
+    pub async fn clear_buffer_pool(&self) -> usize {
+        self.engine.clear_buffer_pool().await
+    }
+
     pub async fn read_point(&self) -> Lsn {
-        self.inner.lock().await.read_point
+        self.engine.read_point().await
+    }
+
+    pub fn storage_health(&self) -> Option<StorageHealth> {
+        self.engine.storage_health()
     }
 
     pub async fn refresh_read_point(&self) -> Result<Lsn, StorageError> {
-        self.set_active();
-        self.render_op_header(&format!("Node {}: REFRESH read_point", self.label));
-        self.reset_steps();
+        let timer = self.start_timer();
+        let _op = self.begin_op(Op::Refresh, String::new(), &format!("Node {}: REFRESH read_point", self.label));
 
-        let state = self.storage.get_durability_state().await?;
-        let mut inner = self.inner.lock().await;
-        let old = inner.read_point;
-        inner.read_point = state.vdl;
-        drop(inner);
+        let vdl = self.engine.refresh_read_point().await.inspect_err(|e| self.op_failed(e, timer))?;
 
-        self.emit(&VizEvent::UpdateReadPoint { old, new: state.vdl });
         self.emit_state_snapshot().await;
-
-        Ok(state.vdl)
+        self.record_latency(Op::Refresh, timer);
+        Ok(vdl)
     }
 
-    async fn emit_state_snapshot(&self) {
-        let inner = self.inner.lock().await;
+    /// Bring the panel up to date with this node and storage.
+    pub async fn emit_state_snapshot(&self) {
+        let pages = self
+            .engine
+            .buffer_pool_entries()
+            .await
+            .iter()
+            .map(|e| (e.page_id, e.read_point))
+            .collect();
         self.storage.emit_state_snapshot(
             self.label.clone(),
-            inner.read_point,
-            inner.next_mtr_id,
-            Vec::new(), // BufferPool doesn't expose keys
+            self.engine.read_point().await,
+            self.engine.next_mtr_id().await,
+            pages,
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viz::events::{VizConfig, VizEvent};
+    use crate::viz::tracer::{TraceRecord, TraceRing, Tracer};
+
+    #[tokio::test]
+    async fn test_new_registers_the_node_and_labels_its_events() {
+        let config = VizConfig { step_delay: Duration::ZERO, enabled: false, ..Default::default() };
+        let renderer = Arc::new(Mutex::new(VizRenderer::new(config)));
+        let ring = TraceRing::new(256);
+        let mut tracer = Tracer::new();
+        tracer.add_sink(ring.clone());
+        renderer.lock().unwrap().set_tracer(tracer);
+        let storage = Arc::new(VizStorageEngine::open_in_memory(None, renderer.clone()).unwrap());
+
+        let node = VizComputeEngine::new(storage, 16, renderer.clone(), "A".to_string());
+        assert!(renderer.lock().unwrap().state().nodes.contains_key("A"));
+
+        node.put(1, 0, b"hello".to_vec()).await.unwrap();
+        node.bus.flush();
+        assert_eq!(renderer.lock().unwrap().state().active_node, "A");
+        // Recovery's steps at open belong to no operation
+        let put: Vec<TraceRecord> = ring.tail(256).into_iter().filter(|r| r.op == Some(Op::Put)).collect();
+        assert!(put.iter().any(|r| matches!(r.event, VizEvent::AssignLsns { .. })));
+        assert!(put.iter().all(|r| r.node.as_deref() == Some("A")));
+    }
+}
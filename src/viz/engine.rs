@@ -1,117 +1,403 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use mini_aurora_common::{
-    DurabilityState, Lsn, Page, PageId, RedoRecord, StorageApi, StorageError,
-    LOG_ENTRY_HEADER_SIZE, empty_page, PAGE_SIZE,
+    AdminApi, AppendedRedo, Capabilities, CompactReport, DurabilityState, EngineObserver, GcReport, Lsn,
+    MemoryBudget, PageId, PageRef, QosReport, QosTarget, RateLimit, RedoRecord, ScrubReport, StorageApi,
+    StorageError, StorageStats, Timestamp, TruncateReport, PAGE_SIZE,
 };
-use mini_aurora_pagestore::page_cache::PageCache;
-use mini_aurora_wal::reader::{ReadResult, WalReader, header_to_record};
-use mini_aurora_wal::recovery::{recover, RecoveryResult};
-use mini_aurora_wal::segment::{LsnLocation, SegmentManager, Tier};
-use mini_aurora_wal::writer::WalWriter;
+use mini_aurora_storage::alerts::{Alert, RecentAlerts};
+use mini_aurora_storage::config::{StoragePreset, TierDisks, TieredConfig};
+use mini_aurora_storage::engine::{HistoryEntry, PageStats, StorageEngine};
+use mini_aurora_storage::qos::Qos;
+use mini_aurora_wal::checksum::ChecksumKind;
+use mini_aurora_wal::memory::MemoryWal;
+use mini_aurora_wal::segment::SegmentMeta;
 
 use super::events::VizEvent;
-use super::renderer::{VizRenderer, data_preview};
+use super::bus::EventBus;
+use super::observer::RendererObserver;
+use super::renderer::VizRenderer;
 
-/// Storage engine with visualization events emitted between each internal step.
+/// `StorageEngine` whose internal steps are rendered as `VizEvent`s.
 ///
-/// Mirrors `StorageEngine` from `crates/storage/src/engine.rs` but interleaves
-/// `VizEvent` emissions so every sub-operation is observable.
+/// The engine itself reports each step through a `RendererObserver`; this
+/// wrapper only adds the fault hooks the scenario runner needs (crash,
+/// restart, WAL corruption) and state snapshots for the diagram. The steps
+/// go out on an `EventBus` while the engine runs, and each call waits for
+/// them to be rendered once it has returned, so step delays never hold the
+/// engine's locks.
 pub struct VizStorageEngine {
-    inner: Mutex<VizInner>,
+    engine: RwLock<Arc<StorageEngine>>,
     renderer: Arc<Mutex<VizRenderer>>,
+    /// Shared with every node on this storage.
+    bus: EventBus,
+    /// How the engine was opened, so a crashed engine can be restarted.
+    open_params: OpenParams,
+    /// Set by `crash`; every operation fails until `restart`.
+    crashed: AtomicBool,
+    /// Alerts every engine this one has run has raised.
+    alerts: Arc<RecentAlerts>,
 }
 
-enum VizWalBackend {
+#[derive(Clone)]
+enum OpenParams {
     SingleFile {
         wal_path: PathBuf,
-        writer: WalWriter,
-        lsn_offsets: HashMap<Lsn, u64>,
+        page_size: Option<usize>,
     },
-    Segmented {
-        manager: SegmentManager,
-        lsn_offsets: HashMap<Lsn, LsnLocation>,
+    Tiered {
+        base_dir: PathBuf,
+        segment_size_bytes: u64,
+        disks: TierDisks,
+        page_size: Option<usize>,
+    },
+    /// `wal` is a handle on the log every restart recovers from.
+    Memory {
+        wal: MemoryWal,
+        page_size: usize,
     },
-}
-
-struct VizInner {
-    backend: VizWalBackend,
-    page_index: HashMap<PageId, Lsn>,
-    next_lsn: Lsn,
-    durability: DurabilityState,
-    page_cache: PageCache,
 }
 
 impl VizStorageEngine {
-    /// Open or create a storage engine backed by a single WAL file.
+    /// Open or create a storage engine backed by a single WAL file. A new
+    /// volume gets `page_size`-byte pages, `PAGE_SIZE` if `None`.
     pub fn open(
         wal_path: &Path,
+        page_size: Option<usize>,
         renderer: Arc<Mutex<VizRenderer>>,
     ) -> Result<Self, StorageError> {
-        if !wal_path.exists() {
-            std::fs::File::create(wal_path)?;
-        }
-
-        let RecoveryResult {
-            durability,
-            page_index,
-            lsn_offsets,
-        } = recover(wal_path)?;
-
-        let next_lsn = if durability.vdl == 0 { 1 } else { durability.vdl + 1 };
-
-        let writer = WalWriter::open(wal_path)?;
-        let page_cache = PageCache::new(1024);
-
-        Ok(Self {
-            inner: Mutex::new(VizInner {
-                backend: VizWalBackend::SingleFile {
-                    wal_path: wal_path.to_path_buf(),
-                    writer,
-                    lsn_offsets,
-                },
-                page_index,
-                next_lsn,
-                durability,
-                page_cache,
-            }),
+        Self::open_with(
+            OpenParams::SingleFile {
+                wal_path: wal_path.to_path_buf(),
+                page_size,
+            },
             renderer,
-        })
+        )
     }
 
     /// Open a storage engine with segmented WAL and hot/cold tiering.
     pub fn open_tiered(
         base_dir: &Path,
         segment_size_bytes: u64,
-        cold_latency: std::time::Duration,
+        disks: TierDisks,
+        page_size: Option<usize>,
         renderer: Arc<Mutex<VizRenderer>>,
     ) -> Result<Self, StorageError> {
-        let mut manager = SegmentManager::open(base_dir, segment_size_bytes, cold_latency)?;
-        let recovery = manager.recover()?;
+        Self::open_with(
+            OpenParams::Tiered {
+                base_dir: base_dir.to_path_buf(),
+                segment_size_bytes,
+                disks,
+                page_size,
+            },
+            renderer,
+        )
+    }
 
-        let next_lsn = if recovery.durability.vdl == 0 { 1 } else { recovery.durability.vdl + 1 };
-        let page_cache = PageCache::new(1024);
+    /// Open a storage engine on a WAL in memory. Nothing is written to
+    /// disk, and a restart recovers from the log as the crash left it.
+    pub fn open_in_memory(
+        page_size: Option<usize>,
+        renderer: Arc<Mutex<VizRenderer>>,
+    ) -> Result<Self, StorageError> {
+        Self::open_with(
+            OpenParams::Memory {
+                wal: MemoryWal::new(),
+                page_size: page_size.unwrap_or(PAGE_SIZE),
+            },
+            renderer,
+        )
+    }
 
+    fn open_with(
+        open_params: OpenParams,
+        renderer: Arc<Mutex<VizRenderer>>,
+    ) -> Result<Self, StorageError> {
+        let alerts = Arc::new(RecentAlerts::default());
+        let bus = EventBus::start(renderer.clone());
+        let engine = Self::open_engine(&open_params, &renderer, &bus)?.with_alert_sink(alerts.clone());
         Ok(Self {
-            inner: Mutex::new(VizInner {
-                backend: VizWalBackend::Segmented {
-                    manager,
-                    lsn_offsets: recovery.lsn_offsets,
-                },
-                page_index: recovery.page_index,
-                next_lsn,
-                durability: recovery.durability,
-                page_cache,
-            }),
+            engine: RwLock::new(Arc::new(engine)),
             renderer,
+            bus,
+            open_params,
+            crashed: AtomicBool::new(false),
+            alerts,
         })
     }
 
-    fn emit(&self, event: VizEvent) {
-        self.renderer.lock().unwrap().render(&event);
+    /// Run recovery and build a fresh engine from what is on disk. Each
+    /// recovery step is rendered under a `STORAGE: RECOVER` header.
+    fn open_engine(
+        params: &OpenParams,
+        renderer: &Arc<Mutex<VizRenderer>>,
+        bus: &EventBus,
+    ) -> Result<StorageEngine, StorageError> {
+        renderer.lock().unwrap().render_operation_header("STORAGE: RECOVER");
+        let observer: Arc<dyn EngineObserver> = Arc::new(RendererObserver::new(bus.clone()));
+        let engine = Self::recover(params, observer);
+        bus.flush();
+        let engine = engine?;
+        // Recovery may have found segments from an earlier run
+        renderer.lock().unwrap().seed_segments(&engine.segments());
+        Ok(engine)
+    }
+
+    fn recover(params: &OpenParams, observer: Arc<dyn EngineObserver>) -> Result<StorageEngine, StorageError> {
+        Ok(match params {
+            OpenParams::SingleFile { wal_path, page_size } => match page_size {
+                Some(page_size) => StorageEngine::open_with_page_size(wal_path, *page_size, observer)?,
+                None => StorageEngine::open_observed(wal_path, observer)?,
+            },
+            OpenParams::Tiered { base_dir, segment_size_bytes, disks, page_size } => {
+                StorageEngine::open_with_preset_observed(
+                    StoragePreset::Tiered(TieredConfig {
+                        segment_size_bytes: *segment_size_bytes,
+                        disks: *disks,
+                        base_dir: base_dir.clone(),
+                        page_size: *page_size,
+                    }),
+                    observer,
+                )?
+            }
+            OpenParams::Memory { wal, page_size } => {
+                StorageEngine::open_memory_wal(wal.clone(), *page_size, observer)?
+            }
+        })
+    }
+
+    /// The running engine, or `Unavailable` while crashed.
+    fn engine(&self) -> Result<Arc<StorageEngine>, StorageError> {
+        if self.is_crashed() {
+            return Err(StorageError::Unavailable("storage has crashed".to_string()));
+        }
+        Ok(self.engine.read().unwrap().clone())
+    }
+
+    /// Simulate a storage crash: every operation fails until `restart`, and
+    /// all in-memory state (indexes, page cache) is lost.
+    pub fn crash(&self) {
+        self.crashed.store(true, Ordering::SeqCst);
+    }
+
+    /// Bring a crashed (or running) engine back by re-running recovery from
+    /// disk. Returns the recovered durability state. The old engine lets go
+    /// of the volume first, as a dead process would, so if recovery fails
+    /// storage stays crashed.
+    pub fn restart(&self) -> Result<DurabilityState, StorageError> {
+        self.crash();
+        self.engine.read().unwrap().release_lock();
+        let fresh = Self::open_engine(&self.open_params, &self.renderer, &self.bus)?
+            .with_qos(self.qos())
+            .with_memory_budget(self.memory_budget())
+            .with_alert_sink(self.alerts.clone());
+        fresh.set_invariant_checks(self.invariant_checks());
+        fresh.set_promote_on_read(self.promote_on_read());
+        fresh.set_checksum(self.checksum());
+        for _ in 0..self.writers() {
+            fresh.attach_writer();
+        }
+        let durability = fresh.state().durability;
+        *self.engine.write().unwrap() = Arc::new(fresh);
+        self.crashed.store(false, Ordering::SeqCst);
+        Ok(durability)
+    }
+
+    /// Alerts raised so far, oldest first, kept across crashes and restarts.
+    pub fn recent_alerts(&self) -> Vec<Alert> {
+        self.alerts.recent()
+    }
+
+    /// Admission control, kept across crashes and restarts.
+    pub fn qos(&self) -> Arc<Qos> {
+        self.engine.read().unwrap().qos()
+    }
+
+    /// Memory budget for storage and every node's buffer pool, kept across
+    /// crashes and restarts.
+    pub fn memory_budget(&self) -> Arc<MemoryBudget> {
+        self.engine.read().unwrap().memory_budget()
+    }
+
+    /// Check the engine's invariants after every operation, kept across
+    /// crashes and restarts.
+    pub fn set_invariant_checks(&self, on: bool) {
+        self.engine.read().unwrap().set_invariant_checks(on);
+    }
+
+    pub fn invariant_checks(&self) -> bool {
+        self.engine.read().unwrap().invariant_checks()
+    }
+
+    /// Promote cold segments back to the hot tier when read (tiered only),
+    /// kept across crashes and restarts.
+    pub fn set_promote_on_read(&self, on: bool) {
+        self.engine.read().unwrap().set_promote_on_read(on);
+    }
+
+    pub fn promote_on_read(&self) -> bool {
+        self.engine.read().unwrap().promote_on_read()
+    }
+
+    /// Checksum records appended from now on with `checksum`, kept across
+    /// crashes and restarts.
+    pub fn set_checksum(&self, checksum: ChecksumKind) {
+        self.engine.read().unwrap().set_checksum(checksum);
+    }
+
+    pub fn checksum(&self) -> ChecksumKind {
+        self.engine.read().unwrap().checksum()
+    }
+
+    /// Invariants the engine breaks right now; see `StorageEngine::invariant_violations`.
+    pub fn invariant_violations(&self) -> Result<Vec<String>, StorageError> {
+        Ok(self.engine()?.invariant_violations())
+    }
+
+    /// Shut down cleanly, so the next open skips WAL recovery.
+    pub fn close(&self) -> Result<(), StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.close()
+    }
+
+    pub fn is_crashed(&self) -> bool {
+        self.crashed.load(Ordering::SeqCst)
+    }
+
+    /// The WAL, if it is in memory.
+    fn memory_wal(&self) -> Option<&MemoryWal> {
+        match &self.open_params {
+            OpenParams::Memory { wal, .. } => Some(wal),
+            _ => None,
+        }
+    }
+
+    /// Flip every bit of the byte at `offset` in the WAL file (the active
+    /// segment for tiered storage). The damage is found by CRC checks on the
+    /// next read of that entry or the next recovery.
+    pub fn corrupt_wal(&self, offset: u64) -> Result<PathBuf, StorageError> {
+        let path = self.engine.read().unwrap().active_wal_path();
+        if let Some(wal) = self.memory_wal() {
+            let mut bytes = wal.bytes_mut();
+            let len = bytes.len();
+            let byte = bytes.get_mut(offset as usize).ok_or_else(|| {
+                let message = format!("corrupt offset {offset} is past the end of the WAL ({len} bytes)");
+                StorageError::Other(message)
+            })?;
+            *byte = !*byte;
+            return Ok(path);
+        }
+
+        let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let len = file.metadata()?.len();
+        if offset >= len {
+            return Err(StorageError::Other(format!(
+                "corrupt offset {offset} is past the end of {} ({len} bytes)",
+                path.display()
+            )));
+        }
+        let mut byte = [0u8; 1];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut byte)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(&[!byte[0]])?;
+        file.sync_all()?;
+        Ok(path)
+    }
+
+    /// Damage the newest cached image of a page, keeping its checksum, so the
+    /// next read of it fails the check. Returns the damaged version.
+    pub fn corrupt_cached_page(&self, page_id: PageId) -> Result<Option<Lsn>, StorageError> {
+        Ok(self.engine()?.corrupt_cached_page(page_id))
+    }
+
+    /// Append `bytes` of garbage to the WAL file (the active segment for
+    /// tiered storage), as a torn or scribbled write would leave it. The
+    /// next recovery stops scanning there and truncates it away.
+    pub fn append_wal_garbage(&self, bytes: usize) -> Result<PathBuf, StorageError> {
+        let path = self.engine.read().unwrap().active_wal_path();
+        // Deterministic noise, so a demo behaves the same every time
+        let mut x: u32 = 0x9E37_79B9;
+        let garbage: Vec<u8> = (0..bytes)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            })
+            .collect();
+        if let Some(wal) = self.memory_wal() {
+            wal.bytes_mut().extend_from_slice(&garbage);
+            return Ok(path);
+        }
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(&garbage)?;
+        file.sync_all()?;
+        Ok(path)
+    }
+
+    /// Cut `bytes` off the end of the WAL file (the active segment for
+    /// tiered storage), losing the tail as a crash mid-write would.
+    /// Returns the path and the new length.
+    pub fn truncate_wal(&self, bytes: u64) -> Result<(PathBuf, u64), StorageError> {
+        let path = self.engine.read().unwrap().active_wal_path();
+        if let Some(wal) = self.memory_wal() {
+            let mut log = wal.bytes_mut();
+            let len = log.len().saturating_sub(bytes as usize);
+            log.truncate(len);
+            return Ok((path, len as u64));
+        }
+        let file = OpenOptions::new().write(true).open(&path)?;
+        let len = file.metadata()?.len().saturating_sub(bytes);
+        file.set_len(len)?;
+        file.sync_all()?;
+        Ok((path, len))
+    }
+
+    /// Size of the WAL file (the active segment for tiered storage).
+    pub fn wal_len(&self) -> Result<u64, StorageError> {
+        if let Some(wal) = self.memory_wal() {
+            return Ok(wal.len());
+        }
+        let path = self.engine.read().unwrap().active_wal_path();
+        Ok(std::fs::metadata(path)?.len())
+    }
+
+    /// Cold segment reads that were served by segment read-ahead (tiered only).
+    pub fn cold_read_ahead_hits(&self) -> u64 {
+        self.engine.read().unwrap().cold_read_ahead_hits()
+    }
+
+    /// The newest consistency point appended at or before `timestamp`.
+    pub fn lsn_for_time(&self, timestamp: Timestamp) -> Result<Lsn, StorageError> {
+        self.engine()?.lsn_for_time(timestamp)
+    }
+
+    /// A page's redo chain, newest first.
+    pub fn page_history(&self, page_id: PageId) -> Result<Vec<HistoryEntry>, StorageError> {
+        self.engine()?.page_history(page_id)
+    }
+
+    /// Per-page write/read counts and chain lengths.
+    pub fn page_stats(&self) -> BTreeMap<PageId, PageStats> {
+        self.engine.read().unwrap().page_stats()
+    }
+
+    /// Engine-wide record, segment and page totals.
+    pub fn stats(&self) -> StorageStats {
+        self.engine.read().unwrap().stats()
+    }
+
+    /// WAL segments (tiered storage only).
+    pub fn segments(&self) -> Vec<SegmentMeta> {
+        self.engine.read().unwrap().segments()
     }
 
     /// Build and emit a state snapshot event.
@@ -120,406 +406,183 @@ impl VizStorageEngine {
         node_label: String,
         read_point: Lsn,
         next_mtr: u64,
-        buffer_pool_pages: Vec<PageId>,
+        buffer_pool_pages: Vec<(PageId, Lsn)>,
     ) {
-        let inner = self.inner.lock().unwrap();
-
-        let wal_file_size = match &inner.backend {
-            VizWalBackend::SingleFile { wal_path, .. } => {
-                std::fs::metadata(wal_path).map(|m| m.len()).unwrap_or(0)
-            }
-            VizWalBackend::Segmented { .. } => 0, // not tracked per-file for segmented
-        };
-
-        let lsn_offset_count = match &inner.backend {
-            VizWalBackend::SingleFile { lsn_offsets, .. } => lsn_offsets.len(),
-            VizWalBackend::Segmented { lsn_offsets, .. } => lsn_offsets.len(),
-        };
-
-        let wal_lsn_range = if inner.next_lsn > 1 {
-            Some((1, inner.next_lsn - 1))
-        } else {
-            None
-        };
-
+        let state = self.engine.read().unwrap().state();
+        let wal_lsn_range = (state.next_lsn > 1).then(|| (1, state.next_lsn - 1));
         let event = VizEvent::StateSnapshot {
             node_label,
             read_point,
             next_mtr,
             buffer_pool_pages,
-            next_lsn: inner.next_lsn,
-            vcl: inner.durability.vcl,
-            vdl: inner.durability.vdl,
-            page_index: inner.page_index.clone(),
-            lsn_offset_count,
-            page_cache_count: inner.page_cache.len(),
-            wal_file_size,
+            next_lsn: state.next_lsn,
+            vcl: state.durability.vcl,
+            vdl: state.durability.vdl,
+            page_index: state.page_index,
+            lsn_offset_count: state.lsn_offset_count,
+            page_cache_count: state.page_cache_count,
+            wal_file_size: state.wal_file_size,
             wal_lsn_range,
         };
-        drop(inner);
-        self.emit(event);
+        self.bus.publish(event, None);
+        self.bus.flush();
+    }
+
+    /// The bus this engine's steps are rendered from; nodes built on this
+    /// storage publish theirs on it too.
+    pub fn bus(&self) -> EventBus {
+        self.bus.clone()
+    }
+
+    /// Wait for the steps to be rendered once the returned guard is
+    /// dropped, after the engine call it covers has released its locks.
+    fn flush_on_return(&self) -> FlushOnReturn<'_> {
+        FlushOnReturn { bus: &self.bus }
+    }
+}
+
+struct FlushOnReturn<'a> {
+    bus: &'a EventBus,
+}
+
+impl Drop for FlushOnReturn<'_> {
+    fn drop(&mut self) {
+        self.bus.flush();
     }
 }
 
 #[async_trait]
 impl StorageApi for VizStorageEngine {
-    async fn append_redo(&self, mut records: Vec<RedoRecord>) -> Result<Lsn, StorageError> {
-        let mut inner = self.inner.lock().unwrap();
+    async fn append_redo_lsns(&self, records: Vec<RedoRecord>) -> Result<AppendedRedo, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.append_redo_lsns(records).await
+    }
 
-        let first_lsn = inner.next_lsn;
+    async fn get_page(&self, page_id: PageId, read_point: Lsn) -> Result<PageRef, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.get_page(page_id, read_point).await
+    }
 
-        // Step: Assign LSNs
-        for record in &mut records {
-            record.lsn = inner.next_lsn;
-            inner.next_lsn += 1;
-        }
-        let last_lsn = inner.next_lsn - 1;
-
-        self.renderer.lock().unwrap().render(&VizEvent::AssignLsns { first_lsn, last_lsn });
-
-        // Step: Link prev_lsn chains
-        for record in &mut records {
-            record.prev_lsn = inner.page_index.get(&record.page_id).copied().unwrap_or(0);
-            self.renderer.lock().unwrap().render(&VizEvent::LinkPrevLsn {
-                lsn: record.lsn,
-                page_id: record.page_id,
-                prev_lsn: record.prev_lsn,
-            });
-        }
+    async fn get_durability_state(&self) -> Result<DurabilityState, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.get_durability_state().await
+    }
 
-        // Step: WAL append (backend-specific)
-        match &mut inner.backend {
-            VizWalBackend::SingleFile { wal_path, writer, lsn_offsets } => {
-                let wal_offset = std::fs::metadata(wal_path.as_path())
-                    .map(|m| m.len())
-                    .unwrap_or(0);
-                let total_bytes: u64 = records.iter()
-                    .map(|r| LOG_ENTRY_HEADER_SIZE as u64 + r.data.len() as u64)
-                    .sum();
-
-                writer.append_batch(&records)?;
-
-                self.renderer.lock().unwrap().render(&VizEvent::WalAppend {
-                    first_lsn,
-                    last_lsn,
-                    offset: wal_offset,
-                    bytes: total_bytes,
-                });
-
-                writer.sync()?;
-                self.renderer.lock().unwrap().render(&VizEvent::WalSync);
-
-                let mut current_offset = wal_offset;
-                for record in &records {
-                    lsn_offsets.insert(record.lsn, current_offset);
-                    self.renderer.lock().unwrap().render(&VizEvent::UpdateLsnOffset {
-                        lsn: record.lsn,
-                        file_offset: current_offset,
-                    });
-                    current_offset += LOG_ENTRY_HEADER_SIZE as u64 + record.data.len() as u64;
-                }
-            }
-            VizWalBackend::Segmented { manager, lsn_offsets } => {
-                let total_bytes: u64 = records.iter()
-                    .map(|r| LOG_ENTRY_HEADER_SIZE as u64 + r.data.len() as u64)
-                    .sum();
-
-                let locations = manager.append_batch(&records)?;
-
-                self.renderer.lock().unwrap().render(&VizEvent::WalAppend {
-                    first_lsn,
-                    last_lsn,
-                    offset: 0,
-                    bytes: total_bytes,
-                });
-
-                manager.sync()?;
-                self.renderer.lock().unwrap().render(&VizEvent::WalSync);
-
-                for (record, loc) in records.iter().zip(locations.iter()) {
-                    lsn_offsets.insert(record.lsn, *loc);
-                    self.renderer.lock().unwrap().render(&VizEvent::UpdateLsnOffset {
-                        lsn: record.lsn,
-                        file_offset: loc.file_offset,
-                    });
-                }
-            }
-        }
+    async fn heartbeat(&self) -> Result<DurabilityState, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.heartbeat().await
+    }
 
-        // Step: Update page index
-        for record in &records {
-            let entry = inner.page_index.entry(record.page_id).or_insert(0);
-            if record.lsn > *entry {
-                *entry = record.lsn;
-            }
-            self.renderer.lock().unwrap().render(&VizEvent::UpdatePageIndex {
-                page_id: record.page_id,
-                latest_lsn: record.lsn,
-            });
-        }
+    /// Known while crashed too: the volume keeps its page size.
+    fn page_size(&self) -> usize {
+        self.engine.read().unwrap().page_size()
+    }
 
-        // Step: Advance VCL
-        let old_vcl = inner.durability.vcl;
-        let highest_lsn = records.last().map(|r| r.lsn).unwrap_or(inner.durability.vcl);
-        inner.durability.vcl = highest_lsn;
-        self.renderer.lock().unwrap().render(&VizEvent::AdvanceVcl {
-            old: old_vcl,
-            new: inner.durability.vcl,
-        });
-
-        // Step: Advance VDL
-        let old_vdl = inner.durability.vdl;
-        if let Some(cpl) = records.iter().rev().find(|r| r.is_mtr_end) {
-            if cpl.lsn > inner.durability.vdl {
-                inner.durability.vdl = cpl.lsn;
-            }
-        }
-        self.renderer.lock().unwrap().render(&VizEvent::AdvanceVdl {
-            old: old_vdl,
-            new: inner.durability.vdl,
-        });
+    async fn handshake(&self, compute: &Capabilities) -> Result<Capabilities, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.handshake(compute).await
+    }
 
-        Ok(inner.durability.vdl)
+    async fn register_reader(
+        &self,
+        reader: &str,
+        read_point: Lsn,
+        lease: Duration,
+    ) -> Result<(), StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.register_reader(reader, read_point, lease).await
     }
 
-    async fn get_page(&self, page_id: PageId, read_point: Lsn) -> Result<Page, StorageError> {
-        let inner = self.inner.lock().unwrap();
+    async fn deregister_reader(&self, reader: &str) -> Result<(), StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.deregister_reader(reader).await
+    }
 
-        if read_point > inner.durability.vdl {
-            return Err(StorageError::LsnBeyondDurable {
-                requested: read_point,
-                durable: inner.durability.vdl,
-            });
-        }
+    fn attach_writer(&self) {
+        self.engine.read().unwrap().attach_writer();
+    }
 
-        // Step: Page cache lookup
-        if let Some(page) = inner.page_cache.get(page_id, read_point) {
-            self.renderer.lock().unwrap().render(&VizEvent::PageCacheLookup {
-                page_id,
-                read_point,
-                hit: true,
-            });
-            return Ok(page);
-        }
-        self.renderer.lock().unwrap().render(&VizEvent::PageCacheLookup {
-            page_id,
-            read_point,
-            hit: false,
-        });
-
-        // Step: Page index lookup
-        let latest_lsn = inner.page_index.get(&page_id).copied().unwrap_or(0);
-        self.renderer.lock().unwrap().render(&VizEvent::PageIndexLookup {
-            page_id,
-            latest_lsn: if latest_lsn == 0 { None } else { Some(latest_lsn) },
-        });
-
-        if latest_lsn == 0 {
-            return Err(StorageError::PageNotFound {
-                page_id,
-                lsn: read_point,
-            });
-        }
+    fn detach_writer(&self) {
+        self.engine.read().unwrap().detach_writer();
+    }
 
-        // Step: Chain walk (backend-specific)
-        let chain = match &inner.backend {
-            VizWalBackend::SingleFile { wal_path, lsn_offsets, .. } => {
-                self.walk_single_file_chain(page_id, latest_lsn, read_point, wal_path, lsn_offsets)?
-            }
-            VizWalBackend::Segmented { manager, lsn_offsets } => {
-                self.walk_segmented_chain(page_id, latest_lsn, read_point, lsn_offsets, manager)?
-            }
-        };
+    fn writers(&self) -> usize {
+        self.engine.read().unwrap().writers()
+    }
+}
 
-        if chain.is_empty() {
-            return Err(StorageError::PageNotFound {
-                page_id,
-                lsn: read_point,
-            });
-        }
+#[async_trait]
+impl AdminApi for VizStorageEngine {
+    async fn checkpoint(&self) -> Result<Lsn, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.checkpoint().await
+    }
 
-        let lsns: Vec<Lsn> = chain.iter().map(|r| r.lsn).collect();
-        self.renderer.lock().unwrap().render(&VizEvent::ChainCollected {
-            page_id,
-            chain_len: chain.len(),
-            lsns,
-        });
-
-        // Step: Materialize page
-        let mut page = empty_page();
-        for record in &chain {
-            let start = record.offset as usize;
-            let end = start + record.data.len();
-            if end > PAGE_SIZE {
-                return Err(StorageError::PageOverflow {
-                    offset: record.offset,
-                    len: record.data.len(),
-                });
-            }
-            page[start..end].copy_from_slice(&record.data);
-
-            self.renderer.lock().unwrap().render(&VizEvent::MaterializeApply {
-                page_id,
-                lsn: record.lsn,
-                offset: record.offset,
-                data_len: record.data.len(),
-                data_preview: data_preview(&record.data, 20),
-            });
-        }
+    async fn gc(&self) -> Result<GcReport, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.gc().await
+    }
 
-        self.renderer.lock().unwrap().render(&VizEvent::MaterializeComplete { page_id, read_point });
+    /// Re-seeds the SEGMENTS panel, which only learns about segments from
+    /// rotation and cooling events.
+    async fn compact(&self) -> Result<CompactReport, StorageError> {
+        let engine = self.engine()?;
+        let report = engine.compact().await;
+        self.bus.flush();
+        let report = report?;
+        self.renderer.lock().unwrap().seed_segments(&engine.segments());
+        Ok(report)
+    }
 
-        // Step: Cache the result
-        inner.page_cache.insert(page_id, read_point, page);
-        self.renderer.lock().unwrap().render(&VizEvent::PageCacheInsert { page_id, read_point });
+    async fn cool(&self) -> Result<Vec<u32>, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.cool().await
+    }
 
-        Ok(page)
+    async fn scrub(&self) -> Result<ScrubReport, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.scrub().await
     }
 
-    async fn get_durability_state(&self) -> Result<DurabilityState, StorageError> {
-        let inner = self.inner.lock().unwrap();
-        Ok(inner.durability.clone())
+    /// Re-seeds the SEGMENTS panel, like `compact`: deleted segments send
+    /// no event.
+    async fn truncate_before(&self, lsn: Lsn) -> Result<TruncateReport, StorageError> {
+        let engine = self.engine()?;
+        let report = engine.truncate_before(lsn).await;
+        self.bus.flush();
+        let report = report?;
+        self.renderer.lock().unwrap().seed_segments(&engine.segments());
+        Ok(report)
     }
-}
 
-// Private chain-walk helpers
-impl VizStorageEngine {
-    fn walk_single_file_chain(
-        &self,
-        page_id: PageId,
-        latest_lsn: Lsn,
-        read_point: Lsn,
-        wal_path: &Path,
-        lsn_offsets: &HashMap<Lsn, u64>,
-    ) -> Result<Vec<RedoRecord>, StorageError> {
-        let mut chain = Vec::new();
-        let mut current_lsn = latest_lsn;
-        let mut reader = WalReader::open(wal_path)?;
-
-        while current_lsn != 0 {
-            if current_lsn > read_point {
-                let offset = match lsn_offsets.get(&current_lsn) {
-                    Some(&off) => off,
-                    None => break,
-                };
-                reader.seek_to(offset)?;
-                match reader.read_entry()? {
-                    ReadResult::Entry(hdr, _data) => {
-                        self.renderer.lock().unwrap().render(&VizEvent::ChainWalkStep {
-                            page_id,
-                            lsn: current_lsn,
-                            prev_lsn: hdr.prev_lsn,
-                            skipped: true,
-                        });
-                        current_lsn = hdr.prev_lsn;
-                        continue;
-                    }
-                    _ => break,
-                }
-            }
+    async fn stats(&self) -> Result<StorageStats, StorageError> {
+        let _flush = self.flush_on_return();
+        AdminApi::stats(self.engine()?.as_ref()).await
+    }
 
-            let offset = match lsn_offsets.get(&current_lsn) {
-                Some(&off) => off,
-                None => break,
-            };
-            reader.seek_to(offset)?;
-            match reader.read_entry()? {
-                ReadResult::Entry(hdr, data) => {
-                    self.renderer.lock().unwrap().render(&VizEvent::ChainWalkStep {
-                        page_id,
-                        lsn: hdr.lsn,
-                        prev_lsn: hdr.prev_lsn,
-                        skipped: false,
-                    });
-                    let record = header_to_record(&hdr, data);
-                    let prev = hdr.prev_lsn;
-                    chain.push(record);
-                    current_lsn = prev;
-                }
-                _ => break,
-            }
-        }
+    async fn flush_cache(&self) -> Result<u64, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.flush_cache().await
+    }
 
-        chain.reverse();
-        Ok(chain)
+    async fn prefetch(&self, limit: usize) -> Result<usize, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.prefetch(limit).await
     }
 
-    fn walk_segmented_chain(
-        &self,
-        page_id: PageId,
-        latest_lsn: Lsn,
-        read_point: Lsn,
-        lsn_offsets: &HashMap<Lsn, LsnLocation>,
-        manager: &SegmentManager,
-    ) -> Result<Vec<RedoRecord>, StorageError> {
-        let mut chain = Vec::new();
-        let mut current_lsn = latest_lsn;
-        let mut last_segment_id: Option<u32> = None;
-        let mut reader_cache: Option<(WalReader, Tier)> = None;
-
-        while current_lsn != 0 {
-            if current_lsn > read_point {
-                let loc = match lsn_offsets.get(&current_lsn) {
-                    Some(loc) => loc,
-                    None => break,
-                };
-                let (mut reader, _tier) = manager.open_segment_reader(loc.segment_id)?;
-                reader.seek_to(loc.file_offset)?;
-                match reader.read_entry()? {
-                    ReadResult::Entry(hdr, _data) => {
-                        self.renderer.lock().unwrap().render(&VizEvent::ChainWalkStep {
-                            page_id,
-                            lsn: current_lsn,
-                            prev_lsn: hdr.prev_lsn,
-                            skipped: true,
-                        });
-                        current_lsn = hdr.prev_lsn;
-                        continue;
-                    }
-                    _ => break,
-                }
-            }
+    async fn warmup(&self, limit: usize) -> Result<usize, StorageError> {
+        let _flush = self.flush_on_return();
+        self.engine()?.warmup(limit).await
+    }
 
-            let loc = match lsn_offsets.get(&current_lsn) {
-                Some(loc) => loc,
-                None => break,
-            };
-
-            let need_new_reader = last_segment_id != Some(loc.segment_id);
-            if need_new_reader {
-                let (reader, tier) = manager.open_segment_reader(loc.segment_id)?;
-                if tier == Tier::Cold {
-                    let latency_ms = manager.cold_latency().as_millis() as u64;
-                    manager.inject_cold_latency();
-                    self.renderer.lock().unwrap().render(&VizEvent::ColdTierRead {
-                        segment_id: loc.segment_id,
-                        latency_ms,
-                    });
-                }
-                reader_cache = Some((reader, tier));
-                last_segment_id = Some(loc.segment_id);
-            }
+    // Admission control outlives a crash, so these work while crashed
 
-            let (ref mut reader, _) = reader_cache.as_mut().unwrap();
-            reader.seek_to(loc.file_offset)?;
-            match reader.read_entry()? {
-                ReadResult::Entry(hdr, data) => {
-                    self.renderer.lock().unwrap().render(&VizEvent::ChainWalkStep {
-                        page_id,
-                        lsn: hdr.lsn,
-                        prev_lsn: hdr.prev_lsn,
-                        skipped: false,
-                    });
-                    let record = header_to_record(&hdr, data);
-                    let prev = hdr.prev_lsn;
-                    chain.push(record);
-                    current_lsn = prev;
-                }
-                _ => break,
-            }
-        }
+    async fn qos(&self) -> Result<QosReport, StorageError> {
+        Ok(self.qos().report())
+    }
 
-        chain.reverse();
-        Ok(chain)
+    async fn set_rate_limit(&self, target: QosTarget, limit: Option<RateLimit>) -> Result<(), StorageError> {
+        self.qos().set_limit(target, limit);
+        Ok(())
     }
 }
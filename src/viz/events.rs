@@ -4,13 +4,16 @@ use std::time::Duration;
 use mini_aurora_common::{Lsn, PageId};
 
 /// Every discrete internal operation that the visualization can display.
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum VizEvent {
     // ── PUT path ──────────────────────────────────────────────────────
 
     /// A new mini-transaction was created.
     MtrCreated { mtr_id: u64, num_records: usize },
 
+    /// Batched puts' MTRs sent to storage in one append.
+    WriteBatchFlushed { mtrs: usize, records: usize },
+
     /// LSNs assigned to the records in this batch.
     AssignLsns { first_lsn: Lsn, last_lsn: Lsn },
 
@@ -20,8 +23,9 @@ pub enum VizEvent {
     /// Records appended to the WAL file.
     WalAppend { first_lsn: Lsn, last_lsn: Lsn, offset: u64, bytes: u64 },
 
-    /// WAL fsync completed.
-    WalSync,
+    /// WAL fsync completed, taking `duration_us` (from submission when
+    /// the append was pipelined).
+    WalSync { duration_us: u64 },
 
     /// Page index updated: page_id now points to latest_lsn.
     UpdatePageIndex { page_id: PageId, latest_lsn: Lsn },
@@ -41,6 +45,9 @@ pub enum VizEvent {
     /// Buffer pool entry invalidated after writing to a page.
     BufferPoolInvalidate { page_id: PageId },
 
+    /// Write applied to the resident buffer pool image (write-through mode).
+    BufferPoolWriteThrough { page_id: PageId, read_point: Lsn },
+
     // ── GET path ──────────────────────────────────────────────────────
 
     /// Buffer pool lookup on the compute side.
@@ -55,14 +62,14 @@ pub enum VizEvent {
     /// One step of the prev_lsn chain walk (backwards through WAL).
     ChainWalkStep { page_id: PageId, lsn: Lsn, prev_lsn: Lsn, skipped: bool },
 
-    /// Chain collection complete.
-    ChainCollected { page_id: PageId, chain_len: usize, lsns: Vec<Lsn> },
+    /// Chain collection complete, after walking for `duration_us`.
+    ChainCollected { page_id: PageId, chain_len: usize, lsns: Vec<Lsn>, duration_us: u64 },
 
     /// Applying one redo record during materialization.
     MaterializeApply { page_id: PageId, lsn: Lsn, offset: u16, data_len: usize, data_preview: String },
 
-    /// Page materialization complete.
-    MaterializeComplete { page_id: PageId, read_point: Lsn },
+    /// Page materialization complete, after replaying for `duration_us`.
+    MaterializeComplete { page_id: PageId, read_point: Lsn, duration_us: u64 },
 
     /// Materialized page inserted into storage page cache.
     PageCacheInsert { page_id: PageId, read_point: Lsn },
@@ -70,10 +77,13 @@ pub enum VizEvent {
     /// Page inserted into compute buffer pool.
     BufferPoolInsert { page_id: PageId, read_point: Lsn },
 
+    /// Warmup preloaded a page into the storage page cache.
+    WarmupProgress { page_id: PageId, done: usize, total: usize },
+
     // ── Tiered storage ────────────────────────────────────────────────
 
     /// A WAL segment was sealed and a new one opened.
-    SegmentRotation { sealed_id: u32, new_id: u32, sealed_lsn_range: (u64, u64) },
+    SegmentRotation { sealed_id: u32, new_id: u32, sealed_lsn_range: (u64, u64), sealed_bytes: u64 },
 
     /// A read hit a cold-tier segment, incurring extra latency.
     ColdTierRead { segment_id: u32, latency_ms: u64 },
@@ -81,6 +91,31 @@ pub enum VizEvent {
     /// A segment was moved from hot to cold tier.
     SegmentCooled { segment_id: u32 },
 
+    /// A cold segment was copied back to the hot tier after a read.
+    SegmentPromoted { segment_id: u32 },
+
+    // ── Recovery ──────────────────────────────────────────────────────
+
+    /// Recovery read a valid WAL entry (offset within its segment when tiered).
+    ScanEntry { lsn: Lsn, page_id: PageId, file_offset: u64, is_cpl: bool },
+
+    /// Recovery found the highest LSN with no gaps below it.
+    VclComputed { vcl: Lsn },
+
+    /// Recovery found the highest consistency point at or below the VCL.
+    VdlComputed { vdl: Lsn },
+
+    /// WAL truncated just past the VDL record; `bytes` were discarded.
+    TruncateAt { offset: u64, bytes: u64 },
+
+    /// Page index and LSN offsets rebuilt from the surviving records.
+    IndexRebuilt { pages: usize, records: usize },
+
+    // ── Health ────────────────────────────────────────────────────────
+
+    /// A node's heartbeats marked storage unhealthy, or healthy again.
+    StorageHealthChanged { node: String, healthy: bool },
+
     // ── State ─────────────────────────────────────────────────────────
 
     /// Full system state snapshot for diagram rendering.
@@ -89,7 +124,9 @@ pub enum VizEvent {
         node_label: String,
         read_point: Lsn,
         next_mtr: u64,
-        buffer_pool_pages: Vec<PageId>,
+        /// Pages resident in the node's buffer pool, each with the read
+        /// point its image is as of.
+        buffer_pool_pages: Vec<(PageId, Lsn)>,
         // Storage state
         next_lsn: Lsn,
         vcl: Lsn,
@@ -103,6 +140,102 @@ pub enum VizEvent {
     },
 }
 
+impl VizEvent {
+    /// The variant name, as it appears in JSON traces.
+    pub fn name(&self) -> &'static str {
+        match self {
+            VizEvent::MtrCreated { .. } => "MtrCreated",
+            VizEvent::WriteBatchFlushed { .. } => "WriteBatchFlushed",
+            VizEvent::AssignLsns { .. } => "AssignLsns",
+            VizEvent::LinkPrevLsn { .. } => "LinkPrevLsn",
+            VizEvent::WalAppend { .. } => "WalAppend",
+            VizEvent::WalSync { .. } => "WalSync",
+            VizEvent::UpdatePageIndex { .. } => "UpdatePageIndex",
+            VizEvent::UpdateLsnOffset { .. } => "UpdateLsnOffset",
+            VizEvent::AdvanceVcl { .. } => "AdvanceVcl",
+            VizEvent::AdvanceVdl { .. } => "AdvanceVdl",
+            VizEvent::UpdateReadPoint { .. } => "UpdateReadPoint",
+            VizEvent::BufferPoolInvalidate { .. } => "BufferPoolInvalidate",
+            VizEvent::BufferPoolWriteThrough { .. } => "BufferPoolWriteThrough",
+            VizEvent::BufferPoolLookup { .. } => "BufferPoolLookup",
+            VizEvent::PageCacheLookup { .. } => "PageCacheLookup",
+            VizEvent::PageIndexLookup { .. } => "PageIndexLookup",
+            VizEvent::ChainWalkStep { .. } => "ChainWalkStep",
+            VizEvent::ChainCollected { .. } => "ChainCollected",
+            VizEvent::MaterializeApply { .. } => "MaterializeApply",
+            VizEvent::MaterializeComplete { .. } => "MaterializeComplete",
+            VizEvent::PageCacheInsert { .. } => "PageCacheInsert",
+            VizEvent::BufferPoolInsert { .. } => "BufferPoolInsert",
+            VizEvent::WarmupProgress { .. } => "WarmupProgress",
+            VizEvent::SegmentRotation { .. } => "SegmentRotation",
+            VizEvent::ColdTierRead { .. } => "ColdTierRead",
+            VizEvent::SegmentCooled { .. } => "SegmentCooled",
+            VizEvent::SegmentPromoted { .. } => "SegmentPromoted",
+            VizEvent::ScanEntry { .. } => "ScanEntry",
+            VizEvent::VclComputed { .. } => "VclComputed",
+            VizEvent::VdlComputed { .. } => "VdlComputed",
+            VizEvent::TruncateAt { .. } => "TruncateAt",
+            VizEvent::IndexRebuilt { .. } => "IndexRebuilt",
+            VizEvent::StorageHealthChanged { .. } => "StorageHealthChanged",
+            VizEvent::StateSnapshot { .. } => "StateSnapshot",
+        }
+    }
+}
+
+/// Which events the viz draws, set with `viz filter`.
+///
+/// Terms match case-insensitively against whole words of the event name
+/// (`ChainWalkStep` is `chain`, `walk`, `step`, or the full name) or of its
+/// log line. An event is shown if it matches any plain term (or there are none) and
+/// no `-term`, so `wal vdl` shows only WAL and VDL events and `-chain`
+/// hides chain-walk steps.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl EventFilter {
+    /// Build a filter from whitespace-separated terms; `None` if empty.
+    pub fn parse(pattern: &str) -> Option<Self> {
+        let mut filter = Self::default();
+        for term in pattern.split_whitespace() {
+            match term.strip_prefix('-') {
+                Some(hidden) if !hidden.is_empty() => filter.exclude.push(hidden.to_lowercase()),
+                Some(_) => {}
+                None => filter.include.push(term.to_lowercase()),
+            }
+        }
+        (!filter.include.is_empty() || !filter.exclude.is_empty()).then_some(filter)
+    }
+
+    /// Whether an event with this name and log line is shown.
+    pub fn matches(&self, name: &str, line: &str) -> bool {
+        let mut words = vec![name.to_lowercase()];
+        for c in name.chars() {
+            if c.is_uppercase() || words.len() == 1 {
+                words.push(String::new());
+            }
+            words.last_mut().unwrap().extend(c.to_lowercase());
+        }
+        words.extend(line.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(str::to_lowercase));
+        let hit = |term: &String| words.contains(term);
+        (self.include.is_empty() || self.include.iter().any(hit)) && !self.exclude.iter().any(hit)
+    }
+}
+
+impl std::fmt::Display for EventFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let terms: Vec<String> = self
+            .include
+            .iter()
+            .cloned()
+            .chain(self.exclude.iter().map(|t| format!("-{t}")))
+            .collect();
+        write!(f, "{}", terms.join(" "))
+    }
+}
+
 /// Configuration for the visualization system.
 #[derive(Debug, Clone)]
 pub struct VizConfig {
@@ -112,6 +245,14 @@ pub struct VizConfig {
     pub color: bool,
     /// Whether visualization is enabled (can be toggled at runtime).
     pub enabled: bool,
+    /// Wait for a keypress after each step instead of sleeping for
+    /// `step_delay`. Needs a step gate on the renderer.
+    pub step: bool,
+    /// Split the event log into one lane per node, so events from nodes
+    /// running at once can be told apart at a glance.
+    pub lanes: bool,
+    /// Show a row of live metrics below the frame.
+    pub metrics_strip: bool,
 }
 
 impl Default for VizConfig {
@@ -120,6 +261,9 @@ impl Default for VizConfig {
             step_delay: Duration::from_millis(300),
             color: true,
             enabled: true,
+            step: false,
+            lanes: false,
+            metrics_strip: false,
         }
     }
 }
@@ -0,0 +1,103 @@
+//! Per-page heatmap of writes, reads, last access LSN and redo-chain
+//! lengths, drawn from the storage engine's page statistics by the REPL
+//! `heatmap` command and the TUI heatmap panel.
+
+use std::collections::BTreeMap;
+
+use mini_aurora_common::{Lsn, PageId};
+use mini_aurora_storage::engine::PageStats;
+
+/// Number of heat levels; level 0 is "none", the top level is the max.
+pub const HEAT_LEVELS: usize = 5;
+
+/// Shade characters per level, for output without color.
+const SHADES: [char; HEAT_LEVELS] = [' ', '░', '▒', '▓', '█'];
+/// ANSI background per level (none, blue, cyan, yellow, red).
+const BACKGROUNDS: [&str; HEAT_LEVELS] = ["", "44", "46", "43", "41"];
+
+/// One statistic across every page, with its heat level per page.
+pub struct HeatRow {
+    pub label: &'static str,
+    /// (value, level) per page, in the grid's page order.
+    pub cells: Vec<(u64, usize)>,
+}
+
+/// Pages as columns and writes, reads, last access and chain length as rows.
+pub struct HeatGrid {
+    pub pages: Vec<PageId>,
+    pub rows: Vec<HeatRow>,
+}
+
+impl HeatGrid {
+    pub fn new(stats: &BTreeMap<PageId, PageStats>) -> Self {
+        let row = |label, value: fn(&PageStats) -> u64| {
+            let max = stats.values().map(value).max().unwrap_or(0);
+            HeatRow {
+                label,
+                cells: stats.values().map(|s| (value(s), heat_level(value(s), max))).collect(),
+            }
+        };
+        // Recency counts from the least recently accessed page; a page not
+        // accessed since the engine opened has none
+        let accessed = || stats.values().map(|s| s.last_access).filter(|&lsn| lsn > 0);
+        let oldest = accessed().min().unwrap_or(1);
+        let newest = accessed().max().unwrap_or(0);
+        let recency = |lsn: Lsn| if lsn == 0 { 0 } else { heat_level(lsn - oldest + 1, newest - oldest + 1) };
+        let last = HeatRow {
+            label: "last",
+            cells: stats.values().map(|s| (s.last_access, recency(s.last_access))).collect(),
+        };
+        Self {
+            pages: stats.keys().copied().collect(),
+            rows: vec![
+                row("writes", |s| s.writes),
+                row("reads", |s| s.reads),
+                last,
+                row("chain", |s| s.chain_len as u64),
+            ],
+        }
+    }
+}
+
+/// Bucket `value` relative to `max`: 0 only for zero, the top level only
+/// for the max itself.
+pub fn heat_level(value: u64, max: u64) -> usize {
+    if value == 0 {
+        0
+    } else if value >= max {
+        HEAT_LEVELS - 1
+    } else {
+        1 + (value * (HEAT_LEVELS as u64 - 2) / max) as usize
+    }
+}
+
+/// The grid as text, `per_line` pages at a time. Cells are shaded by
+/// background color, or by a block character without color.
+pub fn format_heatmap(stats: &BTreeMap<PageId, PageStats>, per_line: usize, color: bool) -> String {
+    if stats.is_empty() {
+        return "No pages written yet.\n".to_string();
+    }
+    let grid = HeatGrid::new(stats);
+    let mut out = String::new();
+    for start in (0..grid.pages.len()).step_by(per_line.max(1)) {
+        let end = (start + per_line.max(1)).min(grid.pages.len());
+        out.push_str(&format!("{:<7}", ""));
+        for page in &grid.pages[start..end] {
+            out.push_str(&format!("{:>7}", format!("pg{page}")));
+        }
+        out.push('\n');
+        for row in &grid.rows {
+            out.push_str(&format!("{:<7}", row.label));
+            for &(value, level) in &row.cells[start..end] {
+                if color && level > 0 {
+                    out.push_str(&format!(" \x1b[30;{}m{value:>5} \x1b[0m", BACKGROUNDS[level]));
+                } else {
+                    out.push_str(&format!(" {}{value:>5}", SHADES[level]));
+                }
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
@@ -0,0 +1,92 @@
+//! Hex+ASCII dumps and byte-range diffs of page images, for the REPL
+//! `hex` and `diff` commands.
+
+use std::ops::Range;
+
+/// Bytes per dump line.
+const BYTES_PER_LINE: usize = 16;
+/// Bytes of each side shown per changed range in a diff.
+const DIFF_PREVIEW: usize = 16;
+
+/// Classic hex dump of `data[range]`, labelled with page offsets:
+///
+/// ```text
+/// 0000  48 65 6c 6c 6f 00 00 00  00 00 00 00 00 00 00 00  |Hello...........|
+/// ```
+pub fn hexdump(data: &[u8], range: Range<usize>) -> String {
+    let range = range.start.min(data.len())..range.end.min(data.len());
+    let mut out = String::new();
+    for line_start in range.clone().step_by(BYTES_PER_LINE) {
+        let line = &data[line_start..(line_start + BYTES_PER_LINE).min(range.end)];
+        let mut hex = String::new();
+        for i in 0..BYTES_PER_LINE {
+            if i == BYTES_PER_LINE / 2 {
+                hex.push(' ');
+            }
+            match line.get(i) {
+                Some(b) => hex.push_str(&format!("{b:02x} ")),
+                None => hex.push_str("   "),
+            }
+        }
+        out.push_str(&format!("{line_start:04x}  {hex} |{}|\n", ascii(line)));
+    }
+    out
+}
+
+/// Default dump range: through the last non-zero byte, whole lines, at
+/// least one line.
+pub fn used_range(data: &[u8], offset: usize) -> Range<usize> {
+    let used = data.iter().rposition(|&b| b != 0).map(|i| i + 1).unwrap_or(0);
+    let end = used.max(offset + 1).div_ceil(BYTES_PER_LINE) * BYTES_PER_LINE;
+    offset..end.min(data.len())
+}
+
+/// Maximal runs of bytes that differ between two equal-length images.
+pub fn changed_ranges(old: &[u8], new: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for i in (0..old.len().min(new.len())).filter(|&i| old[i] != new[i]) {
+        match ranges.last_mut() {
+            Some(last) if last.end == i => last.end = i + 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    ranges
+}
+
+/// Each changed range with its old and new bytes.
+pub fn format_diff(old: &[u8], new: &[u8], old_label: &str, new_label: &str) -> String {
+    let ranges = changed_ranges(old, new);
+    if ranges.is_empty() {
+        return format!("No bytes differ between {old_label} and {new_label}.\n");
+    }
+    let changed: usize = ranges.iter().map(|r| r.len()).sum();
+    let mut out = format!(
+        "{changed} byte{} changed in {} range{} ({old_label} → {new_label}):\n",
+        if changed == 1 { "" } else { "s" },
+        ranges.len(),
+        if ranges.len() == 1 { "" } else { "s" },
+    );
+    let label_width = old_label.len().max(new_label.len());
+    for range in ranges {
+        out.push_str(&format!("  [{}..{}) {} bytes\n", range.start, range.end, range.len()));
+        for (label, data) in [(old_label, old), (new_label, new)] {
+            let shown = &data[range.start..range.end.min(range.start + DIFF_PREVIEW)];
+            let hex: Vec<String> = shown.iter().map(|b| format!("{b:02x}")).collect();
+            let more = if range.len() > DIFF_PREVIEW { " …" } else { "" };
+            out.push_str(&format!(
+                "    {label:<label_width$}  {}{more}  |{}|\n",
+                hex.join(" "),
+                ascii(shown)
+            ));
+        }
+    }
+    out
+}
+
+/// Printable ASCII as-is, everything else as '.'.
+fn ascii(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+        .collect()
+}
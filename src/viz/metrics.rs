@@ -1,5 +1,6 @@
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use super::events::VizEvent;
 
@@ -12,14 +13,123 @@ pub struct MetricsCollector {
     buffer_pool_hits: u64,
     buffer_pool_misses: u64,
     materialize_count: u64,
-    materialize_total_us: u64,
     wal_bytes_written: u64,
+    write_batches: u64,
+    batched_mtrs: u64,
     vcl: u64,
     vdl: u64,
+    cold_reads: u64,
     start_time: Instant,
+    latencies: BTreeMap<(String, Op), LatencyHistogram>,
+    /// When each storage request in the last `RATE_WINDOW` happened.
+    recent_ops: VecDeque<Instant>,
+}
+
+/// How far back `ops_per_sec` looks.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Compute-node operations timed by the metrics layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    Put,
+    Get,
+    Refresh,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Op::Put => "put",
+            Op::Get => "get",
+            Op::Refresh => "refresh",
+        };
+        f.pad(name)
+    }
+}
+
+/// Upper bounds (µs) of the histogram buckets, on a 1-2-5 scale up to 10 s.
+/// Anything slower lands in a final overflow bucket.
+const BUCKET_BOUNDS_US: [u64; 22] = [
+    1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 20_000, 50_000,
+    100_000, 200_000, 500_000, 1_000_000, 2_000_000, 5_000_000, 10_000_000,
+];
+
+/// Fixed-bucket latency histogram. Percentiles are reported as the upper
+/// bound of the bucket they fall in, so they are accurate to the 1-2-5 scale.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    buckets: [u64; BUCKET_BOUNDS_US.len() + 1],
+    count: u64,
+    max_us: u64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, elapsed: Duration) {
+        let us = elapsed.as_micros() as u64;
+        let idx = BUCKET_BOUNDS_US.partition_point(|&bound| bound < us);
+        self.buckets[idx] += 1;
+        self.count += 1;
+        self.max_us = self.max_us.max(us);
+    }
+
+    /// Add `other`'s samples to this histogram.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (bucket, n) in self.buckets.iter_mut().zip(other.buckets) {
+            *bucket += n;
+        }
+        self.count += other.count;
+        self.max_us = self.max_us.max(other.max_us);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Latency (µs) at or below which `q` (0.0–1.0) of samples fall.
+    pub fn percentile_us(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((q * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (idx, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                // The overflow bucket has no bound; the max is the best we know
+                return BUCKET_BOUNDS_US.get(idx).copied().unwrap_or(self.max_us).min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+}
+
+impl fmt::Display for LatencyHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "n={} p50={} p95={} p99={} max={}",
+            self.count,
+            format_us(self.percentile_us(0.50)),
+            format_us(self.percentile_us(0.95)),
+            format_us(self.percentile_us(0.99)),
+            format_us(self.max_us),
+        )
+    }
+}
+
+fn format_us(us: u64) -> String {
+    if us >= 1_000_000 {
+        format!("{:.1}s", us as f64 / 1_000_000.0)
+    } else if us >= 1_000 {
+        format!("{:.1}ms", us as f64 / 1_000.0)
+    } else {
+        format!("{us}\u{00b5}s")
+    }
 }
 
 /// Snapshot of collected metrics.
+#[derive(Default)]
 pub struct MetricsSummary {
     pub write_count: u64,
     pub read_count: u64,
@@ -29,9 +139,18 @@ pub struct MetricsSummary {
     pub buffer_pool_misses: u64,
     pub materialize_count: u64,
     pub wal_bytes_written: u64,
+    /// Appends that carried batched puts, and the puts in them.
+    pub write_batches: u64,
+    pub batched_mtrs: u64,
     pub vcl: u64,
     pub vdl: u64,
+    pub cold_reads: u64,
+    /// WAL appends and page reads reaching storage per second, over the
+    /// last few seconds. Background workers' requests count too.
+    pub ops_per_sec: f64,
     pub uptime_secs: f64,
+    /// Per-node operation latencies, ordered by node then operation.
+    pub latencies: Vec<(String, Op, LatencyHistogram)>,
 }
 
 impl MetricsCollector {
@@ -44,22 +163,36 @@ impl MetricsCollector {
             buffer_pool_hits: 0,
             buffer_pool_misses: 0,
             materialize_count: 0,
-            materialize_total_us: 0,
             wal_bytes_written: 0,
+            write_batches: 0,
+            batched_mtrs: 0,
             vcl: 0,
             vdl: 0,
+            cold_reads: 0,
             start_time: Instant::now(),
+            latencies: BTreeMap::new(),
+            recent_ops: VecDeque::new(),
         }
     }
 
-    /// Record a single event, updating counters.
-    pub fn record_event(&mut self, event: &VizEvent) {
+    /// Record how long one compute-node operation took.
+    pub fn record_latency(&mut self, node: &str, op: Op, elapsed: Duration) {
+        self.latencies
+            .entry((node.to_string(), op))
+            .or_default()
+            .record(elapsed);
+    }
+
+    /// Record a single event, which happened at `at`, updating counters.
+    pub fn record_event(&mut self, event: &VizEvent, at: Instant) {
         match event {
             VizEvent::WalAppend { bytes, .. } => {
                 self.write_count += 1;
                 self.wal_bytes_written += bytes;
+                self.record_op(at);
             }
             VizEvent::PageCacheLookup { hit, .. } => {
+                self.record_op(at);
                 if *hit {
                     self.page_cache_hits += 1;
                 } else {
@@ -77,16 +210,40 @@ impl MetricsCollector {
             VizEvent::MaterializeComplete { .. } => {
                 self.materialize_count += 1;
             }
+            VizEvent::WriteBatchFlushed { mtrs, .. } => {
+                self.write_batches += 1;
+                self.batched_mtrs += *mtrs as u64;
+            }
             VizEvent::AdvanceVcl { new, .. } => {
                 self.vcl = *new;
             }
             VizEvent::AdvanceVdl { new, .. } => {
                 self.vdl = *new;
             }
+            VizEvent::ColdTierRead { .. } => {
+                self.cold_reads += 1;
+            }
             _ => {}
         }
     }
 
+    fn record_op(&mut self, at: Instant) {
+        self.recent_ops.push_back(at);
+        while self.recent_ops.front().is_some_and(|t| at.saturating_duration_since(*t) > RATE_WINDOW) {
+            self.recent_ops.pop_front();
+        }
+    }
+
+    /// Storage requests per second over the last `RATE_WINDOW`, or since
+    /// metrics started if that is more recent.
+    fn ops_per_sec(&self) -> f64 {
+        let now = Instant::now();
+        let recent = |t: &&Instant| now.saturating_duration_since(**t) <= RATE_WINDOW;
+        let count = self.recent_ops.iter().filter(recent).count();
+        let span = self.start_time.elapsed().clamp(Duration::from_secs(1), RATE_WINDOW);
+        count as f64 / span.as_secs_f64()
+    }
+
     /// Return an aggregate summary.
     pub fn summary(&self) -> MetricsSummary {
         MetricsSummary {
@@ -98,39 +255,190 @@ impl MetricsCollector {
             buffer_pool_misses: self.buffer_pool_misses,
             materialize_count: self.materialize_count,
             wal_bytes_written: self.wal_bytes_written,
+            write_batches: self.write_batches,
+            batched_mtrs: self.batched_mtrs,
             vcl: self.vcl,
             vdl: self.vdl,
+            cold_reads: self.cold_reads,
+            ops_per_sec: self.ops_per_sec(),
             uptime_secs: self.start_time.elapsed().as_secs_f64(),
+            latencies: self
+                .latencies
+                .iter()
+                .map(|((node, op), hist)| (node.clone(), *op, hist.clone()))
+                .collect(),
         }
     }
+}
 
-    /// Reset all counters and restart the clock.
-    pub fn reset(&mut self) {
-        *self = Self::new();
+/// `hits` as a whole percentage of all lookups, 0 with none.
+fn hit_pct(hits: u64, misses: u64) -> u64 {
+    let total = hits + misses;
+    if total > 0 {
+        (hits as f64 / total as f64 * 100.0) as u64
+    } else {
+        0
+    }
+}
+
+impl MetricsSummary {
+    pub fn cache_hit_pct(&self) -> u64 {
+        hit_pct(self.page_cache_hits, self.page_cache_misses)
+    }
+
+    pub fn buffer_pool_hit_pct(&self) -> u64 {
+        hit_pct(self.buffer_pool_hits, self.buffer_pool_misses)
+    }
+
+    /// Latencies of `op` across every node.
+    pub fn latency(&self, op: Op) -> LatencyHistogram {
+        let mut merged = LatencyHistogram::default();
+        for (_, _, hist) in self.latencies.iter().filter(|(_, o, _)| *o == op) {
+            merged.merge(hist);
+        }
+        merged
     }
 }
 
 impl fmt::Display for MetricsSummary {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let cache_total = self.page_cache_hits + self.page_cache_misses;
-        let cache_pct = if cache_total > 0 {
-            (self.page_cache_hits as f64 / cache_total as f64 * 100.0) as u64
-        } else {
-            0
-        };
+        let cache_pct = self.cache_hit_pct();
+        let bp_pct = self.buffer_pool_hit_pct();
         let wal_kb = self.wal_bytes_written / 1024;
         write!(
             f,
-            "Writes: {} | Reads: {} | Cache hit: {}% | Materializations: {}\n\
+            "Writes: {} | Reads: {} | Cache hit: {}% | BufPool hit: {}% | Materializations: {}\n\
              WAL: {} KB | VCL={} VDL={} | Uptime: {:.1}s",
             self.write_count,
             self.read_count,
             cache_pct,
+            bp_pct,
             self.materialize_count,
             wal_kb,
             self.vcl,
             self.vdl,
             self.uptime_secs,
-        )
+        )?;
+        if self.cold_reads > 0 {
+            write!(f, "\nCold reads: {}", self.cold_reads)?;
+        }
+        if self.write_batches > 0 {
+            let per_batch = self.batched_mtrs as f64 / self.write_batches as f64;
+            write!(f, "\nWrite batches: {} ({:.1} puts each)", self.write_batches, per_batch)?;
+        }
+        for (node, op, hist) in &self.latencies {
+            write!(f, "\nLatency [{node}] {op:<7} {hist}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parts of a put or get that `PhaseTimer` charges time to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    BufferPool,
+    CacheLookup,
+    ChainWalk,
+    ColdLatency,
+    Materialize,
+    WalWrite,
+    IndexUpdate,
+}
+
+impl Phase {
+    /// The phase that ends with `event`, or `None` for events that start a
+    /// request (the gap before them is network or compute-side work).
+    /// Cold tier reads are split by `PhaseTimer::record`.
+    fn ended_by(event: &VizEvent) -> Option<Phase> {
+        match event {
+            VizEvent::BufferPoolLookup { .. }
+            | VizEvent::BufferPoolInsert { .. }
+            | VizEvent::BufferPoolInvalidate { .. }
+            | VizEvent::BufferPoolWriteThrough { .. } => Some(Phase::BufferPool),
+            VizEvent::PageCacheLookup { .. } => Some(Phase::CacheLookup),
+            VizEvent::ChainWalkStep { .. } | VizEvent::ChainCollected { .. } => Some(Phase::ChainWalk),
+            VizEvent::MaterializeApply { .. }
+            | VizEvent::MaterializeComplete { .. }
+            | VizEvent::PageCacheInsert { .. } => Some(Phase::Materialize),
+            VizEvent::LinkPrevLsn { .. }
+            | VizEvent::WalAppend { .. }
+            | VizEvent::WalSync { .. }
+            | VizEvent::SegmentRotation { .. }
+            | VizEvent::SegmentCooled { .. } => Some(Phase::WalWrite),
+            VizEvent::UpdatePageIndex { .. }
+            | VizEvent::UpdateLsnOffset { .. }
+            | VizEvent::AdvanceVcl { .. }
+            | VizEvent::AdvanceVdl { .. } => Some(Phase::IndexUpdate),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Phase::BufferPool => "buffer pool",
+            Phase::CacheLookup => "cache lookup",
+            Phase::ChainWalk => "chain walk",
+            Phase::ColdLatency => "cold latency",
+            Phase::Materialize => "materialize",
+            Phase::WalWrite => "WAL write",
+            Phase::IndexUpdate => "index update",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Splits one command's time into phases from the gaps between the events
+/// it produces: each gap is charged to the phase of the event that ends it.
+/// Only meaningful with drawing off and no other operations running.
+#[derive(Debug)]
+pub struct PhaseTimer {
+    last: Instant,
+    phases: BTreeMap<Phase, Duration>,
+}
+
+impl PhaseTimer {
+    pub fn start() -> Self {
+        Self {
+            last: Instant::now(),
+            phases: BTreeMap::new(),
+        }
+    }
+
+    /// Charge the time since the previous event to the phase `event` ends.
+    /// `at` is when the event happened, not when it is rendered.
+    pub fn record(&mut self, event: &VizEvent, at: Instant) {
+        let gap = at.saturating_duration_since(self.last);
+        self.last = self.last.max(at);
+        if let VizEvent::ColdTierRead { latency_ms, .. } = event {
+            // The injected latency is known; the rest of the gap is walking
+            let cold = gap.min(Duration::from_millis(*latency_ms));
+            *self.phases.entry(Phase::ColdLatency).or_default() += cold;
+            *self.phases.entry(Phase::ChainWalk).or_default() += gap - cold;
+        } else if let Some(phase) = Phase::ended_by(event) {
+            *self.phases.entry(phase).or_default() += gap;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phases.is_empty()
     }
 }
+
+impl fmt::Display for PhaseTimer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (phase, time)) in self.phases.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{phase} {}", format_us(time.as_micros() as u64))?;
+        }
+        Ok(())
+    }
+}
+
+/// Wall time of a REPL command, in the units `metrics` uses.
+pub fn format_elapsed(elapsed: Duration) -> String {
+    format_us(elapsed.as_micros() as u64)
+}
@@ -1,6 +1,14 @@
+pub mod bus;
+pub mod chain;
+pub mod clock;
 pub mod compute;
 pub mod engine;
 pub mod events;
+pub mod heatmap;
+pub mod hexdump;
 pub mod metrics;
+pub mod observer;
+pub mod oplog;
 pub mod renderer;
+pub mod state;
 pub mod tracer;
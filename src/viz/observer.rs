@@ -0,0 +1,265 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use mini_aurora_common::{EngineObserver, Lsn, PageId, RedoRecord};
+
+use super::bus::EventBus;
+use super::events::VizEvent;
+use super::renderer::data_preview;
+
+tokio::task_local! {
+    /// The node a storage call is running for.
+    static CALLER: String;
+}
+
+/// Run the storage call `call` for node `label`, so the steps storage
+/// takes for it are tagged with that node.
+pub async fn on_behalf_of<F: Future>(label: &str, call: F) -> F::Output {
+    CALLER.scope(label.to_string(), call).await
+}
+
+/// Turns engine hooks into `VizEvent`s on a shared `EventBus`, tagged with
+/// the node they were taken for: the observer's own node, or for storage
+/// the node whose call is running. Steps storage takes by itself, such as
+/// recovery, are untagged.
+pub struct RendererObserver {
+    bus: EventBus,
+    /// Compute node the hooks come from, for events that name one.
+    node: Option<String>,
+}
+
+impl RendererObserver {
+    pub fn new(bus: EventBus) -> Self {
+        Self { bus, node: None }
+    }
+
+    /// Observer for the compute node `label`.
+    pub fn for_node(bus: EventBus, label: &str) -> Self {
+        Self { bus, node: Some(label.to_string()) }
+    }
+
+    fn emit(&self, event: VizEvent) {
+        let node = self.node.clone().or_else(|| CALLER.try_with(Clone::clone).ok());
+        self.bus.publish(event, node);
+    }
+}
+
+impl EngineObserver for RendererObserver {
+    fn lsns_assigned(&self, first_lsn: Lsn, last_lsn: Lsn) {
+        self.emit(VizEvent::AssignLsns { first_lsn, last_lsn });
+    }
+
+    fn prev_lsn_linked(&self, lsn: Lsn, page_id: PageId, prev_lsn: Lsn) {
+        self.emit(VizEvent::LinkPrevLsn { lsn, page_id, prev_lsn });
+    }
+
+    fn wal_appended(&self, first_lsn: Lsn, last_lsn: Lsn, offset: u64, bytes: u64) {
+        self.emit(VizEvent::WalAppend { first_lsn, last_lsn, offset, bytes });
+    }
+
+    fn wal_synced(&self, elapsed: Duration) {
+        self.emit(VizEvent::WalSync { duration_us: elapsed.as_micros() as u64 });
+    }
+
+    fn lsn_offset_recorded(&self, lsn: Lsn, file_offset: u64) {
+        self.emit(VizEvent::UpdateLsnOffset { lsn, file_offset });
+    }
+
+    fn page_index_updated(&self, page_id: PageId, latest_lsn: Lsn) {
+        self.emit(VizEvent::UpdatePageIndex { page_id, latest_lsn });
+    }
+
+    fn vcl_advanced(&self, old: Lsn, new: Lsn) {
+        self.emit(VizEvent::AdvanceVcl { old, new });
+    }
+
+    fn vdl_advanced(&self, old: Lsn, new: Lsn) {
+        self.emit(VizEvent::AdvanceVdl { old, new });
+    }
+
+    fn page_index_lookup(&self, page_id: PageId, latest_lsn: Option<Lsn>) {
+        self.emit(VizEvent::PageIndexLookup { page_id, latest_lsn });
+    }
+
+    fn page_cache_lookup(&self, page_id: PageId, read_point: Lsn, hit: bool) {
+        self.emit(VizEvent::PageCacheLookup { page_id, read_point, hit });
+    }
+
+    fn chain_step(&self, page_id: PageId, lsn: Lsn, prev_lsn: Lsn, skipped: bool) {
+        self.emit(VizEvent::ChainWalkStep { page_id, lsn, prev_lsn, skipped });
+    }
+
+    fn chain_collected(&self, page_id: PageId, chain: &[RedoRecord], elapsed: Duration) {
+        self.emit(VizEvent::ChainCollected {
+            page_id,
+            chain_len: chain.len(),
+            lsns: chain.iter().map(|r| r.lsn).collect(),
+            duration_us: elapsed.as_micros() as u64,
+        });
+    }
+
+    fn redo_applied(&self, record: &RedoRecord) {
+        self.emit(VizEvent::MaterializeApply {
+            page_id: record.page_id,
+            lsn: record.lsn,
+            offset: record.offset,
+            data_len: record.data.len(),
+            data_preview: data_preview(&record.data, 20),
+        });
+    }
+
+    fn page_materialized(&self, page_id: PageId, read_point: Lsn, elapsed: Duration) {
+        self.emit(VizEvent::MaterializeComplete {
+            page_id,
+            read_point,
+            duration_us: elapsed.as_micros() as u64,
+        });
+    }
+
+    fn page_cache_inserted(&self, page_id: PageId, read_point: Lsn) {
+        self.emit(VizEvent::PageCacheInsert { page_id, read_point });
+    }
+
+    fn warmup_progress(&self, page_id: PageId, done: usize, total: usize) {
+        self.emit(VizEvent::WarmupProgress { page_id, done, total });
+    }
+
+    fn cold_tier_read(&self, segment_id: u32, latency: Duration) {
+        self.emit(VizEvent::ColdTierRead {
+            segment_id,
+            latency_ms: latency.as_millis() as u64,
+        });
+    }
+
+    fn segment_rotated(&self, sealed_id: u32, new_id: u32, sealed_lsn_range: (Lsn, Lsn), sealed_bytes: u64) {
+        self.emit(VizEvent::SegmentRotation { sealed_id, new_id, sealed_lsn_range, sealed_bytes });
+    }
+
+    fn segment_cooled(&self, segment_id: u32) {
+        self.emit(VizEvent::SegmentCooled { segment_id });
+    }
+
+    fn segment_promoted(&self, segment_id: u32) {
+        self.emit(VizEvent::SegmentPromoted { segment_id });
+    }
+
+    fn recovery_entry_scanned(&self, lsn: Lsn, page_id: PageId, file_offset: u64, is_mtr_end: bool) {
+        self.emit(VizEvent::ScanEntry { lsn, page_id, file_offset, is_cpl: is_mtr_end });
+    }
+
+    fn recovery_vcl_computed(&self, vcl: Lsn) {
+        self.emit(VizEvent::VclComputed { vcl });
+    }
+
+    fn recovery_vdl_computed(&self, vdl: Lsn) {
+        self.emit(VizEvent::VdlComputed { vdl });
+    }
+
+    fn recovery_truncated(&self, offset: u64, bytes: u64) {
+        self.emit(VizEvent::TruncateAt { offset, bytes });
+    }
+
+    fn recovery_index_rebuilt(&self, pages: usize, records: usize) {
+        self.emit(VizEvent::IndexRebuilt { pages, records });
+    }
+
+    fn mtr_created(&self, mtr_id: u64, num_records: usize) {
+        self.emit(VizEvent::MtrCreated { mtr_id, num_records });
+    }
+
+    fn write_batch_flushed(&self, mtrs: usize, records: usize) {
+        self.emit(VizEvent::WriteBatchFlushed { mtrs, records });
+    }
+
+    fn read_point_updated(&self, old: Lsn, new: Lsn) {
+        self.emit(VizEvent::UpdateReadPoint { old, new });
+    }
+
+    fn buffer_pool_lookup(&self, page_id: PageId, read_point: Lsn, hit: bool) {
+        self.emit(VizEvent::BufferPoolLookup { page_id, read_point, hit });
+    }
+
+    fn buffer_pool_inserted(&self, page_id: PageId, read_point: Lsn) {
+        self.emit(VizEvent::BufferPoolInsert { page_id, read_point });
+    }
+
+    fn buffer_pool_invalidated(&self, page_id: PageId) {
+        self.emit(VizEvent::BufferPoolInvalidate { page_id });
+    }
+
+    fn buffer_pool_written_through(&self, page_id: PageId, read_point: Lsn) {
+        self.emit(VizEvent::BufferPoolWriteThrough { page_id, read_point });
+    }
+
+    // Acked and missed beats arrive every interval; only a change of
+    // health is worth a step in the log
+    fn storage_health_changed(&self, healthy: bool) {
+        if let Some(node) = &self.node {
+            self.emit(VizEvent::StorageHealthChanged { node: node.clone(), healthy });
+        }
+    }
+}
+
+/// Passes every hook to two observers, `first` then `second`.
+pub struct TeeObserver {
+    first: Arc<dyn EngineObserver>,
+    second: Arc<dyn EngineObserver>,
+}
+
+impl TeeObserver {
+    pub fn new(first: Arc<dyn EngineObserver>, second: Arc<dyn EngineObserver>) -> Self {
+        Self { first, second }
+    }
+}
+
+macro_rules! tee_hooks {
+    ($($hook:ident($($arg:ident: $ty:ty),*);)*) => {
+        impl EngineObserver for TeeObserver {
+            $(
+                fn $hook(&self, $($arg: $ty),*) {
+                    self.first.$hook($($arg),*);
+                    self.second.$hook($($arg),*);
+                }
+            )*
+        }
+    };
+}
+
+tee_hooks! {
+    lsns_assigned(first: Lsn, last: Lsn);
+    prev_lsn_linked(lsn: Lsn, page_id: PageId, prev_lsn: Lsn);
+    wal_appended(first: Lsn, last: Lsn, offset: u64, bytes: u64);
+    wal_synced(elapsed: Duration);
+    lsn_offset_recorded(lsn: Lsn, file_offset: u64);
+    page_index_updated(page_id: PageId, latest_lsn: Lsn);
+    vcl_advanced(old: Lsn, new: Lsn);
+    vdl_advanced(old: Lsn, new: Lsn);
+    page_index_lookup(page_id: PageId, latest_lsn: Option<Lsn>);
+    page_cache_lookup(page_id: PageId, read_point: Lsn, hit: bool);
+    chain_step(page_id: PageId, lsn: Lsn, prev_lsn: Lsn, skipped: bool);
+    chain_collected(page_id: PageId, chain: &[RedoRecord], elapsed: Duration);
+    redo_applied(record: &RedoRecord);
+    page_materialized(page_id: PageId, read_point: Lsn, elapsed: Duration);
+    page_cache_inserted(page_id: PageId, read_point: Lsn);
+    warmup_progress(page_id: PageId, done: usize, total: usize);
+    cold_tier_read(segment_id: u32, latency: Duration);
+    segment_rotated(sealed_id: u32, new_id: u32, sealed_lsn_range: (Lsn, Lsn), sealed_bytes: u64);
+    segment_cooled(segment_id: u32);
+    segment_promoted(segment_id: u32);
+    recovery_entry_scanned(lsn: Lsn, page_id: PageId, file_offset: u64, is_mtr_end: bool);
+    recovery_vcl_computed(vcl: Lsn);
+    recovery_vdl_computed(vdl: Lsn);
+    recovery_truncated(offset: u64, bytes: u64);
+    recovery_index_rebuilt(pages: usize, records: usize);
+    mtr_created(mtr_id: u64, num_records: usize);
+    write_batch_flushed(mtrs: usize, records: usize);
+    read_point_updated(old: Lsn, new: Lsn);
+    buffer_pool_lookup(page_id: PageId, read_point: Lsn, hit: bool);
+    buffer_pool_inserted(page_id: PageId, read_point: Lsn);
+    buffer_pool_invalidated(page_id: PageId);
+    buffer_pool_written_through(page_id: PageId, read_point: Lsn);
+    heartbeat_acked(latency: Duration, vdl: Lsn);
+    heartbeat_missed(missed: u32);
+    storage_health_changed(healthy: bool);
+}
@@ -0,0 +1,231 @@
+//! Operation log for the REPL's `oplog` command: one entry per put, get or
+//! refresh on any node, background workers included, kept in a ring buffer
+//! so output that scrolled away can be reviewed.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mini_aurora_common::{EngineObserver, Lsn, PageId, StorageError};
+
+use super::metrics::{format_elapsed, Op};
+
+/// Operations kept in the log; older ones are dropped.
+pub const OPLOG_CAPACITY: usize = 1000;
+
+/// The LSNs an operation touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpLsns {
+    /// A put's records were assigned `first..=last`.
+    Wrote(Lsn, Lsn),
+    /// A get read at this read point.
+    ReadAt(Lsn),
+    /// A refresh moved the read point.
+    Moved(Lsn, Lsn),
+    /// Failed before any LSN was involved.
+    None,
+}
+
+impl fmt::Display for OpLsns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match *self {
+            OpLsns::Wrote(first, last) if first == last => format!("L{last}"),
+            OpLsns::Wrote(first, last) => format!("L{first}..L{last}"),
+            OpLsns::ReadAt(lsn) => format!("@L{lsn}"),
+            OpLsns::Moved(old, new) => format!("L{old}->L{new}"),
+            OpLsns::None => "-".to_string(),
+        };
+        f.pad(&text)
+    }
+}
+
+/// One finished operation.
+#[derive(Debug, Clone)]
+pub struct OpEntry {
+    pub seq: u64,
+    /// When the operation started, since the log was created.
+    pub at: Duration,
+    /// Node label, prefixed with `bg ` for background workers.
+    pub who: String,
+    pub op: Op,
+    /// Pages the operation targeted, e.g. `pg3` or `pg1,pg2`.
+    pub target: String,
+    pub lsns: OpLsns,
+    /// Buffer pool hit or miss, for reads that looked there.
+    pub cache_hit: Option<bool>,
+    pub duration: Duration,
+    pub error: Option<String>,
+}
+
+impl fmt::Display for OpEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cache = match self.cache_hit {
+            Some(true) => "hit",
+            Some(false) => "miss",
+            None => "-",
+        };
+        write!(
+            f,
+            "{:>5}  +{:<9} {:<6} {:<7} {:<10} {:<12} {:<5} {:>8}",
+            self.seq,
+            format!("{:.3}s", self.at.as_secs_f64()),
+            self.who,
+            self.op,
+            self.target,
+            self.lsns,
+            cache,
+            format_elapsed(self.duration),
+        )?;
+        if let Some(error) = &self.error {
+            write!(f, "  error: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Column headings lined up with `OpEntry`'s display.
+pub fn oplog_header() -> String {
+    format!(
+        "{:>5}  {:<10} {:<6} {:<7} {:<10} {:<12} {:<5} {:>8}",
+        "#", "time", "who", "op", "target", "LSNs", "cache", "took"
+    )
+}
+
+struct OpLogInner {
+    entries: VecDeque<OpEntry>,
+    capacity: usize,
+    next_seq: u64,
+    started: Instant,
+}
+
+/// Ring buffer of recent operations. Clones share the same buffer.
+#[derive(Clone)]
+pub struct OpLog {
+    inner: Arc<Mutex<OpLogInner>>,
+}
+
+impl OpLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(OpLogInner {
+                entries: VecDeque::with_capacity(capacity),
+                capacity,
+                next_seq: 1,
+                started: Instant::now(),
+            })),
+        }
+    }
+
+    /// The last `n` entries, oldest first.
+    pub fn tail(&self, n: usize) -> Vec<OpEntry> {
+        let inner = self.inner.lock().unwrap();
+        inner.entries.iter().skip(inner.entries.len().saturating_sub(n)).cloned().collect()
+    }
+
+    fn since_start(&self) -> Duration {
+        self.inner.lock().unwrap().started.elapsed()
+    }
+
+    fn push(&self, mut entry: OpEntry) {
+        let mut inner = self.inner.lock().unwrap();
+        entry.seq = inner.next_seq;
+        inner.next_seq += 1;
+        if inner.entries.len() == inner.capacity {
+            inner.entries.pop_front();
+        }
+        inner.entries.push_back(entry);
+    }
+}
+
+/// An operation between `begin` and `finish`, filled in by hooks.
+struct PendingOp {
+    at: Duration,
+    op: Op,
+    target: String,
+    records: usize,
+    cache_hit: Option<bool>,
+    read_point: Option<Lsn>,
+    moved: Option<(Lsn, Lsn)>,
+}
+
+/// Compute-engine observer that turns one node's operations into `OpLog`
+/// entries. The caller brackets each operation with `begin` and `finish`;
+/// the hooks in between supply the LSNs and the buffer pool result.
+pub struct OpRecorder {
+    who: String,
+    log: OpLog,
+    pending: Mutex<Option<PendingOp>>,
+}
+
+impl OpRecorder {
+    pub fn new(who: impl Into<String>, log: OpLog) -> Self {
+        Self {
+            who: who.into(),
+            log,
+            pending: Mutex::new(None),
+        }
+    }
+
+    pub fn begin(&self, op: Op, target: impl Into<String>) {
+        *self.pending.lock().unwrap() = Some(PendingOp {
+            at: self.log.since_start(),
+            op,
+            target: target.into(),
+            records: 0,
+            cache_hit: None,
+            read_point: None,
+            moved: None,
+        });
+    }
+
+    /// Log the operation started by `begin`, which took `duration`.
+    pub fn finish(&self, error: Option<&StorageError>, duration: Duration) {
+        let Some(pending) = self.pending.lock().unwrap().take() else {
+            return;
+        };
+        let lsns = match (pending.op, pending.moved, pending.read_point) {
+            // A put moves the read point to its own last LSN
+            (Op::Put, Some((_, last)), _) if pending.records > 0 && error.is_none() => {
+                OpLsns::Wrote(last + 1 - pending.records as Lsn, last)
+            }
+            (Op::Get, _, Some(read_point)) => OpLsns::ReadAt(read_point),
+            (Op::Refresh, Some((old, new)), _) => OpLsns::Moved(old, new),
+            _ => OpLsns::None,
+        };
+        self.log.push(OpEntry {
+            seq: 0,
+            at: pending.at,
+            who: self.who.clone(),
+            op: pending.op,
+            target: pending.target,
+            lsns,
+            cache_hit: pending.cache_hit,
+            duration,
+            error: error.map(|e| e.to_string()),
+        });
+    }
+
+    fn update(&self, f: impl FnOnce(&mut PendingOp)) {
+        if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+            f(pending);
+        }
+    }
+}
+
+impl EngineObserver for OpRecorder {
+    fn mtr_created(&self, _mtr_id: u64, num_records: usize) {
+        self.update(|p| p.records = num_records);
+    }
+
+    fn read_point_updated(&self, old: Lsn, new: Lsn) {
+        self.update(|p| p.moved = Some((old, new)));
+    }
+
+    fn buffer_pool_lookup(&self, _page_id: PageId, read_point: Lsn, hit: bool) {
+        self.update(|p| {
+            p.cache_hit = Some(hit);
+            p.read_point = Some(read_point);
+        });
+    }
+}
@@ -1,47 +1,31 @@
-use std::collections::{BTreeMap, HashMap};
 use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use mini_aurora_common::{Lsn, PageId};
+use mini_aurora_wal::segment::{SegmentMeta, Tier};
 
-use super::events::{VizConfig, VizEvent};
-use super::metrics::MetricsCollector;
-use super::tracer::JsonTracer;
+use super::events::{EventFilter, VizConfig, VizEvent};
+use super::metrics::{format_elapsed, MetricsCollector, Op, PhaseTimer};
+use super::oplog::{OpLog, OPLOG_CAPACITY};
+use super::state::{NodeState, VizState};
+use super::tracer::Tracer;
 
 const PANEL_HEIGHT: usize = 15;
 const PANEL_INNER: usize = 24;
+const GUTTER: usize = 2;
 // Panel total = 2 (borders) + PANEL_INNER = 26, plus 2 gutter = 28
-
-/// Per-node compute state tracked in the renderer.
-#[derive(Clone)]
-struct NodeState {
-    read_point: Lsn,
-    next_mtr: u64,
-    buffer_pool: Vec<PageId>,
-}
-
-impl Default for NodeState {
-    fn default() -> Self {
-        Self {
-            read_point: 0,
-            next_mtr: 1,
-            buffer_pool: Vec::new(),
-        }
-    }
-}
-
-/// Shared (storage + WAL) state tracked incrementally from events.
-#[derive(Default)]
-struct SharedState {
-    next_lsn: Lsn,
-    vcl: Lsn,
-    vdl: Lsn,
-    page_index: HashMap<PageId, Lsn>,
-    lsn_offset_count: usize,
-    page_cache_count: u64,
-    wal_file_size: u64,
-    wal_lsn_range: Option<(Lsn, Lsn)>,
-}
+/// Narrowest event log worth showing beside the panel; below this the
+/// panel is stacked above the log instead.
+const MIN_LOG_WIDTH: usize = 36;
+/// Panel rows besides the nodes: storage, WAL, paging and bottom border.
+const PANEL_FIXED_ROWS: usize = 9;
+/// Newest segments listed in the SEGMENTS section; older ones are counted.
+const MAX_SEGMENT_ROWS: usize = 4;
+/// Rows left below the frame for the REPL prompt and command output.
+const RESERVED_ROWS: usize = 4;
 
 // ANSI escape helpers
 struct Ansi {
@@ -70,6 +54,174 @@ impl Ansi {
     fn bold_green(&self, s: &str) -> String {
         if self.color { format!("\x1b[1;32m{s}\x1b[0m") } else { s.to_string() }
     }
+    /// `s` in SGR color `code`, bold if `bold`.
+    fn paint(&self, code: u8, bold: bool, s: &str) -> String {
+        match (self.color, bold) {
+            (false, _) => s.to_string(),
+            (true, false) => format!("\x1b[{code}m{s}\x1b[0m"),
+            (true, true) => format!("\x1b[1;{code}m{s}\x1b[0m"),
+        }
+    }
+}
+
+/// Colors for node-tagged log lines, by the node's place in the panel.
+/// Cyan is left for the panel borders.
+const NODE_COLORS: [u8; 5] = [35, 34, 33, 32, 31];
+/// Narrowest lane worth splitting the log into; below this the log stays
+/// in one column.
+const MIN_LANE_WIDTH: usize = 16;
+
+/// Width of the bars in an operation's latency waterfall.
+const WATERFALL_WIDTH: usize = 20;
+
+/// One line of the event log, with the node its event was taken for.
+#[derive(Clone)]
+struct LogLine {
+    /// `None` for annotations, such as the latency waterfall, which are
+    /// not steps.
+    step: Option<usize>,
+    node: Option<String>,
+    text: String,
+}
+
+/// What the user chose at a step-mode pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepAction {
+    Next,
+    /// Run the rest of the operation without pausing.
+    Finish,
+}
+
+#[derive(Default)]
+struct GateState {
+    /// Pauses only happen while a typed command runs, so background
+    /// workers never swallow the next command as a keypress.
+    armed: bool,
+    /// The hint to show while a pause waits for a keypress.
+    waiting: Option<String>,
+    action: Option<StepAction>,
+    /// Input has ended; pauses return immediately.
+    closed: bool,
+    /// Ctrl-C during the current command; its pauses return immediately.
+    interrupted: bool,
+    /// Lines handed to the REPL as commands, and how many it has finished;
+    /// `None` until the REPL first reports in, so startup scripts run first.
+    offered: u64,
+    finished: Option<u64>,
+}
+
+/// Whose turn it is to read the next line of input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputTurn {
+    /// The REPL is idle and wants a command.
+    Command,
+    /// A step-mode pause wants a keypress; shows the given hint.
+    Step(String),
+}
+
+/// Hands REPL input to a renderer paused in step mode.
+///
+/// The stdin thread asks for its `turn` before reading each line, so it
+/// never prompts over a running command, and offers every line here first.
+/// While the renderer is paused the line is taken as a keypress (Enter for
+/// the next step, `c` to finish the operation) instead of being run as a
+/// command.
+#[derive(Clone, Default)]
+pub struct StepGate {
+    inner: Arc<(Mutex<GateState>, Condvar)>,
+}
+
+impl StepGate {
+    /// Take `line` as a keypress if the renderer is paused. Returns false
+    /// when it should be handled as a command.
+    pub fn offer(&self, line: &str) -> bool {
+        let (lock, cvar) = &*self.inner;
+        let mut gate = lock.lock().unwrap();
+        if gate.waiting.take().is_none() {
+            gate.offered += 1;
+            return false;
+        }
+        gate.action = Some(if line.trim() == "c" { StepAction::Finish } else { StepAction::Next });
+        cvar.notify_all();
+        true
+    }
+
+    /// Allow pauses while a typed command runs. Either way, a new command
+    /// starts uninterrupted.
+    pub fn arm(&self, armed: bool) {
+        let mut gate = self.inner.0.lock().unwrap();
+        gate.armed = armed;
+        gate.interrupted = false;
+    }
+
+    fn is_armed(&self) -> bool {
+        self.inner.0.lock().unwrap().armed
+    }
+
+    /// The REPL has run `commands` of the lines offered to it so far; once
+    /// it has caught up, the stdin thread may prompt for the next one.
+    pub fn finished(&self, commands: u64) {
+        let (lock, cvar) = &*self.inner;
+        lock.lock().unwrap().finished = Some(commands);
+        cvar.notify_all();
+    }
+
+    /// Block until input is wanted. `None` once the gate is closed.
+    pub fn turn(&self) -> Option<InputTurn> {
+        let (lock, cvar) = &*self.inner;
+        let mut gate = lock.lock().unwrap();
+        loop {
+            if gate.closed {
+                return None;
+            }
+            if let Some(hint) = &gate.waiting {
+                return Some(InputTurn::Step(hint.clone()));
+            }
+            if gate.finished == Some(gate.offered) {
+                return Some(InputTurn::Command);
+            }
+            gate = cvar.wait(gate).unwrap();
+        }
+    }
+
+    /// Finish the running command without pausing again, e.g. on Ctrl-C.
+    pub fn interrupt(&self) {
+        let (lock, cvar) = &*self.inner;
+        lock.lock().unwrap().interrupted = true;
+        cvar.notify_all();
+    }
+
+    /// Release any pause for good, e.g. on EOF.
+    pub fn close(&self) {
+        let (lock, cvar) = &*self.inner;
+        lock.lock().unwrap().closed = true;
+        cvar.notify_all();
+    }
+
+    fn wait(&self, hint: String) -> StepAction {
+        let (lock, cvar) = &*self.inner;
+        let mut gate = lock.lock().unwrap();
+        gate.waiting = Some(hint);
+        cvar.notify_all();
+        loop {
+            if gate.closed || gate.interrupted {
+                gate.waiting = None;
+                return StepAction::Finish;
+            }
+            if let Some(action) = gate.action.take() {
+                return action;
+            }
+            gate = cvar.wait(gate).unwrap();
+        }
+    }
+}
+
+/// The events of the latest operation, kept for `replay`.
+struct RecordedOp {
+    header: String,
+    /// Panel state before the first event.
+    start: VizState,
+    events: Vec<(VizEvent, Option<String>)>,
 }
 
 /// Two-column renderer with live in-place updates.
@@ -79,49 +231,80 @@ impl Ansi {
 pub struct VizRenderer {
     config: VizConfig,
     step_num: usize,
-    nodes: BTreeMap<String, NodeState>,
-    active_node: String,
-    interaction: String,
-    shared: SharedState,
-    event_log: Vec<String>,
+    state: VizState,
+    event_log: Vec<LogLine>,
     operation_header: String,
-    term_width: usize,
+    /// Terminal size at the last frame, to detect resizes.
+    last_size: (usize, usize),
     metrics: Option<MetricsCollector>,
-    tracer: Option<JsonTracer>,
+    tracer: Option<Tracer>,
+    /// Source of keypresses for step mode.
+    step_gate: Option<StepGate>,
+    /// `c` was pressed: stop pausing until the next operation.
+    finish_op: bool,
+    /// Events hidden from the log are still applied to the panel.
+    filter: Option<EventFilter>,
+    last_op: Option<RecordedOp>,
+    /// Times the phases of the current REPL command while drawing is off.
+    phase_timer: Option<PhaseTimer>,
+    /// Shared with every node's `OpRecorder`.
+    oplog: OpLog,
+    /// Set from a signal handler, which can't wait for the renderer lock:
+    /// events still update the panel state but are neither drawn nor
+    /// paused on until `take_cancel`.
+    cancel: Arc<AtomicBool>,
+    /// The node running the current operation, whose timed steps are
+    /// collected in `spans` for its latency waterfall.
+    op_node: Option<String>,
+    spans: Vec<(String, Duration)>,
+    /// The current operation's latency, once it has succeeded.
+    op_latency: Option<Duration>,
 }
 
 impl VizRenderer {
     pub fn new(config: VizConfig) -> Self {
-        let term_width = std::env::var("COLUMNS")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(80usize)
-            .max(80);
-
         Self {
             config,
             step_num: 0,
-            nodes: BTreeMap::new(),
-            active_node: String::new(),
-            interaction: String::new(),
-            shared: SharedState {
-                next_lsn: 1,
-                ..Default::default()
-            },
+            state: VizState::default(),
             event_log: Vec::new(),
             operation_header: String::new(),
-            term_width,
+            last_size: (0, 0),
             metrics: Some(MetricsCollector::new()),
             tracer: None,
+            step_gate: None,
+            finish_op: false,
+            filter: None,
+            last_op: None,
+            phase_timer: None,
+            oplog: OpLog::new(OPLOG_CAPACITY),
+            cancel: Arc::new(AtomicBool::new(false)),
+            op_node: None,
+            spans: Vec::new(),
+            op_latency: None,
         }
     }
 
+    /// Only draw events matching `filter`; `None` shows everything.
+    pub fn set_filter(&mut self, filter: Option<EventFilter>) {
+        self.filter = filter;
+    }
+
+    pub fn filter(&self) -> Option<&EventFilter> {
+        self.filter.as_ref()
+    }
+
+    /// Let `config.step` pause on keypresses delivered through `gate`.
+    pub fn set_step_gate(&mut self, gate: StepGate) {
+        self.step_gate = Some(gate);
+    }
+
     pub fn config_mut(&mut self) -> &mut VizConfig {
         &mut self.config
     }
 
-    /// Set a JSON tracer to record all events.
-    pub fn set_tracer(&mut self, tracer: JsonTracer) {
+    /// Set a tracer to record all events.
+    pub fn set_tracer(&mut self, tracer: Tracer) {
         self.tracer = Some(tracer);
     }
 
@@ -130,15 +313,112 @@ impl VizRenderer {
         self.metrics.as_ref().map(|m| m.summary())
     }
 
+    /// Record a compute-node operation latency in the metrics collector.
+    pub fn record_latency(&mut self, node: &str, op: Op, elapsed: Duration) {
+        if let Some(ref mut metrics) = self.metrics {
+            metrics.record_latency(node, op, elapsed);
+        }
+        self.op_latency = Some(elapsed);
+    }
+
+    /// Break the next command's time down by phase. Only done with drawing
+    /// off, since frames and step delays would swamp every phase.
+    pub fn start_phase_timer(&mut self) {
+        if !self.config.enabled {
+            self.phase_timer = Some(PhaseTimer::start());
+        }
+    }
+
+    pub fn take_phase_timer(&mut self) -> Option<PhaseTimer> {
+        self.phase_timer.take()
+    }
+
+    /// The operation log that nodes created on this renderer write to.
+    pub fn oplog(&self) -> OpLog {
+        self.oplog.clone()
+    }
+
+    /// Flag that cancels drawing for the running operation when set.
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancel.clone()
+    }
+
+    /// Clear the cancel flag, returning whether it was set. The panel state
+    /// kept up while drawing was off, so a cancelled operation is redrawn
+    /// as it ended.
+    pub fn take_cancel(&mut self) -> bool {
+        let cancelled = self.cancel.swap(false, Ordering::SeqCst);
+        if cancelled {
+            self.redraw();
+        }
+        cancelled
+    }
+
     /// Register a compute node so it appears in the panel.
     pub fn register_node(&mut self, label: &str) {
-        self.nodes.entry(label.to_string()).or_default();
+        self.state.register_node(label);
+    }
+
+    /// The panel's state, for tests.
+    #[cfg(test)]
+    pub(crate) fn state(&self) -> &VizState {
+        &self.state
+    }
+
+    /// Track WAL segments in the SEGMENTS section (tiered storage only).
+    pub fn seed_segments(&mut self, segments: &[SegmentMeta]) {
+        self.state.seed_segments(segments);
     }
 
     /// Set the active node for subsequent events. Clears the interaction text.
     pub fn set_active_node(&mut self, label: &str) {
-        self.active_node = label.to_string();
-        self.interaction.clear();
+        self.state.set_active_node(label);
+    }
+
+    /// Tag subsequent traced events with a new operation on `node`.
+    pub fn begin_operation(&mut self, node: &str, op: Op) {
+        if let Some(ref mut tracer) = self.tracer {
+            tracer.begin_operation(node, op);
+        }
+        self.op_node = Some(node.to_string());
+        self.spans.clear();
+        self.op_latency = None;
+    }
+
+    pub fn end_operation(&mut self) {
+        if let Some(ref mut tracer) = self.tracer {
+            tracer.end_operation();
+        }
+        self.op_node = None;
+    }
+
+    /// Append the current operation's latency waterfall to the log: one bar
+    /// per timed step (WAL fsync, cold-tier read, chain walk, materialize)
+    /// in the order they ran, then whatever of the latency they don't
+    /// account for.
+    pub fn render_latency_waterfall(&mut self) {
+        if !self.config.enabled || self.spans.is_empty() || self.cancel.load(Ordering::SeqCst) {
+            return;
+        }
+        let timed: Duration = self.spans.iter().map(|(_, d)| *d).sum();
+        let total = self.op_latency.map_or(timed, |latency| latency.max(timed));
+        let mut rows = std::mem::take(&mut self.spans);
+        if let Some(latency) = self.op_latency.filter(|l| *l > timed) {
+            rows.push(("other".to_string(), latency - timed));
+        }
+        let heading = match self.op_latency {
+            Some(latency) => format!("latency {}", format_elapsed(latency)),
+            None => "latency (failed)".to_string(),
+        };
+        self.event_log.push(LogLine { step: None, node: None, text: heading });
+        let mut start = Duration::ZERO;
+        for (label, elapsed) in &rows {
+            let bar = waterfall_bar(start, *elapsed, total);
+            let text = format!("  {label:<16} {bar} {}", format_elapsed(*elapsed));
+            self.event_log.push(LogLine { step: None, node: None, text });
+            start += *elapsed;
+        }
+        self.draw_frame();
     }
 
     /// Reset step counter for a new operation.
@@ -155,183 +435,133 @@ impl VizRenderer {
         self.operation_header = op.to_string();
         self.event_log.clear();
         self.step_num = 0;
-        self.draw_frame();
+        self.finish_op = false;
+        self.last_op = Some(RecordedOp {
+            header: op.to_string(),
+            start: self.state.clone(),
+            events: Vec::new(),
+        });
+        if !self.cancel.load(Ordering::SeqCst) {
+            self.draw_frame();
+        }
+    }
+
+    /// Redraw the current frame at the terminal's new size. Called when the
+    /// terminal is resized between operations.
+    pub fn redraw(&mut self) {
+        if self.config.enabled && !self.operation_header.is_empty() {
+            self.draw_frame();
+        }
     }
 
-    /// Render a single event: update state, log it, redraw frame, sleep.
-    pub fn render(&mut self, event: &VizEvent) {
+    /// Render a single event taken for `node` at `at`: update state, log
+    /// it, redraw frame, sleep.
+    pub fn render(&mut self, event: &VizEvent, node: Option<String>, at: Instant) {
+        if let Some(ref mut timer) = self.phase_timer {
+            timer.record(event, at);
+        }
         // Always record metrics and trace, even when viz is disabled.
         if let Some(ref mut metrics) = self.metrics {
-            metrics.record_event(event);
+            metrics.record_event(event, at);
         }
         if let Some(ref mut tracer) = self.tracer {
-            tracer.trace(event);
+            tracer.trace(event, at);
         }
 
         if !self.config.enabled {
             return;
         }
-
-        match event {
-            VizEvent::StateSnapshot { .. } => {
-                self.update_state(event);
-                // Full state replacement — redraw but no new log entry
-                self.draw_frame();
-            }
-            _ => {
-                self.update_state(event);
-                self.step_num += 1;
-                let line = Self::format_one_liner(event);
-                self.event_log.push(line);
-                self.draw_frame();
+        if node.is_some() && node == self.op_node {
+            if let Some(span) = timed_span(event) {
+                self.spans.push(span);
             }
         }
-
-        if !self.config.step_delay.is_zero() {
-            thread::sleep(self.config.step_delay);
+        if let Some(ref mut op) = self.last_op {
+            op.events.push((event.clone(), node.clone()));
         }
+        self.show(event, node);
     }
 
-    /// Map an event to the interaction line text for the active node.
-    fn interaction_text(event: &VizEvent) -> String {
-        match event {
-            VizEvent::MtrCreated { .. } => "\u{00b7} prepare MTR".to_string(),
-            VizEvent::AssignLsns { .. } => "\u{2193} assign LSNs".to_string(),
-            VizEvent::LinkPrevLsn { .. } => "\u{2193} link prev".to_string(),
-            VizEvent::WalAppend { .. } => "\u{2193} WAL append".to_string(),
-            VizEvent::WalSync => "\u{2193} WAL fsync".to_string(),
-            VizEvent::UpdatePageIndex { .. } => "\u{2193} update idx".to_string(),
-            VizEvent::UpdateLsnOffset { .. } => "\u{2193} lsn offset".to_string(),
-            VizEvent::AdvanceVcl { new, .. } => format!("\u{2191} VCL={new}"),
-            VizEvent::AdvanceVdl { new, .. } => format!("\u{2191} VDL={new}"),
-            VizEvent::UpdateReadPoint { new, .. } => format!("\u{00b7} rp\u{2192}{new}"),
-            VizEvent::BufferPoolInvalidate { page_id } => format!("\u{00b7} evict pg{page_id}"),
-            VizEvent::BufferPoolLookup { hit, .. } => {
-                if *hit { "\u{00b7} buf: HIT".to_string() } else { "\u{00b7} buf: MISS".to_string() }
-            }
-            VizEvent::PageCacheLookup { hit, .. } => {
-                if *hit { "\u{2193} cache: HIT".to_string() } else { "\u{2193} cache: MISS".to_string() }
-            }
-            VizEvent::PageIndexLookup { .. } => "\u{2193} idx lookup".to_string(),
-            VizEvent::ChainWalkStep { lsn, skipped, .. } => {
-                if *skipped {
-                    format!("\u{2193} skip L{lsn}")
-                } else {
-                    format!("\u{2193} collect L{lsn}")
-                }
-            }
-            VizEvent::ChainCollected { .. } => "\u{2193} chain done".to_string(),
-            VizEvent::MaterializeApply { lsn, .. } => format!("\u{2193} apply L{lsn}"),
-            VizEvent::MaterializeComplete { .. } => "\u{2193} materialized".to_string(),
-            VizEvent::PageCacheInsert { .. } => "\u{2191} cache insert".to_string(),
-            VizEvent::BufferPoolInsert { .. } => "\u{2191} page \u{2192} buf".to_string(),
-            VizEvent::SegmentRotation { new_id, .. } => format!("\u{2193} rotate\u{2192}seg{new_id}"),
-            VizEvent::ColdTierRead { segment_id, .. } => format!("\u{2193} cold seg{segment_id}"),
-            VizEvent::SegmentCooled { segment_id } => format!("\u{2193} cool seg{segment_id}"),
-            VizEvent::StateSnapshot { .. } => String::new(),
+    /// Play the latest operation's events again from the state it started
+    /// in. Nothing is traced or counted, and the panel returns to the
+    /// current state afterwards. Returns false if there is nothing to replay.
+    pub fn replay(&mut self) -> bool {
+        let Some(op) = self.last_op.take() else {
+            return false;
+        };
+        if !self.config.enabled || op.events.is_empty() {
+            self.last_op = Some(op);
+            return false;
         }
+        let current = std::mem::replace(&mut self.state, op.start.clone());
+        self.operation_header = format!("REPLAY {}", op.header);
+        self.event_log.clear();
+        self.step_num = 0;
+        self.finish_op = false;
+        self.draw_frame();
+        for (event, node) in &op.events {
+            self.show(event, node.clone());
+        }
+        self.state = current;
+        self.operation_header = op.header.clone();
+        self.last_op = Some(op);
+        true
     }
 
-    /// Update internal state from an event.
-    fn update_state(&mut self, event: &VizEvent) {
-        // Update interaction text for non-snapshot events
-        if !matches!(event, VizEvent::StateSnapshot { .. }) {
-            self.interaction = Self::interaction_text(event);
+    /// Apply an event to the panel, log it, redraw and pause. Events the
+    /// filter hides only update the panel state.
+    fn show(&mut self, event: &VizEvent, node: Option<String>) {
+        self.state.apply(event);
+        if self.cancel.load(Ordering::SeqCst) {
+            return;
         }
-
-        match event {
-            // Per-node events: route to active node
-            VizEvent::MtrCreated { mtr_id, .. } => {
-                if let Some(node) = self.nodes.get_mut(&self.active_node) {
-                    node.next_mtr = mtr_id + 1;
-                }
-            }
-            VizEvent::UpdateReadPoint { new, .. } => {
-                if let Some(node) = self.nodes.get_mut(&self.active_node) {
-                    node.read_point = *new;
-                }
+        if let VizEvent::StateSnapshot { .. } = event {
+            // Full state replacement — redraw but no new log entry, once an
+            // operation has given the frame a header
+            if self.operation_header.is_empty() {
+                return;
             }
-            VizEvent::BufferPoolInvalidate { page_id } => {
-                if let Some(node) = self.nodes.get_mut(&self.active_node) {
-                    node.buffer_pool.retain(|p| p != page_id);
-                }
-            }
-            VizEvent::BufferPoolInsert { page_id, .. } => {
-                if let Some(node) = self.nodes.get_mut(&self.active_node) {
-                    if !node.buffer_pool.contains(page_id) {
-                        node.buffer_pool.push(*page_id);
-                    }
+            self.draw_frame();
+        } else {
+            let line = Self::format_one_liner(event);
+            if let Some(ref filter) = self.filter {
+                if !filter.matches(event.name(), &line) {
+                    return;
                 }
             }
+            self.step_num += 1;
+            self.event_log.push(LogLine { step: Some(self.step_num), node, text: line });
+            self.draw_frame();
+        }
+        self.pause();
+    }
 
-            // Shared state events
-            VizEvent::AssignLsns { last_lsn, .. } => {
-                self.shared.next_lsn = last_lsn + 1;
-            }
-            VizEvent::WalAppend { offset, bytes, first_lsn, last_lsn } => {
-                self.shared.wal_file_size = offset + bytes;
-                self.shared.wal_lsn_range = Some(match self.shared.wal_lsn_range {
-                    Some((first, _)) => (first, *last_lsn),
-                    None => (*first_lsn, *last_lsn),
-                });
-            }
-            VizEvent::UpdatePageIndex { page_id, latest_lsn } => {
-                self.shared.page_index.insert(*page_id, *latest_lsn);
-            }
-            VizEvent::UpdateLsnOffset { .. } => {
-                self.shared.lsn_offset_count += 1;
-            }
-            VizEvent::AdvanceVcl { new, .. } => {
-                self.shared.vcl = *new;
-            }
-            VizEvent::AdvanceVdl { new, .. } => {
-                self.shared.vdl = *new;
-            }
-            VizEvent::PageCacheInsert { .. } => {
-                self.shared.page_cache_count += 1;
-            }
-            VizEvent::StateSnapshot {
-                node_label,
-                read_point,
-                next_mtr,
-                buffer_pool_pages,
-                next_lsn,
-                vcl,
-                vdl,
-                page_index,
-                lsn_offset_count,
-                page_cache_count,
-                wal_file_size,
-                wal_lsn_range,
-            } => {
-                // Update per-node state
-                if let Some(node) = self.nodes.get_mut(node_label) {
-                    node.read_point = *read_point;
-                    node.next_mtr = *next_mtr;
-                    node.buffer_pool = buffer_pool_pages.clone();
+    /// Wait for a keypress in step mode, otherwise sleep for `step_delay`.
+    fn pause(&mut self) {
+        if self.config.step && !self.finish_op {
+            if let Some(gate) = self.step_gate.as_ref().filter(|g| g.is_armed()) {
+                let a = Ansi::new(self.config.color);
+                let hint = a.dim("[step] Enter: next step, c: finish operation");
+                if gate.wait(hint) == StepAction::Finish {
+                    self.finish_op = true;
                 }
-                // Update shared state
-                self.shared.next_lsn = *next_lsn;
-                self.shared.vcl = *vcl;
-                self.shared.vdl = *vdl;
-                self.shared.page_index = page_index.clone();
-                self.shared.lsn_offset_count = *lsn_offset_count;
-                self.shared.page_cache_count = *page_cache_count;
-                self.shared.wal_file_size = *wal_file_size;
-                self.shared.wal_lsn_range = *wal_lsn_range;
+                return;
             }
-
-            // Events that don't change tracked state
-            _ => {}
+        }
+        if !self.config.step_delay.is_zero() {
+            thread::sleep(self.config.step_delay);
         }
     }
 
     /// Convert an event to a compact one-liner (plain text, no ANSI).
-    fn format_one_liner(event: &VizEvent) -> String {
+    pub fn format_one_liner(event: &VizEvent) -> String {
         match event {
             VizEvent::MtrCreated { mtr_id, num_records } => {
                 format!("MTR #{mtr_id} ({num_records} record)")
             }
+            VizEvent::WriteBatchFlushed { mtrs, records } => {
+                format!("Send batch: {mtrs} MTRs, {records} records in one append")
+            }
             VizEvent::AssignLsns { first_lsn, last_lsn } => {
                 if first_lsn == last_lsn {
                     format!("Assign LSN {first_lsn}")
@@ -353,7 +583,7 @@ impl VizRenderer {
                     format!("WAL append L{first_lsn}..{last_lsn} @{offset} ({bytes}B)")
                 }
             }
-            VizEvent::WalSync => "WAL sync (fsync)".to_string(),
+            VizEvent::WalSync { duration_us } => with_duration("WAL sync (fsync)".to_string(), *duration_us),
             VizEvent::UpdatePageIndex { page_id, latest_lsn } => {
                 format!("Page index pg{page_id}->L{latest_lsn}")
             }
@@ -368,6 +598,9 @@ impl VizRenderer {
             VizEvent::BufferPoolInvalidate { page_id } => {
                 format!("Evict pg{page_id} from buffer")
             }
+            VizEvent::BufferPoolWriteThrough { page_id, read_point } => {
+                format!("Patch pg{page_id} in buffer @L{read_point}")
+            }
             VizEvent::BufferPoolLookup { page_id, read_point, hit } => {
                 let tag = if *hit { "HIT" } else { "MISS" };
                 format!("BufPool pg{page_id} @L{read_point}: {tag}")
@@ -387,18 +620,19 @@ impl VizRenderer {
                     format!("Chain: collect L{lsn}")
                 }
             }
-            VizEvent::ChainCollected { page_id, chain_len, lsns } => {
+            VizEvent::ChainCollected { page_id, chain_len, lsns, duration_us } => {
                 let chain_str: Vec<String> = lsns.iter().map(|l| format!("L{l}")).collect();
-                format!(
+                let text = format!(
                     "Chain pg{page_id}: {chain_len} rec [{}]",
                     chain_str.join("->")
-                )
+                );
+                with_duration(text, *duration_us)
             }
             VizEvent::MaterializeApply { lsn, offset, data_len, .. } => {
                 format!("Apply L{lsn} {data_len}B @{offset}")
             }
-            VizEvent::MaterializeComplete { page_id, read_point } => {
-                format!("Materialized pg{page_id} @L{read_point}")
+            VizEvent::MaterializeComplete { page_id, read_point, duration_us } => {
+                with_duration(format!("Materialized pg{page_id} @L{read_point}"), *duration_us)
             }
             VizEvent::PageCacheInsert { page_id, read_point } => {
                 format!("Cache pg{page_id} @L{read_point}")
@@ -406,8 +640,14 @@ impl VizRenderer {
             VizEvent::BufferPoolInsert { page_id, read_point } => {
                 format!("BufPool insert pg{page_id} @L{read_point}")
             }
-            VizEvent::SegmentRotation { sealed_id, new_id, sealed_lsn_range } => {
-                format!("Segment rotate: seal seg{sealed_id} (L{}..L{}), open seg{new_id}", sealed_lsn_range.0, sealed_lsn_range.1)
+            VizEvent::WarmupProgress { page_id, done, total } => {
+                format!("Warmup pg{page_id} ({done}/{total})")
+            }
+            VizEvent::SegmentRotation { sealed_id, new_id, sealed_lsn_range, sealed_bytes } => {
+                format!(
+                    "Segment rotate: seal seg{sealed_id} (L{}..L{}, {sealed_bytes}B), open seg{new_id}",
+                    sealed_lsn_range.0, sealed_lsn_range.1
+                )
             }
             VizEvent::ColdTierRead { segment_id, latency_ms } => {
                 format!("Cold read seg{segment_id} (+{latency_ms}ms)")
@@ -415,13 +655,35 @@ impl VizRenderer {
             VizEvent::SegmentCooled { segment_id } => {
                 format!("Cooled seg{segment_id} hot->cold")
             }
+            VizEvent::SegmentPromoted { segment_id } => {
+                format!("Promoted seg{segment_id} cold->hot")
+            }
+            VizEvent::ScanEntry { lsn, page_id, file_offset, is_cpl } => {
+                let cpl = if *is_cpl { " CPL" } else { "" };
+                format!("Scan L{lsn}(pg{page_id}) @{file_offset}{cpl}")
+            }
+            VizEvent::VclComputed { vcl } => format!("VCL = {vcl} (no gaps below)"),
+            VizEvent::VdlComputed { vdl } => format!("VDL = {vdl} (last CPL <= VCL)"),
+            VizEvent::TruncateAt { offset, bytes } => {
+                format!("Truncate WAL @{offset} (-{bytes}B)")
+            }
+            VizEvent::IndexRebuilt { pages, records } => {
+                format!("Index rebuilt: {pages} pages, {records} records")
+            }
+            VizEvent::StorageHealthChanged { node, healthy } => {
+                if *healthy {
+                    format!("Node {node}: heartbeat acked, storage healthy")
+                } else {
+                    format!("Node {node}: heartbeats missed, storage unhealthy")
+                }
+            }
             VizEvent::StateSnapshot { .. } => String::new(),
         }
     }
 
-    /// Build the 15 right-panel lines from current state.
+    /// Build the right-panel lines from current state.
     ///
-    /// Layout:
+    /// Layout (two nodes shown; 15 rows):
     ///   Row 0:    top border "Node A @L<rp>"
     ///   Row 1:    Node A state line
     ///   Row 2:    Node A interaction line
@@ -435,42 +697,39 @@ impl VizRenderer {
     ///   Row 10:   separator "WAL"
     ///   Row 11:   LSN entries
     ///   Row 12:   size
-    ///   Row 13:   blank
+    ///   Row 13:   node page ("nodes 3-4 of 5") or blank
     ///   Row 14:   bottom border
-    fn build_panel_lines(&self) -> Vec<String> {
+    ///
+    /// Tiered storage adds a SEGMENTS section after the WAL with one row
+    /// per segment, newest last, up to `MAX_SEGMENT_ROWS`.
+    ///
+    /// Each node takes three rows. When more than `max_nodes` are
+    /// registered, only the page of nodes holding the active one is shown.
+    /// `inner` is the width between the borders.
+    fn build_panel_lines(&self, inner: usize, max_nodes: usize) -> Vec<String> {
         let a = Ansi::new(self.config.color);
-        let w = PANEL_INNER;
-        let panel_w = PANEL_INNER + 2;
+        let w = inner;
+        let panel_w = inner + 2;
 
         // Collect nodes in order (BTreeMap gives sorted keys)
-        let node_labels: Vec<&String> = self.nodes.keys().collect();
+        let node_labels: Vec<&String> = self.state.nodes.keys().collect();
 
-        // Helper: get node state or default
         let get_node = |idx: usize| -> (&str, &NodeState) {
-            static DEFAULT: NodeState = NodeState {
-                read_point: 0,
-                next_mtr: 1,
-                buffer_pool: Vec::new(),
-            };
-            if idx < node_labels.len() {
-                let label = node_labels[idx];
-                (label.as_str(), self.nodes.get(label).unwrap_or(&DEFAULT))
-            } else {
-                ("?", &DEFAULT)
-            }
+            let label = node_labels[idx];
+            (label.as_str(), &self.state.nodes[label])
         };
 
         // Format buffer pool for a node
-        let fmt_bp = |bp: &[PageId]| -> String {
+        let fmt_bp = |bp: &[(PageId, Lsn)]| -> String {
             if bp.is_empty() {
                 "(empty)".to_string()
             } else {
-                bp.iter().map(|p| format!("pg{p}")).collect::<Vec<_>>().join(",")
+                bp.iter().map(|(p, l)| format!("pg{p}@L{l}")).collect::<Vec<_>>().join(",")
             }
         };
 
         // Format page index
-        let s = &self.shared;
+        let s = &self.state.shared;
         let mut pi_entries: Vec<_> = s.page_index.iter().collect();
         pi_entries.sort_by_key(|(&pid, _)| pid);
         let pi_str = if pi_entries.is_empty() {
@@ -493,15 +752,8 @@ impl VizRenderer {
             None => "(empty)".to_string(),
         };
 
-        // Pad/truncate a plain-text row to PANEL_INNER
-        let pad_row = |text: &str| -> String {
-            let len = text.len();
-            if len >= w {
-                text[..w].to_string()
-            } else {
-                format!("{text}{}", " ".repeat(w - len))
-            }
-        };
+        // Pad/truncate a plain-text row to the panel width
+        let pad_row = |text: &str| -> String { fit(text, w) };
 
         // Section header helpers
         let section_top = |label: &str| -> String {
@@ -523,9 +775,9 @@ impl VizRenderer {
             let bp = fmt_bp(&node.buffer_pool);
             let state_text = format!(" mtr:{} buf:{}", node.next_mtr, bp);
 
-            let is_active = label == self.active_node;
-            let interaction_text = if is_active && !self.interaction.is_empty() {
-                format!("  {}", self.interaction)
+            let is_active = label == self.state.active_node;
+            let interaction_text = if is_active && !self.state.interaction.is_empty() {
+                format!("  {}", self.state.interaction)
             } else {
                 "  (idle)".to_string()
             };
@@ -536,7 +788,7 @@ impl VizRenderer {
                 a.yellow(&pad_row(&state_text)),
                 a.cyan("\u{2502}")
             );
-            let int_line = if is_active && !self.interaction.is_empty() {
+            let int_line = if is_active && !self.state.interaction.is_empty() {
                 format!(
                     "{}{}{}",
                     a.cyan("\u{2502}"),
@@ -557,129 +809,212 @@ impl VizRenderer {
 
         let mut lines = Vec::with_capacity(PANEL_HEIGHT);
 
-        // --- Node A (row 0-2) ---
-        let (label_a, node_a) = get_node(0);
-        let header_a = format!("Node {} @L{}", label_a, node_a.read_point);
-        lines.push(section_top(&header_a)); // row 0
-        let (state_a, int_a) = build_node_lines(0);
-        lines.push(state_a);  // row 1
-        lines.push(int_a);    // row 2
-
-        // --- Node B (row 3-5) ---
-        if node_labels.len() > 1 {
-            let (label_b, node_b) = get_node(1);
-            let header_b = format!("Node {} @L{}", label_b, node_b.read_point);
-            lines.push(section_sep(&header_b)); // row 3
-            let (state_b, int_b) = build_node_lines(1);
-            lines.push(state_b);  // row 4
-            lines.push(int_b);    // row 5
-        } else {
-            // Single-node mode: fill with blanks
-            lines.push(section_sep("(single node)"));
-            lines.push(format!(
-                "{}{}{}",
-                a.cyan("\u{2502}"),
-                pad_row(""),
-                a.cyan("\u{2502}")
-            ));
-            lines.push(format!(
-                "{}{}{}",
-                a.cyan("\u{2502}"),
-                pad_row(""),
-                a.cyan("\u{2502}")
-            ));
+        // --- Nodes (3 rows each) ---
+        let total = node_labels.len();
+        let per_page = max_nodes.max(1);
+        let active_idx = node_labels
+            .iter()
+            .position(|l| **l == self.state.active_node)
+            .unwrap_or(0);
+        let first = active_idx / per_page * per_page;
+        let shown = first..(first + per_page).min(total);
+        for idx in shown.clone() {
+            let (label, node) = get_node(idx);
+            let down = if node.storage_down { " \u{2717}storage" } else { "" };
+            let header = format!("Node {} @L{}{down}", label, node.read_point);
+            lines.push(if idx == first { section_top(&header) } else { section_sep(&header) });
+            let (state_line, int_line) = build_node_lines(idx);
+            lines.push(state_line);
+            lines.push(int_line);
+        }
+        if total == 0 {
+            lines.push(section_top("(no nodes)"));
         }
 
-        // --- STORAGE (row 6-9) ---
-        lines.push(section_sep("STORAGE")); // row 6
+        // --- STORAGE ---
+        lines.push(section_sep("STORAGE"));
         let vcl_line = format!(" VCL:{} VDL:{} nxt:{}", s.vcl, s.vdl, s.next_lsn);
         lines.push(format!(
             "{}{}{}",
             a.cyan("\u{2502}"),
             a.yellow(&pad_row(&vcl_line)),
             a.cyan("\u{2502}")
-        )); // row 7
+        ));
         let pg_line = format!(" pg: {pi_str}");
         lines.push(format!(
             "{}{}{}",
             a.cyan("\u{2502}"),
             a.yellow(&pad_row(&pg_line)),
             a.cyan("\u{2502}")
-        )); // row 8
+        ));
         let cache_line = format!(" cache:{} off:{}", s.page_cache_count, s.lsn_offset_count);
         lines.push(format!(
             "{}{}{}",
             a.cyan("\u{2502}"),
             a.yellow(&pad_row(&cache_line)),
             a.cyan("\u{2502}")
-        )); // row 9
+        ));
 
-        // --- WAL (row 10-12) ---
-        lines.push(section_sep("WAL")); // row 10
+        // --- WAL ---
+        lines.push(section_sep("WAL"));
         let wal_display = format!(" {wal_str}");
         lines.push(format!(
             "{}{}{}",
             a.cyan("\u{2502}"),
             a.green(&pad_row(&wal_display)),
             a.cyan("\u{2502}")
-        )); // row 11
+        ));
         let wal_size = format!(" {} bytes", s.wal_file_size);
         lines.push(format!(
             "{}{}{}",
             a.cyan("\u{2502}"),
             a.green(&pad_row(&wal_size)),
             a.cyan("\u{2502}")
-        )); // row 12
+        ));
 
-        // Row 13: blank
+        // --- SEGMENTS (tiered only) ---
+        if !s.segments.is_empty() {
+            let older = s.segments.len().saturating_sub(MAX_SEGMENT_ROWS);
+            lines.push(section_sep(&if older > 0 {
+                format!("SEGMENTS +{older} older")
+            } else {
+                "SEGMENTS".to_string()
+            }));
+            for (id, seg) in s.segments.iter().skip(older) {
+                let range = if seg.lsn_range.0 == 0 {
+                    "-".to_string()
+                } else {
+                    format!("L{}-{}", seg.lsn_range.0, seg.lsn_range.1)
+                };
+                let marker = if seg.sealed { ' ' } else { '*' };
+                let promoted = if seg.promoted { "\u{2191}" } else { " " };
+                let mut text = match seg.tier {
+                    Tier::Hot => format!(" seg{id}{marker}HOT{promoted}{range} {}B", seg.size_bytes),
+                    Tier::Cold => format!(" seg{id}{marker}COLD {range} {}B", seg.size_bytes),
+                };
+                if seg.cold_reads > 0 {
+                    text.push_str(&format!(" r{}", seg.cold_reads));
+                }
+                let body = match seg.tier {
+                    Tier::Hot => a.yellow(&pad_row(&text)),
+                    Tier::Cold => a.dim(&pad_row(&text)),
+                };
+                lines.push(format!("{}{body}{}", a.cyan("\u{2502}"), a.cyan("\u{2502}")));
+            }
+        }
+
+        // Which page of nodes is shown, when they don't all fit
+        let paging = if shown.len() < total {
+            format!(" nodes {}-{} of {total}", shown.start + 1, shown.end)
+        } else {
+            String::new()
+        };
         lines.push(format!(
             "{}{}{}",
             a.cyan("\u{2502}"),
-            pad_row(""),
+            a.dim(&pad_row(&paging)),
             a.cyan("\u{2502}")
         ));
-        // Row 14: bottom border
+        // Bottom border
         lines.push(a.cyan(&format!("\u{2514}{}\u{2518}", "\u{2500}".repeat(panel_w - 2))));
 
         lines
     }
 
-    /// Format a single event log entry for the left column.
+    /// Rows taken by the SEGMENTS section, including its header.
+    fn segment_rows(&self) -> usize {
+        match self.state.shared.segments.len() {
+            0 => 0,
+            n => 1 + n.min(MAX_SEGMENT_ROWS),
+        }
+    }
+
+    /// Format a single event log entry for the left column. Events taken
+    /// for a node are tagged and colored by node, and with `lanes` on are
+    /// placed in that node's lane; untagged events get the last lane.
     fn format_log_entry(&self, idx: usize, is_active: bool, width: usize) -> String {
         let a = Ansi::new(self.config.color);
-        let step = idx + 1; // 1-based step numbers
-        let text = &self.event_log[idx];
+        let line = &self.event_log[idx];
+        let Some(step) = line.step else {
+            // Annotations span the whole log, lanes or not
+            return a.dim(&fit(&format!("     {}", line.text), width));
+        };
+        let node_idx = line.node.as_ref().and_then(|n| self.state.nodes.keys().position(|l| l == n));
 
-        // Build plain text: ">3. text" or " 3. text"
+        // Build plain text: ">3. [A] text" or " 3. text"
         let prefix = if is_active { ">" } else { " " };
-        let plain = format!("{prefix}{step:>2}. {text}");
+        let plain = match &line.node {
+            Some(node) => format!("{prefix}{step:>2}. [{node}] {}", line.text),
+            None => format!("{prefix}{step:>2}. {}", line.text),
+        };
 
-        // Truncate or pad to width
-        let display = if plain.len() > width {
-            format!("{}..", &plain[..width - 2])
+        let lanes = self.state.nodes.len() + 1;
+        let (lane, lane_width) = if self.config.lanes && width / lanes >= MIN_LANE_WIDTH {
+            (node_idx.unwrap_or(lanes - 1), width / lanes)
         } else {
-            format!("{plain:<width$}")
+            (0, width)
         };
 
-        if is_active {
-            a.bold_green(&display)
+        // Truncate or pad to the lane
+        let display = if plain.chars().count() > lane_width && lane_width > 2 {
+            format!("{}..", fit(&plain, lane_width - 2))
         } else {
-            a.dim(&display)
-        }
+            fit(&plain, lane_width)
+        };
+
+        let colored = match node_idx {
+            Some(i) => a.paint(NODE_COLORS[i % NODE_COLORS.len()], is_active, &display),
+            None if is_active => a.bold_green(&display),
+            None => a.dim(&display),
+        };
+        let before = lane * lane_width;
+        let after = width.saturating_sub(before + lane_width);
+        format!("{}{colored}{}", " ".repeat(before), " ".repeat(after))
     }
 
-    /// Build and flush the entire two-column frame.
-    fn draw_frame(&self) {
+    /// The metrics row below the frame: storage request rate, hit rates,
+    /// watermarks, WAL written and cold reads. `None` without metrics.
+    fn metrics_strip(&self, width: usize) -> Option<String> {
+        let a = Ansi::new(self.config.color);
+        let m = self.metrics.as_ref()?.summary();
+        let cells = [
+            format!("{:.1} ops/s", m.ops_per_sec),
+            format!("cache {}%", m.cache_hit_pct()),
+            format!("bufpool {}%", m.buffer_pool_hit_pct()),
+            format!("VCL {} VDL {}", m.vcl, m.vdl),
+            format!("WAL {:.2} MB", m.wal_bytes_written as f64 / (1024.0 * 1024.0)),
+            format!("cold {}", m.cold_reads),
+        ];
+        let text = format!(" {}", cells.join(" \u{2502} "));
+        Some(a.yellow(&fit(&text, width)))
+    }
+
+    /// Build and flush the entire frame at the current terminal size.
+    ///
+    /// Wide terminals get the log and panel side by side; narrow ones get
+    /// the panel stacked above the log. Rows beyond the terminal height are
+    /// dropped so the frame never scrolls.
+    fn draw_frame(&mut self) {
         let a = Ansi::new(self.config.color);
         let mut buf = String::with_capacity(4096);
 
+        let (term_width, term_height) = terminal_size();
+        if (term_width, term_height) != self.last_size {
+            // Old rows may have wrapped at the previous width
+            buf.push_str("\x1b[2J");
+            self.last_size = (term_width, term_height);
+        }
+
         // Cursor home (no full clear — we overwrite in-place)
         buf.push_str("\x1b[H");
 
         // Line 1: operation header (full width)
-        let header = format!("\u{2550}\u{2550}\u{2550} {} ", self.operation_header);
+        let mut header = format!("\u{2550}\u{2550}\u{2550} {} ", self.operation_header);
+        if header.chars().count() > term_width {
+            header = fit(&header, term_width);
+        }
         let header_visual_len = header.chars().count();
-        let pad = self.term_width.saturating_sub(header_visual_len);
+        let pad = term_width.saturating_sub(header_visual_len);
         let header_line = format!("{header}{}", "\u{2550}".repeat(pad));
         buf.push_str(&a.bold(&a.cyan(&header_line)));
         buf.push_str("\x1b[K\n"); // clear rest of line
@@ -687,30 +1022,58 @@ impl VizRenderer {
         // Blank line
         buf.push_str("\x1b[K\n");
 
-        // Build right panel
-        let panel_lines = self.build_panel_lines();
+        let strip = self.config.metrics_strip.then(|| self.metrics_strip(term_width)).flatten();
+        let strip_rows = usize::from(strip.is_some());
+        let rows = term_height.saturating_sub(2 + RESERVED_ROWS + strip_rows).max(1);
+        let side_by_side = term_width >= PANEL_INNER + 2 + GUTTER + MIN_LOG_WIDTH;
+
+        if side_by_side {
+            // Left column width: total - panel_width(26) - gutter(2)
+            let max_nodes = rows.saturating_sub(PANEL_FIXED_ROWS + self.segment_rows()) / 3;
+            let panel_lines = self.build_panel_lines(PANEL_INNER, max_nodes);
+            let left_width = term_width - (PANEL_INNER + 2 + GUTTER);
+            let height = panel_lines.len().min(rows);
 
-        // Left column width: total - panel_width(26) - gutter(2)
-        let panel_total = PANEL_INNER + 2; // borders
-        let gutter = 2;
-        let left_width = self.term_width.saturating_sub(panel_total + gutter);
+            // Show the latest entries that fit beside the panel
+            let log_start = self.event_log.len().saturating_sub(height);
+
+            for (row, panel_line) in panel_lines.iter().enumerate().take(height) {
+                let log_idx = log_start + row;
+                let left = if log_idx < self.event_log.len() {
+                    let is_active = log_idx == self.event_log.len() - 1;
+                    self.format_log_entry(log_idx, is_active, left_width)
+                } else {
+                    " ".repeat(left_width)
+                };
 
-        // Show last PANEL_HEIGHT entries from event log
-        let log_start = self.event_log.len().saturating_sub(PANEL_HEIGHT);
+                buf.push_str(&left);
+                buf.push_str(&" ".repeat(GUTTER));
+                buf.push_str(panel_line);
+                buf.push_str("\x1b[K\n"); // clear rest of line
+            }
+        } else {
+            // Stacked: full-width panel, then as much of the log as fits
+            // Leave a few rows for the log below
+            let max_nodes = rows.saturating_sub(PANEL_FIXED_ROWS + self.segment_rows() + 3) / 3;
+            let panel_lines = self.build_panel_lines(term_width.saturating_sub(2).max(12), max_nodes);
+            let panel_rows = panel_lines.len().min(rows);
+            for panel_line in panel_lines.iter().take(panel_rows) {
+                buf.push_str(panel_line);
+                buf.push_str("\x1b[K\n");
+            }
 
-        for row in 0..PANEL_HEIGHT {
-            let log_idx = log_start + row;
-            let left = if log_idx < self.event_log.len() {
+            let log_rows = (rows - panel_rows).min(PANEL_HEIGHT);
+            let log_start = self.event_log.len().saturating_sub(log_rows);
+            for log_idx in log_start..self.event_log.len() {
                 let is_active = log_idx == self.event_log.len() - 1;
-                self.format_log_entry(log_idx, is_active, left_width)
-            } else {
-                " ".repeat(left_width)
-            };
+                buf.push_str(&self.format_log_entry(log_idx, is_active, term_width));
+                buf.push_str("\x1b[K\n");
+            }
+        }
 
-            buf.push_str(&left);
-            buf.push_str(&" ".repeat(gutter));
-            buf.push_str(&panel_lines[row]);
-            buf.push_str("\x1b[K\n"); // clear rest of line
+        if let Some(strip) = strip {
+            buf.push_str(&strip);
+            buf.push_str("\x1b[K\n");
         }
 
         // Clear any leftover lines from previous taller frames
@@ -723,6 +1086,76 @@ impl VizRenderer {
     }
 }
 
+/// A step's label and duration for the latency waterfall, if the event
+/// carries a measured or simulated time.
+fn timed_span(event: &VizEvent) -> Option<(String, Duration)> {
+    let (label, elapsed) = match event {
+        VizEvent::WalSync { duration_us } => ("WAL fsync".to_string(), Duration::from_micros(*duration_us)),
+        VizEvent::ColdTierRead { segment_id, latency_ms } => {
+            (format!("cold seg{segment_id}"), Duration::from_millis(*latency_ms))
+        }
+        VizEvent::ChainCollected { page_id, duration_us, .. } => {
+            (format!("chain pg{page_id}"), Duration::from_micros(*duration_us))
+        }
+        VizEvent::MaterializeComplete { page_id, duration_us, .. } => {
+            (format!("materialize pg{page_id}"), Duration::from_micros(*duration_us))
+        }
+        _ => return None,
+    };
+    // Steps too quick to time (an in-memory WAL, or wasm32) are left out
+    (!elapsed.is_zero()).then_some((label, elapsed))
+}
+
+/// A waterfall bar for a step running `elapsed` from `start`, out of an
+/// operation taking `total`. Every step gets at least one cell.
+fn waterfall_bar(start: Duration, elapsed: Duration, total: Duration) -> String {
+    let cell = |d: Duration| {
+        let share = d.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON);
+        ((share * WATERFALL_WIDTH as f64).round() as usize).min(WATERFALL_WIDTH)
+    };
+    let from = cell(start).min(WATERFALL_WIDTH - 1);
+    let to = cell(start + elapsed).max(from + 1);
+    format!(
+        "{}{}{}",
+        " ".repeat(from),
+        "\u{2588}".repeat(to - from),
+        "\u{00b7}".repeat(WATERFALL_WIDTH - to)
+    )
+}
+
+/// `text` followed by how long its step took, when that was measured.
+fn with_duration(text: String, duration_us: u64) -> String {
+    if duration_us == 0 {
+        return text;
+    }
+    format!("{text} ({})", format_elapsed(Duration::from_micros(duration_us)))
+}
+
+/// Current terminal size as (columns, rows), read on every frame so resizes
+/// reflow the layout. Falls back to `COLUMNS`/`LINES`, then 80x24, when
+/// stdout is not a terminal.
+fn terminal_size() -> (usize, usize) {
+    if let Ok((cols, rows)) = ratatui::crossterm::terminal::size() {
+        if cols > 0 && rows > 0 {
+            return (cols as usize, rows as usize);
+        }
+    }
+    let env = |name: &str, default: usize| {
+        std::env::var(name).ok().and_then(|s| s.parse().ok()).unwrap_or(default)
+    };
+    (env("COLUMNS", 80), env("LINES", 24))
+}
+
+/// Pad or truncate `text` to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    if len >= width {
+        text.chars().take(width).collect()
+    } else {
+        format!("{text}{}", " ".repeat(width - len))
+    }
+}
+
 /// Helper: format a data preview (first N bytes as string or hex).
 pub fn data_preview(data: &[u8], max_len: usize) -> String {
     let slice = if data.len() > max_len { &data[..max_len] } else { data };
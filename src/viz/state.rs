@@ -0,0 +1,351 @@
+use std::collections::{BTreeMap, HashMap};
+
+use mini_aurora_common::{Lsn, PageId};
+use mini_aurora_wal::segment::{SegmentMeta, Tier};
+
+use super::events::VizEvent;
+
+/// Per-node compute state tracked from events.
+#[derive(Clone)]
+pub struct NodeState {
+    pub read_point: Lsn,
+    pub next_mtr: u64,
+    /// Resident pages and the read point each image is as of.
+    pub buffer_pool: Vec<(PageId, Lsn)>,
+    /// Heartbeats have marked storage unhealthy.
+    pub storage_down: bool,
+}
+
+impl Default for NodeState {
+    fn default() -> Self {
+        Self {
+            read_point: 0,
+            next_mtr: 1,
+            buffer_pool: Vec::new(),
+            storage_down: false,
+        }
+    }
+}
+
+/// A WAL segment as tracked from tiering events (tiered storage only).
+#[derive(Clone)]
+pub struct SegmentView {
+    pub tier: Tier,
+    /// (0, 0) until the segment holds a record.
+    pub lsn_range: (Lsn, Lsn),
+    pub size_bytes: u64,
+    pub sealed: bool,
+    /// Reads that paid cold-tier latency for this segment.
+    pub cold_reads: u64,
+    /// Back in the hot tier after a cold read promoted it.
+    pub promoted: bool,
+}
+
+impl From<&SegmentMeta> for SegmentView {
+    fn from(meta: &SegmentMeta) -> Self {
+        Self {
+            tier: meta.tier,
+            lsn_range: meta.lsn_range,
+            size_bytes: meta.size_bytes,
+            sealed: meta.sealed,
+            cold_reads: 0,
+            promoted: meta.promoted,
+        }
+    }
+}
+
+/// Shared (storage + WAL) state tracked incrementally from events.
+#[derive(Clone, Default)]
+pub struct SharedState {
+    pub next_lsn: Lsn,
+    pub vcl: Lsn,
+    pub vdl: Lsn,
+    pub page_index: HashMap<PageId, Lsn>,
+    pub lsn_offset_count: usize,
+    pub page_cache_count: u64,
+    pub wal_file_size: u64,
+    pub wal_lsn_range: Option<(Lsn, Lsn)>,
+    /// Segments by id; empty for single-file storage.
+    pub segments: BTreeMap<u32, SegmentView>,
+}
+
+/// Cluster state as seen through the event stream, shared by the frame
+/// renderer and the TUI.
+#[derive(Clone)]
+pub struct VizState {
+    pub nodes: BTreeMap<String, NodeState>,
+    /// Node that per-node events are attributed to.
+    pub active_node: String,
+    /// Short description of the active node's latest step.
+    pub interaction: String,
+    pub shared: SharedState,
+}
+
+impl Default for VizState {
+    fn default() -> Self {
+        Self {
+            nodes: BTreeMap::new(),
+            active_node: String::new(),
+            interaction: String::new(),
+            shared: SharedState {
+                next_lsn: 1,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl VizState {
+    /// Register a compute node so it appears in the panel.
+    pub fn register_node(&mut self, label: &str) {
+        self.nodes.entry(label.to_string()).or_default();
+    }
+
+    /// Set the active node for subsequent events. Clears the interaction text.
+    pub fn set_active_node(&mut self, label: &str) {
+        self.active_node = label.to_string();
+        self.interaction.clear();
+    }
+
+    /// Track segments from the engine's current list, keeping the cold read
+    /// counts of segments already known. Only called for tiered storage, so
+    /// the SEGMENTS section stays hidden otherwise.
+    pub fn seed_segments(&mut self, segments: &[SegmentMeta]) {
+        let old = std::mem::take(&mut self.shared.segments);
+        self.shared.segments = segments
+            .iter()
+            .map(|s| {
+                let mut view = SegmentView::from(s);
+                view.cold_reads = old.get(&s.id).map_or(0, |v| v.cold_reads);
+                (s.id, view)
+            })
+            .collect();
+    }
+
+    /// Update state from an event.
+    pub fn apply(&mut self, event: &VizEvent) {
+        // Update interaction text for non-snapshot events
+        if !matches!(event, VizEvent::StateSnapshot { .. }) {
+            self.interaction = interaction_text(event);
+        }
+
+        match event {
+            // Per-node events: route to active node
+            VizEvent::MtrCreated { mtr_id, .. } => {
+                if let Some(node) = self.nodes.get_mut(&self.active_node) {
+                    node.next_mtr = mtr_id + 1;
+                }
+            }
+            VizEvent::UpdateReadPoint { new, .. } => {
+                if let Some(node) = self.nodes.get_mut(&self.active_node) {
+                    node.read_point = *new;
+                }
+            }
+            VizEvent::BufferPoolInvalidate { page_id } => {
+                if let Some(node) = self.nodes.get_mut(&self.active_node) {
+                    node.buffer_pool.retain(|(p, _)| p != page_id);
+                }
+            }
+            VizEvent::BufferPoolInsert { page_id, read_point } => {
+                if let Some(node) = self.nodes.get_mut(&self.active_node) {
+                    match node.buffer_pool.iter_mut().find(|(p, _)| p == page_id) {
+                        Some(entry) => entry.1 = *read_point,
+                        None => node.buffer_pool.push((*page_id, *read_point)),
+                    }
+                }
+            }
+            VizEvent::BufferPoolWriteThrough { page_id, read_point } => {
+                if let Some(node) = self.nodes.get_mut(&self.active_node) {
+                    if let Some(entry) = node.buffer_pool.iter_mut().find(|(p, _)| p == page_id) {
+                        entry.1 = *read_point;
+                    }
+                }
+            }
+
+            // Shared state events
+            VizEvent::AssignLsns { last_lsn, .. } => {
+                self.shared.next_lsn = last_lsn + 1;
+            }
+            VizEvent::WalAppend { offset, bytes, first_lsn, last_lsn } => {
+                self.shared.wal_file_size = offset + bytes;
+                self.shared.wal_lsn_range = Some(match self.shared.wal_lsn_range {
+                    Some((first, _)) => (first, *last_lsn),
+                    None => (*first_lsn, *last_lsn),
+                });
+                // Credit the whole batch to the active segment; rotations
+                // that follow move the sealed part out again
+                if let Some(active) = self.shared.segments.values_mut().next_back() {
+                    active.size_bytes += bytes;
+                    if active.lsn_range.0 == 0 {
+                        active.lsn_range.0 = *first_lsn;
+                    }
+                    active.lsn_range.1 = *last_lsn;
+                }
+            }
+            VizEvent::SegmentRotation { sealed_id, new_id, sealed_lsn_range, sealed_bytes } => {
+                let (size, last_lsn) = match self.shared.segments.get_mut(sealed_id) {
+                    Some(sealed) => {
+                        let carried = (sealed.size_bytes, sealed.lsn_range.1);
+                        sealed.sealed = true;
+                        sealed.lsn_range = *sealed_lsn_range;
+                        sealed.size_bytes = *sealed_bytes;
+                        carried
+                    }
+                    None => (*sealed_bytes, sealed_lsn_range.1),
+                };
+                let lsn_range = if last_lsn > sealed_lsn_range.1 {
+                    (sealed_lsn_range.1 + 1, last_lsn)
+                } else {
+                    (0, 0)
+                };
+                self.shared.segments.insert(*new_id, SegmentView {
+                    tier: Tier::Hot,
+                    lsn_range,
+                    size_bytes: size.saturating_sub(*sealed_bytes),
+                    sealed: false,
+                    cold_reads: 0,
+                    promoted: false,
+                });
+            }
+            VizEvent::SegmentCooled { segment_id } => {
+                if let Some(segment) = self.shared.segments.get_mut(segment_id) {
+                    segment.tier = Tier::Cold;
+                    segment.promoted = false;
+                }
+            }
+            VizEvent::SegmentPromoted { segment_id } => {
+                if let Some(segment) = self.shared.segments.get_mut(segment_id) {
+                    segment.tier = Tier::Hot;
+                    segment.promoted = true;
+                }
+            }
+            VizEvent::ColdTierRead { segment_id, .. } => {
+                if let Some(segment) = self.shared.segments.get_mut(segment_id) {
+                    segment.cold_reads += 1;
+                }
+            }
+            VizEvent::UpdatePageIndex { page_id, latest_lsn } => {
+                self.shared.page_index.insert(*page_id, *latest_lsn);
+            }
+            VizEvent::UpdateLsnOffset { .. } => {
+                self.shared.lsn_offset_count += 1;
+            }
+            VizEvent::AdvanceVcl { new, .. } => {
+                self.shared.vcl = *new;
+            }
+            VizEvent::AdvanceVdl { new, .. } => {
+                self.shared.vdl = *new;
+            }
+            VizEvent::PageCacheInsert { .. } => {
+                self.shared.page_cache_count += 1;
+            }
+            VizEvent::VclComputed { vcl } => {
+                self.shared.vcl = *vcl;
+            }
+            VizEvent::VdlComputed { vdl } => {
+                self.shared.vdl = *vdl;
+                self.shared.next_lsn = vdl + 1;
+            }
+            VizEvent::TruncateAt { offset, .. } => {
+                self.shared.wal_file_size = *offset;
+            }
+            VizEvent::IndexRebuilt { records, .. } => {
+                // Recovery starts from an empty page cache
+                self.shared.lsn_offset_count = *records;
+                self.shared.page_cache_count = 0;
+            }
+            VizEvent::StorageHealthChanged { node, healthy } => {
+                if let Some(node) = self.nodes.get_mut(node) {
+                    node.storage_down = !healthy;
+                }
+            }
+            VizEvent::StateSnapshot {
+                node_label,
+                read_point,
+                next_mtr,
+                buffer_pool_pages,
+                next_lsn,
+                vcl,
+                vdl,
+                page_index,
+                lsn_offset_count,
+                page_cache_count,
+                wal_file_size,
+                wal_lsn_range,
+            } => {
+                // Update per-node state
+                if let Some(node) = self.nodes.get_mut(node_label) {
+                    node.read_point = *read_point;
+                    node.next_mtr = *next_mtr;
+                    node.buffer_pool = buffer_pool_pages.clone();
+                }
+                // Update shared state
+                self.shared.next_lsn = *next_lsn;
+                self.shared.vcl = *vcl;
+                self.shared.vdl = *vdl;
+                self.shared.page_index = page_index.clone();
+                self.shared.lsn_offset_count = *lsn_offset_count;
+                self.shared.page_cache_count = *page_cache_count;
+                self.shared.wal_file_size = *wal_file_size;
+                self.shared.wal_lsn_range = *wal_lsn_range;
+            }
+
+            // Events that don't change tracked state
+            _ => {}
+        }
+    }
+}
+
+/// Map an event to the interaction line text for the active node.
+fn interaction_text(event: &VizEvent) -> String {
+    match event {
+        VizEvent::MtrCreated { .. } => "\u{00b7} prepare MTR".to_string(),
+        VizEvent::WriteBatchFlushed { mtrs, .. } => format!("\u{2193} send {mtrs} MTRs"),
+        VizEvent::AssignLsns { .. } => "\u{2193} assign LSNs".to_string(),
+        VizEvent::LinkPrevLsn { .. } => "\u{2193} link prev".to_string(),
+        VizEvent::WalAppend { .. } => "\u{2193} WAL append".to_string(),
+        VizEvent::WalSync { .. } => "\u{2193} WAL fsync".to_string(),
+        VizEvent::UpdatePageIndex { .. } => "\u{2193} update idx".to_string(),
+        VizEvent::UpdateLsnOffset { .. } => "\u{2193} lsn offset".to_string(),
+        VizEvent::AdvanceVcl { new, .. } => format!("\u{2191} VCL={new}"),
+        VizEvent::AdvanceVdl { new, .. } => format!("\u{2191} VDL={new}"),
+        VizEvent::UpdateReadPoint { new, .. } => format!("\u{00b7} rp\u{2192}{new}"),
+        VizEvent::BufferPoolInvalidate { page_id } => format!("\u{00b7} evict pg{page_id}"),
+        VizEvent::BufferPoolWriteThrough { page_id, .. } => format!("\u{00b7} patch pg{page_id}"),
+        VizEvent::BufferPoolLookup { hit, .. } => {
+            if *hit { "\u{00b7} buf: HIT".to_string() } else { "\u{00b7} buf: MISS".to_string() }
+        }
+        VizEvent::PageCacheLookup { hit, .. } => {
+            if *hit { "\u{2193} cache: HIT".to_string() } else { "\u{2193} cache: MISS".to_string() }
+        }
+        VizEvent::PageIndexLookup { .. } => "\u{2193} idx lookup".to_string(),
+        VizEvent::ChainWalkStep { lsn, skipped, .. } => {
+            if *skipped {
+                format!("\u{2193} skip L{lsn}")
+            } else {
+                format!("\u{2193} collect L{lsn}")
+            }
+        }
+        VizEvent::ChainCollected { .. } => "\u{2193} chain done".to_string(),
+        VizEvent::MaterializeApply { lsn, .. } => format!("\u{2193} apply L{lsn}"),
+        VizEvent::MaterializeComplete { .. } => "\u{2193} materialized".to_string(),
+        VizEvent::PageCacheInsert { .. } => "\u{2191} cache insert".to_string(),
+        VizEvent::BufferPoolInsert { .. } => "\u{2191} page \u{2192} buf".to_string(),
+        VizEvent::WarmupProgress { done, total, .. } => format!("\u{2191} warm {done}/{total}"),
+        VizEvent::SegmentRotation { new_id, .. } => format!("\u{2193} rotate\u{2192}seg{new_id}"),
+        VizEvent::ColdTierRead { segment_id, .. } => format!("\u{2193} cold seg{segment_id}"),
+        VizEvent::SegmentCooled { segment_id } => format!("\u{2193} cool seg{segment_id}"),
+        VizEvent::SegmentPromoted { segment_id } => format!("\u{2191} promote seg{segment_id}"),
+        VizEvent::ScanEntry { lsn, is_cpl, .. } => {
+            format!("\u{2193} scan L{lsn}{}", if *is_cpl { " CPL" } else { "" })
+        }
+        VizEvent::VclComputed { vcl } => format!("\u{00b7} VCL={vcl}"),
+        VizEvent::VdlComputed { vdl } => format!("\u{00b7} VDL={vdl}"),
+        VizEvent::TruncateAt { offset, .. } => format!("\u{2193} truncate @{offset}"),
+        VizEvent::IndexRebuilt { .. } => "\u{2191} idx rebuilt".to_string(),
+        VizEvent::StorageHealthChanged { healthy, .. } => {
+            if *healthy { "\u{2713} storage back".to_string() } else { "\u{2717} storage down".to_string() }
+        }
+        VizEvent::StateSnapshot { .. } => String::new(),
+    }
+}
@@ -1,45 +1,295 @@
+use std::collections::VecDeque;
 use std::fs::File;
-use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
 use std::time::Instant;
 
+use serde::{Deserialize, Serialize};
+
 use super::events::VizEvent;
+use super::metrics::Op;
 
-/// Writes one line-delimited JSON entry per event to a file.
-pub struct JsonTracer {
-    writer: BufWriter<File>,
+/// Magic bytes at the start of every binary trace file, bumped whenever a
+/// `VizEvent` changes shape so older files are refused rather than misread.
+pub const BINARY_TRACE_MAGIC: &[u8; 8] = b"MATRACE3";
+
+/// One traced event, with the operation it belongs to.
+///
+/// `op_id`, `op` and `node` are `None` outside an operation; `delta_us` is
+/// the time since the previous event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceRecord {
+    pub seq: u64,
+    pub timestamp_us: u64,
+    pub delta_us: u64,
+    pub op_id: Option<u64>,
+    pub op: Option<Op>,
+    pub node: Option<String>,
+    pub event: VizEvent,
+}
+
+/// Destination for trace records.
+pub trait TraceSink: Send {
+    fn record(&mut self, record: &TraceRecord);
+}
+
+/// Stamps events with sequence numbers, timing and operation context, and
+/// fans them out to every sink.
+pub struct Tracer {
+    sinks: Vec<Box<dyn TraceSink>>,
     seq: u64,
     start: Instant,
+    last_us: u64,
+    next_op_id: u64,
+    current_op: Option<(u64, Op, String)>,
 }
 
-impl JsonTracer {
-    /// Open a new trace file (creates or truncates).
-    pub fn open(path: &Path) -> std::io::Result<Self> {
-        let file = File::create(path)?;
-        Ok(Self {
-            writer: BufWriter::new(file),
+impl Default for Tracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Self {
+            sinks: Vec::new(),
             seq: 0,
             start: Instant::now(),
-        })
+            last_us: 0,
+            next_op_id: 1,
+            current_op: None,
+        }
+    }
+
+    pub fn add_sink(&mut self, sink: impl TraceSink + 'static) {
+        self.sinks.push(Box::new(sink));
+    }
+
+    /// Start a new operation on `node`; events until `end_operation` are
+    /// tagged with its ID.
+    pub fn begin_operation(&mut self, node: &str, op: Op) {
+        self.current_op = Some((self.next_op_id, op, node.to_string()));
+        self.next_op_id += 1;
+    }
+
+    pub fn end_operation(&mut self) {
+        self.current_op = None;
     }
 
-    /// Write one event as a JSON line.
-    pub fn trace(&mut self, event: &VizEvent) {
+    /// Record one event, which happened at `at`, in every sink.
+    pub fn trace(&mut self, event: &VizEvent, at: Instant) {
+        if self.sinks.is_empty() {
+            return;
+        }
         self.seq += 1;
-        let timestamp_us = self.start.elapsed().as_micros() as u64;
+        let timestamp_us = at.saturating_duration_since(self.start).as_micros() as u64;
+        let delta_us = timestamp_us.saturating_sub(self.last_us);
+        self.last_us = self.last_us.max(timestamp_us);
+
+        let record = TraceRecord {
+            seq: self.seq,
+            timestamp_us,
+            delta_us,
+            op_id: self.current_op.as_ref().map(|(id, _, _)| *id),
+            op: self.current_op.as_ref().map(|(_, op, _)| *op),
+            node: self.current_op.as_ref().map(|(_, _, node)| node.clone()),
+            event: event.clone(),
+        };
+        for sink in &mut self.sinks {
+            sink.record(&record);
+        }
+    }
+}
 
-        // Build the JSON line manually to avoid a wrapper struct
-        let event_json = match serde_json::to_string(event) {
-            Ok(j) => j,
-            Err(_) => return,
+/// A trace file that rolls over once it reaches `max_bytes`: the full file
+/// is renamed to `<path>.1`, `<path>.2`, ... and a fresh one started, so
+/// `<path>` always holds the newest records.
+struct RotatingFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    max_bytes: Option<u64>,
+    written: u64,
+    rotations: u32,
+    /// Written at the start of every file.
+    header: &'static [u8],
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_bytes: Option<u64>, header: &'static [u8]) -> io::Result<Self> {
+        let mut file = Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(File::create(path)?),
+            max_bytes,
+            written: 0,
+            rotations: 0,
+            header,
         };
+        file.write_header()?;
+        Ok(file)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        self.writer.write_all(self.header)?;
+        self.written = self.header.len() as u64;
+        Ok(())
+    }
 
-        let line = format!(
-            "{{\"seq\":{},\"timestamp_us\":{},\"event\":{}}}\n",
-            self.seq, timestamp_us, event_json
-        );
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if let Some(max) = self.max_bytes {
+            if self.written > self.header.len() as u64 && self.written + bytes.len() as u64 > max {
+                self.rotate()?;
+            }
+        }
+        self.writer.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        self.writer.flush()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.rotations += 1;
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(format!(".{}", self.rotations));
+        std::fs::rename(&self.path, rotated)?;
+        self.writer = BufWriter::new(File::create(&self.path)?);
+        self.write_header()
+    }
+}
+
+/// Newline-delimited JSON, one `TraceRecord` per line.
+pub struct JsonlSink {
+    file: RotatingFile,
+}
+
+impl JsonlSink {
+    /// Create (or truncate) `path`, rotating every `max_bytes` if set.
+    pub fn open(path: &Path, max_bytes: Option<u64>) -> io::Result<Self> {
+        Ok(Self { file: RotatingFile::open(path, max_bytes, b"")? })
+    }
+}
+
+impl TraceSink for JsonlSink {
+    fn record(&mut self, record: &TraceRecord) {
+        let Ok(mut line) = serde_json::to_vec(record) else {
+            return;
+        };
+        line.push(b'\n');
+        let _ = self.file.write(&line);
+    }
+}
+
+/// Compact binary trace for long runs: `BINARY_TRACE_MAGIC`, then each
+/// record as a little-endian `u32` length followed by its bincode encoding.
+pub struct BinarySink {
+    file: RotatingFile,
+}
+
+impl BinarySink {
+    /// Create (or truncate) `path`, rotating every `max_bytes` if set.
+    pub fn open(path: &Path, max_bytes: Option<u64>) -> io::Result<Self> {
+        Ok(Self { file: RotatingFile::open(path, max_bytes, BINARY_TRACE_MAGIC)? })
+    }
+}
+
+impl TraceSink for BinarySink {
+    fn record(&mut self, record: &TraceRecord) {
+        let Ok(body) = bincode::serialize(record) else {
+            return;
+        };
+        let mut frame = Vec::with_capacity(4 + body.len());
+        frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&body);
+        let _ = self.file.write(&frame);
+    }
+}
+
+/// Read every record of a binary trace file.
+pub fn read_binary_trace(path: &Path) -> anyhow::Result<Vec<TraceRecord>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != BINARY_TRACE_MAGIC {
+        anyhow::bail!("{} is not a binary trace", path.display());
+    }
+    let mut records = Vec::new();
+    let mut len_buf = [0u8; 4];
+    loop {
+        match reader.read_exact(&mut len_buf) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let mut body = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+        reader.read_exact(&mut body)?;
+        records.push(bincode::deserialize(&body)?);
+    }
+    Ok(records)
+}
+
+/// The most recent records, kept in memory for `trace tail` in the REPL.
+/// Clones share the same buffer.
+#[derive(Clone)]
+pub struct TraceRing {
+    records: Arc<Mutex<VecDeque<TraceRecord>>>,
+    capacity: usize,
+}
+
+impl TraceRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// The last `n` records, oldest first.
+    pub fn tail(&self, n: usize) -> Vec<TraceRecord> {
+        let records = self.records.lock().unwrap();
+        records.iter().skip(records.len().saturating_sub(n)).cloned().collect()
+    }
+}
+
+impl TraceSink for TraceRing {
+    fn record(&mut self, record: &TraceRecord) {
+        let mut records = self.records.lock().unwrap();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record.clone());
+    }
+}
+
+/// Forwards records to another thread, e.g. the TUI's event log.
+impl TraceSink for mpsc::Sender<TraceRecord> {
+    fn record(&mut self, record: &TraceRecord) {
+        // A closed receiver just means nobody is watching any more
+        let _ = self.send(record.clone());
+    }
+}
+
+/// Trace files requested on the command line.
+#[derive(Default)]
+pub struct TraceOptions {
+    pub json: Option<PathBuf>,
+    pub binary: Option<PathBuf>,
+    /// Rotate trace files at this size.
+    pub rotate_bytes: Option<u64>,
+}
 
-        let _ = self.writer.write_all(line.as_bytes());
-        let _ = self.writer.flush();
+impl TraceOptions {
+    /// Add a file sink for each requested trace file, printing where each
+    /// one goes.
+    pub fn add_sinks(&self, tracer: &mut Tracer) -> io::Result<()> {
+        if let Some(path) = &self.json {
+            tracer.add_sink(JsonlSink::open(path, self.rotate_bytes)?);
+            println!("Tracing events to: {}", path.display());
+        }
+        if let Some(path) = &self.binary {
+            tracer.add_sink(BinarySink::open(path, self.rotate_bytes)?);
+            println!("Tracing events (binary) to: {}", path.display());
+        }
+        Ok(())
     }
 }
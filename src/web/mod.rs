@@ -0,0 +1,273 @@
+//! `web` mode (feature `web`): an HTTP server with a small browser dashboard
+//! for presenting the cluster to a room.
+//!
+//! Every viz event is pushed to connected browsers over a WebSocket at
+//! `/ws`, along with a metrics and segment update once a second. Browsers
+//! send commands back over the same socket and see their results inline.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::{Html, IntoResponse};
+use axum::routing::get;
+use axum::Router;
+use mini_aurora_compute::engine::WritePolicy;
+use mini_aurora_storage::config::TierDisks;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::broadcast;
+
+use crate::data_dir::Session;
+use crate::viz::compute::VizComputeEngine;
+use crate::viz::engine::VizStorageEngine;
+use crate::viz::events::{VizConfig, VizEvent};
+use crate::viz::renderer::VizRenderer;
+use crate::viz::tracer::{TraceOptions, TraceRecord, TraceSink, Tracer};
+
+/// Messages buffered per browser before it starts missing events.
+const CHANNEL_CAPACITY: usize = 4096;
+
+const INDEX_HTML: &str = include_str!("index.html");
+
+/// Publishes each record to every connected browser as JSON. The latest
+/// snapshot per node is kept so new browsers start from current state.
+struct BroadcastSink {
+    tx: broadcast::Sender<String>,
+    snapshots: Arc<Mutex<BTreeMap<String, String>>>,
+}
+
+impl TraceSink for BroadcastSink {
+    fn record(&mut self, record: &TraceRecord) {
+        let message = json!({
+            "type": "event",
+            "line": VizRenderer::format_one_liner(&record.event),
+            "record": record,
+        })
+        .to_string();
+        if let VizEvent::StateSnapshot { node_label, .. } = &record.event {
+            self.snapshots.lock().unwrap().insert(node_label.clone(), message.clone());
+        }
+        // No receivers just means no browser is open yet
+        let _ = self.tx.send(message);
+    }
+}
+
+struct WebState {
+    preset: String,
+    storage: Arc<VizStorageEngine>,
+    renderer: Arc<Mutex<VizRenderer>>,
+    nodes: BTreeMap<String, Arc<VizComputeEngine>>,
+    tx: broadcast::Sender<String>,
+    snapshots: Arc<Mutex<BTreeMap<String, String>>>,
+}
+
+/// A command typed in the browser, run on `node`.
+#[derive(Deserialize)]
+struct CommandRequest {
+    node: String,
+    cmd: String,
+}
+
+pub async fn run_web(
+    session: &Session,
+    trace: &TraceOptions,
+    preset: &str,
+    segment_size: u64,
+    disks: TierDisks,
+    write_policy: WritePolicy,
+    port: u16,
+) -> anyhow::Result<()> {
+    // Browsers draw the diagram; the renderer only traces
+    let config = VizConfig {
+        step_delay: Duration::ZERO,
+        color: false,
+        enabled: false,
+        step: false,
+        lanes: false,
+        metrics_strip: false,
+    };
+    let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+    let snapshots = Arc::new(Mutex::new(BTreeMap::new()));
+    let mut renderer_inner = VizRenderer::new(config);
+    let mut tracer = Tracer::new();
+    tracer.add_sink(BroadcastSink { tx: tx.clone(), snapshots: snapshots.clone() });
+    trace.add_sinks(&mut tracer)?;
+    renderer_inner.set_tracer(tracer);
+    let renderer = Arc::new(Mutex::new(renderer_inner));
+
+    let storage: Arc<VizStorageEngine> = match preset {
+        "tiered" => {
+            let base_dir = session.tiered_dir();
+            Arc::new(VizStorageEngine::open_tiered(&base_dir, segment_size, disks, None, renderer.clone())?)
+        }
+        "memory" => Arc::new(VizStorageEngine::open_in_memory(None, renderer.clone())?),
+        _ => {
+            Arc::new(VizStorageEngine::open(&session.wal_path(), None, renderer.clone())?)
+        }
+    };
+
+    let mut nodes = BTreeMap::new();
+    for label in ["A", "B"] {
+        let node = Arc::new(
+            VizComputeEngine::new(storage.clone(), 256, renderer.clone(), label.to_string())
+                .with_write_policy(write_policy),
+        );
+        node.refresh_read_point().await?;
+        nodes.insert(label.to_string(), node);
+    }
+
+    let state = Arc::new(WebState {
+        preset: preset.to_string(),
+        storage,
+        renderer,
+        nodes,
+        tx,
+        snapshots,
+    });
+    tokio::spawn(publish_metrics(state.clone()));
+
+    let app = Router::new()
+        .route("/", get(|| async { Html(INDEX_HTML) }))
+        .route("/ws", get(ws_handler))
+        .with_state(state);
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    println!("=== Mini-Aurora Web Dashboard (preset: {preset}) ===");
+    println!("Open http://localhost:{port}/ in a browser; Ctrl-C to stop.");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Once a second, send metrics, storage stats and the segment list to every
+/// browser.
+async fn publish_metrics(state: Arc<WebState>) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        if state.tx.receiver_count() == 0 {
+            continue;
+        }
+        let Some(summary) = state.renderer.lock().unwrap().metrics_summary() else {
+            continue;
+        };
+        let latencies: Vec<_> = summary
+            .latencies
+            .iter()
+            .map(|(node, op, hist)| {
+                json!({
+                    "node": node,
+                    "op": op.to_string(),
+                    "p50_us": hist.percentile_us(0.50),
+                    "p99_us": hist.percentile_us(0.99),
+                })
+            })
+            .collect();
+        let message = json!({
+            "type": "metrics",
+            "write_count": summary.write_count,
+            "read_count": summary.read_count,
+            "page_cache_hits": summary.page_cache_hits,
+            "page_cache_misses": summary.page_cache_misses,
+            "buffer_pool_hits": summary.buffer_pool_hits,
+            "buffer_pool_misses": summary.buffer_pool_misses,
+            "wal_bytes_written": summary.wal_bytes_written,
+            "latencies": latencies,
+            "segments": state.storage.segments(),
+            "storage": state.storage.stats(),
+        });
+        let _ = state.tx.send(message.to_string());
+    }
+}
+
+async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<WebState>>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_socket(socket, state))
+}
+
+async fn handle_socket(mut socket: WebSocket, state: Arc<WebState>) {
+    // Subscribe before replaying snapshots so nothing falls in between
+    let mut events = state.tx.subscribe();
+    let hello = json!({
+        "type": "hello",
+        "preset": state.preset,
+        "nodes": state.nodes.keys().collect::<Vec<_>>(),
+    });
+    let snapshots: Vec<String> = state.snapshots.lock().unwrap().values().cloned().collect();
+    for message in std::iter::once(hello.to_string()).chain(snapshots) {
+        if socket.send(Message::Text(message)).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(message) => {
+                    if socket.send(Message::Text(message)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    let notice = json!({ "type": "result", "text": format!("(missed {missed} events)") });
+                    if socket.send(Message::Text(notice.to_string())).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    let text = match serde_json::from_str::<CommandRequest>(&text) {
+                        Ok(request) => run_command(&state, &request).await,
+                        Err(e) => format!("Bad request: {e}"),
+                    };
+                    let reply = json!({ "type": "result", "text": text });
+                    if socket.send(Message::Text(reply.to_string())).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => return,
+            },
+        }
+    }
+}
+
+/// Run one browser command and describe the outcome.
+async fn run_command(state: &WebState, request: &CommandRequest) -> String {
+    let Some(node) = state.nodes.get(&request.node) else {
+        return format!("Unknown node: {}", request.node);
+    };
+    let label = &request.node;
+    let parts: Vec<&str> = request.cmd.split_whitespace().collect();
+    match parts.as_slice() {
+        ["put", page, offset, text @ ..] if !text.is_empty() => {
+            match (page.parse(), offset.parse::<u16>()) {
+                (Ok(page_id), Ok(offset)) => match node.put(page_id, offset, text.join(" ").into_bytes()).await {
+                    Ok(vdl) => format!("{label}: PUT pg{page_id} OK (VDL={vdl})"),
+                    Err(e) => format!("{label}: Error: {e}"),
+                },
+                _ => "Usage: put <page> <offset> <text>".to_string(),
+            }
+        }
+        ["get", page] => match page.parse() {
+            Ok(page_id) => match node.get(page_id).await {
+                Ok(data) => {
+                    let end = data.iter().rposition(|&b| b != 0).map(|i| i + 1).unwrap_or(0);
+                    format!("{label}: pg{page_id} = {:?}", String::from_utf8_lossy(&data[..end.min(64)]))
+                }
+                Err(e) => format!("{label}: Error: {e}"),
+            },
+            Err(_) => "Usage: get <page>".to_string(),
+        },
+        ["refresh"] => match node.refresh_read_point().await {
+            Ok(vdl) => format!("{label}: read point refreshed to {vdl}"),
+            Err(e) => format!("{label}: Error: {e}"),
+        },
+        _ => format!("Unknown command: {} (try put, get, refresh)", request.cmd),
+    }
+}
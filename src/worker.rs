@@ -0,0 +1,393 @@
+//! Background load workers shared by the viz REPL (`bg`) and the scenario
+//! runner (`bg` steps).
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mini_aurora_common::{PageId, PageRef, QosClass, StorageApi, StorageError, PAGE_SIZES};
+use mini_aurora_compute::engine::ComputeEngine;
+use mini_aurora_compute::workload::{KeyDistribution, PageSampler, ZIPFIAN_THETA};
+use mini_aurora_storage::qos::Tagged;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+
+use crate::viz::engine::VizStorageEngine;
+use crate::viz::metrics::Op;
+use crate::viz::oplog::OpRecorder;
+use crate::viz::renderer::VizRenderer;
+
+/// RAII guard: suppresses viz rendering while held, restores on drop.
+/// With viz disabled, the steps VizStorageEngine renders after each call
+/// skip drawing and step delays, so operations complete in microseconds
+/// instead of seconds.
+pub struct VizGuard {
+    renderer: Arc<Mutex<VizRenderer>>,
+    was_enabled: bool,
+}
+
+impl VizGuard {
+    pub fn suppress(renderer: &Arc<Mutex<VizRenderer>>) -> Self {
+        let was_enabled = {
+            let mut r = renderer.lock().unwrap();
+            let e = r.config_mut().enabled;
+            r.config_mut().enabled = false;
+            e
+        };
+        Self { renderer: renderer.clone(), was_enabled }
+    }
+}
+
+impl Drop for VizGuard {
+    fn drop(&mut self) {
+        self.renderer.lock().unwrap().config_mut().enabled = self.was_enabled;
+    }
+}
+
+/// A running background worker.
+pub struct WorkerHandle {
+    pub kind: WorkerKind,
+    pub interval_ms: u64,
+    pub options: WorkerOptions,
+    cancel: CancellationToken,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl WorkerHandle {
+    /// Cancel the worker and wait for its current operation to finish.
+    pub async fn stop(self) {
+        self.cancel.cancel();
+        let _ = self.task.await;
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerKind {
+    Write,
+    Read,
+    Mixed,
+}
+
+impl WorkerKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "write" => Some(WorkerKind::Write),
+            "read" => Some(WorkerKind::Read),
+            "mixed" => Some(WorkerKind::Mixed),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WorkerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkerKind::Write => write!(f, "write"),
+            WorkerKind::Read => write!(f, "read"),
+            WorkerKind::Mixed => write!(f, "mixed"),
+        }
+    }
+}
+
+/// Pages read when no `pages=` range is given.
+const DEFAULT_READ_PAGES: (PageId, PageId) = (1, 10);
+
+/// Largest `size`: the largest page size. Writes too big for the volume's
+/// pages fail as they are made.
+const MAX_SIZE: usize = PAGE_SIZES[PAGE_SIZES.len() - 1];
+
+/// Shape of a worker's load: `[pages=1..100] [size=64] [dist=zipf:1.1]`.
+///
+/// Without `pages`, writes go to fresh pages and reads cycle over pages
+/// 1..=10 (sampled from `dist` if one is given). With it, reads and writes
+/// both pick pages from the range.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkerOptions {
+    /// Inclusive page range.
+    pub pages: Option<(PageId, PageId)>,
+    /// Bytes per write; by default a short `bg-<page>` tag.
+    pub size: Option<usize>,
+    /// How pages are picked from the range; uniform by default.
+    pub dist: Option<KeyDistribution>,
+}
+
+impl WorkerOptions {
+    /// Parse `key=value` words as typed after `bg <node> <kind> <ms>`.
+    pub fn parse(words: &[&str]) -> Result<Self, String> {
+        let mut options = Self::default();
+        for word in words {
+            let (key, value) = word
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got {word:?}"))?;
+            options.set(key, value)?;
+        }
+        Ok(options)
+    }
+
+    /// Build from the optional fields of a scenario `bg` step.
+    pub fn from_fields(pages: Option<&str>, size: Option<usize>, dist: Option<&str>) -> Result<Self, String> {
+        let mut options = Self::default();
+        if let Some(pages) = pages {
+            options.set("pages", pages)?;
+        }
+        if let Some(size) = size {
+            options.set("size", &size.to_string())?;
+        }
+        if let Some(dist) = dist {
+            options.set("dist", dist)?;
+        }
+        Ok(options)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "pages" => {
+                let (first, last) = value.split_once("..").unwrap_or((value, value));
+                let parse = |v: &str| v.parse::<PageId>().map_err(|_| format!("Invalid page range {value:?}"));
+                let (first, last) = (parse(first)?, parse(last)?);
+                if first == 0 || last < first {
+                    return Err(format!("Invalid page range {value:?}: expected 1 <= first <= last"));
+                }
+                self.pages = Some((first, last));
+            }
+            "size" => match value.parse::<usize>() {
+                Ok(size) if (1..=MAX_SIZE).contains(&size) => self.size = Some(size),
+                _ => return Err(format!("Invalid size {value:?}: expected 1..={MAX_SIZE}")),
+            },
+            "dist" => {
+                self.dist = Some(match value.split_once(':') {
+                    None if value == "uniform" => KeyDistribution::Uniform,
+                    None if value == "zipf" => KeyDistribution::Zipfian { theta: ZIPFIAN_THETA },
+                    Some(("zipf", theta)) => match theta.parse::<f64>() {
+                        Ok(theta) if theta > 0.0 => KeyDistribution::Zipfian { theta },
+                        _ => return Err(format!("Invalid zipf skew {theta:?}: expected a number > 0")),
+                    },
+                    _ => return Err(format!("Unknown distribution {value:?}: use uniform, zipf or zipf:<theta>")),
+                });
+            }
+            _ => return Err(format!("Unknown option {key:?}: use pages, size or dist")),
+        }
+        Ok(())
+    }
+
+    /// The `pages`, `size` and `dist` fields of a scenario `bg` step.
+    pub fn to_fields(&self) -> (Option<String>, Option<usize>, Option<String>) {
+        let pages = self.pages.map(|(first, last)| format!("{first}..{last}"));
+        let dist = self.dist.map(|dist| match dist {
+            KeyDistribution::Uniform => "uniform".to_string(),
+            KeyDistribution::Zipfian { theta } => format!("zipf:{theta}"),
+        });
+        (pages, self.size, dist)
+    }
+
+    /// Picks read pages, and write pages when a range is given. `None`
+    /// means reads cycle over the default pages in order.
+    fn sampler(&self, seed: u64) -> Option<PageSampler> {
+        if self.pages.is_none() && self.dist.is_none() {
+            return None;
+        }
+        let (first, last) = self.pages.unwrap_or(DEFAULT_READ_PAGES);
+        Some(PageSampler::new(self.dist.unwrap_or(KeyDistribution::Uniform), first, last, seed))
+    }
+
+    /// Payload for a write to `page`.
+    fn payload(&self, page: PageId) -> Vec<u8> {
+        let tag = format!("bg-{page}");
+        match self.size {
+            Some(size) => tag.bytes().chain(std::iter::repeat(b'.')).take(size).collect(),
+            None => tag.into_bytes(),
+        }
+    }
+}
+
+/// Each option is written with a leading space, so an empty set prints
+/// nothing after e.g. "every 100ms".
+impl fmt::Display for WorkerOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (pages, size, dist) = self.to_fields();
+        if let Some(pages) = pages {
+            write!(f, " pages={pages}")?;
+        }
+        if let Some(size) = size {
+            write!(f, " size={size}")?;
+        }
+        if let Some(dist) = dist {
+            write!(f, " dist={dist}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What the workers of one REPL, TUI or scenario share: its storage and
+/// renderer, the counter writes without a page range draw fresh pages
+/// from, and where result lines go. Built once, alongside the nodes.
+pub struct WorkerHost {
+    pub storage: Arc<VizStorageEngine>,
+    pub renderer: Arc<Mutex<VizRenderer>>,
+    pub page_counter: Arc<AtomicU64>,
+    pub output: UnboundedSender<String>,
+}
+
+impl WorkerHost {
+    /// Fresh write pages start at 100, clear of the pages typed at a prompt.
+    pub fn new(
+        storage: Arc<VizStorageEngine>,
+        renderer: Arc<Mutex<VizRenderer>>,
+        output: UnboundedSender<String>,
+    ) -> Self {
+        Self { storage, renderer, page_counter: Arc::new(AtomicU64::new(100)), output }
+    }
+}
+
+/// Start a `kind` worker labelled `label` on `host` that runs one operation
+/// every `interval_ms`, shaped by `options`.
+pub async fn spawn_worker(
+    label: String,
+    kind: WorkerKind,
+    interval_ms: u64,
+    options: WorkerOptions,
+    host: &WorkerHost,
+) -> WorkerHandle {
+    let cancel = CancellationToken::new();
+    let bg_counter = host.page_counter.clone();
+    let cancel_clone = cancel.clone();
+    let node_label = label;
+    let storage = host.storage.clone();
+    let renderer = &host.renderer;
+    let renderer_for_bg = renderer.clone();
+    let bg_tx = host.output.clone();
+
+    // Non-viz ComputeEngine: shares storage but never touches the
+    // renderer at the compute level (no set_active, no render_op_header,
+    // no event emissions). Storage-level renders are suppressed via
+    // VizGuard so each call returns in microseconds, not seconds.
+    // Its only observer feeds the oplog, so bg operations can be reviewed
+    // after their output has scrolled away.
+    let qos = storage.qos();
+    // No spaces, so `qos limit bg-A ...` can name it
+    let caller = format!("bg-{node_label}");
+    let memory = storage.memory_budget();
+    // Requests the compute engine sends from tasks of its own, like
+    // prefetches, are still admitted as this worker
+    let storage_for_bg: Arc<dyn StorageApi> =
+        Arc::new(Tagged::new(storage, &caller, QosClass::Background));
+    let ops = Arc::new(OpRecorder::new(format!("bg {node_label}"), renderer.lock().unwrap().oplog()));
+    let bg_compute = ComputeEngine::new(storage_for_bg, 256)
+        .with_observer(ops.clone())
+        .with_memory_budget(&memory);
+    {
+        let _guard = VizGuard::suppress(renderer);
+        let _ = bg_compute.refresh_read_point().await;
+    }
+
+    // Seeded by label so scenario replays draw the same pages
+    let seed = node_label.bytes().fold(0u64, |h, b| h.wrapping_mul(31).wrapping_add(b as u64));
+    let mut sampler = options.sampler(seed);
+    let worker_options = options.clone();
+
+    let task = tokio::spawn(async move {
+        let options = worker_options;
+        let mut cycle: u64 = 0;
+        let mut pick_page = |cycle: u64| match sampler.as_mut() {
+            Some(sampler) => sampler.next_page(),
+            None => (cycle % DEFAULT_READ_PAGES.1) + DEFAULT_READ_PAGES.0,
+        };
+        loop {
+            if cancel_clone.is_cancelled() {
+                break;
+            }
+
+            // One admission per operation, as a background caller. Taken
+            // before suppressing viz: waiting behind a foreground request
+            // with the guard held would hide that request's steps.
+            let permit = tokio::select! {
+                _ = cancel_clone.cancelled() => break,
+                permit = qos.admit(&caller, QosClass::Background) => permit,
+            };
+
+            // Scope the VizGuard so viz is restored before the sleep. The
+            // operation runs under the permit, so storage doesn't admit its
+            // requests again.
+            permit.covering(async {
+                let _guard = VizGuard::suppress(&renderer_for_bg);
+                match kind {
+                    WorkerKind::Write => {
+                        let pg = match options.pages {
+                            Some(_) => pick_page(cycle),
+                            None => bg_counter.fetch_add(1, Ordering::Relaxed),
+                        };
+                        ops.begin(Op::Put, format!("pg{pg}"));
+                        let started = Instant::now();
+                        let result = bg_compute.put(pg, 0, options.payload(pg)).await;
+                        ops.finish(result.as_ref().err(), started.elapsed());
+                        match result {
+                            Ok(vdl) => { let _ = bg_tx.send(format!("[bg {node_label}] PUT pg{pg} OK (VDL={vdl})")); }
+                            Err(e) => { let _ = bg_tx.send(format!("[bg {node_label}] PUT pg{pg} Error: {e}")); }
+                        }
+                    }
+                    WorkerKind::Read => {
+                        let pg = pick_page(cycle);
+                        ops.begin(Op::Get, format!("pg{pg}"));
+                        let started = Instant::now();
+                        let result = bg_compute.get(pg).await;
+                        ops.finish(result.as_ref().err(), started.elapsed());
+                        let _ = bg_tx.send(get_line(&node_label, pg, &result));
+                    }
+                    WorkerKind::Mixed => {
+                        if cycle.is_multiple_of(2) {
+                            ops.begin(Op::Refresh, "");
+                            let started = Instant::now();
+                            let result = bg_compute.refresh_read_point().await;
+                            ops.finish(result.as_ref().err(), started.elapsed());
+                            match result {
+                                Ok(rp) => { let _ = bg_tx.send(format!("[bg {node_label}] REFRESH -> rp={rp}")); }
+                                Err(e) => { let _ = bg_tx.send(format!("[bg {node_label}] REFRESH Error: {e}")); }
+                            }
+                        } else {
+                            let pg = pick_page(cycle / 2);
+                            ops.begin(Op::Get, format!("pg{pg}"));
+                            let started = Instant::now();
+                            let result = bg_compute.get(pg).await;
+                            ops.finish(result.as_ref().err(), started.elapsed());
+                            let _ = bg_tx.send(get_line(&node_label, pg, &result));
+                        }
+                    }
+                }
+            }).await; // _guard dropped: viz restored before sleep
+
+            cycle += 1;
+            tokio::select! {
+                _ = cancel_clone.cancelled() => break,
+                _ = tokio::time::sleep(Duration::from_millis(interval_ms)) => {}
+            }
+        }
+    });
+
+    WorkerHandle {
+        kind,
+        interval_ms,
+        options,
+        cancel,
+        task,
+    }
+}
+
+/// A background GET's result line: the page's text up to its first zero
+/// byte, cut to 20 bytes, or the error.
+fn get_line(node_label: &str, pg: PageId, result: &Result<PageRef, StorageError>) -> String {
+    match result {
+        Ok(page) => {
+            let end = page.iter().position(|&b| b == 0).unwrap_or(page.len());
+            let preview = if end == 0 {
+                "(empty)".to_string()
+            } else {
+                format!("{:?}", String::from_utf8_lossy(&page[..end.min(20)]))
+            };
+            format!("[bg {node_label}] GET pg{pg} -> {preview}")
+        }
+        Err(e) => format!("[bg {node_label}] GET pg{pg} Error: {e}"),
+    }
+}
@@ -1,7 +1,9 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use mini_aurora_common::{PageId, RedoRecord, StorageApi};
 use mini_aurora_compute::engine::ComputeEngine;
+use mini_aurora_compute::transaction::MtrLimits;
 use mini_aurora_storage::engine::StorageEngine;
 use mini_aurora_wal::recovery::recover;
 use mini_aurora_wal::writer::WalWriter;
@@ -11,6 +13,8 @@ fn setup() -> (TempDir, Arc<StorageEngine>) {
     let dir = TempDir::new().unwrap();
     let wal_path = dir.path().join("test.wal");
     let engine = Arc::new(StorageEngine::open(&wal_path).unwrap());
+    // Every test doubles as a check of the engine's invariants
+    engine.set_invariant_checks(true);
     (dir, engine)
 }
 
@@ -100,10 +104,11 @@ async fn test_crash_mid_record_recovery() {
             lsn: 0,
             page_id: 1,
             offset: 0,
-            data: vec![0xAA; 100],
+            data: vec![0xAA; 100].into(),
             prev_lsn: 0,
             mtr_id: 1,
             is_mtr_end: true,
+            timestamp: 0,
         }];
         engine.append_redo(records1).await.unwrap();
 
@@ -111,10 +116,11 @@ async fn test_crash_mid_record_recovery() {
             lsn: 0,
             page_id: 2,
             offset: 0,
-            data: vec![0xBB; 50],
+            data: vec![0xBB; 50].into(),
             prev_lsn: 0,
             mtr_id: 2,
             is_mtr_end: true,
+            timestamp: 0,
         }];
         engine.append_redo(records2).await.unwrap();
     }
@@ -152,13 +158,13 @@ async fn test_crash_mid_mtr_recovery() {
     {
         let mut writer = WalWriter::open(&wal_path).unwrap();
         let complete_mtr = vec![
-            RedoRecord { lsn: 1, page_id: 1, offset: 0, data: vec![0xAA], prev_lsn: 0, mtr_id: 1, is_mtr_end: false },
-            RedoRecord { lsn: 2, page_id: 2, offset: 0, data: vec![0xBB], prev_lsn: 0, mtr_id: 1, is_mtr_end: true },
+            RedoRecord { lsn: 1, page_id: 1, offset: 0, data: vec![0xAA].into(), prev_lsn: 0, mtr_id: 1, is_mtr_end: false, timestamp: 0 },
+            RedoRecord { lsn: 2, page_id: 2, offset: 0, data: vec![0xBB].into(), prev_lsn: 0, mtr_id: 1, is_mtr_end: true, timestamp: 0 },
         ];
         writer.append_batch(&complete_mtr).unwrap();
 
         let incomplete_mtr = vec![
-            RedoRecord { lsn: 3, page_id: 3, offset: 0, data: vec![0xCC], prev_lsn: 0, mtr_id: 2, is_mtr_end: false },
+            RedoRecord { lsn: 3, page_id: 3, offset: 0, data: vec![0xCC].into(), prev_lsn: 0, mtr_id: 2, is_mtr_end: false, timestamp: 0 },
         ];
         writer.append_batch(&incomplete_mtr).unwrap();
         writer.sync().unwrap();
@@ -238,10 +244,11 @@ async fn test_versioned_reads() {
         lsn: 0,
         page_id: 1,
         offset: 0,
-        data: vec![0x01],
+        data: vec![0x01].into(),
         prev_lsn: 0,
         mtr_id: 1,
         is_mtr_end: true,
+        timestamp: 0,
     }];
     storage.append_redo(records1).await.unwrap();
 
@@ -249,10 +256,11 @@ async fn test_versioned_reads() {
         lsn: 0,
         page_id: 1,
         offset: 0,
-        data: vec![0x02],
+        data: vec![0x02].into(),
         prev_lsn: 0,
         mtr_id: 2,
         is_mtr_end: true,
+        timestamp: 0,
     }];
     storage.append_redo(records2).await.unwrap();
 
@@ -260,10 +268,11 @@ async fn test_versioned_reads() {
         lsn: 0,
         page_id: 1,
         offset: 0,
-        data: vec![0x03],
+        data: vec![0x03].into(),
         prev_lsn: 0,
         mtr_id: 3,
         is_mtr_end: true,
+        timestamp: 0,
     }];
     storage.append_redo(records3).await.unwrap();
 
@@ -322,9 +331,9 @@ async fn test_durability_tracking() {
 
     // Write with CPL
     let records = vec![
-        RedoRecord { lsn: 0, page_id: 1, offset: 0, data: vec![1], prev_lsn: 0, mtr_id: 1, is_mtr_end: false },
-        RedoRecord { lsn: 0, page_id: 2, offset: 0, data: vec![2], prev_lsn: 0, mtr_id: 1, is_mtr_end: false },
-        RedoRecord { lsn: 0, page_id: 3, offset: 0, data: vec![3], prev_lsn: 0, mtr_id: 1, is_mtr_end: true },
+        RedoRecord { lsn: 0, page_id: 1, offset: 0, data: vec![1].into(), prev_lsn: 0, mtr_id: 1, is_mtr_end: false, timestamp: 0 },
+        RedoRecord { lsn: 0, page_id: 2, offset: 0, data: vec![2].into(), prev_lsn: 0, mtr_id: 1, is_mtr_end: false, timestamp: 0 },
+        RedoRecord { lsn: 0, page_id: 3, offset: 0, data: vec![3].into(), prev_lsn: 0, mtr_id: 1, is_mtr_end: true, timestamp: 0 },
     ];
     storage.append_redo(records).await.unwrap();
 
@@ -334,7 +343,7 @@ async fn test_durability_tracking() {
 
     // Write without CPL (incomplete MTR)
     let records = vec![
-        RedoRecord { lsn: 0, page_id: 4, offset: 0, data: vec![4], prev_lsn: 0, mtr_id: 2, is_mtr_end: false },
+        RedoRecord { lsn: 0, page_id: 4, offset: 0, data: vec![4].into(), prev_lsn: 0, mtr_id: 2, is_mtr_end: false, timestamp: 0 },
     ];
     storage.append_redo(records).await.unwrap();
 
@@ -365,3 +374,58 @@ async fn test_interleaved_pages() {
     assert_eq!(p2[0], 0x20);
     assert_eq!(p2[1], 0x21);
 }
+
+// =========================================================================
+// Test 12: Reader leases hold storage's minimum read point
+// =========================================================================
+#[tokio::test]
+async fn test_reader_lease_holds_min_read_point() {
+    let (_dir, storage) = setup();
+    let compute = ComputeEngine::new(storage.clone(), 256).with_reader_lease("A", Duration::from_secs(60));
+
+    for i in 1..=3u8 {
+        compute.put(1, 0, vec![i]).await.unwrap();
+    }
+    assert_eq!(compute.renew_reader_lease().await.unwrap(), Some(3));
+    assert_eq!(storage.min_read_point(), 3);
+
+    let mut session = compute.session().await;
+    session.set_read_point(1);
+    assert_eq!(compute.oldest_read_point(), 1);
+    assert_eq!(compute.renew_reader_lease().await.unwrap(), Some(1));
+    assert_eq!(storage.min_read_point(), 1);
+    assert_eq!(storage.reader_leases()[0].reader, "A");
+
+    drop(session);
+    compute.renew_reader_lease().await.unwrap();
+    assert_eq!(storage.min_read_point(), 3);
+
+    // A read point past the VDL can't be registered
+    assert!(storage.register_reader("B", 9, Duration::from_secs(60)).await.is_err());
+
+    let mut compute = compute;
+    compute.release_reader_lease().await.unwrap();
+    assert!(storage.reader_leases().is_empty());
+    assert_eq!(compute.renew_reader_lease().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_mtr_limits_refused_on_shared_storage() {
+    let (_dir, storage) = setup();
+    let limits = MtrLimits::new(2, 1024).unwrap();
+    let node_a = make_compute(storage.clone());
+    let node_b = make_compute(storage.clone());
+    assert_eq!(storage.writers(), 2);
+    assert!(node_a.with_mtr_limits(limits).is_err());
+
+    // Once the other node is gone, the last one can split its MTRs
+    drop(node_b);
+    let node = make_compute(storage.clone()).with_mtr_limits(limits).unwrap();
+    let mut mtr = node.begin().await;
+    for page_id in 1..=3 {
+        mtr.write(page_id, 0, vec![page_id as u8]);
+    }
+    let vdl = node.commit(mtr).await.unwrap();
+    assert_eq!(vdl, 3);
+    assert_eq!(node.get(3).await.unwrap()[0], 3);
+}
@@ -1,8 +1,7 @@
 use std::sync::Arc;
-use std::time::Duration;
 
-use mini_aurora_common::{StorageApi, PAGE_SIZE};
-use mini_aurora_storage::config::{StoragePreset, TieredConfig};
+use mini_aurora_common::StorageApi;
+use mini_aurora_storage::config::{StoragePreset, TierDisks, TieredConfig};
 use mini_aurora_storage::engine::StorageEngine;
 use mini_aurora_compute::engine::ComputeEngine;
 use tempfile::TempDir;
@@ -11,10 +10,13 @@ fn setup_tiered(segment_size: u64) -> (TempDir, Arc<StorageEngine>) {
     let dir = TempDir::new().unwrap();
     let preset = StoragePreset::Tiered(TieredConfig {
         segment_size_bytes: segment_size,
-        cold_latency: Duration::from_millis(0),
+        disks: TierDisks::default(),
         base_dir: dir.path().to_path_buf(),
+        page_size: None,
     });
     let engine = Arc::new(StorageEngine::open_with_preset(preset).unwrap());
+    // Every test doubles as a check of the engine's invariants
+    engine.set_invariant_checks(true);
     (dir, engine)
 }
 
@@ -52,7 +54,7 @@ async fn test_tiered_segment_rotation_via_compute() {
     // Verify all pages readable
     for pid in 1u64..=5 {
         let page = compute.get(pid).await.unwrap();
-        let end = page.iter().position(|&b| b == 0).unwrap_or(PAGE_SIZE);
+        let end = page.iter().position(|&b| b == 0).unwrap_or(page.len());
         assert!(end > 0, "Page {pid} should not be empty");
     }
 